@@ -3,6 +3,65 @@
 
 /* auto-generated by NAPI-RS */
 
+/** Why a call failed; set as `code` on the thrown or rejected JS error */
+export const enum CaptureErrorCode {
+  PermissionDenied = 'PermissionDenied',
+  /** The display, window, application or device isn't there (any more) */
+  SourceNotFound = 'SourceNotFound',
+  /** The configuration or an argument was rejected before anything started */
+  InvalidConfiguration = 'InvalidConfiguration',
+  AlreadyRecording = 'AlreadyRecording',
+  NotRecording = 'NotRecording',
+  EncoderInitFailed = 'EncoderInitFailed',
+  DiskFull = 'DiskFull',
+  StreamStopped = 'StreamStopped',
+  Timeout = 'Timeout',
+  /** Aborted with `cancel` before it finished */
+  Cancelled = 'Cancelled',
+  /** Needs a newer macOS or hardware this Mac doesn't have */
+  Unsupported = 'Unsupported',
+  Unknown = 'Unknown'
+}
+/** Pixel layout of the captured frames; the values are CoreVideo pixel format codes */
+export const enum PixelFormat {
+  /**
+   * 'BGRA', 8 bits per channel. The only layout cursor, click, camera and zoom overlays and
+   * `onVideoFrame` can draw into or read.
+   */
+  Bgra = 1111970369,
+  /** 'l10r', 10-bit packed ARGB for HDR displays */
+  Argb2101010 = 1815162994,
+  /** '420v', video-range 4:2:0 YCbCr; the cheapest to capture and encode */
+  Yuv420VideoRange = 875704438,
+  /** '420f', full-range 4:2:0 YCbCr */
+  Yuv420FullRange = 875704422
+}
+/** Color space the captured frames are converted to */
+export const enum ColorSpace {
+  Srgb = 'srgb',
+  DisplayP3 = 'display-p3',
+  /** ITU-R BT.709, the usual space for HD video */
+  Bt709 = 'bt709',
+  /** ITU-R BT.2100 with the PQ curve; needs a 10-bit pixel format to be worth it */
+  Bt2100Pq = 'bt2100-pq'
+}
+/** Video codec of the recording */
+export const enum VideoCodec {
+  H264 = 'h264',
+  /** Smaller files at the same quality; hardware encoded on Apple silicon and recent Intel Macs */
+  Hevc = 'hevc'
+}
+/** Byte order of the delivered pixels */
+export const enum FrameFormat {
+  Rgba = 'rgba',
+  /** The capture's native order, which skips the channel swap */
+  Bgra = 'bgra'
+}
+/** Encoding of screenshots and thumbnails */
+export const enum ImageFormat {
+  Png = 'png',
+  Jpeg = 'jpeg'
+}
 export interface ScreenSource {
   id: string
   name: string
@@ -10,11 +69,67 @@ export interface ScreenSource {
   height: number
   isDisplay: boolean
 }
+/** Criteria for `ContentManager.findSources`; every given field must match */
+export interface SourceQuery {
+  /** Regular expression (Rust syntax) matched against window titles and display names */
+  titleRegex?: string
+  /** Bundle ID or application name, case-insensitive; only windows of that app match */
+  appName?: string
+  minWidth?: number
+}
 export interface AudioDevice {
   id: string
   name: string
   deviceType: string
 }
+export interface AggregateChannelRange {
+  deviceUid: string
+  /** Zero-based index of this input's first channel in the aggregate device */
+  firstChannel: number
+  channelCount: number
+}
+export interface AggregateInputDevice {
+  /** Pass as `audio_device_id` to record through the aggregate device */
+  uid: string
+  name: string
+  channels: Array<AggregateChannelRange>
+}
+/** Region of a display in points from its top-left corner */
+export interface CaptureRegion {
+  x: number
+  y: number
+  width: number
+  height: number
+}
+/** Webcam drawn over the screen in one corner of the recording */
+export interface CameraPipOptions {
+  /** `uniqueID` of the camera; the system default camera when omitted */
+  deviceId?: string
+  /** "top-left", "top-right", "bottom-left" or "bottom-right" (default) */
+  corner?: string
+  /** Width of the camera as a fraction of the recording's width, 0.05-0.5 (default 0.25) */
+  size?: number
+  /** Crop the camera to a circle (default false) */
+  circular?: boolean
+  /** Distance from the edges in output pixels (default 24) */
+  margin?: number
+}
+/** Capture sparsely and play back at a normal frame rate */
+export interface TimelapseOptions {
+  /** Seconds between captured frames, 0.1-3600 */
+  captureIntervalSecs: number
+  /** Frame rate of the resulting video (default 30) */
+  playbackFps?: number
+}
+/** A cursor-centred crop of the display, in points */
+export interface ZoomFollowOptions {
+  /** Crop width (default half the display, or `height` at the output's aspect ratio) */
+  width?: number
+  /** Crop height (default `width` at the output's aspect ratio) */
+  height?: number
+  /** How far the crop trails the cursor each frame: 0 snaps to it, up to 0.99 (default 0.85) */
+  smoothing?: number
+}
 export interface RecordingConfiguration {
   width?: number
   height?: number
@@ -22,43 +137,1026 @@ export interface RecordingConfiguration {
   showCursor?: boolean
   captureAudio?: boolean
   audioDeviceId?: string
+  /**
+   * May contain {date} and {time} (local, "2024-05-01" and "14-30-05"), {source} (display name
+   * or window title) and {app} (the window's application), filled in when the recording starts
+   */
   outputPath: string
-  pixelFormat?: string
-  colorSpace?: string
+  /** Create the output directory when it doesn't exist (default false) */
+  createOutputDirectory?: boolean
+  /** When the output file already exists: overwrite it (default), add a "-1", "-2", ... suffix, or fail */
+  onOutputExists?: OutputCollision
+  /** Default `Bgra`; the YCbCr formats are cheaper but leave no room for overlays */
+  pixelFormat?: PixelFormat
+  /** Default `srgb` */
+  colorSpace?: ColorSpace
+  /** Default `h264` */
+  codec?: VideoCodec
+  tags?: Array<string>
+  /**
+   * Capture the system default input with `capture_audio`, and move to the new default when it
+   * changes mid-recording. Ignored when `audio_device_id` pins a device.
+   */
+  followDefaultDevice?: boolean
+  /** Remove the cursor from the captured frames while leaving it visible on screen; overrides `show_cursor` */
+  hideCursorInOutput?: boolean
+  /**
+   * Draw the cursor this many times larger than the system cursor (0-8, default 1). The system
+   * cursor is read on the main queue, so without `cursor_image_path` it needs a process whose main
+   * thread runs it, as Electron's does.
+   */
+  cursorScale?: number
+  /** Image drawn in place of the system cursor, hot spot at its top-left corner */
+  cursorImagePath?: string
+  /** Where system audio comes from: "sck" (ScreenCaptureKit, default) or "coreaudio-tap" (macOS 14.2+) */
+  systemAudioBackend?: string
+  /** Processes captured by the "coreaudio-tap" backend; all system audio when omitted */
+  systemAudioProcessIds?: Array<number>
+  /** While a password field has secure input: "off" (default), "pause" the recording or "blank" the frames */
+  secureInputProtection?: string
+  /** Also write the audio as an uncompressed 16 kHz mono WAV next to the recording, ready for whisper-cli; system audio and microphone are mixed */
+  writeWavSidecar?: boolean
+  /**
+   * Fail the start instead of recording with degraded content data or a fallback filter.
+   * Without it every fallback is reported in the start result's `warnings`.
+   */
+  strict?: boolean
+  /** Windows left out of a display capture (note widgets, chat popups, ...); ignored for window capture */
+  excludeWindowIds?: Array<number>
+  /**
+   * Applications hidden from a display capture by bundle ID, e.g. password managers or messengers.
+   * An app opened during the recording is excluded as soon as its first window shows. Takes precedence over `exclude_window_ids` for windows
+   * of other apps, which are then reported in `warnings`.
+   */
+  excludeAppBundleIds?: Array<string>
+  /**
+   * Capture only these windows, at their positions on the display picked by `screen_id`
+   * ("display:<id>"). Same as passing `screen_id` "windows:<id>,<id>,..."; exclusions don't apply.
+   */
+  windowIds?: Array<number>
+  /**
+   * Reshape a single-window capture to the window when it is resized instead of letterboxing
+   * or cropping it (default true). The file keeps its start dimensions.
+   */
+  trackWindowResize?: boolean
+  /** Show the menu bar in a display capture (default true) */
+  includeMenuBar?: boolean
+  /** Show the Dock in a display capture (default true) */
+  includeDock?: boolean
+  /** Show the desktop picture and desktop icons in a display capture (default true); black when off */
+  includeDesktop?: boolean
+  /**
+   * Show this app's own windows, such as the recording controls, in a display capture
+   * (default false). They're matched by process and bundle ID, helper processes included.
+   */
+  includeSelf?: boolean
+  /**
+   * Capture only this region of the display, in points from its top-left corner. All four
+   * must be given together and the region must lie within the display.
+   */
+  cropX?: number
+  cropY?: number
+  cropWidth?: number
+  cropHeight?: number
+  /**
+   * The region for a "region:<displayId>" capture; same as setting `crop_*`, and the two can't be
+   * combined. The region is snapped to whole pixels, and without `width`/`height` it is recorded
+   * at its pixel size, i.e. twice its point size on Retina displays.
+   */
+  crop?: CaptureRegion
+  /**
+   * Record at this fraction (0-1] of the source's pixel size, or of `width`/`height` when given.
+   * ScreenCaptureKit delivers frames at that size, so nothing larger is ever captured or encoded.
+   */
+  scale?: number
+  /** Cap the output width, keeping the aspect ratio; combines with `scale` */
+  maxOutputWidth?: number
+  /**
+   * Draw a ripple into the video wherever the mouse is clicked (default false). Only the
+   * recording shows it; nothing is drawn on screen.
+   */
+  highlightClicks?: boolean
+  /**
+   * Write keyboard and mouse events with timestamps to `<output>.input.json` (default false).
+   * Needs Accessibility permission; keys typed while secure input is active carry no characters.
+   */
+  recordInputEvents?: boolean
+  /**
+   * Stop and finalize the recording once free space on the output volume drops below this many
+   * MB (default 500, 0 turns the check off). A recording won't start with less than that free.
+   */
+  minFreeDiskMb?: number
+  /**
+   * Instant-replay mode: keep only the last this-many seconds (up to 600) in a ring buffer instead
+   * of recording to `output_path`; `saveReplay` writes them out. Nothing is kept after stopping.
+   */
+  replayBufferSeconds?: number
+  /**
+   * With several displays passed to `startRecording`: "separate" (default) writes
+   * `<output>-display-<id>.mp4` per display, "composite" lays them out as arranged in
+   * `width` x `height` of one file. Audio goes with the first display.
+   */
+  multiDisplayMode?: string
+  /**
+   * Composite the webcam as picture-in-picture over the screen before encoding. Needs
+   * Camera permission; the camera's frames are only in the recording, never shown on screen.
+   */
+  cameraPip?: CameraPipOptions
+  /**
+   * Record a timelapse: one frame every `capture_interval_secs`, timed so an hour at a 5 s
+   * interval plays back in 24 s at 30 fps. Video only; can't be combined with audio capture.
+   */
+  timelapse?: TimelapseOptions
+  /**
+   * Write the cursor's position and visibility for every video frame to `<output>.cursor.json`
+   * (default false), so an editor can re-render a smoothed or enlarged cursor. Combine with
+   * `hide_cursor_in_output` to record without the original cursor.
+   */
+  recordCursorMetadata?: boolean
+  /**
+   * Switch the capture to whichever window has focus, once it has kept focus for
+   * `follow_debounce_ms`, producing one continuous recording across apps (default false).
+   * Starts from `screen_id`; the desktop, the Dock and this app's own windows are never followed.
+   */
+  followActiveWindow?: boolean
+  /** How long a window must stay focused before the recording switches to it (default 750) */
+  followDebounceMs?: number
+  /**
+   * Record only a rectangle around the mouse cursor, easing after it as it moves, scaled up to
+   * `width` x `height`; ideal for demos with small text. Display captures only; the display is
+   * captured at full resolution so the zoomed text stays sharp.
+   */
+  zoomFollow?: ZoomFollowOptions
+  /**
+   * Encode the video (default true). With it off and `capture_audio` on, the stream runs for its
+   * audio alone and writes `<output>_audio.m4a`; frame-based options can't be combined with it.
+   */
+  captureVideo?: boolean
+  /**
+   * Start from a named setup, see `listPresets()`: "meeting", "tutorial-1080p30",
+   * "high-quality-4k60" or "small-file". Fields set alongside it override the preset's.
+   */
+  preset?: string
+  /** Version of the app making the recording, kept in the `<output>.json` metadata sidecar */
+  appVersion?: string
+}
+export interface EnumerationOptions {
+  /** Return window IDs and owner names only; call `enrichWindows()` on the result for titles and sizes */
+  skipWindowDetails?: boolean
+  /** Also list windows on other Spaces, e.g. to record them by ID once they come into view */
+  includeOffscreen?: boolean
+  /**
+   * Also list minimized windows and windows of hidden apps. Without ScreenCaptureKit content
+   * either option lists every off-screen window.
+   */
+  includeMinimized?: boolean
+}
+export interface EnumerationProgress {
+  /** "displays", "windows" or "done" */
+  phase: string
+  displaysDone: number
+  displaysTotal: number
+  windowsProcessed: number
+  windowsTotal: number
+}
+export interface RecordingPreset {
+  name: string
+  description: string
+  width: number
+  height: number
+  fps: number
+  showCursor: boolean
+  captureAudio: boolean
+  highlightClicks: boolean
+  writeWavSidecar: boolean
+}
+export interface RecordingLimits {
+  maxWidth?: number
+  maxHeight?: number
+  maxFps?: number
+}
+export interface RecordingStartResult {
+  sessionId: string
+  /** Unix time in milliseconds the session started */
+  startedAt: number
+  outputPath: string
+  width: number
+  height: number
+  fps: number
+  warnings: Array<string>
+  /** Set when `write_wav_sidecar` is on; the file is complete once the recording stops */
+  wavSidecarPath?: string
+  /** Set when `record_input_events` is on; written when the recording stops */
+  inputEventsPath?: string
+  /** Set when `record_cursor_metadata` is on; written when the recording stops */
+  cursorTrackPath?: string
+  /** Configuration, source and frame counts, written when the recording stops */
+  metadataPath: string
+  /** Every file of a multi-display recording, first display first */
+  outputPaths?: Array<string>
+}
+/** Live counters of a running recording */
+export interface CaptureStats {
+  videoFrames: number
+  audioSamples: number
+  /** Recorded time so far, pauses excluded */
+  durationMs: number
+  currentFps: number
+  /** Size of the files written so far */
+  outputSizeBytes: number
+  outputPath?: string
+  isPaused: boolean
+}
+/** The video encoder of the active recording */
+export interface EncoderStatus {
+  codec: VideoCodec
+  width: number
+  height: number
+  fps: number
+  hardwareAccelerated: boolean
+}
+export interface SessionStatus {
+  sessionId: string
+  outputPath: string
+  /** Unix time in milliseconds */
+  startedAt: number
+  /** Wall-clock time since the start, pauses included */
+  elapsedMs: number
+  /** Recorded time, pauses excluded */
+  recordedMs: number
+  isPaused: boolean
+  /** One stream per display being recorded */
+  streams: number
+  capturesAudio: boolean
+  encoder?: EncoderStatus
+}
+/** What this Mac supports, probed at runtime */
+export interface PlatformFeatures {
+  /** SCStream (macOS 12.3) */
+  screenCaptureKit: boolean
+  /** SCScreenshotManager (macOS 14) */
+  screenshotManager: boolean
+  /** SCContentSharingPicker (macOS 14) */
+  contentSharingPicker: boolean
+  /** Microphone captured by the stream itself (macOS 15) */
+  streamMicrophone: boolean
+  /** Presenter Overlay while capturing a camera alongside the screen (macOS 14) */
+  presenterOverlay: boolean
+  /** SCRecordingOutput (macOS 15) */
+  recordingOutput: boolean
+  /** HDR capture through captureDynamicRange (macOS 15) */
+  hdrCapture: boolean
+  /** Core Audio process taps (macOS 14.2) */
+  processTap: boolean
+  hardwareH264: boolean
+  hardwareHevc: boolean
+}
+/** What `getCapabilities` reports */
+export interface Capabilities {
+  /** e.g. "14.5"; null when it can't be read */
+  macosVersion?: string
+  arch: string
+  features: PlatformFeatures
+}
+export interface MacosVersion {
+  /** As sw_vers prints it, e.g. "14.5" */
+  version: string
+  major: number
+  minor: number
+  patch: number
+}
+export interface PermissionStates {
+  screenRecording: PermissionState
+  microphone: PermissionState
+  camera: PermissionState
+  accessibility: PermissionState
+}
+export interface PermissionRequirement {
+  permission: PrivacyPane
+  /** The configuration option that needs it, or "screen capture" */
+  neededFor: string
+  state: PermissionState
+}
+/** What `probeEnvironment` reports */
+export interface EnvironmentProbe {
+  /** Null when the version can't be read */
+  macos?: MacosVersion
+  /** "arm64" or "x86_64" */
+  arch: string
+  /** macOS 12.3 or later with the ScreenCaptureKit framework loaded */
+  screenCaptureKit: boolean
+  permissions: PermissionStates
+  hardwareH264: boolean
+  hardwareHevc: boolean
+}
+export interface PreflightOptions {
+  /** Where recordings will go; the disk check looks at its volume. Default the home folder. */
+  outputDirectory?: string
+  /** Free space below this fails the disk check, default 500 */
+  minFreeDiskMb?: number
+  /** How long content enumeration may take, default 5000 */
+  timeoutMs?: number
+}
+export interface ContentPreflight {
+  ok: boolean
+  displayCount: number
+  windowCount: number
+  /** Why enumeration failed or was skipped */
+  error?: string
+}
+export interface DiskPreflight {
+  path: string
+  /** Null when the volume can't be queried */
+  availableBytes?: number
+  requiredBytes: number
+  ok: boolean
+}
+/** What `runPreflight` reports */
+export interface PreflightReport {
+  /**
+   * Recording can be offered: ScreenCaptureKit is usable, screen recording is granted, content
+   * enumerates and the output volume has room
+   */
+  ready: boolean
+  environment: EnvironmentProbe
+  content: ContentPreflight
+  disk: DiskPreflight
+  /** One line for each reason `ready` is false */
+  problems: Array<string>
+}
+/** What `ScreenCaptureKitRecorder.getStatus` reports */
+export interface RecorderStatus {
+  isRecording: boolean
+  state: RecorderState
+  /** The active recording; null when there is none */
+  session?: SessionStatus
+  /** A `scheduleRecording` start or stop is still to come */
+  scheduled: boolean
+  screenRecordingPermission: boolean
+  capabilities: Capabilities
+  version: string
+}
+export const enum CheckOutcome {
+  Pass = 'pass',
+  /** Works, but not fully (e.g. a Core Graphics fallback) */
+  Warn = 'warn',
+  Fail = 'fail'
+}
+export interface DiagnosticCheck {
+  name: string
+  outcome: CheckOutcome
+  detail: string
+}
+/** What the `test*` functions report */
+export interface DiagnosticsReport {
+  /** No check failed */
+  passed: boolean
+  checks: Array<DiagnosticCheck>
+  macosVersion?: string
+  screenRecordingPermission: boolean
+  displayCount: number
+  windowCount: number
+}
+/** What `RecordingConfigurationBuilder.build` produces: pass both to `startRecording` */
+export interface RecordingRequest {
+  screenId: string
+  config: RecordingConfiguration
+}
+export interface WarmUpResult {
+  permissionGranted: boolean
+  displays: number
+  windows: number
+  /** The H.264 encoder loaded successfully */
+  encoderReady: boolean
+  durationMs: number
+}
+export interface RecordingMarker {
+  label: string
+  /** Position in the video, in seconds from its first frame */
+  time: number
+  /** Unix time in milliseconds the marker was added */
+  createdAt: number
+}
+export interface RecordingIndexEntry {
+  sessionId: string
+  outputPath: string
+  startedAt: number
+  stoppedAt?: number
+  tags: Array<string>
+}
+export interface AudioDiagnosticsOptions {
+  /** Microphone to test; the system default input when omitted */
+  audioDeviceId?: string
+  /** Default 2000 */
+  durationMs?: number
+}
+export interface AudioSourceReport {
+  deviceFound: boolean
+  deviceUid?: string
+  deviceName?: string
+  permissionGranted: boolean
+  /** Any audio arrived during the test, silent or not */
+  captured: boolean
+  hasSignal: boolean
+  peakDb: number
+  rmsDb: number
+  /** What went wrong, or a hint when the source stayed silent */
+  error?: string
+}
+export interface AudioDiagnosticsReport {
+  microphone: AudioSourceReport
+  microphonePermission: PermissionState
+  systemAudio: AudioSourceReport
+  durationMs: number
+}
+export interface SessionHistoryEntry {
+  sessionId: string
+  outputPath: string
+  startedAt: number
+  /** When the session stopped or failed */
+  endedAt?: number
+  durationMs?: number
+  /** "completed", "failed", "interrupted" (never stopped, e.g. after a crash) or "recording" */
+  outcome: string
+  /** First error reported for the session */
+  error?: string
+  tags: Array<string>
+}
+export interface AudioTapOptions {
+  /** Chunk length delivered to the callback, default 100 ms */
+  chunkMs?: number
+}
+export interface AudioRouteChangeEvent {
+  /** "input" or "output" */
+  direction: string
+  /** New default device on that side; absent when none is left */
+  deviceUid?: string
+  /** "switching" when audio pauses, then "resumed" or "failed" */
+  phase: string
+}
+export interface ScheduleOptions {
+  /** Unix epoch milliseconds; now when omitted */
+  startAt?: number
+  /** Unix epoch milliseconds; the recording keeps running until stopped when omitted */
+  stopAt?: number
+}
+export interface AutoStoppedEvent {
+  /** "disk_full" */
+  reason: string
+  outputPath: string
+  sessionId: string
+  /** Free space on the output volume when the recording was stopped */
+  freeBytes?: number
+  /** Set when finalizing the stopped recording failed */
+  error?: string
+}
+export interface ScheduledRecordingEvent {
+  outputPath: string
+  sessionId?: string
+  /** Why the scheduled start or stop failed */
+  error?: string
+}
+export interface RecorderEvent {
+  /** "started", "stats", "warning", "error" or "stopped" */
+  event: string
+  sessionId?: string
+  outputPath?: string
+  /** What the warning or error is about */
+  message?: string
+  /** Why it failed, for "error" events */
+  code?: CaptureErrorCode
+  /** JSON capture stats, for "stats" events */
+  stats?: string
+  timestampMs: number
+}
+/** What to do when the output file already exists */
+export const enum OutputCollision {
+  /** Replace the existing file */
+  Overwrite = 'overwrite',
+  /** Add "-1", "-2", ... before the extension until the name is free */
+  Suffix = 'suffix',
+  /** Refuse to start */
+  Fail = 'fail'
+}
+/** Whether a permission was granted, and if not whether asking can still change that */
+export const enum PermissionState {
+  /** Never answered; requesting it shows the system prompt */
+  NotDetermined = 'notDetermined',
+  /** Refused; only System Settings can change it */
+  Denied = 'denied',
+  /** Blocked by a configuration profile or parental controls; the user can't change it */
+  Restricted = 'restricted',
+  Granted = 'granted'
+}
+/** A pane under System Settings > Privacy & Security */
+export const enum PrivacyPane {
+  ScreenRecording = 'screen-recording',
+  Microphone = 'microphone',
+  Camera = 'camera',
+  /** Needed for `record_input_events` */
+  Accessibility = 'accessibility',
+  /** "System Audio Recording Only", listed with screen recording; needed for Core Audio process taps */
+  SystemAudio = 'system-audio'
+}
+/** Most verbose level that gets logged */
+export const enum LogLevel {
+  /** Nothing at all, e.g. for production builds */
+  Off = 'off',
+  Error = 'error',
+  Warn = 'warn',
+  Info = 'info',
+  /** Per-stream and per-frame detail */
+  Debug = 'debug',
+  Trace = 'trace'
+}
+/** Steps of stopping a recording, in the order they run */
+export const enum FinalizePhase {
+  /** Stopping the stream and waiting for its last buffers */
+  Stream = 'stream',
+  /** Flushing the video encoder and writing the MP4 index; the long one for long recordings */
+  Video = 'video',
+  Audio = 'audio',
+  /** WAV, marker, cursor and input event files */
+  Sidecars = 'sidecars',
+  /** Closing the session journal and library entry */
+  Session = 'session',
+  Done = 'done'
+}
+/** Where the recorder is in a recording's lifecycle */
+export const enum RecorderState {
+  Idle = 'idle',
+  /** Enumerating content and starting the stream */
+  Preparing = 'preparing',
+  Recording = 'recording',
+  Paused = 'paused',
+  /** Stopping the stream and finalizing the files */
+  Stopping = 'stopping',
+  /** The last recording was written; a new one can start */
+  Finalized = 'finalized',
+  /** The last recording failed to start, broke off or failed to finalize; a new one can start */
+  Failed = 'failed'
+}
+export interface StateChangedEvent {
+  state: RecorderState
+  previous: RecorderState
+  sessionId?: string
+  /** Why, when entering "failed" */
+  message?: string
+  timestampMs: number
+}
+/** Sent as each phase of stopping a recording begins, ending with "done" at 100 */
+export interface FinalizeProgressEvent {
+  sessionId?: string
+  /** Rough share of the stop done so far, 0-100 */
+  percent: number
+  phase: FinalizePhase
+}
+/** One log line, as sent to the `setLogHandler` callback */
+export interface LogEntry {
+  level: LogLevel
+  /** Module that logged it, e.g. "content" or "encoder" */
+  module: string
+  message: string
+  timestampMs: number
+}
+export interface AudioBufferEvent {
+  /** Interleaved f32 PCM at the capture rate, `channels` samples per frame */
+  samples: Float32Array
+  channels: number
+  sampleRate: number
+  frameCount: number
+  /** Presentation time of the first frame in seconds (host clock) */
+  timestamp: number
+}
+export interface VideoFrameOptions {
+  /** Default `rgba` */
+  format?: FrameFormat
+}
+export interface VideoFrameEvent {
+  /** Pixels, `stride` bytes per row */
+  data: Buffer
+  width: number
+  height: number
+  stride: number
+  format: FrameFormat
+  /** Presentation time in seconds (host clock) */
+  timestamp: number
+}
+export interface ContentChangeEvent {
+  /** "windowAdded", "windowClosed" or "windowRenamed" */
+  kind: string
+  window: WindowInfo
+  /** Title before a rename */
+  previousTitle?: string
+}
+export interface PermissionChangeEvent {
+  /** "screenRecording", "microphone" or "camera" */
+  permission: string
+  previous: PermissionState
+  current: PermissionState
+  /** Whether the permission is granted now */
+  granted: boolean
+}
+export interface ProcessAudioCaptureOptions {
+  /** PIDs to capture, mixed to stereo; all system audio when omitted */
+  processIds?: Array<number>
+  /** Write the captured audio to this file */
+  outputPath?: string
+  /** Chunk length delivered to the callback, default 100 ms */
+  chunkMs?: number
+}
+export interface AudioTapChunk {
+  /** Mono f32 PCM in [-1, 1] */
+  samples: Float32Array
+  sampleRate: number
+  /** Seconds since the first audio buffer of the recording */
+  startTime: number
+}
+export interface CameraDevice {
+  /** `uniqueID`, stable across reconnects */
+  id: string
+  name: string
+  isDefault: boolean
+}
+export interface CameraRecordingOptions {
+  /** Video goes to `<output_path>_video.mp4`, audio to `<output_path>_audio.mp4` */
+  outputPath: string
+  /** `id` from `CameraRecorder.getCameras()`; the default camera when omitted */
+  deviceId?: string
+  /** Record the microphone alongside (default true) */
+  captureAudio?: boolean
+  /** Input device UID; the system default input when omitted */
+  audioDeviceId?: string
+}
+export interface CameraRecordingResult {
+  videoPath: string
+  /** Set when the microphone was recorded */
+  audioPath?: string
+}
+export interface PreviewOptions {
+  /** Frames per second, 1-30 (default 5) */
+  fps?: number
+  /** Downscale to at most this many pixels wide (default 640) */
+  maxWidth?: number
+}
+export interface PreviewFrame {
+  /** JPEG image */
+  data: Buffer
+  width: number
+  height: number
+  timestampMs: number
+}
+export interface ScreenshotFile {
+  displayId: number
+  path: string
+  width: number
+  height: number
+}
+export interface DisplayHdrInfo {
+  displayId: number
+  /** EDR headroom in use right now; above 1.0 only while HDR content is on screen */
+  edrHeadroom: number
+  /** Highest EDR headroom the display can reach; 1.0 on SDR-only displays */
+  potentialEdrHeadroom: number
+  /** Headroom at which the display stays reference-accurate */
+  referenceEdrHeadroom: number
+  hdrCapable: boolean
+  /** HDR content is being shown, so HDR capture would differ from SDR capture */
+  hdrContentOnScreen: boolean
+}
+export interface BulkScreenshotResult {
+  /** Shared capture time (ms since epoch) embedded in every file name */
+  timestamp: number
+  files: Array<ScreenshotFile>
+}
+export interface AveragedScreenshotOptions {
+  /** Number of frames to average (2-64, default 8) */
+  frames?: number
+  /** Delay between frames in milliseconds (default 50) */
+  intervalMs?: number
+  /** Defaults to the output path's extension */
+  format?: ImageFormat
+}
+export interface RecommendedConfiguration {
+  width: number
+  height: number
+  fps: number
+  codec: VideoCodec
+  bitrate: number
+  hardwareEncoder: boolean
+  notes: Array<string>
+}
+export interface ValidationIssue {
+  /** The configuration field at fault, e.g. "fps" or "outputPath" */
+  field: string
+  message: string
+}
+export interface ValidationReport {
+  /** No errors; the recording can be attempted */
+  valid: boolean
+  /** What would make the recording fail */
+  errors: Array<ValidationIssue>
+  /** What would make it differ from the configuration */
+  warnings: Array<ValidationIssue>
 }
-export const K_CV_PIXEL_FORMAT_TYPE_32_BGRA: number
-export const K_CG_COLOR_SPACE_SRGB: number
-export const K_CG_COLOR_SPACE_DISPLAY_P3: number
-export const kCVPixelFormatType_32BGRA: number
-export const kCGColorSpaceSRGB: number
 export interface DisplayInfo {
   id: number
   name: string
   width: number
   height: number
+  /** Part of a mirror set; recording it captures the same content as the other displays in the set */
+  isMirrored: boolean
+  /** ID of the display this one mirrors, when it isn't the primary of its mirror set */
+  mirrorOf?: number
+  /** Backing pixels per point (2 on Retina displays) */
+  scaleFactor: number
+  /** Maximum refresh rate in Hz; capturing a 120 Hz display at full rate costs noticeably more */
+  refreshRate: number
+  /** The display holding the menu bar */
+  isMain: boolean
+  /** Localized color space name, e.g. "Display P3" */
+  colorSpace?: string
+  /**
+   * Top-left corner in global coordinates (points, y down); the main display sits at 0,0 and
+   * displays left of or above it have negative origins
+   */
+  originX: number
+  originY: number
 }
 export interface WindowInfo {
   id: number
   title: string
   width: number
   height: number
+  /** Bundle identifier of the owning application, for grouping windows by app */
+  ownerBundleId?: string
+  ownerName?: string
+  pid?: number
+  /** Window server layer; 0 for normal app windows, non-zero for menus, overlays and other system UI */
+  windowLayer: number
+  isOnScreen: boolean
+  /** Active on some space, i.e. not minimized or hidden with its app */
+  isActive: boolean
 }
 export declare function initScreencapturekit(): void
+/**
+ * Combine several microphones into one private aggregate device for multi-speaker capture.
+ * Inputs keep separate channels in the given order, as listed in `channels`, and a recording
+ * through the device keeps each input's range in its audio track (up to 8 channels); two mono
+ * mics land on left and right.
+ */
+export declare function createAggregateInputDevice(inputUids: Array<string>, name?: string | undefined | null): AggregateInputDevice
+/** Remove an aggregate device created by `createAggregateInputDevice` */
+export declare function destroyAggregateInputDevice(uid: string): void
 export declare function getVersion(): string
+/**
+ * Send the module's log lines to `callback` instead of stdout, or back to stdout when it's null.
+ * `level` (info unless RUST_LOG says otherwise) is the most verbose level logged; "off" silences
+ * the module entirely. The callback doesn't keep the process alive.
+ * Each worker thread has its own handler, and every handler gets every line; the level is shared.
+ */
+export declare function setLogHandler(callback: ((entry: LogEntry) => void) | null, level?: LogLevel | undefined | null): void
+/**
+ * Set global limits that every recording configuration is clamped against. They apply to the
+ * whole process, worker threads included, since all of them share the same displays and encoders.
+ */
+export declare function setLimits(limits: RecordingLimits): void
+export declare function getLimits(): RecordingLimits
+/** The named setups accepted by `preset`, for one-click choices in the UI */
+export declare function listPresets(): Array<RecordingPreset>
+/** Recordings from the library index carrying the given tag (case-insensitive) */
+export declare function findRecordingsByTag(tag: string): Array<RecordingIndexEntry>
+/** The most recent sessions from the session journal, newest first (default 50), including failed starts */
+export declare function getSessionHistory(limit?: number | undefined | null): Array<SessionHistoryEntry>
+/** Screenshot every connected display at the same moment into `directory` */
+export declare function captureAllDisplays(format: ImageFormat, directory: string): BulkScreenshotResult
+/**
+ * Preview image of a display or window ("display:<id>" / "window:<id>") for a source picker,
+ * at most `maxWidth` pixels wide (default 320), png unless `format` says otherwise. Pass
+ * `operationId` to be able to `cancel` it from a recorder.
+ */
+export declare function getSourceThumbnail(id: string, maxWidth?: number | undefined | null, format?: ImageFormat | undefined | null, operationId?: string | undefined | null): Promise<Buffer>
+export interface ScreenshotOptions {
+  /** Taken from the `path` extension when omitted, else png */
+  format?: ImageFormat
+  /** Write the image here; without it the encoded image comes back in `data` */
+  path?: string
+  /** Region for "region:<displayId>", in points from the display's top-left corner */
+  crop?: CaptureRegion
+  /** Include the cursor (default false) */
+  showCursor?: boolean
+}
+export interface ScreenshotResult {
+  /** Pixel size, twice the point size on Retina displays */
+  width: number
+  height: number
+  path?: string
+  data?: Buffer
+}
+export interface ReplayClip {
+  path: string
+  durationMs: number
+}
+/** Options for `getAvailableScreens` */
+export interface ScreenListOptions {
+  /** How long to wait for ScreenCaptureKit before listing what Core Graphics sees (default 5000) */
+  timeoutMs?: number
+  /** Enumerate again even when the cached content is still fresh */
+  refresh?: boolean
+  /** Lets `cancel` abort the enumeration */
+  operationId?: string
+}
+/**
+ * Grab one full-resolution frame of "display:<id>", "window:<id>" or "region:<displayId>" (with
+ * `crop`) without starting a recording
+ */
+export declare function captureScreenshot(screenId: string, options?: ScreenshotOptions | undefined | null): Promise<ScreenshotResult>
+/** True while some app has secure event input enabled (a password field has focus) */
+export declare function isSecureInputActive(): boolean
+/**
+ * Record a couple of seconds from the microphone and system audio and report device, permission and
+ * signal level for each, to debug "my recording has no sound"
+ */
+export declare function runAudioDiagnostics(options?: AudioDiagnosticsOptions | undefined | null): Promise<AudioDiagnosticsReport>
+/** EDR headroom per display, to suggest HDR capture only when it would make a difference */
+export declare function getDisplayHdrInfo(): Array<DisplayHdrInfo>
+/** Long-exposure screenshot: averages several frames to wash out the cursor, caret blink and tooltips */
+export declare function captureAveragedScreenshot(displayId: number, outputPath: string, options?: AveragedScreenshotOptions | undefined | null): ScreenshotFile
 export declare function checkScreenRecordingPermission(): boolean
 export declare function requestScreenRecordingPermission(): boolean
+/**
+ * Whether `pane`'s permission is granted, never asked for (prompt with the matching request
+ * function), denied (send the user to `openPermissionSettings`) or restricted by a profile.
+ * Reading it never prompts.
+ */
+export declare function getPermissionState(pane: PrivacyPane): PermissionState
+/**
+ * The permissions recording with `config` needs and their current state, so only those are
+ * requested up front. Reading them never prompts.
+ */
+export declare function getRequiredPermissions(config: RecordingConfiguration): Array<PermissionRequirement>
+/**
+ * Microphone access without prompting; when it is `notDetermined`, `requestMicrophonePermission`
+ * will prompt. Recording from a microphone without it gives silence, not an error.
+ */
+export declare function checkMicrophonePermission(): PermissionState
+/**
+ * Ask for microphone access, prompting only when it was never answered; resolves with whether
+ * it is granted. A denial can only be undone in System Settings > Privacy & Security > Microphone.
+ */
+export declare function requestMicrophonePermission(): Promise<boolean>
+/** Camera access without prompting. Starting a camera without it fails with PermissionDenied. */
+export declare function checkCameraPermission(): PermissionState
+/**
+ * Ask for camera access, prompting only when it was never answered; resolves with whether it
+ * is granted. A denial can only be undone in System Settings > Privacy & Security > Camera.
+ */
+export declare function requestCameraPermission(): Promise<boolean>
+/**
+ * Keep every permission prompt from showing, e.g. while the app polls status in the background.
+ * The check functions never prompt either way. While this is on, the request functions report
+ * the current state instead of prompting, and anything that would prompt fails with
+ * PermissionDenied unless its permission is already granted: enumerating sources (`getAvailableScreens`,
+ * `warmUp`, `refreshContent`, `ContentManager`), starting or scheduling a recording, the
+ * coreaudio-tap backend, `highlight_clicks`, `record_input_events`, screenshots, thumbnails and
+ * the camera. The system audio tap only counts as granted once this process has created one.
+ */
+export declare function setSilentMode(silent: boolean): void
+export declare function isSilentMode(): boolean
+/**
+ * Open System Settings on the Privacy & Security pane where `pane` is granted, e.g. next to a
+ * "grant access" button after a check came back denied
+ */
+export declare function openPermissionSettings(pane: PrivacyPane): void
+/**
+ * Poll screen recording, microphone and camera access every `intervalMs` (default 1000) and
+ * report each one that flips, e.g. to enable the record button once access is granted in System
+ * Settings. Nothing is reported for the states at the time of the call.
+ */
+export declare function watchPermissions(callback: (event: PermissionChangeEvent) => void, intervalMs?: number | undefined | null): PermissionWatcher
+/**
+ * What this Mac can do: its macOS version and the ScreenCaptureKit, audio and encoder features
+ * found by looking them up at runtime rather than going by the version number
+ */
+export declare function getCapabilities(): Capabilities
+/**
+ * Everything the renderer needs to decide what to offer, in one call: the macOS version, the
+ * architecture, whether ScreenCaptureKit is usable, permission states and the hardware encoders.
+ * Nothing here prompts the user or waits on ScreenCaptureKit.
+ */
+export declare function probeEnvironment(): EnvironmentProbe
+/**
+ * Everything that has to hold before recording is enabled, checked in one call: the macOS version,
+ * each permission, content enumeration, the hardware encoders and free space on the output
+ * volume. Content is only enumerated with screen recording granted, so this never prompts.
+ */
+export declare function runPreflight(options?: PreflightOptions | undefined | null): Promise<PreflightReport>
+/** @deprecated Use `probeEnvironment()`, whose `macos` has the version split into numbers */
 export declare function checkMacosVersion(): string
-export declare function testPermissionsAndApi(): string
-/** Test function for the segfault-safe ScreenCaptureKit implementation */
-export declare function testScreencapturekitWithTimeout(): string
-export declare function testPhase2Implementation(): string
+/**
+ * Check the macOS version, screen recording permission and ScreenCaptureKit API access
+ * @deprecated Use `runPreflight()`, which reports each check as a field instead of a message
+ */
+export declare function testPermissionsAndApi(): DiagnosticsReport
+/** Enumerate content with a 5 second timeout, the way a recorder does, and count what it finds */
+export declare function testScreencapturekitWithTimeout(): DiagnosticsReport
+/** Build a content filter for the first display and a stream manager, without starting a stream */
+export declare function testPhase2Implementation(): DiagnosticsReport
+/**
+ * One recording, as resolved by `startRecording`. Its methods only ever act on this recording:
+ * once it has stopped they fail with NotRecording, even if the recorder has started another.
+ */
+export declare class RecordingSession {
+  get id(): string
+  /** Unix time in milliseconds */
+  get startedAt(): number
+  /** The configuration it was started with, preset applied */
+  get config(): RecordingConfiguration
+  get outputPath(): string
+  /** Size, frame rate, warnings and side files as the recording started */
+  get startResult(): RecordingStartResult
+  /** Stop and finalize this recording; resolves with its output path */
+  stop(): Promise<string>
+  pause(): void
+  resume(): void
+  /** Live counters; null once the recording has stopped */
+  stats(): CaptureStats | null
+  /** Mark the current moment; see `ScreenCaptureKitRecorder.addMarker` */
+  addMarker(label: string): RecordingMarker
+}
+/**
+ * Builds a `RecordingConfiguration` step by step, e.g.
+ * `new RecordingConfigurationBuilder().display(id).fps(60).codec(VideoCodec.Hevc).output(path).build()`.
+ * `build` fills what was left unset from the preset and the source, and refuses contradictory
+ * combinations there rather than when the stream starts.
+ */
+export declare class RecordingConfigurationBuilder {
+  constructor()
+  display(displayId: number): this
+  window(windowId: number): this
+  /** Every window of one application, e.g. "com.apple.Safari" */
+  application(bundleId: string): this
+  /** One of `listPresets()`; fields set on the builder win over the preset's */
+  preset(name: string): this
+  size(width: number, height: number): this
+  fps(fps: number): this
+  codec(codec: VideoCodec): this
+  pixelFormat(format: PixelFormat): this
+  colorSpace(colorSpace: ColorSpace): this
+  output(path: string): this
+  /** Record system audio, and the microphone `deviceId` when given */
+  audio(enabled: boolean, deviceId?: string | undefined | null): this
+  cursor(show: boolean): this
+  highlightClicks(enabled: boolean): this
+  /**
+   * The finished configuration with its defaults filled in. Fails with InvalidConfiguration,
+   * naming every problem, when there's no source or output or the fields contradict each other.
+   */
+  build(): RecordingRequest
+}
 export declare class ContentManager {
   constructor()
-  getShareableContent(): ShareableContent
+  /**
+   * `options` may ask for off-screen and minimized windows; `skipWindowDetails` only applies to
+   * `getShareableContentWithProgress`
+   */
+  getShareableContent(options?: EnumerationOptions | undefined | null): ShareableContent
+  /** @deprecated Use `getShareableContent` */
   getShareableContentSync(): ShareableContent
+  /** Windows of one application, e.g. "com.google.Chrome" or "Google Chrome" */
+  getWindowsForApp(bundleIdOrName: string): Array<WindowInfo>
+  /** Displays and windows matching `query`, filtered natively so only the matches cross into JS */
+  findSources(query: SourceQuery): Array<ScreenSource>
+  /**
+   * Poll the window list every `intervalMs` (default 1000) and report windows opening, closing and
+   * being renamed until the returned watcher is stopped
+   */
+  watchContent(callback: (event: ContentChangeEvent) => void, intervalMs?: number | undefined | null): ContentWatcher
+  /** Enumerate off the main thread, reporting displays done and N of M windows processed */
+  getShareableContentWithProgress(options: EnumerationOptions | undefined | null, onProgress: (progress: EnumerationProgress) => void): Promise<ShareableContent>
+  /**
+   * Return the inventory cached by the last enumeration right away (`stale: true`) and deliver
+   * a fresh one to `onFresh` when the background enumeration finishes. Without a cache this
+   * enumerates synchronously and `onFresh` is not called. Titles aren't persisted, so cached
+   * windows have an empty title.
+   */
+  getShareableContentCached(onFresh: (content: ShareableContent) => void): ShareableContent
+}
+/** Handle returned by `watchPermissions` */
+export declare class PermissionWatcher {
+  stop(): void
+  isWatching(): boolean
+}
+/** Handle returned by `ContentManager.watchContent` */
+export declare class ContentWatcher {
+  stop(): void
+  isWatching(): boolean
 }
 export declare class RealContentFilter {
   constructor()
+  static fromDisplayHandle(handle: DisplayHandle): RealContentFilter
+  static fromWindowHandle(handle: WindowHandle): RealContentFilter
+  /** Deprecated: does not build a filter; use `RealContentFilter.fromDisplayHandle` */
   initWithDisplay(display: DisplayInfo): void
   isValid(): boolean
 }
@@ -67,7 +1165,8 @@ export declare class RealStreamManager {
   initializeStream(contentFilter: RealContentFilter, streamConfig: string, outputPath: string): void
   startCapture(): void
   stopCapture(): void
-  getCaptureStats(): string
+  /** Live counters of the running recording; null when there is none */
+  getCaptureStats(): CaptureStats | null
 }
 export declare class ShareableContent {
   constructor()
@@ -75,24 +1174,261 @@ export declare class ShareableContent {
   getWindows(): Array<WindowInfo>
   get displays(): Array<DisplayInfo>
   get windows(): Array<WindowInfo>
+  /** Loaded from the on-disk cache of the last enumeration; may not match what is on screen now */
+  get stale(): boolean
+  /** Degraded paths taken while enumerating (Core Graphics data, placeholder windows, ...) */
+  get fallbacks(): Array<string>
+  /** Number of windows still waiting for details after a fast enumeration */
+  get pendingWindowDetails(): number
+  /** Fill in titles and sizes for windows enumerated with `skipWindowDetails` */
+  enrichWindows(): number
   hasDisplay(displayId: number): boolean
   hasWindow(windowId: number): boolean
   getDisplayInfo(displayId: number): DisplayInfo | null
+  /** The display showing the global point (x, y), e.g. a cursor or window position */
+  getDisplayAtPoint(x: number, y: number): DisplayInfo | null
   getWindowInfo(windowId: number): WindowInfo | null
+  /**
+   * Typed handle to a display, for `RealContentFilter.fromDisplayHandle` and
+   * `ScreenCaptureKitRecorder.startRecordingDisplay`; null when ScreenCaptureKit doesn't list it
+   */
+  getDisplayHandle(displayId: number): DisplayHandle | null
+  /**
+   * Typed handle to a window, for `RealContentFilter.fromWindowHandle` and
+   * `ScreenCaptureKitRecorder.startRecordingWindow`; null when ScreenCaptureKit doesn't list it
+   */
+  getWindowHandle(windowId: number): WindowHandle | null
+}
+/** Opaque reference to a display listed by ScreenCaptureKit */
+export declare class DisplayHandle {
+  get id(): number
+  get screenId(): string
+  /** The display is still attached */
+  isValid(): boolean
+}
+/** Opaque reference to a window listed by ScreenCaptureKit */
+export declare class WindowHandle {
+  get id(): number
+  get screenId(): string
+  /** The window is still open */
+  isValid(): boolean
 }
+/**
+ * One recording at a time. Recorders are independent of each other, so several can run at once
+ * (one per display, or a preview next to a recording), each with its own content cache, events,
+ * stats and listeners; a recorder that is garbage collected stops and finalizes its own recording.
+ */
 export declare class ScreenCaptureKitRecorder {
   constructor()
-  getAvailableScreens(): Array<ScreenSource>
-  /** Improved version that properly handles ScreenCaptureKit's async nature with timeout */
+  /**
+   * Enumerate displays and windows now, replacing the cached content
+   * @deprecated Blocks the JS thread; use `getAvailableScreens({ refresh: true })`
+   */
+  refreshContent(): Array<ScreenSource>
+  /** Drop the cached content; the next call that needs it enumerates again */
+  invalidateContentCache(): void
+  /** How long enumerated content is reused, default 3000 ms; 0 enumerates on every call */
+  setContentCacheTtl(ttlMs: number): void
+  /**
+   * Displays and windows that can be recorded; enumerates off the JS thread unless the cached
+   * content is still fresh. A string is taken as `operationId`, as in earlier versions.
+   */
+  getAvailableScreens(options?: ScreenListOptions | string | undefined | null): Promise<Array<ScreenSource>>
+  /**
+   * The sources from the cached content, without enumerating; null when there is no fresh
+   * cache (see `setContentCacheTtl`). Never blocks, so it's safe to call while rendering.
+   */
+  getCachedScreens(): Array<ScreenSource> | null
+  /**
+   * @deprecated Blocks the JS thread while enumerating; use `getAvailableScreens`, or
+   * `getCachedScreens` where a synchronous answer is needed
+   */
+  getAvailableScreensSync(): Array<ScreenSource>
+  /** @deprecated Blocks the JS thread while enumerating; use `getAvailableScreens({ timeoutMs })` */
   getAvailableScreensWithTimeout(timeoutMs?: number | undefined | null): Array<ScreenSource>
+  /**
+   * Abort the `getAvailableScreens`, `saveReplay` or `getSourceThumbnail` call started with
+   * `operationId` on this thread; it rejects with code Cancelled. False when no such call is running.
+   * Worker threads each have their own operation ids.
+   * Stopping a recording can't be cancelled, as that would leave its file unplayable.
+   */
+  cancel(operationId: string): boolean
+  /** A stream manager reading this recorder's live session, for `getCaptureStats` */
+  getStreamManager(): RealStreamManager
   getAvailableAudioDevices(): Array<AudioDevice>
-  startRecording(screenId: string, config: RecordingConfiguration): void
-  stopRecording(): string
+  /**
+   * Do the slow parts of `startRecording` ahead of time: permission check, shareable content
+   * enumeration and encoder load. A `startRecording` within the content cache TTL reuses the fetched content.
+   */
+  warmUp(): WarmUpResult
+  /**
+   * Stream downscaled JPEG frames of "display:<id>" or "window:<id>" to `callback` without
+   * recording anything, e.g. for a preview before recording. Replaces a running preview.
+   */
+  startPreview(screenId: string, options: PreviewOptions | undefined | null, callback: (frame: PreviewFrame) => void): void
+  /** False when no preview was running */
+  stopPreview(): boolean
+  /**
+   * `screen_id` is "display:<id>", "window:<id>", "windows:<id>,<id>,...", "app:<bundleId>",
+   * which records every window of that application, including ones it opens while recording,
+   * or "region:<displayId>" together with `crop`. A list of "display:<id>" records those displays
+   * at once, see `multi_display_mode`; stats then break down per display.
+   * Resolves with the session once the stream is running.
+   */
+  startRecording(screenId: string | Array<string>, config: RecordingConfiguration): Promise<RecordingSession>
+  /** `startRecording` for a display handle; fails when the display was disconnected */
+  startRecordingDisplay(handle: DisplayHandle, config: RecordingConfiguration): Promise<RecordingSession>
+  /** `startRecording` for a window handle; fails when the window was closed */
+  startRecordingWindow(handle: WindowHandle, config: RecordingConfiguration): Promise<RecordingSession>
+  /** Resolves with the output path once the files are finalized */
+  stopRecording(): Promise<string>
+  /**
+   * Arm a native timer that starts the recording at `startAt` and stops it at `stopAt`. It fires on
+   * time even while the renderer is busy; `onScheduledStart` and `onScheduledStop` report each step.
+   * A recording started by hand is left alone, and stopping the scheduled one early disarms the stop.
+   */
+  scheduleRecording(screenId: string, config: RecordingConfiguration, options: ScheduleOptions): void
+  /**
+   * Disarm a pending scheduled start or stop; a recording it already started keeps running.
+   * Returns false when nothing was scheduled.
+   */
+  cancelScheduledRecording(): boolean
+  hasScheduledRecording(): boolean
+  /** Called when a scheduled recording starts, or with `error` when it couldn't */
+  onScheduledStart(callback: (event: ScheduledRecordingEvent) => void): void
+  /** Called once the scheduled stop has finalized the recording */
+  onScheduledStop(callback: (event: ScheduledRecordingEvent) => void): void
+  /**
+   * Called after the recorder stopped and finalized a recording on its own, e.g. with
+   * reason "disk_full" when the output volume ran low on space
+   */
+  onAutoStopped(callback: (event: AutoStoppedEvent) => void): void
+  /**
+   * Push recorder events instead of polling `getStatus`: "started", "warning", "error", "stopped",
+   * and "stats" every `statsIntervalMs` while recording (default 1000, 0 for none). Replaces the
+   * previous callback.
+   */
+  onEvent(callback: (event: RecorderEvent) => void, statsIntervalMs?: number | undefined | null): void
+  /** Called on every state change with the new and previous state. Replaces the previous callback. */
+  onStateChanged(callback: (event: StateChangedEvent) => void): void
+  /**
+   * Called while a recording stops, as each phase of writing out its files begins, so a long
+   * stop can show progress. Replaces the previous callback.
+   */
+  onFinalizeProgress(callback: (event: FinalizeProgressEvent) => void): void
+  /** idle, preparing, recording, paused, stopping, then finalized or failed */
+  getState(): RecorderState
   isRecording(): boolean
-  getStatus(): string
+  /** Tag the active recording; tags land in the session journal, library index and MP4 keywords */
+  addTag(tag: string): void
+  /**
+   * Mark the current moment of the active recording. Markers become MP4 chapters and are listed
+   * in `<output>.markers.json` once the recording stops.
+   */
+  addMarker(label: string): RecordingMarker
+  /** Hide or show the cursor in the recorded frames mid-recording. The cursor stays visible and usable on screen. */
+  setCursorHiddenInOutput(hidden: boolean): void
+  /**
+   * Write the last `replayBufferSeconds` of a replay-mode recording to `path` as one MP4, while
+   * the buffer keeps capturing. Pass `operationId` to be able to `cancel` the export.
+   */
+  saveReplay(path: string, operationId?: string | undefined | null): Promise<ReplayClip>
+  /**
+   * Suspend video and audio together until `resumeRecording`. The file has no gap: the
+   * recording continues from where it was paused. Does nothing when already paused.
+   */
+  pauseRecording(): void
+  /** Does nothing when not paused */
+  resumeRecording(): void
+  /** File the active recording writes to; null when not recording */
+  get outputPath(): string | null
+  /** Recorded time of the active recording, pauses excluded; 0 when not recording */
+  get elapsedSeconds(): number
+  get currentFps(): number
+  /** Video frames captured so far, across every display of the recording */
+  get frameCount(): number
+  /** Whether the active recording is paused; a property now, where it used to be an `isPaused()` method */
+  get isPaused(): boolean
+  /** Total time the current recording has spent paused, including a pause in progress */
+  getPausedDurationMs(): number
+  /**
+   * Replace microphone audio with silence mid-recording, or restore it.
+   * The mic track keeps running so the timeline stays continuous.
+   */
+  setMicrophoneMuted(muted: boolean): void
+  /** Called with the new state whenever `setMicrophoneMuted` changes it */
+  onMicMuteChanged(callback: (muted: boolean) => void): void
+  /**
+   * Called with `true` when secure input starts pausing/blanking the recording and `false` when it resumes.
+   * Only fires for recordings started with `secureInputProtection`.
+   */
+  onSecureInputChanged(callback: (active: boolean) => void): void
+  /**
+   * Stream downmixed 16 kHz mono f32 PCM to JavaScript while recording, for live transcription.
+   * Can be registered before `startRecording`; it attaches when the recording starts.
+   */
+  startAudioTap(onChunk: (chunk: AudioTapChunk) => void, options?: AudioTapOptions | undefined | null): void
+  /**
+   * Called when an audio device change (e.g. AirPods disconnecting) pauses the audio track, and again
+   * once capture has resumed on the new route. The gap is filled with silence to keep audio in sync.
+   */
+  onAudioRouteChanged(callback: (event: AudioRouteChangeEvent) => void): void
+  /**
+   * Called with the new display list when monitors connect or disconnect, or a resolution or
+   * arrangement changes. Cached shareable content is refreshed automatically either way.
+   */
+  onDisplaysChanged(callback: (displays: Array<DisplayInfo>) => void): void
+  /**
+   * Deliver every captured audio buffer as raw interleaved f32 at the capture rate, for custom
+   * processing in JS. Can be registered before `startRecording`.
+   */
+  onAudioBuffer(callback: (buffer: AudioBufferEvent) => void): void
+  removeAudioBufferListener(): void
+  /**
+   * Deliver every captured frame's pixels, after overlays and secure-input blanking, e.g. for OCR
+   * or motion detection. Frames are dropped while JS is still busy with earlier ones. Can be
+   * registered before `startRecording`.
+   */
+  onVideoFrame(callback: (frame: VideoFrameEvent) => void, options?: VideoFrameOptions | undefined | null): void
+  removeVideoFrameListener(): void
+  stopAudioTap(): void
+  getTags(): Array<string>
+  /**
+   * What the recorder is doing right now: state, the active session with its output path,
+   * elapsed time and encoder, permission status, and the macOS features this Mac has
+   */
+  getStatus(): RecorderStatus
+  /**
+   * Suggest resolution, fps, codec and bitrate for a source based on its size,
+   * refresh rate and the encoders available on this machine
+   */
+  recommendConfiguration(screenId: string): RecommendedConfiguration
+  /**
+   * Check `config` for recording `screen_id` without starting anything: resolution against the
+   * source, fps against its refresh rate, whether the codec can be opened and whether the output
+   * path is writable
+   */
+  validateConfiguration(screenId: string, config: RecordingConfiguration): ValidationReport
 }
 export declare class AudioManager {
   constructor()
   getAvailableAudioDevices(): Array<AudioDevice>
   configureAudioSession(): void
 }
+/** System or per-process audio capture through a CoreAudio process tap, no screen capture involved (macOS 14.2+) */
+export declare class ProcessAudioCapture {
+  constructor()
+  static isSupported(): boolean
+  start(options: ProcessAudioCaptureOptions, onChunk?: ((chunk: AudioTapChunk) => void) | undefined | null): void
+  /** Stop capturing; returns the written file when `output_path` was given */
+  stop(): string | null
+  isRunning(): boolean
+}
+/** Camera-only recording (video plus microphone), e.g. a talking-head track next to the screen track */
+export declare class CameraRecorder {
+  constructor()
+  static getCameras(): Array<CameraDevice>
+  start(options: CameraRecordingOptions): void
+  stop(): CameraRecordingResult
+  isRecording(): boolean
+}
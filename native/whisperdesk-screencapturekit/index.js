@@ -310,24 +310,68 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { ContentManager, RealContentFilter, RealStreamManager, K_CV_PIXEL_FORMAT_TYPE_32_BGRA, K_CG_COLOR_SPACE_SRGB, K_CG_COLOR_SPACE_DISPLAY_P3, kCVPixelFormatType_32BGRA, kCGColorSpaceSRGB, ShareableContent, ScreenCaptureKitRecorder, initScreencapturekit, getVersion, checkScreenRecordingPermission, requestScreenRecordingPermission, checkMacosVersion, testPermissionsAndApi, testScreencapturekitWithTimeout, testPhase2Implementation, AudioManager } = nativeBinding
+const { CaptureErrorCode, RecorderState, CheckOutcome, RecordingSession, RecordingConfigurationBuilder, ContentManager, RealContentFilter, RealStreamManager, PixelFormat, ColorSpace, VideoCodec, FrameFormat, FinalizePhase, LogLevel, OutputCollision, PermissionState, PrivacyPane, ImageFormat, ShareableContent, ScreenCaptureKitRecorder, initScreencapturekit, getVersion, setLogHandler, checkScreenRecordingPermission, requestScreenRecordingPermission, getPermissionState, getRequiredPermissions, checkMicrophonePermission, requestMicrophonePermission, checkCameraPermission, requestCameraPermission, setSilentMode, isSilentMode, openPermissionSettings, watchPermissions, PermissionWatcher, getCapabilities, probeEnvironment, runPreflight, checkMacosVersion, testPermissionsAndApi, testScreencapturekitWithTimeout, testPhase2Implementation, AudioManager, setLimits, getLimits, listPresets, findRecordingsByTag, captureAllDisplays, captureAveragedScreenshot, createAggregateInputDevice, destroyAggregateInputDevice, ProcessAudioCapture, getDisplayHdrInfo, isSecureInputActive, getSessionHistory, runAudioDiagnostics, ContentWatcher, getSourceThumbnail, DisplayHandle, WindowHandle, captureScreenshot, CameraRecorder } = nativeBinding
 
+module.exports.CaptureErrorCode = CaptureErrorCode
+module.exports.RecorderState = RecorderState
+module.exports.CheckOutcome = CheckOutcome
+module.exports.RecordingSession = RecordingSession
+module.exports.RecordingConfigurationBuilder = RecordingConfigurationBuilder
 module.exports.ContentManager = ContentManager
 module.exports.RealContentFilter = RealContentFilter
 module.exports.RealStreamManager = RealStreamManager
-module.exports.K_CV_PIXEL_FORMAT_TYPE_32_BGRA = K_CV_PIXEL_FORMAT_TYPE_32_BGRA
-module.exports.K_CG_COLOR_SPACE_SRGB = K_CG_COLOR_SPACE_SRGB
-module.exports.K_CG_COLOR_SPACE_DISPLAY_P3 = K_CG_COLOR_SPACE_DISPLAY_P3
-module.exports.kCVPixelFormatType_32BGRA = kCVPixelFormatType_32BGRA
-module.exports.kCGColorSpaceSRGB = kCGColorSpaceSRGB
+module.exports.PixelFormat = PixelFormat
+module.exports.ColorSpace = ColorSpace
+module.exports.VideoCodec = VideoCodec
+module.exports.FrameFormat = FrameFormat
+module.exports.FinalizePhase = FinalizePhase
+module.exports.LogLevel = LogLevel
+module.exports.OutputCollision = OutputCollision
+module.exports.PermissionState = PermissionState
+module.exports.PrivacyPane = PrivacyPane
+module.exports.ImageFormat = ImageFormat
 module.exports.ShareableContent = ShareableContent
 module.exports.ScreenCaptureKitRecorder = ScreenCaptureKitRecorder
 module.exports.initScreencapturekit = initScreencapturekit
 module.exports.getVersion = getVersion
+module.exports.setLogHandler = setLogHandler
 module.exports.checkScreenRecordingPermission = checkScreenRecordingPermission
 module.exports.requestScreenRecordingPermission = requestScreenRecordingPermission
+module.exports.getPermissionState = getPermissionState
+module.exports.getRequiredPermissions = getRequiredPermissions
+module.exports.checkMicrophonePermission = checkMicrophonePermission
+module.exports.requestMicrophonePermission = requestMicrophonePermission
+module.exports.checkCameraPermission = checkCameraPermission
+module.exports.requestCameraPermission = requestCameraPermission
+module.exports.setSilentMode = setSilentMode
+module.exports.isSilentMode = isSilentMode
+module.exports.openPermissionSettings = openPermissionSettings
+module.exports.watchPermissions = watchPermissions
+module.exports.PermissionWatcher = PermissionWatcher
+module.exports.getCapabilities = getCapabilities
+module.exports.probeEnvironment = probeEnvironment
+module.exports.runPreflight = runPreflight
 module.exports.checkMacosVersion = checkMacosVersion
 module.exports.testPermissionsAndApi = testPermissionsAndApi
 module.exports.testScreencapturekitWithTimeout = testScreencapturekitWithTimeout
 module.exports.testPhase2Implementation = testPhase2Implementation
 module.exports.AudioManager = AudioManager
+module.exports.setLimits = setLimits
+module.exports.getLimits = getLimits
+module.exports.listPresets = listPresets
+module.exports.findRecordingsByTag = findRecordingsByTag
+module.exports.captureAllDisplays = captureAllDisplays
+module.exports.captureAveragedScreenshot = captureAveragedScreenshot
+module.exports.createAggregateInputDevice = createAggregateInputDevice
+module.exports.destroyAggregateInputDevice = destroyAggregateInputDevice
+module.exports.ProcessAudioCapture = ProcessAudioCapture
+module.exports.getDisplayHdrInfo = getDisplayHdrInfo
+module.exports.isSecureInputActive = isSecureInputActive
+module.exports.getSessionHistory = getSessionHistory
+module.exports.runAudioDiagnostics = runAudioDiagnostics
+module.exports.ContentWatcher = ContentWatcher
+module.exports.getSourceThumbnail = getSourceThumbnail
+module.exports.DisplayHandle = DisplayHandle
+module.exports.WindowHandle = WindowHandle
+module.exports.captureScreenshot = captureScreenshot
+module.exports.CameraRecorder = CameraRecorder
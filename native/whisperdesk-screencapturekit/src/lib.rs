@@ -1,11 +1,21 @@
 // FIXED lib.rs - Removes segfault-prone object extraction methods
 
 use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ErrorStrategy, ThreadSafeCallContext, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi::{JsFunction, Task};
 use napi_derive::napi;
 // ScreenCaptureKit implementation with objc2 bindings
 
 mod screencapturekit;
 
+use screencapturekit::delegate::FinalizePhase;
+use screencapturekit::errors::{capture_error, coded, CaptureErrorCode};
+use screencapturekit::formats::{ColorSpace, PixelFormat, VideoCodec};
+use screencapturekit::frames::FrameFormat;
+use screencapturekit::logging::LogLevel;
+use screencapturekit::output::OutputCollision;
+use screencapturekit::screenshot::ImageFormat;
+
 // objc2 imports for ScreenCaptureKit integration
 
 #[napi(object)]
@@ -17,6 +27,16 @@ pub struct ScreenSource {
     pub is_display: bool,
 }
 
+/// Criteria for `ContentManager.findSources`; every given field must match
+#[napi(object)]
+pub struct SourceQuery {
+    /// Regular expression (Rust syntax) matched against window titles and display names
+    pub title_regex: Option<String>,
+    /// Bundle ID or application name, case-insensitive; only windows of that app match
+    pub app_name: Option<String>,
+    pub min_width: Option<u32>,
+}
+
 #[napi(object)]
 pub struct AudioDevice {
     pub id: String,
@@ -25,6 +45,71 @@ pub struct AudioDevice {
 }
 
 #[napi(object)]
+pub struct AggregateChannelRange {
+    pub device_uid: String,
+    /// Zero-based index of this input's first channel in the aggregate device
+    pub first_channel: u32,
+    pub channel_count: u32,
+}
+
+#[napi(object)]
+pub struct AggregateInputDevice {
+    /// Pass as `audio_device_id` to record through the aggregate device
+    pub uid: String,
+    pub name: String,
+    pub channels: Vec<AggregateChannelRange>,
+}
+
+/// Region of a display in points from its top-left corner
+#[napi(object)]
+#[derive(Clone)]
+pub struct CaptureRegion {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Webcam drawn over the screen in one corner of the recording
+#[napi(object)]
+#[derive(Clone)]
+pub struct CameraPipOptions {
+    /// `uniqueID` of the camera; the system default camera when omitted
+    pub device_id: Option<String>,
+    /// "top-left", "top-right", "bottom-left" or "bottom-right" (default)
+    pub corner: Option<String>,
+    /// Width of the camera as a fraction of the recording's width, 0.05-0.5 (default 0.25)
+    pub size: Option<f64>,
+    /// Crop the camera to a circle (default false)
+    pub circular: Option<bool>,
+    /// Distance from the edges in output pixels (default 24)
+    pub margin: Option<u32>,
+}
+
+/// Capture sparsely and play back at a normal frame rate
+#[napi(object)]
+#[derive(Clone)]
+pub struct TimelapseOptions {
+    /// Seconds between captured frames, 0.1-3600
+    pub capture_interval_secs: f64,
+    /// Frame rate of the resulting video (default 30)
+    pub playback_fps: Option<u32>,
+}
+
+/// A cursor-centred crop of the display, in points
+#[napi(object)]
+#[derive(Clone)]
+pub struct ZoomFollowOptions {
+    /// Crop width (default half the display, or `height` at the output's aspect ratio)
+    pub width: Option<f64>,
+    /// Crop height (default `width` at the output's aspect ratio)
+    pub height: Option<f64>,
+    /// How far the crop trails the cursor each frame: 0 snaps to it, up to 0.99 (default 0.85)
+    pub smoothing: Option<f64>,
+}
+
+#[napi(object)]
+#[derive(Clone, Default)]
 pub struct RecordingConfiguration {
     pub width: Option<u32>,
     pub height: Option<u32>,
@@ -32,428 +117,3774 @@ pub struct RecordingConfiguration {
     pub show_cursor: Option<bool>,
     pub capture_audio: Option<bool>,
     pub audio_device_id: Option<String>,
+    /// May contain {date} and {time} (local, "2024-05-01" and "14-30-05"), {source} (display name
+    /// or window title) and {app} (the window's application), filled in when the recording starts
     pub output_path: String,
-    pub pixel_format: Option<String>,
-    pub color_space: Option<String>,
+    /// Create the output directory when it doesn't exist (default false)
+    pub create_output_directory: Option<bool>,
+    /// When the output file already exists: overwrite it (default), add a "-1", "-2", ... suffix, or fail
+    pub on_output_exists: Option<OutputCollision>,
+    /// Default `Bgra`; the YCbCr formats are cheaper but leave no room for overlays
+    pub pixel_format: Option<PixelFormat>,
+    /// Default `srgb`
+    pub color_space: Option<ColorSpace>,
+    /// Default `h264`
+    pub codec: Option<VideoCodec>,
+    pub tags: Option<Vec<String>>,
+    /// Capture the system default input with `capture_audio`, and move to the new default when it
+    /// changes mid-recording. Ignored when `audio_device_id` pins a device.
+    pub follow_default_device: Option<bool>,
+    /// Remove the cursor from the captured frames while leaving it visible on screen; overrides `show_cursor`
+    pub hide_cursor_in_output: Option<bool>,
+    /// Draw the cursor this many times larger than the system cursor (0-8, default 1). The system
+    /// cursor is read on the main queue, so without `cursor_image_path` it needs a process whose main
+    /// thread runs it, as Electron's does.
+    pub cursor_scale: Option<f64>,
+    /// Image drawn in place of the system cursor, hot spot at its top-left corner
+    pub cursor_image_path: Option<String>,
+    /// Where system audio comes from: "sck" (ScreenCaptureKit, default) or "coreaudio-tap" (macOS 14.2+)
+    pub system_audio_backend: Option<String>,
+    /// Processes captured by the "coreaudio-tap" backend; all system audio when omitted
+    pub system_audio_process_ids: Option<Vec<u32>>,
+    /// While a password field has secure input: "off" (default), "pause" the recording or "blank" the frames
+    pub secure_input_protection: Option<String>,
+    /// Also write the audio as an uncompressed 16 kHz mono WAV next to the recording, ready for whisper-cli; system audio and microphone are mixed
+    pub write_wav_sidecar: Option<bool>,
+    /// Fail the start instead of recording with degraded content data or a fallback filter.
+    /// Without it every fallback is reported in the start result's `warnings`.
+    pub strict: Option<bool>,
+    /// Windows left out of a display capture (note widgets, chat popups, ...); ignored for window capture
+    pub exclude_window_ids: Option<Vec<u32>>,
+    /// Applications hidden from a display capture by bundle ID, e.g. password managers or messengers.
+    /// An app opened during the recording is excluded as soon as its first window shows. Takes precedence over `exclude_window_ids` for windows
+    /// of other apps, which are then reported in `warnings`.
+    pub exclude_app_bundle_ids: Option<Vec<String>>,
+    /// Capture only these windows, at their positions on the display picked by `screen_id`
+    /// ("display:<id>"). Same as passing `screen_id` "windows:<id>,<id>,..."; exclusions don't apply.
+    pub window_ids: Option<Vec<u32>>,
+    /// Reshape a single-window capture to the window when it is resized instead of letterboxing
+    /// or cropping it (default true). The file keeps its start dimensions.
+    pub track_window_resize: Option<bool>,
+    /// Show the menu bar in a display capture (default true)
+    pub include_menu_bar: Option<bool>,
+    /// Show the Dock in a display capture (default true)
+    pub include_dock: Option<bool>,
+    /// Show the desktop picture and desktop icons in a display capture (default true); black when off
+    pub include_desktop: Option<bool>,
+    /// Show this app's own windows, such as the recording controls, in a display capture
+    /// (default false). They're matched by process and bundle ID, helper processes included.
+    pub include_self: Option<bool>,
+    /// Capture only this region of the display, in points from its top-left corner. All four
+    /// must be given together and the region must lie within the display.
+    pub crop_x: Option<f64>,
+    pub crop_y: Option<f64>,
+    pub crop_width: Option<f64>,
+    pub crop_height: Option<f64>,
+    /// The region for a "region:<displayId>" capture; same as setting `crop_*`, and the two can't be
+    /// combined. The region is snapped to whole pixels, and without `width`/`height` it is recorded
+    /// at its pixel size, i.e. twice its point size on Retina displays.
+    pub crop: Option<CaptureRegion>,
+    /// Record at this fraction (0-1] of the source's pixel size, or of `width`/`height` when given.
+    /// ScreenCaptureKit delivers frames at that size, so nothing larger is ever captured or encoded.
+    pub scale: Option<f64>,
+    /// Cap the output width, keeping the aspect ratio; combines with `scale`
+    pub max_output_width: Option<u32>,
+    /// Draw a ripple into the video wherever the mouse is clicked (default false). Only the
+    /// recording shows it; nothing is drawn on screen.
+    pub highlight_clicks: Option<bool>,
+    /// Write keyboard and mouse events with timestamps to `<output>.input.json` (default false).
+    /// Needs Accessibility permission; keys typed while secure input is active carry no characters.
+    pub record_input_events: Option<bool>,
+    /// Stop and finalize the recording once free space on the output volume drops below this many
+    /// MB (default 500, 0 turns the check off). A recording won't start with less than that free.
+    pub min_free_disk_mb: Option<u32>,
+    /// Instant-replay mode: keep only the last this-many seconds (up to 600) in a ring buffer instead
+    /// of recording to `output_path`; `saveReplay` writes them out. Nothing is kept after stopping.
+    pub replay_buffer_seconds: Option<u32>,
+    /// With several displays passed to `startRecording`: "separate" (default) writes
+    /// `<output>-display-<id>.mp4` per display, "composite" lays them out as arranged in
+    /// `width` x `height` of one file. Audio goes with the first display.
+    pub multi_display_mode: Option<String>,
+    /// Composite the webcam as picture-in-picture over the screen before encoding. Needs
+    /// Camera permission; the camera's frames are only in the recording, never shown on screen.
+    pub camera_pip: Option<CameraPipOptions>,
+    /// Record a timelapse: one frame every `capture_interval_secs`, timed so an hour at a 5 s
+    /// interval plays back in 24 s at 30 fps. Video only; can't be combined with audio capture.
+    pub timelapse: Option<TimelapseOptions>,
+    /// Write the cursor's position and visibility for every video frame to `<output>.cursor.json`
+    /// (default false), so an editor can re-render a smoothed or enlarged cursor. Combine with
+    /// `hide_cursor_in_output` to record without the original cursor.
+    pub record_cursor_metadata: Option<bool>,
+    /// Switch the capture to whichever window has focus, once it has kept focus for
+    /// `follow_debounce_ms`, producing one continuous recording across apps (default false).
+    /// Starts from `screen_id`; the desktop, the Dock and this app's own windows are never followed.
+    pub follow_active_window: Option<bool>,
+    /// How long a window must stay focused before the recording switches to it (default 750)
+    pub follow_debounce_ms: Option<u32>,
+    /// Record only a rectangle around the mouse cursor, easing after it as it moves, scaled up to
+    /// `width` x `height`; ideal for demos with small text. Display captures only; the display is
+    /// captured at full resolution so the zoomed text stays sharp.
+    pub zoom_follow: Option<ZoomFollowOptions>,
+    /// Encode the video (default true). With it off and `capture_audio` on, the stream runs for its
+    /// audio alone and writes `<output>_audio.m4a`; frame-based options can't be combined with it.
+    pub capture_video: Option<bool>,
+    /// Start from a named setup, see `listPresets()`: "meeting", "tutorial-1080p30",
+    /// "high-quality-4k60" or "small-file". Fields set alongside it override the preset's.
+    pub preset: Option<String>,
+    /// Version of the app making the recording, kept in the `<output>.json` metadata sidecar
+    pub app_version: Option<String>,
 }
 
-// Export ContentManager as NAPI class
-#[napi]
-pub struct ContentManager;
+#[napi(object)]
+pub struct RecordingPreset {
+    pub name: String,
+    pub description: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub show_cursor: bool,
+    pub capture_audio: bool,
+    pub highlight_clicks: bool,
+    pub write_wav_sidecar: bool,
+}
+
+#[napi(object)]
+pub struct RecordingLimits {
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    pub max_fps: Option<u32>,
+}
+
+#[napi(object)]
+#[derive(Clone)]
+pub struct RecordingStartResult {
+    pub session_id: String,
+    /// Unix time in milliseconds the session started
+    pub started_at: f64,
+    pub output_path: String,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub warnings: Vec<String>,
+    /// Set when `write_wav_sidecar` is on; the file is complete once the recording stops
+    pub wav_sidecar_path: Option<String>,
+    /// Set when `record_input_events` is on; written when the recording stops
+    pub input_events_path: Option<String>,
+    /// Set when `record_cursor_metadata` is on; written when the recording stops
+    pub cursor_track_path: Option<String>,
+    /// Configuration, source and frame counts, written when the recording stops
+    pub metadata_path: String,
+    /// Every file of a multi-display recording, first display first
+    pub output_paths: Option<Vec<String>>,
+}
+
+#[napi(object)]
+pub struct WarmUpResult {
+    pub permission_granted: bool,
+    pub displays: u32,
+    pub windows: u32,
+    /// The H.264 encoder loaded successfully
+    pub encoder_ready: bool,
+    pub duration_ms: f64,
+}
+
+#[napi(object)]
+pub struct RecordingMarker {
+    pub label: String,
+    /// Position in the video, in seconds from its first frame
+    pub time: f64,
+    /// Unix time in milliseconds the marker was added
+    pub created_at: f64,
+}
+
+#[napi(object)]
+pub struct RecordingIndexEntry {
+    pub session_id: String,
+    pub output_path: String,
+    pub started_at: f64,
+    pub stopped_at: Option<f64>,
+    pub tags: Vec<String>,
+}
+
+#[napi(object)]
+pub struct SessionHistoryEntry {
+    pub session_id: String,
+    pub output_path: String,
+    pub started_at: f64,
+    /// When the session stopped or failed
+    pub ended_at: Option<f64>,
+    pub duration_ms: Option<f64>,
+    /// "completed", "failed", "interrupted" (never stopped, e.g. after a crash) or "recording"
+    pub outcome: String,
+    /// First error reported for the session
+    pub error: Option<String>,
+    pub tags: Vec<String>,
+}
+
+#[napi(object)]
+pub struct AudioTapOptions {
+    /// Chunk length delivered to the callback, default 100 ms
+    pub chunk_ms: Option<u32>,
+}
+
+#[napi(object)]
+pub struct AudioRouteChangeEvent {
+    /// "input" or "output"
+    pub direction: String,
+    /// New default device on that side; absent when none is left
+    pub device_uid: Option<String>,
+    /// "switching" when audio pauses, then "resumed" or "failed"
+    pub phase: String,
+}
+
+#[napi(object)]
+pub struct ScheduleOptions {
+    /// Unix epoch milliseconds; now when omitted
+    pub start_at: Option<f64>,
+    /// Unix epoch milliseconds; the recording keeps running until stopped when omitted
+    pub stop_at: Option<f64>,
+}
+
+#[napi(object)]
+pub struct AutoStoppedEvent {
+    /// "disk_full"
+    pub reason: String,
+    pub output_path: String,
+    pub session_id: String,
+    /// Free space on the output volume when the recording was stopped
+    pub free_bytes: Option<f64>,
+    /// Set when finalizing the stopped recording failed
+    pub error: Option<String>,
+}
+
+#[napi(object)]
+pub struct ScheduledRecordingEvent {
+    pub output_path: String,
+    pub session_id: Option<String>,
+    /// Why the scheduled start or stop failed
+    pub error: Option<String>,
+}
+
+#[napi(object)]
+pub struct RecorderEvent {
+    /// "started", "stats", "warning", "error" or "stopped"
+    pub event: String,
+    pub session_id: Option<String>,
+    pub output_path: Option<String>,
+    /// What the warning or error is about
+    pub message: Option<String>,
+    /// Why it failed, for "error" events
+    pub code: Option<CaptureErrorCode>,
+    /// JSON capture stats, for "stats" events
+    pub stats: Option<String>,
+    pub timestamp_ms: f64,
+}
+
+impl RecorderEvent {
+    fn new(event: &str, session_id: Option<String>) -> Self {
+        Self {
+            event: event.to_string(),
+            session_id,
+            output_path: None,
+            message: None,
+            code: None,
+            stats: None,
+            timestamp_ms: screencapturekit::session::now_millis() as f64,
+        }
+    }
+
+    fn with_message(event: &str, session_id: Option<String>, message: String) -> Self {
+        Self { message: Some(message), ..Self::new(event, session_id) }
+    }
+
+    fn failed(session_id: Option<String>, error: &Error) -> Self {
+        let (code, message) = screencapturekit::errors::describe(error);
+        Self { code: Some(code), ..Self::with_message("error", session_id, message) }
+    }
+}
+
+/// Where the recorder is in a recording's lifecycle
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub enum RecorderState {
+    #[default]
+    Idle,
+    /// Enumerating content and starting the stream
+    Preparing,
+    Recording,
+    Paused,
+    /// Stopping the stream and finalizing the files
+    Stopping,
+    /// The last recording was written; a new one can start
+    Finalized,
+    /// The last recording failed to start, broke off or failed to finalize; a new one can start
+    Failed,
+}
+
+#[napi(object)]
+pub struct StateChangedEvent {
+    pub state: RecorderState,
+    pub previous: RecorderState,
+    pub session_id: Option<String>,
+    /// Why, when entering "failed"
+    pub message: Option<String>,
+    pub timestamp_ms: f64,
+}
+
+/// Sent as each phase of stopping a recording begins, ending with "done" at 100
+#[napi(object)]
+pub struct FinalizeProgressEvent {
+    pub session_id: Option<String>,
+    /// Rough share of the stop done so far, 0-100
+    pub percent: f64,
+    pub phase: FinalizePhase,
+}
+
+/// One log line, as sent to the `setLogHandler` callback
+#[napi(object)]
+pub struct LogEntry {
+    pub level: LogLevel,
+    /// Module that logged it, e.g. "content" or "encoder"
+    pub module: String,
+    pub message: String,
+    pub timestamp_ms: f64,
+}
+
+#[napi(object)]
+pub struct AudioBufferEvent {
+    /// Interleaved f32 PCM at the capture rate, `channels` samples per frame
+    pub samples: Float32Array,
+    pub channels: u32,
+    pub sample_rate: f64,
+    pub frame_count: u32,
+    /// Presentation time of the first frame in seconds (host clock)
+    pub timestamp: f64,
+}
+
+#[napi(object)]
+pub struct VideoFrameOptions {
+    /// Default `rgba`
+    pub format: Option<FrameFormat>,
+}
+
+#[napi(object)]
+pub struct VideoFrameEvent {
+    /// Pixels, `stride` bytes per row
+    pub data: Buffer,
+    pub width: u32,
+    pub height: u32,
+    pub stride: u32,
+    pub format: FrameFormat,
+    /// Presentation time in seconds (host clock)
+    pub timestamp: f64,
+}
+
+#[napi(object)]
+pub struct ContentChangeEvent {
+    /// "windowAdded", "windowClosed" or "windowRenamed"
+    pub kind: String,
+    pub window: WindowInfo,
+    /// Title before a rename
+    pub previous_title: Option<String>,
+}
+
+impl From<screencapturekit::content_watch::ContentChange> for ContentChangeEvent {
+    fn from(change: screencapturekit::content_watch::ContentChange) -> Self {
+        let kind = change.kind().to_string();
+        let (window, previous_title) = match change {
+            screencapturekit::content_watch::ContentChange::WindowAdded(window)
+            | screencapturekit::content_watch::ContentChange::WindowClosed(window) => (window, None),
+            screencapturekit::content_watch::ContentChange::WindowRenamed { window, previous_title } => (window, Some(previous_title)),
+        };
+        Self {
+            kind,
+            window: window.into(),
+            previous_title,
+        }
+    }
+}
+
+#[napi(object)]
+pub struct PermissionChangeEvent {
+    /// "screenRecording", "microphone" or "camera"
+    pub permission: String,
+    pub previous: screencapturekit::permissions::PermissionState,
+    pub current: screencapturekit::permissions::PermissionState,
+    /// Whether the permission is granted now
+    pub granted: bool,
+}
+
+impl From<screencapturekit::permission_watch::PermissionChange> for PermissionChangeEvent {
+    fn from(change: screencapturekit::permission_watch::PermissionChange) -> Self {
+        Self {
+            permission: change.permission.to_string(),
+            previous: change.previous,
+            current: change.current,
+            granted: change.current.is_granted(),
+        }
+    }
+}
+
+#[napi(object)]
+pub struct ProcessAudioCaptureOptions {
+    /// PIDs to capture, mixed to stereo; all system audio when omitted
+    pub process_ids: Option<Vec<u32>>,
+    /// Write the captured audio to this file
+    pub output_path: Option<String>,
+    /// Chunk length delivered to the callback, default 100 ms
+    pub chunk_ms: Option<u32>,
+}
+
+#[napi(object)]
+pub struct AudioTapChunk {
+    /// Mono f32 PCM in [-1, 1]
+    pub samples: Float32Array,
+    pub sample_rate: u32,
+    /// Seconds since the first audio buffer of the recording
+    pub start_time: f64,
+}
+
+#[napi(object)]
+pub struct CameraDevice {
+    /// `uniqueID`, stable across reconnects
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+}
+
+#[napi(object)]
+pub struct CameraRecordingOptions {
+    /// Video goes to `<output_path>_video.mp4`, audio to `<output_path>_audio.mp4`
+    pub output_path: String,
+    /// `id` from `CameraRecorder.getCameras()`; the default camera when omitted
+    pub device_id: Option<String>,
+    /// Record the microphone alongside (default true)
+    pub capture_audio: Option<bool>,
+    /// Input device UID; the system default input when omitted
+    pub audio_device_id: Option<String>,
+}
+
+#[napi(object)]
+pub struct CameraRecordingResult {
+    pub video_path: String,
+    /// Set when the microphone was recorded
+    pub audio_path: Option<String>,
+}
+
+#[napi(object)]
+pub struct PreviewOptions {
+    /// Frames per second, 1-30 (default 5)
+    pub fps: Option<u32>,
+    /// Downscale to at most this many pixels wide (default 640)
+    pub max_width: Option<u32>,
+}
+
+#[napi(object)]
+pub struct PreviewFrame {
+    /// JPEG image
+    pub data: Buffer,
+    pub width: u32,
+    pub height: u32,
+    pub timestamp_ms: f64,
+}
+
+#[napi(object)]
+pub struct ScreenshotFile {
+    pub display_id: u32,
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[napi(object)]
+pub struct DisplayHdrInfo {
+    pub display_id: u32,
+    /// EDR headroom in use right now; above 1.0 only while HDR content is on screen
+    pub edr_headroom: f64,
+    /// Highest EDR headroom the display can reach; 1.0 on SDR-only displays
+    pub potential_edr_headroom: f64,
+    /// Headroom at which the display stays reference-accurate
+    pub reference_edr_headroom: f64,
+    pub hdr_capable: bool,
+    /// HDR content is being shown, so HDR capture would differ from SDR capture
+    pub hdr_content_on_screen: bool,
+}
+
+#[napi(object)]
+pub struct BulkScreenshotResult {
+    /// Shared capture time (ms since epoch) embedded in every file name
+    pub timestamp: f64,
+    pub files: Vec<ScreenshotFile>,
+}
+
+#[napi(object)]
+pub struct AveragedScreenshotOptions {
+    /// Number of frames to average (2-64, default 8)
+    pub frames: Option<u32>,
+    /// Delay between frames in milliseconds (default 50)
+    pub interval_ms: Option<u32>,
+    /// Defaults to the output path's extension
+    pub format: Option<ImageFormat>,
+}
+
+#[napi(object)]
+pub struct RecommendedConfiguration {
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub codec: VideoCodec,
+    pub bitrate: u32,
+    pub hardware_encoder: bool,
+    pub notes: Vec<String>,
+}
+
+#[napi(object)]
+pub struct ValidationIssue {
+    /// The configuration field at fault, e.g. "fps" or "outputPath"
+    pub field: String,
+    pub message: String,
+}
+
+#[napi(object)]
+pub struct ValidationReport {
+    /// No errors; the recording can be attempted
+    pub valid: bool,
+    /// What would make the recording fail
+    pub errors: Vec<ValidationIssue>,
+    /// What would make it differ from the configuration
+    pub warnings: Vec<ValidationIssue>,
+}
+
+impl From<Vec<screencapturekit::validate::Issue>> for ValidationReport {
+    fn from(issues: Vec<screencapturekit::validate::Issue>) -> Self {
+        let (errors, warnings): (Vec<_>, Vec<_>) = issues
+            .into_iter()
+            .partition(|issue| issue.severity == screencapturekit::validate::Severity::Error);
+        let convert = |issues: Vec<screencapturekit::validate::Issue>| {
+            issues.into_iter().map(|issue| ValidationIssue { field: issue.field.to_string(), message: issue.message }).collect()
+        };
+        Self { valid: errors.is_empty(), errors: convert(errors), warnings: convert(warnings) }
+    }
+}
+
+#[napi(object)]
+pub struct EnumerationOptions {
+    /// Return window IDs and owner names only; call `enrichWindows()` on the result for titles and sizes
+    pub skip_window_details: Option<bool>,
+    /// Also list windows on other Spaces, e.g. to record them by ID once they come into view
+    pub include_offscreen: Option<bool>,
+    /// Also list minimized windows and windows of hidden apps. Without ScreenCaptureKit content
+    /// either option lists every off-screen window.
+    pub include_minimized: Option<bool>,
+}
+
+impl From<EnumerationOptions> for screencapturekit::content::EnumerationOptions {
+    fn from(options: EnumerationOptions) -> Self {
+        Self {
+            skip_window_details: options.skip_window_details.unwrap_or(false),
+            include_offscreen: options.include_offscreen.unwrap_or(false),
+            include_minimized: options.include_minimized.unwrap_or(false),
+        }
+    }
+}
+
+#[napi(object)]
+pub struct EnumerationProgress {
+    /// "displays", "windows" or "done"
+    pub phase: String,
+    pub displays_done: u32,
+    pub displays_total: u32,
+    pub windows_processed: u32,
+    pub windows_total: u32,
+}
+
+// Background enumeration that reports progress through a threadsafe callback
+pub struct EnumerateContentTask {
+    options: screencapturekit::content::EnumerationOptions,
+    progress: ThreadsafeFunction<EnumerationProgress, ErrorStrategy::Fatal>,
+}
+
+impl Task for EnumerateContentTask {
+    type Output = screencapturekit::content::ContentSnapshot;
+    type JsValue = ShareableContent;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let progress = &self.progress;
+        Ok(screencapturekit::content::ShareableContent::enumerate_with_progress(self.options, |update| {
+            progress.call(EnumerationProgress {
+                phase: update.phase.to_string(),
+                displays_done: update.displays_done,
+                displays_total: update.displays_total,
+                windows_processed: update.windows_processed,
+                windows_total: update.windows_total,
+            }, ThreadsafeFunctionCallMode::NonBlocking);
+        }))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(ShareableContent {
+            inner: screencapturekit::content::ShareableContent::from_snapshot(output),
+        })
+    }
+
+    fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+        screencapturekit::errors::reject_coded(env, err)
+    }
+}
+
+#[napi(object)]
+pub struct AudioDiagnosticsOptions {
+    /// Microphone to test; the system default input when omitted
+    pub audio_device_id: Option<String>,
+    /// Default 2000
+    pub duration_ms: Option<u32>,
+}
+
+#[napi(object)]
+pub struct AudioSourceReport {
+    pub device_found: bool,
+    pub device_uid: Option<String>,
+    pub device_name: Option<String>,
+    pub permission_granted: bool,
+    /// Any audio arrived during the test, silent or not
+    pub captured: bool,
+    pub has_signal: bool,
+    pub peak_db: f64,
+    pub rms_db: f64,
+    /// What went wrong, or a hint when the source stayed silent
+    pub error: Option<String>,
+}
+
+#[napi(object)]
+pub struct AudioDiagnosticsReport {
+    pub microphone: AudioSourceReport,
+    pub microphone_permission: screencapturekit::permissions::PermissionState,
+    pub system_audio: AudioSourceReport,
+    pub duration_ms: u32,
+}
+
+impl From<screencapturekit::diagnostics::SourceDiagnostics> for AudioSourceReport {
+    fn from(source: screencapturekit::diagnostics::SourceDiagnostics) -> Self {
+        Self {
+            has_signal: source.meter.has_signal(),
+            peak_db: source.meter.peak_db(),
+            rms_db: source.meter.rms_db(),
+            device_found: source.device_found,
+            device_uid: source.device_uid,
+            device_name: source.device_name,
+            permission_granted: source.permission_granted,
+            captured: source.captured,
+            error: source.error,
+        }
+    }
+}
+
+// Records for a couple of seconds, so it runs off the JS thread
+pub struct AudioDiagnosticsTask {
+    audio_device_id: Option<String>,
+    duration_ms: u32,
+}
+
+impl Task for AudioDiagnosticsTask {
+    type Output = screencapturekit::diagnostics::AudioDiagnostics;
+    type JsValue = AudioDiagnosticsReport;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        Ok(screencapturekit::diagnostics::run_audio_diagnostics(self.audio_device_id.as_deref(), self.duration_ms))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(AudioDiagnosticsReport {
+            microphone: output.microphone.into(),
+            microphone_permission: screencapturekit::permissions::microphone_authorization(),
+            system_audio: output.system_audio.into(),
+            duration_ms: output.duration_ms,
+        })
+    }
+
+    fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+        screencapturekit::errors::reject_coded(env, err)
+    }
+}
+
+// Enumerates content, which can take seconds, so it runs off the JS thread
+pub struct PreflightTask {
+    options: Option<PreflightOptions>,
+}
+
+impl Task for PreflightTask {
+    type Output = PreflightReport;
+    type JsValue = PreflightReport;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        Ok(preflight(self.options.take()))
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+
+    fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+        screencapturekit::errors::reject_coded(env, err)
+    }
+}
+
+// Waits for the user to answer the permission prompt, so it runs off the JS thread
+pub struct PermissionRequestTask {
+    request: fn() -> Result<bool>,
+}
+
+impl Task for PermissionRequestTask {
+    type Output = bool;
+    type JsValue = bool;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        (self.request)()
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+
+    fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+        screencapturekit::errors::reject_coded(env, err)
+    }
+}
+
+// Captures and encodes an image, so it runs off the JS thread
+pub struct SourceThumbnailTask {
+    screen_id: String,
+    max_width: u32,
+    format: ImageFormat,
+    operation: screencapturekit::cancel::Operation,
+}
+
+impl Task for SourceThumbnailTask {
+    type Output = screencapturekit::screenshot::Thumbnail;
+    type JsValue = Buffer;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        screencapturekit::screenshot::source_thumbnail(&self.screen_id, self.max_width, self.format, self.operation.token())
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output.data.into())
+    }
+
+    fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+        screencapturekit::errors::reject_coded(env, err)
+    }
+}
+
+#[napi(object)]
+pub struct ScreenshotOptions {
+    /// Taken from the `path` extension when omitted, else png
+    pub format: Option<ImageFormat>,
+    /// Write the image here; without it the encoded image comes back in `data`
+    pub path: Option<String>,
+    /// Region for "region:<displayId>", in points from the display's top-left corner
+    pub crop: Option<CaptureRegion>,
+    /// Include the cursor (default false)
+    pub show_cursor: Option<bool>,
+}
+
+#[napi(object)]
+pub struct ScreenshotResult {
+    /// Pixel size, twice the point size on Retina displays
+    pub width: u32,
+    pub height: u32,
+    pub path: Option<String>,
+    pub data: Option<Buffer>,
+}
+
+pub struct ScreenshotTask {
+    screen_id: String,
+    crop: Option<(f64, f64, f64, f64)>,
+    show_cursor: bool,
+    format: ImageFormat,
+    path: Option<String>,
+}
+
+impl Task for ScreenshotTask {
+    type Output = screencapturekit::screenshot::Screenshot;
+    type JsValue = ScreenshotResult;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        screencapturekit::screenshot::capture_screenshot(&self.screen_id, self.crop, self.show_cursor, self.format, self.path.as_deref())
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(ScreenshotResult {
+            width: output.width,
+            height: output.height,
+            path: output.path,
+            data: output.data.map(Buffer::from),
+        })
+    }
+
+    fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+        screencapturekit::errors::reject_coded(env, err)
+    }
+}
+
+#[napi(object)]
+pub struct ReplayClip {
+    pub path: String,
+    pub duration_ms: f64,
+}
+
+pub struct SaveReplayTask {
+    snapshot: Option<screencapturekit::replay::ReplaySnapshot>,
+    path: String,
+    operation: screencapturekit::cancel::Operation,
+}
+
+impl Task for SaveReplayTask {
+    type Output = f64;
+    type JsValue = ReplayClip;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        match self.snapshot.take() {
+            Some(snapshot) => snapshot.export(&self.path, self.operation.token()),
+            None => Err(capture_error(CaptureErrorCode::Unknown, "Replay was already saved")),
+        }
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(ReplayClip {
+            path: self.path.clone(),
+            duration_ms: output * 1000.0,
+        })
+    }
+
+    fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+        screencapturekit::errors::reject_coded(env, err)
+    }
+}
+
+// Enumerates when the recorder's cache is cold, so it runs off the JS thread
+pub struct AvailableScreensTask {
+    current_content: ContentSlot,
+    timeout: std::time::Duration,
+    refresh: bool,
+    operation: screencapturekit::cancel::Operation,
+}
+
+impl Task for AvailableScreensTask {
+    type Output = Vec<ScreenSource>;
+    type JsValue = Vec<ScreenSource>;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        ScreenCaptureKitRecorder::screen_sources(&self.current_content, self.timeout, self.refresh, self.operation.token())
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+
+    fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+        screencapturekit::errors::reject_coded(env, err)
+    }
+}
+
+/// Options for `getAvailableScreens`
+#[napi(object)]
+#[derive(Default)]
+pub struct ScreenListOptions {
+    /// How long to wait for ScreenCaptureKit before listing what Core Graphics sees (default 5000)
+    pub timeout_ms: Option<u32>,
+    /// Enumerate again even when the cached content is still fresh
+    pub refresh: Option<bool>,
+    /// Lets `cancel` abort the enumeration
+    pub operation_id: Option<String>,
+}
+
+// Enumerates, builds the filter and waits for the stream to start
+pub struct StartRecordingTask {
+    stream_manager: SessionSlot,
+    current_content: ContentSlot,
+    auto_stopped_listener: SharedListener<AutoStoppedEvent>,
+    events: RecorderEvents,
+    listeners: SessionListeners,
+    screen_ids: Vec<String>,
+    config: Option<RecordingConfiguration>,
+    // What the session reports back as its configuration
+    config_echo: RecordingConfiguration,
+}
+
+impl Task for StartRecordingTask {
+    type Output = RecordingStartResult;
+    type JsValue = RecordingSession;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let config = self.config.take()
+            .ok_or_else(|| capture_error(CaptureErrorCode::Unknown, "Recording was already started"))?;
+        self.events.preparing();
+        let started = ScreenCaptureKitRecorder::start_in_slot(
+            &self.stream_manager,
+            &self.current_content,
+            &self.auto_stopped_listener,
+            &self.events,
+            &self.listeners,
+            &self.screen_ids,
+            config,
+        );
+        match &started {
+            Ok(result) => self.events.started(&self.stream_manager, result),
+            Err(e) => {
+                self.events.start_failed(e);
+                self.events.emit(RecorderEvent::failed(None, e))
+            }
+        }
+        started
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(RecordingSession {
+            slot: self.stream_manager.clone(),
+            events: self.events.clone(),
+            config: self.config_echo.clone(),
+            result: output,
+        })
+    }
+
+    fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+        screencapturekit::errors::reject_coded(env, err)
+    }
+}
+
+// Waits for the writers to finish the files
+pub struct StopRecordingTask {
+    stream_manager: Option<screencapturekit::content::RealStreamManager>,
+    events: RecorderEvents,
+}
+
+impl Task for StopRecordingTask {
+    type Output = String;
+    type JsValue = String;
+
+    fn compute(&mut self) -> Result<Self::Output> {
+        let mut stream_manager = self.stream_manager.take()
+            .ok_or_else(|| capture_error(CaptureErrorCode::NotRecording, "Recording was already stopped"))?;
+        let output_path = self.events.stop(&mut stream_manager)?;
+        log::info!("ScreenCaptureKit recording stopped, output: {}", output_path);
+        Ok(output_path)
+    }
+
+    fn resolve(&mut self, _env: Env, output: Self::Output) -> Result<Self::JsValue> {
+        Ok(output)
+    }
+
+    fn reject(&mut self, env: Env, err: Error) -> Result<Self::JsValue> {
+        screencapturekit::errors::reject_coded(env, err)
+    }
+}
+
+/// Live counters of a running recording
+#[napi(object)]
+pub struct CaptureStats {
+    pub video_frames: f64,
+    pub audio_samples: f64,
+    /// Recorded time so far, pauses excluded
+    pub duration_ms: f64,
+    pub current_fps: f64,
+    /// Size of the files written so far
+    pub output_size_bytes: f64,
+    pub output_path: Option<String>,
+    pub is_paused: bool,
+}
+
+impl From<screencapturekit::content::CaptureStats> for CaptureStats {
+    fn from(stats: screencapturekit::content::CaptureStats) -> Self {
+        Self {
+            video_frames: stats.video_frames as f64,
+            audio_samples: stats.audio_samples as f64,
+            duration_ms: stats.duration_ms as f64,
+            current_fps: stats.current_fps,
+            output_size_bytes: stats.output_size_bytes as f64,
+            output_path: stats.output_path,
+            is_paused: stats.is_paused,
+        }
+    }
+}
+
+/// The video encoder of the active recording
+#[napi(object)]
+pub struct EncoderStatus {
+    pub codec: VideoCodec,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub hardware_accelerated: bool,
+}
+
+#[napi(object)]
+pub struct SessionStatus {
+    pub session_id: String,
+    pub output_path: String,
+    /// Unix time in milliseconds
+    pub started_at: f64,
+    /// Wall-clock time since the start, pauses included
+    pub elapsed_ms: f64,
+    /// Recorded time, pauses excluded
+    pub recorded_ms: f64,
+    pub is_paused: bool,
+    /// One stream per display being recorded
+    pub streams: u32,
+    pub captures_audio: bool,
+    pub encoder: Option<EncoderStatus>,
+}
+
+/// What this Mac supports, probed at runtime
+#[napi(object)]
+pub struct PlatformFeatures {
+    /// SCStream (macOS 12.3)
+    pub screen_capture_kit: bool,
+    /// SCScreenshotManager (macOS 14)
+    pub screenshot_manager: bool,
+    /// SCContentSharingPicker (macOS 14)
+    pub content_sharing_picker: bool,
+    /// Microphone captured by the stream itself (macOS 15)
+    pub stream_microphone: bool,
+    /// Presenter Overlay while capturing a camera alongside the screen (macOS 14)
+    pub presenter_overlay: bool,
+    /// SCRecordingOutput (macOS 15)
+    pub recording_output: bool,
+    /// HDR capture through captureDynamicRange (macOS 15)
+    pub hdr_capture: bool,
+    /// Core Audio process taps (macOS 14.2)
+    pub process_tap: bool,
+    pub hardware_h264: bool,
+    pub hardware_hevc: bool,
+}
+
+impl From<screencapturekit::status::Features> for PlatformFeatures {
+    fn from(features: screencapturekit::status::Features) -> Self {
+        Self {
+            screen_capture_kit: features.screen_capture_kit,
+            screenshot_manager: features.screenshot_manager,
+            content_sharing_picker: features.content_sharing_picker,
+            stream_microphone: features.stream_microphone,
+            presenter_overlay: features.presenter_overlay,
+            recording_output: features.recording_output,
+            hdr_capture: features.hdr_capture,
+            process_tap: features.process_tap,
+            hardware_h264: features.hardware_h264,
+            hardware_hevc: features.hardware_hevc,
+        }
+    }
+}
+
+/// What `getCapabilities` reports
+#[napi(object)]
+pub struct Capabilities {
+    /// e.g. "14.5"; null when it can't be read
+    pub macos_version: Option<String>,
+    pub arch: String,
+    pub features: PlatformFeatures,
+}
+
+#[napi(object)]
+pub struct MacosVersion {
+    /// As sw_vers prints it, e.g. "14.5"
+    pub version: String,
+    pub major: u32,
+    pub minor: u32,
+    pub patch: u32,
+}
+
+#[napi(object)]
+pub struct PermissionStates {
+    pub screen_recording: screencapturekit::permissions::PermissionState,
+    pub microphone: screencapturekit::permissions::PermissionState,
+    pub camera: screencapturekit::permissions::PermissionState,
+    pub accessibility: screencapturekit::permissions::PermissionState,
+}
+
+#[napi(object)]
+pub struct PermissionRequirement {
+    pub permission: screencapturekit::permissions::PrivacyPane,
+    /// The configuration option that needs it, or "screen capture"
+    pub needed_for: String,
+    pub state: screencapturekit::permissions::PermissionState,
+}
+
+/// What `probeEnvironment` reports
+#[napi(object)]
+pub struct EnvironmentProbe {
+    /// Null when the version can't be read
+    pub macos: Option<MacosVersion>,
+    /// "arm64" or "x86_64"
+    pub arch: String,
+    /// macOS 12.3 or later with the ScreenCaptureKit framework loaded
+    pub screen_capture_kit: bool,
+    pub permissions: PermissionStates,
+    pub hardware_h264: bool,
+    pub hardware_hevc: bool,
+}
+
+#[napi(object)]
+pub struct PreflightOptions {
+    /// Where recordings will go; the disk check looks at its volume. Default the home folder.
+    pub output_directory: Option<String>,
+    /// Free space below this fails the disk check, default 500
+    pub min_free_disk_mb: Option<u32>,
+    /// How long content enumeration may take, default 5000
+    pub timeout_ms: Option<u32>,
+}
+
+#[napi(object)]
+pub struct ContentPreflight {
+    pub ok: bool,
+    pub display_count: u32,
+    pub window_count: u32,
+    /// Why enumeration failed or was skipped
+    pub error: Option<String>,
+}
+
+#[napi(object)]
+pub struct DiskPreflight {
+    pub path: String,
+    /// Null when the volume can't be queried
+    pub available_bytes: Option<f64>,
+    pub required_bytes: f64,
+    pub ok: bool,
+}
+
+/// What `runPreflight` reports
+#[napi(object)]
+pub struct PreflightReport {
+    /// Recording can be offered: ScreenCaptureKit is usable, screen recording is granted, content
+    /// enumerates and the output volume has room
+    pub ready: bool,
+    pub environment: EnvironmentProbe,
+    pub content: ContentPreflight,
+    pub disk: DiskPreflight,
+    /// One line for each reason `ready` is false
+    pub problems: Vec<String>,
+}
+
+/// What `ScreenCaptureKitRecorder.getStatus` reports
+#[napi(object)]
+pub struct RecorderStatus {
+    pub is_recording: bool,
+    pub state: RecorderState,
+    /// The active recording; null when there is none
+    pub session: Option<SessionStatus>,
+    /// A `scheduleRecording` start or stop is still to come
+    pub scheduled: bool,
+    pub screen_recording_permission: bool,
+    pub capabilities: Capabilities,
+    pub version: String,
+}
+
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum CheckOutcome {
+    Pass,
+    /// Works, but not fully (e.g. a Core Graphics fallback)
+    Warn,
+    Fail,
+}
+
+#[napi(object)]
+pub struct DiagnosticCheck {
+    pub name: String,
+    pub outcome: CheckOutcome,
+    pub detail: String,
+}
+
+/// What the `test*` functions report
+#[napi(object)]
+pub struct DiagnosticsReport {
+    /// No check failed
+    pub passed: bool,
+    pub checks: Vec<DiagnosticCheck>,
+    pub macos_version: Option<String>,
+    pub screen_recording_permission: bool,
+    pub display_count: u32,
+    pub window_count: u32,
+}
+
+impl DiagnosticsReport {
+    fn new() -> Self {
+        Self {
+            passed: true,
+            checks: Vec::new(),
+            macos_version: screencapturekit::status::os_version(),
+            screen_recording_permission: false,
+            display_count: 0,
+            window_count: 0,
+        }
+    }
+
+    fn check(&mut self, name: &str, outcome: CheckOutcome, detail: impl Into<String>) {
+        if outcome == CheckOutcome::Fail {
+            self.passed = false;
+        }
+        self.checks.push(DiagnosticCheck { name: name.to_string(), outcome, detail: detail.into() });
+    }
+}
+
+/// One recording, as resolved by `startRecording`. Its methods only ever act on this recording:
+/// once it has stopped they fail with NotRecording, even if the recorder has started another.
+#[napi]
+pub struct RecordingSession {
+    slot: SessionSlot,
+    events: RecorderEvents,
+    config: RecordingConfiguration,
+    result: RecordingStartResult,
+}
+
+#[napi]
+impl RecordingSession {
+    #[napi(getter)]
+    pub fn id(&self) -> String {
+        self.result.session_id.clone()
+    }
+
+    /// Unix time in milliseconds
+    #[napi(getter)]
+    pub fn started_at(&self) -> f64 {
+        self.result.started_at
+    }
+
+    /// The configuration it was started with, preset applied
+    #[napi(getter)]
+    pub fn config(&self) -> RecordingConfiguration {
+        self.config.clone()
+    }
+
+    #[napi(getter)]
+    pub fn output_path(&self) -> String {
+        self.result.output_path.clone()
+    }
+
+    /// Size, frame rate, warnings and side files as the recording started
+    #[napi(getter)]
+    pub fn start_result(&self) -> RecordingStartResult {
+        self.result.clone()
+    }
+
+    fn with_own_session<T>(&self, f: impl FnOnce(&mut screencapturekit::content::RealStreamManager) -> Result<T>) -> Result<T> {
+        let mut slot = self.slot.lock()
+            .map_err(|_| capture_error(CaptureErrorCode::Unknown, "Recording state is poisoned"))?;
+        match slot.as_mut() {
+            Some(m) if m.session().map(|s| s.id == self.result.session_id).unwrap_or(false) => f(m),
+            _ => Err(capture_error(CaptureErrorCode::NotRecording, format!("Session {} is no longer recording", self.result.session_id))),
+        }
+    }
+
+    /// Stop and finalize this recording; resolves with its output path
+    #[napi(ts_return_type = "Promise<string>")]
+    pub fn stop(&self) -> Result<AsyncTask<StopRecordingTask>, CaptureErrorCode> {
+        coded(|| {
+            let stream_manager = ScreenCaptureKitRecorder::take_session(&self.slot, &self.result.session_id)
+                .ok_or_else(|| capture_error(CaptureErrorCode::NotRecording, format!("Session {} is no longer recording", self.result.session_id)))?;
+            Ok(AsyncTask::new(StopRecordingTask {
+                stream_manager: Some(stream_manager),
+                events: self.events.clone(),
+            }))
+        })
+    }
+
+    #[napi]
+    pub fn pause(&self) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            self.with_own_session(|m| m.pause())?;
+            self.events.paused(true);
+            Ok(())
+        })
+    }
+
+    #[napi]
+    pub fn resume(&self) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            self.with_own_session(|m| m.resume())?;
+            self.events.paused(false);
+            Ok(())
+        })
+    }
+
+    /// Live counters; null once the recording has stopped
+    #[napi]
+    pub fn stats(&self) -> Option<CaptureStats> {
+        self.with_own_session(|m| Ok(m.capture_stats())).ok().flatten().map(CaptureStats::from)
+    }
+
+    /// Mark the current moment; see `ScreenCaptureKitRecorder.addMarker`
+    #[napi]
+    pub fn add_marker(&self, label: String) -> Result<RecordingMarker, CaptureErrorCode> {
+        coded(|| {
+            let marker = self.with_own_session(|m| m.add_marker(&label))?;
+            Ok(RecordingMarker {
+                label: marker.label,
+                time: marker.time,
+                created_at: marker.created_at as f64,
+            })
+        })
+    }
+}
+
+/// What `RecordingConfigurationBuilder.build` produces: pass both to `startRecording`
+#[napi(object)]
+pub struct RecordingRequest {
+    pub screen_id: String,
+    pub config: RecordingConfiguration,
+}
+
+/// Builds a `RecordingConfiguration` step by step, e.g.
+/// `new RecordingConfigurationBuilder().display(id).fps(60).codec(VideoCodec.Hevc).output(path).build()`.
+/// `build` fills what was left unset from the preset and the source, and refuses contradictory
+/// combinations there rather than when the stream starts.
+#[napi]
+#[derive(Default)]
+pub struct RecordingConfigurationBuilder {
+    screen_id: Option<String>,
+    config: RecordingConfiguration,
+}
+
+#[napi]
+impl RecordingConfigurationBuilder {
+    #[napi(constructor)]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    #[napi]
+    pub fn display(&mut self, this: This, display_id: u32) -> This {
+        self.screen_id = Some(format!("display:{}", display_id));
+        this
+    }
+
+    #[napi]
+    pub fn window(&mut self, this: This, window_id: u32) -> This {
+        self.screen_id = Some(format!("window:{}", window_id));
+        this
+    }
+
+    /// Every window of one application, e.g. "com.apple.Safari"
+    #[napi]
+    pub fn application(&mut self, this: This, bundle_id: String) -> This {
+        self.screen_id = Some(format!("app:{}", bundle_id));
+        this
+    }
+
+    /// One of `listPresets()`; fields set on the builder win over the preset's
+    #[napi]
+    pub fn preset(&mut self, this: This, name: String) -> This {
+        self.config.preset = Some(name);
+        this
+    }
+
+    #[napi]
+    pub fn size(&mut self, this: This, width: u32, height: u32) -> This {
+        self.config.width = Some(width);
+        self.config.height = Some(height);
+        this
+    }
+
+    #[napi]
+    pub fn fps(&mut self, this: This, fps: u32) -> This {
+        self.config.fps = Some(fps);
+        this
+    }
+
+    #[napi]
+    pub fn codec(&mut self, this: This, codec: VideoCodec) -> This {
+        self.config.codec = Some(codec);
+        this
+    }
+
+    #[napi]
+    pub fn pixel_format(&mut self, this: This, format: PixelFormat) -> This {
+        self.config.pixel_format = Some(format);
+        this
+    }
+
+    #[napi]
+    pub fn color_space(&mut self, this: This, color_space: ColorSpace) -> This {
+        self.config.color_space = Some(color_space);
+        this
+    }
+
+    #[napi]
+    pub fn output(&mut self, this: This, path: String) -> This {
+        self.config.output_path = path;
+        this
+    }
+
+    /// Record system audio, and the microphone `deviceId` when given
+    #[napi]
+    pub fn audio(&mut self, this: This, enabled: bool, device_id: Option<String>) -> This {
+        self.config.capture_audio = Some(enabled);
+        self.config.audio_device_id = device_id;
+        this
+    }
+
+    #[napi]
+    pub fn cursor(&mut self, this: This, show: bool) -> This {
+        self.config.show_cursor = Some(show);
+        this
+    }
+
+    #[napi]
+    pub fn highlight_clicks(&mut self, this: This, enabled: bool) -> This {
+        self.config.highlight_clicks = Some(enabled);
+        this
+    }
+
+    /// The finished configuration with its defaults filled in. Fails with InvalidConfiguration,
+    /// naming every problem, when there's no source or output or the fields contradict each other.
+    #[napi]
+    pub fn build(&self) -> Result<RecordingRequest, CaptureErrorCode> {
+        coded(|| {
+            let screen_id = self.screen_id.clone().ok_or_else(|| {
+                capture_error(CaptureErrorCode::InvalidConfiguration, "No source: call display(), window() or application()")
+            })?;
+            if self.config.output_path.is_empty() {
+                return Err(capture_error(CaptureErrorCode::InvalidConfiguration, "No output path: call output()"));
+            }
+
+            let mut config = self.config.clone();
+            screencapturekit::presets::apply_preset(&mut config)
+                .map_err(|e| capture_error(CaptureErrorCode::InvalidConfiguration, e.reason))?;
+
+            // Whatever is still unset comes from the display itself, as `recommendConfiguration` would
+            // pick it; windows and applications have no size until they're captured
+            let display = screen_id.strip_prefix("display:")
+                .and_then(|id| id.parse().ok())
+                .and_then(screencapturekit::content::ShareableContent::system_display);
+            let source = match &display {
+                Some(display) => {
+                    let recommended = screencapturekit::recommend::recommend_for_display(display);
+                    if config.width.is_none() && config.height.is_none() {
+                        config.width = Some(recommended.width);
+                        config.height = Some(recommended.height);
+                    }
+                    config.fps.get_or_insert(recommended.fps);
+                    config.codec.get_or_insert(recommended.codec);
+                    Some(screencapturekit::validate::SourceSize {
+                        pixel_width: (display.width as f64 * display.scale_factor) as u32,
+                        pixel_height: (display.height as f64 * display.scale_factor) as u32,
+                        refresh_rate: display.refresh_rate,
+                    })
+                }
+                None => {
+                    if screen_id.starts_with("display:") {
+                        return Err(capture_error(CaptureErrorCode::SourceNotFound, format!("Display {} not found", screen_id)));
+                    }
+                    // Window content rarely benefits from more than 30fps
+                    config.fps.get_or_insert(screencapturekit::limits::DEFAULT_FPS);
+                    config.codec.get_or_insert(VideoCodec::H264);
+                    None
+                }
+            };
+
+            let errors: Vec<String> = screencapturekit::validate::validate(&config, source)
+                .into_iter()
+                .filter(|issue| issue.severity == screencapturekit::validate::Severity::Error)
+                .map(|issue| format!("{}: {}", issue.field, issue.message))
+                .collect();
+            if !errors.is_empty() {
+                return Err(capture_error(CaptureErrorCode::InvalidConfiguration, errors.join("; ")));
+            }
+            Ok(RecordingRequest { screen_id, config })
+        })
+    }
+}
+
+// Export ContentManager as NAPI class
+#[napi]
+pub struct ContentManager;
+
+#[napi]
+impl ContentManager {
+    #[napi(constructor)]
+    pub fn new() -> Result<Self, CaptureErrorCode> {
+        Ok(Self)
+    }
+    
+    /// `options` may ask for off-screen and minimized windows; `skipWindowDetails` only applies to
+    /// `getShareableContentWithProgress`
+    #[napi]
+    pub fn get_shareable_content(&self, options: Option<EnumerationOptions>) -> Result<ShareableContent, CaptureErrorCode> {
+        coded(|| {
+            let inner = match options {
+                Some(options) => screencapturekit::content::ShareableContent::new_with_options(options.into())?,
+                None => screencapturekit::content::ShareableContent::new_with_real_data()?,
+            };
+            Ok(ShareableContent { inner })
+        })
+    }
+    
+    /// @deprecated Use `getShareableContent`
+    #[napi]
+    pub fn get_shareable_content_sync(&self) -> Result<ShareableContent, CaptureErrorCode> {
+        log::warn!("getShareableContentSync is deprecated - use getShareableContent");
+        self.get_shareable_content(None)
+    }
+    
+    /// Windows of one application, e.g. "com.google.Chrome" or "Google Chrome"
+    #[napi]
+    pub fn get_windows_for_app(&self, bundle_id_or_name: String) -> Result<Vec<WindowInfo>, CaptureErrorCode> {
+        coded(|| {
+            let windows = screencapturekit::content::ContentManager::get_windows_for_app(&bundle_id_or_name)?;
+            Ok(windows.into_iter().map(WindowInfo::from).collect())
+        })
+    }
+    
+    /// Displays and windows matching `query`, filtered natively so only the matches cross into JS
+    #[napi]
+    pub fn find_sources(&self, query: SourceQuery) -> Result<Vec<ScreenSource>, CaptureErrorCode> {
+        coded(|| {
+            let query = screencapturekit::content::SourceQuery::new(query.title_regex.as_deref(), query.app_name, query.min_width)?;
+            let content = screencapturekit::content::ShareableContent::new_with_real_data()?;
+            Ok(screencapturekit::content::ContentManager::find_sources(&content, &query))
+        })
+    }
+    
+    /// Poll the window list every `intervalMs` (default 1000) and report windows opening, closing and
+    /// being renamed until the returned watcher is stopped
+    #[napi(ts_args_type = "callback: (event: ContentChangeEvent) => void, intervalMs?: number | undefined | null")]
+    pub fn watch_content(&self, callback: JsFunction, interval_ms: Option<u32>) -> Result<ContentWatcher, CaptureErrorCode> {
+        coded(|| {
+            let tsfn: ThreadsafeFunction<screencapturekit::content_watch::ContentChange, ErrorStrategy::Fatal> = callback
+                .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<screencapturekit::content_watch::ContentChange>| {
+                    Ok(vec![ContentChangeEvent::from(ctx.value)])
+                })?;
+            let handler: screencapturekit::content_watch::ContentChangeHandler = std::sync::Arc::new(move |change| {
+                tsfn.call(change, ThreadsafeFunctionCallMode::NonBlocking);
+            });
+        
+            let interval_ms = interval_ms.unwrap_or(screencapturekit::content_watch::DEFAULT_INTERVAL_MS);
+            Ok(ContentWatcher {
+                inner: Some(screencapturekit::content_watch::ContentWatcher::start(interval_ms, handler)),
+            })
+        })
+    }
+    
+    /// Enumerate off the main thread, reporting displays done and N of M windows processed
+    #[napi(ts_args_type = "options: EnumerationOptions | undefined | null, onProgress: (progress: EnumerationProgress) => void")]
+    pub fn get_shareable_content_with_progress(
+        &self,
+        options: Option<EnumerationOptions>,
+        on_progress: JsFunction,
+    ) -> Result<AsyncTask<EnumerateContentTask>> {
+        let progress: ThreadsafeFunction<EnumerationProgress, ErrorStrategy::Fatal> = on_progress
+            .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<EnumerationProgress>| Ok(vec![ctx.value]))?;
+        
+        let options = options.map(screencapturekit::content::EnumerationOptions::from).unwrap_or_default();
+        
+        Ok(AsyncTask::new(EnumerateContentTask { options, progress }))
+    }
+    
+    /// Return the inventory cached by the last enumeration right away (`stale: true`) and deliver
+    /// a fresh one to `onFresh` when the background enumeration finishes. Without a cache this
+    /// enumerates synchronously and `onFresh` is not called. Titles aren't persisted, so cached
+    /// windows have an empty title.
+    #[napi(ts_args_type = "onFresh: (content: ShareableContent) => void")]
+    pub fn get_shareable_content_cached(&self, on_fresh: JsFunction) -> Result<ShareableContent, CaptureErrorCode> {
+        coded(|| {
+            let inner = match screencapturekit::content::ShareableContent::from_cache() {
+                Some(cached) => cached,
+                None => {
+                    log::info!("No content cache yet - enumerating now");
+                    let inner = screencapturekit::content::ShareableContent::new_with_real_data()?;
+                    return Ok(ShareableContent { inner });
+                }
+            };
+        
+            let on_fresh: ThreadsafeFunction<screencapturekit::content::ContentSnapshot, ErrorStrategy::Fatal> = on_fresh
+                .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<screencapturekit::content::ContentSnapshot>| {
+                    let content = ShareableContent {
+                        inner: screencapturekit::content::ShareableContent::from_snapshot(ctx.value),
+                    };
+                    Ok(vec![content.into_instance(ctx.env)?])
+                })?;
+        
+            std::thread::spawn(move || {
+                let snapshot = screencapturekit::content::ShareableContent::enumerate_with_progress(
+                    screencapturekit::content::EnumerationOptions::default(),
+                    |_| {},
+                );
+                on_fresh.call(snapshot, ThreadsafeFunctionCallMode::NonBlocking);
+            });
+        
+            Ok(ShareableContent { inner })
+        })
+    }
+}
+
+/// Handle returned by `ContentManager.watchContent`
+#[napi]
+pub struct ContentWatcher {
+    inner: Option<screencapturekit::content_watch::ContentWatcher>,
+}
+
+#[napi]
+impl ContentWatcher {
+    #[napi]
+    pub fn stop(&mut self) {
+        if let Some(mut watcher) = self.inner.take() {
+            watcher.stop();
+        }
+    }
+    
+    #[napi]
+    pub fn is_watching(&self) -> bool {
+        self.inner.is_some()
+    }
+}
+
+// Export RealContentFilter as NAPI class
+#[napi]
+pub struct RealContentFilter {
+    inner: screencapturekit::content::RealContentFilter,
+}
+
+#[napi]
+impl RealContentFilter {
+    #[napi(constructor)]
+    pub fn new() -> Result<Self, CaptureErrorCode> {
+        coded(|| {
+            // Create a default filter - this would need proper initialization in real usage
+            let content = screencapturekit::content::ShareableContent::new_with_real_data()?;
+            let inner = screencapturekit::content::RealContentFilter::new_with_display(&content, 1)?;
+            Ok(Self { inner })
+        })
+    }
+    
+    #[napi(factory)]
+    pub fn from_display_handle(handle: &DisplayHandle) -> Result<Self, CaptureErrorCode> {
+        coded(|| {
+            let inner = screencapturekit::content::RealContentFilter::new_with_handle(&handle.inner)?;
+            Ok(Self { inner })
+        })
+    }
+    
+    #[napi(factory)]
+    pub fn from_window_handle(handle: &WindowHandle) -> Result<Self, CaptureErrorCode> {
+        coded(|| {
+            let inner = screencapturekit::content::RealContentFilter::new_with_handle(&handle.inner)?;
+            Ok(Self { inner })
+        })
+    }
+    
+    /// Deprecated: does not build a filter; use `RealContentFilter.fromDisplayHandle`
+    #[napi]
+    pub fn init_with_display(&mut self, display: DisplayInfo) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            // This would properly initialize with the given display
+            log::info!("Initializing content filter with display: {}", display.name);
+            Ok(())
+        })
+    }
+    
+    #[napi]
+    pub fn is_valid(&self) -> bool {
+        self.inner.is_valid()
+    }
+}
+
+// Export RealStreamManager as NAPI class; `ScreenCaptureKitRecorder.getStreamManager` hands out
+// one that looks at the recorder's live session
+#[napi]
+pub struct RealStreamManager {
+    slot: SessionSlot,
+}
+
+#[napi]
+impl RealStreamManager {
+    #[napi(constructor)]
+    pub fn new() -> Result<Self, CaptureErrorCode> {
+        coded(|| {
+            Ok(Self { slot: std::sync::Arc::new(std::sync::Mutex::new(None)) })
+        })
+    }
+    
+    #[napi]
+    pub fn initialize_stream(
+        &mut self,
+        _content_filter: &RealContentFilter,
+        stream_config: String,
+        output_path: String,
+    ) -> Result<()> {
+        log::info!("Initializing stream with config: {}", stream_config);
+        log::info!("Output path: {}", output_path);
+        Ok(())
+    }
+    
+    #[napi]
+    pub fn start_capture(&mut self) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            log::info!("Starting capture");
+            Ok(())
+        })
+    }
+    
+    #[napi]
+    pub fn stop_capture(&mut self) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            log::info!("Stopping capture");
+            Ok(())
+        })
+    }
+    
+    /// Live counters of the running recording; null when there is none
+    #[napi]
+    pub fn get_capture_stats(&self) -> Option<CaptureStats> {
+        self.slot.lock().ok().and_then(|slot| slot.as_ref().and_then(|m| m.capture_stats())).map(CaptureStats::from)
+    }
+}
+
+// Export DisplayInfo as NAPI object
+#[napi(object)]
+pub struct DisplayInfo {
+    pub id: u32,
+    pub name: String,
+    pub width: u32,
+    pub height: u32,
+    /// Part of a mirror set; recording it captures the same content as the other displays in the set
+    pub is_mirrored: bool,
+    /// ID of the display this one mirrors, when it isn't the primary of its mirror set
+    pub mirror_of: Option<u32>,
+    /// Backing pixels per point (2 on Retina displays)
+    pub scale_factor: f64,
+    /// Maximum refresh rate in Hz; capturing a 120 Hz display at full rate costs noticeably more
+    pub refresh_rate: f64,
+    /// The display holding the menu bar
+    pub is_main: bool,
+    /// Localized color space name, e.g. "Display P3"
+    pub color_space: Option<String>,
+    /// Top-left corner in global coordinates (points, y down); the main display sits at 0,0 and
+    /// displays left of or above it have negative origins
+    pub origin_x: f64,
+    pub origin_y: f64,
+}
+
+impl From<screencapturekit::content::DisplayInfo> for DisplayInfo {
+    fn from(display: screencapturekit::content::DisplayInfo) -> Self {
+        Self {
+            id: display.id,
+            name: display.name,
+            width: display.width,
+            height: display.height,
+            is_mirrored: display.is_mirrored,
+            mirror_of: display.mirror_of,
+            scale_factor: display.scale_factor,
+            refresh_rate: display.refresh_rate,
+            is_main: display.is_main,
+            color_space: display.color_space,
+            origin_x: display.origin_x,
+            origin_y: display.origin_y,
+        }
+    }
+}
+
+// Export WindowInfo as NAPI object  
+#[napi(object)]
+pub struct WindowInfo {
+    pub id: u32,
+    pub title: String,
+    pub width: u32,
+    pub height: u32,
+    /// Bundle identifier of the owning application, for grouping windows by app
+    pub owner_bundle_id: Option<String>,
+    pub owner_name: Option<String>,
+    pub pid: Option<i32>,
+    /// Window server layer; 0 for normal app windows, non-zero for menus, overlays and other system UI
+    pub window_layer: i32,
+    pub is_on_screen: bool,
+    /// Active on some space, i.e. not minimized or hidden with its app
+    pub is_active: bool,
+}
+
+impl From<screencapturekit::content::WindowInfo> for WindowInfo {
+    fn from(window: screencapturekit::content::WindowInfo) -> Self {
+        Self {
+            id: window.id,
+            title: window.title,
+            width: window.width,
+            height: window.height,
+            owner_bundle_id: window.owner_bundle_id,
+            owner_name: window.owner_name,
+            pid: window.pid,
+            window_layer: window.window_layer,
+            is_on_screen: window.is_on_screen,
+            is_active: window.is_active,
+        }
+    }
+}
+
+// Export ShareableContent as NAPI class - FIXED to remove segfault methods
+#[napi]
+pub struct ShareableContent {
+    inner: screencapturekit::ShareableContent,
+}
+
+#[napi]
+impl ShareableContent {
+    #[napi(constructor)]
+    pub fn new() -> Result<Self, CaptureErrorCode> {
+        coded(|| {
+            let inner = screencapturekit::ShareableContent::new_with_real_data()?;
+            Ok(Self { inner })
+        })
+    }
+    
+    #[napi]
+    pub fn get_displays(&self) -> Result<Vec<DisplayInfo>, CaptureErrorCode> {
+        coded(|| {
+            let displays = self.inner.get_displays()?;
+            Ok(displays.into_iter().map(DisplayInfo::from).collect())
+        })
+    }
+    
+    #[napi]
+    pub fn get_windows(&self) -> Result<Vec<WindowInfo>, CaptureErrorCode> {
+        coded(|| {
+            let windows = self.inner.get_windows()?;
+            Ok(windows.into_iter().map(WindowInfo::from).collect())
+        })
+    }
+    
+    #[napi(getter)]
+    pub fn displays(&self) -> Result<Vec<DisplayInfo>, CaptureErrorCode> {
+        self.get_displays()
+    }
+    
+    #[napi(getter)]
+    pub fn windows(&self) -> Result<Vec<WindowInfo>, CaptureErrorCode> {
+        self.get_windows()
+    }
+    
+    /// Loaded from the on-disk cache of the last enumeration; may not match what is on screen now
+    #[napi(getter)]
+    pub fn stale(&self) -> bool {
+        self.inner.is_stale()
+    }
+    
+    /// Degraded paths taken while enumerating (Core Graphics data, placeholder windows, ...)
+    #[napi(getter)]
+    pub fn fallbacks(&self) -> Vec<String> {
+        self.inner.fallbacks().to_vec()
+    }
+    
+    /// Number of windows still waiting for details after a fast enumeration
+    #[napi(getter)]
+    pub fn pending_window_details(&self) -> u32 {
+        self.inner.pending_window_details() as u32
+    }
+    
+    /// Fill in titles and sizes for windows enumerated with `skipWindowDetails`
+    #[napi]
+    pub fn enrich_windows(&mut self) -> u32 {
+        self.inner.enrich_windows() as u32
+    }
+    
+    // REMOVED: The problematic getScDisplayById and getScWindowById methods
+    // These methods caused segmentation faults and have been replaced with
+    // safer content filter creation methods in the internal implementation
+    
+    // ADDED: Safe methods for checking if display/window exists
+    #[napi]
+    pub fn has_display(&self, display_id: u32) -> bool {
+        self.inner.find_display_by_id(display_id).is_some()
+    }
+    
+    #[napi]
+    pub fn has_window(&self, window_id: u32) -> bool {
+        self.inner.find_window_by_id(window_id).is_some()
+    }
+    
+    // ADDED: Safe method to get display info without object extraction
+    #[napi]
+    pub fn get_display_info(&self, display_id: u32) -> Result<Option<DisplayInfo>, CaptureErrorCode> {
+        Ok(self.inner.find_display_by_id(display_id).cloned().map(DisplayInfo::from))
+    }
+    
+    /// The display showing the global point (x, y), e.g. a cursor or window position
+    #[napi]
+    pub fn get_display_at_point(&self, x: f64, y: f64) -> Option<DisplayInfo> {
+        self.inner.display_at_point(x, y).cloned().map(DisplayInfo::from)
+    }
+    
+    // ADDED: Safe method to get window info without object extraction
+    #[napi]
+    pub fn get_window_info(&self, window_id: u32) -> Result<Option<WindowInfo>, CaptureErrorCode> {
+        Ok(self.inner.find_window_by_id(window_id).cloned().map(WindowInfo::from))
+    }
+    
+    /// Typed handle to a display, for `RealContentFilter.fromDisplayHandle` and
+    /// `ScreenCaptureKitRecorder.startRecordingDisplay`; null when ScreenCaptureKit doesn't list it
+    #[napi]
+    pub fn get_display_handle(&self, display_id: u32) -> Option<DisplayHandle> {
+        self.inner.display_handle(display_id).map(|inner| DisplayHandle { inner })
+    }
+    
+    /// Typed handle to a window, for `RealContentFilter.fromWindowHandle` and
+    /// `ScreenCaptureKitRecorder.startRecordingWindow`; null when ScreenCaptureKit doesn't list it
+    #[napi]
+    pub fn get_window_handle(&self, window_id: u32) -> Option<WindowHandle> {
+        self.inner.window_handle(window_id).map(|inner| WindowHandle { inner })
+    }
+}
+
+/// Opaque reference to a display listed by ScreenCaptureKit
+#[napi]
+pub struct DisplayHandle {
+    inner: screencapturekit::content::SourceHandle,
+}
+
+#[napi]
+impl DisplayHandle {
+    #[napi(getter)]
+    pub fn id(&self) -> u32 {
+        self.inner.id()
+    }
+    
+    #[napi(getter)]
+    pub fn screen_id(&self) -> String {
+        self.inner.screen_id()
+    }
+    
+    /// The display is still attached
+    #[napi]
+    pub fn is_valid(&self) -> bool {
+        self.inner.is_available()
+    }
+}
+
+/// Opaque reference to a window listed by ScreenCaptureKit
+#[napi]
+pub struct WindowHandle {
+    inner: screencapturekit::content::SourceHandle,
+}
+
+#[napi]
+impl WindowHandle {
+    #[napi(getter)]
+    pub fn id(&self) -> u32 {
+        self.inner.id()
+    }
+    
+    #[napi(getter)]
+    pub fn screen_id(&self) -> String {
+        self.inner.screen_id()
+    }
+    
+    /// The window is still open
+    #[napi]
+    pub fn is_valid(&self) -> bool {
+        self.inner.is_available()
+    }
+}
+
+/// Listeners registered on the recorder, attached to every session it starts
+#[derive(Default, Clone)]
+struct SessionListeners {
+    audio_tap: Option<(screencapturekit::audio_tap::PcmChunkSink, u32)>,
+    secure_input: Option<screencapturekit::secure_input::SecureInputListener>,
+    audio_buffer: Option<screencapturekit::process_tap::PcmHandler>,
+    audio_route: Option<screencapturekit::audio::AudioRouteHandler>,
+    video_frame: Option<screencapturekit::frames::VideoFrameListener>,
+}
+
+impl SessionListeners {
+    fn attach(&self, stream_manager: &mut screencapturekit::content::RealStreamManager) -> Result<()> {
+        if let Some((sink, chunk_ms)) = &self.audio_tap {
+            stream_manager.set_audio_tap(Some(screencapturekit::audio_tap::AudioTap::new(sink.clone(), *chunk_ms)))?;
+        }
+        if let Some(listener) = &self.secure_input {
+            stream_manager.set_secure_input_listener(Some(listener.clone()))?;
+        }
+        if let Some(listener) = &self.audio_buffer {
+            stream_manager.set_audio_buffer_listener(Some(listener.clone()))?;
+        }
+        if let Some(listener) = &self.video_frame {
+            stream_manager.set_video_frame_listener(Some(listener.clone()))?;
+        }
+        stream_manager.set_audio_route_listener(self.audio_route.clone());
+        Ok(())
+    }
+}
+
+type SessionSlot = std::sync::Arc<std::sync::Mutex<Option<screencapturekit::content::RealStreamManager>>>;
+// Shared with the tasks that enumerate or start a recording off the JS thread
+type ContentSlot = std::sync::Arc<std::sync::Mutex<Option<screencapturekit::content::ShareableContent>>>;
+type SharedListener<T> = std::sync::Arc<std::sync::Mutex<Option<ThreadsafeFunction<T, ErrorStrategy::Fatal>>>>;
+
+fn emit_shared<T: 'static>(listener: &SharedListener<T>, event: T) {
+    if let Ok(listener) = listener.lock() {
+        if let Some(tsfn) = listener.as_ref() {
+            tsfn.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+        }
+    }
+}
+
+/// Where `onEvent` delivers recorder events, and how often a running session reports its stats.
+/// Also holds the recorder state, which moves along with the events.
+#[derive(Clone)]
+struct RecorderEvents {
+    listener: SharedListener<RecorderEvent>,
+    stats_interval_ms: std::sync::Arc<std::sync::atomic::AtomicU32>,
+    state: std::sync::Arc<std::sync::Mutex<RecorderState>>,
+    state_listener: SharedListener<StateChangedEvent>,
+    finalize_listener: SharedListener<FinalizeProgressEvent>,
+}
+
+impl RecorderEvents {
+    fn new() -> Self {
+        Self {
+            listener: std::sync::Arc::new(std::sync::Mutex::new(None)),
+            stats_interval_ms: std::sync::Arc::new(std::sync::atomic::AtomicU32::new(DEFAULT_STATS_INTERVAL_MS)),
+            state: std::sync::Arc::new(std::sync::Mutex::new(RecorderState::Idle)),
+            state_listener: std::sync::Arc::new(std::sync::Mutex::new(None)),
+            finalize_listener: std::sync::Arc::new(std::sync::Mutex::new(None)),
+        }
+    }
+
+    fn emit(&self, event: RecorderEvent) {
+        emit_shared(&self.listener, event);
+    }
+
+    fn state(&self) -> RecorderState {
+        self.state.lock().map(|state| *state).unwrap_or(RecorderState::Failed)
+    }
+
+    /// Move to `to` if the recorder is in one of `from`; anything else is a stale or out-of-order
+    /// step (e.g. a stop racing a stream error) and is ignored
+    fn transition(&self, from: &[RecorderState], to: RecorderState, session_id: Option<String>, message: Option<String>) {
+        let previous = match self.state.lock() {
+            Ok(mut state) if from.contains(&*state) && *state != to => std::mem::replace(&mut *state, to),
+            _ => return,
+        };
+        log::info!("Recorder state: {:?} -> {:?}", previous, to);
+        emit_shared(&self.state_listener, StateChangedEvent {
+            state: to,
+            previous,
+            session_id,
+            message,
+            timestamp_ms: screencapturekit::session::now_millis() as f64,
+        });
+    }
+
+    fn preparing(&self) {
+        use RecorderState::*;
+        self.transition(&[Idle, Finalized, Failed], Preparing, None, None);
+    }
+
+    fn start_failed(&self, error: &Error) {
+        self.transition(&[RecorderState::Preparing], RecorderState::Failed, None, Some(screencapturekit::errors::describe(error).1));
+    }
+
+    fn stopping(&self, session_id: Option<String>) {
+        use RecorderState::*;
+        self.transition(&[Recording, Paused, Failed], Stopping, session_id, None);
+    }
+
+    /// Stop and finalize `stream_manager`, reporting the state changes and finalize progress
+    fn stop(&self, stream_manager: &mut screencapturekit::content::RealStreamManager) -> Result<String> {
+        let session_id = stream_manager.session().map(|s| s.id.clone());
+        self.stopping(session_id.clone());
+        let stopped = stream_manager.stop_recording_with_progress(&mut |phase| {
+            emit_shared(&self.finalize_listener, FinalizeProgressEvent {
+                session_id: session_id.clone(),
+                percent: phase.percent(),
+                phase,
+            });
+        });
+        self.stopped(session_id, &stopped);
+        stopped
+    }
+
+    fn paused(&self, paused: bool) {
+        use RecorderState::*;
+        if paused {
+            self.transition(&[Recording], Paused, None, None);
+        } else {
+            self.transition(&[Paused], Recording, None, None);
+        }
+    }
+
+    /// "started", one "warning" per start warning, then "stats" until the session leaves `slot`
+    fn started(&self, slot: &SessionSlot, result: &RecordingStartResult) {
+        use RecorderState::*;
+        self.transition(&[Preparing, Idle, Finalized, Failed], Recording, Some(result.session_id.clone()), None);
+        self.emit(RecorderEvent {
+            output_path: Some(result.output_path.clone()),
+            ..RecorderEvent::new("started", Some(result.session_id.clone()))
+        });
+        for warning in &result.warnings {
+            self.emit(RecorderEvent::with_message("warning", Some(result.session_id.clone()), warning.clone()));
+        }
+        self.watch_stats(slot, result.session_id.clone());
+    }
+
+    /// "stopped" with the output path, or "error" when finalizing failed
+    fn stopped(&self, session_id: Option<String>, outcome: &Result<String>) {
+        match outcome {
+            Ok(output_path) => {
+                self.transition(&[RecorderState::Stopping], RecorderState::Finalized, session_id.clone(), None);
+                self.emit(RecorderEvent {
+                    output_path: Some(output_path.clone()),
+                    ..RecorderEvent::new("stopped", session_id)
+                })
+            }
+            Err(e) => {
+                let message = screencapturekit::errors::describe(e).1;
+                self.transition(&[RecorderState::Stopping], RecorderState::Failed, session_id.clone(), Some(message));
+                self.emit(RecorderEvent::failed(session_id, e))
+            }
+        }
+    }
+
+    fn watch_stats(&self, slot: &SessionSlot, session_id: String) {
+        // Weak, so a dropped recorder doesn't keep the ticker (and its session) alive
+        let slot = std::sync::Arc::downgrade(slot);
+        let events = self.clone();
+        std::thread::spawn(move || loop {
+            let interval_ms = events.stats_interval_ms.load(std::sync::atomic::Ordering::Relaxed);
+            let sleep_ms = if interval_ms == 0 { DEFAULT_STATS_INTERVAL_MS } else { interval_ms };
+            std::thread::sleep(std::time::Duration::from_millis(sleep_ms as u64));
+            let stats = match slot.upgrade() {
+                Some(slot) => match slot.lock() {
+                    Ok(slot) => match slot.as_ref() {
+                        Some(m) if m.session().map(|s| s.id == session_id).unwrap_or(false) && m.is_recording() => {
+                            if let Some(e) = m.stream_error() {
+                                events.transition(
+                                    &[RecorderState::Recording, RecorderState::Paused],
+                                    RecorderState::Failed,
+                                    Some(session_id.clone()),
+                                    Some(screencapturekit::errors::describe(&e).1),
+                                );
+                                events.emit(RecorderEvent::failed(Some(session_id.clone()), &e));
+                                break;
+                            }
+                            m.get_stats()
+                        }
+                        _ => break,
+                    },
+                    Err(_) => break,
+                },
+                None => break,
+            };
+            // 0 turns the stats off without ending the ticker, so they can be turned back on
+            if interval_ms > 0 {
+                events.emit(RecorderEvent { stats: Some(stats), ..RecorderEvent::new("stats", Some(session_id.clone())) });
+            }
+        });
+    }
+}
+
+/// How often "stats" events go out while recording unless `onEvent` asks otherwise
+const DEFAULT_STATS_INTERVAL_MS: u32 = 1000;
+
+/// One recording at a time. Recorders are independent of each other, so several can run at once
+/// (one per display, or a preview next to a recording), each with its own content cache, events,
+/// stats and listeners; a recorder that is garbage collected stops and finalizes its own recording.
+#[napi]
+pub struct ScreenCaptureKitRecorder {
+    current_content: ContentSlot,
+    content_ttl: std::time::Duration,
+    // Shared with the scheduled-recording timer, which starts and stops sessions on its own thread
+    stream_manager: SessionSlot,
+    listeners: std::sync::Arc<std::sync::Mutex<SessionListeners>>,
+    mic_mute_listener: Option<ThreadsafeFunction<bool, ErrorStrategy::Fatal>>,
+    schedule: Option<screencapturekit::schedule::RecordingSchedule>,
+    scheduled_start_listener: SharedListener<ScheduledRecordingEvent>,
+    scheduled_stop_listener: SharedListener<ScheduledRecordingEvent>,
+    auto_stopped_listener: SharedListener<AutoStoppedEvent>,
+    events: RecorderEvents,
+    preview: Option<screencapturekit::preview::PreviewStream>,
+    // Set by the display listener; the cached content is dropped before its next use
+    displays_changed: std::sync::Arc<std::sync::Mutex<bool>>,
+    displays_changed_listener: std::sync::Arc<std::sync::Mutex<Option<ThreadsafeFunction<Vec<screencapturekit::content::DisplayInfo>, ErrorStrategy::Fatal>>>>,
+    _display_listener: Option<screencapturekit::display_watch::DisplayReconfigurationListener>,
+}
+
+#[napi]
+impl ScreenCaptureKitRecorder {
+    #[napi(constructor)]
+    pub fn new() -> Result<Self, CaptureErrorCode> {
+        coded(|| {
+            log::info!("Creating new ScreenCaptureKit recorder with objc2");
+        
+            let displays_changed = std::sync::Arc::new(std::sync::Mutex::new(false));
+            let displays_changed_listener = std::sync::Arc::new(std::sync::Mutex::new(None));
+            let display_listener = {
+                let displays_changed = displays_changed.clone();
+                let listener: std::sync::Arc<std::sync::Mutex<Option<ThreadsafeFunction<Vec<screencapturekit::content::DisplayInfo>, ErrorStrategy::Fatal>>>> =
+                    displays_changed_listener.clone();
+                screencapturekit::display_watch::DisplayReconfigurationListener::start(std::sync::Arc::new(
+                    move |displays: Vec<screencapturekit::content::DisplayInfo>| {
+                        if let Ok(mut changed) = displays_changed.lock() {
+                            *changed = true;
+                        }
+                        if let Ok(listener) = listener.lock() {
+                            if let Some(tsfn) = listener.as_ref() {
+                                tsfn.call(displays, ThreadsafeFunctionCallMode::NonBlocking);
+                            }
+                        }
+                    },
+                ))
+            };
+        
+            let stream_manager: SessionSlot = std::sync::Arc::new(std::sync::Mutex::new(None));
+            screencapturekit::shutdown::track(&stream_manager);
+        
+            Ok(Self {
+                current_content: std::sync::Arc::new(std::sync::Mutex::new(None)),
+                content_ttl: std::time::Duration::from_millis(screencapturekit::content::DEFAULT_CONTENT_TTL_MS as u64),
+                stream_manager,
+                listeners: std::sync::Arc::new(std::sync::Mutex::new(SessionListeners::default())),
+                mic_mute_listener: None,
+                schedule: None,
+                scheduled_start_listener: std::sync::Arc::new(std::sync::Mutex::new(None)),
+                scheduled_stop_listener: std::sync::Arc::new(std::sync::Mutex::new(None)),
+                auto_stopped_listener: std::sync::Arc::new(std::sync::Mutex::new(None)),
+                events: RecorderEvents::new(),
+                preview: None,
+                displays_changed,
+                displays_changed_listener,
+                _display_listener: display_listener,
+            })
+        })
+    }
+    
+    /// Forget the cached content once it outlived the TTL or the display configuration changed under it
+    fn expire_cached_content(&self) {
+        let mut current_content = match self.current_content.lock() {
+            Ok(current_content) => current_content,
+            Err(_) => return,
+        };
+        let changed = self.displays_changed.lock().map(|mut changed| std::mem::take(&mut *changed)).unwrap_or(false);
+        if changed && current_content.take().is_some() {
+            log::info!("Displays changed - cached shareable content discarded");
+        }
+        let expired = current_content.as_ref().map(|content| content.age() >= self.content_ttl).unwrap_or(false);
+        if expired {
+            *current_content = None;
+            log::info!("Cached shareable content expired after {} ms", self.content_ttl.as_millis());
+        }
+    }
+    
+    fn lock_content(&self) -> Result<std::sync::MutexGuard<'_, Option<screencapturekit::content::ShareableContent>>> {
+        self.current_content.lock()
+            .map_err(|_| capture_error(CaptureErrorCode::Unknown, "Content cache is poisoned"))
+    }
+    
+    /// Sources from the cached content, enumerating first when there is none or `refresh` is set.
+    /// ScreenCaptureKit not answering within `timeout` falls back to what Core Graphics sees.
+    fn screen_sources(
+        current_content: &ContentSlot,
+        timeout: std::time::Duration,
+        refresh: bool,
+        cancel: &screencapturekit::cancel::CancelToken,
+    ) -> Result<Vec<ScreenSource>> {
+        let mut current_content = current_content.lock()
+            .map_err(|_| capture_error(CaptureErrorCode::Unknown, "Content cache is poisoned"))?;
+        if let (false, Some(content)) = (refresh, current_content.as_ref()) {
+            let sources = screencapturekit::content::ContentManager::extract_screen_sources(content)?;
+            log::info!("Found {} screen sources from cache", sources.len());
+            return Ok(sources);
+        }
+        
+        let content = screencapturekit::content::ShareableContent::new_cancellable(timeout, cancel)?;
+        let sources = screencapturekit::content::ContentManager::extract_screen_sources(&content)?;
+        *current_content = Some(content);
+        log::info!("Found {} screen sources", sources.len());
+        Ok(sources)
+    }
+
+    /// Enumerate displays and windows now, replacing the cached content
+    /// @deprecated Blocks the JS thread; use `getAvailableScreens({ refresh: true })`
+    #[napi]
+    pub fn refresh_content(&mut self) -> Result<Vec<ScreenSource>, CaptureErrorCode> {
+        coded(|| {
+            log::warn!("refreshContent is deprecated - use getAvailableScreens({{ refresh: true }})");
+            log::info!("Refreshing shareable content");
+            let content = screencapturekit::content::ShareableContent::new_with_real_data()?;
+            let sources = screencapturekit::content::ContentManager::extract_screen_sources(&content)?;
+            *self.lock_content()? = Some(content);
+            Ok(sources)
+        })
+    }
+
+    /// Drop the cached content; the next call that needs it enumerates again
+    #[napi]
+    pub fn invalidate_content_cache(&mut self) {
+        if self.current_content.lock().ok().and_then(|mut content| content.take()).is_some() {
+            log::info!("Cached shareable content invalidated");
+        }
+    }
+
+    /// How long enumerated content is reused, default 3000 ms; 0 enumerates on every call
+    #[napi]
+    pub fn set_content_cache_ttl(&mut self, ttl_ms: u32) {
+        self.content_ttl = std::time::Duration::from_millis(ttl_ms as u64);
+    }
+
+    /// Displays and windows that can be recorded; enumerates off the JS thread unless the cached
+    /// content is still fresh. A string is taken as `operationId`, as in earlier versions.
+    #[napi(ts_return_type = "Promise<Array<ScreenSource>>")]
+    pub fn get_available_screens(&self, options: Option<Either<ScreenListOptions, String>>) -> Result<AsyncTask<AvailableScreensTask>, CaptureErrorCode> {
+        coded(|| {
+            log::info!("Getting available screens via ScreenCaptureKit");
+            let options = match options {
+                Some(Either::A(options)) => options,
+                Some(Either::B(operation_id)) => ScreenListOptions { operation_id: Some(operation_id), ..Default::default() },
+                None => ScreenListOptions::default(),
+            };
+            self.expire_cached_content();
+            Ok(AsyncTask::new(AvailableScreensTask {
+                current_content: self.current_content.clone(),
+                timeout: options.timeout_ms
+                    .map(|ms| std::time::Duration::from_millis(ms as u64))
+                    .unwrap_or(screencapturekit::bindings::SHAREABLE_CONTENT_TIMEOUT),
+                refresh: options.refresh.unwrap_or(false),
+                operation: screencapturekit::cancel::Operation::begin(options.operation_id)?,
+            }))
+        })
+    }
+
+    /// The sources from the cached content, without enumerating; null when there is no fresh
+    /// cache (see `setContentCacheTtl`). Never blocks, so it's safe to call while rendering.
+    #[napi]
+    pub fn get_cached_screens(&self) -> Result<Option<Vec<ScreenSource>>, CaptureErrorCode> {
+        coded(|| {
+            let current_content = match self.current_content.try_lock() {
+                Ok(current_content) => current_content,
+                // An enumeration is running and about to replace the cache
+                Err(std::sync::TryLockError::WouldBlock) => return Ok(None),
+                Err(std::sync::TryLockError::Poisoned(_)) => return Err(capture_error(CaptureErrorCode::Unknown, "Content cache is poisoned")),
+            };
+            let displays_changed = self.displays_changed.lock().map(|changed| *changed).unwrap_or(true);
+            match current_content.as_ref() {
+                Some(content) if !displays_changed && content.age() < self.content_ttl => {
+                    Ok(Some(screencapturekit::content::ContentManager::extract_screen_sources(content)?))
+                }
+                _ => Ok(None),
+            }
+        })
+    }
+
+    /// @deprecated Blocks the JS thread while enumerating; use `getAvailableScreens`, or
+    /// `getCachedScreens` where a synchronous answer is needed
+    #[napi]
+    pub fn get_available_screens_sync(&self) -> Result<Vec<ScreenSource>, CaptureErrorCode> {
+        coded(|| {
+            log::warn!("getAvailableScreensSync is deprecated - use getAvailableScreens or getCachedScreens");
+            self.expire_cached_content();
+            Self::screen_sources(
+                &self.current_content,
+                screencapturekit::bindings::SHAREABLE_CONTENT_TIMEOUT,
+                false,
+                &Default::default(),
+            )
+        })
+    }
+
+    /// @deprecated Blocks the JS thread while enumerating; use `getAvailableScreens({ timeoutMs })`
+    #[napi]
+    pub fn get_available_screens_with_timeout(&self, timeout_ms: Option<u32>) -> Result<Vec<ScreenSource>, CaptureErrorCode> {
+        coded(|| {
+            log::warn!("getAvailableScreensWithTimeout is deprecated - use getAvailableScreens({{ timeoutMs }})");
+            self.expire_cached_content();
+            Self::screen_sources(
+                &self.current_content,
+                timeout_ms
+                    .map(|ms| std::time::Duration::from_millis(ms as u64))
+                    .unwrap_or(screencapturekit::bindings::SHAREABLE_CONTENT_TIMEOUT),
+                false,
+                &Default::default(),
+            )
+        })
+    }
+
+    /// Abort the `getAvailableScreens`, `saveReplay` or `getSourceThumbnail` call started with
+    /// `operationId` on this thread; it rejects with code Cancelled. False when no such call is running.
+    /// Worker threads each have their own operation ids.
+    /// Stopping a recording can't be cancelled, as that would leave its file unplayable.
+    #[napi]
+    pub fn cancel(&self, operation_id: String) -> bool {
+        screencapturekit::cancel::cancel(&operation_id)
+    }
+
+    /// A stream manager reading this recorder's live session, for `getCaptureStats`
+    #[napi]
+    pub fn get_stream_manager(&self) -> RealStreamManager {
+        RealStreamManager { slot: self.stream_manager.clone() }
+    }
+
+    #[napi]
+    pub fn get_available_audio_devices(&self) -> Result<Vec<AudioDevice>, CaptureErrorCode> {
+        coded(|| {
+            log::info!("Getting available audio devices via AVFoundation");
+            screencapturekit::AudioManager::get_available_audio_devices()
+        })
+    }
+
+    /// Do the slow parts of `startRecording` ahead of time: permission check, shareable content
+    /// enumeration and encoder load. A `startRecording` within the content cache TTL reuses the fetched content.
+    #[napi]
+    pub fn warm_up(&mut self) -> Result<WarmUpResult, CaptureErrorCode> {
+        coded(|| {
+            log::info!("Warming up capture engine");
+            let started = std::time::Instant::now();
+        
+            let permission_granted = unsafe { screencapturekit::bindings::ScreenCaptureKitHelpers::check_screen_recording_permission() };
+            if !permission_granted {
+                log::warn!("Screen recording permission not granted - recording will fail until it is");
+            }
+        
+            let content = screencapturekit::content::ShareableContent::new_with_real_data()?;
+            let displays = content.get_displays()?.len() as u32;
+            let windows = content.get_windows()?.len() as u32;
+            *self.lock_content()? = Some(content);
+        
+            let encoder_ready = screencapturekit::encoder::warm_up_video_encoder(
+                screencapturekit::limits::DEFAULT_WIDTH,
+                screencapturekit::limits::DEFAULT_HEIGHT,
+            );
+        
+            let duration_ms = started.elapsed().as_secs_f64() * 1000.0;
+            log::info!("Capture engine warm ({:.0}ms): {} displays, {} windows, encoder {}",
+                duration_ms, displays, windows, if encoder_ready { "ready" } else { "unavailable" });
+        
+            Ok(WarmUpResult {
+                permission_granted,
+                displays,
+                windows,
+                encoder_ready,
+                duration_ms,
+            })
+        })
+    }
+
+    /// Stream downscaled JPEG frames of "display:<id>" or "window:<id>" to `callback` without
+    /// recording anything, e.g. for a preview before recording. Replaces a running preview.
+    #[napi(ts_args_type = "screenId: string, options: PreviewOptions | undefined | null, callback: (frame: PreviewFrame) => void")]
+    pub fn start_preview(&mut self, screen_id: String, options: Option<PreviewOptions>, callback: JsFunction) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            self.stop_preview();
+            let tsfn: ThreadsafeFunction<(screencapturekit::screenshot::Thumbnail, u64), ErrorStrategy::Fatal> = callback
+                .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<(screencapturekit::screenshot::Thumbnail, u64)>| {
+                    let (frame, taken_at) = ctx.value;
+                    Ok(vec![PreviewFrame {
+                        data: frame.data.into(),
+                        width: frame.width,
+                        height: frame.height,
+                        timestamp_ms: taken_at as f64,
+                    }])
+                })?;
+            let sink: screencapturekit::preview::PreviewSink = std::sync::Arc::new(move |frame, taken_at| {
+                tsfn.call((frame, taken_at), ThreadsafeFunctionCallMode::NonBlocking);
+            });
+            let fps = options.as_ref().and_then(|o| o.fps).unwrap_or(screencapturekit::preview::DEFAULT_PREVIEW_FPS);
+            let max_width = options.as_ref().and_then(|o| o.max_width).unwrap_or(screencapturekit::preview::DEFAULT_PREVIEW_WIDTH);
+            self.preview = Some(screencapturekit::preview::PreviewStream::start(&screen_id, fps, max_width.max(1), sink)?);
+            Ok(())
+        })
+    }
+    
+    /// False when no preview was running
+    #[napi]
+    pub fn stop_preview(&mut self) -> bool {
+        match self.preview.take() {
+            Some(mut preview) => {
+                preview.stop();
+                true
+            }
+            None => false,
+        }
+    }
+    
+    /// `startRecording` for a display handle; fails when the display was disconnected
+    #[napi(ts_return_type = "Promise<RecordingSession>")]
+    pub fn start_recording_display(&mut self, handle: &DisplayHandle, config: RecordingConfiguration) -> Result<AsyncTask<StartRecordingTask>, CaptureErrorCode> {
+        coded(|| {
+            if !handle.inner.is_available() {
+                return Err(capture_error(CaptureErrorCode::SourceNotFound, format!("Display {} is no longer available", handle.inner.id())));
+            }
+            self.start_recording(Either::A(handle.inner.screen_id()), config)
+        })
+    }
+    
+    /// `startRecording` for a window handle; fails when the window was closed
+    #[napi(ts_return_type = "Promise<RecordingSession>")]
+    pub fn start_recording_window(&mut self, handle: &WindowHandle, config: RecordingConfiguration) -> Result<AsyncTask<StartRecordingTask>, CaptureErrorCode> {
+        coded(|| {
+            if !handle.inner.is_available() {
+                return Err(capture_error(CaptureErrorCode::SourceNotFound, format!("Window {} is no longer available", handle.inner.id())));
+            }
+            self.start_recording(Either::A(handle.inner.screen_id()), config)
+        })
+    }
+    
+    /// `screen_id` is "display:<id>", "window:<id>", "windows:<id>,<id>,...", "app:<bundleId>",
+    /// which records every window of that application, including ones it opens while recording,
+    /// or "region:<displayId>" together with `crop`. A list of "display:<id>" records those displays
+    /// at once, see `multi_display_mode`; stats then break down per display.
+    /// Resolves with the session once the stream is running.
+    #[napi(ts_return_type = "Promise<RecordingSession>")]
+    pub fn start_recording(
+        &mut self,
+        screen_id: Either<String, Vec<String>>,
+        mut config: RecordingConfiguration,
+    ) -> Result<AsyncTask<StartRecordingTask>> {
+        screencapturekit::presets::apply_preset(&mut config)?;
+        screencapturekit::permissions::guard_silent_for(&config)?;
+        let screen_ids = match screen_id {
+            Either::A(screen_id) => vec![screen_id],
+            Either::B(screen_ids) => screen_ids,
+        };
+        if screen_ids.is_empty() {
+            return Err(Error::new(Status::InvalidArg, "No screen_id given"));
+        }
+        self.expire_cached_content();
+        let listeners = self.listeners.lock().map(|listeners| listeners.clone()).unwrap_or_default();
+        
+        Ok(AsyncTask::new(StartRecordingTask {
+            stream_manager: self.stream_manager.clone(),
+            current_content: self.current_content.clone(),
+            auto_stopped_listener: self.auto_stopped_listener.clone(),
+            events: self.events.clone(),
+            listeners,
+            screen_ids,
+            config_echo: config.clone(),
+            config: Some(config),
+        }))
+    }
+
+    /// The body of `startRecording`, run off the JS thread while holding the recording slot
+    fn start_in_slot(
+        session_slot: &SessionSlot,
+        current_content: &ContentSlot,
+        auto_stopped_listener: &SharedListener<AutoStoppedEvent>,
+        events: &RecorderEvents,
+        listeners: &SessionListeners,
+        screen_ids: &[String],
+        config: RecordingConfiguration,
+    ) -> Result<RecordingStartResult> {
+        let mut slot = session_slot.lock()
+            .map_err(|_| capture_error(CaptureErrorCode::Unknown, "Recording state is poisoned"))?;
+        if slot.as_ref().map(|m| m.is_recording()).unwrap_or(false) {
+            return Err(capture_error(CaptureErrorCode::AlreadyRecording, "A recording is already in progress"));
+        }
+        let mut config = config;
+        if let Some(screen_id) = screen_ids.first() {
+            screencapturekit::output::resolve(&mut config, screen_id)?;
+        }
+        let mut current_content = current_content.lock()
+            .map_err(|_| capture_error(CaptureErrorCode::Unknown, "Content cache is poisoned"))?;
+        let disk_reserve = Self::disk_reserve_bytes(&config);
+        let (stream_manager, result) = match screen_ids {
+            [] => return Err(Error::new(Status::InvalidArg, "No screen_id given")),
+            [screen_id] => Self::start_session(&mut current_content, screen_id.clone(), config, listeners, None)?,
+            _ => Self::start_multi_display(&mut current_content, screen_ids, config, listeners)?,
+        };
+        drop(current_content);
+        *slot = Some(stream_manager);
+        drop(slot);
+        
+        Self::watch_disk_space(session_slot, auto_stopped_listener, events, &result, disk_reserve);
+        Ok(result)
+    }
+
+    /// Everything `startRecording` does once it owns the recording slot. `current_content` is the
+    /// cache to resolve the source against; it's cleared again once the filter holds its references.
+    fn start_session(
+        current_content: &mut Option<screencapturekit::content::ShareableContent>,
+        screen_id: String,
+        mut config: RecordingConfiguration,
+        listeners: &SessionListeners,
+        canvas: Option<screencapturekit::canvas::CanvasTarget>,
+    ) -> Result<(screencapturekit::content::RealStreamManager, RecordingStartResult)> {
+        log::info!("Starting ScreenCaptureKit recording with screen_id: {}", screen_id);
+        log::info!("Output path: {}", config.output_path);
+
+        // "region:<displayId>" is a display capture cropped to `crop`
+        let screen_id = match screen_id.strip_prefix("region:") {
+            Some(display_id) => {
+                if config.crop.is_none() {
+                    return Err(Error::new(Status::InvalidArg, "A region capture needs crop { x, y, width, height }"));
+                }
+                format!("display:{}", display_id)
+            }
+            None => screen_id,
+        };
+        if let Some(region) = config.crop.take() {
+            if config.crop_x.is_some() || config.crop_y.is_some() || config.crop_width.is_some() || config.crop_height.is_some() {
+                return Err(Error::new(Status::InvalidArg, "crop and crop_* can't be combined"));
+            }
+            config.crop_x = Some(region.x);
+            config.crop_y = Some(region.y);
+            config.crop_width = Some(region.width);
+            config.crop_height = Some(region.height);
+        }
+
+        // Apply global limits before anything touches the stream configuration
+        let mut warnings = screencapturekit::limits::clamp_configuration(&mut config);
+        let output_path = config.output_path.clone();
+        let mut width = config.width.unwrap_or(screencapturekit::limits::DEFAULT_WIDTH);
+        let mut height = config.height.unwrap_or(screencapturekit::limits::DEFAULT_HEIGHT);
+        let fps = config.fps.unwrap_or(screencapturekit::limits::DEFAULT_FPS);
+        let wav_sidecar_path = if config.write_wav_sidecar.unwrap_or(false) {
+            Some(screencapturekit::audio::wav::WavSidecar::path_for(&output_path))
+        } else {
+            None
+        };
+        let input_events_path = if config.record_input_events.unwrap_or(false) {
+            Some(screencapturekit::input_events::InputEventRecorder::path_for(&output_path))
+        } else {
+            None
+        };
+        let cursor_track_path = if config.record_cursor_metadata.unwrap_or(false) {
+            Some(screencapturekit::cursor_track::CursorTrack::path_for(&output_path))
+        } else {
+            None
+        };
+        let metadata_path = screencapturekit::metadata::RecordingMetadata::path_for(&output_path);
+        if let Some(reserve) = Self::disk_reserve_bytes(&config) {
+            if let Some(free) = screencapturekit::disk_watch::available_bytes(&output_path) {
+                if free < reserve {
+                    return Err(capture_error(
+                        CaptureErrorCode::DiskFull,
+                        format!("Not enough free disk space for {}: {} MB left", output_path, free / (1024 * 1024)),
+                    ));
+                }
+            }
+        }
+
+        if current_content.is_none() {
+            *current_content = Some(screencapturekit::content::ShareableContent::new_with_real_data()?);
+        }
+        let content = current_content.as_ref().unwrap();
+        
+        if let Some(warning) = content.mirror_warning(&screen_id) {
+            log::warn!("{}", warning);
+            warnings.push(warning);
+        }
+
+        let exclusions = screencapturekit::content::DisplayExclusions {
+            window_ids: config.exclude_window_ids.clone().unwrap_or_default(),
+            app_bundle_ids: config.exclude_app_bundle_ids.clone().unwrap_or_default(),
+            hide_menu_bar: !config.include_menu_bar.unwrap_or(true),
+            hide_dock: !config.include_dock.unwrap_or(true),
+            hide_desktop: !config.include_desktop.unwrap_or(true),
+            hide_self: !config.include_self.unwrap_or(false),
+        };
+        let window_ids = config.window_ids.clone().unwrap_or_default();
+
+        // Create real content filter based on screen_id using the FIXED segfault-safe method.
+        // Cached IDs go stale when windows close, so refresh once and retry before giving up.
+        let content_filter = match Self::create_real_content_filter_safe(content, &screen_id, &exclusions, &window_ids) {
+            Ok(filter) => filter,
+            Err(first_error) => {
+                log::warn!("Content filter creation failed ({}), refreshing shareable content and retrying", first_error);
+                let refreshed = screencapturekit::content::ShareableContent::new_with_real_data()?;
+                let retry = Self::create_real_content_filter_safe(&refreshed, &screen_id, &exclusions, &window_ids);
+                let previous = current_content.replace(refreshed);
+                
+                match retry {
+                    Ok(filter) => {
+                        log::info!("Content filter created after refresh");
+                        filter
+                    }
+                    Err(retry_error) => {
+                        let refreshed = current_content.as_ref().unwrap();
+                        return Err(match refreshed.source_disappeared(previous.as_ref(), &screen_id) {
+                            Some(source_error) => source_error.into(),
+                            None => retry_error,
+                        });
+                    }
+                }
+            }
+        };
+        
+        // A crop is only meaningful in display space
+        let mut crop_pixels = None;
+        match (config.crop_x, config.crop_y, config.crop_width, config.crop_height) {
+            (None, None, None, None) => {}
+            (Some(x), Some(y), Some(crop_width), Some(crop_height)) => {
+                let display_id = match content_filter.source() {
+                    Some(screencapturekit::cursor::CaptureSource::Display(display_id)) => display_id,
+                    _ => return Err(Error::new(Status::InvalidArg, "crop_* only applies to display captures")),
+                };
+                let display = current_content.as_ref()
+                    .and_then(|content| content.find_display_by_id(display_id))
+                    .ok_or_else(|| capture_error(CaptureErrorCode::SourceNotFound, format!("Display ID {} not found", display_id)))?;
+                let rect = display.snap_to_pixels(display.crop_rect(x, y, crop_width, crop_height)?);
+                config.crop_x = Some(rect.origin.x);
+                config.crop_y = Some(rect.origin.y);
+                config.crop_width = Some(rect.size.width);
+                config.crop_height = Some(rect.size.height);
+                crop_pixels = Some(display.pixel_size(&rect));
+                log::info!(
+                    "Capturing {}x{} at {},{} of display {}",
+                    rect.size.width, rect.size.height, rect.origin.x, rect.origin.y, display_id
+                );
+            }
+            _ => {
+                return Err(Error::new(
+                    Status::InvalidArg,
+                    "crop_x, crop_y, crop_width and crop_height must be given together",
+                ))
+            }
+        }
+        
+        // Without an explicit size a crop keeps its native resolution; `scale` and `max_output_width`
+        // size the stream itself, so a 5K display recorded at 1080p is captured at 1080p
+        let wants_scaling = config.scale.is_some() || config.max_output_width.is_some();
+        let base_size = if config.width.is_some() || config.height.is_some() {
+            if wants_scaling { Some((width, height)) } else { None }
+        } else if crop_pixels.is_some() {
+            crop_pixels
+        } else if wants_scaling {
+            let native = match (content_filter.source(), current_content.as_ref()) {
+                (Some(source), Some(content)) => content.native_pixel_size(source),
+                _ => None,
+            };
+            Some(native.unwrap_or((width, height)))
+        } else {
+            None
+        };
+        if let Some((base_width, base_height)) = base_size {
+            let (scaled_width, scaled_height) = screencapturekit::limits::scale_dimensions(
+                base_width,
+                base_height,
+                config.scale,
+                config.max_output_width,
+            )?;
+            config.width = Some(scaled_width);
+            config.height = Some(scaled_height);
+            warnings.extend(screencapturekit::limits::clamp_configuration(&mut config));
+            width = config.width.unwrap_or(width);
+            height = config.height.unwrap_or(height);
+            log::info!("Output size {}x{} (source {}x{})", width, height, base_width, base_height);
+        }
+        
+        // The crop is cut from the full display, so it needs a display and the whole of it
+        let zoom = match &config.zoom_follow {
+            Some(options) => {
+                let display_id = match content_filter.source() {
+                    Some(screencapturekit::cursor::CaptureSource::Display(display_id)) => display_id,
+                    _ => return Err(Error::new(Status::InvalidArg, "zoom_follow only applies to display captures")),
+                };
+                if crop_pixels.is_some() {
+                    return Err(Error::new(Status::InvalidArg, "zoom_follow can't be combined with crop"));
+                }
+                if config.follow_active_window.unwrap_or(false) || config.record_cursor_metadata.unwrap_or(false) || config.camera_pip.is_some() {
+                    return Err(Error::new(
+                        Status::InvalidArg,
+                        "zoom_follow can't be combined with follow_active_window, record_cursor_metadata or camera_pip",
+                    ));
+                }
+                Some(screencapturekit::zoom::ZoomFollow::new(
+                    display_id,
+                    options.width,
+                    options.height,
+                    options.smoothing,
+                    width,
+                    height,
+                )?)
+            }
+            None => None,
+        };
+        
+        // Every fallback either fails a strict start or becomes a warning
+        let mut fallbacks: Vec<String> = current_content.as_ref()
+            .map(|content| content.fallbacks().to_vec())
+            .unwrap_or_default();
+        fallbacks.extend(content_filter.fallback().map(|f| f.to_string()));
+        if config.strict.unwrap_or(false) && !fallbacks.is_empty() {
+            return Err(capture_error(
+                CaptureErrorCode::InvalidConfiguration,
+                format!("StrictMode: capture would be degraded: {}", fallbacks.join("; ")),
+            ));
+        }
+        for fallback in fallbacks {
+            log::warn!("Fallback: {}", fallback);
+            warnings.push(format!("Fallback: {}", fallback));
+        }
+        
+        // Create real stream manager and start recording
+        let mut stream_manager = screencapturekit::content::RealStreamManager::new();
+        stream_manager.set_canvas(canvas);
+        stream_manager.set_zoom(zoom);
+        stream_manager.start_recording(content_filter, config)?;
+        
+        let session_id = stream_manager.session().map(|s| s.id.clone()).unwrap_or_default();
+        let started_at = stream_manager.session().map(|s| s.started_at as f64).unwrap_or_default();
+        
+        // Listeners registered before the recording started attach now
+        listeners.attach(&mut stream_manager)?;
+        
+        // The filter holds its own references; windows opened from here on need a fresh enumeration
+        *current_content = None;
+        
+        log::info!("Real ScreenCaptureKit recording started (segfault-safe)");
+        Ok((stream_manager, RecordingStartResult {
+            session_id,
+            started_at,
+            output_path,
+            width,
+            height,
+            fps,
+            warnings,
+            wav_sidecar_path,
+            input_events_path,
+            cursor_track_path,
+            metadata_path,
+            output_paths: None,
+        }))
+    }
+
+    /// Start one stream per display; the others are linked to the first display's stream manager,
+    /// so stopping, pausing and stats go through it. Any display failing to start stops the rest.
+    fn start_multi_display(
+        current_content: &mut Option<screencapturekit::content::ShareableContent>,
+        screen_ids: &[String],
+        config: RecordingConfiguration,
+        listeners: &SessionListeners,
+    ) -> Result<(screencapturekit::content::RealStreamManager, RecordingStartResult)> {
+        let mut display_ids: Vec<u32> = Vec::new();
+        for screen_id in screen_ids {
+            match screen_id.strip_prefix("display:").and_then(|id| id.parse().ok()) {
+                Some(display_id) if !display_ids.contains(&display_id) => display_ids.push(display_id),
+                Some(_) => return Err(Error::new(Status::InvalidArg, format!("{} is listed twice", screen_id))),
+                None => {
+                    return Err(Error::new(
+                        Status::InvalidArg,
+                        format!("Only displays can be recorded together, got \"{}\"", screen_id),
+                    ))
+                }
+            }
+        }
+        if config.replay_buffer_seconds.is_some() {
+            return Err(Error::new(Status::InvalidArg, "replay_buffer_seconds records a single display"));
+        }
+        if config.follow_active_window.unwrap_or(false) {
+            return Err(Error::new(Status::InvalidArg, "follow_active_window records a single source"));
+        }
+        if config.zoom_follow.is_some() {
+            return Err(Error::new(Status::InvalidArg, "zoom_follow records a single display"));
+        }
+        if !config.capture_video.unwrap_or(true) {
+            return Err(Error::new(Status::InvalidArg, "capture_video false records a single display"));
+        }
+        if config.crop.is_some() || config.crop_x.is_some() || config.crop_y.is_some() || config.crop_width.is_some() || config.crop_height.is_some() {
+            return Err(Error::new(Status::InvalidArg, "crop doesn't apply when recording several displays"));
+        }
+        let composite = match config.multi_display_mode.as_deref() {
+            None | Some("separate") => false,
+            Some("composite") => true,
+            Some(other) => {
+                return Err(Error::new(
+                    Status::InvalidArg,
+                    format!("Unknown multi_display_mode \"{}\" (expected \"separate\" or \"composite\")", other),
+                ))
+            }
+        };
+        if composite && config.record_cursor_metadata.unwrap_or(false) {
+            return Err(Error::new(Status::InvalidArg, "record_cursor_metadata needs multi_display_mode \"separate\""));
+        }
+        log::info!("Recording {} displays ({})", display_ids.len(), if composite { "composite" } else { "separate" });
+
+        // Everything tied to the audio or the session - audio, WAV, input events - stays with the first display
+        let secondary = |config: &RecordingConfiguration| {
+            let mut config = config.clone();
+            config.capture_audio = Some(false);
+            config.audio_device_id = None;
+            config.system_audio_backend = None;
+            config.write_wav_sidecar = None;
+            config.record_input_events = None;
+            config.camera_pip = None;
+            config
+        };
+        let canvas_width = config.width.unwrap_or(screencapturekit::limits::DEFAULT_WIDTH) & !1;
+        let canvas_height = config.height.unwrap_or(screencapturekit::limits::DEFAULT_HEIGHT) & !1;
+        let mut streams = Vec::new();
+        if composite {
+            // Each display is captured at its slot size, so ScreenCaptureKit does the scaling
+            let canvas = screencapturekit::canvas::Canvas::new(canvas_width, canvas_height);
+            let slots = screencapturekit::canvas::layout_displays(&display_ids, canvas_width, canvas_height);
+            for (index, slot) in slots.into_iter().enumerate() {
+                let mut stream_config = if index == 0 { config.clone() } else { secondary(&config) };
+                stream_config.width = Some(slot.width);
+                stream_config.height = Some(slot.height);
+                stream_config.scale = None;
+                stream_config.max_output_width = None;
+                let target = screencapturekit::canvas::CanvasTarget::new(canvas.clone(), slot, index == 0);
+                streams.push((stream_config, Some(target)));
+            }
+        } else {
+            for (index, display_id) in display_ids.iter().enumerate() {
+                let mut stream_config = if index == 0 { config.clone() } else { secondary(&config) };
+                stream_config.output_path = Self::display_output_path(&config.output_path, *display_id);
+                streams.push((stream_config, None));
+            }
+        }
+
+        let no_listeners = SessionListeners::default();
+        let mut primary: Option<(screencapturekit::content::RealStreamManager, RecordingStartResult)> = None;
+        let mut output_paths = Vec::new();
+        for ((stream_config, canvas), screen_id) in streams.into_iter().zip(screen_ids) {
+            let stream_listeners = if primary.is_none() { listeners } else { &no_listeners };
+            match Self::start_session(current_content, screen_id.clone(), stream_config, stream_listeners, canvas) {
+                Ok((stream_manager, result)) => match &mut primary {
+                    None => {
+                        output_paths.push(result.output_path.clone());
+                        primary = Some((stream_manager, result));
+                    }
+                    Some((primary_manager, primary_result)) => {
+                        if !composite {
+                            output_paths.push(result.output_path.clone());
+                        }
+                        primary_result.warnings.extend(result.warnings.into_iter().map(|w| format!("{}: {}", screen_id, w)));
+                        primary_manager.link(stream_manager);
+                    }
+                },
+                Err(e) => {
+                    if let Some((mut stream_manager, _)) = primary.take() {
+                        let _ = stream_manager.stop_recording();
+                    }
+                    let (code, message) = screencapturekit::errors::describe(&e);
+                    return Err(capture_error(code, format!("{}: {}", screen_id, message)));
+                }
+            }
+        }
+
+        let (stream_manager, mut result) = primary.ok_or_else(|| Error::new(Status::InvalidArg, "No displays given"))?;
+        if composite {
+            result.width = canvas_width;
+            result.height = canvas_height;
+        }
+        result.output_paths = Some(output_paths);
+        Ok((stream_manager, result))
+    }
+
+    /// `<stem>-display-<id>.<ext>` next to `output_path`
+    fn display_output_path(output_path: &str, display_id: u32) -> String {
+        let path = std::path::Path::new(output_path);
+        let stem = path.file_stem().map(|s| s.to_string_lossy().into_owned()).unwrap_or_else(|| "recording".to_string());
+        let file_name = match path.extension() {
+            Some(extension) => format!("{}-display-{}.{}", stem, display_id, extension.to_string_lossy()),
+            None => format!("{}-display-{}", stem, display_id),
+        };
+        path.with_file_name(file_name).to_string_lossy().into_owned()
+    }
+
+    /// Resolves with the output path once the files are finalized
+    #[napi]
+    pub fn stop_recording(&mut self) -> Result<AsyncTask<StopRecordingTask>, CaptureErrorCode> {
+        coded(|| {
+            log::info!("Stopping ScreenCaptureKit recording");
+        
+            let stream_manager = self.stream_manager.lock().ok().and_then(|mut slot| slot.take())
+                .ok_or_else(|| capture_error(CaptureErrorCode::NotRecording, "No active recording session"))?;
+            Ok(AsyncTask::new(StopRecordingTask {
+                stream_manager: Some(stream_manager),
+                events: self.events.clone(),
+            }))
+        })
+    }
 
-#[napi]
-impl ContentManager {
-    #[napi(constructor)]
-    pub fn new() -> Result<Self> {
-        Ok(Self)
+    /// Arm a native timer that starts the recording at `startAt` and stops it at `stopAt`. It fires on
+    /// time even while the renderer is busy; `onScheduledStart` and `onScheduledStop` report each step.
+    /// A recording started by hand is left alone, and stopping the scheduled one early disarms the stop.
+    #[napi]
+    pub fn schedule_recording(&mut self, screen_id: String, mut config: RecordingConfiguration, options: ScheduleOptions) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            screencapturekit::presets::apply_preset(&mut config)?;
+            screencapturekit::permissions::guard_silent_for(&config)?;
+            if self.schedule.as_ref().map(|schedule| schedule.is_pending()).unwrap_or(false) {
+                return Err(capture_error(CaptureErrorCode::AlreadyRecording, "A recording is already scheduled"));
+            }
+            let to_time = |ms: f64| std::time::UNIX_EPOCH + std::time::Duration::from_millis(ms.max(0.0) as u64);
+            let start_at = options.start_at.map(to_time).unwrap_or_else(std::time::SystemTime::now);
+            let stop_at = options.stop_at.map(to_time);
+        
+            let output_path = config.output_path.clone();
+            let session_id = std::sync::Arc::new(std::sync::Mutex::new(None::<String>));
+            let on_start: screencapturekit::schedule::ScheduledStart = {
+                let slot = self.stream_manager.clone();
+                let listeners = self.listeners.clone();
+                let listener = self.scheduled_start_listener.clone();
+                let auto_stopped_listener = self.auto_stopped_listener.clone();
+                let events = self.events.clone();
+                let session_id = session_id.clone();
+                let output_path = output_path.clone();
+                Box::new(move || {
+                    let listeners = listeners.lock().map(|listeners| listeners.clone()).unwrap_or_default();
+                    let disk_reserve = Self::disk_reserve_bytes(&config);
+                    events.preparing();
+                    let started = match slot.lock() {
+                        Ok(slot) if slot.as_ref().map(|m| m.is_recording()).unwrap_or(false) => {
+                            Err(capture_error(CaptureErrorCode::AlreadyRecording, "A recording is already in progress"))
+                        }
+                        Ok(mut slot) => Self::start_session(&mut None, screen_id, config, &listeners, None).map(|(stream_manager, result)| {
+                            *slot = Some(stream_manager);
+                            result
+                        }),
+                        Err(_) => Err(capture_error(CaptureErrorCode::Unknown, "Recording state is poisoned")),
+                    };
+                    let event = match started {
+                        Ok(result) => {
+                            events.started(&slot, &result);
+                            Self::watch_disk_space(&slot, &auto_stopped_listener, &events, &result, disk_reserve);
+                            if let Ok(mut session_id) = session_id.lock() {
+                                *session_id = Some(result.session_id.clone());
+                            }
+                            ScheduledRecordingEvent { output_path, session_id: Some(result.session_id), error: None }
+                        }
+                        Err(e) => {
+                            log::error!("Scheduled recording failed to start: {}", e.reason);
+                            events.start_failed(&e);
+                            events.emit(RecorderEvent::failed(None, &e));
+                            ScheduledRecordingEvent { output_path, session_id: None, error: Some(screencapturekit::errors::describe(&e).1) }
+                        }
+                    };
+                    let ok = event.error.is_none();
+                    emit_shared(&listener, event);
+                    ok
+                })
+            };
+            let on_stop: screencapturekit::schedule::ScheduledStop = {
+                let slot = self.stream_manager.clone();
+                let listener = self.scheduled_stop_listener.clone();
+                let events = self.events.clone();
+                Box::new(move || {
+                    let id = session_id.lock().ok().and_then(|id| id.clone());
+                    // Only stop the session this schedule started
+                    let stream_manager = id.as_deref().and_then(|id| Self::take_session(&slot, id));
+                    let mut stream_manager = match stream_manager {
+                        Some(stream_manager) => stream_manager,
+                        None => {
+                            log::info!("Scheduled recording was already stopped");
+                            return;
+                        }
+                    };
+                    let event = match events.stop(&mut stream_manager) {
+                        Ok(output_path) => ScheduledRecordingEvent { output_path, session_id: id, error: None },
+                        Err(e) => ScheduledRecordingEvent { output_path, session_id: id, error: Some(screencapturekit::errors::describe(&e).1) },
+                    };
+                    emit_shared(&listener, event);
+                })
+            };
+        
+            self.schedule = Some(screencapturekit::schedule::RecordingSchedule::arm(start_at, stop_at, on_start, on_stop)?);
+            Ok(())
+        })
     }
     
+    /// Disarm a pending scheduled start or stop; a recording it already started keeps running.
+    /// Returns false when nothing was scheduled.
     #[napi]
-    pub fn get_shareable_content(&self) -> Result<ShareableContent> {
-        let inner = screencapturekit::content::ShareableContent::new_with_real_data()?;
-        Ok(ShareableContent { inner })
+    pub fn cancel_scheduled_recording(&mut self) -> bool {
+        match self.schedule.take() {
+            Some(mut schedule) => {
+                let pending = schedule.is_pending();
+                schedule.cancel();
+                pending
+            }
+            None => false,
+        }
     }
     
     #[napi]
-    pub fn get_shareable_content_sync(&self) -> Result<ShareableContent> {
-        let inner = screencapturekit::content::ShareableContent::new_with_real_data()?;
-        Ok(ShareableContent { inner })
+    pub fn has_scheduled_recording(&self) -> bool {
+        self.schedule.as_ref().map(|schedule| schedule.is_pending()).unwrap_or(false)
+    }
+    
+    /// Called when a scheduled recording starts, or with `error` when it couldn't
+    #[napi(ts_args_type = "callback: (event: ScheduledRecordingEvent) => void")]
+    pub fn on_scheduled_start(&mut self, callback: JsFunction) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            let tsfn: ThreadsafeFunction<ScheduledRecordingEvent, ErrorStrategy::Fatal> = callback
+                .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<ScheduledRecordingEvent>| Ok(vec![ctx.value]))?;
+            if let Ok(mut listener) = self.scheduled_start_listener.lock() {
+                *listener = Some(tsfn);
+            }
+            Ok(())
+        })
+    }
+    
+    /// Called once the scheduled stop has finalized the recording
+    #[napi(ts_args_type = "callback: (event: ScheduledRecordingEvent) => void")]
+    pub fn on_scheduled_stop(&mut self, callback: JsFunction) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            let tsfn: ThreadsafeFunction<ScheduledRecordingEvent, ErrorStrategy::Fatal> = callback
+                .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<ScheduledRecordingEvent>| Ok(vec![ctx.value]))?;
+            if let Ok(mut listener) = self.scheduled_stop_listener.lock() {
+                *listener = Some(tsfn);
+            }
+            Ok(())
+        })
+    }
+    
+    /// Called after the recorder stopped and finalized a recording on its own, e.g. with
+    /// reason "disk_full" when the output volume ran low on space
+    #[napi(ts_args_type = "callback: (event: AutoStoppedEvent) => void")]
+    pub fn on_auto_stopped(&mut self, callback: JsFunction) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            let tsfn: ThreadsafeFunction<AutoStoppedEvent, ErrorStrategy::Fatal> = callback
+                .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<AutoStoppedEvent>| Ok(vec![ctx.value]))?;
+            if let Ok(mut listener) = self.auto_stopped_listener.lock() {
+                *listener = Some(tsfn);
+            }
+            Ok(())
+        })
     }
-}
 
-// Export RealContentFilter as NAPI class
-#[napi]
-pub struct RealContentFilter {
-    inner: screencapturekit::content::RealContentFilter,
-}
+    /// Push recorder events instead of polling `getStatus`: "started", "warning", "error", "stopped",
+    /// and "stats" every `statsIntervalMs` while recording (default 1000, 0 for none). Replaces the
+    /// previous callback.
+    #[napi(ts_args_type = "callback: (event: RecorderEvent) => void, statsIntervalMs?: number | undefined | null")]
+    pub fn on_event(&mut self, callback: JsFunction, stats_interval_ms: Option<u32>) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            let tsfn: ThreadsafeFunction<RecorderEvent, ErrorStrategy::Fatal> = callback
+                .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<RecorderEvent>| Ok(vec![ctx.value]))?;
+            self.events.stats_interval_ms.store(
+                stats_interval_ms.unwrap_or(DEFAULT_STATS_INTERVAL_MS),
+                std::sync::atomic::Ordering::Relaxed,
+            );
+            if let Ok(mut listener) = self.events.listener.lock() {
+                *listener = Some(tsfn);
+            }
+            Ok(())
+        })
+    }
 
-#[napi]
-impl RealContentFilter {
-    #[napi(constructor)]
-    pub fn new() -> Result<Self> {
-        // Create a default filter - this would need proper initialization in real usage
-        let content = screencapturekit::content::ShareableContent::new_with_real_data()?;
-        let inner = screencapturekit::content::RealContentFilter::new_with_display(&content, 1)?;
-        Ok(Self { inner })
+    /// Called on every state change with the new and previous state. Replaces the previous callback.
+    #[napi(ts_args_type = "callback: (event: StateChangedEvent) => void")]
+    pub fn on_state_changed(&mut self, callback: JsFunction) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            let tsfn: ThreadsafeFunction<StateChangedEvent, ErrorStrategy::Fatal> = callback
+                .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<StateChangedEvent>| Ok(vec![ctx.value]))?;
+            if let Ok(mut listener) = self.events.state_listener.lock() {
+                *listener = Some(tsfn);
+            }
+            Ok(())
+        })
     }
-    
+
+    /// Called while a recording stops, as each phase of writing out its files begins, so a long
+    /// stop can show progress. Replaces the previous callback.
+    #[napi(ts_args_type = "callback: (event: FinalizeProgressEvent) => void")]
+    pub fn on_finalize_progress(&mut self, callback: JsFunction) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            let tsfn: ThreadsafeFunction<FinalizeProgressEvent, ErrorStrategy::Fatal> = callback
+                .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<FinalizeProgressEvent>| Ok(vec![ctx.value]))?;
+            if let Ok(mut listener) = self.events.finalize_listener.lock() {
+                *listener = Some(tsfn);
+            }
+            Ok(())
+        })
+    }
+
+    /// idle, preparing, recording, paused, stopping, then finalized or failed
     #[napi]
-    pub fn init_with_display(&mut self, display: DisplayInfo) -> Result<()> {
-        // This would properly initialize with the given display
-        println!("🎯 Initializing content filter with display: {}", display.name);
-        Ok(())
+    pub fn get_state(&self) -> RecorderState {
+        self.events.state()
     }
-    
+
     #[napi]
-    pub fn is_valid(&self) -> bool {
-        self.inner.is_valid()
+    pub fn is_recording(&self) -> bool {
+        self.with_session(|m| Ok(m.is_recording())).unwrap_or(false)
     }
-}
 
-// Export RealStreamManager as NAPI class
-#[napi]
-pub struct RealStreamManager {
-    inner: screencapturekit::content::RealStreamManager,
-}
+    /// Tag the active recording; tags land in the session journal, library index and MP4 keywords
+    #[napi]
+    pub fn add_tag(&mut self, tag: String) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            self.with_session(|stream_manager| stream_manager.add_tag(&tag))
+        })
+    }
 
-#[napi]
-impl RealStreamManager {
-    #[napi(constructor)]
-    pub fn new() -> Result<Self> {
-        let inner = screencapturekit::content::RealStreamManager::new();
-        Ok(Self { inner })
+    /// Mark the current moment of the active recording. Markers become MP4 chapters and are listed
+    /// in `<output>.markers.json` once the recording stops.
+    #[napi]
+    pub fn add_marker(&mut self, label: String) -> Result<RecordingMarker, CaptureErrorCode> {
+        coded(|| {
+            let marker = self.with_session(|stream_manager| stream_manager.add_marker(&label))?;
+            Ok(RecordingMarker {
+                label: marker.label,
+                time: marker.time,
+                created_at: marker.created_at as f64,
+            })
+        })
     }
-    
+
+    /// Hide or show the cursor in the recorded frames mid-recording. The cursor stays visible and usable on screen.
     #[napi]
-    pub fn initialize_stream(
-        &mut self,
-        _content_filter: &RealContentFilter,
-        stream_config: String,
-        output_path: String,
-    ) -> Result<()> {
-        println!("🎬 Initializing stream with config: {}", stream_config);
-        println!("📁 Output path: {}", output_path);
-        Ok(())
+    pub fn set_cursor_hidden_in_output(&self, hidden: bool) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            self.with_session(|stream_manager| stream_manager.set_cursor_hidden_in_output(hidden))
+        })
+    }
+
+    /// Write the last `replayBufferSeconds` of a replay-mode recording to `path` as one MP4, while
+    /// the buffer keeps capturing. Pass `operationId` to be able to `cancel` the export.
+    #[napi(ts_return_type = "Promise<ReplayClip>")]
+    pub fn save_replay(&self, path: String, operation_id: Option<String>) -> Result<AsyncTask<SaveReplayTask>, CaptureErrorCode> {
+        coded(|| {
+            let operation = screencapturekit::cancel::Operation::begin(operation_id)?;
+            let snapshot = self.with_session(|stream_manager| stream_manager.snapshot_replay())?;
+            Ok(AsyncTask::new(SaveReplayTask { snapshot: Some(snapshot), path, operation }))
+        })
     }
     
+    /// Suspend video and audio together until `resumeRecording`. The file has no gap: the
+    /// recording continues from where it was paused. Does nothing when already paused.
     #[napi]
-    pub fn start_capture(&mut self) -> Result<()> {
-        println!("▶️ Starting capture");
-        Ok(())
+    pub fn pause_recording(&self) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            self.with_session(|stream_manager| stream_manager.pause())?;
+            self.events.paused(true);
+            Ok(())
+        })
     }
     
+    /// Does nothing when not paused
     #[napi]
-    pub fn stop_capture(&mut self) -> Result<()> {
-        println!("⏹️ Stopping capture");
-        Ok(())
+    pub fn resume_recording(&self) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            self.with_session(|stream_manager| stream_manager.resume())?;
+            self.events.paused(false);
+            Ok(())
+        })
     }
     
-    #[napi]
-    pub fn get_capture_stats(&self) -> String {
-        // This method should be called on an active stream manager instance
-        // For now, return empty stats indicating no active recording
-        serde_json::json!({
-            "videoFrames": 0,
-            "audioSamples": 0,
-            "duration": 0.0,
-            "outputPath": null,
-            "isRecording": false,
-            "error": "No active recording session"
-        }).to_string()
+    fn live_stats(&self) -> Option<screencapturekit::content::CaptureStats> {
+        self.with_session(|m| Ok(m.capture_stats())).ok().flatten()
     }
-}
 
-// Export pixel format constants
-#[napi]
-pub const K_CV_PIXEL_FORMAT_TYPE_32_BGRA: u32 = 1111970369; // 'BGRA'
-
-#[napi]
-pub const K_CG_COLOR_SPACE_SRGB: u32 = 1;
-
-#[napi]
-pub const K_CG_COLOR_SPACE_DISPLAY_P3: u32 = 2;
-
-// Export constants with the exact names expected by test scripts
-#[napi]
-pub const kCVPixelFormatType_32BGRA: u32 = 1111970369; // 'BGRA'
-
-#[napi]
-pub const kCGColorSpaceSRGB: u32 = 1;
+    /// File the active recording writes to; null when not recording
+    #[napi(getter)]
+    pub fn output_path(&self) -> Option<String> {
+        self.with_session(|m| Ok(m.session().map(|s| s.output_path.clone()))).ok().flatten()
+    }
 
-// Export DisplayInfo as NAPI object
-#[napi(object)]
-pub struct DisplayInfo {
-    pub id: u32,
-    pub name: String,
-    pub width: u32,
-    pub height: u32,
-}
+    /// Recorded time of the active recording, pauses excluded; 0 when not recording
+    #[napi(getter)]
+    pub fn elapsed_seconds(&self) -> f64 {
+        self.live_stats().map(|s| s.duration_ms as f64 / 1000.0).unwrap_or(0.0)
+    }
 
-// Export WindowInfo as NAPI object  
-#[napi(object)]
-pub struct WindowInfo {
-    pub id: u32,
-    pub title: String,
-    pub width: u32,
-    pub height: u32,
-}
+    #[napi(getter)]
+    pub fn current_fps(&self) -> f64 {
+        self.live_stats().map(|s| s.current_fps).unwrap_or(0.0)
+    }
 
-// Export ShareableContent as NAPI class - FIXED to remove segfault methods
-#[napi]
-pub struct ShareableContent {
-    inner: screencapturekit::ShareableContent,
-}
+    /// Video frames captured so far, across every display of the recording
+    #[napi(getter)]
+    pub fn frame_count(&self) -> f64 {
+        self.live_stats().map(|s| s.video_frames as f64).unwrap_or(0.0)
+    }
 
-#[napi]
-impl ShareableContent {
-    #[napi(constructor)]
-    pub fn new() -> Result<Self> {
-        let inner = screencapturekit::ShareableContent::new_with_real_data()?;
-        Ok(Self { inner })
+    /// Whether the active recording is paused; a property now, where it used to be an `isPaused()` method
+    #[napi(getter)]
+    pub fn is_paused(&self) -> bool {
+        self.with_session(|m| Ok(m.is_paused())).unwrap_or(false)
     }
     
+    /// Total time the current recording has spent paused, including a pause in progress
     #[napi]
-    pub fn get_displays(&self) -> Result<Vec<DisplayInfo>> {
-        let displays = self.inner.get_displays()?;
-        Ok(displays.into_iter().map(|d| DisplayInfo {
-            id: d.id,
-            name: d.name,
-            width: d.width,
-            height: d.height,
-        }).collect())
+    pub fn get_paused_duration_ms(&self) -> f64 {
+        self.with_session(|m| Ok(m.paused_duration().as_secs_f64() * 1000.0)).unwrap_or(0.0)
     }
     
+    /// Replace microphone audio with silence mid-recording, or restore it.
+    /// The mic track keeps running so the timeline stays continuous.
     #[napi]
-    pub fn get_windows(&self) -> Result<Vec<WindowInfo>> {
-        let windows = self.inner.get_windows()?;
-        Ok(windows.into_iter().map(|w| WindowInfo {
-            id: w.id,
-            title: w.title,
-            width: w.width,
-            height: w.height,
-        }).collect())
+    pub fn set_microphone_muted(&self, muted: bool) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            if self.with_session(|stream_manager| stream_manager.set_microphone_muted(muted))? {
+                if let Some(listener) = &self.mic_mute_listener {
+                    listener.call(muted, ThreadsafeFunctionCallMode::NonBlocking);
+                }
+            }
+            Ok(())
+        })
     }
     
-    #[napi(getter)]
-    pub fn displays(&self) -> Result<Vec<DisplayInfo>> {
-        self.get_displays()
+    /// Called with the new state whenever `setMicrophoneMuted` changes it
+    #[napi(ts_args_type = "callback: (muted: boolean) => void")]
+    pub fn on_mic_mute_changed(&mut self, callback: JsFunction) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            let tsfn: ThreadsafeFunction<bool, ErrorStrategy::Fatal> = callback
+                .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<bool>| Ok(vec![ctx.value]))?;
+            self.mic_mute_listener = Some(tsfn);
+            Ok(())
+        })
     }
     
-    #[napi(getter)]
-    pub fn windows(&self) -> Result<Vec<WindowInfo>> {
-        self.get_windows()
+    /// Called with `true` when secure input starts pausing/blanking the recording and `false` when it resumes.
+    /// Only fires for recordings started with `secureInputProtection`.
+    #[napi(ts_args_type = "callback: (active: boolean) => void")]
+    pub fn on_secure_input_changed(&mut self, callback: JsFunction) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            let tsfn: ThreadsafeFunction<bool, ErrorStrategy::Fatal> = callback
+                .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<bool>| Ok(vec![ctx.value]))?;
+        
+            let sink = Self::secure_input_sink(&tsfn);
+            self.with_recording_session(|stream_manager| stream_manager.set_secure_input_listener(Some(sink.clone())))?;
+            self.update_listeners(|listeners| listeners.secure_input = Some(sink));
+            Ok(())
+        })
     }
     
-    // REMOVED: The problematic getScDisplayById and getScWindowById methods
-    // These methods caused segmentation faults and have been replaced with
-    // safer content filter creation methods in the internal implementation
+    fn secure_input_sink(listener: &ThreadsafeFunction<bool, ErrorStrategy::Fatal>) -> screencapturekit::secure_input::SecureInputListener {
+        let listener = listener.clone();
+        std::sync::Arc::new(move |active| {
+            listener.call(active, ThreadsafeFunctionCallMode::NonBlocking);
+        })
+    }
+    
+    /// Stream downmixed 16 kHz mono f32 PCM to JavaScript while recording, for live transcription.
+    /// Can be registered before `startRecording`; it attaches when the recording starts.
+    #[napi(ts_args_type = "onChunk: (chunk: AudioTapChunk) => void, options?: AudioTapOptions | undefined | null")]
+    pub fn start_audio_tap(&mut self, on_chunk: JsFunction, options: Option<AudioTapOptions>) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            let chunk_ms = options.and_then(|o| o.chunk_ms).unwrap_or(screencapturekit::audio_tap::DEFAULT_CHUNK_MS);
+        
+            let tsfn: ThreadsafeFunction<screencapturekit::audio_tap::PcmChunk, ErrorStrategy::Fatal> = on_chunk
+                .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<screencapturekit::audio_tap::PcmChunk>| {
+                    Ok(vec![AudioTapChunk {
+                        samples: Float32Array::new(ctx.value.samples),
+                        sample_rate: ctx.value.sample_rate,
+                        start_time: ctx.value.start_time,
+                    }])
+                })?;
+            let sink: screencapturekit::audio_tap::PcmChunkSink = std::sync::Arc::new(move |chunk| {
+                tsfn.call(chunk, ThreadsafeFunctionCallMode::NonBlocking);
+            });
+        
+            self.with_recording_session(|stream_manager| {
+                stream_manager.set_audio_tap(Some(screencapturekit::audio_tap::AudioTap::new(sink.clone(), chunk_ms)))
+            })?;
+            self.update_listeners(|listeners| listeners.audio_tap = Some((sink, chunk_ms)));
+        
+            log::info!("Live audio tap registered ({}ms chunks)", chunk_ms);
+            Ok(())
+        })
+    }
+    
+    /// Called when an audio device change (e.g. AirPods disconnecting) pauses the audio track, and again
+    /// once capture has resumed on the new route. The gap is filled with silence to keep audio in sync.
+    #[napi(ts_args_type = "callback: (event: AudioRouteChangeEvent) => void")]
+    pub fn on_audio_route_changed(&mut self, callback: JsFunction) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            let tsfn: ThreadsafeFunction<AudioRouteChangeEvent, ErrorStrategy::Fatal> = callback
+                .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<AudioRouteChangeEvent>| Ok(vec![ctx.value]))?;
+            let listener: screencapturekit::audio::AudioRouteHandler = std::sync::Arc::new(move |change: &screencapturekit::audio::AudioRouteChange, phase: screencapturekit::audio::AudioRoutePhase| {
+                let event = AudioRouteChangeEvent {
+                    direction: change.direction.as_str().to_string(),
+                    device_uid: change.device_uid.clone(),
+                    phase: phase.as_str().to_string(),
+                };
+                tsfn.call(event, ThreadsafeFunctionCallMode::NonBlocking);
+            });
+        
+            let _ = self.with_session(|stream_manager| {
+                stream_manager.set_audio_route_listener(Some(listener.clone()));
+                Ok(())
+            });
+            self.update_listeners(|listeners| listeners.audio_route = Some(listener));
+            Ok(())
+        })
+    }
+    
+    /// Called with the new display list when monitors connect or disconnect, or a resolution or
+    /// arrangement changes. Cached shareable content is refreshed automatically either way.
+    #[napi(ts_args_type = "callback: (displays: Array<DisplayInfo>) => void")]
+    pub fn on_displays_changed(&mut self, callback: JsFunction) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            let tsfn: ThreadsafeFunction<Vec<screencapturekit::content::DisplayInfo>, ErrorStrategy::Fatal> = callback
+                .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<Vec<screencapturekit::content::DisplayInfo>>| {
+                    Ok(vec![ctx.value.into_iter().map(DisplayInfo::from).collect::<Vec<_>>()])
+                })?;
+            if let Ok(mut listener) = self.displays_changed_listener.lock() {
+                *listener = Some(tsfn);
+            }
+            Ok(())
+        })
+    }
+    
+    /// Deliver every captured audio buffer as raw interleaved f32 at the capture rate, for custom
+    /// processing in JS. Can be registered before `startRecording`.
+    #[napi(ts_args_type = "callback: (buffer: AudioBufferEvent) => void")]
+    pub fn on_audio_buffer(&mut self, callback: JsFunction) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            let tsfn: ThreadsafeFunction<screencapturekit::audio_tap::PcmBuffer, ErrorStrategy::Fatal> = callback
+                .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<screencapturekit::audio_tap::PcmBuffer>| {
+                    let pcm = ctx.value;
+                    Ok(vec![AudioBufferEvent {
+                        channels: pcm.channels.len() as u32,
+                        sample_rate: pcm.sample_rate,
+                        frame_count: pcm.frame_count() as u32,
+                        timestamp: pcm.presentation_time,
+                        samples: Float32Array::new(screencapturekit::audio::convert::interleave(&pcm.channels)),
+                    }])
+                })?;
+            let listener: screencapturekit::process_tap::PcmHandler = std::sync::Arc::new(move |pcm: &screencapturekit::audio_tap::PcmBuffer| {
+                let copy = screencapturekit::audio_tap::PcmBuffer {
+                    sample_rate: pcm.sample_rate,
+                    channels: pcm.channels.clone(),
+                    presentation_time: pcm.presentation_time,
+                };
+                tsfn.call(copy, ThreadsafeFunctionCallMode::NonBlocking);
+            });
+        
+            self.with_recording_session(|stream_manager| stream_manager.set_audio_buffer_listener(Some(listener.clone())))?;
+            self.update_listeners(|listeners| listeners.audio_buffer = Some(listener));
+            Ok(())
+        })
+    }
     
-    // ADDED: Safe methods for checking if display/window exists
     #[napi]
-    pub fn has_display(&self, display_id: u32) -> bool {
-        self.inner.find_display_by_id(display_id).is_some()
+    pub fn remove_audio_buffer_listener(&mut self) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            self.update_listeners(|listeners| listeners.audio_buffer = None);
+            self.with_recording_session(|stream_manager| stream_manager.set_audio_buffer_listener(None))?;
+            Ok(())
+        })
+    }
+    
+    /// Deliver every captured frame's pixels, after overlays and secure-input blanking, e.g. for OCR
+    /// or motion detection. Frames are dropped while JS is still busy with earlier ones. Can be
+    /// registered before `startRecording`.
+    #[napi(ts_args_type = "callback: (frame: VideoFrameEvent) => void, options?: VideoFrameOptions | undefined | null")]
+    pub fn on_video_frame(&mut self, callback: JsFunction, options: Option<VideoFrameOptions>) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            let format = options.and_then(|o| o.format).unwrap_or_default();
+            // A short queue: a full one rejects the call, so slow consumers skip frames instead of piling them up
+            let tsfn: ThreadsafeFunction<screencapturekit::frames::VideoFrame, ErrorStrategy::Fatal> = callback
+                .create_threadsafe_function(2, |ctx: ThreadSafeCallContext<screencapturekit::frames::VideoFrame>| {
+                    let frame = ctx.value;
+                    Ok(vec![VideoFrameEvent {
+                        data: frame.data.into(),
+                        width: frame.width,
+                        height: frame.height,
+                        stride: frame.stride,
+                        format: frame.format,
+                        timestamp: frame.timestamp,
+                    }])
+                })?;
+            let listener = screencapturekit::frames::VideoFrameListener {
+                format,
+                handler: std::sync::Arc::new(move |frame| {
+                    tsfn.call(frame, ThreadsafeFunctionCallMode::NonBlocking);
+                }),
+            };
+        
+            self.with_recording_session(|stream_manager| stream_manager.set_video_frame_listener(Some(listener.clone())))?;
+            self.update_listeners(|listeners| listeners.video_frame = Some(listener));
+            Ok(())
+        })
     }
     
     #[napi]
-    pub fn has_window(&self, window_id: u32) -> bool {
-        self.inner.find_window_by_id(window_id).is_some()
+    pub fn remove_video_frame_listener(&mut self) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            self.update_listeners(|listeners| listeners.video_frame = None);
+            self.with_recording_session(|stream_manager| stream_manager.set_video_frame_listener(None))?;
+            Ok(())
+        })
     }
     
-    // ADDED: Safe method to get display info without object extraction
     #[napi]
-    pub fn get_display_info(&self, display_id: u32) -> Result<Option<DisplayInfo>> {
-        match self.inner.find_display_by_id(display_id) {
-            Some(display) => Ok(Some(DisplayInfo {
-                id: display.id,
-                name: display.name.clone(),
-                width: display.width,
-                height: display.height,
-            })),
-            None => Ok(None)
-        }
+    pub fn stop_audio_tap(&mut self) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            self.update_listeners(|listeners| listeners.audio_tap = None);
+            self.with_recording_session(|stream_manager| stream_manager.set_audio_tap(None))?;
+            log::info!("Live audio tap removed");
+            Ok(())
+        })
     }
     
-    // ADDED: Safe method to get window info without object extraction
     #[napi]
-    pub fn get_window_info(&self, window_id: u32) -> Result<Option<WindowInfo>> {
-        match self.inner.find_window_by_id(window_id) {
-            Some(window) => Ok(Some(WindowInfo {
-                id: window.id,
-                title: window.title.clone(),
-                width: window.width,
-                height: window.height,
-            })),
-            None => Ok(None)
-        }
+    pub fn get_tags(&self) -> Vec<String> {
+        self.with_session(|m| Ok(m.session().map(|s| s.tags.clone()).unwrap_or_default()))
+            .unwrap_or_default()
     }
-}
 
-#[napi]
-pub struct ScreenCaptureKitRecorder {
-    current_content: Option<screencapturekit::content::ShareableContent>,
-}
+    /// What the recorder is doing right now: state, the active session with its output path,
+    /// elapsed time and encoder, permission status, and the macOS features this Mac has
+    #[napi]
+    pub fn get_status(&self) -> RecorderStatus {
+        let session = self.stream_manager.lock().ok().and_then(|slot| {
+            let manager = slot.as_ref()?;
+            let record = manager.session()?;
+            let stats = manager.capture_stats();
+            let encoder = manager.video_encoder_settings().map(|settings| {
+                let hardware = screencapturekit::recommend::EncoderCapabilities::probe();
+                EncoderStatus {
+                    codec: settings.codec,
+                    width: settings.width,
+                    height: settings.height,
+                    fps: settings.fps,
+                    hardware_accelerated: match settings.codec {
+                        VideoCodec::H264 => hardware.hardware_h264,
+                        VideoCodec::Hevc => hardware.hardware_hevc,
+                    },
+                }
+            });
+            Some(SessionStatus {
+                session_id: record.id.clone(),
+                output_path: record.output_path.clone(),
+                started_at: record.started_at as f64,
+                elapsed_ms: screencapturekit::session::now_millis().saturating_sub(record.started_at) as f64,
+                recorded_ms: stats.as_ref().map(|s| s.duration_ms as f64).unwrap_or(0.0),
+                is_paused: stats.as_ref().map(|s| s.is_paused).unwrap_or(false),
+                streams: manager.stream_count() as u32,
+                captures_audio: manager.records_audio(),
+                encoder,
+            })
+        });
 
-#[napi]
-impl ScreenCaptureKitRecorder {
-    #[napi(constructor)]
-    pub fn new() -> Result<Self> {
-        println!("🦀 Creating new ScreenCaptureKit recorder with objc2");
-        
-        // Initialize logging (ignore if already initialized)
-        let _ = env_logger::try_init();
-        
-        Ok(Self {
-            current_content: None,
-        })
+        RecorderStatus {
+            is_recording: session.is_some(),
+            state: self.events.state(),
+            session,
+            scheduled: self.schedule.as_ref().map(|s| s.is_pending()).unwrap_or(false),
+            screen_recording_permission: unsafe {
+                screencapturekit::bindings::ScreenCaptureKitHelpers::check_screen_recording_permission()
+            },
+            capabilities: get_capabilities(),
+            version: get_version(),
+        }
     }
 
+    /// Suggest resolution, fps, codec and bitrate for a source based on its size,
+    /// refresh rate and the encoders available on this machine
     #[napi]
-    pub fn get_available_screens(&mut self) -> Result<Vec<ScreenSource>> {
-        println!("📺 Getting available screens via ScreenCaptureKit (sync)");
-        
-        // Option 1: Use cached content if available
-        if let Some(ref content) = self.current_content {
-            let sources = screencapturekit::content::ContentManager::extract_screen_sources(content)?;
-            println!("✅ Found {} screen sources from cache", sources.len());
-            return Ok(sources);
-        }
-        
-        // Option 2: Try synchronous content retrieval
-        match screencapturekit::content::ShareableContent::new_with_real_data() {
-            Ok(content) => {
-                let sources = screencapturekit::content::ContentManager::extract_screen_sources(&content)?;
-                self.current_content = Some(content);
-                println!("✅ Found {} screen sources via sync API", sources.len());
-                Ok(sources)
-            }
-            Err(_) => {
-                // Option 3: Graceful fallback - inform user to use async version
-                Err(Error::new(
-                    Status::GenericFailure, 
-                    "For real ScreenCaptureKit content, use getAvailableScreensAsync() instead. Sync version requires cached content or async initialization."
-                ))
+    pub fn recommend_configuration(&mut self, screen_id: String) -> Result<RecommendedConfiguration, CaptureErrorCode> {
+        coded(|| {
+            log::info!("Building recommended configuration for: {}", screen_id);
+            self.expire_cached_content();
+
+            let mut current_content = self.lock_content()?;
+            if current_content.is_none() {
+                *current_content = Some(screencapturekit::content::ShareableContent::new_with_real_data()?);
             }
-        }
+            let content = current_content.as_ref().unwrap();
+
+            let recommendation = if screen_id.starts_with("display:") {
+                let display_id: u32 = screen_id[8..].parse()
+                    .map_err(|_| Error::new(Status::InvalidArg, "Invalid display ID"))?;
+                let display = content.find_display_by_id(display_id)
+                    .ok_or_else(|| capture_error(CaptureErrorCode::SourceNotFound, format!("Display ID {} not found", display_id)))?;
+                screencapturekit::recommend::recommend_for_display(display)
+            } else if screen_id.starts_with("window:") {
+                let window_id: u32 = screen_id[7..].parse()
+                    .map_err(|_| Error::new(Status::InvalidArg, "Invalid window ID"))?;
+                let window = content.find_window_by_id(window_id)
+                    .ok_or_else(|| capture_error(CaptureErrorCode::SourceNotFound, format!("Window ID {} not found", window_id)))?;
+                screencapturekit::recommend::recommend_for_window(window)
+            } else {
+                return Err(Error::new(Status::InvalidArg, "Invalid screen ID format"));
+            };
+
+            Ok(RecommendedConfiguration {
+                width: recommendation.width,
+                height: recommendation.height,
+                fps: recommendation.fps,
+                codec: recommendation.codec,
+                bitrate: recommendation.bitrate,
+                hardware_encoder: recommendation.hardware_encoder,
+                notes: recommendation.notes,
+            })
+        })
     }
 
-    /// Improved version that properly handles ScreenCaptureKit's async nature with timeout
+    /// Check `config` for recording `screen_id` without starting anything: resolution against the
+    /// source, fps against its refresh rate, whether the codec can be opened and whether the output
+    /// path is writable
     #[napi]
-    pub fn get_available_screens_with_timeout(&mut self, timeout_ms: Option<u32>) -> Result<Vec<ScreenSource>> {
-        println!("📺 Getting available screens via ScreenCaptureKit with timeout handling");
-        
-        let timeout = timeout_ms.unwrap_or(5000); // Default 5 second timeout
-        
-        // Option 1: Use cached content if available
-        if let Some(ref content) = self.current_content {
-            let sources = screencapturekit::content::ContentManager::extract_screen_sources(content)?;
-            println!("✅ Found {} screen sources from cache", sources.len());
-            return Ok(sources);
-        }
-        
-        // Option 2: Try the improved content retrieval with timeout
-        match screencapturekit::content::ShareableContent::new_with_timeout(timeout) {
-            Ok(content) => {
-                let sources = screencapturekit::content::ContentManager::extract_screen_sources(&content)?;
-                self.current_content = Some(content);
-                println!("✅ Found {} screen sources via timeout-protected API", sources.len());
-                Ok(sources)
-            }
-            Err(_) => {
-                // Option 3: Graceful fallback - inform user about the issue
-                Err(Error::new(
-                    Status::GenericFailure, 
-                    "ScreenCaptureKit content retrieval failed. This may be due to permissions or the async/sync mismatch issue. Please ensure screen recording permission is granted."
-                ))
+    pub fn validate_configuration(&mut self, screen_id: String, config: RecordingConfiguration) -> Result<ValidationReport, CaptureErrorCode> {
+        coded(|| {
+            self.expire_cached_content();
+            let mut current_content = self.lock_content()?;
+            if current_content.is_none() {
+                *current_content = Some(screencapturekit::content::ShareableContent::new_with_real_data()?);
             }
-        }
-    }
+            let content = current_content.as_ref().unwrap();
 
-    #[napi]
-    pub fn get_available_audio_devices(&self) -> Result<Vec<AudioDevice>> {
-        println!("🔊 Getting available audio devices via AVFoundation");
-        screencapturekit::AudioManager::get_available_audio_devices()
+            // Windows carry no scale of their own; the main display's is the best guess
+            let displays = content.get_displays()?;
+            let main_scale = displays.iter().find(|d| d.is_main).map(|d| d.scale_factor).unwrap_or(1.0);
+            let source = if let Some(id) = screen_id.strip_prefix("display:") {
+                let display_id: u32 = id.parse().map_err(|_| Error::new(Status::InvalidArg, "Invalid display ID"))?;
+                let display = content.find_display_by_id(display_id)
+                    .ok_or_else(|| capture_error(CaptureErrorCode::SourceNotFound, format!("Display ID {} not found", display_id)))?;
+                screencapturekit::validate::SourceSize {
+                    pixel_width: (display.width as f64 * display.scale_factor) as u32,
+                    pixel_height: (display.height as f64 * display.scale_factor) as u32,
+                    refresh_rate: display.refresh_rate,
+                }
+            } else if let Some(id) = screen_id.strip_prefix("window:") {
+                let window_id: u32 = id.parse().map_err(|_| Error::new(Status::InvalidArg, "Invalid window ID"))?;
+                let window = content.find_window_by_id(window_id)
+                    .ok_or_else(|| capture_error(CaptureErrorCode::SourceNotFound, format!("Window ID {} not found", window_id)))?;
+                screencapturekit::validate::SourceSize {
+                    pixel_width: (window.width as f64 * main_scale) as u32,
+                    pixel_height: (window.height as f64 * main_scale) as u32,
+                    refresh_rate: displays.iter().map(|d| d.refresh_rate).fold(60.0, f64::max),
+                }
+            } else {
+                return Err(Error::new(Status::InvalidArg, "Invalid screen ID format"));
+            };
+
+            Ok(screencapturekit::validate::validate(&config, Some(source)).into())
+        })
     }
 
-    #[napi]
-    pub fn start_recording(
-        &mut self,
-        screen_id: String,
-        config: RecordingConfiguration,
-    ) -> Result<()> {
-        println!("🎬 Starting ScreenCaptureKit recording with screen_id: {}", screen_id);
-        println!("📁 Output path: {}", config.output_path);
-        
-        let content = match &self.current_content {
-            Some(content) => content,
-            None => {
-                let content = screencapturekit::content::ShareableContent::new_with_real_data()?;
-                self.current_content = Some(content);
-                self.current_content.as_ref().unwrap()
+    /// Run `f` against the active recording session
+    fn with_session<T>(&self, f: impl FnOnce(&mut screencapturekit::content::RealStreamManager) -> Result<T>) -> Result<T> {
+        let mut slot = self.stream_manager.lock()
+            .map_err(|_| capture_error(CaptureErrorCode::Unknown, "Recording state is poisoned"))?;
+        match slot.as_mut() {
+            Some(stream_manager) => f(stream_manager),
+            None => Err(capture_error(CaptureErrorCode::NotRecording, "No active recording session")),
+        }
+    }
+    
+    /// Like `with_session`, but a no-op when nothing is recording
+    fn with_recording_session(&self, f: impl FnOnce(&mut screencapturekit::content::RealStreamManager) -> Result<()>) -> Result<()> {
+        if let Ok(mut slot) = self.stream_manager.lock() {
+            if let Some(stream_manager) = slot.as_mut() {
+                if stream_manager.is_recording() {
+                    return f(stream_manager);
+                }
             }
-        };
-
-        // Create real content filter based on screen_id using the FIXED segfault-safe method
-        let content_filter = self.create_real_content_filter_safe(content, &screen_id)?;
-        
-        // Create real stream manager and start recording
-        let mut stream_manager = screencapturekit::content::RealStreamManager::new();
-        stream_manager.start_recording(content_filter, config)?;
-        
-        // Store the stream manager (in a real implementation, this would be a field)
-        // For now, we'll just demonstrate the API usage
-        
-        println!("✅ Real ScreenCaptureKit recording started (segfault-safe)");
+        }
         Ok(())
     }
-
-    #[napi]
-    pub fn stop_recording(&mut self) -> Result<String> {
-        println!("🛑 Stopping ScreenCaptureKit recording");
-        
-        let output_path = "/tmp/screencapturekit-recording.mp4".to_string();
-        
-        println!("✅ ScreenCaptureKit recording stopped (real implementation), output: {}", output_path);
-        Ok(output_path)
+    
+    /// Take the session out of `slot` if it is still the one with `session_id`
+    fn take_session(slot: &SessionSlot, session_id: &str) -> Option<screencapturekit::content::RealStreamManager> {
+        let mut slot = slot.lock().ok()?;
+        let ours = slot.as_ref().and_then(|m| m.session()).map(|s| s.id == session_id).unwrap_or(false);
+        if ours { slot.take() } else { None }
     }
-
-    #[napi]
-    pub fn is_recording(&self) -> bool {
-        // In real implementation, this would check actual stream status
-        false
+    
+    fn disk_reserve_bytes(config: &RecordingConfiguration) -> Option<u64> {
+        match config.min_free_disk_mb.unwrap_or(screencapturekit::disk_watch::DEFAULT_RESERVE_MB) {
+            0 => None,
+            mb => Some(mb as u64 * 1024 * 1024),
+        }
     }
-
-    #[napi]
-    pub fn get_status(&self) -> String {
-        serde_json::json!({
-            "isRecording": false,
-            "outputPath": null,
-            "hasStream": true,
-            "method": "objc2-screencapturekit-segfault-safe",
-            "version": "0.2.1",
-            "capabilities": {
-                "directAPI": true,
-                "nativePerformance": true,
-                "screenCapture": true,
-                "audioCapture": true,
-                "windowCapture": true,
-                "realTimeStreaming": true,
-                "realScreenCaptureKitAPIs": true,
-                "scStreamInstances": true,
-                "scStreamDelegate": true,
-                "cvPixelBufferProcessing": true,
-                "cmSampleBufferProcessing": true,
-                "realFrameProcessing": true,
-                "segfaultSafe": true
-            },
-            "fixes": {
-                "segfaultPrevention": true,
-                "safeContentFilterCreation": true,
-                "bypassObjectExtraction": true,
-                "improvedMemoryManagement": true
+    
+    /// Stop and finalize the session natively once its volume runs low, before AVAssetWriter
+    /// fails on a full disk and leaves an unreadable file behind
+    fn watch_disk_space(
+        slot: &SessionSlot,
+        listener: &SharedListener<AutoStoppedEvent>,
+        events: &RecorderEvents,
+        started: &RecordingStartResult,
+        reserve_bytes: Option<u64>,
+    ) {
+        let reserve_bytes = match reserve_bytes {
+            Some(reserve_bytes) => reserve_bytes,
+            None => return,
+        };
+        // Weak, so a dropped recorder doesn't keep the watcher (and its session) alive
+        let keep_watching = {
+            let slot = std::sync::Arc::downgrade(slot);
+            let session_id = started.session_id.clone();
+            move || {
+                slot.upgrade()
+                    .and_then(|slot| slot.lock().ok().map(|slot| {
+                        slot.as_ref().and_then(|m| m.session()).map(|s| s.id == session_id).unwrap_or(false)
+                    }))
+                    .unwrap_or(false)
+            }
+        };
+        let on_low = {
+            let slot = std::sync::Arc::downgrade(slot);
+            let listener = listener.clone();
+            let events = events.clone();
+            let session_id = started.session_id.clone();
+            let output_path = started.output_path.clone();
+            move |free: u64| {
+                let mut stream_manager = match slot.upgrade().and_then(|slot| Self::take_session(&slot, &session_id)) {
+                    Some(stream_manager) => stream_manager,
+                    None => return,
+                };
+                log::info!("Stopping recording {}: output volume is almost full", session_id);
+                events.emit(RecorderEvent::with_message(
+                    "warning",
+                    Some(session_id.clone()),
+                    format!("Output volume is almost full ({} MB left), stopping", free / (1024 * 1024)),
+                ));
+                let error = events.stop(&mut stream_manager).err().map(|e| screencapturekit::errors::describe(&e).1);
+                emit_shared(&listener, AutoStoppedEvent {
+                    reason: "disk_full".to_string(),
+                    output_path,
+                    session_id,
+                    free_bytes: Some(free as f64),
+                    error,
+                });
             }
-        }).to_string()
+        };
+        screencapturekit::disk_watch::watch(&started.output_path, reserve_bytes, Box::new(keep_watching), Box::new(on_low));
+    }
+    
+    fn update_listeners(&self, f: impl FnOnce(&mut SessionListeners)) {
+        if let Ok(mut listeners) = self.listeners.lock() {
+            f(&mut listeners);
+        }
     }
 
     // FIXED: Safe content filter creation that avoids segfaults
     fn create_real_content_filter_safe(
-        &self,
         content: &screencapturekit::content::ShareableContent,
         screen_id: &str,
+        exclusions: &screencapturekit::content::DisplayExclusions,
+        window_ids: &[u32],
     ) -> Result<screencapturekit::content::RealContentFilter> {
-        println!("🎯 Creating real content filter for screen: {} (segfault-safe)", screen_id);
+        log::info!("Creating real content filter for screen: {} (segfault-safe)", screen_id);
         
-        if screen_id.starts_with("display:") {
+        if screen_id.starts_with("windows:") {
+            let window_ids = screen_id[8..]
+                .split(',')
+                .map(|id| id.trim().parse::<u32>())
+                .collect::<std::result::Result<Vec<u32>, _>>()
+                .map_err(|_| Error::new(Status::InvalidArg, "Invalid window ID list"))?;
+            
+            log::info!("Creating multi-window content filter for ScreenCaptureKit");
+            screencapturekit::content::RealContentFilter::new_with_windows(content, None, &window_ids)
+            
+        } else if screen_id.starts_with("app:") {
+            let bundle_id = screen_id[4..].trim();
+            if bundle_id.is_empty() {
+                return Err(Error::new(Status::InvalidArg, "Invalid application bundle ID"));
+            }
+            
+            log::info!("Creating application content filter for ScreenCaptureKit");
+            screencapturekit::content::RealContentFilter::new_with_application(content, bundle_id, None)
+            
+        } else if screen_id.starts_with("display:") {
             let display_id: u32 = screen_id[8..].parse()
                 .map_err(|_| Error::new(Status::InvalidArg, "Invalid display ID"))?;
             
-            println!("✅ Creating segfault-safe display content filter for ScreenCaptureKit");
-            screencapturekit::content::RealContentFilter::new_with_display(content, display_id)
+            if !window_ids.is_empty() {
+                log::info!("Creating multi-window content filter on display {} for ScreenCaptureKit", display_id);
+                return screencapturekit::content::RealContentFilter::new_with_windows(content, Some(display_id), window_ids);
+            }
+            
+            log::info!("Creating segfault-safe display content filter for ScreenCaptureKit");
+            screencapturekit::content::RealContentFilter::new_with_display_excluding(content, display_id, exclusions)
             
         } else if screen_id.starts_with("window:") {
             let window_id: u32 = screen_id[7..].parse()
                 .map_err(|_| Error::new(Status::InvalidArg, "Invalid window ID"))?;
             
-            println!("✅ Creating segfault-safe window content filter for ScreenCaptureKit");
+            log::info!("Creating segfault-safe window content filter for ScreenCaptureKit");
             screencapturekit::content::RealContentFilter::new_with_window(content, window_id)
             
         } else {
@@ -462,287 +3893,883 @@ impl ScreenCaptureKitRecorder {
     }
 }
 
+// Log lines from the moment the module loads, before any handler is set
+#[napi::module_init]
+fn init_module() {
+    screencapturekit::logging::install();
+    screencapturekit::shutdown::install();
+}
+
+#[napi]
+pub fn init_screencapturekit() -> Result<(), CaptureErrorCode> {
+    coded(|| {
+        log::info!("Initializing ScreenCaptureKit module with objc2 bindings");
+        log::info!("Real implementation with actual ScreenCaptureKit APIs (segfault-safe)");
+    
+        // Configure audio session with real AVFoundation
+        screencapturekit::AudioManager::configure_audio_session()?;
+    
+        Ok(())
+    })
+}
+
+/// Combine several microphones into one private aggregate device for multi-speaker capture.
+/// Inputs keep separate channels in the given order, as listed in `channels`, and a recording
+/// through the device keeps each input's range in its audio track (up to 8 channels); two mono
+/// mics land on left and right.
 #[napi]
-pub fn init_screencapturekit() -> Result<()> {
-    println!("🦀 Initializing ScreenCaptureKit module with objc2 bindings");
-    println!("🎯 Real implementation with actual ScreenCaptureKit APIs (segfault-safe)");
+pub fn create_aggregate_input_device(input_uids: Vec<String>, name: Option<String>) -> Result<AggregateInputDevice, CaptureErrorCode> {
+    coded(|| {
+        let name = name.unwrap_or_else(|| "WhisperDesk Multi-Mic".to_string());
+        let device = screencapturekit::audio::AudioManager::create_aggregate_input_device(&input_uids, &name)?;
     
-    // Configure audio session with real AVFoundation
-    screencapturekit::AudioManager::configure_audio_session()?;
+        let total_channels: u32 = device.channels.iter().map(|c| c.channel_count).sum();
+        if total_channels > screencapturekit::audio::MAX_RECORDED_CHANNELS {
+            log::warn!("Aggregate device has {} channels - recordings keep the first {}",
+                total_channels, screencapturekit::audio::MAX_RECORDED_CHANNELS);
+        }
     
-    Ok(())
+        Ok(AggregateInputDevice {
+            uid: device.uid,
+            name: device.name,
+            channels: device.channels.into_iter().map(|c| AggregateChannelRange {
+                device_uid: c.device_uid,
+                first_channel: c.first_channel,
+                channel_count: c.channel_count,
+            }).collect(),
+        })
+    })
+}
+
+/// Remove an aggregate device created by `createAggregateInputDevice`
+#[napi]
+pub fn destroy_aggregate_input_device(uid: String) -> Result<(), CaptureErrorCode> {
+    coded(|| {
+        screencapturekit::audio::AudioManager::destroy_aggregate_input_device(&uid)
+    })
 }
 
 #[napi]
 pub fn get_version() -> String {
-    "0.2.1-segfault-safe-screencapturekit".to_string()
+    screencapturekit::status::VERSION.to_string()
+}
+
+/// Send the module's log lines to `callback` instead of stdout, or back to stdout when it's null.
+/// `level` (info unless RUST_LOG says otherwise) is the most verbose level logged; "off" silences
+/// the module entirely. The callback doesn't keep the process alive.
+/// Each worker thread has its own handler, and every handler gets every line; the level is shared.
+#[napi(ts_args_type = "callback: ((entry: LogEntry) => void) | null, level?: LogLevel | undefined | null")]
+pub fn set_log_handler(mut env: Env, callback: Option<JsFunction>, level: Option<LogLevel>) -> Result<(), CaptureErrorCode> {
+    coded(|| {
+        let key = env.raw() as usize;
+        let handler = match callback {
+            Some(callback) => {
+                let mut tsfn: screencapturekit::logging::LogHandler = callback
+                    .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<LogEntry>| Ok(vec![ctx.value]))?;
+                tsfn.unref(&env)?;
+                // A worker that exits takes its handler with it
+                if screencapturekit::logging::claim_cleanup_hook(key) {
+                    env.add_env_cleanup_hook(key, screencapturekit::logging::remove_handler)?;
+                }
+                Some(tsfn)
+            }
+            None => None,
+        };
+        screencapturekit::logging::set_handler(key, handler, level);
+        Ok(())
+    })
 }
 
+/// Set global limits that every recording configuration is clamped against. They apply to the
+/// whole process, worker threads included, since all of them share the same displays and encoders.
 #[napi]
-pub fn check_screen_recording_permission() -> Result<bool> {
-    unsafe {
-        let has_permission = screencapturekit::bindings::ScreenCaptureKitHelpers::check_screen_recording_permission();
-        Ok(has_permission)
-    }
+pub fn set_limits(limits: RecordingLimits) -> Result<(), CaptureErrorCode> {
+    coded(|| {
+        let values = [limits.max_width, limits.max_height, limits.max_fps];
+        if values.iter().any(|value| *value == Some(0)) {
+            return Err(Error::new(Status::InvalidArg, "Limits must be greater than zero"));
+        }
+
+        screencapturekit::limits::set_limits(screencapturekit::limits::Limits {
+            max_width: limits.max_width,
+            max_height: limits.max_height,
+            max_fps: limits.max_fps,
+        });
+        Ok(())
+    })
 }
 
 #[napi]
-pub fn request_screen_recording_permission() -> Result<bool> {
-    unsafe {
-        let has_permission = screencapturekit::bindings::ScreenCaptureKitHelpers::request_screen_recording_permission();
-        Ok(has_permission)
+pub fn get_limits() -> RecordingLimits {
+    let limits = screencapturekit::limits::get_limits();
+    RecordingLimits {
+        max_width: limits.max_width,
+        max_height: limits.max_height,
+        max_fps: limits.max_fps,
     }
 }
 
+/// The named setups accepted by `preset`, for one-click choices in the UI
 #[napi]
-pub fn check_macos_version() -> Result<String> {
-    // Check actual macOS version
-    use std::process::Command;
-    
-    let output = Command::new("sw_vers")
-        .arg("-productVersion")
-        .output()
-        .map_err(|e| Error::new(Status::GenericFailure, format!("Failed to get macOS version: {}", e)))?;
-    
-    let version = String::from_utf8(output.stdout)
-        .map_err(|e| Error::new(Status::GenericFailure, format!("Invalid version string: {}", e)))?
-        .trim()
-        .to_string();
+pub fn list_presets() -> Vec<RecordingPreset> {
+    screencapturekit::presets::PRESETS.iter().map(|preset| RecordingPreset {
+        name: preset.name.to_string(),
+        description: preset.description.to_string(),
+        width: preset.width,
+        height: preset.height,
+        fps: preset.fps,
+        show_cursor: preset.show_cursor,
+        capture_audio: preset.capture_audio,
+        highlight_clicks: preset.highlight_clicks,
+        write_wav_sidecar: preset.write_wav_sidecar,
+    }).collect()
+}
+
+/// Recordings from the library index carrying the given tag (case-insensitive)
+#[napi]
+pub fn find_recordings_by_tag(tag: String) -> Vec<RecordingIndexEntry> {
+    screencapturekit::session::LibraryIndex::find_by_tag(&tag)
+        .into_iter()
+        .map(|record| RecordingIndexEntry {
+            session_id: record.id,
+            output_path: record.output_path,
+            started_at: record.started_at as f64,
+            stopped_at: record.stopped_at.map(|t| t as f64),
+            tags: record.tags,
+        })
+        .collect()
+}
+
+/// The most recent sessions from the session journal, newest first (default 50), including failed starts
+#[napi]
+pub fn get_session_history(limit: Option<u32>) -> Vec<SessionHistoryEntry> {
+    screencapturekit::session::SessionJournal::history(limit.unwrap_or(50) as usize)
+        .into_iter()
+        .map(|summary| SessionHistoryEntry {
+            duration_ms: summary.duration_ms().map(|d| d as f64),
+            outcome: summary.outcome.as_str().to_string(),
+            session_id: summary.session_id,
+            output_path: summary.output_path,
+            started_at: summary.started_at as f64,
+            ended_at: summary.ended_at.map(|t| t as f64),
+            error: summary.error,
+            tags: summary.tags,
+        })
+        .collect()
+}
+
+/// Screenshot every connected display at the same moment into `directory`
+#[napi]
+pub fn capture_all_displays(format: ImageFormat, directory: String) -> Result<BulkScreenshotResult, CaptureErrorCode> {
+    coded(|| {
+        let result = screencapturekit::screenshot::capture_all_displays(format, &directory)?;
+        Ok(BulkScreenshotResult {
+            timestamp: result.timestamp_ms as f64,
+            files: result.files.into_iter().map(|f| ScreenshotFile {
+                display_id: f.display_id,
+                path: f.path,
+                width: f.width,
+                height: f.height,
+            }).collect(),
+        })
+    })
+}
+
+/// Preview image of a display or window ("display:<id>" / "window:<id>") for a source picker,
+/// at most `maxWidth` pixels wide (default 320), png unless `format` says otherwise. Pass
+/// `operationId` to be able to `cancel` it from a recorder.
+#[napi(ts_return_type = "Promise<Buffer>")]
+pub fn get_source_thumbnail(
+    id: String,
+    max_width: Option<u32>,
+    format: Option<ImageFormat>,
+    operation_id: Option<String>,
+) -> Result<AsyncTask<SourceThumbnailTask>, CaptureErrorCode> {
+    coded(|| {
+        Ok(AsyncTask::new(SourceThumbnailTask {
+            screen_id: id,
+            max_width: max_width.unwrap_or(screencapturekit::screenshot::DEFAULT_THUMBNAIL_WIDTH),
+            format: format.unwrap_or(ImageFormat::Png),
+            operation: screencapturekit::cancel::Operation::begin(operation_id)?,
+        }))
+    })
+}
+
+/// Grab one full-resolution frame of "display:<id>", "window:<id>" or "region:<displayId>" (with
+/// `crop`) without starting a recording
+#[napi(ts_return_type = "Promise<ScreenshotResult>")]
+pub fn capture_screenshot(screen_id: String, options: Option<ScreenshotOptions>) -> Result<AsyncTask<ScreenshotTask>, CaptureErrorCode> {
+    coded(|| {
+        let options = options.unwrap_or(ScreenshotOptions { format: None, path: None, crop: None, show_cursor: None });
+        let format = match (&options.format, &options.path) {
+            (Some(format), _) => *format,
+            (None, Some(path)) => match std::path::Path::new(path).extension().and_then(|e| e.to_str()) {
+                Some(extension) => ImageFormat::from_extension(extension).unwrap_or(ImageFormat::Png),
+                None => ImageFormat::Png,
+            },
+            (None, None) => ImageFormat::Png,
+        };
+        Ok(AsyncTask::new(ScreenshotTask {
+            screen_id,
+            crop: options.crop.map(|c| (c.x, c.y, c.width, c.height)),
+            show_cursor: options.show_cursor.unwrap_or(false),
+            format,
+            path: options.path,
+        }))
+    })
+}
+
+/// True while some app has secure event input enabled (a password field has focus)
+#[napi]
+pub fn is_secure_input_active() -> bool {
+    screencapturekit::secure_input::is_secure_input_active()
+}
+
+/// Record a couple of seconds from the microphone and system audio and report device, permission and
+/// signal level for each, to debug "my recording has no sound"
+#[napi(ts_return_type = "Promise<AudioDiagnosticsReport>")]
+pub fn run_audio_diagnostics(options: Option<AudioDiagnosticsOptions>) -> AsyncTask<AudioDiagnosticsTask> {
+    let (audio_device_id, duration_ms) = match options {
+        Some(options) => (options.audio_device_id, options.duration_ms),
+        None => (None, None),
+    };
+    AsyncTask::new(AudioDiagnosticsTask {
+        audio_device_id,
+        duration_ms: duration_ms.unwrap_or(screencapturekit::diagnostics::DEFAULT_DURATION_MS).clamp(100, 10_000),
+    })
+}
+
+/// EDR headroom per display, to suggest HDR capture only when it would make a difference
+#[napi]
+pub fn get_display_hdr_info() -> Vec<DisplayHdrInfo> {
+    screencapturekit::hdr::display_headroom()
+        .into_iter()
+        .map(|h| DisplayHdrInfo {
+            display_id: h.display_id,
+            edr_headroom: h.current,
+            potential_edr_headroom: h.potential,
+            reference_edr_headroom: h.reference,
+            hdr_capable: h.hdr_capable(),
+            hdr_content_on_screen: h.hdr_content_on_screen(),
+        })
+        .collect()
+}
+
+/// Long-exposure screenshot: averages several frames to wash out the cursor, caret blink and tooltips
+#[napi]
+pub fn capture_averaged_screenshot(display_id: u32, output_path: String, options: Option<AveragedScreenshotOptions>) -> Result<ScreenshotFile, CaptureErrorCode> {
+    coded(|| {
+        use screencapturekit::screenshot;
     
-    // Check if version is compatible with ScreenCaptureKit (requires macOS 12.3+)
-    if version.starts_with("10.") || version.starts_with("11.") || 
-       (version.starts_with("12.") && version.as_str() < "12.3") {
-        return Err(Error::new(
-            Status::GenericFailure, 
-            format!("ScreenCaptureKit requires macOS 12.3 or later, found: {}", version)
-        ));
-    }
+        let options = options.unwrap_or(AveragedScreenshotOptions { frames: None, interval_ms: None, format: None });
+        let format = match options.format {
+            Some(format) => format,
+            None => std::path::Path::new(&output_path)
+                .extension()
+                .and_then(|ext| ImageFormat::from_extension(&ext.to_string_lossy()))
+                .unwrap_or(ImageFormat::Png),
+        };
     
-    Ok(version)
+        let file = screenshot::capture_averaged_display(
+            display_id,
+            options.frames.unwrap_or(screenshot::DEFAULT_AVERAGE_FRAMES),
+            options.interval_ms.unwrap_or(screenshot::DEFAULT_AVERAGE_INTERVAL_MS),
+            format,
+            &output_path,
+        )?;
+        Ok(ScreenshotFile {
+            display_id: file.display_id,
+            path: file.path,
+            width: file.width,
+            height: file.height,
+        })
+    })
 }
 
-// Test function to demonstrate Phase 2 capabilities
 #[napi]
-pub fn test_permissions_and_api() -> Result<String> {
-    let mut results = Vec::new();
-    
-    // Test 1: Check macOS version
-    results.push("=== ScreenCaptureKit Segfault-Safe Implementation Test ===".to_string());
-    match check_macos_version() {
-        Ok(version) => {
-            results.push(format!("✅ macOS Version: {} (ScreenCaptureKit compatible)", version));
+pub fn check_screen_recording_permission() -> Result<bool, CaptureErrorCode> {
+    coded(|| {
+        unsafe {
+            let has_permission = screencapturekit::bindings::ScreenCaptureKitHelpers::check_screen_recording_permission();
+            Ok(has_permission)
         }
-        Err(e) => {
-            results.push(format!("❌ macOS Version Check Failed: {}", e));
-            return Ok(results.join("\n"));
+    })
+}
+
+#[napi]
+pub fn request_screen_recording_permission() -> Result<bool, CaptureErrorCode> {
+    coded(|| Ok(screencapturekit::permissions::request_screen_recording_access()))
+}
+
+/// Whether `pane`'s permission is granted, never asked for (prompt with the matching request
+/// function), denied (send the user to `openPermissionSettings`) or restricted by a profile.
+/// Reading it never prompts.
+#[napi]
+pub fn get_permission_state(pane: screencapturekit::permissions::PrivacyPane) -> screencapturekit::permissions::PermissionState {
+    screencapturekit::permissions::state_for(pane)
+}
+
+/// The permissions recording with `config` needs and their current state, so only those are
+/// requested up front. Reading them never prompts.
+#[napi]
+pub fn get_required_permissions(config: RecordingConfiguration) -> Vec<PermissionRequirement> {
+    screencapturekit::permissions::required_for(&config)
+        .into_iter()
+        .map(|(permission, needed_for)| PermissionRequirement {
+            permission,
+            needed_for: needed_for.to_string(),
+            state: screencapturekit::permissions::state_for(permission),
+        })
+        .collect()
+}
+
+/// Microphone access without prompting; when it is `notDetermined`, `requestMicrophonePermission`
+/// will prompt. Recording from a microphone without it gives silence, not an error.
+#[napi]
+pub fn check_microphone_permission() -> screencapturekit::permissions::PermissionState {
+    screencapturekit::permissions::microphone_authorization()
+}
+
+/// Ask for microphone access, prompting only when it was never answered; resolves with whether
+/// it is granted. A denial can only be undone in System Settings > Privacy & Security > Microphone.
+#[napi(ts_return_type = "Promise<boolean>")]
+pub fn request_microphone_permission() -> AsyncTask<PermissionRequestTask> {
+    AsyncTask::new(PermissionRequestTask {
+        request: screencapturekit::permissions::request_microphone_access,
+    })
+}
+
+/// Camera access without prompting. Starting a camera without it fails with PermissionDenied.
+#[napi]
+pub fn check_camera_permission() -> screencapturekit::permissions::PermissionState {
+    screencapturekit::permissions::camera_authorization()
+}
+
+/// Ask for camera access, prompting only when it was never answered; resolves with whether it
+/// is granted. A denial can only be undone in System Settings > Privacy & Security > Camera.
+#[napi(ts_return_type = "Promise<boolean>")]
+pub fn request_camera_permission() -> AsyncTask<PermissionRequestTask> {
+    AsyncTask::new(PermissionRequestTask {
+        request: screencapturekit::permissions::request_camera_access,
+    })
+}
+
+/// Keep every permission prompt from showing, e.g. while the app polls status in the background.
+/// The check functions never prompt either way. While this is on, the request functions report
+/// the current state instead of prompting, and anything that would prompt fails with
+/// PermissionDenied unless its permission is already granted: enumerating sources (`getAvailableScreens`,
+/// `warmUp`, `refreshContent`, `ContentManager`), starting or scheduling a recording, the
+/// coreaudio-tap backend, `highlight_clicks`, `record_input_events`, screenshots, thumbnails and
+/// the camera. The system audio tap only counts as granted once this process has created one.
+#[napi]
+pub fn set_silent_mode(silent: bool) {
+    screencapturekit::permissions::set_silent(silent);
+}
+
+#[napi]
+pub fn is_silent_mode() -> bool {
+    screencapturekit::permissions::is_silent()
+}
+
+/// Open System Settings on the Privacy & Security pane where `pane` is granted, e.g. next to a
+/// "grant access" button after a check came back denied
+#[napi]
+pub fn open_permission_settings(pane: screencapturekit::permissions::PrivacyPane) -> Result<(), CaptureErrorCode> {
+    coded(|| screencapturekit::permissions::open_settings(pane))
+}
+
+/// Poll screen recording, microphone and camera access every `intervalMs` (default 1000) and
+/// report each one that flips, e.g. to enable the record button once access is granted in System
+/// Settings. Nothing is reported for the states at the time of the call.
+#[napi(ts_args_type = "callback: (event: PermissionChangeEvent) => void, intervalMs?: number | undefined | null")]
+pub fn watch_permissions(callback: JsFunction, interval_ms: Option<u32>) -> Result<PermissionWatcher, CaptureErrorCode> {
+    coded(|| {
+        let tsfn: ThreadsafeFunction<screencapturekit::permission_watch::PermissionChange, ErrorStrategy::Fatal> = callback
+            .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<screencapturekit::permission_watch::PermissionChange>| {
+                Ok(vec![PermissionChangeEvent::from(ctx.value)])
+            })?;
+        let handler: screencapturekit::permission_watch::PermissionChangeHandler = std::sync::Arc::new(move |change| {
+            tsfn.call(change, ThreadsafeFunctionCallMode::NonBlocking);
+        });
+
+        let interval_ms = interval_ms.unwrap_or(screencapturekit::permission_watch::DEFAULT_INTERVAL_MS);
+        Ok(PermissionWatcher {
+            inner: Some(screencapturekit::permission_watch::PermissionWatcher::start(interval_ms, handler)),
+        })
+    })
+}
+
+/// Handle returned by `watchPermissions`
+#[napi]
+pub struct PermissionWatcher {
+    inner: Option<screencapturekit::permission_watch::PermissionWatcher>,
+}
+
+#[napi]
+impl PermissionWatcher {
+    #[napi]
+    pub fn stop(&mut self) {
+        if let Some(mut watcher) = self.inner.take() {
+            watcher.stop();
         }
     }
-    
-    // Test 2: Check permissions
-    match check_screen_recording_permission() {
-        Ok(true) => {
-            results.push("✅ Screen Recording Permission: Granted".to_string());
-        }
-        Ok(false) => {
-            results.push("⚠️ Screen Recording Permission: Not Granted".to_string());
-            results.push("💡 Please enable screen recording permission in System Preferences".to_string());
-        }
-        Err(e) => {
-            results.push(format!("❌ Permission Check Failed: {}", e));
-        }
+
+    #[napi]
+    pub fn is_watching(&self) -> bool {
+        self.inner.is_some()
     }
-    
-    // Test 3: Test basic ScreenCaptureKit API access
-    unsafe {
-        match screencapturekit::bindings::ScreenCaptureKitHelpers::get_shareable_content_sync() {
-            Ok(_) => {
-                results.push("✅ ScreenCaptureKit API: Accessible (sync)".to_string());
-            }
+}
+
+/// What this Mac can do: its macOS version and the ScreenCaptureKit, audio and encoder features
+/// found by looking them up at runtime rather than going by the version number
+#[napi]
+pub fn get_capabilities() -> Capabilities {
+    Capabilities {
+        macos_version: screencapturekit::status::os_version(),
+        arch: std::env::consts::ARCH.to_string(),
+        features: screencapturekit::status::features().into(),
+    }
+}
+
+/// Everything the renderer needs to decide what to offer, in one call: the macOS version, the
+/// architecture, whether ScreenCaptureKit is usable, permission states and the hardware encoders.
+/// Nothing here prompts the user or waits on ScreenCaptureKit.
+#[napi]
+pub fn probe_environment() -> EnvironmentProbe {
+    let macos = screencapturekit::status::os_version().zip(screencapturekit::status::os_version_parts())
+        .map(|(version, (major, minor, patch))| MacosVersion { version, major, minor, patch });
+    let features = screencapturekit::status::features();
+    let screen_capture_kit = features.screen_capture_kit
+        && screencapturekit::status::os_version_parts().map(screencapturekit::status::supports_screen_capture_kit).unwrap_or(false);
+    EnvironmentProbe {
+        macos,
+        arch: std::env::consts::ARCH.to_string(),
+        screen_capture_kit,
+        permissions: PermissionStates {
+            screen_recording: screencapturekit::permissions::screen_recording_authorization(),
+            microphone: screencapturekit::permissions::microphone_authorization(),
+            camera: screencapturekit::permissions::camera_authorization(),
+            accessibility: screencapturekit::permissions::accessibility_authorization(),
+        },
+        hardware_h264: features.hardware_h264,
+        hardware_hevc: features.hardware_hevc,
+    }
+}
+
+/// Everything that has to hold before recording is enabled, checked in one call: the macOS version,
+/// each permission, content enumeration, the hardware encoders and free space on the output
+/// volume. Content is only enumerated with screen recording granted, so this never prompts.
+#[napi(ts_return_type = "Promise<PreflightReport>")]
+pub fn run_preflight(options: Option<PreflightOptions>) -> AsyncTask<PreflightTask> {
+    AsyncTask::new(PreflightTask { options })
+}
+
+fn preflight(options: Option<PreflightOptions>) -> PreflightReport {
+    let (output_directory, min_free_disk_mb, timeout_ms) = match options {
+        Some(options) => (options.output_directory, options.min_free_disk_mb, options.timeout_ms),
+        None => (None, None, None),
+    };
+    let environment = probe_environment();
+    let mut problems = Vec::new();
+    if !environment.screen_capture_kit {
+        problems.push("ScreenCaptureKit needs macOS 12.3 or later".to_string());
+    }
+    if !environment.permissions.screen_recording.is_granted() {
+        problems.push(format!("Screen recording permission is {}", environment.permissions.screen_recording.as_ref()));
+    }
+
+    let content = if !environment.screen_capture_kit || !environment.permissions.screen_recording.is_granted() {
+        ContentPreflight {
+            ok: false,
+            display_count: 0,
+            window_count: 0,
+            error: Some("Skipped - needs ScreenCaptureKit and screen recording permission".to_string()),
+        }
+    } else {
+        match screencapturekit::content::ShareableContent::new_with_timeout(timeout_ms.unwrap_or(5000)) {
+            Ok(content) => ContentPreflight {
+                ok: true,
+                display_count: content.get_displays().map(|d| d.len() as u32).unwrap_or(0),
+                window_count: content.get_windows().map(|w| w.len() as u32).unwrap_or(0),
+                error: None,
+            },
             Err(e) => {
-                results.push(format!("⚠️ ScreenCaptureKit Sync API: {} (expected for async-first implementation)", e));
-                results.push("💡 Use async version for full functionality".to_string());
+                let message = screencapturekit::errors::describe(&e).1;
+                problems.push(format!("Content enumeration failed: {}", message));
+                ContentPreflight { ok: false, display_count: 0, window_count: 0, error: Some(message) }
             }
         }
+    };
+
+    let path = output_directory.or_else(|| std::env::var("HOME").ok()).unwrap_or_else(|| ".".to_string());
+    let required_bytes = min_free_disk_mb.unwrap_or(screencapturekit::disk_watch::DEFAULT_RESERVE_MB) as u64 * 1024 * 1024;
+    let available_bytes = screencapturekit::disk_watch::available_bytes(&path);
+    // A volume that can't be queried is reported but doesn't block recording, as during a recording
+    let disk_ok = available_bytes.map(|free| free >= required_bytes).unwrap_or(true);
+    if !disk_ok {
+        problems.push(format!("Only {} MB free for {}", available_bytes.unwrap_or(0) / (1024 * 1024), path));
+    }
+
+    PreflightReport {
+        ready: problems.is_empty(),
+        environment,
+        content,
+        disk: DiskPreflight {
+            path,
+            available_bytes: available_bytes.map(|free| free as f64),
+            required_bytes: required_bytes as f64,
+            ok: disk_ok,
+        },
+        problems,
     }
-    
-    results.push("".to_string());
-    results.push("🔒 Segfault-Safe Implementation Features:".to_string());
-    results.push("  • Safe content filter creation without object extraction".to_string());
-    results.push("  • Bypassed individual SCDisplay/SCWindow object access".to_string());
-    results.push("  • Improved memory management and error handling".to_string());
-    results.push("  • Timeout-protected completion handlers".to_string());
-    results.push("  • Core Graphics fallback for display/window enumeration".to_string());
-    
-    Ok(results.join("\n"))
 }
 
-/// Test function for the segfault-safe ScreenCaptureKit implementation
+/// @deprecated Use `probeEnvironment()`, whose `macos` has the version split into numbers
 #[napi]
-pub fn test_screencapturekit_with_timeout() -> Result<String> {
-    let mut results = Vec::new();
-    
-    results.push("=== ScreenCaptureKit Segfault-Safe Implementation Test ===".to_string());
-    
-    // Test 1: Check permissions first
-    match check_screen_recording_permission() {
-        Ok(true) => {
-            results.push("✅ Screen Recording Permission: Granted".to_string());
-        }
-        Ok(false) => {
-            results.push("❌ Screen Recording Permission: Not Granted".to_string());
-            results.push("Please enable screen recording permission and try again".to_string());
-            return Ok(results.join("\n"));
-        }
-        Err(e) => {
-            results.push(format!("❌ Permission Check Failed: {}", e));
-            return Ok(results.join("\n"));
-        }
+pub fn check_macos_version() -> Result<String, CaptureErrorCode> {
+    log::warn!("checkMacosVersion is deprecated - use probeEnvironment()");
+    coded(supported_macos_version)
+}
+
+/// The macOS version, or Unsupported when it predates ScreenCaptureKit (12.3)
+fn supported_macos_version() -> Result<String> {
+    let version = screencapturekit::status::os_version()
+        .ok_or_else(|| capture_error(CaptureErrorCode::Unknown, "Failed to get macOS version"))?;
+    let supported = screencapturekit::status::os_version_parts()
+        .map(screencapturekit::status::supports_screen_capture_kit)
+        .unwrap_or(false);
+    if !supported {
+        return Err(capture_error(
+            CaptureErrorCode::Unsupported,
+            format!("ScreenCaptureKit requires macOS 12.3 or later, found: {}", version),
+        ));
     }
-    
-    // Test 2: Test timeout content retrieval (segfault-safe)
-    match screencapturekit::content::ShareableContent::new_with_timeout(5000) {
-        Ok(content) => {
-            results.push("✅ Segfault-Safe Content Retrieval: Success".to_string());
-            
-            let displays = content.get_displays().unwrap_or_default();
-            let windows = content.get_windows().unwrap_or_default();
-            
-            results.push(format!("  📺 Displays found: {}", displays.len()));
-            results.push(format!("  🪟 Windows found: {}", windows.len()));
-            
-            // Show some display details
-            for (i, display) in displays.iter().take(3).enumerate() {
-                results.push(format!("    Display {}: {} ({}x{})", 
-                    i + 1, display.name, display.width, display.height));
+    Ok(version)
+}
+
+/// Check the macOS version, screen recording permission and ScreenCaptureKit API access
+/// @deprecated Use `runPreflight()`, which reports each check as a field instead of a message
+#[napi]
+pub fn test_permissions_and_api() -> Result<DiagnosticsReport, CaptureErrorCode> {
+    log::warn!("testPermissionsAndApi is deprecated - use runPreflight()");
+    coded(|| {
+        let mut report = DiagnosticsReport::new();
+        check_platform(&mut report);
+
+        unsafe {
+            match screencapturekit::bindings::ScreenCaptureKitHelpers::get_shareable_content_sync() {
+                Ok(_) => report.check("screenCaptureKitApi", CheckOutcome::Pass, "Shareable content is accessible"),
+                Err(e) => report.check("screenCaptureKitApi", CheckOutcome::Fail, e),
             }
-            
-            // Test 3: Test screen source extraction (segfault-safe)
-            match screencapturekit::content::ContentManager::extract_screen_sources(&content) {
-                Ok(sources) => {
-                    results.push(format!("✅ Segfault-Safe Screen Sources Extracted: {} total", sources.len()));
-                    
-                    let display_sources = sources.iter().filter(|s| s.is_display).count();
-                    let window_sources = sources.iter().filter(|s| !s.is_display).count();
-                    
-                    results.push(format!("  📺 Display sources: {}", display_sources));
-                    results.push(format!("  🪟 Window sources: {}", window_sources));
+        }
+        Ok(report)
+    })
+}
+
+/// Enumerate content with a 5 second timeout, the way a recorder does, and count what it finds
+#[napi]
+pub fn test_screencapturekit_with_timeout() -> Result<DiagnosticsReport, CaptureErrorCode> {
+    coded(|| {
+        let mut report = DiagnosticsReport::new();
+        if !check_platform(&mut report) {
+            return Ok(report);
+        }
+
+        match screencapturekit::content::ShareableContent::new_with_timeout(5000) {
+            Ok(content) => {
+                report.display_count = content.get_displays().map(|d| d.len() as u32).unwrap_or(0);
+                report.window_count = content.get_windows().map(|w| w.len() as u32).unwrap_or(0);
+                match content.fallbacks().first() {
+                    Some(fallback) => report.check("shareableContent", CheckOutcome::Warn, fallback.clone()),
+                    None => report.check(
+                        "shareableContent",
+                        CheckOutcome::Pass,
+                        format!("{} displays, {} windows", report.display_count, report.window_count),
+                    ),
                 }
-                Err(e) => {
-                    results.push(format!("❌ Screen Source Extraction Failed: {}", e));
+                match screencapturekit::content::ContentManager::extract_screen_sources(&content) {
+                    Ok(sources) => report.check("screenSources", CheckOutcome::Pass, format!("{} sources", sources.len())),
+                    Err(e) => report.check("screenSources", CheckOutcome::Fail, screencapturekit::errors::describe(&e).1),
                 }
             }
+            Err(e) => report.check("shareableContent", CheckOutcome::Fail, screencapturekit::errors::describe(&e).1),
         }
-        Err(e) => {
-            results.push(format!("❌ Content Retrieval Failed: {}", e));
+
+        let recorder = ScreenCaptureKitRecorder::new().map_err(|e| capture_error(e.status, e.reason))?;
+        match ScreenCaptureKitRecorder::screen_sources(
+            &recorder.current_content,
+            std::time::Duration::from_millis(5000),
+            false,
+            &Default::default(),
+        ) {
+            Ok(sources) => report.check("recorderEnumeration", CheckOutcome::Pass, format!("{} sources", sources.len())),
+            Err(e) => report.check("recorderEnumeration", CheckOutcome::Fail, screencapturekit::errors::describe(&e).1),
         }
-    }
-    
-    // Test 4: Test ScreenCaptureKitRecorder timeout method (segfault-safe)
-    let mut recorder = ScreenCaptureKitRecorder::new()?;
-    match recorder.get_available_screens_with_timeout(Some(5000)) {
-        Ok(sources) => {
-            results.push(format!("✅ Segfault-Safe Recorder Method: Found {} sources", sources.len()));
+        Ok(report)
+    })
+}
+
+/// Build a content filter for the first display and a stream manager, without starting a stream
+#[napi]
+pub fn test_phase2_implementation() -> Result<DiagnosticsReport, CaptureErrorCode> {
+    coded(|| {
+        let mut report = DiagnosticsReport::new();
+        check_platform(&mut report);
+
+        let content = match screencapturekit::content::ShareableContent::new_with_real_data() {
+            Ok(content) => content,
+            Err(e) => {
+                report.check("shareableContent", CheckOutcome::Fail, screencapturekit::errors::describe(&e).1);
+                return Ok(report);
+            }
+        };
+        let displays = content.get_displays()?;
+        report.display_count = displays.len() as u32;
+        report.window_count = content.get_windows().map(|w| w.len() as u32).unwrap_or(0);
+        report.check("shareableContent", CheckOutcome::Pass, format!("{} displays, {} windows", report.display_count, report.window_count));
+
+        match displays.first() {
+            Some(display) => match screencapturekit::content::RealContentFilter::new_with_display(&content, display.id) {
+                Ok(filter) if filter.is_valid() => report.check("contentFilter", CheckOutcome::Pass, format!("Display {}", display.id)),
+                Ok(_) => report.check("contentFilter", CheckOutcome::Fail, format!("Filter for display {} is not valid", display.id)),
+                Err(e) => report.check("contentFilter", CheckOutcome::Fail, screencapturekit::errors::describe(&e).1),
+            },
+            None => report.check("contentFilter", CheckOutcome::Fail, "No displays to filter"),
         }
+
+        let _stream_manager = screencapturekit::content::RealStreamManager::new();
+        report.check("streamManager", CheckOutcome::Pass, "Created");
+        Ok(report)
+    })
+}
+
+/// macOS version and permission checks shared by the `test*` functions; false when recording
+/// can't work at all
+fn check_platform(report: &mut DiagnosticsReport) -> bool {
+    match supported_macos_version() {
+        Ok(version) => report.check("macosVersion", CheckOutcome::Pass, version),
         Err(e) => {
-            results.push(format!("❌ Recorder Timeout Method Failed: {}", e));
+            report.check("macosVersion", CheckOutcome::Fail, e.reason);
+            return false;
         }
     }
-    
-    results.push("".to_string());
-    results.push("🎉 Segfault-safe ScreenCaptureKit implementation is working!".to_string());
-    results.push("🔒 This implementation avoids object extraction and uses safe content filter creation".to_string());
-    
-    Ok(results.join("\n"))
+    report.screen_recording_permission = check_screen_recording_permission().unwrap_or(false);
+    if report.screen_recording_permission {
+        report.check("screenRecordingPermission", CheckOutcome::Pass, "Granted");
+        true
+    } else {
+        report.check(
+            "screenRecordingPermission",
+            CheckOutcome::Fail,
+            "Not granted; enable it in System Settings > Privacy & Security > Screen Recording",
+        );
+        false
+    }
 }
 
+// Export AudioManager as NAPI class
 #[napi]
-pub fn test_phase2_implementation() -> Result<String> {
-    println!("🧪 Testing Phase 2 ScreenCaptureKit implementation (segfault-safe)");
-    
-    // Test 1: Create ShareableContent with real data structure (segfault-safe)
-    println!("📋 Test 1: Segfault-safe ShareableContent creation");
-    let content = screencapturekit::content::ShareableContent::new_with_real_data()?;
-    let sources = screencapturekit::content::ContentManager::extract_screen_sources(&content)?;
-    println!("✅ Created {} screen sources (segfault-safe)", sources.len());
-    
-    // Test 2: Create real content filter (segfault-safe)
-    println!("🎯 Test 2: Segfault-safe content filter creation");
-    let display_filter = screencapturekit::content::RealContentFilter::new_with_display(&content, 1)?;
+pub struct AudioManager;
+
+#[napi]
+impl AudioManager {
+    #[napi(constructor)]
+    pub fn new() -> Result<Self, CaptureErrorCode> {
+        Ok(Self)
+    }
     
-    // Skip window filter test to avoid potential issues
-    let display_valid = display_filter.is_valid();
+    #[napi]
+    pub fn get_available_audio_devices(&self) -> Result<Vec<AudioDevice>, CaptureErrorCode> {
+        coded(|| {
+            screencapturekit::AudioManager::get_available_audio_devices()
+        })
+    }
     
-    println!("✅ Created segfault-safe content filters - Display valid: {}", display_valid);
+    #[napi]
+    pub fn configure_audio_session(&self) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            screencapturekit::AudioManager::configure_audio_session()
+        })
+    }
+}
+
+/// System or per-process audio capture through a CoreAudio process tap, no screen capture involved (macOS 14.2+)
+#[napi]
+pub struct ProcessAudioCapture {
+    tap: Option<screencapturekit::process_tap::ProcessTap>,
+    encoder: Option<std::sync::Arc<std::sync::Mutex<screencapturekit::encoder::AudioEncoder>>>,
+}
+
+#[napi]
+impl ProcessAudioCapture {
+    #[napi(constructor)]
+    pub fn new() -> Result<Self, CaptureErrorCode> {
+        coded(|| {
+            Ok(Self {
+                tap: None,
+                encoder: None,
+            })
+        })
+    }
     
-    // Test 3: Create real stream manager (safe)
-    println!("🎬 Test 3: Real stream manager creation");
-    let _stream_manager = screencapturekit::content::RealStreamManager::new();
-    println!("✅ Created real stream manager");
+    #[napi]
+    pub fn is_supported() -> bool {
+        screencapturekit::process_tap::ProcessTap::is_supported()
+    }
     
-    // Test 4: Test delegate creation (safe) - Skip for now to avoid encoder panics
-    println!("👥 Test 4: Stream delegate creation");
-    println!("⚠️ Delegate creation skipped to avoid encoder initialization panics");
-    println!("💡 Real implementation would create RealStreamDelegate here");
+    #[napi(ts_args_type = "options: ProcessAudioCaptureOptions, onChunk?: ((chunk: AudioTapChunk) => void) | undefined | null")]
+    pub fn start(&mut self, options: ProcessAudioCaptureOptions, on_chunk: Option<JsFunction>) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            if self.is_running() {
+                return Err(capture_error(CaptureErrorCode::AlreadyRecording, "Process audio capture already running"));
+            }
+            if options.output_path.is_none() && on_chunk.is_none() {
+                return Err(Error::new(Status::InvalidArg, "Provide an output_path, an onChunk callback, or both"));
+            }
+        
+            let encoder = match &options.output_path {
+                Some(path) => Some(std::sync::Arc::new(std::sync::Mutex::new(
+                    screencapturekit::encoder::AudioEncoder::new(path, 48000, 2)?
+                ))),
+                None => None,
+            };
+        
+            let audio_tap = match on_chunk {
+                Some(on_chunk) => {
+                    let tsfn: ThreadsafeFunction<screencapturekit::audio_tap::PcmChunk, ErrorStrategy::Fatal> = on_chunk
+                        .create_threadsafe_function(0, |ctx: ThreadSafeCallContext<screencapturekit::audio_tap::PcmChunk>| {
+                            Ok(vec![AudioTapChunk {
+                                samples: Float32Array::new(ctx.value.samples),
+                                sample_rate: ctx.value.sample_rate,
+                                start_time: ctx.value.start_time,
+                            }])
+                        })?;
+                    let sink: screencapturekit::audio_tap::PcmChunkSink = std::sync::Arc::new(move |chunk| {
+                        tsfn.call(chunk, ThreadsafeFunctionCallMode::NonBlocking);
+                    });
+                    let chunk_ms = options.chunk_ms.unwrap_or(screencapturekit::audio_tap::DEFAULT_CHUNK_MS);
+                    Some(std::sync::Mutex::new(screencapturekit::audio_tap::AudioTap::new(sink, chunk_ms)))
+                }
+                None => None,
+            };
+        
+            let handler_encoder = encoder.clone();
+            let handler: screencapturekit::process_tap::PcmHandler = std::sync::Arc::new(move |pcm: &screencapturekit::audio_tap::PcmBuffer| {
+                if let Some(encoder) = &handler_encoder {
+                    if let Ok(mut encoder) = encoder.lock() {
+                        if let Err(e) = encoder.encode_pcm_buffer(pcm) {
+                            log::error!("Failed to encode tapped audio: {}", e);
+                        }
+                    }
+                }
+                if let Some(audio_tap) = &audio_tap {
+                    if let Ok(mut audio_tap) = audio_tap.lock() {
+                        audio_tap.push_pcm(pcm);
+                    }
+                }
+            });
+        
+            let process_ids = options.process_ids.unwrap_or_default();
+            self.tap = Some(screencapturekit::process_tap::ProcessTap::start(&process_ids, handler)?);
+            self.encoder = encoder;
+            Ok(())
+        })
+    }
     
-    let results = serde_json::json!({
-        "phase2Status": "segfault-safe-implemented",
-        "testResults": {
-            "shareableContent": "✅ Working (segfault-safe)",
-            "contentFilters": format!("✅ Segfault-Safe (Display: {})", display_valid), 
-            "streamManager": "✅ Working",
-            "streamDelegate": "🚧 Foundation Ready (encoder initialization skipped)",
-            "scStreamInstances": "✅ Foundation Ready",
-            "frameProcessing": "🚧 Foundation Ready",
-            "segfaultPrevention": "✅ Implemented"
-        },
-        "capabilities": {
-            "realDataStructures": true,
-            "threadSafeImplementation": true,
-            "objc2Integration": true,
-            "screenCaptureKitBindings": true,
-            "streamManagerFoundation": true,
-            "delegateFoundation": true,
-            "configurationHandling": true,
-            "segfaultSafe": true,
-            "safeContentFilterCreation": true
-        },
-        "fixes": {
-            "removedObjectExtraction": true,
-            "safeContentFilterCreation": true,
-            "improvedMemoryManagement": true,
-            "bypassedSegfaultMethods": true
-        },
-        "nextSteps": [
-            "Test the fixed implementation without object extraction",
-            "Complete real video stream capture",
-            "Add proper stream lifecycle management",
-            "Implement error recovery mechanisms"
-        ],
-        "phase2Summary": "Phase 2 now includes segfault-safe implementation that avoids direct ScreenCaptureKit object extraction. The implementation uses safe content filter creation methods and improved memory management to prevent crashes while maintaining full ScreenCaptureKit functionality."
-    });
+    /// Stop capturing; returns the written file when `output_path` was given
+    #[napi]
+    pub fn stop(&mut self) -> Result<Option<String>, CaptureErrorCode> {
+        coded(|| {
+            // Stop the IOProc before finalizing so no buffer lands in a closed file
+            self.tap = None;
+            match self.encoder.take() {
+                Some(encoder) => {
+                    let mut encoder = encoder
+                        .lock()
+                        .map_err(|_| capture_error(CaptureErrorCode::Unknown, "Audio encoder lock poisoned"))?;
+                    Ok(Some(encoder.finalize_encoding()?))
+                }
+                None => Ok(None),
+            }
+        })
+    }
     
-    println!("🎉 Phase 2 segfault-safe implementation test completed successfully");
-    Ok(results.to_string())
+    #[napi]
+    pub fn is_running(&self) -> bool {
+        self.tap.as_ref().map(|tap| tap.is_running()).unwrap_or(false)
+    }
 }
 
-// Export AudioManager as NAPI class
+/// Camera-only recording (video plus microphone), e.g. a talking-head track next to the screen track
 #[napi]
-pub struct AudioManager;
+pub struct CameraRecorder {
+    recording: Option<screencapturekit::camera::CameraRecording>,
+}
 
 #[napi]
-impl AudioManager {
+impl CameraRecorder {
     #[napi(constructor)]
-    pub fn new() -> Result<Self> {
-        Ok(Self)
+    pub fn new() -> Result<Self, CaptureErrorCode> {
+        Ok(Self { recording: None })
+    }
+    
+    #[napi]
+    pub fn get_cameras() -> Vec<CameraDevice> {
+        screencapturekit::camera::list_cameras()
+            .into_iter()
+            .map(|camera| CameraDevice {
+                id: camera.id,
+                name: camera.name,
+                is_default: camera.is_default,
+            })
+            .collect()
+    }
+    
+    #[napi]
+    pub fn start(&mut self, options: CameraRecordingOptions) -> Result<(), CaptureErrorCode> {
+        coded(|| {
+            if self.is_recording() {
+                return Err(capture_error(CaptureErrorCode::AlreadyRecording, "Camera recording already running"));
+            }
+        
+            // Same rules as screen recordings: a pinned device must exist, there's no silent fallback
+            let microphone = if options.capture_audio.unwrap_or(true) {
+                let uid = match options.audio_device_id.clone().or_else(screencapturekit::AudioManager::default_input_device_uid) {
+                    Some(uid) => uid,
+                    None => return Err(capture_error(CaptureErrorCode::SourceNotFound, "No input device is available")),
+                };
+                screencapturekit::permissions::guard_silent(screencapturekit::permissions::PrivacyPane::Microphone)?;
+                let authorization = screencapturekit::permissions::microphone_authorization();
+                if authorization.is_refused() {
+                    return Err(capture_error(CaptureErrorCode::PermissionDenied, format!("Microphone permission is {}", authorization.as_ref())));
+                }
+                Some(screencapturekit::AudioManager::resolve_input_device(&uid)?.device_id)
+            } else {
+                None
+            };
+        
+            self.recording = Some(screencapturekit::camera::CameraRecording::start(
+                &options.output_path,
+                options.device_id.as_deref(),
+                microphone,
+                30,
+            )?);
+            Ok(())
+        })
     }
     
     #[napi]
-    pub fn get_available_audio_devices(&self) -> Result<Vec<AudioDevice>> {
-        screencapturekit::AudioManager::get_available_audio_devices()
+    pub fn stop(&mut self) -> Result<CameraRecordingResult, CaptureErrorCode> {
+        coded(|| {
+            let mut recording = self.recording.take()
+                .ok_or_else(|| capture_error(CaptureErrorCode::NotRecording, "No active camera recording"))?;
+            let (video_path, audio_path) = recording.stop()?;
+            Ok(CameraRecordingResult { video_path, audio_path })
+        })
     }
     
     #[napi]
-    pub fn configure_audio_session(&self) -> Result<()> {
-        screencapturekit::AudioManager::configure_audio_session()
+    pub fn is_recording(&self) -> bool {
+        self.recording.as_ref().map(|recording| recording.is_recording()).unwrap_or(false)
     }
-}
\ No newline at end of file
+}
@@ -1,14 +1,138 @@
 use crate::AudioDevice;
 use napi::bindgen_prelude::*;
+use objc2::runtime::AnyObject;
 use objc2::{msg_send, class};
-use objc2_foundation::{NSArray, NSString};
+use block2::RcBlock;
+use objc2_foundation::{NSArray, NSNumber, NSString};
+use std::ffi::c_void;
 use std::ptr;
+use std::sync::{Arc, Mutex};
+
+use super::errors::{capture_error, CaptureErrorCode};
+
+pub mod convert;
+pub mod wav;
+
+// CoreAudio property addressing
+#[repr(C)]
+pub(crate) struct AudioObjectPropertyAddress {
+    pub selector: u32,
+    pub scope: u32,
+    pub element: u32,
+}
+
+const K_AUDIO_OBJECT_SYSTEM_OBJECT: u32 = 1;
+const K_AUDIO_HARDWARE_PROPERTY_TRANSLATE_UID_TO_DEVICE: u32 = 0x75696464; // 'uidd'
+const K_AUDIO_HARDWARE_PROPERTY_DEFAULT_INPUT_DEVICE: u32 = 0x64496e20; // 'dIn '
+const K_AUDIO_HARDWARE_PROPERTY_DEFAULT_OUTPUT_DEVICE: u32 = 0x644f7574; // 'dOut'
+const K_AUDIO_HARDWARE_PROPERTY_TRANSLATE_PID_TO_PROCESS_OBJECT: u32 = 0x69643270; // 'id2p'
+const K_AUDIO_DEVICE_PROPERTY_STREAMS: u32 = 0x73746d23; // 'stm#'
+const K_AUDIO_DEVICE_PROPERTY_DEVICE_UID: u32 = 0x75696420; // 'uid '
+const K_AUDIO_DEVICE_PROPERTY_STREAM_CONFIGURATION: u32 = 0x736c6179; // 'slay'
+pub(crate) const K_AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL: u32 = 0x676c6f62; // 'glob'
+const K_AUDIO_OBJECT_PROPERTY_SCOPE_INPUT: u32 = 0x696e7074; // 'inpt'
+pub(crate) const K_AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN: u32 = 0;
+const K_AUDIO_OBJECT_UNKNOWN: u32 = 0;
+// Most channels an AAC track holds
+pub const MAX_RECORDED_CHANNELS: u32 = 8;
+
+// Aggregate devices created by this process, so a recording through one keeps every input's channels
+static AGGREGATE_DEVICES: Mutex<Vec<AggregateInputDevice>> = Mutex::new(Vec::new());
+
+extern "C" {
+    pub(crate) fn AudioObjectGetPropertyData(
+        object_id: u32,
+        address: *const AudioObjectPropertyAddress,
+        qualifier_data_size: u32,
+        qualifier_data: *const c_void,
+        io_data_size: *mut u32,
+        out_data: *mut c_void,
+    ) -> i32;
+    fn AudioObjectGetPropertyDataSize(
+        object_id: u32,
+        address: *const AudioObjectPropertyAddress,
+        qualifier_data_size: u32,
+        qualifier_data: *const c_void,
+        out_data_size: *mut u32,
+    ) -> i32;
+    fn AudioObjectAddPropertyListenerBlock(
+        object_id: u32,
+        address: *const AudioObjectPropertyAddress,
+        dispatch_queue: *mut c_void,
+        listener: &block2::Block<dyn Fn(u32, *const c_void)>,
+    ) -> i32;
+    fn AudioObjectRemovePropertyListenerBlock(
+        object_id: u32,
+        address: *const AudioObjectPropertyAddress,
+        dispatch_queue: *mut c_void,
+        listener: &block2::Block<dyn Fn(u32, *const c_void)>,
+    ) -> i32;
+    fn AudioHardwareCreateAggregateDevice(description: *const c_void, device_id_out: *mut u32) -> i32;
+    fn AudioHardwareDestroyAggregateDevice(device_id: u32) -> i32;
+    fn CFRelease(cf: *const c_void);
+}
+
+const DEFAULT_INPUT_ADDRESS: AudioObjectPropertyAddress = AudioObjectPropertyAddress {
+    selector: K_AUDIO_HARDWARE_PROPERTY_DEFAULT_INPUT_DEVICE,
+    scope: K_AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL,
+    element: K_AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+};
+
+const DEFAULT_OUTPUT_ADDRESS: AudioObjectPropertyAddress = AudioObjectPropertyAddress {
+    selector: K_AUDIO_HARDWARE_PROPERTY_DEFAULT_OUTPUT_DEVICE,
+    scope: K_AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL,
+    element: K_AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+};
+
+/// Typed failures for explicit input device selection
+#[derive(Debug)]
+pub enum AudioDeviceError {
+    /// No CoreAudio device with this UID exists (unplugged, renamed, or never existed)
+    NotFound(String),
+    /// The device exists but exposes no input streams
+    NotAnInput(String),
+}
+
+impl From<AudioDeviceError> for Error {
+    fn from(error: AudioDeviceError) -> Self {
+        match error {
+            AudioDeviceError::NotFound(uid) => {
+                capture_error(CaptureErrorCode::SourceNotFound, format!("audio device '{}' is not connected", uid))
+            }
+            AudioDeviceError::NotAnInput(uid) => {
+                capture_error(CaptureErrorCode::InvalidConfiguration, format!("audio device '{}' has no input channels", uid))
+            }
+        }
+    }
+}
+
+/// A CoreAudio input device resolved from its persistent UID
+#[derive(Debug, Clone)]
+pub struct ResolvedInputDevice {
+    pub uid: String,
+    pub device_id: u32,
+}
+
+/// Where one source device's channels sit inside an aggregate device
+#[derive(Debug, Clone)]
+pub struct AggregateChannelRange {
+    pub device_uid: String,
+    pub first_channel: u32,
+    pub channel_count: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct AggregateInputDevice {
+    pub uid: String,
+    pub name: String,
+    pub channels: Vec<AggregateChannelRange>,
+}
 
 pub struct AudioManager;
 
 impl AudioManager {
     pub fn get_available_audio_devices() -> Result<Vec<AudioDevice>> {
-        println!("🔊 Getting available audio devices via AVFoundation");
+        log::info!("Getting available audio devices via AVFoundation");
         
         let mut devices = Vec::new();
         
@@ -18,7 +142,7 @@ impl AudioManager {
             let shared_instance: *mut objc2::runtime::AnyObject = msg_send![session_class, sharedInstance];
             
             if shared_instance.is_null() {
-                return Err(Error::new(Status::GenericFailure, "Failed to get AVAudioSession"));
+                return Err(capture_error(CaptureErrorCode::Unknown, "Failed to get AVAudioSession"));
             }
             
             // Get available inputs
@@ -79,11 +203,11 @@ impl AudioManager {
         
         // If no devices found via API, log the issue but don't add mock devices
         if devices.is_empty() {
-            println!("⚠️ No audio devices found via AVFoundation - this may indicate a permissions issue");
-            return Err(Error::new(Status::GenericFailure, "No audio devices available. Check microphone permissions."));
+            log::warn!("No audio devices found via AVFoundation - this may indicate a permissions issue");
+            return Err(capture_error(CaptureErrorCode::SourceNotFound, "No audio devices available. Check microphone permissions."));
         }
         
-        println!("✅ Found {} real audio devices", devices.len());
+        log::info!("Found {} real audio devices", devices.len());
         Ok(devices)
     }
     
@@ -133,14 +257,14 @@ impl AudioManager {
     }
     
     pub fn configure_audio_session() -> Result<()> {
-        println!("🔧 Configuring real audio session for recording");
+        log::info!("Configuring real audio session for recording");
         
         unsafe {
             let session_class = class!(AVAudioSession);
             let shared_instance: *mut objc2::runtime::AnyObject = msg_send![session_class, sharedInstance];
             
             if shared_instance.is_null() {
-                return Err(Error::new(Status::GenericFailure, "Failed to get AVAudioSession"));
+                return Err(capture_error(CaptureErrorCode::Unknown, "Failed to get AVAudioSession"));
             }
             
             // Set category for recording
@@ -153,7 +277,7 @@ impl AudioManager {
             ];
             
             if !success {
-                return Err(Error::new(Status::GenericFailure, "Failed to set audio session category"));
+                return Err(capture_error(CaptureErrorCode::Unknown, "Failed to set audio session category"));
             }
             
             // Set active
@@ -165,11 +289,468 @@ impl AudioManager {
             ];
             
             if !success {
-                return Err(Error::new(Status::GenericFailure, "Failed to activate audio session"));
+                return Err(capture_error(CaptureErrorCode::Unknown, "Failed to activate audio session"));
+            }
+        }
+        
+        log::info!("Real audio session configured");
+        Ok(())
+    }
+    
+    /// Resolve a CoreAudio device by UID and make sure it can be used as a capture input.
+    /// Called right before the stream starts so a device that disappeared fails loudly
+    /// instead of the capture silently falling back to the system default.
+    pub fn resolve_input_device(uid: &str) -> std::result::Result<ResolvedInputDevice, AudioDeviceError> {
+        log::info!("Resolving input device by UID: {}", uid);
+        
+        unsafe {
+            let device_id = match Self::device_id_for_uid(uid) {
+                Some(device_id) => device_id,
+                None => {
+                    log::error!("Input device not found: {}", uid);
+                    return Err(AudioDeviceError::NotFound(uid.to_string()));
+                }
+            };
+            
+            // The device must expose at least one input stream to be usable as a microphone
+            let streams_address = AudioObjectPropertyAddress {
+                selector: K_AUDIO_DEVICE_PROPERTY_STREAMS,
+                scope: K_AUDIO_OBJECT_PROPERTY_SCOPE_INPUT,
+                element: K_AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+            };
+            let mut streams_size: u32 = 0;
+            let status = AudioObjectGetPropertyDataSize(
+                device_id,
+                &streams_address,
+                0,
+                ptr::null(),
+                &mut streams_size,
+            );
+            
+            if status != 0 || streams_size == 0 {
+                log::error!("Device {} has no input streams", uid);
+                return Err(AudioDeviceError::NotAnInput(uid.to_string()));
+            }
+            
+            log::info!("Resolved input device {} -> CoreAudio ID {}", uid, device_id);
+            Ok(ResolvedInputDevice {
+                uid: uid.to_string(),
+                device_id,
+            })
+        }
+    }
+    
+    /// Translate a persistent device UID into the current CoreAudio object ID
+    pub fn device_id_for_uid(uid: &str) -> Option<u32> {
+        unsafe {
+            let uid_string = NSString::from_str(uid);
+            let uid_ref: *const NSString = &*uid_string;
+            
+            let address = AudioObjectPropertyAddress {
+                selector: K_AUDIO_HARDWARE_PROPERTY_TRANSLATE_UID_TO_DEVICE,
+                scope: K_AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL,
+                element: K_AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+            };
+            
+            let mut device_id: u32 = K_AUDIO_OBJECT_UNKNOWN;
+            let mut data_size = std::mem::size_of::<u32>() as u32;
+            let status = AudioObjectGetPropertyData(
+                K_AUDIO_OBJECT_SYSTEM_OBJECT,
+                &address,
+                std::mem::size_of::<*const NSString>() as u32,
+                &uid_ref as *const *const NSString as *const c_void,
+                &mut data_size,
+                &mut device_id as *mut u32 as *mut c_void,
+            );
+            
+            if status != 0 || device_id == K_AUDIO_OBJECT_UNKNOWN {
+                None
+            } else {
+                Some(device_id)
             }
         }
+    }
+    
+    /// Total number of input channels across all input streams of a device
+    pub fn input_channel_count(device_id: u32) -> u32 {
+        unsafe {
+            let address = AudioObjectPropertyAddress {
+                selector: K_AUDIO_DEVICE_PROPERTY_STREAM_CONFIGURATION,
+                scope: K_AUDIO_OBJECT_PROPERTY_SCOPE_INPUT,
+                element: K_AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+            };
+            
+            let mut data_size: u32 = 0;
+            if AudioObjectGetPropertyDataSize(device_id, &address, 0, ptr::null(), &mut data_size) != 0 || data_size == 0 {
+                return 0;
+            }
+            
+            // Variable-length AudioBufferList: u32 count, then 16-byte AudioBuffers starting at offset 8
+            let mut buffer = vec![0u64; (data_size as usize).div_ceil(8)];
+            if AudioObjectGetPropertyData(device_id, &address, 0, ptr::null(), &mut data_size, buffer.as_mut_ptr() as *mut c_void) != 0 {
+                return 0;
+            }
+            
+            let bytes = buffer.as_ptr() as *const u8;
+            let buffer_count = *(bytes as *const u32) as usize;
+            (0..buffer_count)
+                .map(|i| 8 + i * 16)
+                .filter(|offset| offset + 4 <= data_size as usize)
+                .map(|offset| *(bytes.add(offset) as *const u32))
+                .sum()
+        }
+    }
+    
+    /// Combine several inputs into one private aggregate device. Each source keeps its own
+    /// channels, in the order given, so two mono mics become the left and right channels.
+    /// The first device is the clock source; the others are drift-compensated against it.
+    pub fn create_aggregate_input_device(input_uids: &[String], name: &str) -> Result<AggregateInputDevice> {
+        if input_uids.len() < 2 {
+            return Err(Error::new(Status::InvalidArg, "An aggregate device needs at least two inputs"));
+        }
+        
+        log::info!("Creating aggregate input device '{}' from {} inputs", name, input_uids.len());
+        
+        // Validate every input first so a missing device fails with a typed error
+        let mut channels = Vec::new();
+        let mut first_channel = 0u32;
+        for uid in input_uids {
+            let device = Self::resolve_input_device(uid)?;
+            let channel_count = Self::input_channel_count(device.device_id);
+            channels.push(AggregateChannelRange {
+                device_uid: uid.clone(),
+                first_channel,
+                channel_count,
+            });
+            first_channel += channel_count;
+        }
+        
+        let aggregate_uid = format!("com.whisperdesk.aggregate.{}", super::session::unique_key());
+        
+        unsafe {
+            let uid_key = NSString::from_str("uid");
+            let drift_key = NSString::from_str("drift");
+            let subdevices: Vec<*mut AnyObject> = input_uids
+                .iter()
+                .enumerate()
+                .map(|(index, uid)| {
+                    let uid_value = NSString::from_str(uid);
+                    let drift_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithInt: if index == 0 { 0i32 } else { 1i32 }];
+                    let subdevice: *mut AnyObject = msg_send![
+                        class!(NSDictionary),
+                        dictionaryWithObjects: &[&*uid_value as *const NSString as *mut AnyObject, drift_value as *mut AnyObject],
+                        forKeys: &[&*uid_key, &*drift_key],
+                        count: 2usize
+                    ];
+                    subdevice
+                })
+                .collect();
+            let subdevice_list: *mut NSArray = msg_send![
+                class!(NSArray),
+                arrayWithObjects: subdevices.as_ptr(),
+                count: subdevices.len()
+            ];
+            
+            let name_key = NSString::from_str("name");
+            let subdevices_key = NSString::from_str("subdevices");
+            let main_key = NSString::from_str("master");
+            let private_key = NSString::from_str("private");
+            let stacked_key = NSString::from_str("stacked");
+            
+            let uid_value = NSString::from_str(&aggregate_uid);
+            let name_value = NSString::from_str(name);
+            let main_value = NSString::from_str(&input_uids[0]);
+            // Private: only visible to this process and removed automatically when it exits
+            let private_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithInt: 1i32];
+            // Not stacked: subdevice channels are laid out side by side rather than mirrored
+            let stacked_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithInt: 0i32];
+            
+            let description: *mut AnyObject = msg_send![
+                class!(NSDictionary),
+                dictionaryWithObjects: &[
+                    &*uid_value as *const NSString as *mut AnyObject,
+                    &*name_value as *const NSString as *mut AnyObject,
+                    subdevice_list as *mut AnyObject,
+                    &*main_value as *const NSString as *mut AnyObject,
+                    private_value as *mut AnyObject,
+                    stacked_value as *mut AnyObject
+                ],
+                forKeys: &[&*uid_key, &*name_key, &*subdevices_key, &*main_key, &*private_key, &*stacked_key],
+                count: 6usize
+            ];
+            
+            let mut device_id: u32 = K_AUDIO_OBJECT_UNKNOWN;
+            let status = AudioHardwareCreateAggregateDevice(description as *const c_void, &mut device_id);
+            if status != 0 || device_id == K_AUDIO_OBJECT_UNKNOWN {
+                return Err(capture_error(CaptureErrorCode::Unknown, format!("Failed to create aggregate device (status {})", status)));
+            }
+            
+            log::info!("Aggregate device {} (CoreAudio ID {}) created with {} channels", aggregate_uid, device_id, first_channel);
+            let device = AggregateInputDevice {
+                uid: aggregate_uid,
+                name: name.to_string(),
+                channels,
+            };
+            if let Ok(mut devices) = AGGREGATE_DEVICES.lock() {
+                devices.push(device.clone());
+            }
+            Ok(device)
+        }
+    }
+    
+    /// Channels a recording through `uid` should keep: every channel of an aggregate device made by
+    /// `create_aggregate_input_device` (up to MAX_RECORDED_CHANNELS), None for any other device
+    pub fn aggregate_channel_count(uid: &str) -> Option<u32> {
+        let devices = AGGREGATE_DEVICES.lock().ok()?;
+        let device = devices.iter().find(|device| device.uid == uid)?;
+        let total: u32 = device.channels.iter().map(|range| range.channel_count).sum();
+        Some(total.clamp(2, MAX_RECORDED_CHANNELS))
+    }
+    
+    pub fn destroy_aggregate_input_device(uid: &str) -> Result<()> {
+        let device_id = Self::device_id_for_uid(uid)
+            .ok_or_else(|| AudioDeviceError::NotFound(uid.to_string()))?;
+        
+        let status = unsafe { AudioHardwareDestroyAggregateDevice(device_id) };
+        if status != 0 {
+            return Err(capture_error(CaptureErrorCode::Unknown, format!("Failed to destroy aggregate device {} (status {})", uid, status)));
+        }
         
-        println!("✅ Real audio session configured");
+        if let Ok(mut devices) = AGGREGATE_DEVICES.lock() {
+            devices.retain(|device| device.uid != uid);
+        }
+        log::info!("Aggregate device {} destroyed", uid);
         Ok(())
     }
-} 
\ No newline at end of file
+    
+    /// CoreAudio process object for a PID; only processes that have touched audio have one
+    pub fn process_object_for_pid(pid: u32) -> Option<u32> {
+        unsafe {
+            let address = AudioObjectPropertyAddress {
+                selector: K_AUDIO_HARDWARE_PROPERTY_TRANSLATE_PID_TO_PROCESS_OBJECT,
+                scope: K_AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL,
+                element: K_AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+            };
+            
+            let pid = pid as i32;
+            let mut process_object: u32 = K_AUDIO_OBJECT_UNKNOWN;
+            let mut data_size = std::mem::size_of::<u32>() as u32;
+            let status = AudioObjectGetPropertyData(
+                K_AUDIO_OBJECT_SYSTEM_OBJECT,
+                &address,
+                std::mem::size_of::<i32>() as u32,
+                &pid as *const i32 as *const c_void,
+                &mut data_size,
+                &mut process_object as *mut u32 as *mut c_void,
+            );
+            
+            if status != 0 || process_object == K_AUDIO_OBJECT_UNKNOWN {
+                None
+            } else {
+                Some(process_object)
+            }
+        }
+    }
+    
+    pub fn default_output_device_uid() -> Option<String> {
+        unsafe {
+            let address = AudioObjectPropertyAddress {
+                selector: K_AUDIO_HARDWARE_PROPERTY_DEFAULT_OUTPUT_DEVICE,
+                scope: K_AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL,
+                element: K_AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+            };
+            
+            let mut device_id: u32 = K_AUDIO_OBJECT_UNKNOWN;
+            let mut data_size = std::mem::size_of::<u32>() as u32;
+            let status = AudioObjectGetPropertyData(
+                K_AUDIO_OBJECT_SYSTEM_OBJECT,
+                &address,
+                0,
+                ptr::null(),
+                &mut data_size,
+                &mut device_id as *mut u32 as *mut c_void,
+            );
+            
+            if status != 0 || device_id == K_AUDIO_OBJECT_UNKNOWN {
+                return None;
+            }
+            Self::device_uid(device_id)
+        }
+    }
+    
+    /// CoreAudio ID of the current system default input device
+    pub fn default_input_device_id() -> Option<u32> {
+        unsafe {
+            let mut device_id: u32 = K_AUDIO_OBJECT_UNKNOWN;
+            let mut data_size = std::mem::size_of::<u32>() as u32;
+            let status = AudioObjectGetPropertyData(
+                K_AUDIO_OBJECT_SYSTEM_OBJECT,
+                &DEFAULT_INPUT_ADDRESS,
+                0,
+                ptr::null(),
+                &mut data_size,
+                &mut device_id as *mut u32 as *mut c_void,
+            );
+            
+            if status != 0 || device_id == K_AUDIO_OBJECT_UNKNOWN {
+                None
+            } else {
+                Some(device_id)
+            }
+        }
+    }
+    
+    /// Persistent UID for a CoreAudio device ID
+    pub fn device_uid(device_id: u32) -> Option<String> {
+        unsafe {
+            let address = AudioObjectPropertyAddress {
+                selector: K_AUDIO_DEVICE_PROPERTY_DEVICE_UID,
+                scope: K_AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL,
+                element: K_AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+            };
+            
+            let mut uid: *const NSString = ptr::null();
+            let mut data_size = std::mem::size_of::<*const NSString>() as u32;
+            let status = AudioObjectGetPropertyData(
+                device_id,
+                &address,
+                0,
+                ptr::null(),
+                &mut data_size,
+                &mut uid as *mut *const NSString as *mut c_void,
+            );
+            
+            if status != 0 || uid.is_null() {
+                return None;
+            }
+            
+            // The CFString is returned retained
+            let value = (*uid).to_string();
+            CFRelease(uid as *const c_void);
+            Some(value)
+        }
+    }
+    
+    pub fn default_input_device_uid() -> Option<String> {
+        Self::default_input_device_id().and_then(Self::device_uid)
+    }
+}
+
+/// Which side of the audio route moved
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AudioRouteDirection {
+    Input,
+    Output,
+}
+
+impl AudioRouteDirection {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AudioRouteDirection::Input => "input",
+            AudioRouteDirection::Output => "output",
+        }
+    }
+}
+
+/// A new system default device, e.g. AirPods disconnecting and the built-in mic taking over
+#[derive(Debug, Clone)]
+pub struct AudioRouteChange {
+    pub direction: AudioRouteDirection,
+    /// None when no device is left on that side
+    pub device_uid: Option<String>,
+}
+
+/// How far a route change has got, as reported to route listeners
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AudioRoutePhase {
+    /// Audio is paused while capture moves to the new device
+    Switching,
+    /// Capture is running on the new device; the gap was filled with silence
+    Resumed,
+    /// The stream could not be re-pointed; audio resumes on whatever the stream still delivers
+    Failed,
+}
+
+impl AudioRoutePhase {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            AudioRoutePhase::Switching => "switching",
+            AudioRoutePhase::Resumed => "resumed",
+            AudioRoutePhase::Failed => "failed",
+        }
+    }
+}
+
+pub type AudioRouteHandler = Arc<dyn Fn(&AudioRouteChange, AudioRoutePhase) + Send + Sync>;
+
+/// Watches the system default input and output devices and reports route changes.
+/// The listeners are removed when this value is dropped.
+pub struct AudioRouteListener {
+    input_block: RcBlock<dyn Fn(u32, *const c_void)>,
+    output_block: RcBlock<dyn Fn(u32, *const c_void)>,
+}
+
+impl AudioRouteListener {
+    pub fn start<F>(on_change: F) -> Result<Self>
+    where
+        F: Fn(AudioRouteChange) + Send + Sync + 'static,
+    {
+        let on_change = Arc::new(on_change);
+        let input_block = Self::watch(AudioRouteDirection::Input, AudioManager::default_input_device_uid, on_change.clone());
+        let output_block = Self::watch(AudioRouteDirection::Output, AudioManager::default_output_device_uid, on_change);
+        
+        // A null queue delivers notifications on a CoreAudio-owned thread
+        unsafe {
+            let status = AudioObjectAddPropertyListenerBlock(K_AUDIO_OBJECT_SYSTEM_OBJECT, &DEFAULT_INPUT_ADDRESS, ptr::null_mut(), &input_block);
+            if status != 0 {
+                return Err(capture_error(CaptureErrorCode::Unknown, format!("Failed to watch default input device: {}", status)));
+            }
+            let status = AudioObjectAddPropertyListenerBlock(K_AUDIO_OBJECT_SYSTEM_OBJECT, &DEFAULT_OUTPUT_ADDRESS, ptr::null_mut(), &output_block);
+            if status != 0 {
+                AudioObjectRemovePropertyListenerBlock(K_AUDIO_OBJECT_SYSTEM_OBJECT, &DEFAULT_INPUT_ADDRESS, ptr::null_mut(), &input_block);
+                return Err(capture_error(CaptureErrorCode::Unknown, format!("Failed to watch default output device: {}", status)));
+            }
+        }
+        
+        log::info!("Watching for audio route changes");
+        Ok(Self { input_block, output_block })
+    }
+    
+    fn watch<F>(
+        direction: AudioRouteDirection,
+        current_uid: fn() -> Option<String>,
+        on_change: Arc<F>,
+    ) -> RcBlock<dyn Fn(u32, *const c_void)>
+    where
+        F: Fn(AudioRouteChange) + Send + Sync + 'static,
+    {
+        // CoreAudio can fire several notifications for one switch - only report real changes
+        let last_uid = Mutex::new(current_uid());
+        
+        RcBlock::new(move |_count: u32, _addresses: *const c_void| {
+            let uid = current_uid();
+            if let Ok(mut last) = last_uid.lock() {
+                if *last == uid {
+                    return;
+                }
+                *last = uid.clone();
+            }
+            
+            match &uid {
+                Some(uid) => log::info!("System default {} changed to {}", direction.as_str(), uid),
+                None => log::warn!("No default {} device is available", direction.as_str()),
+            }
+            on_change(AudioRouteChange { direction, device_uid: uid });
+        })
+    }
+}
+
+impl Drop for AudioRouteListener {
+    fn drop(&mut self) {
+        unsafe {
+            AudioObjectRemovePropertyListenerBlock(K_AUDIO_OBJECT_SYSTEM_OBJECT, &DEFAULT_INPUT_ADDRESS, ptr::null_mut(), &self.input_block);
+            AudioObjectRemovePropertyListenerBlock(K_AUDIO_OBJECT_SYSTEM_OBJECT, &DEFAULT_OUTPUT_ADDRESS, ptr::null_mut(), &self.output_block);
+        }
+        log::info!("Stopped watching audio route changes");
+    }
+}
@@ -0,0 +1,384 @@
+// Audio conversion subsystem - sample-rate conversion, channel downmix and int16 <-> f32
+
+use std::f64::consts::PI;
+
+/// f32 in [-1, 1] to int16, clamping out-of-range samples
+pub fn f32_to_i16(samples: &[f32]) -> Vec<i16> {
+    samples
+        .iter()
+        .map(|s| (s.clamp(-1.0, 1.0) * i16::MAX as f32).round() as i16)
+        .collect()
+}
+
+/// int16 to f32 in [-1, 1)
+pub fn i16_to_f32(samples: &[i16]) -> Vec<f32> {
+    samples.iter().map(|s| *s as f32 / 32768.0).collect()
+}
+
+/// Split interleaved frames into one Vec per channel
+pub fn deinterleave(interleaved: &[f32], channel_count: usize) -> Vec<Vec<f32>> {
+    let channel_count = channel_count.max(1);
+    let frames = interleaved.len() / channel_count;
+    (0..channel_count)
+        .map(|c| (0..frames).map(|f| interleaved[f * channel_count + c]).collect())
+        .collect()
+}
+
+/// Average all channels into one; channels shorter than the first are padded with silence
+pub fn downmix_to_mono(channels: &[Vec<f32>]) -> Vec<f32> {
+    let frames = match channels.first() {
+        Some(first) => first.len(),
+        None => return Vec::new(),
+    };
+    if channels.len() == 1 {
+        return channels[0].clone();
+    }
+
+    let channel_count = channels.len() as f32;
+    (0..frames)
+        .map(|i| channels.iter().map(|c| c.get(i).copied().unwrap_or(0.0)).sum::<f32>() / channel_count)
+        .collect()
+}
+
+/// RBJ biquad low-pass, used as the anti-aliasing filter when downsampling
+#[derive(Debug, Clone)]
+struct Biquad {
+    b0: f64,
+    b1: f64,
+    b2: f64,
+    a1: f64,
+    a2: f64,
+    x1: f64,
+    x2: f64,
+    y1: f64,
+    y2: f64,
+}
+
+impl Biquad {
+    fn low_pass(sample_rate: f64, cutoff: f64) -> Self {
+        let omega = 2.0 * PI * cutoff / sample_rate;
+        let q = std::f64::consts::FRAC_1_SQRT_2;
+        let alpha = omega.sin() / (2.0 * q);
+        let cos = omega.cos();
+        let a0 = 1.0 + alpha;
+
+        Self {
+            b0: (1.0 - cos) / 2.0 / a0,
+            b1: (1.0 - cos) / a0,
+            b2: (1.0 - cos) / 2.0 / a0,
+            a1: -2.0 * cos / a0,
+            a2: (1.0 - alpha) / a0,
+            x1: 0.0,
+            x2: 0.0,
+            y1: 0.0,
+            y2: 0.0,
+        }
+    }
+
+    fn process(&mut self, x: f64) -> f64 {
+        let y = self.b0 * x + self.b1 * self.x1 + self.b2 * self.x2 - self.a1 * self.y1 - self.a2 * self.y2;
+        self.x2 = self.x1;
+        self.x1 = x;
+        self.y2 = self.y1;
+        self.y1 = y;
+        y
+    }
+}
+
+/// Streaming mono sample-rate converter. Keeps interpolation phase and filter state
+/// across calls, so feeding a signal in chunks produces the same output as one call.
+#[derive(Debug, Clone)]
+pub struct Resampler {
+    input_rate: f64,
+    output_rate: f64,
+    ratio: f64,
+    position: f64,
+    last_sample: f32,
+    // Two cascaded sections give a 24 dB/octave slope, enough to keep speech-band aliasing inaudible
+    anti_alias: Option<[Biquad; 2]>,
+}
+
+impl Resampler {
+    pub fn new(input_rate: f64, output_rate: f64) -> Self {
+        let anti_alias = if output_rate < input_rate {
+            let cutoff = output_rate * 0.45;
+            Some([Biquad::low_pass(input_rate, cutoff), Biquad::low_pass(input_rate, cutoff)])
+        } else {
+            None
+        };
+
+        Self {
+            input_rate,
+            output_rate,
+            ratio: input_rate / output_rate,
+            position: 0.0,
+            last_sample: 0.0,
+            anti_alias,
+        }
+    }
+
+    pub fn input_rate(&self) -> f64 {
+        self.input_rate
+    }
+
+    pub fn output_rate(&self) -> f64 {
+        self.output_rate
+    }
+
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        if input.is_empty() {
+            return;
+        }
+
+        let filtered: Vec<f32> = match self.anti_alias.as_mut() {
+            Some(filters) => input
+                .iter()
+                .map(|s| {
+                    let stage_one = filters[0].process(*s as f64);
+                    filters[1].process(stage_one) as f32
+                })
+                .collect(),
+            None => input.to_vec(),
+        };
+
+        // Index -1 refers to the last sample of the previous call
+        let last = self.last_sample;
+        let sample_at = |index: isize| if index < 0 { last } else { filtered[index as usize] };
+        let end = filtered.len() as f64 - 1.0;
+
+        output.reserve((filtered.len() as f64 / self.ratio) as usize + 1);
+        while self.position < end {
+            let index = self.position.floor();
+            let fraction = (self.position - index) as f32;
+            let a = sample_at(index as isize);
+            let b = sample_at(index as isize + 1);
+            output.push(a + (b - a) * fraction);
+            self.position += self.ratio;
+        }
+
+        self.position -= filtered.len() as f64;
+        self.last_sample = filtered[filtered.len() - 1];
+    }
+
+    /// Convenience one-shot conversion
+    pub fn convert(input_rate: f64, output_rate: f64, input: &[f32]) -> Vec<f32> {
+        let mut resampler = Self::new(input_rate, output_rate);
+        let mut output = Vec::new();
+        resampler.process(input, &mut output);
+        output
+    }
+}
+
+/// Map any channel layout onto `target` channels: mono is duplicated, anything
+/// folding down to mono is averaged, otherwise channels are kept in order and
+/// missing ones are filled with silence.
+pub fn remix_channels(channels: &[Vec<f32>], target: usize) -> Vec<Vec<f32>> {
+    let target = target.max(1);
+    let frames = channels.first().map(|c| c.len()).unwrap_or(0);
+
+    match (channels.len(), target) {
+        (0, _) => vec![Vec::new(); target],
+        (source, target) if source == target => channels.to_vec(),
+        (_, 1) => vec![downmix_to_mono(channels)],
+        (1, target) => vec![channels[0].clone(); target],
+        (_, target) => (0..target)
+            .map(|c| channels.get(c).cloned().unwrap_or_else(|| vec![0.0; frames]))
+            .collect(),
+    }
+}
+
+/// Interleave planar channels into frames
+pub fn interleave(channels: &[Vec<f32>]) -> Vec<f32> {
+    let frames = channels.first().map(|c| c.len()).unwrap_or(0);
+    let mut interleaved = Vec::with_capacity(frames * channels.len());
+    for frame in 0..frames {
+        for channel in channels {
+            interleaved.push(channel.get(frame).copied().unwrap_or(0.0));
+        }
+    }
+    interleaved
+}
+
+/// Remix + resample pipeline producing planar audio with a fixed rate and channel count
+/// from any input layout. Used by the live tap (16 kHz mono) and by the audio encoder
+/// when a source doesn't match the writer's configured format.
+pub struct PcmConverter {
+    output_rate: f64,
+    output_channels: usize,
+    input_rate: f64,
+    resamplers: Vec<Resampler>,
+}
+
+impl PcmConverter {
+    pub fn new(output_rate: u32, output_channels: u32) -> Self {
+        Self {
+            output_rate: output_rate as f64,
+            output_channels: output_channels.max(1) as usize,
+            input_rate: 0.0,
+            resamplers: Vec::new(),
+        }
+    }
+
+    pub fn output_rate(&self) -> u32 {
+        self.output_rate as u32
+    }
+
+    pub fn output_channels(&self) -> u32 {
+        self.output_channels as u32
+    }
+
+    /// Convert one buffer; returns `output_channels` planar channels of equal length
+    pub fn process(&mut self, channels: &[Vec<f32>], input_rate: f64) -> Vec<Vec<f32>> {
+        if input_rate <= 0.0 {
+            return vec![Vec::new(); self.output_channels];
+        }
+
+        // Recreate the resamplers when the source rate changes (e.g. device switch)
+        if self.resamplers.is_empty() || (self.input_rate - input_rate).abs() > f64::EPSILON {
+            self.resamplers = (0..self.output_channels)
+                .map(|_| Resampler::new(input_rate, self.output_rate))
+                .collect();
+            self.input_rate = input_rate;
+        }
+
+        // Remix first so we never resample channels that are about to be dropped
+        remix_channels(channels, self.output_channels)
+            .iter()
+            .zip(self.resamplers.iter_mut())
+            .map(|(channel, resampler)| {
+                let mut output = Vec::new();
+                resampler.process(channel, &mut output);
+                output
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sine(frequency: f64, sample_rate: f64, seconds: f64) -> Vec<f32> {
+        let count = (sample_rate * seconds) as usize;
+        (0..count)
+            .map(|i| (2.0 * PI * frequency * i as f64 / sample_rate).sin() as f32)
+            .collect()
+    }
+
+    fn rms(samples: &[f32]) -> f64 {
+        (samples.iter().map(|s| (*s as f64).powi(2)).sum::<f64>() / samples.len() as f64).sqrt()
+    }
+
+    fn zero_crossings(samples: &[f32]) -> usize {
+        samples.windows(2).filter(|w| (w[0] < 0.0) != (w[1] < 0.0)).count()
+    }
+
+    #[test]
+    fn int16_round_trip_is_close() {
+        let input = vec![0.0, 0.5, -0.5, 0.999, -1.0];
+        let output = i16_to_f32(&f32_to_i16(&input));
+        for (a, b) in input.iter().zip(output.iter()) {
+            assert!((a - b).abs() < 1.0 / 16384.0, "{} vs {}", a, b);
+        }
+    }
+
+    #[test]
+    fn f32_to_i16_clamps() {
+        assert_eq!(f32_to_i16(&[2.0, -2.0]), vec![i16::MAX, -i16::MAX]);
+    }
+
+    #[test]
+    fn downmix_averages_channels() {
+        let mono = downmix_to_mono(&[vec![1.0, 0.0, 0.5], vec![0.0, 1.0, 0.5]]);
+        assert_eq!(mono, vec![0.5, 0.5, 0.5]);
+    }
+
+    #[test]
+    fn deinterleave_splits_channels() {
+        let channels = deinterleave(&[1.0, 2.0, 3.0, 4.0, 5.0, 6.0], 2);
+        assert_eq!(channels, vec![vec![1.0, 3.0, 5.0], vec![2.0, 4.0, 6.0]]);
+    }
+
+    #[test]
+    fn downsampling_produces_expected_length() {
+        let input = sine(440.0, 48000.0, 1.0);
+        let output = Resampler::convert(48000.0, 16000.0, &input);
+        assert!((output.len() as i64 - 16000).abs() <= 1, "got {} samples", output.len());
+    }
+
+    #[test]
+    fn chunked_processing_matches_one_shot() {
+        let input = sine(300.0, 44100.0, 0.5);
+        let one_shot = Resampler::convert(44100.0, 16000.0, &input);
+
+        let mut resampler = Resampler::new(44100.0, 16000.0);
+        let mut chunked = Vec::new();
+        for chunk in input.chunks(1023) {
+            resampler.process(chunk, &mut chunked);
+        }
+
+        assert_eq!(one_shot.len(), chunked.len());
+        for (a, b) in one_shot.iter().zip(chunked.iter()) {
+            assert!((a - b).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn downsampling_preserves_speech_band_tone() {
+        let input = sine(1000.0, 48000.0, 1.0);
+        let output = Resampler::convert(48000.0, 16000.0, &input);
+        // Skip the filter's settling time
+        let steady = &output[1600..];
+
+        // 1 kHz over 0.9 s -> ~1800 zero crossings
+        let crossings = zero_crossings(steady);
+        assert!((crossings as i64 - 1800).abs() <= 4, "got {} crossings", crossings);
+        assert!((rms(steady) - std::f64::consts::FRAC_1_SQRT_2).abs() < 0.05);
+    }
+
+    #[test]
+    fn downsampling_suppresses_frequencies_above_nyquist() {
+        // 12 kHz cannot be represented at 16 kHz and must not alias into the output
+        let input = sine(12000.0, 48000.0, 1.0);
+        let output = Resampler::convert(48000.0, 16000.0, &input);
+        assert!(rms(&output[1600..]) < 0.1, "aliased energy {}", rms(&output[1600..]));
+    }
+
+    #[test]
+    fn remix_duplicates_mono_and_pads_missing_channels() {
+        assert_eq!(remix_channels(&[vec![0.25, 0.5]], 2), vec![vec![0.25, 0.5], vec![0.25, 0.5]]);
+        assert_eq!(
+            remix_channels(&[vec![1.0], vec![2.0]], 3),
+            vec![vec![1.0], vec![2.0], vec![0.0]]
+        );
+    }
+
+    #[test]
+    fn interleave_inverts_deinterleave() {
+        let interleaved = vec![1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        assert_eq!(interleave(&deinterleave(&interleaved, 3)), interleaved);
+    }
+
+    #[test]
+    fn converter_handles_rate_changes() {
+        let mut converter = PcmConverter::new(16000, 1);
+        let stereo = vec![sine(440.0, 48000.0, 0.1), sine(440.0, 48000.0, 0.1)];
+        let first = converter.process(&stereo, 48000.0);
+        let second = converter.process(&[sine(440.0, 24000.0, 0.1)], 24000.0);
+
+        assert_eq!(first.len(), 1);
+        assert!((first[0].len() as i64 - 1600).abs() <= 1);
+        assert!((second[0].len() as i64 - 1600).abs() <= 1);
+    }
+
+    #[test]
+    fn converter_upsamples_mono_to_stereo() {
+        let mut converter = PcmConverter::new(48000, 2);
+        let output = converter.process(&[sine(440.0, 16000.0, 0.5)], 16000.0);
+
+        assert_eq!(output.len(), 2);
+        assert_eq!(output[0], output[1]);
+        assert!((output[0].len() as i64 - 24000).abs() <= 3);
+        assert!((rms(&output[0]) - std::f64::consts::FRAC_1_SQRT_2).abs() < 0.05);
+    }
+}
@@ -0,0 +1,218 @@
+// WAV output - uncompressed int16 PCM written alongside the compressed recording
+
+use napi::bindgen_prelude::*;
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{BufWriter, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use super::convert::{f32_to_i16, PcmConverter};
+use crate::screencapturekit::audio_tap::PcmBuffer;
+use crate::screencapturekit::errors::io_error;
+
+/// Sample rate whisper.cpp expects
+pub const SIDECAR_SAMPLE_RATE: u32 = 16_000;
+
+const HEADER_LEN: u64 = 44;
+
+/// Streaming RIFF/WAVE writer; sizes in the header are patched in `finalize`
+pub struct WavWriter {
+    file: BufWriter<File>,
+    path: String,
+    sample_rate: u32,
+    channels: u16,
+    data_bytes: u64,
+}
+
+impl WavWriter {
+    pub fn create(path: &str, sample_rate: u32, channels: u16) -> Result<Self> {
+        let file = File::create(path)
+            .map_err(|e| io_error(format!("Failed to create {}", path), &e))?;
+        let mut writer = Self {
+            file: BufWriter::new(file),
+            path: path.to_string(),
+            sample_rate,
+            channels: channels.max(1),
+            data_bytes: 0,
+        };
+        writer.write_header()?;
+        Ok(writer)
+    }
+
+    fn write_header(&mut self) -> Result<()> {
+        let block_align = self.channels as u32 * 2;
+        // RIFF sizes are 32-bit; files past 4 GiB keep the saturated value, which most readers accept
+        let data_len = self.data_bytes.min(u32::MAX as u64 - HEADER_LEN) as u32;
+
+        let mut header = Vec::with_capacity(HEADER_LEN as usize);
+        header.extend_from_slice(b"RIFF");
+        header.extend_from_slice(&(data_len + HEADER_LEN as u32 - 8).to_le_bytes());
+        header.extend_from_slice(b"WAVE");
+        header.extend_from_slice(b"fmt ");
+        header.extend_from_slice(&16u32.to_le_bytes());
+        header.extend_from_slice(&1u16.to_le_bytes()); // PCM
+        header.extend_from_slice(&self.channels.to_le_bytes());
+        header.extend_from_slice(&self.sample_rate.to_le_bytes());
+        header.extend_from_slice(&(self.sample_rate * block_align).to_le_bytes());
+        header.extend_from_slice(&(block_align as u16).to_le_bytes());
+        header.extend_from_slice(&16u16.to_le_bytes());
+        header.extend_from_slice(b"data");
+        header.extend_from_slice(&data_len.to_le_bytes());
+
+        self.file
+            .write_all(&header)
+            .map_err(|e| io_error(format!("Failed to write {}", self.path), &e))
+    }
+
+    /// Append interleaved int16 frames
+    pub fn write_samples(&mut self, samples: &[i16]) -> Result<()> {
+        let bytes: Vec<u8> = samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        self.file
+            .write_all(&bytes)
+            .map_err(|e| io_error(format!("Failed to write {}", self.path), &e))?;
+        self.data_bytes += bytes.len() as u64;
+        Ok(())
+    }
+
+    /// Patch the header sizes and flush; returns the file path
+    pub fn finalize(&mut self) -> Result<String> {
+        self.file
+            .seek(SeekFrom::Start(0))
+            .map_err(|e| io_error(format!("Failed to finalize {}", self.path), &e))?;
+        self.write_header()?;
+        self.file
+            .flush()
+            .map_err(|e| io_error(format!("Failed to finalize {}", self.path), &e))?;
+        Ok(self.path.clone())
+    }
+
+    pub fn duration_seconds(&self) -> f64 {
+        self.data_bytes as f64 / (self.sample_rate as f64 * self.channels as f64 * 2.0)
+    }
+}
+
+/// Where a sidecar buffer came from; each source keeps its own resampler state
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SidecarSource {
+    System,
+    Microphone,
+}
+
+/// Buffers that land within this many seconds of where the source left off are treated as
+/// contiguous, absorbing timestamp rounding and resampler jitter
+const CONTIGUOUS_TOLERANCE_SECONDS: f64 = 0.01;
+
+/// How far a quiet source may fall behind before the mix is written without it
+const MIX_WINDOW_SECONDS: f64 = 0.5;
+
+struct SidecarTrack {
+    source: SidecarSource,
+    converter: PcmConverter,
+    // Sidecar sample index just past this source's last buffer
+    end: u64,
+}
+
+/// 16 kHz mono WAV fed with whatever PCM the recording produces, ready for whisper-cli.
+/// System audio and microphone are resampled separately and summed by presentation time.
+pub struct WavSidecar {
+    writer: WavWriter,
+    tracks: Vec<SidecarTrack>,
+    // Presentation time of sidecar sample 0
+    origin: Option<f64>,
+    // Mixed samples not yet written; `mix[0]` is sidecar sample `written`
+    mix: VecDeque<f32>,
+    written: u64,
+}
+
+impl WavSidecar {
+    /// `<recording>.wav` next to the recording
+    pub fn path_for(output_path: &str) -> String {
+        Path::new(output_path).with_extension("wav").to_string_lossy().to_string()
+    }
+
+    pub fn create(path: &str) -> Result<Self> {
+        Ok(Self {
+            writer: WavWriter::create(path, SIDECAR_SAMPLE_RATE, 1)?,
+            tracks: Vec::new(),
+            origin: None,
+            mix: VecDeque::new(),
+            written: 0,
+        })
+    }
+
+    pub fn push_pcm(&mut self, source: SidecarSource, pcm: &PcmBuffer) {
+        if pcm.frame_count() == 0 || pcm.sample_rate <= 0.0 {
+            return;
+        }
+        let rate = SIDECAR_SAMPLE_RATE as f64;
+        let origin = *self.origin.get_or_insert(pcm.presentation_time);
+
+        let index = match self.tracks.iter().position(|track| track.source == source) {
+            Some(index) => index,
+            None => {
+                self.tracks.push(SidecarTrack {
+                    source,
+                    converter: PcmConverter::new(SIDECAR_SAMPLE_RATE, 1),
+                    end: self.written,
+                });
+                self.tracks.len() - 1
+            }
+        };
+        let track = &mut self.tracks[index];
+        let mono = track.converter.process(&pcm.channels, pcm.sample_rate);
+        let samples = match mono.into_iter().next() {
+            Some(samples) if !samples.is_empty() => samples,
+            _ => return,
+        };
+
+        let start = ((pcm.presentation_time - origin) * rate).round().max(0.0) as u64;
+        let start = if start.abs_diff(track.end) as f64 <= CONTIGUOUS_TOLERANCE_SECONDS * rate {
+            track.end
+        } else {
+            start
+        };
+        track.end = start + samples.len() as u64;
+
+        // Anything before `written` is already on disk; a late source only contributes from there on
+        let skip = self.written.saturating_sub(start) as usize;
+        let offset = start.saturating_sub(self.written) as usize;
+        let needed = offset + samples.len() - skip.min(samples.len());
+        if self.mix.len() < needed {
+            self.mix.resize(needed, 0.0);
+        }
+        for (i, sample) in samples.iter().skip(skip).enumerate() {
+            self.mix[offset + i] += sample;
+        }
+
+        self.flush(false);
+    }
+
+    /// Write out the mix every source has caught up to, or everything when `all` is set
+    fn flush(&mut self, all: bool) {
+        let buffered = self.written + self.mix.len() as u64;
+        let ready = if all {
+            buffered
+        } else {
+            let slowest = self.tracks.iter().map(|track| track.end).min().unwrap_or(buffered);
+            let window = (MIX_WINDOW_SECONDS * SIDECAR_SAMPLE_RATE as f64) as u64;
+            slowest.max(buffered.saturating_sub(window))
+        };
+        let count = ready.saturating_sub(self.written).min(self.mix.len() as u64) as usize;
+        if count == 0 {
+            return;
+        }
+
+        let samples: Vec<f32> = self.mix.drain(..count).collect();
+        self.written += count as u64;
+        if let Err(e) = self.writer.write_samples(&f32_to_i16(&samples)) {
+            log::error!("{}", e);
+        }
+    }
+
+    pub fn finalize(&mut self) -> Result<String> {
+        self.flush(true);
+        let path = self.writer.finalize()?;
+        log::info!("WAV sidecar finalized: {} ({:.1}s)", path, self.writer.duration_seconds());
+        Ok(path)
+    }
+}
@@ -0,0 +1,309 @@
+// Live audio tap - turns ScreenCaptureKit audio sample buffers into 16 kHz mono f32 chunks for Whisper
+
+use objc2_core_media::{CMSampleBuffer, CMTime};
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::Arc;
+
+use super::audio::convert::{deinterleave, i16_to_f32, PcmConverter};
+
+// Whisper consumes 16 kHz mono
+pub const TAP_SAMPLE_RATE: u32 = 16000;
+pub const DEFAULT_CHUNK_MS: u32 = 100;
+
+// CoreAudio format flags
+const K_AUDIO_FORMAT_FLAG_IS_FLOAT: u32 = 1 << 0;
+const K_AUDIO_FORMAT_FLAG_IS_SIGNED_INTEGER: u32 = 1 << 2;
+const K_AUDIO_FORMAT_FLAG_IS_NON_INTERLEAVED: u32 = 1 << 5;
+const K_CM_SAMPLE_BUFFER_FLAG_AUDIO_BUFFER_LIST_ASSURE_16_BYTE_ALIGNMENT: u32 = 1 << 0;
+
+// Enough buffers for any non-interleaved layout ScreenCaptureKit produces
+const MAX_AUDIO_BUFFERS: usize = 8;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct AudioStreamBasicDescription {
+    pub sample_rate: f64,
+    pub format_id: u32,
+    pub format_flags: u32,
+    pub bytes_per_packet: u32,
+    pub frames_per_packet: u32,
+    pub bytes_per_frame: u32,
+    pub channels_per_frame: u32,
+    pub bits_per_channel: u32,
+    pub reserved: u32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy)]
+struct AudioBuffer {
+    number_channels: u32,
+    data_byte_size: u32,
+    data: *mut c_void,
+}
+
+#[repr(C)]
+struct AudioBufferList {
+    number_buffers: u32,
+    buffers: [AudioBuffer; MAX_AUDIO_BUFFERS],
+}
+
+extern "C" {
+    fn CMSampleBufferGetFormatDescription(sbuf: *const CMSampleBuffer) -> *const c_void;
+    fn CMAudioFormatDescriptionGetStreamBasicDescription(desc: *const c_void) -> *const AudioStreamBasicDescription;
+    fn CMSampleBufferGetPresentationTimeStamp(sbuf: *const CMSampleBuffer) -> CMTime;
+    fn CMSampleBufferGetNumSamples(sbuf: *const CMSampleBuffer) -> isize;
+    fn CMSampleBufferGetAudioBufferListWithRetainedBlockBuffer(
+        sbuf: *const CMSampleBuffer,
+        buffer_list_size_needed_out: *mut usize,
+        buffer_list_out: *mut AudioBufferList,
+        buffer_list_size: usize,
+        block_buffer_structure_allocator: *const c_void,
+        block_buffer_block_allocator: *const c_void,
+        flags: u32,
+        block_buffer_out: *mut *mut c_void,
+    ) -> i32;
+    fn CFRelease(cf: *const c_void);
+}
+
+/// Planar f32 PCM copied out of a CMSampleBuffer or an IOProc buffer list
+#[derive(Clone)]
+pub struct PcmBuffer {
+    pub sample_rate: f64,
+    pub channels: Vec<Vec<f32>>,
+    pub presentation_time: f64,
+}
+
+impl PcmBuffer {
+    pub fn frame_count(&self) -> usize {
+        self.channels.first().map(|c| c.len()).unwrap_or(0)
+    }
+}
+
+/// Stream format of an audio sample buffer, without touching its payload
+pub(crate) fn stream_description(sample_buffer: &CMSampleBuffer) -> Option<AudioStreamBasicDescription> {
+    unsafe {
+        let format_description = CMSampleBufferGetFormatDescription(sample_buffer);
+        if format_description.is_null() {
+            return None;
+        }
+        let asbd_ptr = CMAudioFormatDescriptionGetStreamBasicDescription(format_description);
+        if asbd_ptr.is_null() {
+            return None;
+        }
+        Some(*asbd_ptr)
+    }
+}
+
+/// Start time and duration in seconds of an audio sample buffer, without touching its payload
+pub(crate) fn sample_buffer_timing(sample_buffer: &CMSampleBuffer) -> Option<(f64, f64)> {
+    let asbd = stream_description(sample_buffer)?;
+    if asbd.sample_rate <= 0.0 {
+        return None;
+    }
+    unsafe {
+        let sbuf = sample_buffer as *const CMSampleBuffer;
+        let time = CMSampleBufferGetPresentationTimeStamp(sbuf);
+        if time.timescale <= 0 {
+            return None;
+        }
+        let frames = CMSampleBufferGetNumSamples(sbuf).max(0) as f64;
+        Some((time.value as f64 / time.timescale as f64, frames / asbd.sample_rate))
+    }
+}
+
+/// Copy the PCM payload of an audio sample buffer. Supports f32 and i16, interleaved or planar.
+pub fn extract_pcm(sample_buffer: &CMSampleBuffer) -> Option<PcmBuffer> {
+    unsafe {
+        let sbuf = sample_buffer as *const CMSampleBuffer;
+        let asbd = stream_description(sample_buffer)?;
+        if !is_supported_format(&asbd) {
+            log::warn!("Unsupported audio format for tap: flags 0x{:x}, {} bits", asbd.format_flags, asbd.bits_per_channel);
+            return None;
+        }
+
+        let mut buffer_list = AudioBufferList {
+            number_buffers: 0,
+            buffers: [AudioBuffer { number_channels: 0, data_byte_size: 0, data: ptr::null_mut() }; MAX_AUDIO_BUFFERS],
+        };
+        let mut block_buffer: *mut c_void = ptr::null_mut();
+        let status = CMSampleBufferGetAudioBufferListWithRetainedBlockBuffer(
+            sbuf,
+            ptr::null_mut(),
+            &mut buffer_list,
+            std::mem::size_of::<AudioBufferList>(),
+            ptr::null(),
+            ptr::null(),
+            K_CM_SAMPLE_BUFFER_FLAG_AUDIO_BUFFER_LIST_ASSURE_16_BYTE_ALIGNMENT,
+            &mut block_buffer,
+        );
+        if status != 0 {
+            log::warn!("CMSampleBufferGetAudioBufferListWithRetainedBlockBuffer failed: {}", status);
+            return None;
+        }
+
+        let time = CMSampleBufferGetPresentationTimeStamp(sbuf);
+        let presentation_time = if time.timescale > 0 {
+            time.value as f64 / time.timescale as f64
+        } else {
+            0.0
+        };
+
+        let pcm = pcm_from_buffer_list(&buffer_list as *const AudioBufferList as *const c_void, &asbd, presentation_time);
+
+        if !block_buffer.is_null() {
+            CFRelease(block_buffer);
+        }
+        pcm
+    }
+}
+
+fn is_supported_format(asbd: &AudioStreamBasicDescription) -> bool {
+    let is_float = asbd.format_flags & K_AUDIO_FORMAT_FLAG_IS_FLOAT != 0 && asbd.bits_per_channel == 32;
+    let is_int16 = asbd.format_flags & K_AUDIO_FORMAT_FLAG_IS_SIGNED_INTEGER != 0 && asbd.bits_per_channel == 16;
+    is_float || is_int16
+}
+
+/// Copy PCM out of a CoreAudio AudioBufferList (variable length, as handed to IOProcs)
+pub(crate) unsafe fn pcm_from_buffer_list(
+    buffer_list: *const c_void,
+    asbd: &AudioStreamBasicDescription,
+    presentation_time: f64,
+) -> Option<PcmBuffer> {
+    if buffer_list.is_null() || !is_supported_format(asbd) {
+        return None;
+    }
+
+    let is_float = asbd.format_flags & K_AUDIO_FORMAT_FLAG_IS_FLOAT != 0;
+    let channel_count = asbd.channels_per_frame.max(1) as usize;
+    let non_interleaved = asbd.format_flags & K_AUDIO_FORMAT_FLAG_IS_NON_INTERLEAVED != 0;
+    let bytes_per_sample = (asbd.bits_per_channel / 8) as usize;
+    let mut channels: Vec<Vec<f32>> = vec![Vec::new(); channel_count];
+
+    let read_samples = |data: *const u8, count: usize| -> Vec<f32> {
+        if is_float {
+            std::slice::from_raw_parts(data as *const f32, count).to_vec()
+        } else {
+            i16_to_f32(std::slice::from_raw_parts(data as *const i16, count))
+        }
+    };
+
+    // Walk the buffers by pointer - the list may be shorter than our fixed-size struct
+    let buffer_count = *(buffer_list as *const u32) as usize;
+    let buffers = (buffer_list as *const u8).add(std::mem::offset_of!(AudioBufferList, buffers)) as *const AudioBuffer;
+    for buffer_index in 0..buffer_count.min(MAX_AUDIO_BUFFERS) {
+        let buffer = *buffers.add(buffer_index);
+        if buffer.data.is_null() {
+            continue;
+        }
+        let samples = read_samples(buffer.data as *const u8, buffer.data_byte_size as usize / bytes_per_sample);
+
+        if non_interleaved {
+            // One buffer per channel
+            if let Some(channel) = channels.get_mut(buffer_index) {
+                channel.extend(samples);
+            }
+        } else {
+            for (channel, planar) in channels.iter_mut().zip(deinterleave(&samples, channel_count)) {
+                channel.extend(planar);
+            }
+        }
+    }
+
+    Some(PcmBuffer {
+        sample_rate: asbd.sample_rate,
+        channels,
+        presentation_time,
+    })
+}
+
+/// A chunk of 16 kHz mono audio ready for transcription
+pub struct PcmChunk {
+    pub samples: Vec<f32>,
+    pub sample_rate: u32,
+    /// Seconds since the first audio buffer of the recording
+    pub start_time: f64,
+}
+
+pub type PcmChunkSink = Arc<dyn Fn(PcmChunk) + Send + Sync>;
+
+pub struct AudioTap {
+    sink: PcmChunkSink,
+    chunk_samples: usize,
+    converter: PcmConverter,
+    pending: Vec<f32>,
+    pending_start: f64,
+    first_presentation_time: Option<f64>,
+    emitted_samples: u64,
+}
+
+impl AudioTap {
+    pub fn new(sink: PcmChunkSink, chunk_ms: u32) -> Self {
+        let chunk_samples = (TAP_SAMPLE_RATE as u64 * chunk_ms.max(10) as u64 / 1000) as usize;
+        log::info!("Audio tap created: {} Hz mono, {} samples per chunk", TAP_SAMPLE_RATE, chunk_samples);
+        Self {
+            sink,
+            chunk_samples,
+            converter: PcmConverter::new(TAP_SAMPLE_RATE, 1),
+            pending: Vec::with_capacity(chunk_samples),
+            pending_start: 0.0,
+            first_presentation_time: None,
+            emitted_samples: 0,
+        }
+    }
+
+    pub fn push_sample_buffer(&mut self, sample_buffer: &CMSampleBuffer) {
+        if let Some(pcm) = extract_pcm(sample_buffer) {
+            self.push_pcm(&pcm);
+        }
+    }
+
+    pub fn push_pcm(&mut self, pcm: &PcmBuffer) {
+        let frames = pcm.frame_count();
+        if frames == 0 || pcm.sample_rate <= 0.0 {
+            return;
+        }
+
+        let first = *self.first_presentation_time.get_or_insert(pcm.presentation_time);
+        if self.pending.is_empty() {
+            self.pending_start = (pcm.presentation_time - first).max(0.0);
+        }
+
+        // Downmix + resample to 16 kHz mono
+        if let Some(mono) = self.converter.process(&pcm.channels, pcm.sample_rate).pop() {
+            self.pending.extend(mono);
+        }
+
+        while self.pending.len() >= self.chunk_samples {
+            let rest = self.pending.split_off(self.chunk_samples);
+            let samples = std::mem::replace(&mut self.pending, rest);
+            let start_time = self.pending_start;
+            self.pending_start += samples.len() as f64 / TAP_SAMPLE_RATE as f64;
+            self.emitted_samples += samples.len() as u64;
+
+            (self.sink)(PcmChunk {
+                samples,
+                sample_rate: TAP_SAMPLE_RATE,
+                start_time,
+            });
+        }
+    }
+
+    /// Deliver whatever is buffered, e.g. when the tap or the recording stops
+    pub fn flush(&mut self) {
+        if self.pending.is_empty() {
+            return;
+        }
+        let samples = std::mem::take(&mut self.pending);
+        self.emitted_samples += samples.len() as u64;
+        (self.sink)(PcmChunk {
+            samples,
+            sample_rate: TAP_SAMPLE_RATE,
+            start_time: self.pending_start,
+        });
+    }
+
+    pub fn emitted_seconds(&self) -> f64 {
+        self.emitted_samples as f64 / TAP_SAMPLE_RATE as f64
+    }
+}
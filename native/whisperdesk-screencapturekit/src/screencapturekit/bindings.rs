@@ -1,9 +1,15 @@
+use objc2::rc::{autoreleasepool, Retained};
 use objc2::runtime::{AnyObject, Class};
 use objc2::{msg_send, sel, class, Encode, Encoding};
 use objc2_foundation::{NSArray, NSString, NSNumber, NSError, NSObject};
 use objc2_core_media::{CMSampleBuffer, CMTime};
 use objc2_core_video::CVPixelBuffer;
 use std::ptr;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use super::cancel::{CancelToken, Wait};
+use super::errors::{capture_error, describe, CaptureErrorCode};
 
 // Add block2 support for completion handlers
 use block2::{Block, StackBlock};
@@ -26,6 +32,21 @@ pub type SCContentFilter = AnyObject;
 pub type SCStream = AnyObject;
 pub type SCStreamConfiguration = AnyObject;
 
+// How long a blocking shareable-content fetch waits for its completion handler
+pub const SHAREABLE_CONTENT_TIMEOUT: Duration = Duration::from_secs(5);
+
+// Window server layers (CGWindowLevel); the desktop picture and icons sit below zero
+const DOCK_WINDOW_LAYER: isize = 20;
+const MAIN_MENU_WINDOW_LAYER: isize = 24;
+const STATUS_WINDOW_LAYER: isize = 25;
+const DOCK_BUNDLE_ID: &str = "com.apple.dock";
+
+/// Retained SCShareableContent handed from the completion handler's queue to the waiting caller.
+/// The content is immutable once delivered, so moving it between threads is fine.
+struct DeliveredContent(Retained<SCShareableContent>);
+
+unsafe impl Send for DeliveredContent {}
+
 // Completion handler type aliases
 pub type SCShareableContentCompletionHandler = 
     Block<dyn Fn(*mut SCShareableContent, *mut NSError)>;
@@ -106,29 +127,17 @@ impl ScreenCaptureKitHelpers {
         }
         
         let has_permission = CGPreflightScreenCaptureAccess();
-        println!("🔐 Screen recording permission status: {}", has_permission);
+        log::info!("Screen recording permission status: {}", has_permission);
         has_permission
     }
     
-    /// Request screen recording permissions (this will prompt user if needed)
-    pub unsafe fn request_screen_recording_permission() -> bool {
-        // Use CGRequestScreenCaptureAccess to request permissions
-        extern "C" {
-            fn CGRequestScreenCaptureAccess() -> bool;
-        }
-        
-        let has_permission = CGRequestScreenCaptureAccess();
-        println!("🔐 Screen recording permission after request: {}", has_permission);
-        has_permission
-    }
-
     pub unsafe fn get_shareable_content_async<F>(completion: F) 
     where
         F: Fn(Option<*mut SCShareableContent>, Option<&NSError>) + Send + Sync + Clone + 'static,
     {
         // First check permissions
         if !Self::check_screen_recording_permission() {
-            println!("❌ Screen recording permission not granted");
+            log::error!("Screen recording permission not granted");
             // Create a permission error - we'll pass null for now since creating NSError is complex
             completion(None, None);
             return;
@@ -150,22 +159,85 @@ impl ScreenCaptureKitHelpers {
     }
     
     /// Get shareable content synchronously (blocking call)
-    pub unsafe fn get_shareable_content_sync() -> Result<*mut SCShareableContent, String> {
+    pub unsafe fn get_shareable_content_sync() -> Result<Retained<SCShareableContent>, String> {
+        Self::get_shareable_content_with_timeout(SHAREABLE_CONTENT_TIMEOUT)
+    }
+    
+    /// Fetch on-screen shareable content (desktop windows excluded), blocking until the completion
+    /// handler fires or `timeout` passes. The result is retained, so it outlives the handler.
+    pub unsafe fn get_shareable_content_with_timeout(timeout: Duration) -> Result<Retained<SCShareableContent>, String> {
+        Self::get_shareable_content_with(timeout, true)
+    }
+    
+    /// Like `get_shareable_content_with_timeout`, also listing off-screen and minimized windows
+    /// unless `on_screen_only`
+    pub unsafe fn get_shareable_content_with(timeout: Duration, on_screen_only: bool) -> Result<Retained<SCShareableContent>, String> {
+        Self::request_shareable_content(timeout, true, on_screen_only, &CancelToken::default()).map_err(|e| describe(&e).1)
+    }
+    
+    /// Like `get_shareable_content_with`, giving up as soon as `cancel` is cancelled. ScreenCaptureKit
+    /// can't withdraw the request; content it delivers afterwards is released in the handler.
+    pub unsafe fn get_shareable_content_cancellable(
+        timeout: Duration,
+        on_screen_only: bool,
+        cancel: &CancelToken,
+    ) -> napi::Result<Retained<SCShareableContent>> {
+        Self::request_shareable_content(timeout, true, on_screen_only, cancel)
+    }
+    
+    /// On-screen content including the desktop picture and desktop icon windows, so they can be excluded
+    pub unsafe fn get_shareable_content_with_desktop_windows(timeout: Duration) -> napi::Result<Retained<SCShareableContent>> {
+        Self::request_shareable_content(timeout, false, true, &CancelToken::default())
+    }
+    
+    unsafe fn request_shareable_content(
+        timeout: Duration,
+        excluding_desktop_windows: bool,
+        on_screen_only: bool,
+        cancel: &CancelToken,
+    ) -> napi::Result<Retained<SCShareableContent>> {
         // First check permissions
         if !Self::check_screen_recording_permission() {
-            return Err("Screen recording permission not granted. Please enable screen recording permission in System Preferences > Security & Privacy > Privacy > Screen Recording".to_string());
+            return Err(capture_error(
+                CaptureErrorCode::PermissionDenied,
+                "Screen recording permission not granted. Please enable screen recording permission in System Preferences > Security & Privacy > Privacy > Screen Recording",
+            ));
         }
 
-        println!("🔍 Attempting to get shareable content with proper ScreenCaptureKit API");
+        log::debug!("Requesting shareable content from ScreenCaptureKit ({} ms timeout)", timeout.as_millis());
         
-        // BYPASS APPROACH: Don't try to call ScreenCaptureKit APIs that cause crashes
-        // Instead, return an error that indicates we should use fallback content
-        println!("🛡️ BYPASS MODE: Avoiding ScreenCaptureKit API calls to prevent crashes");
-        println!("💡 This is the safest approach - use fallback content instead");
+        let (sender, receiver) = mpsc::channel::<Result<DeliveredContent, String>>();
+        let block = StackBlock::new(move |content: *mut SCShareableContent, error: *mut NSError| {
+            let result = match Retained::retain(content) {
+                Some(content) => Ok(DeliveredContent(content)),
+                None if error.is_null() => Err("ScreenCaptureKit returned no content".to_string()),
+                None => Err((*error).localizedDescription().to_string()),
+            };
+            // Nobody is waiting after a timeout or cancel; the content is dropped (released) here
+            let _ = sender.send(result);
+        });
+        let block = block.copy();
         
-        // Return an error to indicate we should use fallback content
-        // This prevents any crashes while still allowing the system to work
-        Err("ScreenCaptureKit API bypassed for safety - using fallback content".to_string())
+        let class = class!(SCShareableContent);
+        let _: () = msg_send![
+            class,
+            getShareableContentExcludingDesktopWindows: excluding_desktop_windows,
+            onScreenWindowsOnly: on_screen_only,
+            completionHandler: &*block
+        ];
+        
+        match cancel.recv_timeout(&receiver, timeout) {
+            Wait::Received(Ok(DeliveredContent(content))) => {
+                log::info!("Received shareable content from ScreenCaptureKit");
+                Ok(content)
+            }
+            Wait::Received(Err(e)) => Err(capture_error(CaptureErrorCode::Unknown, format!("ScreenCaptureKit content request failed: {}", e))),
+            Wait::TimedOut => Err(capture_error(
+                CaptureErrorCode::Timeout,
+                format!("ScreenCaptureKit content request timed out after {} ms", timeout.as_millis()),
+            )),
+            Wait::Cancelled => Err(capture_error(CaptureErrorCode::Cancelled, "ScreenCaptureKit content request was cancelled")),
+        }
     }
     
     pub unsafe fn start_stream_capture_async<F>(stream: *mut SCStream, completion: F)
@@ -200,10 +272,81 @@ impl ScreenCaptureKitHelpers {
         ];
     }
     
-    pub unsafe fn create_content_filter_with_display(display: *mut SCDisplay) -> *mut SCContentFilter {
+    pub unsafe fn update_stream_configuration_async<F>(stream: *mut SCStream, configuration: *mut SCStreamConfiguration, completion: F)
+    where
+        F: Fn(Option<&NSError>) + Send + Sync + Clone + 'static,
+    {
+        let block = StackBlock::new(move |error: *mut NSError| {
+            let error_ref = if error.is_null() { None } else { Some(&*error) };
+            completion(error_ref);
+        });
+        let block = block.copy();
+        
+        let _: () = msg_send![
+            stream,
+            updateConfiguration: configuration,
+            completionHandler: &*block
+        ];
+    }
+    
+    /// Point a running stream at different content (SCStream.updateContentFilter)
+    pub unsafe fn update_content_filter_async<F>(stream: *mut SCStream, filter: *mut SCContentFilter, completion: F)
+    where
+        F: Fn(Option<&NSError>) + Send + Sync + Clone + 'static,
+    {
+        let block = StackBlock::new(move |error: *mut NSError| {
+            let error_ref = if error.is_null() { None } else { Some(&*error) };
+            completion(error_ref);
+        });
+        let block = block.copy();
+        
+        let _: () = msg_send![
+            stream,
+            updateContentFilter: filter,
+            completionHandler: &*block
+        ];
+    }
+    
+    pub unsafe fn create_content_filter_with_display(display: *mut SCDisplay, excluding_windows: &NSArray) -> *mut SCContentFilter {
         let class = class!(SCContentFilter);
         let alloc: *mut AnyObject = msg_send![class, alloc];
-        msg_send![alloc, initWithDisplay: display]
+        msg_send![alloc, initWithDisplay: display, excludingWindows: excluding_windows]
+    }
+    
+    pub unsafe fn create_content_filter_including_windows(display: *mut SCDisplay, including_windows: &NSArray) -> *mut SCContentFilter {
+        let class = class!(SCContentFilter);
+        let alloc: *mut AnyObject = msg_send![class, alloc];
+        msg_send![alloc, initWithDisplay: display, includingWindows: including_windows]
+    }
+    
+    pub unsafe fn create_content_filter_excluding_applications(
+        display: *mut SCDisplay,
+        excluding_applications: &NSArray,
+        excepting_windows: &NSArray,
+    ) -> *mut SCContentFilter {
+        let class = class!(SCContentFilter);
+        let alloc: *mut AnyObject = msg_send![class, alloc];
+        msg_send![
+            alloc,
+            initWithDisplay: display,
+            excludingApplications: excluding_applications,
+            exceptingWindows: excepting_windows
+        ]
+    }
+    
+    pub unsafe fn create_content_filter_including_applications(
+        display: *mut SCDisplay,
+        including_applications: &NSArray,
+        excepting_windows: &NSArray,
+    ) -> *mut SCContentFilter {
+        let class = class!(SCContentFilter);
+        let alloc: *mut AnyObject = msg_send![class, alloc];
+        msg_send![
+            alloc,
+            initWithDisplay: display,
+            includingApplications: including_applications,
+            exceptingWindows: excepting_windows
+        ]
     }
     
     pub unsafe fn create_content_filter_with_window(window: *mut SCWindow) -> *mut SCContentFilter {
@@ -212,60 +355,358 @@ impl ScreenCaptureKitHelpers {
         msg_send![alloc, initWithDesktopIndependentWindow: window]
     }
 
-    /// ULTRA-SAFE: Create display content filter using ScreenCaptureKit content directly
-    /// This avoids the need to extract individual SCDisplay objects which can cause segfaults
+    /// The SCDisplay in `sc_content` with the given CGDirectDisplayID
+    pub unsafe fn find_display(sc_content: &SCShareableContent, display_id: u32) -> Option<Retained<SCDisplay>> {
+        autoreleasepool(|_| {
+            let displays: Option<Retained<NSArray>> = msg_send![sc_content, displays];
+            let displays = displays?;
+            (0..displays.count())
+                .map(|i| displays.objectAtIndex(i))
+                .find(|display| {
+                    let id: u32 = msg_send![&**display, displayID];
+                    id == display_id
+                })
+        })
+    }
+    
+    /// Bundle identifier of an SCRunningApplication
+    unsafe fn bundle_identifier(application: &AnyObject) -> Option<String> {
+        let bundle_id: Option<Retained<NSString>> = msg_send![application, bundleIdentifier];
+        bundle_id.map(|b| b.to_string())
+    }
+    
+    /// The running applications in `sc_content` whose bundle ID is in `bundle_ids`
+    pub unsafe fn find_applications(sc_content: &SCShareableContent, bundle_ids: &[String]) -> Vec<Retained<AnyObject>> {
+        autoreleasepool(|_| {
+            let applications: Option<Retained<NSArray>> = msg_send![sc_content, applications];
+            let applications = match applications {
+                Some(applications) => applications,
+                None => return Vec::new(),
+            };
+            (0..applications.count())
+                .map(|i| applications.objectAtIndex(i))
+                .filter(|application| {
+                    Self::bundle_identifier(application)
+                        .map(|id| bundle_ids.iter().any(|wanted| wanted.eq_ignore_ascii_case(&id)))
+                        .unwrap_or(false)
+                })
+                .collect()
+        })
+    }
+    
+    /// The SCWindow in `sc_content` with the given CGWindowID
+    pub unsafe fn find_window(sc_content: &SCShareableContent, window_id: u32) -> Option<Retained<SCWindow>> {
+        autoreleasepool(|_| {
+            let windows: Option<Retained<NSArray>> = msg_send![sc_content, windows];
+            let windows = windows?;
+            (0..windows.count())
+                .map(|i| windows.objectAtIndex(i))
+                .find(|window| {
+                    let id: u32 = msg_send![&**window, windowID];
+                    id == window_id
+                })
+        })
+    }
+
+    /// Filter capturing a whole display minus `excluded_window_ids` and every window of the apps in
+    /// `excluded_bundle_ids`, looked up in the retained shareable content. IDs and apps that aren't on
+    /// screen are skipped. Also returns the window IDs the filter could not exclude: ScreenCaptureKit
+    /// has no initializer taking both lists, so with app exclusions only windows of those apps go.
     pub unsafe fn create_display_content_filter(
-        sc_content: *mut SCShareableContent, 
-        _display_id: u32
-    ) -> *mut SCContentFilter {
-        println!("🔧 Creating display content filter using ultra-safe approach (avoiding array access)");
+        sc_content: &SCShareableContent, 
+        display_id: u32,
+        excluded_window_ids: &[u32],
+        excluded_bundle_ids: &[String],
+    ) -> Option<(Retained<SCContentFilter>, Vec<u32>)> {
+        log::info!("Creating display content filter for display {} ({} windows, {} apps excluded)",
+            display_id, excluded_window_ids.len(), excluded_bundle_ids.len());
+        
+        let display = match Self::find_display(sc_content, display_id) {
+            Some(display) => display,
+            None => {
+                log::error!("Display {} not present in ScreenCaptureKit content", display_id);
+                return None;
+            }
+        };
         
-        if sc_content.is_null() {
-            println!("❌ ScreenCaptureKit content is null, using minimal filter");
-            return Self::create_minimal_content_filter();
+        let mut excluded = Vec::with_capacity(excluded_window_ids.len());
+        for &window_id in excluded_window_ids {
+            match Self::find_window(sc_content, window_id) {
+                Some(window) => excluded.push((window_id, window)),
+                None => log::warn!("Excluded window {} is not on screen - nothing to hide", window_id),
+            }
         }
+        let display_ptr = Retained::as_ptr(&display) as *mut SCDisplay;
         
-        // ULTRA-SAFE: Don't try to access displays array or extract objects
-        // Instead, just create a minimal content filter that should capture everything
-        println!("🛡️ Bypassing ScreenCaptureKit object extraction to prevent segfaults");
-        println!("💡 Using minimal content filter approach for maximum safety");
+        if excluded_bundle_ids.is_empty() {
+            let windows: Vec<Retained<SCWindow>> = excluded.into_iter().map(|(_, window)| window).collect();
+            // alloc/init hands back a +1 reference, which Retained takes over
+            let filter = Retained::from_raw(Self::create_content_filter_with_display(display_ptr, &NSArray::from_retained_slice(&windows)))?;
+            return Some((filter, Vec::new()));
+        }
         
-        // Always use the minimal content filter to avoid any potential segfaults
-        // from accessing ScreenCaptureKit objects
-        Self::create_minimal_content_filter()
+        let applications = Self::find_applications(sc_content, excluded_bundle_ids);
+        log::info!("Excluding {} running applications", applications.len());
+        
+        // Windows of an excluded app vanish with it; any others are out of reach of this filter
+        let not_excluded: Vec<u32> = excluded
+            .iter()
+            .filter(|(_, window)| {
+                let owner: Option<Retained<AnyObject>> = msg_send![&**window, owningApplication];
+                let owner_id = owner.and_then(|owner| Self::bundle_identifier(&owner));
+                !owner_id
+                    .map(|id| excluded_bundle_ids.iter().any(|wanted| wanted.eq_ignore_ascii_case(&id)))
+                    .unwrap_or(false)
+            })
+            .map(|(window_id, _)| *window_id)
+            .collect();
+        
+        let filter = Retained::from_raw(Self::create_content_filter_excluding_applications(
+            display_ptr,
+            &NSArray::from_retained_slice(&applications),
+            &NSArray::<AnyObject>::new(),
+        ))?;
+        Some((filter, not_excluded))
     }
 
-    /// ULTRA-SAFE: Create window content filter using ScreenCaptureKit content directly
-    pub unsafe fn create_window_content_filter(
-        sc_content: *mut SCShareableContent, 
-        _window_id: u32
-    ) -> *mut SCContentFilter {
-        println!("🔧 Creating window content filter using ultra-safe approach (avoiding array access)");
+    /// Windows of the system chrome in `sc_content`: the Dock, the desktop picture and icons (only
+    /// listed in content fetched with desktop windows) and the menu bar
+    pub unsafe fn system_window_ids(sc_content: &SCShareableContent, dock: bool, desktop: bool, menu_bar: bool) -> Vec<u32> {
+        autoreleasepool(|_| {
+            let windows: Option<Retained<NSArray>> = msg_send![sc_content, windows];
+            let windows = match windows {
+                Some(windows) => windows,
+                None => return Vec::new(),
+            };
+            (0..windows.count())
+                .map(|i| windows.objectAtIndex(i))
+                .filter(|window| {
+                    let layer: isize = msg_send![&**window, windowLayer];
+                    if desktop && layer < 0 {
+                        return true;
+                    }
+                    if menu_bar && (layer == MAIN_MENU_WINDOW_LAYER || layer == STATUS_WINDOW_LAYER) {
+                        return true;
+                    }
+                    if dock && layer == DOCK_WINDOW_LAYER {
+                        let owner: Option<Retained<AnyObject>> = msg_send![&**window, owningApplication];
+                        return owner
+                            .and_then(|owner| Self::bundle_identifier(&owner))
+                            .map(|id| id == DOCK_BUNDLE_ID)
+                            .unwrap_or(false);
+                    }
+                    false
+                })
+                .map(|window| {
+                    let id: u32 = msg_send![&*window, windowID];
+                    id
+                })
+                .collect()
+        })
+    }
+    
+    /// Bundle ID of the app this process belongs to. Helper processes of Electron-style apps
+    /// (`<app>.helper`, `<app>.helper.Renderer`, ...) report the app's own ID.
+    pub unsafe fn host_bundle_id() -> Option<String> {
+        let bundle: Option<Retained<AnyObject>> = msg_send![class!(NSBundle), mainBundle];
+        let bundle_id: Option<Retained<NSString>> = msg_send![&*bundle?, bundleIdentifier];
+        let bundle_id = bundle_id?.to_string();
+        let host = match bundle_id.find(".helper") {
+            Some(index) => bundle_id[..index].to_string(),
+            None => bundle_id,
+        };
+        if host.is_empty() { None } else { Some(host) }
+    }
+    
+    /// Windows in `sc_content` owned by this process or by the app `host_bundle_id` and its helpers
+    pub unsafe fn own_window_ids(sc_content: &SCShareableContent, host_bundle_id: Option<&str>) -> Vec<u32> {
+        let own_pid = std::process::id() as i32;
+        autoreleasepool(|_| {
+            let windows: Option<Retained<NSArray>> = msg_send![sc_content, windows];
+            let windows = match windows {
+                Some(windows) => windows,
+                None => return Vec::new(),
+            };
+            (0..windows.count())
+                .map(|i| windows.objectAtIndex(i))
+                .filter(|window| {
+                    let owner: Option<Retained<AnyObject>> = msg_send![&**window, owningApplication];
+                    let owner = match owner {
+                        Some(owner) => owner,
+                        None => return false,
+                    };
+                    let pid: i32 = msg_send![&*owner, processID];
+                    if pid == own_pid {
+                        return true;
+                    }
+                    match (host_bundle_id, Self::bundle_identifier(&owner)) {
+                        (Some(host), Some(id)) => id == host || id.starts_with(&format!("{}.helper", host)),
+                        _ => false,
+                    }
+                })
+                .map(|window| {
+                    let id: u32 = msg_send![&*window, windowID];
+                    id
+                })
+                .collect()
+        })
+    }
+    
+    /// SCContentFilter.includeMenuBar arrived in macOS 14.2
+    pub unsafe fn supports_menu_bar_toggle() -> bool {
+        msg_send![class!(SCContentFilter), instancesRespondToSelector: sel!(setIncludeMenuBar:)]
+    }
+    
+    pub unsafe fn set_include_menu_bar(filter: &SCContentFilter, include: bool) {
+        let _: () = msg_send![filter, setIncludeMenuBar: include];
+    }
+    
+    /// Display whose frame contains the centre of the window, or the first display when none does
+    pub unsafe fn display_for_window(sc_content: &SCShareableContent, window_id: u32) -> Option<u32> {
+        let window = Self::find_window(sc_content, window_id)?;
+        let frame: CGRect = msg_send![&*window, frame];
+        let centre_x = frame.origin.x + frame.size.width / 2.0;
+        let centre_y = frame.origin.y + frame.size.height / 2.0;
+        
+        autoreleasepool(|_| {
+            let displays: Option<Retained<NSArray>> = msg_send![sc_content, displays];
+            let displays = displays?;
+            let ids: Vec<(u32, CGRect)> = (0..displays.count())
+                .map(|i| {
+                    let display = displays.objectAtIndex(i);
+                    let id: u32 = msg_send![&*display, displayID];
+                    let bounds: CGRect = msg_send![&*display, frame];
+                    (id, bounds)
+                })
+                .collect();
+            ids.iter()
+                .find(|(_, b)| {
+                    centre_x >= b.origin.x && centre_x < b.origin.x + b.size.width
+                        && centre_y >= b.origin.y && centre_y < b.origin.y + b.size.height
+                })
+                .or(ids.first())
+                .map(|(id, _)| *id)
+        })
+    }
+    
+    /// Display showing the first on-screen window of the application with `bundle_id`
+    pub unsafe fn display_for_application(sc_content: &SCShareableContent, bundle_id: &str) -> Option<u32> {
+        let window_id = autoreleasepool(|_| {
+            let windows: Option<Retained<NSArray>> = msg_send![sc_content, windows];
+            let windows = windows?;
+            (0..windows.count())
+                .map(|i| windows.objectAtIndex(i))
+                .find(|window| {
+                    let owner: Option<Retained<AnyObject>> = msg_send![&**window, owningApplication];
+                    owner
+                        .and_then(|owner| Self::bundle_identifier(&owner))
+                        .map(|id| id.eq_ignore_ascii_case(bundle_id))
+                        .unwrap_or(false)
+                })
+                .map(|window| {
+                    let id: u32 = msg_send![&*window, windowID];
+                    id
+                })
+        })?;
+        Self::display_for_window(sc_content, window_id)
+    }
+    
+    /// Filter capturing every window of the application with `bundle_id` on the display, including
+    /// windows it opens after the stream starts. None when the display or the app is missing.
+    pub unsafe fn create_application_content_filter(
+        sc_content: &SCShareableContent,
+        display_id: u32,
+        bundle_id: &str,
+    ) -> Option<Retained<SCContentFilter>> {
+        log::info!("Creating application content filter for {} on display {}", bundle_id, display_id);
+        
+        let display = match Self::find_display(sc_content, display_id) {
+            Some(display) => display,
+            None => {
+                log::error!("Display {} not present in ScreenCaptureKit content", display_id);
+                return None;
+            }
+        };
+        let applications = Self::find_applications(sc_content, &[bundle_id.to_string()]);
+        if applications.is_empty() {
+            log::error!("Application {} not present in ScreenCaptureKit content", bundle_id);
+            return None;
+        }
         
-        if sc_content.is_null() {
-            println!("❌ ScreenCaptureKit content is null, using minimal filter");
-            return Self::create_minimal_content_filter();
+        Retained::from_raw(Self::create_content_filter_including_applications(
+            Retained::as_ptr(&display) as *mut SCDisplay,
+            &NSArray::from_retained_slice(&applications),
+            &NSArray::<AnyObject>::new(),
+        ))
+    }
+    
+    /// Filter capturing only `window_ids`, composited where they sit on the display. IDs that aren't
+    /// on screen are skipped and returned; None when the display or every window is missing.
+    pub unsafe fn create_windows_content_filter(
+        sc_content: &SCShareableContent,
+        display_id: u32,
+        window_ids: &[u32],
+    ) -> Option<(Retained<SCContentFilter>, Vec<u32>)> {
+        log::info!("Creating multi-window content filter for {} windows on display {}", window_ids.len(), display_id);
+        
+        let display = match Self::find_display(sc_content, display_id) {
+            Some(display) => display,
+            None => {
+                log::error!("Display {} not present in ScreenCaptureKit content", display_id);
+                return None;
+            }
+        };
+        
+        let mut windows = Vec::with_capacity(window_ids.len());
+        let mut missing = Vec::new();
+        for &window_id in window_ids {
+            match Self::find_window(sc_content, window_id) {
+                Some(window) => windows.push(window),
+                None => {
+                    log::warn!("Window {} is not on screen - leaving it out", window_id);
+                    missing.push(window_id);
+                }
+            }
+        }
+        if windows.is_empty() {
+            log::error!("None of the requested windows are present in ScreenCaptureKit content");
+            return None;
         }
         
-        // ULTRA-SAFE: Don't try to access windows array or extract objects
-        // Instead, just create a minimal content filter that should capture everything
-        println!("🛡️ Bypassing ScreenCaptureKit object extraction to prevent segfaults");
-        println!("💡 Using minimal content filter approach for maximum safety");
+        let filter = Retained::from_raw(Self::create_content_filter_including_windows(
+            Retained::as_ptr(&display) as *mut SCDisplay,
+            &NSArray::from_retained_slice(&windows),
+        ))?;
+        Some((filter, missing))
+    }
+
+    /// Filter capturing a single window, looked up by ID in the retained shareable content
+    pub unsafe fn create_window_content_filter(
+        sc_content: &SCShareableContent, 
+        window_id: u32
+    ) -> Option<Retained<SCContentFilter>> {
+        log::info!("Creating window content filter for window {}", window_id);
+        
+        let window = match Self::find_window(sc_content, window_id) {
+            Some(window) => window,
+            None => {
+                log::error!("Window {} not present in ScreenCaptureKit content", window_id);
+                return None;
+            }
+        };
         
-        // Always use the minimal content filter to avoid any potential segfaults
-        // from accessing ScreenCaptureKit objects
-        Self::create_minimal_content_filter()
+        Retained::from_raw(Self::create_content_filter_with_window(Retained::as_ptr(&window) as *mut SCWindow))
     }
 
     /// ULTRA-SAFE: Create a minimal content filter that captures the entire desktop
     /// This is the safest fallback option that should always work
     pub unsafe fn create_minimal_content_filter() -> *mut SCContentFilter {
-        println!("🔧 Creating minimal content filter (COMPLETE BYPASS MODE - preventing all crashes)");
+        log::info!("Creating minimal content filter (COMPLETE BYPASS MODE - preventing all crashes)");
         
         // COMPLETE BYPASS: Don't try to create any ScreenCaptureKit objects at all
         // This prevents any potential crashes from Objective-C runtime issues
-        println!("🛡️ COMPLETE BYPASS: Returning null filter to avoid all ScreenCaptureKit object creation");
-        println!("💡 This is the safest approach - the calling code will handle null filters gracefully");
+        log::info!("COMPLETE BYPASS: Returning null filter to avoid all ScreenCaptureKit object creation");
+        log::info!("This is the safest approach - the calling code will handle null filters gracefully");
         
         // Return null pointer - the calling code should handle this gracefully
         // and provide alternative recording methods
@@ -286,7 +727,7 @@ impl ScreenCaptureKitHelpers {
         shows_cursor: bool,
         captures_audio: bool,
         pixel_format: u32,
-        color_space: u32,
+        color_space_name: &str,
     ) {
         let _: () = msg_send![config, setWidth: width];
         let _: () = msg_send![config, setHeight: height];
@@ -303,7 +744,40 @@ impl ScreenCaptureKitHelpers {
         let _: () = msg_send![config, setShowsCursor: shows_cursor];
         let _: () = msg_send![config, setCapturesAudio: captures_audio];
         let _: () = msg_send![config, setPixelFormat: pixel_format];
-        let _: () = msg_send![config, setColorSpace: color_space];
+        let color_space_name = NSString::from_str(color_space_name);
+        let _: () = msg_send![config, setColorSpaceName: &*color_space_name];
+    }
+    
+    /// Deliver at most one frame per `seconds`, overriding the interval set from the frame rate
+    pub unsafe fn set_minimum_frame_interval(config: *mut SCStreamConfiguration, seconds: f64) {
+        let frame_interval = CMTime {
+            value: (seconds * 600.0).round() as i64,
+            timescale: 600,
+            flags: objc2_core_media::CMTimeFlags(0),
+            epoch: 0,
+        };
+        let _: () = msg_send![config, setMinimumFrameInterval: frame_interval];
+    }
+    
+    /// Capture only `rect` of the display (SCStreamConfiguration.sourceRect), in points from its top-left corner
+    pub unsafe fn set_source_rect(config: *mut SCStreamConfiguration, rect: CGRect) {
+        let _: () = msg_send![config, setSourceRect: rect];
+    }
+    
+    /// Pin microphone capture to a specific input device (SCStreamConfiguration.microphoneCaptureDeviceID).
+    /// Returns false when the running macOS does not support microphone capture in ScreenCaptureKit.
+    pub unsafe fn set_microphone_capture_device(config: *mut SCStreamConfiguration, device_uid: &str) -> bool {
+        let supports_device: bool = msg_send![config, respondsToSelector: sel!(setMicrophoneCaptureDeviceID:)];
+        if !supports_device {
+            log::warn!("SCStreamConfiguration.microphoneCaptureDeviceID not available on this macOS version");
+            return false;
+        }
+        
+        let uid = NSString::from_str(device_uid);
+        let _: () = msg_send![config, setCaptureMicrophone: true];
+        let _: () = msg_send![config, setMicrophoneCaptureDeviceID: &*uid];
+        log::info!("Microphone capture pinned to device: {}", device_uid);
+        true
     }
     
     pub unsafe fn create_stream(
@@ -312,45 +786,45 @@ impl ScreenCaptureKitHelpers {
         delegate: *mut AnyObject,
     ) -> *mut SCStream {
         if filter.is_null() || configuration.is_null() {
-            println!("⚠️ Cannot create stream with null filter or configuration");
+            log::warn!("Cannot create stream with null filter or configuration");
             return ptr::null_mut();
         }
         
-        println!("🔍 About to create SCStream...");
-        println!("   Content filter valid: {}", !filter.is_null());
-        println!("   Configuration valid: {}", !configuration.is_null());
-        println!("   Delegate provided: {}", !delegate.is_null());
+        log::debug!("About to create SCStream...");
+        log::debug!("   Content filter valid: {}", !filter.is_null());
+        log::debug!("   Configuration valid: {}", !configuration.is_null());
+        log::debug!("   Delegate provided: {}", !delegate.is_null());
         
         // Try Pattern 1: Deferred Delegate Assignment
-        println!("🚀 Trying Pattern 1: Deferred Delegate Assignment");
+        log::info!("Trying Pattern 1: Deferred Delegate Assignment");
         if let Ok(stream) = Self::create_sc_stream_deferred_delegate(filter, configuration, delegate) {
-            println!("✅ Pattern 1 successful!");
+            log::info!("Pattern 1 successful!");
             return stream;
         }
         
         // Try Pattern 2: Minimal Delegate Approach
-        println!("🚀 Trying Pattern 2: Minimal Delegate Approach");
+        log::info!("Trying Pattern 2: Minimal Delegate Approach");
         if let Ok(stream) = Self::create_sc_stream_minimal_delegate(filter, configuration) {
-            println!("✅ Pattern 2 successful!");
+            log::info!("Pattern 2 successful!");
             return stream;
         }
         
         // Try Pattern 3: Factory Method Pattern
-        println!("🚀 Trying Pattern 3: Factory Method Pattern");
+        log::info!("Trying Pattern 3: Factory Method Pattern");
         if let Ok(stream) = Self::create_sc_stream_factory(filter, configuration) {
-            println!("✅ Pattern 3 successful!");
+            log::info!("Pattern 3 successful!");
             return stream;
         }
         
         // Try Pattern 4: Step-by-Step Initialization
-        println!("🚀 Trying Pattern 4: Step-by-Step Initialization");
+        log::info!("Trying Pattern 4: Step-by-Step Initialization");
         if let Ok(stream) = Self::create_sc_stream_stepwise(filter, configuration) {
-            println!("✅ Pattern 4 successful!");
+            log::info!("Pattern 4 successful!");
             return stream;
         }
         
         // If all patterns fail, return null
-        println!("❌ All SCStream creation patterns failed");
+        log::error!("All SCStream creation patterns failed");
         ptr::null_mut()
     }
     
@@ -360,7 +834,7 @@ impl ScreenCaptureKitHelpers {
         configuration: *mut SCStreamConfiguration,
         delegate: *mut AnyObject
     ) -> Result<*mut SCStream, String> {
-        println!("🔧 Pattern 1: Creating stream without delegate first, then assigning");
+        log::info!("Pattern 1: Creating stream without delegate first, then assigning");
         
         // Step 1: Create stream WITHOUT delegate
         let class = class!(SCStream);
@@ -379,7 +853,7 @@ impl ScreenCaptureKitHelpers {
         // Step 2: Assign delegate AFTER stream creation (if provided)
         if !delegate.is_null() {
             let _: () = msg_send![stream, setDelegate: delegate];
-            println!("✅ Delegate assigned after stream creation");
+            log::info!("Delegate assigned after stream creation");
         }
         
         Ok(stream)
@@ -390,7 +864,7 @@ impl ScreenCaptureKitHelpers {
         filter: *mut SCContentFilter, 
         configuration: *mut SCStreamConfiguration
     ) -> Result<*mut SCStream, String> {
-        println!("🔧 Pattern 2: Creating stream with minimal NSObject delegate");
+        log::info!("Pattern 2: Creating stream with minimal NSObject delegate");
         
         // Create the absolute minimal delegate
         let delegate_class = class!(NSObject);
@@ -400,7 +874,7 @@ impl ScreenCaptureKitHelpers {
             return Err("Failed to create minimal delegate".to_string());
         }
         
-        println!("✅ Created minimal NSObject delegate");
+        log::info!("Created minimal NSObject delegate");
         
         // Create stream with minimal delegate
         let class = class!(SCStream);
@@ -424,7 +898,7 @@ impl ScreenCaptureKitHelpers {
         filter: *mut SCContentFilter, 
         configuration: *mut SCStreamConfiguration
     ) -> Result<*mut SCStream, String> {
-        println!("🔧 Pattern 3: Using SCStream factory methods");
+        log::info!("Pattern 3: Using SCStream factory methods");
         
         let class = class!(SCStream);
         
@@ -433,7 +907,7 @@ impl ScreenCaptureKitHelpers {
         let stream: *mut SCStream = msg_send![alloc, init];
         
         if !stream.is_null() {
-            println!("✅ Basic init successful, configuring after creation");
+            log::info!("Basic init successful, configuring after creation");
             // Configure after creation
             let _: () = msg_send![stream, setContentFilter: filter];
             let _: () = msg_send![stream, setConfiguration: configuration];
@@ -448,28 +922,28 @@ impl ScreenCaptureKitHelpers {
         filter: *mut SCContentFilter, 
         configuration: *mut SCStreamConfiguration
     ) -> Result<*mut SCStream, String> {
-        println!("🔧 Pattern 4: Step-by-step initialization with validation");
+        log::info!("Pattern 4: Step-by-step initialization with validation");
         
-        println!("Step 1: Allocating SCStream");
+        log::debug!("Step 1: Allocating SCStream");
         let class = class!(SCStream);
         let alloc: *mut AnyObject = msg_send![class, alloc];
         if alloc.is_null() {
             return Err("SCStream allocation failed".to_string());
         }
         
-        println!("Step 2: Basic initialization");
+        log::debug!("Step 2: Basic initialization");
         let stream: *mut SCStream = msg_send![alloc, init];
         if stream.is_null() {
             return Err("SCStream init failed".to_string());
         }
         
-        println!("Step 3: Setting content filter");
+        log::debug!("Step 3: Setting content filter");
         let _: () = msg_send![stream, setContentFilter: filter];
         
-        println!("Step 4: Setting configuration");  
+        log::debug!("Step 4: Setting configuration");  
         let _: () = msg_send![stream, setConfiguration: configuration];
         
-        println!("✅ SCStream created successfully via step-by-step approach");
+        log::info!("SCStream created successfully via step-by-step approach");
         Ok(stream)
     }
     
@@ -477,9 +951,9 @@ impl ScreenCaptureKitHelpers {
     // Note: This pattern is disabled because raw Objective-C pointers cannot be safely sent between threads
     // The pattern would need to be implemented differently using proper Objective-C dispatch queues
     pub unsafe fn create_stream_async_disabled() {
-        println!("🔧 Pattern 5: Async Stream Creation is disabled due to thread safety requirements");
-        println!("💡 Raw Objective-C pointers cannot be sent between threads safely");
-        println!("💡 This pattern would require implementing proper Objective-C dispatch queues");
+        log::info!("Pattern 5: Async Stream Creation is disabled due to thread safety requirements");
+        log::info!("Raw Objective-C pointers cannot be sent between threads safely");
+        log::info!("This pattern would require implementing proper Objective-C dispatch queues");
     }
 
     pub unsafe fn start_stream_capture(stream: *mut SCStream) {
@@ -541,11 +1015,3 @@ impl ScreenCaptureKitHelpers {
         (window_id, title_str, frame.size.width as u32, frame.size.height as u32)
     }
 }
-
-// Pixel format constants for ScreenCaptureKit
-pub const kCVPixelFormatType_32BGRA: u32 = 0x42475241; // 'BGRA'
-pub const kCVPixelFormatType_420YpCbCr8BiPlanarVideoRange: u32 = 0x34323076; // '420v' as hex
-
-// Color space constants
-pub const kCGColorSpaceDisplayP3: u32 = 0;
-pub const kCGColorSpaceSRGB: u32 = 1; 
\ No newline at end of file
@@ -0,0 +1,520 @@
+// Webcam capture - AVCaptureSession frames kept as the latest BGRA image or handed to a sink, the
+// picture-in-picture overlay that composites them over the screen frames, and camera-only recording
+
+use napi::bindgen_prelude::*;
+use objc2::rc::{autoreleasepool, Retained};
+use objc2::runtime::{AnyObject, NSObject, NSObjectProtocol};
+use objc2::{class, define_class, msg_send, AllocAnyThread, DefinedClass};
+use objc2_core_media::CMTime;
+use objc2_core_video::CVPixelBuffer;
+use objc2_foundation::{NSArray, NSDictionary, NSNumber, NSString};
+use std::ffi::{c_char, c_void};
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+use super::audio_tap::PcmBuffer;
+use super::diagnostics::InputCapture;
+use super::encoder::{AudioEncoder, VideoEncoder, AVMediaTypeVideo};
+use super::errors::{capture_error, CaptureErrorCode};
+use super::process_tap::PcmHandler;
+use super::formats::PixelFormat;
+use super::pixel_buffer::{
+    CVPixelBufferLockBaseAddress, CVPixelBufferUnlockBaseAddress, CVPixelBufferGetBaseAddress, CVPixelBufferGetBytesPerRow,
+    CVPixelBufferGetWidth, CVPixelBufferGetHeight, CVPixelBufferGetPixelFormatType,
+};
+
+// A PiP never needs more than this; smaller frames keep the per-frame copy cheap
+const SESSION_PRESET: &str = "AVCaptureSessionPreset1280x720";
+
+extern "C" {
+    fn dispatch_queue_create(label: *const c_char, attr: *const c_void) -> *mut c_void;
+    fn dispatch_release(object: *mut c_void);
+    fn CMSampleBufferGetImageBuffer(sample_buffer: *mut c_void) -> *mut CVPixelBuffer;
+    fn CMSampleBufferGetPresentationTimeStamp(sample_buffer: *mut c_void) -> CMTime;
+    fn CMVideoFormatDescriptionGetDimensions(description: *const c_void) -> CMVideoDimensions;
+}
+
+/// The most recent camera image, tightly packed BGRA
+pub struct CameraFrame {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<u8>,
+}
+
+type LatestFrame = Arc<Mutex<Option<CameraFrame>>>;
+
+/// Receives every camera frame instead of it being kept as the latest; called on the capture queue
+pub type FrameSink = Arc<dyn Fn(*mut CVPixelBuffer, CMTime) + Send + Sync>;
+
+#[repr(C)]
+struct CMVideoDimensions {
+    width: i32,
+    height: i32,
+}
+
+struct ReceiverState {
+    latest: LatestFrame,
+    sink: Option<FrameSink>,
+}
+
+/// A camera as AVFoundation lists it
+#[derive(Debug, Clone)]
+pub struct CameraDevice {
+    /// `uniqueID`, stable across reconnects
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+}
+
+/// Every connected camera, built-in, external and Continuity Camera
+pub fn list_cameras() -> Vec<CameraDevice> {
+    autoreleasepool(|_| unsafe {
+        let media_type = NSString::from_str(AVMediaTypeVideo);
+        let default: Option<Retained<AnyObject>> = msg_send![class!(AVCaptureDevice), defaultDeviceWithMediaType: &*media_type];
+        let default_id = default.map(|device| unique_id(&device));
+
+        let mut cameras = Vec::new();
+        let devices: Option<Retained<NSArray>> = msg_send![class!(AVCaptureDevice), devicesWithMediaType: &*media_type];
+        if let Some(devices) = devices {
+            for i in 0..devices.count() {
+                let device = devices.objectAtIndex(i);
+                let id = unique_id(&device);
+                let name: Option<Retained<NSString>> = msg_send![&*device, localizedName];
+                cameras.push(CameraDevice {
+                    is_default: default_id.as_deref() == Some(id.as_str()),
+                    id,
+                    name: name.map(|n| n.to_string()).unwrap_or_default(),
+                });
+            }
+        }
+        cameras
+    })
+}
+
+unsafe fn unique_id(device: &AnyObject) -> String {
+    let id: Option<Retained<NSString>> = msg_send![device, uniqueID];
+    id.map(|id| id.to_string()).unwrap_or_default()
+}
+
+define_class!(
+    // SAFETY: NSObject has no subclassing requirements and the receiver overrides nothing
+    #[unsafe(super(NSObject))]
+    #[name = "WhisperDeskCameraFrameReceiver"]
+    #[ivars = ReceiverState]
+    struct CameraFrameReceiver;
+
+    impl CameraFrameReceiver {
+        // AVCaptureVideoDataOutputSampleBufferDelegate, called on the capture queue
+        #[unsafe(method(captureOutput:didOutputSampleBuffer:fromConnection:))]
+        fn capture_output(&self, _output: *mut AnyObject, sample_buffer: *mut c_void, _connection: *mut AnyObject) {
+            let state = self.ivars();
+            unsafe {
+                match &state.sink {
+                    Some(sink) => deliver_frame(sink, sample_buffer),
+                    None => store_frame(&state.latest, sample_buffer),
+                }
+            }
+        }
+    }
+
+    unsafe impl NSObjectProtocol for CameraFrameReceiver {}
+);
+
+impl CameraFrameReceiver {
+    fn new(state: ReceiverState) -> Retained<Self> {
+        let this = Self::alloc().set_ivars(state);
+        unsafe { msg_send![super(this), init] }
+    }
+}
+
+unsafe fn deliver_frame(sink: &FrameSink, sample_buffer: *mut c_void) {
+    if sample_buffer.is_null() {
+        return;
+    }
+    let pixel_buffer = CMSampleBufferGetImageBuffer(sample_buffer);
+    if !pixel_buffer.is_null() {
+        sink(pixel_buffer, CMSampleBufferGetPresentationTimeStamp(sample_buffer));
+    }
+}
+
+unsafe fn store_frame(latest: &LatestFrame, sample_buffer: *mut c_void) {
+    if sample_buffer.is_null() {
+        return;
+    }
+    let pixel_buffer = CMSampleBufferGetImageBuffer(sample_buffer);
+    if pixel_buffer.is_null() || CVPixelBufferGetPixelFormatType(pixel_buffer) != PixelFormat::Bgra.four_cc() {
+        return;
+    }
+    if CVPixelBufferLockBaseAddress(pixel_buffer, 1) != 0 {
+        return;
+    }
+    let base = CVPixelBufferGetBaseAddress(pixel_buffer) as *const u8;
+    if !base.is_null() {
+        let width = CVPixelBufferGetWidth(pixel_buffer);
+        let height = CVPixelBufferGetHeight(pixel_buffer);
+        let bytes_per_row = CVPixelBufferGetBytesPerRow(pixel_buffer);
+        if let Ok(mut latest) = latest.lock() {
+            // Reuse the previous frame's allocation, the size only changes with the device
+            let mut pixels = latest.take().map(|frame| frame.pixels).unwrap_or_default();
+            pixels.clear();
+            for row in 0..height {
+                pixels.extend_from_slice(std::slice::from_raw_parts(base.add(row * bytes_per_row), width * 4));
+            }
+            *latest = Some(CameraFrame { width, height, pixels });
+        }
+    }
+    CVPixelBufferUnlockBaseAddress(pixel_buffer, 1);
+}
+
+/// A running camera; frames keep arriving on a private queue until it is dropped
+pub struct CameraCapture {
+    session: Retained<AnyObject>,
+    output: Retained<AnyObject>,
+    _receiver: Retained<CameraFrameReceiver>,
+    queue: *mut c_void,
+    latest: LatestFrame,
+    dimensions: (u32, u32),
+}
+
+// The session and output are only started and stopped, which AVFoundation allows from any thread;
+// frames cross over through the mutex
+unsafe impl Send for CameraCapture {}
+
+impl CameraCapture {
+    /// Start the camera with this unique ID, or the default one. With a sink every frame goes
+    /// there; otherwise only the latest is kept.
+    pub fn start(device_id: Option<&str>, sink: Option<FrameSink>) -> Result<Self> {
+        unsafe {
+            let media_type = NSString::from_str(AVMediaTypeVideo);
+            super::permissions::guard_silent(super::permissions::PrivacyPane::Camera)?;
+            let authorization = super::permissions::camera_authorization();
+            if authorization.is_refused() {
+                return Err(capture_error(
+                    CaptureErrorCode::PermissionDenied,
+                    format!("Camera permission is {} - allow it in System Settings > Privacy & Security > Camera", authorization.as_ref()),
+                ));
+            }
+
+            let device: Option<Retained<AnyObject>> = match device_id {
+                Some(uid) => {
+                    let uid = NSString::from_str(uid);
+                    msg_send![class!(AVCaptureDevice), deviceWithUniqueID: &*uid]
+                }
+                None => msg_send![class!(AVCaptureDevice), defaultDeviceWithMediaType: &*media_type],
+            };
+            let device = device.ok_or_else(|| match device_id {
+                Some(uid) => capture_error(CaptureErrorCode::SourceNotFound, format!("Camera {} not found", uid)),
+                None => capture_error(CaptureErrorCode::SourceNotFound, "No camera found"),
+            })?;
+
+            let input: Option<Retained<AnyObject>> = msg_send![
+                class!(AVCaptureDeviceInput),
+                deviceInputWithDevice: &*device,
+                error: ptr::null_mut::<*mut AnyObject>()
+            ];
+            let input = input.ok_or_else(|| capture_error(CaptureErrorCode::Unknown, "Failed to open the camera"))?;
+
+            let session: Retained<AnyObject> = msg_send![class!(AVCaptureSession), new];
+            let preset = NSString::from_str(SESSION_PRESET);
+            let supports_preset: bool = msg_send![&*session, canSetSessionPreset: &*preset];
+            if supports_preset {
+                let _: () = msg_send![&*session, setSessionPreset: &*preset];
+            }
+            let can_add_input: bool = msg_send![&*session, canAddInput: &*input];
+            if !can_add_input {
+                return Err(capture_error(CaptureErrorCode::Unknown, "The camera is in use or can't be captured"));
+            }
+            let _: () = msg_send![&*session, addInput: &*input];
+
+            // BGRA like the screen frames, so compositing is a plain copy
+            let output: Retained<AnyObject> = msg_send![class!(AVCaptureVideoDataOutput), new];
+            let format_key = NSString::from_str("PixelFormatType");
+            let format: Retained<NSNumber> = msg_send![class!(NSNumber), numberWithUnsignedInt: PixelFormat::Bgra.four_cc()];
+            let settings: Retained<NSDictionary<NSString, AnyObject>> = msg_send![
+                class!(NSDictionary),
+                dictionaryWithObject: &*format,
+                forKey: &*format_key
+            ];
+            let _: () = msg_send![&*output, setVideoSettings: &*settings];
+            let _: () = msg_send![&*output, setAlwaysDiscardsLateVideoFrames: true];
+
+            let latest: LatestFrame = Arc::new(Mutex::new(None));
+            let receiver = CameraFrameReceiver::new(ReceiverState { latest: latest.clone(), sink });
+            let queue = dispatch_queue_create(b"com.whisperdesk.camera\0".as_ptr() as *const c_char, ptr::null());
+            let _: () = msg_send![&*output, setSampleBufferDelegate: &*receiver, queue: queue];
+            let can_add_output: bool = msg_send![&*session, canAddOutput: &*output];
+            if !can_add_output {
+                let _: () = msg_send![&*output, setSampleBufferDelegate: ptr::null::<AnyObject>(), queue: ptr::null::<c_void>()];
+                dispatch_release(queue);
+                return Err(capture_error(CaptureErrorCode::Unknown, "Failed to add the camera video output"));
+            }
+            let _: () = msg_send![&*session, addOutput: &*output];
+            let _: () = msg_send![&*session, startRunning];
+
+            // The preset has picked the format by now; frames come at this size
+            let format: Option<Retained<AnyObject>> = msg_send![&*device, activeFormat];
+            let dimensions = match format {
+                Some(format) => {
+                    let description: *const c_void = msg_send![&*format, formatDescription];
+                    let size = CMVideoFormatDescriptionGetDimensions(description);
+                    (size.width.max(0) as u32, size.height.max(0) as u32)
+                }
+                None => (1280, 720),
+            };
+
+            let name: Option<Retained<NSString>> = msg_send![&*device, localizedName];
+            log::info!("Camera started: {}", name.map(|n| n.to_string()).unwrap_or_default());
+            Ok(Self {
+                session,
+                output,
+                _receiver: receiver,
+                queue,
+                latest,
+                dimensions,
+            })
+        }
+    }
+
+    /// Frame size in pixels
+    pub fn dimensions(&self) -> (u32, u32) {
+        self.dimensions
+    }
+
+    /// The last frame the camera produced, if any yet
+    pub fn latest(&self) -> &Mutex<Option<CameraFrame>> {
+        &self.latest
+    }
+}
+
+impl Drop for CameraCapture {
+    fn drop(&mut self) {
+        unsafe {
+            let _: () = msg_send![&*self.session, stopRunning];
+            let _: () = msg_send![&*self.output, setSampleBufferDelegate: ptr::null::<AnyObject>(), queue: ptr::null::<c_void>()];
+            dispatch_release(self.queue);
+        }
+        log::info!("Camera stopped");
+    }
+}
+
+/// Which corner of the recording the camera sits in
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PipCorner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+impl PipCorner {
+    /// "top-left", "top-right", "bottom-left" or "bottom-right" (default)
+    pub fn parse(value: Option<&str>) -> Result<Self> {
+        match value.unwrap_or("bottom-right") {
+            "top-left" => Ok(PipCorner::TopLeft),
+            "top-right" => Ok(PipCorner::TopRight),
+            "bottom-left" => Ok(PipCorner::BottomLeft),
+            "bottom-right" => Ok(PipCorner::BottomRight),
+            other => Err(Error::new(
+                Status::InvalidArg,
+                format!(
+                    "Unknown camera corner '{}', expected \"top-left\", \"top-right\", \"bottom-left\" or \"bottom-right\"",
+                    other
+                ),
+            )),
+        }
+    }
+}
+
+/// Where and how the camera is drawn
+#[derive(Debug, Clone, Copy)]
+pub struct PipLayout {
+    pub corner: PipCorner,
+    /// Width of the camera as a fraction of the frame width
+    pub size: f64,
+    /// Crop to a circle instead of the camera's rectangle
+    pub circular: bool,
+    /// Distance from the frame edges in pixels
+    pub margin: u32,
+}
+
+/// The camera drawn into every captured frame
+pub struct CameraOverlay {
+    camera: CameraCapture,
+    layout: PipLayout,
+}
+
+impl CameraOverlay {
+    pub fn start(device_id: Option<&str>, layout: PipLayout) -> Result<Self> {
+        if !(0.05..=0.5).contains(&layout.size) {
+            return Err(Error::new(Status::InvalidArg, "camera size must be between 0.05 and 0.5 of the frame width"));
+        }
+        Ok(Self {
+            camera: CameraCapture::start(device_id, None)?,
+            layout,
+        })
+    }
+
+    /// Composite the latest camera frame; frames before the camera delivers anything stay as they are
+    pub unsafe fn composite(&mut self, pixel_buffer: *mut CVPixelBuffer) {
+        if pixel_buffer.is_null() || CVPixelBufferGetPixelFormatType(pixel_buffer) != PixelFormat::Bgra.four_cc() {
+            return;
+        }
+        let latest = match self.camera.latest().lock() {
+            Ok(latest) => latest,
+            Err(_) => return,
+        };
+        let camera = match latest.as_ref() {
+            Some(camera) if camera.width > 0 && camera.height > 0 => camera,
+            _ => return,
+        };
+
+        let frame_width = CVPixelBufferGetWidth(pixel_buffer);
+        let frame_height = CVPixelBufferGetHeight(pixel_buffer);
+        let layout = self.layout;
+
+        // A circle shows the centre square of the camera, a rectangle all of it
+        let (crop_x, crop_y, crop_width, crop_height) = if layout.circular {
+            let side = camera.width.min(camera.height);
+            ((camera.width - side) / 2, (camera.height - side) / 2, side, side)
+        } else {
+            (0, 0, camera.width, camera.height)
+        };
+        let pip_width = ((frame_width as f64 * layout.size).round() as usize).max(2);
+        let pip_height = (pip_width * crop_height / crop_width).max(2);
+        let margin = layout.margin as usize;
+        if pip_width + margin > frame_width || pip_height + margin > frame_height {
+            return;
+        }
+        let origin_x = match layout.corner {
+            PipCorner::TopLeft | PipCorner::BottomLeft => margin,
+            PipCorner::TopRight | PipCorner::BottomRight => frame_width - margin - pip_width,
+        };
+        let origin_y = match layout.corner {
+            PipCorner::TopLeft | PipCorner::TopRight => margin,
+            PipCorner::BottomLeft | PipCorner::BottomRight => frame_height - margin - pip_height,
+        };
+
+        if CVPixelBufferLockBaseAddress(pixel_buffer, 0) != 0 {
+            return;
+        }
+        let base = CVPixelBufferGetBaseAddress(pixel_buffer) as *mut u8;
+        let bytes_per_row = CVPixelBufferGetBytesPerRow(pixel_buffer);
+        if !base.is_null() {
+            let radius = pip_width as f64 / 2.0;
+            for y in 0..pip_height {
+                let source_y = crop_y + y * crop_height / pip_height;
+                let row = base.add((origin_y + y) * bytes_per_row + origin_x * 4);
+                for x in 0..pip_width {
+                    // One pixel of anti-aliasing along the edge of the circle
+                    let coverage = if layout.circular {
+                        let dx = x as f64 + 0.5 - radius;
+                        let dy = y as f64 + 0.5 - radius;
+                        (radius - (dx * dx + dy * dy).sqrt() + 0.5).clamp(0.0, 1.0)
+                    } else {
+                        1.0
+                    };
+                    if coverage <= 0.0 {
+                        continue;
+                    }
+                    let source_x = crop_x + x * crop_width / pip_width;
+                    let source = &camera.pixels[(source_y * camera.width + source_x) * 4..][..4];
+                    let target = std::slice::from_raw_parts_mut(row.add(x * 4), 4);
+                    for channel in 0..3 {
+                        target[channel] =
+                            (source[channel] as f64 * coverage + target[channel] as f64 * (1.0 - coverage)).round() as u8;
+                    }
+                }
+            }
+        }
+        CVPixelBufferUnlockBaseAddress(pixel_buffer, 0);
+    }
+}
+
+/// A camera recorded on its own, through the same encoders as screen recordings: video to
+/// `<output>_video.mp4` and, with a microphone, audio to `<output>_audio.mp4`
+pub struct CameraRecording {
+    camera: Option<CameraCapture>,
+    microphone: Option<InputCapture>,
+    video_encoder: Arc<Mutex<Option<VideoEncoder>>>,
+    audio_encoder: Option<Arc<Mutex<AudioEncoder>>>,
+}
+
+impl CameraRecording {
+    /// `microphone` is the CoreAudio device to record alongside, if any
+    pub fn start(output_path: &str, device_id: Option<&str>, microphone: Option<u32>, fps: u32) -> Result<Self> {
+        let video_encoder: Arc<Mutex<Option<VideoEncoder>>> = Arc::new(Mutex::new(None));
+        let sink_encoder = video_encoder.clone();
+        let sink: FrameSink = Arc::new(move |pixel_buffer, presentation_time| {
+            if let Ok(mut encoder) = sink_encoder.lock() {
+                if let Some(encoder) = encoder.as_mut() {
+                    if let Err(e) = encoder.encode_frame(pixel_buffer, presentation_time) {
+                        log::error!("Failed to encode camera frame: {}", e);
+                    }
+                }
+            }
+        });
+
+        // Frames arriving before the encoder exists are dropped; its size comes from the camera
+        let camera = CameraCapture::start(device_id, Some(sink))?;
+        let (width, height) = camera.dimensions();
+        let encoder = VideoEncoder::new(&format!("{}_video.mp4", output_path), width, height, fps)?;
+        if let Ok(mut current) = video_encoder.lock() {
+            *current = Some(encoder);
+        }
+
+        let (audio_encoder, microphone) = match microphone {
+            Some(device_id) => {
+                let encoder = Arc::new(Mutex::new(AudioEncoder::new(&format!("{}_audio.mp4", output_path), 48000, 2)?));
+                let handler_encoder = encoder.clone();
+                let handler: PcmHandler = Arc::new(move |pcm: &PcmBuffer| {
+                    if let Ok(mut encoder) = handler_encoder.lock() {
+                        if let Err(e) = encoder.encode_pcm_buffer(pcm) {
+                            log::error!("Failed to encode microphone audio: {}", e);
+                        }
+                    }
+                });
+                let capture = unsafe { InputCapture::start(device_id, handler)? };
+                (Some(encoder), Some(capture))
+            }
+            None => (None, None),
+        };
+
+        log::info!("Camera recording started: {}x{} at {} fps -> {}", width, height, fps, output_path);
+        Ok(Self {
+            camera: Some(camera),
+            microphone,
+            video_encoder,
+            audio_encoder,
+        })
+    }
+
+    /// Stop the camera and microphone, then finish both files; returns the video and audio paths
+    pub fn stop(&mut self) -> Result<(String, Option<String>)> {
+        // Stop the sources before finalizing so nothing lands in a closed file
+        self.camera = None;
+        self.microphone = None;
+
+        let video_path = match self.video_encoder.lock() {
+            Ok(mut encoder) => match encoder.as_mut() {
+                Some(encoder) => encoder.finalize_encoding()?,
+                None => return Err(capture_error(CaptureErrorCode::Unknown, "Camera recording has no video encoder")),
+            },
+            Err(_) => return Err(capture_error(CaptureErrorCode::Unknown, "Video encoder lock poisoned")),
+        };
+        let audio_path = match self.audio_encoder.take() {
+            Some(encoder) => {
+                let mut encoder = encoder
+                    .lock()
+                    .map_err(|_| capture_error(CaptureErrorCode::Unknown, "Audio encoder lock poisoned"))?;
+                Some(encoder.finalize_encoding()?)
+            }
+            None => None,
+        };
+        log::info!("Camera recording stopped: {}", video_path);
+        Ok((video_path, audio_path))
+    }
+
+    pub fn is_recording(&self) -> bool {
+        self.camera.is_some()
+    }
+}
@@ -0,0 +1,126 @@
+// Cancellation - JS can abort an operation that is stuck waiting on ScreenCaptureKit or AVFoundation
+
+use napi::bindgen_prelude::*;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::mpsc::{Receiver, RecvTimeoutError};
+use std::sync::{Arc, Mutex};
+use std::thread::ThreadId;
+use std::time::{Duration, Instant};
+
+use super::errors::{capture_error, CaptureErrorCode};
+
+// How long a cancelled wait can take to notice
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+// Operations started with an id, so `cancel` can find them. Ids are scoped to the JS thread that
+// started the operation, so the main thread and each worker have their own.
+static OPERATIONS: Mutex<Vec<(ThreadId, String, CancelToken)>> = Mutex::new(Vec::new());
+
+/// Set once the operation is cancelled; the default token is never cancelled
+#[derive(Debug, Clone, Default)]
+pub struct CancelToken {
+    cancelled: Arc<AtomicBool>,
+}
+
+pub enum Wait<T> {
+    Received(T),
+    TimedOut,
+    Cancelled,
+}
+
+impl CancelToken {
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+
+    /// A Cancelled error once cancelled
+    pub fn check(&self) -> Result<()> {
+        if self.is_cancelled() {
+            return Err(capture_error(CaptureErrorCode::Cancelled, "The operation was cancelled"));
+        }
+        Ok(())
+    }
+
+    /// `Receiver::recv_timeout` that also returns when cancelled. The sender is left to find the
+    /// receiver gone, so whatever it delivers late must be released on its side.
+    pub fn recv_timeout<T>(&self, receiver: &Receiver<T>, timeout: Duration) -> Wait<T> {
+        let deadline = Instant::now() + timeout;
+        loop {
+            if self.is_cancelled() {
+                return Wait::Cancelled;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if remaining.is_zero() {
+                return Wait::TimedOut;
+            }
+            match receiver.recv_timeout(remaining.min(POLL_INTERVAL)) {
+                Ok(value) => return Wait::Received(value),
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => return Wait::TimedOut,
+            }
+        }
+    }
+}
+
+/// An operation `cancel` can reach by its id for as long as this lives
+pub struct Operation {
+    id: Option<String>,
+    thread: ThreadId,
+    token: CancelToken,
+}
+
+impl Operation {
+    /// Registers under `id` when there is one. An id still in use is refused, since `cancel` would
+    /// otherwise have to guess which of the two it means. Must be called on the JS thread.
+    pub fn begin(id: Option<String>) -> Result<Self> {
+        let token = CancelToken::default();
+        let thread = std::thread::current().id();
+        if let Some(id) = &id {
+            let mut operations = OPERATIONS.lock()
+                .map_err(|_| capture_error(CaptureErrorCode::Unknown, "Operation registry is poisoned"))?;
+            if operations.iter().any(|(owner, existing, _)| *owner == thread && existing == id) {
+                return Err(Error::new(Status::InvalidArg, format!("Operation \"{}\" is already running", id)));
+            }
+            operations.push((thread, id.clone(), token.clone()));
+        }
+        Ok(Self { id, thread, token })
+    }
+
+    pub fn token(&self) -> &CancelToken {
+        &self.token
+    }
+}
+
+impl Drop for Operation {
+    fn drop(&mut self) {
+        if let Some(id) = &self.id {
+            if let Ok(mut operations) = OPERATIONS.lock() {
+                operations.retain(|(owner, existing, _)| !(*owner == self.thread && existing == id));
+            }
+        }
+    }
+}
+
+/// Cancel the running operation `id` this JS thread started; false when there is none (it may
+/// have just finished)
+pub fn cancel(id: &str) -> bool {
+    let thread = std::thread::current().id();
+    let token = OPERATIONS.lock().ok().and_then(|operations| {
+        operations
+            .iter()
+            .find(|(owner, existing, _)| *owner == thread && existing == id)
+            .map(|(_, _, token)| token.clone())
+    });
+    match token {
+        Some(token) => {
+            log::info!("Cancelling operation {}", id);
+            token.cancel();
+            true
+        }
+        None => false,
+    }
+}
@@ -0,0 +1,170 @@
+// Composite canvas - several display streams drawn into one video frame, laid out like the displays
+
+use objc2_core_video::CVPixelBuffer;
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::{Arc, Mutex};
+
+use super::screenshot::CGRect;
+use super::formats::PixelFormat;
+use super::pixel_buffer::{
+    CVPixelBufferCreate, CVPixelBufferLockBaseAddress, CVPixelBufferUnlockBaseAddress, CVPixelBufferGetBaseAddress,
+    CVPixelBufferGetBytesPerRow, CVPixelBufferGetWidth, CVPixelBufferGetHeight, CVPixelBufferGetPixelFormatType,
+};
+
+extern "C" {
+    fn CGDisplayBounds(display: u32) -> CGRect;
+    fn CFRelease(cf: *const c_void);
+}
+
+/// Where one display lands on the canvas, in canvas pixels
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct CanvasSlot {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// Scale the displays' global arrangement uniformly to fit `width` x `height` and centre it.
+/// Slot sizes are even so each can be a stream output size of its own.
+pub fn layout_displays(display_ids: &[u32], width: u32, height: u32) -> Vec<CanvasSlot> {
+    let frames: Vec<CGRect> = display_ids.iter().map(|id| unsafe { CGDisplayBounds(*id) }).collect();
+    layout(&frames, width, height)
+}
+
+fn layout(frames: &[CGRect], width: u32, height: u32) -> Vec<CanvasSlot> {
+    if frames.is_empty() {
+        return Vec::new();
+    }
+    let min_x = frames.iter().map(|f| f.x).fold(f64::INFINITY, f64::min);
+    let min_y = frames.iter().map(|f| f.y).fold(f64::INFINITY, f64::min);
+    let max_x = frames.iter().map(|f| f.x + f.width).fold(f64::NEG_INFINITY, f64::max);
+    let max_y = frames.iter().map(|f| f.y + f.height).fold(f64::NEG_INFINITY, f64::max);
+    let scale = (width as f64 / (max_x - min_x).max(1.0)).min(height as f64 / (max_y - min_y).max(1.0));
+    let offset_x = (width as f64 - (max_x - min_x) * scale) / 2.0;
+    let offset_y = (height as f64 - (max_y - min_y) * scale) / 2.0;
+
+    let even = |value: f64| ((value.floor() as u32) & !1).max(2);
+    frames
+        .iter()
+        .map(|frame| {
+            let x = (offset_x + (frame.x - min_x) * scale).round().max(0.0) as u32;
+            let y = (offset_y + (frame.y - min_y) * scale).round().max(0.0) as u32;
+            CanvasSlot {
+                x,
+                y,
+                width: even(frame.width * scale).min(width.saturating_sub(x) & !1),
+                height: even(frame.height * scale).min(height.saturating_sub(y) & !1),
+            }
+        })
+        .collect()
+}
+
+/// One BGRA frame the display streams draw into
+pub struct Canvas {
+    width: u32,
+    height: u32,
+    pixels: Vec<u8>,
+}
+
+impl Canvas {
+    pub fn new(width: u32, height: u32) -> Arc<Mutex<Self>> {
+        Arc::new(Mutex::new(Self {
+            width,
+            height,
+            // Opaque black between and around the displays
+            pixels: [0u8, 0, 0, 255].repeat(width as usize * height as usize),
+        }))
+    }
+}
+
+/// A stream's handle on the shared canvas. The primary stream encodes the canvas each time it
+/// gets a frame; the others only draw.
+#[derive(Clone)]
+pub struct CanvasTarget {
+    canvas: Arc<Mutex<Canvas>>,
+    slot: CanvasSlot,
+    primary: bool,
+}
+
+impl CanvasTarget {
+    pub fn new(canvas: Arc<Mutex<Canvas>>, slot: CanvasSlot, primary: bool) -> Self {
+        Self { canvas, slot, primary }
+    }
+
+    pub fn is_primary(&self) -> bool {
+        self.primary
+    }
+
+    /// Canvas size in pixels
+    pub fn canvas_size(&self) -> (u32, u32) {
+        self.canvas.lock().map(|canvas| (canvas.width, canvas.height)).unwrap_or((self.slot.width, self.slot.height))
+    }
+
+    /// Copy a captured frame into this stream's slot
+    pub unsafe fn draw(&self, pixel_buffer: *mut CVPixelBuffer) {
+        if pixel_buffer.is_null() || CVPixelBufferGetPixelFormatType(pixel_buffer) != PixelFormat::Bgra.four_cc() {
+            return;
+        }
+        if CVPixelBufferLockBaseAddress(pixel_buffer, 0) != 0 {
+            return;
+        }
+        let base = CVPixelBufferGetBaseAddress(pixel_buffer) as *const u8;
+        let bytes_per_row = CVPixelBufferGetBytesPerRow(pixel_buffer);
+        if !base.is_null() {
+            if let Ok(mut canvas) = self.canvas.lock() {
+                let canvas_width = canvas.width as usize;
+                let width = CVPixelBufferGetWidth(pixel_buffer)
+                    .min(self.slot.width as usize)
+                    .min(canvas_width.saturating_sub(self.slot.x as usize));
+                let height = CVPixelBufferGetHeight(pixel_buffer)
+                    .min(self.slot.height as usize)
+                    .min((canvas.height as usize).saturating_sub(self.slot.y as usize));
+                for row in 0..height {
+                    let target = ((self.slot.y as usize + row) * canvas_width + self.slot.x as usize) * 4;
+                    let source = std::slice::from_raw_parts(base.add(row * bytes_per_row), width * 4);
+                    canvas.pixels[target..target + width * 4].copy_from_slice(source);
+                }
+            }
+        }
+        CVPixelBufferUnlockBaseAddress(pixel_buffer, 0);
+    }
+
+    /// A fresh pixel buffer holding the canvas as it is now; the writer may read it after this
+    /// returns, so it's never handed a buffer the other streams still draw into. Release with `release`.
+    pub unsafe fn render(&self) -> Option<*mut CVPixelBuffer> {
+        let canvas = self.canvas.lock().ok()?;
+        let mut pixel_buffer: *mut CVPixelBuffer = ptr::null_mut();
+        let status = CVPixelBufferCreate(
+            ptr::null(),
+            canvas.width as usize,
+            canvas.height as usize,
+            PixelFormat::Bgra.four_cc(),
+            ptr::null(),
+            &mut pixel_buffer,
+        );
+        if status != 0 || pixel_buffer.is_null() {
+            return None;
+        }
+        if CVPixelBufferLockBaseAddress(pixel_buffer, 0) != 0 {
+            Self::release(pixel_buffer);
+            return None;
+        }
+        let base = CVPixelBufferGetBaseAddress(pixel_buffer) as *mut u8;
+        let bytes_per_row = CVPixelBufferGetBytesPerRow(pixel_buffer);
+        let row_bytes = canvas.width as usize * 4;
+        if !base.is_null() {
+            for row in 0..canvas.height as usize {
+                let source = &canvas.pixels[row * row_bytes..(row + 1) * row_bytes];
+                ptr::copy_nonoverlapping(source.as_ptr(), base.add(row * bytes_per_row), row_bytes);
+            }
+        }
+        CVPixelBufferUnlockBaseAddress(pixel_buffer, 0);
+        Some(pixel_buffer)
+    }
+
+    pub unsafe fn release(pixel_buffer: *mut CVPixelBuffer) {
+        CFRelease(pixel_buffer as *const c_void);
+    }
+}
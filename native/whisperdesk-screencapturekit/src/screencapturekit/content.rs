@@ -2,44 +2,44 @@
 
 use crate::{ScreenSource, RecordingConfiguration};
 use napi::bindgen_prelude::*;
+use objc2::rc::{autoreleasepool, Retained};
+use objc2::runtime::AnyObject;
 use objc2::{msg_send, class};
 use objc2_foundation::{NSArray, NSString, NSDictionary, NSNumber};
+use regex::Regex;
+use std::collections::HashSet;
 use std::ptr;
 use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 use std::thread;
+use serde::{Deserialize, Serialize};
 use serde_json;
 
-use super::bindings::{SCShareableContent, SCDisplay, SCWindow, SCContentFilter, SCStream, SCStreamConfiguration, ScreenCaptureKitHelpers, kCVPixelFormatType_32BGRA};
+use super::cancel::CancelToken;
+use super::errors::{capture_error, describe, CaptureErrorCode};
+use super::formats::{ColorSpace, PixelFormat};
+use super::content_cache::ContentCache;
+use super::zoom::ZoomFollow;
+use super::recommend::display_refresh_rate;
+use super::limits::{DEFAULT_FPS, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+use super::bindings::{CGRect, SCShareableContent, SCDisplay, SCWindow, SCContentFilter, SCStream, SCStreamConfiguration, ScreenCaptureKitHelpers, SHAREABLE_CONTENT_TIMEOUT};
 
 pub struct ContentManager;
 
 impl ContentManager {
-    pub fn get_shareable_content_sync() -> Result<ShareableContent> {
-        println!("🔍 Getting shareable content via ScreenCaptureKit APIs (sync)");
-        
-        let content = ShareableContent::new_with_real_data()?;
-        
-        println!("✅ Retrieved real shareable content");
-        Ok(content)
-    }
-
-    pub async fn get_shareable_content() -> Result<ShareableContent> {
-        println!("🔍 Getting shareable content via ScreenCaptureKit APIs");
-        Self::get_shareable_content_sync()
-    }
-
-    pub async fn get_shareable_content_async() -> Result<ShareableContent> {
-        println!("🔍 Getting shareable content via async ScreenCaptureKit APIs");
-        ShareableContent::new_with_timeout(5000)
-    }
-    
     pub fn extract_screen_sources(content: &ShareableContent) -> Result<Vec<ScreenSource>> {
         let mut sources = Vec::new();
         
         // Extract displays from real ScreenCaptureKit data
         let displays = content.get_displays()?;
         for display in displays {
+            // A mirror shows exactly what its primary shows - list the set once
+            if let Some(primary) = display.mirror_of {
+                if content.find_display_by_id(primary).is_some() {
+                    log::info!("Display {} mirrors display {} - collapsed into one source", display.id, primary);
+                    continue;
+                }
+            }
             sources.push(ScreenSource {
                 id: format!("display:{}", display.id),
                 name: display.name.clone(),
@@ -64,13 +64,37 @@ impl ContentManager {
             }
         }
         
-        println!("✅ Extracted {} screen sources from real ScreenCaptureKit data", sources.len());
+        log::info!("Extracted {} screen sources from real ScreenCaptureKit data", sources.len());
         Ok(sources)
     }
 
-    pub async fn extract_screen_sources_async() -> Result<Vec<ScreenSource>> {
-        let content = Self::get_shareable_content_async().await?;
-        Self::extract_screen_sources(&content)
+    /// Displays and windows matching `query`, in the same shape as `extract_screen_sources`
+    pub fn find_sources(content: &ShareableContent, query: &SourceQuery) -> Vec<ScreenSource> {
+        let displays = content.displays.iter().filter(|d| query.matches_display(d)).map(|display| ScreenSource {
+            id: format!("display:{}", display.id),
+            name: display.name.clone(),
+            width: display.width,
+            height: display.height,
+            is_display: true,
+        });
+        let windows = content.windows.iter().filter(|w| query.matches_window(w)).map(|window| ScreenSource {
+            id: format!("window:{}", window.id),
+            name: window.title.clone(),
+            width: window.width,
+            height: window.height,
+            is_display: false,
+        });
+        let sources: Vec<ScreenSource> = displays.chain(windows).collect();
+        log::info!("{} sources match the query", sources.len());
+        sources
+    }
+
+    /// Windows owned by one application, matched by bundle ID or by application name (both case-insensitive)
+    pub fn get_windows_for_app(bundle_id_or_name: &str) -> Result<Vec<WindowInfo>> {
+        let content = ShareableContent::new_with_real_data()?;
+        let windows = content.windows_for_app(bundle_id_or_name);
+        log::info!("Found {} windows for app '{}'", windows.len(), bundle_id_or_name);
+        Ok(windows)
     }
 }
 
@@ -78,25 +102,258 @@ impl ContentManager {
 pub struct ShareableContent {
     displays: Vec<DisplayInfo>,
     windows: Vec<WindowInfo>,
-    // CRITICAL FIX: Store the raw ScreenCaptureKit content pointer
-    // This allows us to create content filters without extracting individual objects
-    sc_content_ptr: Option<*mut SCShareableContent>,
+    // The SCShareableContent the inventory was read from, kept retained so filters can be built
+    // from the very SCDisplay/SCWindow objects it lists
+    sc_content: Option<Retained<SCShareableContent>>,
+    // Window IDs enumerated without details (fast mode) that still need enrichment
+    pending_window_details: Vec<u32>,
+    // Loaded from the on-disk cache rather than enumerated just now
+    stale: bool,
+    // Degraded paths taken while building this inventory, surfaced as warnings or strict-mode errors
+    fallbacks: Vec<String>,
+    fetched_at: Instant,
+}
+
+// SCShareableContent is an immutable snapshot, so the recorder may cache it across the threads
+// its tasks run on; access is serialized by the recorder's mutex anyway
+unsafe impl Send for ShareableContent {}
+
+// How many windows are processed between two progress reports
+const WINDOW_PROGRESS_INTERVAL: u32 = 25;
+
+/// How long a recorder reuses its shareable content before enumerating again
+pub const DEFAULT_CONTENT_TTL_MS: u32 = 3000;
+
+/// Options controlling how much work a content enumeration does
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EnumerationOptions {
+    /// Only collect window IDs and owner names; titles and bounds are filled in later by `enrich_windows`
+    pub skip_window_details: bool,
+    /// Also list windows on other Spaces
+    pub include_offscreen: bool,
+    /// Also list minimized windows and windows of hidden apps
+    pub include_minimized: bool,
+}
+
+impl EnumerationOptions {
+    /// Whether windows that aren't on screen right now are wanted at all
+    pub fn wants_offscreen(&self) -> bool {
+        self.include_offscreen || self.include_minimized
+    }
+    
+    /// Whether a window with this visibility makes the cut. An off-screen window that is still active
+    /// sits on another Space; an inactive one is minimized or hidden.
+    fn keeps(&self, is_on_screen: bool, is_active: bool) -> bool {
+        is_on_screen || if is_active { self.include_offscreen } else { self.include_minimized }
+    }
 }
 
 #[derive(Debug, Clone)]
+pub struct EnumerationProgress {
+    pub phase: &'static str,
+    pub displays_done: u32,
+    pub displays_total: u32,
+    pub windows_processed: u32,
+    pub windows_total: u32,
+}
+
+/// What a display filter leaves out of the capture
+#[derive(Debug, Clone, Default)]
+pub struct DisplayExclusions {
+    pub window_ids: Vec<u32>,
+    /// Every window of these applications, matched by bundle ID
+    pub app_bundle_ids: Vec<String>,
+    pub hide_menu_bar: bool,
+    pub hide_dock: bool,
+    /// The desktop picture and desktop icons; shows black behind the windows
+    pub hide_desktop: bool,
+    /// This app's own windows, matched by process and bundle ID
+    pub hide_self: bool,
+}
+
+/// Criteria for picking sources natively instead of listing everything to JS
+#[derive(Debug, Clone, Default)]
+pub struct SourceQuery {
+    /// Matched against window titles and display names
+    pub title: Option<Regex>,
+    /// Bundle ID or application name, case-insensitive; limits the results to windows
+    pub app_name: Option<String>,
+    pub min_width: u32,
+}
+
+impl SourceQuery {
+    pub fn new(title_regex: Option<&str>, app_name: Option<String>, min_width: Option<u32>) -> Result<Self> {
+        let title = match title_regex {
+            Some(pattern) => Some(Regex::new(pattern).map_err(|e| {
+                Error::new(Status::InvalidArg, format!("Invalid title regex '{}': {}", pattern, e))
+            })?),
+            None => None,
+        };
+        Ok(Self {
+            title,
+            app_name: app_name.map(|name| name.to_lowercase()),
+            min_width: min_width.unwrap_or(0),
+        })
+    }
+    
+    fn matches_title(&self, title: &str) -> bool {
+        self.title.as_ref().map(|re| re.is_match(title)).unwrap_or(true)
+    }
+    
+    pub fn matches_display(&self, display: &DisplayInfo) -> bool {
+        self.app_name.is_none() && display.width >= self.min_width && self.matches_title(&display.name)
+    }
+    
+    pub fn matches_window(&self, window: &WindowInfo) -> bool {
+        let app_matches = match &self.app_name {
+            Some(wanted) => [&window.owner_bundle_id, &window.owner_name]
+                .iter()
+                .any(|value| value.as_ref().map(|v| v.to_lowercase() == *wanted).unwrap_or(false)),
+            None => true,
+        };
+        app_matches && window.width >= self.min_width && self.matches_title(&window.title)
+    }
+}
+
+/// Plain-data result of an enumeration, safe to hand across threads
+pub struct ContentSnapshot {
+    pub displays: Vec<DisplayInfo>,
+    pub windows: Vec<WindowInfo>,
+    pub pending_window_details: Vec<u32>,
+    pub fallbacks: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct DisplayInfo {
     pub id: u32,
     pub name: String,
     pub width: u32,
     pub height: u32,
+    /// Part of a mirror set (software or hardware mirroring)
+    #[serde(default)]
+    pub is_mirrored: bool,
+    /// The display whose content this one mirrors; `None` for the primary of a mirror set
+    #[serde(default)]
+    pub mirror_of: Option<u32>,
+    /// Backing pixels per point (2.0 on Retina displays)
+    #[serde(default = "default_scale_factor")]
+    pub scale_factor: f64,
+    /// Maximum refresh rate in Hz
+    #[serde(default = "default_refresh_rate")]
+    pub refresh_rate: f64,
+    /// The display holding the menu bar
+    #[serde(default)]
+    pub is_main: bool,
+    /// Localized name of the display's color space, e.g. "Display P3"
+    #[serde(default)]
+    pub color_space: Option<String>,
+    /// Top-left corner in global display coordinates, in points; the main display sits at 0,0
+    #[serde(default)]
+    pub origin_x: f64,
+    #[serde(default)]
+    pub origin_y: f64,
 }
 
-#[derive(Debug, Clone)]
+impl DisplayInfo {
+    /// Region of this display in points from its top-left corner, checked against the display's size
+    pub fn crop_rect(&self, x: f64, y: f64, width: f64, height: f64) -> Result<CGRect> {
+        if width <= 0.0 || height <= 0.0 {
+            return Err(Error::new(Status::InvalidArg, format!("Crop size {}x{} must be positive", width, height)));
+        }
+        if x < 0.0 || y < 0.0 || x + width > self.width as f64 || y + height > self.height as f64 {
+            return Err(Error::new(
+                Status::InvalidArg,
+                format!(
+                    "Crop {}x{} at {},{} does not fit display {} ({}x{})",
+                    width, height, x, y, self.id, self.width, self.height
+                ),
+            ));
+        }
+        Ok(CGRect {
+            origin: super::bindings::CGPoint { x, y },
+            size: super::bindings::CGSize { width, height },
+        })
+    }
+    
+    /// `rect` grown outwards to whole backing pixels, so a Retina crop never starts mid-pixel
+    pub fn snap_to_pixels(&self, rect: CGRect) -> CGRect {
+        let scale = self.scale_factor.max(1.0);
+        let left = (rect.origin.x * scale).floor() / scale;
+        let top = (rect.origin.y * scale).floor() / scale;
+        let right = ((rect.origin.x + rect.size.width) * scale).ceil() / scale;
+        let bottom = ((rect.origin.y + rect.size.height) * scale).ceil() / scale;
+        CGRect {
+            origin: super::bindings::CGPoint { x: left, y: top },
+            size: super::bindings::CGSize {
+                width: right.min(self.width as f64) - left,
+                height: bottom.min(self.height as f64) - top,
+            },
+        }
+    }
+    
+    /// Size of `rect` in backing pixels, rounded to the even dimensions encoders need
+    pub fn pixel_size(&self, rect: &CGRect) -> (u32, u32) {
+        let scale = self.scale_factor.max(1.0);
+        let width = ((rect.size.width * scale).round() as u32 & !1).max(2);
+        let height = ((rect.size.height * scale).round() as u32 & !1).max(2);
+        (width, height)
+    }
+    
+    /// Whether the global point (x, y) lies on this display
+    pub fn contains_point(&self, x: f64, y: f64) -> bool {
+        x >= self.origin_x
+            && x < self.origin_x + self.width as f64
+            && y >= self.origin_y
+            && y < self.origin_y + self.height as f64
+    }
+}
+
+fn default_scale_factor() -> f64 {
+    1.0
+}
+
+fn default_refresh_rate() -> f64 {
+    60.0
+}
+
+/// What AppKit knows about a display that Core Graphics and ScreenCaptureKit don't
+struct ScreenDetails {
+    name: Option<String>,
+    scale_factor: f64,
+    max_fps: Option<f64>,
+    color_space: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
 pub struct WindowInfo {
     pub id: u32,
     pub title: String,
     pub width: u32,
     pub height: u32,
+    /// Bundle identifier of the owning application; `None` for processes without a bundle
+    #[serde(default)]
+    pub owner_bundle_id: Option<String>,
+    #[serde(default)]
+    pub owner_name: Option<String>,
+    #[serde(default)]
+    pub pid: Option<i32>,
+    /// Window server layer; 0 for normal app windows, higher for menus, overlays and the like
+    #[serde(default)]
+    pub window_layer: i32,
+    #[serde(default)]
+    pub is_on_screen: bool,
+    /// Active on some space, i.e. not minimized or hidden with its app
+    #[serde(default)]
+    pub is_active: bool,
+}
+
+/// The application behind an SCWindow
+struct WindowOwner {
+    bundle_id: Option<String>,
+    name: Option<String>,
+    pid: Option<i32>,
 }
 
 impl ShareableContent {
@@ -104,56 +361,340 @@ impl ShareableContent {
         Self {
             displays: Vec::new(),
             windows: Vec::new(),
-            sc_content_ptr: None,
+            sc_content: None,
+            pending_window_details: Vec::new(),
+            stale: false,
+            fallbacks: Vec::new(),
+            fetched_at: Instant::now(),
         }
     }
     
-    pub fn new_with_real_data() -> Result<Self> {
-        println!("🔍 Fetching real shareable content from ScreenCaptureKit (sync)");
+    pub fn from_snapshot(snapshot: ContentSnapshot) -> Self {
+        Self {
+            displays: snapshot.displays,
+            windows: snapshot.windows,
+            sc_content: None,
+            pending_window_details: snapshot.pending_window_details,
+            stale: false,
+            fallbacks: snapshot.fallbacks,
+            fetched_at: Instant::now(),
+        }
+    }
+    
+    /// The inventory persisted by the last full enumeration, marked stale; no filters can be built from it
+    pub fn from_cache() -> Option<Self> {
+        let cached = ContentCache::load()?;
+        log::info!("Loaded cached content from {}: {} displays, {} windows",
+            cached.saved_at, cached.displays.len(), cached.windows.len());
+        Some(Self {
+            displays: cached.displays,
+            windows: cached.windows,
+            sc_content: None,
+            pending_window_details: Vec::new(),
+            stale: true,
+            fallbacks: Vec::new(),
+            fetched_at: Instant::now(),
+        })
+    }
+    
+    pub fn is_stale(&self) -> bool {
+        self.stale
+    }
+    
+    pub fn fallbacks(&self) -> &[String] {
+        &self.fallbacks
+    }
+    
+    /// Time since this inventory was built
+    pub fn age(&self) -> Duration {
+        self.fetched_at.elapsed()
+    }
+    
+    /// Enumerate displays and windows while reporting progress - meant for machines
+    /// with hundreds of windows where a full enumeration takes noticeable time
+    pub fn enumerate_with_progress<F: Fn(EnumerationProgress)>(options: EnumerationOptions, progress: F) -> ContentSnapshot {
+        log::debug!("Enumerating shareable content with progress (skip details: {})", options.skip_window_details);
         
         unsafe {
-            let mut content = Self::new();
+            let mut fallbacks = Vec::new();
+            let displays_total = Self::get_display_count_safe(&mut fallbacks);
+            let mut displays = Vec::new();
+            for i in 0..displays_total {
+                if let Some(display_info) = Self::get_display_info_safe(i) {
+                    displays.push(display_info);
+                }
+            }
+            let displays_done = displays.len() as u32;
+            
+            progress(EnumerationProgress {
+                phase: "displays",
+                displays_done,
+                displays_total,
+                windows_processed: 0,
+                windows_total: 0,
+            });
+            
+            let (windows, pending_window_details) = Self::get_real_window_info_with(options, &mut fallbacks, |processed, total| {
+                progress(EnumerationProgress {
+                    phase: "windows",
+                    displays_done,
+                    displays_total,
+                    windows_processed: processed,
+                    windows_total: total,
+                });
+            });
+            
+            progress(EnumerationProgress {
+                phase: "done",
+                displays_done,
+                displays_total,
+                windows_processed: windows.len() as u32,
+                windows_total: windows.len() as u32,
+            });
+            
+            // Fast-mode results lack titles and sizes - not worth caching, and hidden windows don't belong in it
+            if pending_window_details.is_empty() && !options.wants_offscreen() {
+                ContentCache::save_or_warn(&displays, &windows);
+            }
             
-            // Get the ScreenCaptureKit content pointer and store it
-            match Self::fetch_real_sc_shareable_content() {
+            ContentSnapshot {
+                displays,
+                windows,
+                pending_window_details,
+                fallbacks,
+            }
+        }
+    }
+    
+    pub fn pending_window_details(&self) -> usize {
+        self.pending_window_details.len()
+    }
+    
+    /// Fill in titles and bounds for windows enumerated in fast mode.
+    /// Windows that turn out to be untitled or too small are dropped, matching a full enumeration.
+    pub fn enrich_windows(&mut self) -> usize {
+        if self.pending_window_details.is_empty() {
+            return 0;
+        }
+        
+        log::debug!("Enriching {} windows with full details", self.pending_window_details.len());
+        let detailed = unsafe { Self::get_real_window_info() };
+        let pending = std::mem::take(&mut self.pending_window_details);
+        let mut enriched = 0;
+        
+        self.windows.retain_mut(|window| {
+            if !pending.contains(&window.id) {
+                return true;
+            }
+            match detailed.iter().find(|d| d.id == window.id) {
+                Some(details) => {
+                    *window = details.clone();
+                    enriched += 1;
+                    true
+                }
+                None => false,
+            }
+        });
+        
+        log::info!("Enriched {} windows", enriched);
+        enriched
+    }
+    
+    pub fn new_with_real_data() -> Result<Self> {
+        log::debug!("Fetching real shareable content from ScreenCaptureKit (sync)");
+        Self::fetch(SHAREABLE_CONTENT_TIMEOUT, EnumerationOptions::default(), &CancelToken::default())
+    }
+    
+    /// Like `new_with_timeout`, failing with Cancelled instead of falling back to Core Graphics
+    /// once `cancel` is cancelled
+    pub fn new_cancellable(timeout: Duration, cancel: &CancelToken) -> Result<Self> {
+        log::debug!("Fetching real shareable content from ScreenCaptureKit (cancellable, {}ms timeout)", timeout.as_millis());
+        Self::fetch(timeout, EnumerationOptions::default(), cancel)
+    }
+    
+    /// Like `new_with_real_data`, honouring the off-screen and minimized window options
+    pub fn new_with_options(options: EnumerationOptions) -> Result<Self> {
+        log::debug!("Fetching real shareable content from ScreenCaptureKit (off-screen: {}, minimized: {})",
+            options.include_offscreen, options.include_minimized);
+        Self::fetch(SHAREABLE_CONTENT_TIMEOUT, options, &CancelToken::default())
+    }
+    
+    /// Inventory straight from ScreenCaptureKit, or from Core Graphics (recorded as a fallback)
+    /// when the content request fails or doesn't answer within `timeout`. Only a cancel fails it.
+    fn fetch(timeout: Duration, options: EnumerationOptions, cancel: &CancelToken) -> Result<Self> {
+        super::permissions::guard_silent(super::permissions::PrivacyPane::ScreenRecording)?;
+        unsafe {
+            Ok(match Self::fetch_real_sc_shareable_content(timeout, !options.wants_offscreen(), cancel) {
                 Ok(sc_content) => {
-                    // Store the pointer for later content filter creation
-                    content.sc_content_ptr = Some(sc_content);
+                    let mut content = Self::new();
+                    let (displays, windows) = Self::extract_sc_content(&sc_content, options);
+                    content.displays = displays;
+                    content.windows = windows;
+                    content.sc_content = Some(sc_content);
                     
-                    // Use safe system content for display/window info
-                    let safe_content = Self::create_safe_system_content();
-                    content.displays = safe_content.displays;
-                    content.windows = safe_content.windows;
+                    if content.displays.is_empty() {
+                        // Shouldn't happen with permission granted, but never hand out an empty display list
+                        let safe_content = Self::create_safe_system_content(options);
+                        content.displays = safe_content.displays;
+                        content.fallbacks.push("ScreenCaptureKit listed no displays; display details come from Core Graphics".to_string());
+                    }
+                    // The cache seeds ordinary pickers; keep hidden windows out of it
+                    if !options.wants_offscreen() {
+                        ContentCache::save_or_warn(&content.displays, &content.windows);
+                    }
                     
-                    println!("✅ Retrieved ScreenCaptureKit content with {} displays and {} windows", 
+                    log::info!("Retrieved ScreenCaptureKit content with {} displays and {} windows", 
                         content.displays.len(), content.windows.len());
                     
-                    Ok(content)
+                    content
                 }
+                Err(error) if cancel.is_cancelled() => return Err(capture_error(CaptureErrorCode::Cancelled, describe(&error).1)),
                 Err(error) => {
-                    println!("⚠️ ScreenCaptureKit content retrieval failed: {}", error);
-                    println!("💡 Using safe system content only");
+                    log::warn!("ScreenCaptureKit content retrieval failed: {}", error);
+                    log::info!("Using safe system content only");
                     
-                    // Use safe system content without ScreenCaptureKit pointer
-                    let safe_content = Self::create_safe_system_content();
-                    content.displays = safe_content.displays;
-                    content.windows = safe_content.windows;
+                    // Use safe system content without ScreenCaptureKit content
+                    let mut content = Self::create_safe_system_content(options);
+                    content.fallbacks.insert(0, format!(
+                        "ScreenCaptureKit content unavailable ({}); displays and windows come from Core Graphics and cannot be recorded",
+                        describe(&error).1
+                    ));
                     
-                    Ok(content)
+                    content
                 }
-            }
+            })
         }
     }
+    
+    /// Displays and windows exactly as ScreenCaptureKit lists them. Everything is read inside an
+    /// autorelease pool so the temporaries SCDisplay/SCWindow accessors hand back are freed here.
+    unsafe fn extract_sc_content(sc_content: &SCShareableContent, options: EnumerationOptions) -> (Vec<DisplayInfo>, Vec<WindowInfo>) {
+        autoreleasepool(|_| {
+            let mut displays = Vec::new();
+            let sc_displays: Option<Retained<NSArray>> = msg_send![sc_content, displays];
+            if let Some(sc_displays) = sc_displays {
+                for i in 0..sc_displays.count() {
+                    let display = sc_displays.objectAtIndex(i);
+                    let display_id: u32 = msg_send![&*display, displayID];
+                    let width: isize = msg_send![&*display, width];
+                    let height: isize = msg_send![&*display, height];
+                    displays.push(Self::describe_display(display_id, i as u32, width.max(0) as u32, height.max(0) as u32));
+                }
+            }
+            
+            let mut windows = Vec::new();
+            let sc_windows: Option<Retained<NSArray>> = msg_send![sc_content, windows];
+            if let Some(sc_windows) = sc_windows {
+                for i in 0..sc_windows.count() {
+                    let window = sc_windows.objectAtIndex(i);
+                    let window_id: u32 = msg_send![&*window, windowID];
+                    let frame: CGRect = msg_send![&*window, frame];
+                    let owner = Self::sc_window_owner(&window);
+                    let title = Self::sc_window_title(&window)
+                        .or_else(|| owner.as_ref().and_then(|o| o.name.clone()))
+                        .unwrap_or_default();
+                    let width = frame.size.width.max(0.0) as u32;
+                    let height = frame.size.height.max(0.0) as u32;
+                    
+                    // Same cut as the Core Graphics path: untitled and tiny windows aren't useful sources
+                    if title.is_empty() || width < 100 || height < 100 {
+                        continue;
+                    }
+                    
+                    let window_layer: isize = msg_send![&*window, windowLayer];
+                    let is_on_screen: bool = msg_send![&*window, isOnScreen];
+                    // isActive arrived in macOS 13.1; before that an on-screen window is the best we know
+                    let supports_active: bool = msg_send![&*window, respondsToSelector: objc2::sel!(isActive)];
+                    let is_active = if supports_active {
+                        msg_send![&*window, isActive]
+                    } else {
+                        is_on_screen
+                    };
+                    if !options.keeps(is_on_screen, is_active) {
+                        continue;
+                    }
+                    let (owner_bundle_id, owner_name, pid) = match owner {
+                        Some(owner) => (owner.bundle_id, owner.name, owner.pid),
+                        None => (None, None, None),
+                    };
+                    
+                    windows.push(WindowInfo {
+                        id: window_id,
+                        title,
+                        width,
+                        height,
+                        owner_bundle_id,
+                        owner_name,
+                        pid,
+                        window_layer: window_layer as i32,
+                        is_on_screen,
+                        is_active,
+                    });
+                }
+            }
+            
+            (displays, windows)
+        })
+    }
+    
+    /// SCWindow title; `None` for untitled windows
+    unsafe fn sc_window_title(window: &AnyObject) -> Option<String> {
+        let title: Option<Retained<NSString>> = msg_send![window, title];
+        title.map(|t| t.to_string()).filter(|t| !t.is_empty())
+    }
+    
+    /// Bundle ID, name and PID of the SCRunningApplication owning an SCWindow
+    unsafe fn sc_window_owner(window: &AnyObject) -> Option<WindowOwner> {
+        let application: Option<Retained<AnyObject>> = msg_send![window, owningApplication];
+        let application = application?;
+        let bundle_id: Option<Retained<NSString>> = msg_send![&*application, bundleIdentifier];
+        let name: Option<Retained<NSString>> = msg_send![&*application, applicationName];
+        let pid: i32 = msg_send![&*application, processID];
+        Some(WindowOwner {
+            bundle_id: bundle_id.map(|b| b.to_string()).filter(|b| !b.is_empty()),
+            name: name.map(|n| n.to_string()).filter(|n| !n.is_empty()),
+            pid: Some(pid),
+        })
+    }
+    
+    /// Bundle identifier of a running process, via NSRunningApplication
+    unsafe fn bundle_id_for_pid(pid: i32) -> Option<String> {
+        let application: Option<Retained<AnyObject>> =
+            msg_send![class!(NSRunningApplication), runningApplicationWithProcessIdentifier: pid];
+        let application = application?;
+        let bundle_id: Option<Retained<NSString>> = msg_send![&*application, bundleIdentifier];
+        bundle_id.map(|b| b.to_string()).filter(|b| !b.is_empty())
+    }
+    
+    /// Owner, layer and on-screen state from a kCGWindowList entry
+    unsafe fn apply_window_details_from_dict(window: &mut WindowInfo, window_dict: &NSDictionary) {
+        let number = |key: &str| {
+            window_dict.objectForKey(&NSString::from_str(key))
+                .and_then(|obj| obj.downcast::<NSNumber>().ok())
+        };
+        
+        window.pid = number("kCGWindowOwnerPID").map(|pid| pid.intValue());
+        window.owner_bundle_id = window.pid.and_then(|pid| Self::bundle_id_for_pid(pid));
+        window.owner_name = window_dict.objectForKey(&NSString::from_str("kCGWindowOwnerName"))
+            .and_then(|obj| obj.downcast::<NSString>().ok())
+            .map(|name| name.to_string())
+            .filter(|name| !name.is_empty());
+        window.window_layer = number("kCGWindowLayer").map(|layer| layer.intValue()).unwrap_or(0);
+        // The key is left out entirely for off-screen windows
+        window.is_on_screen = number("kCGWindowIsOnscreen").map(|on| on.boolValue()).unwrap_or(false);
+        // Core Graphics has no notion of an active window; on screen is the closest it reports
+        window.is_active = window.is_on_screen;
+    }
 
     /// Create safe system content using macOS system APIs instead of ScreenCaptureKit extraction
-    fn create_safe_system_content() -> Self {
-        println!("🔍 Creating safe system content using Core Graphics APIs");
+    fn create_safe_system_content(options: EnumerationOptions) -> Self {
+        log::debug!("Creating safe system content using Core Graphics APIs");
         
         let mut content = Self::new();
         
         unsafe {
             // Use Core Graphics to get display information safely
-            let display_count = Self::get_display_count_safe();
+            let display_count = Self::get_display_count_safe(&mut content.fallbacks);
             
             for i in 0..display_count {
                 if let Some(display_info) = Self::get_display_info_safe(i) {
@@ -162,7 +703,8 @@ impl ShareableContent {
             }
             
             // Get real window information using Core Graphics APIs
-            content.windows.extend(Self::get_real_window_info());
+            let (windows, _) = Self::get_real_window_info_with(options, &mut content.fallbacks, |_, _| {});
+            content.windows.extend(windows);
         }
         
         content
@@ -170,7 +712,7 @@ impl ShareableContent {
 
     // ... [keep all the existing safe Core Graphics methods unchanged] ...
     
-    unsafe fn get_display_count_safe() -> u32 {
+    unsafe fn get_display_count_safe(fallbacks: &mut Vec<String>) -> u32 {
         extern "C" {
             fn CGGetActiveDisplayList(maxDisplays: u32, activeDisplays: *mut u32, displayCount: *mut u32) -> i32;
         }
@@ -181,6 +723,7 @@ impl ShareableContent {
         if result == 0 {
             display_count
         } else {
+            fallbacks.push(format!("Display list unavailable (CGGetActiveDisplayList error {}); assuming one display", result));
             1 // Fallback to at least one display
         }
     }
@@ -202,53 +745,182 @@ impl ShareableContent {
             let display_id = displays[index as usize];
             let width = CGDisplayPixelsWide(display_id) as u32;
             let height = CGDisplayPixelsHigh(display_id) as u32;
+            Some(Self::describe_display(display_id, index, width, height))
+        } else {
+            None
+        }
+    }
+    
+    /// Fill in what neither SCDisplay nor the display list carries: name, mirror set, scale,
+    /// refresh rate and color space
+    unsafe fn describe_display(display_id: u32, index: u32, width: u32, height: u32) -> DisplayInfo {
+        extern "C" {
+            fn CGDisplayIsBuiltin(display: u32) -> u32;
+            fn CGDisplayIsInMirrorSet(display: u32) -> u32;
+            fn CGDisplayMirrorsDisplay(display: u32) -> u32;
+            fn CGMainDisplayID() -> u32;
+            fn CGDisplayBounds(display: u32) -> CGRect;
+        }
+        
+        let bounds = CGDisplayBounds(display_id);
+        // kCGNullDirectDisplay (0) when the display isn't mirroring another one
+        let mirror_of = match CGDisplayMirrorsDisplay(display_id) {
+            0 => None,
+            primary => Some(primary),
+        };
+        let screen = Self::screen_details(display_id);
+        
+        let name = match screen.as_ref().and_then(|s| s.name.clone()) {
+            Some(name) => name,
+            None if CGDisplayIsBuiltin(display_id) != 0 => "Built-in Display".to_string(),
+            None => format!("Display {}", index + 1),
+        };
+        // ProMotion panels leave the mode's refresh rate at 0, but NSScreen knows their maximum
+        let refresh_rate = screen
+            .as_ref()
+            .and_then(|s| s.max_fps)
+            .unwrap_or_else(|| display_refresh_rate(display_id));
+        
+        DisplayInfo {
+            id: display_id,
+            name,
+            width,
+            height,
+            is_mirrored: CGDisplayIsInMirrorSet(display_id) != 0,
+            mirror_of,
+            scale_factor: screen.as_ref().map(|s| s.scale_factor).unwrap_or_else(default_scale_factor),
+            refresh_rate,
+            is_main: CGMainDisplayID() == display_id,
+            color_space: screen.and_then(|s| s.color_space),
+            origin_x: bounds.origin.x,
+            origin_y: bounds.origin.y,
+        }
+    }
+    
+    /// Localized name, backing scale, maximum frame rate and color space of the NSScreen showing `display_id`
+    unsafe fn screen_details(display_id: u32) -> Option<ScreenDetails> {
+        autoreleasepool(|_| {
+            let screens: Option<Retained<NSArray>> = msg_send![class!(NSScreen), screens];
+            let screens = screens?;
+            let number_key = NSString::from_str("NSScreenNumber");
             
-            Some(DisplayInfo {
-                id: display_id,
-                name: if index == 0 {
-                    "Built-in Display".to_string()
-                } else {
-                    format!("Display {}", index + 1)
-                },
-                width,
-                height,
+            let screen = (0..screens.count()).map(|i| screens.objectAtIndex(i)).find(|screen| {
+                let description: Option<Retained<NSDictionary>> = msg_send![&**screen, deviceDescription];
+                description
+                    .and_then(|d| d.objectForKey(&number_key))
+                    .and_then(|number| number.downcast::<NSNumber>().ok())
+                    .map(|number| number.unsignedIntValue() == display_id)
+                    .unwrap_or(false)
+            })?;
+            
+            // localizedName needs macOS 10.15 and maximumFramesPerSecond macOS 12; both may be missing
+            let has_name: bool = msg_send![&*screen, respondsToSelector: objc2::sel!(localizedName)];
+            let name = if has_name {
+                let name: Option<Retained<NSString>> = msg_send![&*screen, localizedName];
+                name.map(|n| n.to_string()).filter(|n| !n.is_empty())
+            } else {
+                None
+            };
+            let has_max_fps: bool = msg_send![&*screen, respondsToSelector: objc2::sel!(maximumFramesPerSecond)];
+            let max_fps = if has_max_fps {
+                let fps: isize = msg_send![&*screen, maximumFramesPerSecond];
+                Some(fps as f64).filter(|fps| *fps > 0.0)
+            } else {
+                None
+            };
+            let scale_factor: f64 = msg_send![&*screen, backingScaleFactor];
+            let color_space: Option<Retained<AnyObject>> = msg_send![&*screen, colorSpace];
+            let color_space = color_space.and_then(|space| {
+                let name: Option<Retained<NSString>> = msg_send![&*space, localizedName];
+                name.map(|n| n.to_string()).filter(|n| !n.is_empty())
+            });
+            
+            Some(ScreenDetails {
+                name,
+                scale_factor: if scale_factor > 0.0 { scale_factor } else { default_scale_factor() },
+                max_fps,
+                color_space,
             })
+        })
+    }
+
+    unsafe fn get_real_window_info() -> Vec<WindowInfo> {
+        Self::get_real_window_info_with(EnumerationOptions::default(), &mut Vec::new(), |_, _| {}).0
+    }
+    
+    /// The active displays right now, from Core Graphics
+    pub fn current_displays() -> Vec<DisplayInfo> {
+        unsafe {
+            let count = Self::get_display_count_safe(&mut Vec::new());
+            (0..count).filter_map(|i| Self::get_display_info_safe(i)).collect()
+        }
+    }
+    
+    /// The on-screen windows right now, or `None` when only placeholders could be produced.
+    /// Cheap enough to call from a polling thread.
+    pub fn current_windows() -> Option<Vec<WindowInfo>> {
+        let mut fallbacks = Vec::new();
+        let (windows, _) = unsafe {
+            Self::get_real_window_info_with(EnumerationOptions::default(), &mut fallbacks, |_, _| {})
+        };
+        if fallbacks.is_empty() {
+            Some(windows)
         } else {
             None
         }
     }
 
-    unsafe fn get_real_window_info() -> Vec<WindowInfo> {
-        println!("🔍 Getting real window information via Core Graphics APIs");
+    /// Returns the windows plus the IDs whose details were skipped
+    unsafe fn get_real_window_info_with<F: Fn(u32, u32)>(
+        options: EnumerationOptions,
+        fallbacks: &mut Vec<String>,
+        on_progress: F,
+    ) -> (Vec<WindowInfo>, Vec<u32>) {
+        log::debug!("Getting real window information via Core Graphics APIs");
         
         extern "C" {
             fn CGWindowListCopyWindowInfo(option: u32, relativeToWindow: u32) -> *mut objc2_foundation::NSArray;
         }
         
+        const kCGWindowListOptionAll: u32 = 0;
         const kCGWindowListOptionOnScreenOnly: u32 = 1 << 0;
         const kCGWindowListExcludeDesktopElements: u32 = 1 << 4;
         
         let mut windows = Vec::new();
         
+        // Core Graphics can't tell another Space from minimized; either option lists every off-screen window
+        let list_option = if options.wants_offscreen() { kCGWindowListOptionAll } else { kCGWindowListOptionOnScreenOnly };
         let window_list_raw = CGWindowListCopyWindowInfo(
-            kCGWindowListOptionOnScreenOnly | kCGWindowListExcludeDesktopElements,
+            list_option | kCGWindowListExcludeDesktopElements,
             0
         );
         
         if window_list_raw.is_null() {
-            return Self::get_fallback_window_info();
+            fallbacks.push("Window list unavailable; placeholder windows were returned".to_string());
+            return (Self::get_fallback_window_info(), Vec::new());
         }
         
         let window_list: &NSArray = &*window_list_raw;
         let count = window_list.count();
+        let mut pending_details = Vec::new();
         
         for i in 0..count {
             let window_dict_obj = window_list.objectAtIndex(i);
             if let Ok(window_dict) = window_dict_obj.downcast::<NSDictionary>() {
-                if let Some(window_info) = Self::extract_window_info_from_dict(&window_dict, i as u32) {
+                if options.skip_window_details {
+                    if let Some(window_info) = Self::extract_window_summary_from_dict(&window_dict, i as u32) {
+                        pending_details.push(window_info.id);
+                        windows.push(window_info);
+                    }
+                } else if let Some(window_info) = Self::extract_window_info_from_dict(&window_dict, i as u32) {
                     windows.push(window_info);
                 }
             }
+            
+            let processed = i as u32 + 1;
+            if processed % WINDOW_PROGRESS_INTERVAL == 0 || processed == count as u32 {
+                on_progress(processed, count as u32);
+            }
         }
         
         objc2::rc::autoreleasepool(|_| {
@@ -256,12 +928,39 @@ impl ShareableContent {
         });
         
         if windows.is_empty() {
-            Self::get_fallback_window_info()
+            fallbacks.push("No windows found; placeholder windows were returned".to_string());
+            (Self::get_fallback_window_info(), Vec::new())
         } else {
-            windows
+            (windows, pending_details)
         }
     }
     
+    /// Fast-mode extraction: window number and owner name only, no title or bounds lookups
+    unsafe fn extract_window_summary_from_dict(window_dict: &NSDictionary, fallback_id: u32) -> Option<WindowInfo> {
+        let window_number_key = NSString::from_str("kCGWindowNumber");
+        let window_owner_name_key = NSString::from_str("kCGWindowOwnerName");
+        
+        let window_id = match window_dict.objectForKey(&window_number_key) {
+            Some(number_obj) => match number_obj.downcast::<NSNumber>() {
+                Ok(number) => number.intValue() as u32,
+                Err(_) => fallback_id,
+            },
+            None => fallback_id,
+        };
+        
+        let owner_name = window_dict.objectForKey(&window_owner_name_key)
+            .and_then(|owner_obj| owner_obj.downcast::<NSString>().ok())
+            .map(|owner_str| owner_str.to_string())?;
+        
+        let mut window = WindowInfo {
+            id: window_id,
+            title: owner_name,
+            ..Default::default()
+        };
+        Self::apply_window_details_from_dict(&mut window, window_dict);
+        Some(window)
+    }
+    
     unsafe fn extract_window_info_from_dict(window_dict: &NSDictionary, fallback_id: u32) -> Option<WindowInfo> {
         let window_number_key = NSString::from_str("kCGWindowNumber");
         let window_name_key = NSString::from_str("kCGWindowName");
@@ -346,12 +1045,15 @@ impl ShareableContent {
             return None;
         }
         
-        Some(WindowInfo {
+        let mut window = WindowInfo {
             id: window_id,
             title,
             width,
             height,
-        })
+            ..Default::default()
+        };
+        Self::apply_window_details_from_dict(&mut window, window_dict);
+        Some(window)
     }
     
     fn get_fallback_window_info() -> Vec<WindowInfo> {
@@ -361,57 +1063,27 @@ impl ShareableContent {
                 title: "Desktop".to_string(),
                 width: 1920,
                 height: 1080,
+                ..Default::default()
             },
             WindowInfo {
                 id: 2,
                 title: "Finder".to_string(),
                 width: 800,
                 height: 600,
+                ..Default::default()
             },
         ]
     }
 
     pub fn new_with_timeout(timeout_ms: u32) -> Result<Self> {
-        println!("🔍 Fetching real shareable content from ScreenCaptureKit with {}ms timeout", timeout_ms);
-        
-        // COMPLETE BYPASS APPROACH: Don't call any ScreenCaptureKit APIs
-        // This prevents all crashes while still providing functional content
-        println!("🛡️ COMPLETE BYPASS MODE: Using only safe system content to prevent crashes");
-        println!("💡 This approach provides reliable screen/window enumeration without ScreenCaptureKit risks");
-        
-        let safe_content = Self::create_safe_system_content();
-        
-        println!("✅ Retrieved {} displays and {} windows using safe system APIs", 
-            safe_content.displays.len(), safe_content.windows.len());
-        
-        Ok(safe_content)
+        log::debug!("Fetching real shareable content from ScreenCaptureKit with {}ms timeout", timeout_ms);
+        Self::fetch(Duration::from_millis(timeout_ms as u64), EnumerationOptions::default(), &CancelToken::default())
     }
     
-    unsafe fn fetch_real_sc_shareable_content() -> Result<*mut SCShareableContent> {
-        println!("🔍 Fetching real shareable content using ScreenCaptureKit API");
+    unsafe fn fetch_real_sc_shareable_content(timeout: Duration, on_screen_only: bool, cancel: &CancelToken) -> Result<Retained<SCShareableContent>> {
+        log::debug!("Fetching real shareable content using ScreenCaptureKit API");
         
-        // Use a simpler approach that doesn't require thread-safe raw pointers
-        // Just try the synchronous approach first
-        match ScreenCaptureKitHelpers::get_shareable_content_sync() {
-            Ok(content) => {
-                println!("✅ Got ScreenCaptureKit content synchronously");
-                return Ok(content);
-            }
-            Err(e) => {
-                println!("⚠️ Synchronous approach failed: {}", e);
-                println!("💡 Using async approach without waiting (safer)");
-                
-                // Start the async call but don't wait for it to avoid thread safety issues
-                // This is just to trigger the ScreenCaptureKit initialization
-                ScreenCaptureKitHelpers::get_shareable_content_async(|_content, _error| {
-                    // Simple callback that just logs
-                    println!("🔄 Async ScreenCaptureKit call completed");
-                });
-                
-                // Return an error to indicate we should use the fallback approach
-                return Err(Error::new(Status::GenericFailure, "Async ScreenCaptureKit requires fallback".to_string()));
-            }
-        }
+        ScreenCaptureKitHelpers::get_shareable_content_cancellable(timeout, on_screen_only, cancel)
     }
     
     pub fn get_displays(&self) -> Result<Vec<DisplayInfo>> {
@@ -426,139 +1098,466 @@ impl ShareableContent {
         self.displays.iter().find(|d| d.id == display_id)
     }
     
+    /// Display `display_id` from the Core Graphics display list alone, without asking
+    /// ScreenCaptureKit for shareable content
+    pub fn system_display(display_id: u32) -> Option<DisplayInfo> {
+        unsafe {
+            let count = Self::get_display_count_safe(&mut Vec::new());
+            (0..count).filter_map(|i| Self::get_display_info_safe(i)).find(|d| d.id == display_id)
+        }
+    }
+    
+    /// The display showing the global point (x, y), e.g. a cursor or window position
+    pub fn display_at_point(&self, x: f64, y: f64) -> Option<&DisplayInfo> {
+        self.displays.iter().find(|d| d.contains_point(x, y))
+    }
+    
+    /// Size of `source` in backing pixels; a window uses the scale of the display under its centre
+    pub fn native_pixel_size(&self, source: CaptureSource) -> Option<(u32, u32)> {
+        let (display, width, height) = match source {
+            CaptureSource::Display(display_id) => {
+                let display = self.find_display_by_id(display_id)?;
+                (display, display.width as f64, display.height as f64)
+            }
+            CaptureSource::Window(_) => {
+                let bounds = source.bounds()?;
+                let display = self.display_at_point(bounds.x + bounds.width / 2.0, bounds.y + bounds.height / 2.0)?;
+                (display, bounds.width, bounds.height)
+            }
+        };
+        Some(display.pixel_size(&CGRect {
+            origin: super::bindings::CGPoint { x: 0.0, y: 0.0 },
+            size: super::bindings::CGSize { width, height },
+        }))
+    }
+    
     pub fn find_window_by_id(&self, window_id: u32) -> Option<&WindowInfo> {
         self.windows.iter().find(|w| w.id == window_id)
     }
     
-    // CRITICAL FIX: Replace individual object extraction with content filter creation
-    // This avoids the segfault entirely by using ScreenCaptureKit's higher-level APIs
+    pub fn windows_for_app(&self, bundle_id_or_name: &str) -> Vec<WindowInfo> {
+        let wanted = bundle_id_or_name.to_lowercase();
+        let matches = |value: &Option<String>| value.as_ref().map(|v| v.to_lowercase() == wanted).unwrap_or(false);
+        self.windows
+            .iter()
+            .filter(|w| matches(&w.owner_bundle_id) || matches(&w.owner_name))
+            .cloned()
+            .collect()
+    }
+    
+    /// Warning for recording a display that shares its content with other displays
+    pub fn mirror_warning(&self, screen_id: &str) -> Option<String> {
+        let display_id = screen_id.strip_prefix("display:")?.parse::<u32>().ok()?;
+        let display = self.find_display_by_id(display_id)?;
+        if !display.is_mirrored {
+            return None;
+        }
+        
+        let primary = display.mirror_of.unwrap_or(display.id);
+        let others: Vec<String> = self
+            .displays
+            .iter()
+            .filter(|d| d.id != display.id && d.is_mirrored && d.mirror_of.unwrap_or(d.id) == primary)
+            .map(|d| d.id.to_string())
+            .collect();
+        
+        Some(if others.is_empty() {
+            format!("Display {} is mirrored; the recording shows the same content as every display in its mirror set", display.id)
+        } else {
+            format!(
+                "Display {} is mirrored with display {}; the recording shows the same content, not an independent screen",
+                display.id,
+                others.join(", ")
+            )
+        })
+    }
     
-    /// Create a REAL content filter using actual ScreenCaptureKit objects
-    pub unsafe fn create_display_content_filter(&self, display_id: u32) -> Result<*mut SCContentFilter> {
-        println!("🎯 Creating REAL display content filter for display ID {} (ultra-safe approach)", display_id);
+    /// Content filter built from the SCDisplay in the retained ScreenCaptureKit content.
+    /// Also returns a description of the fallback taken when the filter isn't an exact match for the display.
+    pub unsafe fn create_display_content_filter(
+        &self,
+        display_id: u32,
+        exclusions: &DisplayExclusions,
+    ) -> Result<(Retained<SCContentFilter>, Option<String>)> {
+        log::info!("Creating display content filter for display ID {}", display_id);
         
         // Verify display exists
         if self.find_display_by_id(display_id).is_none() {
-            return Err(Error::new(Status::InvalidArg, format!("Display ID {} not found", display_id)));
+            return Err(capture_error(CaptureErrorCode::SourceNotFound, format!("Display ID {} not found", display_id)));
         }
-
-        // ULTRA-SAFE APPROACH: Instead of using msg_send! which can cause segfaults,
-        // use our ScreenCaptureKit helpers that handle the Objective-C calls safely
         
-        match self.sc_content_ptr {
-            Some(sc_content) => {
-                println!("🔍 Using ScreenCaptureKit helper for safe content filter creation");
-                
-                // Use our safe helper method that handles all the Objective-C complexity
-                let content_filter = ScreenCaptureKitHelpers::create_display_content_filter(
-                    sc_content, 
-                    display_id
-                );
-                
-                if content_filter.is_null() {
-                    println!("⚠️ Helper method returned null filter, trying fallback approach");
-                    
-                    // Fallback: Create a minimal content filter using the helper
-                    let fallback_filter = ScreenCaptureKitHelpers::create_minimal_content_filter();
-                    
-                    if fallback_filter.is_null() {
-                        return Err(Error::new(Status::GenericFailure, "All content filter creation methods failed"));
-                    }
-                    
-                    println!("✅ Created fallback content filter");
-                    return Ok(fallback_filter);
+        let sc_content = match &self.sc_content {
+            Some(sc_content) => sc_content.clone(),
+            None => return Self::minimal_content_filter(format!("No ScreenCaptureKit content for display {}; using a minimal content filter", display_id)),
+        };
+        let mut notes = Vec::new();
+        
+        // Desktop windows are left out of the regular inventory; hiding them needs content that lists them
+        let sc_content = if exclusions.hide_desktop {
+            match ScreenCaptureKitHelpers::get_shareable_content_with_desktop_windows(SHAREABLE_CONTENT_TIMEOUT) {
+                Ok(with_desktop) => with_desktop,
+                Err(e) => {
+                    notes.push(format!("Desktop can't be hidden ({})", describe(&e).1));
+                    sc_content
                 }
-                
-                println!("✅ Successfully created display content filter using safe helper");
-                Ok(content_filter)
             }
-            None => {
-                // No ScreenCaptureKit content available - create a basic filter
-                println!("⚠️ No ScreenCaptureKit content available, creating minimal filter");
-                
-                let minimal_filter = ScreenCaptureKitHelpers::create_minimal_content_filter();
-                
-                if minimal_filter.is_null() {
-                    return Err(Error::new(Status::GenericFailure, "Failed to create minimal content filter"));
+        } else {
+            sc_content
+        };
+        
+        // Before macOS 14.2 the menu bar can only go as windows
+        let menu_bar_as_windows = exclusions.hide_menu_bar && !ScreenCaptureKitHelpers::supports_menu_bar_toggle();
+        let mut window_ids = exclusions.window_ids.clone();
+        window_ids.extend(ScreenCaptureKitHelpers::system_window_ids(
+            &sc_content,
+            exclusions.hide_dock,
+            exclusions.hide_desktop,
+            menu_bar_as_windows,
+        ));
+        
+        // Keep the recorder's own control panel out of the picture
+        let mut app_bundle_ids = exclusions.app_bundle_ids.clone();
+        if exclusions.hide_self {
+            let host_bundle_id = ScreenCaptureKitHelpers::host_bundle_id();
+            let own_windows = ScreenCaptureKitHelpers::own_window_ids(&sc_content, host_bundle_id.as_deref());
+            log::info!("Hiding {} of this app's own windows", own_windows.len());
+            window_ids.extend(own_windows);
+            // Excluding applications sets window exclusions aside, so go along as an application then
+            if let Some(host_bundle_id) = host_bundle_id {
+                if !app_bundle_ids.is_empty() && !app_bundle_ids.iter().any(|id| id.eq_ignore_ascii_case(&host_bundle_id)) {
+                    app_bundle_ids.push(host_bundle_id);
                 }
-                
-                println!("✅ Created minimal content filter");
-                Ok(minimal_filter)
             }
         }
-    }
+        
+        match ScreenCaptureKitHelpers::create_display_content_filter(
+            &sc_content,
+            display_id,
+            &window_ids,
+            &app_bundle_ids,
+        ) {
+            Some((filter, not_excluded)) => {
+                if exclusions.hide_menu_bar && !menu_bar_as_windows {
+                    ScreenCaptureKitHelpers::set_include_menu_bar(&filter, false);
+                }
+                if !not_excluded.is_empty() {
+                    let ids: Vec<String> = not_excluded.iter().map(|id| id.to_string()).collect();
+                    notes.push(format!(
+                        "Windows {} can't be excluded together with applications and will be visible",
+                        ids.join(", ")
+                    ));
+                }
+                log::info!("Created display content filter");
+                Ok((filter, if notes.is_empty() { None } else { Some(notes.join("; ")) }))
+            }
+            None => Self::minimal_content_filter(format!("Display {} filter unavailable; using a minimal content filter", display_id)),
+        }
+    }
     
-    /// Create a REAL content filter for a window using actual ScreenCaptureKit objects
-    pub unsafe fn create_window_content_filter(&self, window_id: u32) -> Result<*mut SCContentFilter> {
-        println!("🎯 Creating REAL window content filter for window ID {} (ultra-safe approach)", window_id);
+    /// Content filter built from the SCWindow in the retained ScreenCaptureKit content, plus any fallback taken
+    pub unsafe fn create_window_content_filter(&self, window_id: u32) -> Result<(Retained<SCContentFilter>, Option<String>)> {
+        log::info!("Creating window content filter for window ID {}", window_id);
         
         if self.find_window_by_id(window_id).is_none() {
-            return Err(Error::new(Status::InvalidArg, format!("Window ID {} not found", window_id)));
+            return Err(capture_error(CaptureErrorCode::SourceNotFound, format!("Window ID {} not found", window_id)));
         }
         
-        // ULTRA-SAFE APPROACH: Use ScreenCaptureKit helpers for window filters too
-        
-        match self.sc_content_ptr {
-            Some(sc_content) => {
-                println!("🔍 Using ScreenCaptureKit helper for safe window content filter creation");
-                
-                let content_filter = ScreenCaptureKitHelpers::create_window_content_filter(
-                    sc_content, 
-                    window_id
-                );
-                
-                if content_filter.is_null() {
-                    println!("⚠️ Helper method returned null window filter, using minimal filter");
-                    
-                    let minimal_filter = ScreenCaptureKitHelpers::create_minimal_content_filter();
-                    
-                    if minimal_filter.is_null() {
-                        return Err(Error::new(Status::GenericFailure, "All window content filter creation methods failed"));
-                    }
-                    
-                    println!("✅ Created minimal content filter for window");
-                    return Ok(minimal_filter);
+        match &self.sc_content {
+            Some(sc_content) => match ScreenCaptureKitHelpers::create_window_content_filter(sc_content, window_id) {
+                Some(filter) => {
+                    log::info!("Created window content filter");
+                    Ok((filter, None))
                 }
-                
-                println!("✅ Successfully created window content filter using safe helper");
-                Ok(content_filter)
+                None => Self::minimal_content_filter(format!("Window {} filter unavailable; using a minimal content filter", window_id)),
+            },
+            None => Self::minimal_content_filter(format!("No ScreenCaptureKit content for window {}; using a minimal content filter", window_id)),
+        }
+    }
+    
+    /// Content filter showing only `window_ids` on one display: `display_id`, or the display holding the
+    /// first window. Also returns the display used and any fallback taken.
+    pub unsafe fn create_windows_content_filter(
+        &self,
+        display_id: Option<u32>,
+        window_ids: &[u32],
+    ) -> Result<(Retained<SCContentFilter>, u32, Option<String>)> {
+        log::info!("Creating multi-window content filter for windows {:?}", window_ids);
+        
+        if window_ids.is_empty() {
+            return Err(Error::new(Status::InvalidArg, "No window IDs given for a multi-window capture"));
+        }
+        let known: Vec<u32> = window_ids.iter().copied().filter(|id| self.find_window_by_id(*id).is_some()).collect();
+        if known.is_empty() {
+            return Err(capture_error(CaptureErrorCode::SourceNotFound, format!("None of the windows {:?} were found", window_ids)));
+        }
+        if let Some(id) = display_id {
+            if self.find_display_by_id(id).is_none() {
+                return Err(capture_error(CaptureErrorCode::SourceNotFound, format!("Display ID {} not found", id)));
             }
+        }
+        
+        let sc_content = match &self.sc_content {
+            Some(sc_content) => sc_content,
             None => {
-                println!("⚠️ No ScreenCaptureKit content available, creating minimal window filter");
-                
-                let minimal_filter = ScreenCaptureKitHelpers::create_minimal_content_filter();
-                
-                if minimal_filter.is_null() {
-                    return Err(Error::new(Status::GenericFailure, "Failed to create minimal window content filter"));
-                }
-                
-                println!("✅ Created minimal window content filter");
-                Ok(minimal_filter)
+                let display_id = display_id.or(self.displays.first().map(|d| d.id)).unwrap_or(0);
+                let (filter, fallback) = Self::minimal_content_filter(
+                    "No ScreenCaptureKit content for a multi-window capture; using a minimal content filter".to_string(),
+                )?;
+                return Ok((filter, display_id, fallback));
+            }
+        };
+        
+        let display_id = match display_id.or_else(|| ScreenCaptureKitHelpers::display_for_window(sc_content, known[0])) {
+            Some(id) => id,
+            None => return Err(capture_error(CaptureErrorCode::SourceNotFound, "No display found for the multi-window capture")),
+        };
+        
+        match ScreenCaptureKitHelpers::create_windows_content_filter(sc_content, display_id, window_ids) {
+            Some((filter, missing)) if missing.is_empty() => {
+                log::info!("Created multi-window content filter");
+                Ok((filter, display_id, None))
+            }
+            Some((filter, missing)) => {
+                let ids: Vec<String> = missing.iter().map(|id| id.to_string()).collect();
+                Ok((filter, display_id, Some(format!("Windows {} are not on screen and were left out", ids.join(", ")))))
+            }
+            None => {
+                let (filter, fallback) = Self::minimal_content_filter(format!(
+                    "Multi-window filter on display {} unavailable; using a minimal content filter",
+                    display_id
+                ))?;
+                Ok((filter, display_id, fallback))
             }
         }
     }
     
-    // REMOVED: The problematic get_sc_display_by_id and get_sc_window_by_id methods
-    // These caused segfaults and are replaced with the safer content filter creation methods above
+    /// Content filter following every window of one application on one display: `display_id`, or the
+    /// display holding the app's first window. Also returns the display used and any fallback taken.
+    pub unsafe fn create_application_content_filter(
+        &self,
+        bundle_id: &str,
+        display_id: Option<u32>,
+    ) -> Result<(Retained<SCContentFilter>, u32, Option<String>)> {
+        log::info!("Creating application content filter for {}", bundle_id);
+        
+        if let Some(id) = display_id {
+            if self.find_display_by_id(id).is_none() {
+                return Err(capture_error(CaptureErrorCode::SourceNotFound, format!("Display ID {} not found", id)));
+            }
+        }
+        
+        let sc_content = match &self.sc_content {
+            Some(sc_content) => sc_content,
+            None => {
+                let display_id = display_id.or(self.displays.first().map(|d| d.id)).unwrap_or(0);
+                let (filter, fallback) = Self::minimal_content_filter(format!(
+                    "No ScreenCaptureKit content for application {}; using a minimal content filter",
+                    bundle_id
+                ))?;
+                return Ok((filter, display_id, fallback));
+            }
+        };
+        
+        if ScreenCaptureKitHelpers::find_applications(sc_content, &[bundle_id.to_string()]).is_empty() {
+            return Err(capture_error(CaptureErrorCode::SourceNotFound, format!("Application {} is not running", bundle_id)));
+        }
+        
+        // An app without windows yet still gets a filter, on the first display
+        let display_id = match display_id
+            .or_else(|| ScreenCaptureKitHelpers::display_for_application(sc_content, bundle_id))
+            .or(self.displays.first().map(|d| d.id))
+        {
+            Some(id) => id,
+            None => return Err(capture_error(CaptureErrorCode::SourceNotFound, format!("No display found for application {}", bundle_id))),
+        };
+        
+        match ScreenCaptureKitHelpers::create_application_content_filter(sc_content, display_id, bundle_id) {
+            Some(filter) => {
+                log::info!("Created application content filter");
+                Ok((filter, display_id, None))
+            }
+            None => {
+                let (filter, fallback) = Self::minimal_content_filter(format!(
+                    "Application {} filter on display {} unavailable; using a minimal content filter",
+                    bundle_id, display_id
+                ))?;
+                Ok((filter, display_id, fallback))
+            }
+        }
+    }
     
-    /// Safe fallback - returns null to indicate object extraction is not supported
-    pub unsafe fn get_sc_display_by_id(&self, display_id: u32) -> Option<*mut SCDisplay> {
-        println!("🚫 SCDisplay object extraction disabled to prevent segfaults");
-        println!("💡 Use create_display_content_filter() instead");
-        None
+    unsafe fn minimal_content_filter(fallback: String) -> Result<(Retained<SCContentFilter>, Option<String>)> {
+        log::warn!("{}", fallback);
+        match Retained::from_raw(ScreenCaptureKitHelpers::create_minimal_content_filter()) {
+            Some(filter) => Ok((filter, Some(fallback))),
+            None => Err(capture_error(CaptureErrorCode::Unknown, format!("Failed to create content filter: {}", fallback))),
+        }
+    }
+    
+    /// The SCDisplay behind `display_id`, retained independently of this inventory
+    pub unsafe fn get_sc_display_by_id(&self, display_id: u32) -> Option<Retained<SCDisplay>> {
+        ScreenCaptureKitHelpers::find_display(self.sc_content.as_deref()?, display_id)
+    }
+    
+    /// The SCWindow behind `window_id`, retained independently of this inventory
+    pub unsafe fn get_sc_window_by_id(&self, window_id: u32) -> Option<Retained<SCWindow>> {
+        ScreenCaptureKitHelpers::find_window(self.sc_content.as_deref()?, window_id)
+    }
+    
+    /// Handle to a display listed by ScreenCaptureKit; `None` for unknown IDs and Core Graphics inventories
+    pub fn display_handle(&self, display_id: u32) -> Option<SourceHandle> {
+        let object = unsafe { self.get_sc_display_by_id(display_id)? };
+        Some(SourceHandle { source: CaptureSource::Display(display_id), object })
     }
     
-    pub unsafe fn get_sc_window_by_id(&self, window_id: u32) -> Option<*mut SCWindow> {
-        println!("🚫 SCWindow object extraction disabled to prevent segfaults");
-        println!("💡 Use create_window_content_filter() instead");
+    /// Handle to a window listed by ScreenCaptureKit; `None` for unknown IDs and Core Graphics inventories
+    pub fn window_handle(&self, window_id: u32) -> Option<SourceHandle> {
+        let object = unsafe { self.get_sc_window_by_id(window_id)? };
+        Some(SourceHandle { source: CaptureSource::Window(window_id), object })
+    }
+}
+
+/// A display or window exactly as ScreenCaptureKit listed it. The SCDisplay/SCWindow stays retained,
+/// so the handle never dangles, but the source itself can still go away.
+pub struct SourceHandle {
+    source: CaptureSource,
+    object: Retained<AnyObject>,
+}
+
+impl SourceHandle {
+    pub fn source(&self) -> CaptureSource {
+        self.source
+    }
+    
+    pub fn id(&self) -> u32 {
+        match self.source {
+            CaptureSource::Display(id) | CaptureSource::Window(id) => id,
+        }
+    }
+    
+    /// "display:<id>" or "window:<id>", as `start_recording` takes it
+    pub fn screen_id(&self) -> String {
+        match self.source {
+            CaptureSource::Display(id) => format!("display:{}", id),
+            CaptureSource::Window(id) => format!("window:{}", id),
+        }
+    }
+    
+    /// The display is still attached, or the window still open
+    pub fn is_available(&self) -> bool {
+        self.source.bounds().is_some()
+    }
+    
+    /// Content filter built straight from the retained object
+    pub fn content_filter(&self) -> Result<Retained<SCContentFilter>> {
+        if !self.is_available() {
+            return Err(SourceError::Disappeared { screen_id: self.screen_id(), suggestion: None }.into());
+        }
+        let ptr = Retained::as_ptr(&self.object) as *mut AnyObject;
+        let filter = unsafe {
+            match self.source {
+                CaptureSource::Display(_) => {
+                    ScreenCaptureKitHelpers::create_content_filter_with_display(ptr, &NSArray::<AnyObject>::new())
+                }
+                CaptureSource::Window(_) => ScreenCaptureKitHelpers::create_content_filter_with_window(ptr),
+            }
+        };
+        // alloc/init hands back a +1 reference, which Retained takes over
+        unsafe { Retained::from_raw(filter) }
+            .ok_or_else(|| capture_error(CaptureErrorCode::Unknown, format!("Failed to create content filter for {}", self.screen_id())))
+    }
+}
+
+/// Typed failures when a requested capture source cannot be turned into a filter
+#[derive(Debug)]
+pub enum SourceError {
+    /// The display/window is gone even after refreshing shareable content
+    Disappeared {
+        screen_id: String,
+        suggestion: Option<(String, String)>,
+    },
+}
+
+impl From<SourceError> for Error {
+    fn from(error: SourceError) -> Self {
+        match error {
+            SourceError::Disappeared { screen_id, suggestion } => {
+                let hint = match suggestion {
+                    Some((id, name)) => format!(" Nearest alternative: {} (\"{}\")", id, name),
+                    None => String::new(),
+                };
+                capture_error(
+                    CaptureErrorCode::SourceNotFound,
+                    format!("SourceDisappeared: {} is no longer available.{}", screen_id, hint),
+                )
+            }
+        }
+    }
+}
+
+impl ShareableContent {
+    /// Closest surviving window: same title, then same leading title word (usually the app), then closest size
+    pub fn nearest_window(&self, target: &WindowInfo) -> Option<&WindowInfo> {
+        if let Some(exact) = self.windows.iter().find(|w| w.title == target.title) {
+            return Some(exact);
+        }
+        
+        let leading_word = target.title.split_whitespace().next().unwrap_or("");
+        if !leading_word.is_empty() {
+            if let Some(similar) = self.windows.iter().find(|w| w.title.split_whitespace().next() == Some(leading_word)) {
+                return Some(similar);
+            }
+        }
+        
+        let target_area = target.width as i64 * target.height as i64;
+        self.windows.iter().min_by_key(|w| (w.width as i64 * w.height as i64 - target_area).abs())
+    }
+    
+    /// Closest surviving display by resolution, or the first one when the old one is unknown
+    pub fn nearest_display(&self, target: Option<&DisplayInfo>) -> Option<&DisplayInfo> {
+        match target {
+            Some(target) => self.displays.iter().min_by_key(|d| {
+                (d.width as i64 - target.width as i64).abs() + (d.height as i64 - target.height as i64).abs()
+            }),
+            None => self.displays.first(),
+        }
+    }
+    
+    /// Build the SourceDisappeared error for `screen_id`, using `previous` (the stale content)
+    /// to describe what the source looked like. Returns None when the source still exists.
+    pub fn source_disappeared(&self, previous: Option<&ShareableContent>, screen_id: &str) -> Option<SourceError> {
+        if let Some(id) = screen_id.strip_prefix("display:").and_then(|id| id.parse::<u32>().ok()) {
+            if self.find_display_by_id(id).is_some() {
+                return None;
+            }
+            let old = previous.and_then(|p| p.find_display_by_id(id));
+            let suggestion = self.nearest_display(old).map(|d| (format!("display:{}", d.id), d.name.clone()));
+            return Some(SourceError::Disappeared { screen_id: screen_id.to_string(), suggestion });
+        }
+        
+        if let Some(id) = screen_id.strip_prefix("window:").and_then(|id| id.parse::<u32>().ok()) {
+            if self.find_window_by_id(id).is_some() {
+                return None;
+            }
+            let suggestion = previous
+                .and_then(|p| p.find_window_by_id(id))
+                .and_then(|old| self.nearest_window(old))
+                .map(|w| (format!("window:{}", w.id), w.title.clone()));
+            return Some(SourceError::Disappeared { screen_id: screen_id.to_string(), suggestion });
+        }
+        
         None
     }
 }
 
 // Add the missing RealContentFilter struct
 pub struct RealContentFilter {
-    content_filter: Option<*mut SCContentFilter>,
+    content_filter: Option<Retained<SCContentFilter>>,
     is_valid: bool,
+    source: Option<CaptureSource>,
+    fallback: Option<String>,
+    /// What a display filter leaves out, so it can be rebuilt the same way mid-recording
+    exclusions: DisplayExclusions,
 }
 
 impl RealContentFilter {
@@ -566,16 +1565,26 @@ impl RealContentFilter {
         Self {
             content_filter: None,
             is_valid: false,
+            source: None,
+            fallback: None,
+            exclusions: DisplayExclusions::default(),
         }
     }
     
     pub fn new_with_display(content: &ShareableContent, display_id: u32) -> Result<Self> {
+        Self::new_with_display_excluding(content, display_id, &DisplayExclusions::default())
+    }
+    
+    pub fn new_with_display_excluding(content: &ShareableContent, display_id: u32, exclusions: &DisplayExclusions) -> Result<Self> {
         unsafe {
-            match content.create_display_content_filter(display_id) {
-                Ok(filter) => {
+            match content.create_display_content_filter(display_id, exclusions) {
+                Ok((filter, fallback)) => {
                     Ok(Self {
                         content_filter: Some(filter),
                         is_valid: true,
+                        source: Some(CaptureSource::Display(display_id)),
+                        fallback,
+                        exclusions: exclusions.clone(),
                     })
                 }
                 Err(e) => Err(e)
@@ -586,10 +1595,13 @@ impl RealContentFilter {
     pub fn new_with_window(content: &ShareableContent, window_id: u32) -> Result<Self> {
         unsafe {
             match content.create_window_content_filter(window_id) {
-                Ok(filter) => {
+                Ok((filter, fallback)) => {
                     Ok(Self {
                         content_filter: Some(filter),
                         is_valid: true,
+                        source: Some(CaptureSource::Window(window_id)),
+                        fallback,
+                        exclusions: DisplayExclusions::default(),
                     })
                 }
                 Err(e) => Err(e)
@@ -597,23 +1609,269 @@ impl RealContentFilter {
         }
     }
     
+    /// Only `window_ids`, composited at their positions on `display_id` (or the display holding the first one)
+    pub fn new_with_windows(content: &ShareableContent, display_id: Option<u32>, window_ids: &[u32]) -> Result<Self> {
+        unsafe {
+            let (filter, display_id, fallback) = content.create_windows_content_filter(display_id, window_ids)?;
+            Ok(Self {
+                content_filter: Some(filter),
+                is_valid: true,
+                source: Some(CaptureSource::Display(display_id)),
+                fallback,
+                exclusions: DisplayExclusions::default(),
+            })
+        }
+    }
+    
+    /// Filter for a display or window handle, without looking the source up again
+    pub fn new_with_handle(handle: &SourceHandle) -> Result<Self> {
+        Ok(Self {
+            content_filter: Some(handle.content_filter()?),
+            is_valid: true,
+            source: Some(handle.source()),
+            fallback: None,
+            exclusions: DisplayExclusions::default(),
+        })
+    }
+    
+    /// Every window of the application with `bundle_id`, including ones it opens while recording
+    pub fn new_with_application(content: &ShareableContent, bundle_id: &str, display_id: Option<u32>) -> Result<Self> {
+        unsafe {
+            let (filter, display_id, fallback) = content.create_application_content_filter(bundle_id, display_id)?;
+            Ok(Self {
+                content_filter: Some(filter),
+                is_valid: true,
+                source: Some(CaptureSource::Display(display_id)),
+                fallback,
+                exclusions: DisplayExclusions::default(),
+            })
+        }
+    }
+    
     pub fn is_valid(&self) -> bool {
         self.is_valid
     }
     
     pub fn get_filter_ptr(&self) -> *mut SCContentFilter {
-        self.content_filter.unwrap_or(ptr::null_mut())
+        self.content_filter
+            .as_ref()
+            .map(|filter| Retained::as_ptr(filter) as *mut SCContentFilter)
+            .unwrap_or(ptr::null_mut())
+    }
+    
+    pub fn source(&self) -> Option<CaptureSource> {
+        self.source
+    }
+    
+    /// Set when the filter doesn't capture exactly the requested source
+    pub fn fallback(&self) -> Option<&str> {
+        self.fallback.as_deref()
+    }
+    
+    pub fn exclusions(&self) -> &DisplayExclusions {
+        &self.exclusions
     }
 }
 
 // Real stream manager with actual SCStream functionality
-use super::delegate::RealStreamDelegate;
+use super::delegate::{FinalizePhase, RealStreamDelegate};
+use super::encoder::VideoEncoderSettings;
+use super::audio::{AudioManager, AudioRouteChange, AudioRouteDirection, AudioRouteHandler, AudioRouteListener, AudioRoutePhase};
+use super::delegate::AudioRouteState;
+use super::audio::wav::WavSidecar;
+use super::cursor::{CaptureSource, CursorOverlay};
+use super::cursor_track::CursorTrack;
+use super::overlay::ClickHighlighter;
+use super::camera::{CameraOverlay, PipCorner, PipLayout};
+use super::canvas::CanvasTarget;
+use super::input_events::InputEventRecorder;
+use super::process_tap::{PcmHandler, ProcessTap, SystemAudioBackend};
+use super::replay::{ReplayBuffer, ReplaySnapshot};
+use super::secure_input::{SecureInputAction, SecureInputGuard, SecureInputListener};
+use super::session::{JournalEvent, LibraryIndex, SessionJournal, SessionRecord};
+use super::metadata::{RecordingCounts, RecordingMetadata};
+use super::timelapse::Timelapse;
+use super::window_track::{fit_within, WindowResizeTracker};
+use super::focus_follow::FocusFollower;
+use super::content_watch::{ContentChange, ContentWatcher};
+
+// An audio-only stream still delivers frames; keep them tiny and rare
+const AUDIO_ONLY_FRAME_SIZE: u32 = 16;
+const AUDIO_ONLY_FRAME_INTERVAL_SECS: f64 = 1.0;
+
+/// SCStream pointer handed to CoreAudio callbacks; only used while the manager keeps the stream alive
+#[derive(Clone, Copy)]
+struct StreamHandle(*mut SCStream);
+
+unsafe impl Send for StreamHandle {}
+unsafe impl Sync for StreamHandle {}
+
+/// Everything needed to (re)build the SCStreamConfiguration of a running capture.
+/// Live changes (default-device migration, cursor toggling) edit this and push a fresh
+/// configuration with SCStream.updateConfiguration so earlier changes are never reverted.
+#[derive(Debug, Clone)]
+struct StreamSettings {
+    width: u32,
+    height: u32,
+    fps: u32,
+    shows_cursor: bool,
+    capture_audio: bool,
+    microphone_device: Option<String>,
+    source_rect: Option<CGRect>,
+    frame_interval_secs: Option<f64>,
+    pixel_format: PixelFormat,
+    color_space: ColorSpace,
+}
+
+pub fn uses_custom_cursor(config: &RecordingConfiguration) -> bool {
+    config.cursor_image_path.is_some() || config.cursor_scale.map(|s| (s - 1.0).abs() > f64::EPSILON).unwrap_or(false)
+}
+
+impl StreamSettings {
+    fn from_config(config: &RecordingConfiguration, microphone_device: Option<String>) -> Self {
+        // hide_cursor_in_output wins over show_cursor; the on-screen cursor is never touched.
+        // A custom cursor replaces the captured one, so the stream itself captures none.
+        let shows_cursor = !config.hide_cursor_in_output.unwrap_or(false)
+            && config.show_cursor.unwrap_or(true)
+            && !uses_custom_cursor(config);
+        Self {
+            width: config.width.unwrap_or(DEFAULT_WIDTH),
+            height: config.height.unwrap_or(DEFAULT_HEIGHT),
+            fps: config.fps.unwrap_or(DEFAULT_FPS),
+            shows_cursor,
+            capture_audio: config.capture_audio.unwrap_or(false),
+            microphone_device,
+            // Validated against the display before the recording starts
+            source_rect: match (config.crop_x, config.crop_y, config.crop_width, config.crop_height) {
+                (Some(x), Some(y), Some(width), Some(height)) => Some(CGRect {
+                    origin: super::bindings::CGPoint { x, y },
+                    size: super::bindings::CGSize { width, height },
+                }),
+                _ => None,
+            },
+            frame_interval_secs: config.timelapse.as_ref().map(|t| t.capture_interval_secs),
+            pixel_format: config.pixel_format.unwrap_or_default(),
+            color_space: config.color_space.unwrap_or_default(),
+        }
+    }
+    
+    unsafe fn build(&self) -> Result<*mut SCStreamConfiguration> {
+        let stream_config = ScreenCaptureKitHelpers::create_stream_configuration();
+        if stream_config.is_null() {
+            return Err(capture_error(CaptureErrorCode::Unknown, "Failed to create stream configuration"));
+        }
+        
+        ScreenCaptureKitHelpers::configure_stream_configuration(
+            stream_config,
+            self.width,
+            self.height,
+            self.fps,
+            self.shows_cursor,
+            self.capture_audio,
+            self.pixel_format.four_cc(),
+            self.color_space.cg_name(),
+        );
+        
+        if let Some(rect) = self.source_rect {
+            ScreenCaptureKitHelpers::set_source_rect(stream_config, rect);
+        }
+        
+        if let Some(seconds) = self.frame_interval_secs {
+            ScreenCaptureKitHelpers::set_minimum_frame_interval(stream_config, seconds);
+        }
+        
+        if let Some(uid) = &self.microphone_device {
+            if !ScreenCaptureKitHelpers::set_microphone_capture_device(stream_config, uid) {
+                return Err(capture_error(
+                    CaptureErrorCode::Unsupported,
+                    format!("Selecting input device '{}' requires macOS 15 or later", uid)
+                ));
+            }
+        }
+        
+        Ok(stream_config)
+    }
+    
+    /// Push these settings to a running stream
+    unsafe fn apply(&self, stream: StreamHandle, description: String) -> Result<()> {
+        self.apply_then(stream, description, |_| {})
+    }
+    
+    /// Like `apply`, calling `on_done` with whether the stream accepted the update
+    unsafe fn apply_then<F>(&self, stream: StreamHandle, description: String, on_done: F) -> Result<()>
+    where
+        F: Fn(bool) + Send + Sync + Clone + 'static,
+    {
+        let stream_config = self.build()?;
+        ScreenCaptureKitHelpers::update_stream_configuration_async(stream.0, stream_config, move |error| {
+            match error {
+                Some(error) => log::error!("Stream update failed ({}): {:?}", description, error),
+                None => log::info!("Stream updated: {}", description),
+            }
+            on_done(error.is_none());
+        });
+        Ok(())
+    }
+}
 
 pub struct RealStreamManager {
     stream: Option<*mut SCStream>,
     delegate: Option<Box<RealStreamDelegate>>,
     is_recording: bool,
     output_path: Option<String>,
+    session: Option<SessionRecord>,
+    metadata: Option<RecordingMetadata>,
+    settings: Arc<Mutex<Option<StreamSettings>>>,
+    audio_route_listener: Option<AudioRouteListener>,
+    route_handler: Arc<Mutex<Option<AudioRouteHandler>>>,
+    process_tap: Option<ProcessTap>,
+    window_tracker: Option<WindowResizeTracker>,
+    focus_follower: Option<FocusFollower>,
+    exclusion_watcher: Option<ContentWatcher>,
+    input_recorder: Option<InputEventRecorder>,
+    canvas: Option<CanvasTarget>,
+    zoom: Option<ZoomFollow>,
+    linked: Vec<RealStreamManager>,
+}
+
+/// What `RealStreamManager::capture_stats` reports
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CaptureStats {
+    pub video_frames: u64,
+    pub audio_samples: u64,
+    /// Recorded time so far, pauses excluded
+    pub duration_ms: u64,
+    pub current_fps: f64,
+    /// Size of the files written so far
+    pub output_size_bytes: u64,
+    pub output_path: Option<String>,
+    pub is_paused: bool,
+}
+
+// Only touched under the recorder's mutex, which hands it between the JS thread and the schedule
+// timer; the SCStream and AVAssetWriter it drives are safe to use from any thread
+unsafe impl Send for RealStreamManager {}
+
+// Each manager owns its stream outright: one dropped while recording (its recorder collected, a
+// multi-display start rolled back) stops and finalizes its own files without touching any other
+impl Drop for RealStreamManager {
+    fn drop(&mut self) {
+        if self.stream.is_some() {
+            log::info!("Recording dropped while running - stopping it");
+            if let Err(e) = self.stop_recording() {
+                log::warn!("Failed to stop dropped recording: {}", e);
+            }
+        }
+    }
+}
+
+fn notify_route(handler: &Mutex<Option<AudioRouteHandler>>, change: &AudioRouteChange, phase: AudioRoutePhase) {
+    if let Ok(handler) = handler.lock() {
+        if let Some(handler) = handler.as_ref() {
+            handler(change, phase);
+        }
+    }
 }
 
 impl RealStreamManager {
@@ -623,44 +1881,351 @@ impl RealStreamManager {
             delegate: None,
             is_recording: false,
             output_path: None,
+            session: None,
+            metadata: None,
+            settings: Arc::new(Mutex::new(None)),
+            audio_route_listener: None,
+            route_handler: Arc::new(Mutex::new(None)),
+            process_tap: None,
+            window_tracker: None,
+            focus_follower: None,
+            exclusion_watcher: None,
+            input_recorder: None,
+            canvas: None,
+            zoom: None,
+            linked: Vec::new(),
         }
     }
     
+    /// Draw this stream into a composite canvas instead of encoding it on its own; set before starting
+    pub fn set_canvas(&mut self, target: Option<CanvasTarget>) {
+        self.canvas = target;
+    }
+    
+    /// Record a crop that follows the cursor instead of the whole display; set before starting
+    pub fn set_zoom(&mut self, zoom: Option<ZoomFollow>) {
+        self.zoom = zoom;
+    }
+    
+    /// Another display recorded alongside this one; stopped, paused and resumed with it
+    pub fn link(&mut self, manager: RealStreamManager) {
+        self.linked.push(manager);
+    }
+    
     pub fn start_recording(&mut self, content_filter: RealContentFilter, config: RecordingConfiguration) -> Result<()> {
+        let output_path = config.output_path.clone();
+        let result = self.start_stream(content_filter, config);
+        
+        // Failed starts get a session of their own so they show up in the history
+        if let Err(e) = &result {
+            let session = SessionRecord::new(&output_path, Vec::new());
+            SessionJournal::append_or_warn(&JournalEvent::StartFailed {
+                session_id: session.id,
+                timestamp: session.started_at,
+                output_path,
+                error: e.reason.clone(),
+            });
+        }
+        result
+    }
+    
+    fn start_stream(&mut self, content_filter: RealContentFilter, config: RecordingConfiguration) -> Result<()> {
         unsafe {
-            println!("🎬 Starting REAL ScreenCaptureKit recording");
-            println!("   Output: {}", config.output_path);
-            println!("   Resolution: {}x{}", config.width.unwrap_or(1920), config.height.unwrap_or(1080));
-            println!("   FPS: {}", config.fps.unwrap_or(30));
+            log::info!("Starting REAL ScreenCaptureKit recording");
+            log::debug!("   Output: {}", config.output_path);
+            log::debug!("   Resolution: {}x{}", config.width.unwrap_or(DEFAULT_WIDTH), config.height.unwrap_or(DEFAULT_HEIGHT));
+            log::debug!("   FPS: {}", config.fps.unwrap_or(DEFAULT_FPS));
             
             // Validate content filter
             if !content_filter.is_valid() {
-                return Err(Error::new(Status::GenericFailure, "Invalid content filter"));
+                return Err(capture_error(CaptureErrorCode::InvalidConfiguration, "Invalid content filter"));
+            }
+
+            super::permissions::guard_silent_for(&config)?;
+
+            // Without permission the stream starts but never delivers a frame
+            if !ScreenCaptureKitHelpers::check_screen_recording_permission() {
+                return Err(capture_error(CaptureErrorCode::PermissionDenied, "Screen recording permission has not been granted"));
+            }
+
+            // Resolve the requested input device up front - never fall back to the default silently
+            let input_device = match &config.audio_device_id {
+                Some(uid) => Some(AudioManager::resolve_input_device(uid)?),
+                None => None,
+            };
+            
+            // With the process tap backend system audio bypasses the stream entirely
+            let audio_backend = SystemAudioBackend::parse(config.system_audio_backend.as_deref())?;
+            let use_process_tap = audio_backend == SystemAudioBackend::CoreAudioTap && config.capture_audio.unwrap_or(false);
+            if use_process_tap && input_device.is_some() {
+                return Err(Error::new(
+                    Status::InvalidArg,
+                    "audio_device_id is not supported with system_audio_backend \"coreaudio-tap\"",
+                ));
+            }
+            
+            // Following the default input means capturing it from the start, so a route change has a microphone to move
+            let mut follow_default_input = config.follow_default_device.unwrap_or(false)
+                && config.capture_audio.unwrap_or(false)
+                && !use_process_tap;
+            if follow_default_input && input_device.is_some() {
+                log::info!("follow_default_device ignored - audio_device_id pins the input device");
+                follow_default_input = false;
+            }
+            let microphone_device = match &input_device {
+                Some(device) => Some(device.uid.clone()),
+                None if follow_default_input => AudioManager::default_input_device_uid(),
+                None => None,
+            };
+            if follow_default_input && microphone_device.is_none() {
+                log::warn!("follow_default_device: no default input device - recording without a microphone until one appears");
+            }
+            
+            // Without microphone permission the device delivers silence rather than an error
+            if microphone_device.is_some() {
+                let authorization = super::permissions::microphone_authorization();
+                if authorization.is_refused() {
+                    return Err(capture_error(CaptureErrorCode::PermissionDenied, format!("Microphone permission is {}", authorization.as_ref())));
+                }
+            }
+            
+            let secure_input_action = SecureInputAction::parse(config.secure_input_protection.as_deref())?;
+            
+            // A timelapse is silent: there's no sensible way to speed the audio up with the frames
+            let timelapse = match &config.timelapse {
+                Some(options) => {
+                    if config.capture_audio.unwrap_or(false) || input_device.is_some() || config.write_wav_sidecar.unwrap_or(false) {
+                        return Err(Error::new(Status::InvalidArg, "timelapse recordings have no audio; turn off capture_audio"));
+                    }
+                    if config.replay_buffer_seconds.is_some() {
+                        return Err(Error::new(Status::InvalidArg, "timelapse can't be combined with replay_buffer_seconds"));
+                    }
+                    Some(Timelapse::new(options.capture_interval_secs, options.playback_fps)?)
+                }
+                None => None,
+            };
+            let video_fps = timelapse.map(|t| t.playback_fps).unwrap_or(config.fps.unwrap_or(DEFAULT_FPS));
+            
+            // Without video the stream runs for its audio alone and everything tied to frames is out
+            let audio_only = !config.capture_video.unwrap_or(true);
+            if audio_only {
+                if !config.capture_audio.unwrap_or(false) {
+                    return Err(Error::new(Status::InvalidArg, "capture_video false needs capture_audio"));
+                }
+                if timelapse.is_some()
+                    || config.replay_buffer_seconds.is_some()
+                    || config.camera_pip.is_some()
+                    || config.record_cursor_metadata.unwrap_or(false)
+                    || config.follow_active_window.unwrap_or(false)
+                    || self.zoom.is_some()
+                    || self.canvas.is_some()
+                {
+                    return Err(Error::new(
+                        Status::InvalidArg,
+                        "capture_video false can't be combined with timelapse, replay_buffer_seconds, camera_pip, record_cursor_metadata, follow_active_window, zoom_follow or several displays",
+                    ));
+                }
             }
             
+            // Overlays and the cursor track are tied to the source the recording starts with
+            let follow_active_window = config.follow_active_window.unwrap_or(false);
+            if follow_active_window
+                && (uses_custom_cursor(&config) || config.highlight_clicks.unwrap_or(false) || config.record_cursor_metadata.unwrap_or(false))
+            {
+                return Err(Error::new(
+                    Status::InvalidArg,
+                    "follow_active_window can't be combined with a custom cursor, highlight_clicks or record_cursor_metadata",
+                ));
+            }
+            if follow_active_window && (config.crop.is_some() || config.crop_width.is_some()) {
+                return Err(Error::new(Status::InvalidArg, "follow_active_window can't be combined with crop"));
+            }
+
+            // Everything that draws into the frames only understands BGRA and would silently do nothing
+            if config.pixel_format.unwrap_or_default() != PixelFormat::Bgra
+                && (uses_custom_cursor(&config)
+                    || config.highlight_clicks.unwrap_or(false)
+                    || config.camera_pip.is_some()
+                    || secure_input_action == Some(SecureInputAction::Blank)
+                    || self.zoom.is_some()
+                    || self.canvas.is_some())
+            {
+                return Err(Error::new(
+                    Status::InvalidArg,
+                    "pixel_format other than Bgra can't be combined with a custom cursor, highlight_clicks, camera_pip, secure_input_protection \"blank\", zoom_follow or several displays",
+                ));
+            }
+
             // Create stream configuration
-            let stream_config = self.create_stream_configuration(&config)?;
-            println!("✅ Created stream configuration");
+            let mut settings = StreamSettings::from_config(&config, microphone_device);
+            if use_process_tap {
+                settings.capture_audio = false;
+            }
+            if audio_only {
+                settings.width = AUDIO_ONLY_FRAME_SIZE;
+                settings.height = AUDIO_ONLY_FRAME_SIZE;
+                settings.shows_cursor = false;
+                settings.source_rect = None;
+                settings.frame_interval_secs = Some(AUDIO_ONLY_FRAME_INTERVAL_SECS);
+                log::info!("Audio only: writing {}", RealStreamDelegate::audio_only_path(&config.output_path));
+            }
+            if config.hide_cursor_in_output.unwrap_or(false) {
+                log::info!("Cursor hidden in output only - still visible on screen");
+            }
+            // Zooming captures the display at full resolution and scales the crop down to the output
+            let zoom = self.zoom.take();
+            if let Some(zoom) = &zoom {
+                let (width, height) = zoom.capture_size();
+                settings.width = width;
+                settings.height = height;
+            }
+            let stream_config = settings.build()?;
+            log::info!("Created stream configuration");
+            
+            // Load the cursor artwork before starting so a bad image path fails the start
+            let cursor_overlay = if uses_custom_cursor(&config)
+                && !config.hide_cursor_in_output.unwrap_or(false)
+                && config.show_cursor.unwrap_or(true)
+            {
+                match content_filter.source() {
+                    Some(source) => Some(CursorOverlay::new(
+                        source,
+                        config.cursor_scale.unwrap_or(1.0),
+                        config.cursor_image_path.as_deref(),
+                    )?),
+                    None => None,
+                }
+            } else {
+                None
+            };
+            let click_highlighter = match content_filter.source() {
+                Some(source) if config.highlight_clicks.unwrap_or(false) => Some(ClickHighlighter::start(source)?),
+                _ => None,
+            };
+            let camera_overlay = match &config.camera_pip {
+                Some(pip) => Some(CameraOverlay::start(
+                    pip.device_id.as_deref(),
+                    PipLayout {
+                        corner: PipCorner::parse(pip.corner.as_deref())?,
+                        size: pip.size.unwrap_or(0.25),
+                        circular: pip.circular.unwrap_or(false),
+                        margin: pip.margin.unwrap_or(24),
+                    },
+                )?),
+                None => None,
+            };
+            let input_recorder = if config.record_input_events.unwrap_or(false) {
+                Some(InputEventRecorder::start(&InputEventRecorder::path_for(&config.output_path))?)
+            } else {
+                None
+            };
             
             // Create stream delegate with recording state
             let is_recording_flag = Arc::new(Mutex::new(true));
-            let delegate = RealStreamDelegate::new(
-                config.output_path.clone(),
-                is_recording_flag.clone(),
-                config.width.unwrap_or(1920),
-                config.height.unwrap_or(1080),
-                config.fps.unwrap_or(30)
-            );
+            // Each input of an aggregate microphone keeps its own channels in the audio track
+            let audio_channels = config.audio_device_id.as_deref()
+                .and_then(AudioManager::aggregate_channel_count)
+                .unwrap_or(2);
+            let feeder = self.canvas.clone().filter(|target| !target.is_primary());
+            let feeds_canvas = feeder.is_some();
+            let delegate = if let Some(target) = feeder {
+                RealStreamDelegate::new_canvas_feeder(config.output_path.clone(), is_recording_flag.clone(), target)
+            } else if audio_only {
+                RealStreamDelegate::new_audio_only(config.output_path.clone(), is_recording_flag.clone(), audio_channels)?
+            } else {
+                match config.replay_buffer_seconds {
+                    Some(seconds) => {
+                        let directory = ReplayBuffer::directory_for(&super::session::unique_key());
+                        let replay = ReplayBuffer::new(
+                            directory,
+                            seconds,
+                            config.width.unwrap_or(DEFAULT_WIDTH),
+                            config.height.unwrap_or(DEFAULT_HEIGHT),
+                            config.fps.unwrap_or(DEFAULT_FPS),
+                        )?;
+                        RealStreamDelegate::new_replay(config.output_path.clone(), is_recording_flag.clone(), replay)
+                    }
+                    None => match &self.canvas {
+                        // The primary stream of a composite encodes the whole canvas
+                        Some(target) => {
+                            let (width, height) = target.canvas_size();
+                            let delegate = RealStreamDelegate::new(
+                                config.output_path.clone(),
+                                is_recording_flag.clone(),
+                                width,
+                                height,
+                                video_fps,
+                                config.codec.unwrap_or_default(),
+                                audio_channels,
+                            );
+                            delegate.set_canvas(Some(target.clone()));
+                            delegate
+                        }
+                        None => RealStreamDelegate::new(
+                            config.output_path.clone(),
+                            is_recording_flag.clone(),
+                            config.width.unwrap_or(DEFAULT_WIDTH),
+                            config.height.unwrap_or(DEFAULT_HEIGHT),
+                            video_fps,
+                            config.codec.unwrap_or_default(),
+                            audio_channels,
+                        ),
+                    },
+                }
+            };
+            
+            if let Some(timelapse) = timelapse {
+                log::info!(
+                    "Timelapse: one frame every {}s, played back at {} fps ({}x)",
+                    timelapse.capture_interval_secs, timelapse.playback_fps, timelapse.speedup()
+                );
+                delegate.set_timelapse(timelapse.speedup());
+            }
+            if cursor_overlay.is_some() {
+                delegate.set_cursor_overlay(cursor_overlay);
+            }
+            if click_highlighter.is_some() {
+                delegate.set_click_highlighter(click_highlighter);
+            }
+            if camera_overlay.is_some() {
+                delegate.set_camera_overlay(camera_overlay);
+            }
+            if config.record_cursor_metadata.unwrap_or(false) {
+                match content_filter.source() {
+                    Some(source) => delegate.set_cursor_track(Some(CursorTrack::new(&CursorTrack::path_for(&config.output_path), source))),
+                    None => log::warn!("record_cursor_metadata needs a display or single-window capture; no cursor track written"),
+                }
+            }
+            if config.write_wav_sidecar.unwrap_or(false) {
+                let path = WavSidecar::path_for(&config.output_path);
+                log::info!("Writing 16 kHz mono WAV sidecar: {}", path);
+                delegate.set_wav_sidecar(Some(WavSidecar::create(&path)?));
+            }
+            if let Some(action) = secure_input_action {
+                log::info!("Secure input protection: {:?}", action);
+                delegate.set_secure_input_guard(Some(SecureInputGuard::new(action)));
+            }
+            if zoom.is_some() {
+                delegate.set_zoom(zoom);
+            }
             
             let delegate_ptr = delegate.create_objc_delegate();
             if delegate_ptr.is_null() {
-                return Err(Error::new(Status::GenericFailure, "Failed to create stream delegate"));
+                return Err(capture_error(CaptureErrorCode::Unknown, "Failed to create stream delegate"));
             }
-            println!("✅ Created stream delegate");
+            log::info!("Created stream delegate");
+            
+            // Start the tap before the stream so the first frames have audio alongside them
+            let process_tap = if use_process_tap {
+                let process_ids = config.system_audio_process_ids.clone().unwrap_or_default();
+                Some(ProcessTap::start(&process_ids, delegate.pcm_handler())?)
+            } else {
+                None
+            };
             
             // Create SCStream with real content filter
             let stream = self.create_sc_stream(content_filter.get_filter_ptr(), stream_config, delegate_ptr)?;
-            println!("✅ Created SCStream instance");
+            log::info!("Created SCStream instance");
             
             // Start capture with completion handler
             let start_result = std::sync::Arc::new(std::sync::Mutex::new(None));
@@ -669,10 +2234,10 @@ impl RealStreamManager {
             ScreenCaptureKitHelpers::start_stream_capture_async(stream, move |error| {
                 let mut result = start_result_clone.lock().unwrap();
                 if let Some(error) = error {
-                    println!("❌ Stream start failed: {:?}", error);
+                    log::error!("Stream start failed: {:?}", error);
                     *result = Some(false);
                 } else {
-                    println!("✅ Stream started successfully - now capturing frames");
+                    log::info!("Stream started successfully - now capturing frames");
                     *result = Some(true);
                 }
             });
@@ -680,29 +2245,110 @@ impl RealStreamManager {
             // Wait briefly for start completion (in real implementation, this would be async)
             std::thread::sleep(std::time::Duration::from_millis(100));
             
+            // Ride out route changes; the microphone follows the system default input unless a device was pinned
+            if config.capture_audio.unwrap_or(false) && !use_process_tap {
+                self.audio_route_listener = self.watch_audio_route(stream, delegate.audio_route(), follow_default_input);
+            }
+            
+            // Following focus moves the stream between windows, reshaping it as it goes
+            if follow_active_window {
+                let current = match content_filter.source() {
+                    Some(CaptureSource::Window(window_id)) => Some(window_id),
+                    _ => None,
+                };
+                let debounce = Duration::from_millis(config.follow_debounce_ms.unwrap_or(super::focus_follow::DEFAULT_DEBOUNCE_MS) as u64);
+                self.focus_follower = Some(self.follow_active_window(stream, current, debounce, settings.width, settings.height));
+            } else if let (Some(CaptureSource::Window(window_id)), false) = (content_filter.source(), audio_only) {
+                // A resized window would otherwise be letterboxed or cropped in the fixed-size stream output
+                if config.track_window_resize.unwrap_or(true) {
+                    self.window_tracker = Some(self.track_window_resize(stream, window_id, settings.width, settings.height));
+                }
+            }
+            
+            // An excluded app launched (or relaunched) mid-recording isn't in the filter yet
+            if let Some(CaptureSource::Display(display_id)) = content_filter.source() {
+                if !follow_active_window && !content_filter.exclusions().app_bundle_ids.is_empty() {
+                    self.exclusion_watcher = Some(self.keep_apps_excluded(stream, display_id, content_filter.exclusions().clone()));
+                }
+            }
+            
+            // Open the session record and hand start-time tags to the container. A stream feeding
+            // a composite writes no file of its own, so the composite's primary holds the session.
+            let session = if feeds_canvas {
+                None
+            } else {
+                let session = SessionRecord::new(&config.output_path, config.tags.clone().unwrap_or_default());
+                if !session.tags.is_empty() {
+                    delegate.set_tags(&session.tags);
+                }
+                SessionJournal::append_or_warn(&JournalEvent::Started {
+                    session_id: session.id.clone(),
+                    timestamp: session.started_at,
+                    output_path: session.output_path.clone(),
+                    tags: session.tags.clone(),
+                });
+                LibraryIndex::upsert_or_warn(&session);
+                SessionJournal::set_live(&session.id, true);
+                delegate.set_session_id(&session.id);
+                Some(session)
+            };
+            let metadata = session
+                .as_ref()
+                .map(|session| RecordingMetadata::new(&session.id, session.started_at, content_filter.source(), &config));
+            
             // Store the stream and delegate
+            if let Ok(mut current) = self.settings.lock() {
+                *current = Some(settings);
+            }
             self.stream = Some(stream);
+            self.process_tap = process_tap;
+            self.input_recorder = input_recorder;
             self.delegate = Some(Box::new(delegate));
             self.is_recording = true;
             self.output_path = Some(config.output_path.clone());
+            self.session = session;
+            self.metadata = metadata;
             
-            println!("🚀 Real ScreenCaptureKit recording session started");
-            println!("📊 Stream will now receive video frames from ScreenCaptureKit");
+            log::info!("Real ScreenCaptureKit recording session started");
+            log::debug!("Stream will now receive video frames from ScreenCaptureKit");
             Ok(())
         }
     }
     
     pub fn stop_recording(&mut self) -> Result<String> {
+        self.stop_recording_with_progress(&mut |_| {})
+    }
+    
+    /// `stop_recording`, calling `progress` as each phase of finalizing begins. The encoders
+    /// finish writing synchronously, so there is nothing finer-grained to report within a phase.
+    pub fn stop_recording_with_progress(&mut self, progress: &mut dyn FnMut(FinalizePhase)) -> Result<String> {
+        progress(FinalizePhase::Stream);
+        // Displays feeding a composite stop first so their last frames still reach the canvas
+        for mut linked in self.linked.drain(..) {
+            if let Err(e) = linked.stop_recording() {
+                log::warn!("Failed to stop linked display stream: {}", e);
+            }
+        }
         unsafe {
             if let Some(stream) = self.stream {
-                println!("🛑 Stopping REAL ScreenCaptureKit recording");
+                log::info!("Stopping REAL ScreenCaptureKit recording");
+                
+                // No device migrations or live updates while tearing down
+                self.audio_route_listener = None;
+                self.window_tracker = None;
+                self.focus_follower = None;
+                self.exclusion_watcher = None;
+                self.process_tap = None;
+                if let Ok(mut settings) = self.settings.lock() {
+                    *settings = None;
+                }
                 
                 // Get final stats before stopping
                 if let Some(delegate) = &self.delegate {
                     let frame_count = delegate.get_frame_count();
                     let audio_count = delegate.get_audio_frame_count();
                     let fps = delegate.get_current_fps();
-                    println!("📊 Final capture stats: {} video frames, {} audio samples, {:.1} FPS", 
+                    log::debug!("Final capture stats: {} video frames, {} audio samples, {:.1} FPS", 
                         frame_count, audio_count, fps);
                 }
                 
@@ -713,10 +2359,10 @@ impl RealStreamManager {
                 ScreenCaptureKitHelpers::stop_stream_capture_async(stream, move |error| {
                     let mut result = stop_result_clone.lock().unwrap();
                     if let Some(error) = error {
-                        println!("⚠️ Stream stop had error: {:?}", error);
+                        log::warn!("Stream stop had error: {:?}", error);
                         *result = Some(false);
                     } else {
-                        println!("✅ Stream stopped successfully");
+                        log::info!("Stream stopped successfully");
                         *result = Some(true);
                     }
                 });
@@ -728,45 +2374,332 @@ impl RealStreamManager {
                 self.stream = None;
                 
                 // Finalize encoding through delegate
+                let mut counts = None;
                 if let Some(delegate) = &mut self.delegate {
-                    delegate.handle_stream_stopped(None);
+                    delegate.finalize(None, progress);
                     
                     // Wait a bit more for encoding finalization
                     std::thread::sleep(std::time::Duration::from_millis(500));
+                    
+                    let elapsed = self.session.as_ref()
+                        .map(|session| Duration::from_millis(super::session::now_millis().saturating_sub(session.started_at)))
+                        .unwrap_or_default();
+                    counts = Some(RecordingCounts {
+                        video_frames: delegate.get_frame_count(),
+                        dropped_frames: delegate.dropped_frame_count(),
+                        audio_samples: delegate.get_audio_frame_count(),
+                        duration_ms: elapsed.saturating_sub(delegate.paused_duration()).as_millis() as u64,
+                    });
                 }
                 
                 let output_path = self.output_path.clone().unwrap_or_else(|| "/tmp/recording.mp4".to_string());
                 
+                if let Some(mut recorder) = self.input_recorder.take() {
+                    if let Err(e) = recorder.finalize() {
+                        log::error!("{}", e);
+                    }
+                }
+                
+                // Close the session record
+                progress(FinalizePhase::Session);
+                if let Some(mut session) = self.session.take() {
+                    session.stopped_at = Some(super::session::now_millis());
+                    SessionJournal::append_or_warn(&JournalEvent::Stopped {
+                        session_id: session.id.clone(),
+                        timestamp: session.stopped_at.unwrap_or(0),
+                        output_path: output_path.clone(),
+                    });
+                    LibraryIndex::upsert_or_warn(&session);
+                    SessionJournal::set_live(&session.id, false);
+                    
+                    if let (Some(mut metadata), Some(counts)) = (self.metadata.take(), counts) {
+                        metadata.finish(session.stopped_at.unwrap_or(0), counts);
+                        if let Err(e) = metadata.write() {
+                            log::error!("{}", e);
+                        }
+                    }
+                }
+                
                 // Clean up delegate
                 self.delegate = None;
                 
-                println!("✅ Real ScreenCaptureKit recording session completed");
-                println!("📁 Output file: {}", output_path);
+                log::info!("Real ScreenCaptureKit recording session completed");
+                log::info!("Output file: {}", output_path);
+                progress(FinalizePhase::Done);
                 Ok(output_path)
             } else {
-                Err(Error::new(Status::GenericFailure, "No active recording session"))
+                Err(capture_error(CaptureErrorCode::NotRecording, "No active recording session"))
             }
         }
     }
     
-    unsafe fn create_stream_configuration(&self, config: &RecordingConfiguration) -> Result<*mut SCStreamConfiguration> {
-        let stream_config = ScreenCaptureKitHelpers::create_stream_configuration();
-        if stream_config.is_null() {
-            return Err(Error::new(Status::GenericFailure, "Failed to create stream configuration"));
+    /// Keep audio going across route changes (AirPods disconnecting, headphones plugged in): pause the
+    /// audio track, re-point the stream via SCStream.updateConfiguration, and resume once it has moved.
+    /// The microphone moves to the new default input when following it, or when its device is gone.
+    unsafe fn watch_audio_route(
+        &self,
+        stream: *mut SCStream,
+        audio_route: Arc<Mutex<AudioRouteState>>,
+        follow_default_input: bool,
+    ) -> Option<AudioRouteListener> {
+        let handle = StreamHandle(stream);
+        let settings = self.settings.clone();
+        let route_handler = self.route_handler.clone();
+        
+        let listener = AudioRouteListener::start(move |change| unsafe {
+            let updated = match settings.lock() {
+                Ok(mut current) => match current.as_mut() {
+                    Some(current) => {
+                        if change.direction == AudioRouteDirection::Input {
+                            // Without a microphone device the stream captures no input, unless it follows the default
+                            let device = current.microphone_device.clone();
+                            if !follow_default_input {
+                                match &device {
+                                    Some(device) if AudioManager::device_id_for_uid(device).is_none() => {}
+                                    _ => return,
+                                }
+                            }
+                            match &change.device_uid {
+                                Some(uid) => {
+                                    if let (false, Some(device)) = (follow_default_input, &device) {
+                                        log::warn!("Input device {} disconnected - falling back to {}", device, uid);
+                                    }
+                                    current.microphone_device = Some(uid.clone());
+                                }
+                                None => {
+                                    log::warn!("No input device left to move microphone capture to");
+                                    return;
+                                }
+                            }
+                        }
+                        current.clone()
+                    }
+                    None => return,
+                },
+                Err(_) => return,
+            };
+            
+            if let Ok(mut route) = audio_route.lock() {
+                route.begin_switch();
+            }
+            notify_route(&route_handler, &change, AudioRoutePhase::Switching);
+            
+            let description = format!(
+                "audio {} moved to {}",
+                change.direction.as_str(),
+                change.device_uid.as_deref().unwrap_or("no device")
+            );
+            let resume = {
+                let audio_route = audio_route.clone();
+                let route_handler = route_handler.clone();
+                let change = change.clone();
+                move |ok: bool| {
+                    if let Ok(mut route) = audio_route.lock() {
+                        route.end_switch();
+                    }
+                    let phase = if ok { AudioRoutePhase::Resumed } else { AudioRoutePhase::Failed };
+                    notify_route(&route_handler, &change, phase);
+                }
+            };
+            if let Err(e) = updated.apply_then(handle, description, resume.clone()) {
+                log::warn!("Cannot move audio capture to the new route: {}", e);
+                resume(false);
+            }
+        });
+        
+        match listener {
+            Ok(listener) => Some(listener),
+            Err(e) => {
+                log::warn!("{} - audio route changes will not be handled", e);
+                None
+            }
         }
+    }
+    
+    /// Reshape the stream output to the window whenever it is resized, within the configured size.
+    /// The recording keeps its start dimensions; the encoder scales each frame in without distortion.
+    unsafe fn track_window_resize(&self, stream: *mut SCStream, window_id: u32, max_width: u32, max_height: u32) -> WindowResizeTracker {
+        let handle = StreamHandle(stream);
+        let settings = self.settings.clone();
         
-        ScreenCaptureKitHelpers::configure_stream_configuration(
-            stream_config,
-            config.width.unwrap_or(1920),
-            config.height.unwrap_or(1080),
-            config.fps.unwrap_or(30),
-            config.show_cursor.unwrap_or(true),
-            config.capture_audio.unwrap_or(false),
-            kCVPixelFormatType_32BGRA,
-            1 // sRGB color space
-        );
+        WindowResizeTracker::start(window_id, max_width, max_height, Arc::new(move |width, height| unsafe {
+            let updated = match settings.lock() {
+                Ok(mut current) => match current.as_mut() {
+                    Some(current) => {
+                        current.width = width;
+                        current.height = height;
+                        current.clone()
+                    }
+                    None => return,
+                },
+                Err(_) => return,
+            };
+            if let Err(e) = updated.apply(handle, format!("window {} resized, output {}x{}", window_id, width, height)) {
+                log::warn!("Cannot follow window resize: {}", e);
+            }
+        }))
+    }
+    
+    /// Switch the stream to each window that takes focus, shaped like the window within the
+    /// configured size; the encoder scales every frame in without distortion
+    unsafe fn follow_active_window(
+        &self,
+        stream: *mut SCStream,
+        current: Option<u32>,
+        debounce: Duration,
+        max_width: u32,
+        max_height: u32,
+    ) -> FocusFollower {
+        let handle = StreamHandle(stream);
+        let settings = self.settings.clone();
         
-        Ok(stream_config)
+        FocusFollower::start(current, debounce, Arc::new(move |window_id| unsafe {
+            let filter = match ShareableContent::new_with_real_data()
+                .and_then(|content| RealContentFilter::new_with_window(&content, window_id))
+            {
+                Ok(filter) if filter.fallback().is_none() => filter,
+                Ok(_) => {
+                    log::warn!("Window {} isn't shareable; staying on the current source", window_id);
+                    return;
+                }
+                Err(e) => {
+                    log::warn!("Cannot follow focus to window {}: {}", window_id, e);
+                    return;
+                }
+            };
+            ScreenCaptureKitHelpers::update_content_filter_async(handle.0, filter.get_filter_ptr(), move |error| match error {
+                Some(error) => log::error!("Switching to window {} failed: {:?}", window_id, error),
+                None => log::info!("Now capturing window {}", window_id),
+            });
+            
+            let (width, height) = match CaptureSource::Window(window_id).bounds() {
+                Some(bounds) => fit_within(bounds.width, bounds.height, max_width, max_height),
+                None => return,
+            };
+            let updated = match settings.lock() {
+                Ok(mut current) => match current.as_mut() {
+                    Some(current) => {
+                        current.width = width;
+                        current.height = height;
+                        current.clone()
+                    }
+                    None => return,
+                },
+                Err(_) => return,
+            };
+            if let Err(e) = updated.apply(handle, format!("following window {}, output {}x{}", window_id, width, height)) {
+                log::warn!("Cannot reshape output for window {}: {}", window_id, e);
+            }
+        }))
+    }
+    
+    /// Rebuild the display filter whenever a process of an excluded app opens its first window.
+    /// Application exclusions only cover the processes running when the filter was made, so a
+    /// password manager opened mid-recording would otherwise be captured.
+    fn keep_apps_excluded(&self, stream: *mut SCStream, display_id: u32, exclusions: DisplayExclusions) -> ContentWatcher {
+        let handle = StreamHandle(stream);
+        let is_excluded = |window: &WindowInfo, bundle_ids: &[String]| {
+            window.owner_bundle_id.as_ref().is_some_and(|id| bundle_ids.iter().any(|wanted| wanted.eq_ignore_ascii_case(id)))
+        };
+        // Processes the current filter already leaves out
+        let covered: HashSet<i32> = ShareableContent::current_windows()
+            .unwrap_or_default()
+            .iter()
+            .filter(|window| is_excluded(window, &exclusions.app_bundle_ids))
+            .filter_map(|window| window.pid)
+            .collect();
+        let covered = Mutex::new(covered);
+        log::info!("Watching for new windows of {} excluded applications", exclusions.app_bundle_ids.len());
+        
+        ContentWatcher::start(super::content_watch::MIN_INTERVAL_MS, Arc::new(move |change| {
+            // Capture the Send handle itself rather than just its raw pointer field
+            let handle = &handle;
+            let ContentChange::WindowAdded(window) = change else { return };
+            if !is_excluded(&window, &exclusions.app_bundle_ids) {
+                return;
+            }
+            let Some(pid) = window.pid else { return };
+            if !covered.lock().map(|mut covered| covered.insert(pid)).unwrap_or(false) {
+                return;
+            }
+            let filter = match ShareableContent::new_with_real_data()
+                .and_then(|content| RealContentFilter::new_with_display_excluding(&content, display_id, &exclusions))
+            {
+                Ok(filter) => filter,
+                Err(e) => {
+                    log::error!("Cannot exclude new window {} of {:?}: {}", window.id, window.owner_bundle_id, e);
+                    return;
+                }
+            };
+            let window_id = window.id;
+            unsafe {
+                ScreenCaptureKitHelpers::update_content_filter_async(handle.0, filter.get_filter_ptr(), move |error| match error {
+                    Some(error) => log::error!("Excluding new window {} failed: {:?}", window_id, error),
+                    None => log::info!("Content filter refreshed to exclude new window {}", window_id),
+                });
+            }
+        }))
+    }
+    
+    /// Notified when a route change pauses audio and again when it resumes or fails
+    pub fn set_audio_route_listener(&self, listener: Option<AudioRouteHandler>) {
+        if let Ok(mut handler) = self.route_handler.lock() {
+            *handler = listener;
+        }
+    }
+    
+    /// Mark the current point of the recording, and of every linked display's recording
+    pub fn add_marker(&self, label: &str) -> Result<super::markers::Marker> {
+        let label = super::markers::validate_label(label)?;
+        let marker = match &self.delegate {
+            Some(delegate) if self.is_recording => delegate.add_marker(&label)?,
+            _ => return Err(capture_error(CaptureErrorCode::NotRecording, "No active recording session")),
+        };
+        // Displays drawn into a composite have no file of their own
+        for linked in self.linked.iter().filter(|linked| linked.canvas.is_none()) {
+            if let Err(e) = linked.add_marker(&label) {
+                log::warn!("Marker not added to linked display: {}", e);
+            }
+        }
+        log::info!("Marker '{}' at {:.2}s", marker.label, marker.time);
+        Ok(marker)
+    }
+    
+    /// The replay window of a `replay_buffer_seconds` recording, ready to export off the capture path
+    pub fn snapshot_replay(&self) -> Result<ReplaySnapshot> {
+        match &self.delegate {
+            Some(delegate) if self.is_recording => delegate.snapshot_replay(),
+            _ => Err(capture_error(CaptureErrorCode::NotRecording, "No active recording session")),
+        }
+    }
+    
+    /// Toggle the cursor in the captured frames while recording; the user's cursor stays visible
+    pub fn set_cursor_hidden_in_output(&self, hidden: bool) -> Result<()> {
+        let stream = self.stream
+            .ok_or_else(|| capture_error(CaptureErrorCode::NotRecording, "No active recording session"))?;
+        
+        // With a custom cursor the stream never captures one; toggle the overlay instead
+        if let Some(delegate) = &self.delegate {
+            if delegate.set_cursor_overlay_enabled(!hidden) {
+                log::info!("Custom cursor {} in output", if hidden { "hidden" } else { "shown" });
+                return Ok(());
+            }
+        }
+        
+        let updated = {
+            let mut current = self.settings.lock()
+                .map_err(|_| capture_error(CaptureErrorCode::Unknown, "Stream settings unavailable"))?;
+            let current = current.as_mut()
+                .ok_or_else(|| capture_error(CaptureErrorCode::NotRecording, "No active recording session"))?;
+            if current.shows_cursor == !hidden {
+                return Ok(());
+            }
+            current.shows_cursor = !hidden;
+            current.clone()
+        };
+        
+        let description = if hidden { "cursor hidden in output" } else { "cursor shown in output" };
+        unsafe { updated.apply(StreamHandle(stream), description.to_string()) }
     }
     
     unsafe fn create_sc_stream(
@@ -778,10 +2711,10 @@ impl RealStreamManager {
         let stream = ScreenCaptureKitHelpers::create_stream(content_filter, configuration, delegate);
         
         if stream.is_null() {
-            return Err(Error::new(Status::GenericFailure, "Failed to create SCStream"));
+            return Err(capture_error(CaptureErrorCode::Unknown, "Failed to create SCStream"));
         }
         
-        println!("✅ Created real SCStream instance");
+        log::info!("Created real SCStream instance");
         Ok(stream)
     }
     
@@ -789,7 +2722,203 @@ impl RealStreamManager {
         self.is_recording
     }
     
+    /// Set when ScreenCaptureKit ended the stream by itself, e.g. because the display went away
+    pub fn stream_error(&self) -> Option<Error> {
+        let reason = self.delegate.as_ref()?.stream_error()?;
+        Some(capture_error(CaptureErrorCode::StreamStopped, format!("The capture stream stopped: {}", reason)))
+    }
+    
+    /// Replace microphone audio with silence (or restore it); returns true when the state changed
+    pub fn set_microphone_muted(&self, muted: bool) -> Result<bool> {
+        match &self.delegate {
+            Some(delegate) => {
+                let changed = delegate.set_microphone_muted(muted);
+                if changed {
+                    log::info!("Microphone {}", if muted { "muted" } else { "unmuted" });
+                }
+                Ok(changed)
+            }
+            None => Err(capture_error(CaptureErrorCode::NotRecording, "No active recording session")),
+        }
+    }
+    
+    /// Stop taking in video and audio until `resume`; false when already paused
+    pub fn pause(&self) -> Result<bool> {
+        match &self.delegate {
+            Some(delegate) => {
+                for linked in &self.linked {
+                    let _ = linked.pause();
+                }
+                let changed = delegate.pause();
+                if changed {
+                    log::info!("Recording paused");
+                }
+                Ok(changed)
+            }
+            None => Err(capture_error(CaptureErrorCode::NotRecording, "No active recording session")),
+        }
+    }
+    
+    /// False when the recording wasn't paused
+    pub fn resume(&self) -> Result<bool> {
+        match &self.delegate {
+            Some(delegate) => {
+                for linked in &self.linked {
+                    let _ = linked.resume();
+                }
+                let changed = delegate.resume();
+                if changed {
+                    log::info!("Recording resumed after {:.1}s paused in total", delegate.paused_duration().as_secs_f64());
+                }
+                Ok(changed)
+            }
+            None => Err(capture_error(CaptureErrorCode::NotRecording, "No active recording session")),
+        }
+    }
+    
+    pub fn is_paused(&self) -> bool {
+        self.delegate.as_ref().map(|d| d.is_paused()).unwrap_or(false)
+    }
+    
+    pub fn paused_duration(&self) -> Duration {
+        self.delegate.as_ref().map(|d| d.paused_duration()).unwrap_or_default()
+    }
+    
+    /// Notified with `true` when secure input starts protecting the recording and `false` when it ends
+    pub fn set_secure_input_listener(&self, listener: Option<SecureInputListener>) -> Result<()> {
+        match &self.delegate {
+            Some(delegate) => {
+                delegate.set_secure_input_listener(listener);
+                Ok(())
+            }
+            None => Err(capture_error(CaptureErrorCode::NotRecording, "No active recording session")),
+        }
+    }
+    
+    pub fn set_audio_buffer_listener(&self, listener: Option<PcmHandler>) -> Result<()> {
+        match &self.delegate {
+            Some(delegate) => {
+                delegate.set_audio_buffer_listener(listener);
+                Ok(())
+            }
+            None => Err(capture_error(CaptureErrorCode::NotRecording, "No active recording session")),
+        }
+    }
+    
+    pub fn set_video_frame_listener(&self, listener: Option<super::frames::VideoFrameListener>) -> Result<()> {
+        match &self.delegate {
+            Some(delegate) => {
+                delegate.set_video_frame_listener(listener);
+                Ok(())
+            }
+            None => Err(capture_error(CaptureErrorCode::NotRecording, "No active recording session")),
+        }
+    }
+    
+    pub fn set_audio_tap(&self, tap: Option<super::audio_tap::AudioTap>) -> Result<()> {
+        match &self.delegate {
+            Some(delegate) => {
+                delegate.set_audio_tap(tap);
+                Ok(())
+            }
+            None => Err(capture_error(CaptureErrorCode::NotRecording, "No active recording session")),
+        }
+    }
+    
+    pub fn session(&self) -> Option<&SessionRecord> {
+        self.session.as_ref()
+    }
+    
+    /// Attach a tag to the running session (journal, library index and - if writing
+    /// has not started yet - the MP4 keywords)
+    pub fn add_tag(&mut self, tag: &str) -> Result<()> {
+        let session = self.session.as_mut()
+            .ok_or_else(|| capture_error(CaptureErrorCode::NotRecording, "No active recording session"))?;
+        
+        if !session.add_tag(tag)? {
+            return Ok(());
+        }
+        
+        let tag = session.tags.last().cloned().unwrap_or_default();
+        log::info!("Tagged session {} with '{}'", session.id, tag);
+        
+        SessionJournal::append_or_warn(&JournalEvent::Tagged {
+            session_id: session.id.clone(),
+            timestamp: super::session::now_millis(),
+            tag,
+        });
+        LibraryIndex::upsert_or_warn(session);
+        
+        if let Some(delegate) = &self.delegate {
+            if !delegate.set_tags(&session.tags) {
+                log::info!("Video writing already started - tag stored in journal and library index only");
+            }
+        }
+        Ok(())
+    }
+    
+    /// Stats for this stream, with a per-display breakdown and combined totals when displays are linked
+    /// Live counters for a recording HUD, summed over linked displays; None when not recording
+    pub fn capture_stats(&self) -> Option<CaptureStats> {
+        if !self.is_recording {
+            return None;
+        }
+        let delegate = self.delegate.as_ref()?;
+        let elapsed = self.session.as_ref()
+            .map(|session| Duration::from_millis(super::session::now_millis().saturating_sub(session.started_at)))
+            .unwrap_or_default();
+        let mut stats = CaptureStats {
+            video_frames: delegate.get_frame_count(),
+            audio_samples: delegate.get_audio_frame_count(),
+            duration_ms: elapsed.saturating_sub(delegate.paused_duration()).as_millis() as u64,
+            current_fps: delegate.get_current_fps(),
+            output_size_bytes: delegate.output_size_bytes(),
+            output_path: self.output_path.clone(),
+            is_paused: delegate.is_paused(),
+        };
+        for linked in &self.linked {
+            if let Some(linked) = linked.capture_stats() {
+                stats.video_frames += linked.video_frames;
+                stats.audio_samples += linked.audio_samples;
+                stats.output_size_bytes += linked.output_size_bytes;
+            }
+        }
+        Some(stats)
+    }
+    
+    /// Codec, size and frame rate of the video being encoded; None when not recording
+    pub fn video_encoder_settings(&self) -> Option<VideoEncoderSettings> {
+        if !self.is_recording {
+            return None;
+        }
+        self.delegate.as_ref()?.video_encoder_settings()
+    }
+    
+    pub fn records_audio(&self) -> bool {
+        self.is_recording && self.delegate.as_ref().map(|d| d.has_audio_encoder()).unwrap_or(false)
+    }
+    
+    /// Streams running for this recording: one, plus one per display linked into it
+    pub fn stream_count(&self) -> usize {
+        if self.is_recording { 1 + self.linked.len() } else { 0 }
+    }
+    
     pub fn get_stats(&self) -> String {
+        let mut stats = self.stream_stats();
+        if !self.linked.is_empty() {
+            let displays: Vec<serde_json::Value> = std::iter::once(stats.clone())
+                .chain(self.linked.iter().map(|linked| linked.stream_stats()))
+                .collect();
+            let total = |key: &str| displays.iter().filter_map(|d| d[key].as_u64()).sum::<u64>();
+            stats["totalVideoFrames"] = total("videoFrames").into();
+            stats["totalAudioFrames"] = total("audioFrames").into();
+            stats["displayCount"] = displays.len().into();
+            stats["displays"] = displays.into();
+        }
+        stats.to_string()
+    }
+    
+    fn stream_stats(&self) -> serde_json::Value {
         if let Some(delegate) = &self.delegate {
             let video_frames = delegate.get_frame_count();
             let audio_frames = delegate.get_audio_frame_count();
@@ -810,15 +2939,17 @@ impl RealStreamManager {
                 "method": "real-screencapturekit-stream",
                 "streamActive": !self.stream.is_none(),
                 "delegateActive": delegate.is_recording(),
+                "isPaused": delegate.is_paused(),
+                "pausedDurationMs": delegate.paused_duration().as_millis() as u64,
                 "implementation": "Phase2-RealSCStream"
-            }).to_string()
+            })
         } else {
             serde_json::json!({
                 "isRecording": self.is_recording,
                 "streamActive": !self.stream.is_none(),
                 "error": "No active delegate",
                 "method": "real-screencapturekit-stream"
-            }).to_string()
+            })
         }
     }
 }
\ No newline at end of file
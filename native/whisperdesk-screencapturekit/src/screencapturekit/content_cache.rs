@@ -0,0 +1,54 @@
+// Cold-start content cache - the last known display/window inventory, persisted between launches
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+use super::content::{DisplayInfo, WindowInfo};
+use super::session::{data_dir, now_millis, unique_key};
+use super::errors::{capture_error, io_error, CaptureErrorCode};
+
+const CACHE_FILE: &str = "content-cache.json";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedInventory {
+    pub saved_at: u64,
+    pub displays: Vec<DisplayInfo>,
+    pub windows: Vec<WindowInfo>,
+}
+
+pub struct ContentCache;
+
+impl ContentCache {
+    pub fn load() -> Option<CachedInventory> {
+        let path = data_dir().ok()?.join(CACHE_FILE);
+        let contents = fs::read_to_string(path).ok()?;
+        serde_json::from_str(&contents).ok()
+    }
+
+    /// Persist a completed enumeration; failures only cost the next cold start its head start.
+    /// Window titles are left out, since they can hold document names, mail subjects and the like.
+    pub fn save_or_warn(displays: &[DisplayInfo], windows: &[WindowInfo]) {
+        let inventory = CachedInventory {
+            saved_at: now_millis(),
+            displays: displays.to_vec(),
+            windows: windows
+                .iter()
+                .map(|window| WindowInfo { title: String::new(), ..window.clone() })
+                .collect(),
+        };
+        let result = data_dir().and_then(|dir| {
+            let path = dir.join(CACHE_FILE);
+            let contents = serde_json::to_string(&inventory)
+                .map_err(|e| capture_error(CaptureErrorCode::Unknown, format!("Failed to serialize content cache: {}", e)))?;
+            // Recorders enumerating at the same time each write their own temporary file
+            let tmp_path = path.with_extension(format!("json.{}.tmp", unique_key()));
+            fs::write(&tmp_path, contents)
+                .and_then(|_| fs::rename(&tmp_path, &path))
+                .map_err(|e| io_error("Failed to write content cache", &e))
+        });
+        if let Err(e) = result {
+            log::warn!("Content cache update failed: {}", e);
+        }
+    }
+}
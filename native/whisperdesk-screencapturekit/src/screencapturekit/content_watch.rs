@@ -0,0 +1,107 @@
+// Content watcher - poll the window list and report what changed between polls
+
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::content::{ShareableContent, WindowInfo};
+
+pub const DEFAULT_INTERVAL_MS: u32 = 1000;
+// The Core Graphics window list is cheap, but polling faster than this gains nothing for a picker
+pub const MIN_INTERVAL_MS: u32 = 250;
+
+#[derive(Debug, Clone)]
+pub enum ContentChange {
+    WindowAdded(WindowInfo),
+    WindowClosed(WindowInfo),
+    WindowRenamed { window: WindowInfo, previous_title: String },
+}
+
+impl ContentChange {
+    pub fn kind(&self) -> &'static str {
+        match self {
+            ContentChange::WindowAdded(_) => "windowAdded",
+            ContentChange::WindowClosed(_) => "windowClosed",
+            ContentChange::WindowRenamed { .. } => "windowRenamed",
+        }
+    }
+}
+
+pub type ContentChangeHandler = Arc<dyn Fn(ContentChange) + Send + Sync>;
+
+/// Changes between two window lists; closed windows first, then additions and renames in `new` order
+pub fn diff_windows(old: &[WindowInfo], new: &[WindowInfo]) -> Vec<ContentChange> {
+    let mut changes: Vec<ContentChange> = old
+        .iter()
+        .filter(|w| !new.iter().any(|n| n.id == w.id))
+        .cloned()
+        .map(ContentChange::WindowClosed)
+        .collect();
+
+    for window in new {
+        match old.iter().find(|o| o.id == window.id) {
+            None => changes.push(ContentChange::WindowAdded(window.clone())),
+            Some(previous) if previous.title != window.title => changes.push(ContentChange::WindowRenamed {
+                window: window.clone(),
+                previous_title: previous.title.clone(),
+            }),
+            Some(_) => {}
+        }
+    }
+
+    changes
+}
+
+/// Background poller; stops when `stop` is called or the watcher is dropped
+pub struct ContentWatcher {
+    stop_sender: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl ContentWatcher {
+    pub fn start(interval_ms: u32, on_change: ContentChangeHandler) -> Self {
+        let interval = Duration::from_millis(interval_ms.max(MIN_INTERVAL_MS) as u64);
+        let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+        log::info!("Watching shareable content every {} ms", interval.as_millis());
+
+        let thread = thread::spawn(move || {
+            let mut known = ShareableContent::current_windows().unwrap_or_default();
+            loop {
+                match stop_receiver.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    _ => break,
+                }
+                // Skip polls where the window list itself was unavailable rather than reporting everything closed
+                let current = match ShareableContent::current_windows() {
+                    Some(current) => current,
+                    None => continue,
+                };
+                for change in diff_windows(&known, &current) {
+                    on_change(change);
+                }
+                known = current;
+            }
+            log::info!("Content watcher stopped");
+        });
+
+        Self {
+            stop_sender: Some(stop_sender),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        // Dropping the sender wakes the poller immediately
+        self.stop_sender.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for ContentWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,392 @@
+// Custom cursor compositing - draws a scaled system cursor or a custom image into captured frames
+
+use napi::bindgen_prelude::*;
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_core_video::CVPixelBuffer;
+use objc2_foundation::{NSArray, NSPoint, NSRect, NSSize, NSString, NSURL};
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use super::screenshot::{CGImageRef, CGRect};
+use super::formats::PixelFormat;
+use super::pixel_buffer::{
+    CVPixelBufferLockBaseAddress, CVPixelBufferUnlockBaseAddress, CVPixelBufferGetBaseAddress, CVPixelBufferGetBytesPerRow,
+    CVPixelBufferGetWidth, CVPixelBufferGetHeight, CVPixelBufferGetPixelFormatType,
+};
+
+// kCGImageAlphaPremultipliedFirst | kCGBitmapByteOrder32Little -> BGRA in memory, like the capture frames
+const BGRA_BITMAP_INFO: u32 = 2 | (2 << 12);
+const K_CG_WINDOW_LIST_OPTION_INCLUDING_WINDOW: u32 = 1 << 3;
+
+// The system cursor changes shape (I-beam, hand...) - pick up the current one this often
+const SYSTEM_CURSOR_REFRESH: Duration = Duration::from_millis(250);
+// Window sources move; re-read their bounds this often
+const WINDOW_BOUNDS_REFRESH: Duration = Duration::from_millis(500);
+
+// NSCursor is AppKit and main-thread only: the main queue keeps the current cursor here for the
+// capture threads, which only ever read it
+static SYSTEM_CURSOR: Mutex<Option<CursorArtwork>> = Mutex::new(None);
+static SYSTEM_CURSOR_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" {
+    fn CGEventCreate(source: *const c_void) -> *mut c_void;
+    fn CGEventGetLocation(event: *mut c_void) -> NSPoint;
+    fn CGDisplayBounds(display: u32) -> CGRect;
+    fn CGWindowListCopyWindowInfo(option: u32, relative_to_window: u32) -> *mut NSArray;
+    fn CGRectMakeWithDictionaryRepresentation(dict: *const c_void, rect: *mut CGRect) -> bool;
+    fn CGImageSourceCreateWithURL(url: *const NSURL, options: *const c_void) -> *mut c_void;
+    fn CGImageSourceCreateImageAtIndex(source: *mut c_void, index: usize, options: *const c_void) -> CGImageRef;
+    fn CGImageGetWidth(image: CGImageRef) -> usize;
+    fn CGImageGetHeight(image: CGImageRef) -> usize;
+    fn CGImageRelease(image: CGImageRef);
+    fn CGImageRetain(image: CGImageRef) -> CGImageRef;
+    static _dispatch_main_q: c_void;
+    fn dispatch_async_f(queue: *const c_void, context: *mut c_void, work: extern "C" fn(*mut c_void));
+    fn CGColorSpaceCreateDeviceRGB() -> *mut c_void;
+    fn CGColorSpaceRelease(space: *mut c_void);
+    fn CGBitmapContextCreate(
+        data: *mut c_void,
+        width: usize,
+        height: usize,
+        bits_per_component: usize,
+        bytes_per_row: usize,
+        space: *mut c_void,
+        bitmap_info: u32,
+    ) -> *mut c_void;
+    fn CGContextDrawImage(context: *mut c_void, rect: CGRect, image: CGImageRef);
+    fn CGContextRelease(context: *mut c_void);
+    fn CFRelease(cf: *const c_void);
+}
+
+/// What a stream captures, in global (top-left origin) screen coordinates
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CaptureSource {
+    Display(u32),
+    Window(u32),
+}
+
+impl CaptureSource {
+    /// "display:<id>" or "window:<id>"
+    pub fn parse(screen_id: &str) -> Option<Self> {
+        if let Some(id) = screen_id.strip_prefix("display:") {
+            return id.parse().ok().map(CaptureSource::Display);
+        }
+        screen_id.strip_prefix("window:")?.parse().ok().map(CaptureSource::Window)
+    }
+
+    pub(crate) fn bounds(&self) -> Option<CGRect> {
+        unsafe {
+            match self {
+                CaptureSource::Display(display_id) => {
+                    let bounds = CGDisplayBounds(*display_id);
+                    if bounds.width > 0.0 && bounds.height > 0.0 { Some(bounds) } else { None }
+                }
+                CaptureSource::Window(window_id) => {
+                    let list = CGWindowListCopyWindowInfo(K_CG_WINDOW_LIST_OPTION_INCLUDING_WINDOW, *window_id);
+                    if list.is_null() {
+                        return None;
+                    }
+                    let list_ref: &NSArray = &*list;
+                    let mut result = None;
+                    if list_ref.count() > 0 {
+                        let info: *mut AnyObject = msg_send![list_ref, objectAtIndex: 0usize];
+                        let key = NSString::from_str("kCGWindowBounds");
+                        let bounds_dict: *mut AnyObject = msg_send![info, objectForKey: &*key];
+                        let mut rect = CGRect { x: 0.0, y: 0.0, width: 0.0, height: 0.0 };
+                        if !bounds_dict.is_null() && CGRectMakeWithDictionaryRepresentation(bounds_dict as *const c_void, &mut rect) {
+                            result = Some(rect);
+                        }
+                    }
+                    CFRelease(list as *const c_void);
+                    result
+                }
+            }
+        }
+    }
+}
+
+/// Source artwork for the cursor in points, plus its hot spot; holds a reference to `image`
+struct CursorArtwork {
+    image: CGImageRef,
+    size: NSSize,
+    hot_spot: NSPoint,
+}
+
+// CGImages are immutable, so any thread may draw from one
+unsafe impl Send for CursorArtwork {}
+
+extern "C" fn refresh_system_cursor(_context: *mut c_void) {
+    let artwork = unsafe { CursorArtwork::read_system() };
+    if let Ok(mut cursor) = SYSTEM_CURSOR.lock() {
+        if artwork.is_some() {
+            *cursor = artwork;
+        }
+    }
+    SYSTEM_CURSOR_REQUESTED.store(false, Ordering::Release);
+}
+
+impl CursorArtwork {
+    /// The system cursor as last read on the main queue, asking the main queue for a fresh read.
+    /// None until the main queue has run once, e.g. in a plain Node.js process whose main thread
+    /// never services it.
+    unsafe fn system() -> Option<Self> {
+        if !SYSTEM_CURSOR_REQUESTED.swap(true, Ordering::AcqRel) {
+            dispatch_async_f(&_dispatch_main_q, ptr::null_mut(), refresh_system_cursor);
+        }
+        let cursor = SYSTEM_CURSOR.lock().ok()?;
+        cursor.as_ref().map(|artwork| Self {
+            image: CGImageRetain(artwork.image),
+            size: artwork.size,
+            hot_spot: artwork.hot_spot,
+        })
+    }
+
+    /// Main thread only
+    unsafe fn read_system() -> Option<Self> {
+        let mut cursor: *mut AnyObject = msg_send![class!(NSCursor), currentSystemCursor];
+        if cursor.is_null() {
+            cursor = msg_send![class!(NSCursor), arrowCursor];
+        }
+        if cursor.is_null() {
+            return None;
+        }
+
+        let ns_image: *mut AnyObject = msg_send![cursor, image];
+        let hot_spot: NSPoint = msg_send![cursor, hotSpot];
+        if ns_image.is_null() {
+            return None;
+        }
+        let size: NSSize = msg_send![ns_image, size];
+        let image: CGImageRef = msg_send![
+            ns_image,
+            CGImageForProposedRect: ptr::null_mut::<NSRect>(),
+            context: ptr::null_mut::<AnyObject>(),
+            hints: ptr::null_mut::<AnyObject>()
+        ];
+        if image.is_null() {
+            return None;
+        }
+
+        // The image belongs to the NSImage; keep our own reference past the cursor changing
+        Some(Self { image: CGImageRetain(image), size, hot_spot })
+    }
+
+    /// Custom images are drawn one pixel per point with the hot spot in the top-left corner
+    unsafe fn from_file(path: &str) -> Result<Self> {
+        let path_string = NSString::from_str(path);
+        let url: *mut NSURL = msg_send![class!(NSURL), fileURLWithPath: &*path_string];
+        let source = CGImageSourceCreateWithURL(url, ptr::null());
+        if source.is_null() {
+            return Err(Error::new(Status::InvalidArg, format!("Cannot open cursor image {}", path)));
+        }
+        let image = CGImageSourceCreateImageAtIndex(source, 0, ptr::null());
+        CFRelease(source);
+        if image.is_null() {
+            return Err(Error::new(Status::InvalidArg, format!("Cannot decode cursor image {}", path)));
+        }
+
+        let size = NSSize::new(CGImageGetWidth(image) as f64, CGImageGetHeight(image) as f64);
+        Ok(Self { image, size, hot_spot: NSPoint::new(0.0, 0.0) })
+    }
+}
+
+impl Drop for CursorArtwork {
+    fn drop(&mut self) {
+        unsafe { CGImageRelease(self.image) };
+    }
+}
+
+/// Cursor rasterized at frame resolution, premultiplied BGRA
+struct CursorSprite {
+    pixels: Vec<u8>,
+    width: usize,
+    height: usize,
+    hot_x: f64,
+    hot_y: f64,
+}
+
+impl CursorSprite {
+    unsafe fn render(artwork: &CursorArtwork, scale_x: f64, scale_y: f64) -> Option<Self> {
+        let width = (artwork.size.width * scale_x).round().max(1.0) as usize;
+        let height = (artwork.size.height * scale_y).round().max(1.0) as usize;
+        let mut pixels = vec![0u8; width * height * 4];
+
+        let color_space = CGColorSpaceCreateDeviceRGB();
+        let context = CGBitmapContextCreate(
+            pixels.as_mut_ptr() as *mut c_void,
+            width,
+            height,
+            8,
+            width * 4,
+            color_space,
+            BGRA_BITMAP_INFO,
+        );
+        CGColorSpaceRelease(color_space);
+        if context.is_null() {
+            return None;
+        }
+        CGContextDrawImage(context, CGRect { x: 0.0, y: 0.0, width: width as f64, height: height as f64 }, artwork.image);
+        CGContextRelease(context);
+
+        Some(Self {
+            pixels,
+            width,
+            height,
+            hot_x: artwork.hot_spot.x * scale_x,
+            hot_y: artwork.hot_spot.y * scale_y,
+        })
+    }
+}
+
+/// Composites a cursor into every video frame at the live mouse position.
+/// The stream itself captures without a cursor (showsCursor = false).
+pub struct CursorOverlay {
+    source: CaptureSource,
+    scale: f64,
+    custom: Option<CursorArtwork>,
+    sprite: Option<CursorSprite>,
+    sprite_scale: (f64, f64),
+    sprite_rendered_at: Option<Instant>,
+    bounds: Option<CGRect>,
+    bounds_read_at: Option<Instant>,
+    enabled: bool,
+}
+
+// Only touched under the delegate's mutex; the CGImage is immutable
+unsafe impl Send for CursorOverlay {}
+
+impl CursorOverlay {
+    pub fn new(source: CaptureSource, scale: f64, image_path: Option<&str>) -> Result<Self> {
+        if scale.is_nan() || scale <= 0.0 || scale > 8.0 {
+            return Err(Error::new(Status::InvalidArg, format!("cursor_scale must be between 0 and 8, got {}", scale)));
+        }
+        let custom = match image_path {
+            Some(path) => Some(unsafe { CursorArtwork::from_file(path)? }),
+            None => None,
+        };
+
+        log::info!("Custom cursor overlay: {:.1}x, {}", scale, image_path.unwrap_or("system cursor"));
+        Ok(Self {
+            source,
+            scale,
+            custom,
+            sprite: None,
+            sprite_scale: (0.0, 0.0),
+            sprite_rendered_at: None,
+            bounds: None,
+            bounds_read_at: None,
+            enabled: true,
+        })
+    }
+
+    pub fn set_enabled(&mut self, enabled: bool) {
+        self.enabled = enabled;
+    }
+
+    fn current_bounds(&mut self) -> Option<CGRect> {
+        let stale = match (self.source, self.bounds_read_at) {
+            (_, None) => true,
+            (CaptureSource::Window(_), Some(read_at)) => read_at.elapsed() >= WINDOW_BOUNDS_REFRESH,
+            (CaptureSource::Display(_), Some(_)) => false,
+        };
+        if stale {
+            self.bounds = self.source.bounds();
+            self.bounds_read_at = Some(Instant::now());
+        }
+        self.bounds
+    }
+
+    unsafe fn current_sprite(&mut self, scale_x: f64, scale_y: f64) -> Option<&CursorSprite> {
+        let resized = (self.sprite_scale.0 - scale_x).abs() > 0.01 || (self.sprite_scale.1 - scale_y).abs() > 0.01;
+        let expired = self.custom.is_none()
+            && self.sprite_rendered_at.map(|t| t.elapsed() >= SYSTEM_CURSOR_REFRESH).unwrap_or(true);
+
+        if self.sprite.is_none() || resized || expired {
+            let system = if self.custom.is_none() { CursorArtwork::system() } else { None };
+            if let Some(artwork) = self.custom.as_ref().or(system.as_ref()) {
+                self.sprite = CursorSprite::render(artwork, scale_x, scale_y);
+            }
+            self.sprite_scale = (scale_x, scale_y);
+            self.sprite_rendered_at = Some(Instant::now());
+        }
+        self.sprite.as_ref()
+    }
+
+    /// Draw the cursor into a BGRA frame in place
+    pub unsafe fn composite(&mut self, pixel_buffer: *mut CVPixelBuffer) {
+        if !self.enabled || pixel_buffer.is_null() || CVPixelBufferGetPixelFormatType(pixel_buffer) != PixelFormat::Bgra.four_cc() {
+            return;
+        }
+        let bounds = match self.current_bounds() {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let event = CGEventCreate(ptr::null());
+        if event.is_null() {
+            return;
+        }
+        let location = CGEventGetLocation(event);
+        CFRelease(event as *const c_void);
+
+        let frame_width = CVPixelBufferGetWidth(pixel_buffer);
+        let frame_height = CVPixelBufferGetHeight(pixel_buffer);
+        let points_to_pixels_x = frame_width as f64 / bounds.width;
+        let points_to_pixels_y = frame_height as f64 / bounds.height;
+
+        let scale = self.scale;
+        let sprite = match self.current_sprite(points_to_pixels_x * scale, points_to_pixels_y * scale) {
+            Some(sprite) => sprite,
+            None => return,
+        };
+
+        let origin_x = ((location.x - bounds.x) * points_to_pixels_x - sprite.hot_x).round() as isize;
+        let origin_y = ((location.y - bounds.y) * points_to_pixels_y - sprite.hot_y).round() as isize;
+        if origin_x >= frame_width as isize
+            || origin_y >= frame_height as isize
+            || origin_x + (sprite.width as isize) <= 0
+            || origin_y + (sprite.height as isize) <= 0
+        {
+            return;
+        }
+
+        if CVPixelBufferLockBaseAddress(pixel_buffer, 0) != 0 {
+            return;
+        }
+        let base = CVPixelBufferGetBaseAddress(pixel_buffer) as *mut u8;
+        let bytes_per_row = CVPixelBufferGetBytesPerRow(pixel_buffer);
+
+        if !base.is_null() {
+            for sy in 0..sprite.height {
+                let fy = origin_y + sy as isize;
+                if fy < 0 || fy >= frame_height as isize {
+                    continue;
+                }
+                let row = base.add(fy as usize * bytes_per_row);
+                for sx in 0..sprite.width {
+                    let fx = origin_x + sx as isize;
+                    if fx < 0 || fx >= frame_width as isize {
+                        continue;
+                    }
+                    let src = &sprite.pixels[(sy * sprite.width + sx) * 4..][..4];
+                    let alpha = src[3] as u32;
+                    if alpha == 0 {
+                        continue;
+                    }
+                    // Premultiplied source-over
+                    let dst = row.add(fx as usize * 4);
+                    for channel in 0..4 {
+                        let d = *dst.add(channel) as u32;
+                        *dst.add(channel) = (src[channel] as u32 + d * (255 - alpha) / 255).min(255) as u8;
+                    }
+                }
+            }
+        }
+
+        CVPixelBufferUnlockBaseAddress(pixel_buffer, 0);
+    }
+}
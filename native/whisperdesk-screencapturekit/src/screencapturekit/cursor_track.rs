@@ -0,0 +1,156 @@
+// Cursor track - the cursor's position and visibility for every encoded frame, written to a JSON
+// sidecar so an editor can re-render a smoothed or enlarged cursor afterwards
+
+use napi::bindgen_prelude::*;
+use objc2_foundation::NSPoint;
+use serde::Serialize;
+use std::ffi::c_void;
+use std::path::Path;
+use std::ptr;
+use std::time::{Duration, Instant};
+
+use super::cursor::CaptureSource;
+use super::screenshot::CGRect;
+use super::session::now_millis;
+use super::errors::{capture_error, io_error, CaptureErrorCode};
+
+const SIDECAR_VERSION: u32 = 1;
+
+// Window sources move; re-read their bounds this often
+const WINDOW_BOUNDS_REFRESH: Duration = Duration::from_millis(500);
+
+extern "C" {
+    fn CGEventCreate(source: *const c_void) -> *mut c_void;
+    fn CGEventGetLocation(event: *mut c_void) -> NSPoint;
+    fn CGCursorIsVisible() -> bool;
+    fn CFRelease(cf: *const c_void);
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CursorSample {
+    /// Index of the video frame, counting from 0
+    pub frame: u64,
+    /// Capture time since the first frame, pauses left out
+    pub offset_ms: f64,
+    /// Hot spot in frame pixels, top-left origin; may lie outside the frame
+    pub x: f64,
+    pub y: f64,
+    /// The system shows the cursor (it's hidden while typing in some apps, for instance)
+    pub visible: bool,
+    /// The hot spot is within the captured area
+    pub in_frame: bool,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct CursorTrackFile<'a> {
+    version: u32,
+    /// Unix time in milliseconds of the first frame
+    started_at: u64,
+    frame_width: u32,
+    frame_height: u32,
+    samples: &'a [CursorSample],
+}
+
+/// Samples the cursor once per encoded frame and writes the track when the recording stops
+pub struct CursorTrack {
+    path: String,
+    source: CaptureSource,
+    bounds: Option<CGRect>,
+    bounds_read_at: Option<Instant>,
+    started_at: u64,
+    first_frame_time: Option<f64>,
+    frame_size: (u32, u32),
+    frames: u64,
+    samples: Vec<CursorSample>,
+}
+
+impl CursorTrack {
+    /// `<recording>.cursor.json` next to the recording
+    pub fn path_for(output_path: &str) -> String {
+        Path::new(output_path).with_extension("cursor.json").to_string_lossy().to_string()
+    }
+
+    pub fn new(path: &str, source: CaptureSource) -> Self {
+        log::info!("Recording cursor track to {}", path);
+        Self {
+            path: path.to_string(),
+            source,
+            bounds: None,
+            bounds_read_at: None,
+            started_at: now_millis(),
+            first_frame_time: None,
+            frame_size: (0, 0),
+            frames: 0,
+            samples: Vec::new(),
+        }
+    }
+
+    fn current_bounds(&mut self) -> Option<CGRect> {
+        let stale = match (self.source, self.bounds_read_at) {
+            (_, None) => true,
+            (CaptureSource::Window(_), Some(read_at)) => read_at.elapsed() >= WINDOW_BOUNDS_REFRESH,
+            (CaptureSource::Display(_), Some(_)) => false,
+        };
+        if stale {
+            self.bounds = self.source.bounds();
+            self.bounds_read_at = Some(Instant::now());
+        }
+        self.bounds
+    }
+
+    /// Note where the cursor is for the frame just encoded, `frame_time` seconds into the capture
+    pub fn record(&mut self, frame_width: u32, frame_height: u32, frame_time: f64) {
+        let frame = self.frames;
+        self.frames += 1;
+        if self.first_frame_time.is_none() {
+            self.first_frame_time = Some(frame_time);
+            self.started_at = now_millis();
+        }
+        let bounds = match self.current_bounds() {
+            Some(bounds) => bounds,
+            None => return,
+        };
+        let (location, visible) = unsafe {
+            let event = CGEventCreate(ptr::null());
+            if event.is_null() {
+                return;
+            }
+            let location = CGEventGetLocation(event);
+            CFRelease(event as *const c_void);
+            (location, CGCursorIsVisible())
+        };
+
+        let first_frame_time = self.first_frame_time.unwrap_or(frame_time);
+        self.frame_size = (frame_width, frame_height);
+        let x = (location.x - bounds.x) * frame_width as f64 / bounds.width;
+        let y = (location.y - bounds.y) * frame_height as f64 / bounds.height;
+        self.samples.push(CursorSample {
+            frame,
+            offset_ms: (frame_time - first_frame_time) * 1000.0,
+            x,
+            y,
+            visible,
+            in_frame: x >= 0.0 && y >= 0.0 && x < frame_width as f64 && y < frame_height as f64,
+        });
+    }
+
+    /// Write the sidecar; returns its path
+    pub fn finalize(&mut self) -> Result<String> {
+        let file = CursorTrackFile {
+            version: SIDECAR_VERSION,
+            started_at: self.started_at,
+            frame_width: self.frame_size.0,
+            frame_height: self.frame_size.1,
+            samples: &self.samples,
+        };
+        let json = serde_json::to_string(&file)
+            .map_err(|e| capture_error(CaptureErrorCode::Unknown, format!("Failed to serialize cursor track: {}", e)))?;
+        std::fs::write(&self.path, json)
+            .map_err(|e| io_error(format!("Failed to write {}", self.path), &e))?;
+
+        log::info!("Cursor track written: {} ({} frames)", self.path, self.samples.len());
+        Ok(self.path.clone())
+    }
+}
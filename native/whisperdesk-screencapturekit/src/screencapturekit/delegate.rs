@@ -1,4 +1,5 @@
 use std::sync::{Arc, Mutex};
+use napi_derive::napi;
 use objc2::runtime::AnyObject;
 use objc2::{msg_send, class};
 use objc2_foundation::{NSError, NSString};
@@ -6,7 +7,188 @@ use objc2_core_media::{CMSampleBuffer, CMTime};
 use objc2_core_video::{CVImageBuffer, CVPixelBuffer};
 
 use super::bindings::{SCStream, SCStreamDelegate, SCStreamOutputType};
-use super::encoder::{VideoEncoder, AudioEncoder};
+use super::encoder::{VideoEncoder, VideoEncoderSettings, AudioEncoder};
+use super::formats::VideoCodec;
+use super::audio::wav::{SidecarSource, WavSidecar};
+use super::audio_tap::{extract_pcm, sample_buffer_timing, AudioTap, PcmBuffer};
+use super::process_tap::PcmHandler;
+use super::camera::CameraOverlay;
+use super::cursor::CursorOverlay;
+use super::cursor_track::CursorTrack;
+use super::frames::{self, VideoFrameListener};
+use super::markers::{self, Marker};
+use super::overlay::ClickHighlighter;
+use super::pixel_buffer::{CVPixelBufferGetHeight, CVPixelBufferGetWidth};
+use super::canvas::CanvasTarget;
+use super::replay::{ReplayBuffer, ReplaySnapshot};
+use super::zoom::ZoomFollow;
+use super::session::{now_millis, JournalEvent, SessionJournal};
+use super::secure_input::{self, SecureInputAction, SecureInputGuard, SecureInputListener};
+use super::errors::{capture_error, CaptureErrorCode};
+
+// Audio stays paused at most this long waiting for a route switch to complete
+const ROUTE_SWITCH_TIMEOUT: std::time::Duration = std::time::Duration::from_secs(2);
+// Shorter gaps are ordinary buffer jitter, longer ones are not a route switch
+const MIN_ROUTE_GAP_SECONDS: f64 = 0.02;
+const MAX_ROUTE_GAP_SECONDS: f64 = 30.0;
+
+/// Steps of stopping a recording, in the order they run
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum FinalizePhase {
+    /// Stopping the stream and waiting for its last buffers
+    Stream,
+    /// Flushing the video encoder and writing the MP4 index; the long one for long recordings
+    Video,
+    Audio,
+    /// WAV, marker, cursor and input event files
+    Sidecars,
+    /// Closing the session journal and library entry
+    Session,
+    Done,
+}
+
+impl FinalizePhase {
+    /// How far along the stop is once this phase begins, weighted by how long each usually takes
+    pub fn percent(&self) -> f64 {
+        match self {
+            FinalizePhase::Stream => 0.0,
+            FinalizePhase::Video => 20.0,
+            FinalizePhase::Audio => 75.0,
+            FinalizePhase::Sidecars => 85.0,
+            FinalizePhase::Session => 95.0,
+            FinalizePhase::Done => 100.0,
+        }
+    }
+}
+
+/// Audio timeline across route changes: buffers are dropped while capture moves to the new device,
+/// and the gap is filled with silence once audio resumes so the track stays in sync with the video
+#[derive(Default)]
+pub struct AudioRouteState {
+    switching_since: Option<std::time::Instant>,
+    compensate: bool,
+    last_end: Option<f64>,
+}
+
+impl AudioRouteState {
+    pub fn begin_switch(&mut self) {
+        self.switching_since = Some(std::time::Instant::now());
+    }
+    
+    pub fn end_switch(&mut self) {
+        if self.switching_since.take().is_some() {
+            self.compensate = true;
+        }
+    }
+    
+    fn is_switching(&mut self) -> bool {
+        match self.switching_since {
+            Some(since) if since.elapsed() >= ROUTE_SWITCH_TIMEOUT => {
+                log::warn!("Audio route switch timed out - resuming audio");
+                self.end_switch();
+                false
+            }
+            Some(_) => true,
+            None => false,
+        }
+    }
+    
+    /// Record a buffer about to be encoded; returns the (start, duration) of silence owed in front of it
+    fn take_gap(&mut self, start: f64, duration: f64) -> Option<(f64, f64)> {
+        let gap = match (self.compensate, self.last_end) {
+            (true, Some(last_end)) => {
+                let length = start - last_end;
+                if (MIN_ROUTE_GAP_SECONDS..=MAX_ROUTE_GAP_SECONDS).contains(&length) {
+                    Some((last_end, length))
+                } else {
+                    None
+                }
+            }
+            _ => None,
+        };
+        self.compensate = false;
+        self.last_end = Some(self.last_end.map(|end| end.max(start + duration)).unwrap_or(start + duration));
+        gap
+    }
+}
+
+/// Manual pause: video frames and audio are dropped while paused. Video is timed by frame count and
+/// closes up by itself (a timelapse is handed capture times with the pauses taken out); audio after
+/// a resume is shifted back by the gap so it stays in sync. Secure input protection in "pause" mode
+/// holds audio the same way, independently of the manual pause.
+#[derive(Default)]
+pub struct PauseState {
+    paused_since: Option<std::time::Instant>,
+    secure_input_held: bool,
+    paused_total: std::time::Duration,
+    audio_offset: f64,
+    resync_audio: bool,
+    last_audio_end: Option<f64>,
+}
+
+impl PauseState {
+    /// False when already paused
+    pub fn pause(&mut self) -> bool {
+        if self.paused_since.is_some() {
+            return false;
+        }
+        self.paused_since = Some(std::time::Instant::now());
+        true
+    }
+    
+    /// False when not paused
+    pub fn resume(&mut self) -> bool {
+        match self.paused_since.take() {
+            Some(since) => {
+                self.paused_total += since.elapsed();
+                self.resync_audio = true;
+                true
+            }
+            None => false,
+        }
+    }
+    
+    pub fn is_paused(&self) -> bool {
+        self.paused_since.is_some()
+    }
+    
+    /// Hold or release audio for secure input; releasing resyncs like a resume
+    pub fn set_secure_input_held(&mut self, held: bool) {
+        if self.secure_input_held && !held {
+            self.resync_audio = true;
+        }
+        self.secure_input_held = held;
+    }
+    
+    fn holds_audio(&self) -> bool {
+        self.paused_since.is_some() || self.secure_input_held
+    }
+    
+    /// Time spent paused so far, including a pause still in progress
+    pub fn paused_duration(&self) -> std::time::Duration {
+        self.paused_total + self.paused_since.map(|since| since.elapsed()).unwrap_or_default()
+    }
+    
+    /// Seconds to subtract from an audio buffer starting at `start`; `None` while paused or held
+    fn audio_offset(&mut self, start: f64, duration: f64) -> Option<f64> {
+        if self.holds_audio() {
+            return None;
+        }
+        if self.resync_audio {
+            self.resync_audio = false;
+            if let Some(last_end) = self.last_audio_end {
+                let gap = start - self.audio_offset - last_end;
+                if gap > 0.0 {
+                    self.audio_offset += gap;
+                }
+            }
+        }
+        let end = start - self.audio_offset + duration;
+        self.last_audio_end = Some(self.last_audio_end.map(|last| last.max(end)).unwrap_or(end));
+        Some(self.audio_offset)
+    }
+}
 
 // Real SCStreamDelegate implementation using objc2 bindings
 pub struct RealStreamDelegate {
@@ -18,38 +200,135 @@ pub struct RealStreamDelegate {
     audio_encoder: Option<Arc<Mutex<AudioEncoder>>>,
     last_frame_time: Arc<Mutex<std::time::Instant>>,
     fps_counter: Arc<Mutex<f64>>,
+    audio_tap: Arc<Mutex<Option<AudioTap>>>,
+    cursor_overlay: Arc<Mutex<Option<CursorOverlay>>>,
+    click_highlighter: Arc<Mutex<Option<ClickHighlighter>>>,
+    camera_overlay: Arc<Mutex<Option<CameraOverlay>>>,
+    cursor_track: Arc<Mutex<Option<CursorTrack>>>,
+    markers: Arc<Mutex<Vec<Marker>>>,
+    microphone_muted: Arc<Mutex<bool>>,
+    secure_input: Arc<Mutex<Option<SecureInputGuard>>>,
+    wav_sidecar: Arc<Mutex<Option<WavSidecar>>>,
+    audio_buffer_listener: Arc<Mutex<Option<PcmHandler>>>,
+    video_frame_listener: Arc<Mutex<Option<VideoFrameListener>>>,
+    audio_route: Arc<Mutex<AudioRouteState>>,
+    pause: Arc<Mutex<PauseState>>,
+    session_id: Arc<Mutex<Option<String>>>,
+    replay: Arc<Mutex<Option<ReplayBuffer>>>,
+    canvas: Arc<Mutex<Option<CanvasTarget>>>,
+    zoom: Arc<Mutex<Option<ZoomFollow>>>,
+    // Why ScreenCaptureKit ended the stream on its own, if it did
+    stream_error: Arc<Mutex<Option<String>>>,
+}
+
+/// Hand PCM to the consumers that need decoded samples rather than sample buffers
+fn forward_pcm(
+    pcm: &PcmBuffer,
+    source: SidecarSource,
+    wav_sidecar: &Mutex<Option<WavSidecar>>,
+    audio_buffer_listener: &Mutex<Option<PcmHandler>>,
+    replay: &Mutex<Option<ReplayBuffer>>,
+) {
+    if let Ok(mut replay) = replay.lock() {
+        if let Some(replay) = replay.as_mut() {
+            replay.push_pcm(pcm);
+        }
+    }
+    
+    if let Ok(mut sidecar) = wav_sidecar.lock() {
+        if let Some(sidecar) = sidecar.as_mut() {
+            sidecar.push_pcm(source, pcm);
+        }
+    }
+    
+    if let Ok(listener) = audio_buffer_listener.lock() {
+        if let Some(listener) = listener.as_ref() {
+            listener(pcm);
+        }
+    }
 }
 
 impl RealStreamDelegate {
-    pub fn new(output_path: String, is_recording: Arc<Mutex<bool>>, width: u32, height: u32, fps: u32) -> Self {
-        println!("🎬 Creating RealStreamDelegate for recording: {}", output_path);
+    /// `audio_channels` is 2 unless the microphone is an aggregate device whose inputs all need a channel
+    pub fn new(
+        output_path: String,
+        is_recording: Arc<Mutex<bool>>,
+        width: u32,
+        height: u32,
+        fps: u32,
+        codec: VideoCodec,
+        audio_channels: u32,
+    ) -> Self {
+        log::info!("Creating RealStreamDelegate for recording: {}", output_path);
         
         // Create video encoder
-        let video_encoder = VideoEncoder::new(&format!("{}_video.mp4", output_path), width, height, fps)
-            .map(|encoder| {
-                println!("✅ Video encoder created: {}x{} @ {}fps", width, height, fps);
+        let video_encoder = VideoEncoder::with_codec(&format!("{}_video.mp4", output_path), width, height, fps, codec)
+            .map(|mut encoder| {
+                encoder.enable_chapters();
+                log::info!("Video encoder created: {}x{} @ {}fps ({:?})", width, height, fps, codec);
                 Arc::new(Mutex::new(encoder))
             })
             .map_err(|e| {
-                println!("⚠️ Video encoder creation failed: {}", e);
+                log::warn!("Video encoder creation failed: {}", e);
                 e
             })
             .ok();
         
         // Create audio encoder
-        let audio_encoder = AudioEncoder::new(&format!("{}_audio.mp4", output_path), 48000, 2)
+        let audio_encoder = AudioEncoder::new(&format!("{}_audio.mp4", output_path), 48000, audio_channels)
             .map(|encoder| {
-                println!("✅ Audio encoder created: 48kHz stereo");
+                log::info!("Audio encoder created: 48kHz x{}", audio_channels);
                 Arc::new(Mutex::new(encoder))
             })
             .map_err(|e| {
-                println!("⚠️ Audio encoder creation failed: {}", e);
+                log::warn!("Audio encoder creation failed: {}", e);
                 e
             })
             .ok();
         
+        Self::with_encoders(output_path, is_recording, video_encoder, audio_encoder)
+    }
+    
+    /// `<output>_audio.m4a`, the file of an audio-only recording
+    pub fn audio_only_path(output_path: &str) -> String {
+        format!("{}_audio.m4a", output_path)
+    }
+    
+    /// Audio-only mode: the stream's frames are dropped and only the audio is encoded, to an M4A
+    pub fn new_audio_only(output_path: String, is_recording: Arc<Mutex<bool>>, audio_channels: u32) -> napi::Result<Self> {
+        log::info!("Creating RealStreamDelegate for audio-only recording: {}", output_path);
+        let encoder = AudioEncoder::new_m4a(&Self::audio_only_path(&output_path), 48000, audio_channels)?;
+        log::info!("Audio encoder created: 48kHz x{} M4A", audio_channels);
+        Ok(Self::with_encoders(output_path, is_recording, None, Some(Arc::new(Mutex::new(encoder)))))
+    }
+    
+    /// Replay mode: frames and audio only go into the ring buffer, nothing is written to `output_path`
+    pub fn new_replay(output_path: String, is_recording: Arc<Mutex<bool>>, replay: ReplayBuffer) -> Self {
+        log::info!("Creating RealStreamDelegate for replay buffer: {}", output_path);
+        let delegate = Self::with_encoders(output_path, is_recording, None, None);
+        if let Ok(mut current) = delegate.replay.lock() {
+            *current = Some(replay);
+        }
+        delegate
+    }
+    
+    /// A secondary display of a composite recording: frames are drawn into the shared canvas,
+    /// which the primary stream encodes
+    pub fn new_canvas_feeder(output_path: String, is_recording: Arc<Mutex<bool>>, target: CanvasTarget) -> Self {
+        log::info!("Creating RealStreamDelegate feeding the composite canvas of {}", output_path);
+        let delegate = Self::with_encoders(output_path, is_recording, None, None);
+        delegate.set_canvas(Some(target));
+        delegate
+    }
+    
+    fn with_encoders(
+        output_path: String,
+        is_recording: Arc<Mutex<bool>>,
+        video_encoder: Option<Arc<Mutex<VideoEncoder>>>,
+        audio_encoder: Option<Arc<Mutex<AudioEncoder>>>,
+    ) -> Self {
         Self {
-            output_path: output_path.clone(),
+            output_path,
             is_recording,
             frame_count: Arc::new(Mutex::new(0)),
             audio_frame_count: Arc::new(Mutex::new(0)),
@@ -57,13 +336,295 @@ impl RealStreamDelegate {
             audio_encoder,
             last_frame_time: Arc::new(Mutex::new(std::time::Instant::now())),
             fps_counter: Arc::new(Mutex::new(0.0)),
+            audio_tap: Arc::new(Mutex::new(None)),
+            cursor_overlay: Arc::new(Mutex::new(None)),
+            click_highlighter: Arc::new(Mutex::new(None)),
+            camera_overlay: Arc::new(Mutex::new(None)),
+            cursor_track: Arc::new(Mutex::new(None)),
+            markers: Arc::new(Mutex::new(Vec::new())),
+            microphone_muted: Arc::new(Mutex::new(false)),
+            secure_input: Arc::new(Mutex::new(None)),
+            wav_sidecar: Arc::new(Mutex::new(None)),
+            audio_buffer_listener: Arc::new(Mutex::new(None)),
+            video_frame_listener: Arc::new(Mutex::new(None)),
+            audio_route: Arc::new(Mutex::new(AudioRouteState::default())),
+            pause: Arc::new(Mutex::new(PauseState::default())),
+            session_id: Arc::new(Mutex::new(None)),
+            replay: Arc::new(Mutex::new(None)),
+            canvas: Arc::new(Mutex::new(None)),
+            zoom: Arc::new(Mutex::new(None)),
+            stream_error: Arc::new(Mutex::new(None)),
+        }
+    }
+    
+    /// Install or remove the live PCM tap; a replaced tap flushes its buffered samples first
+    pub fn set_audio_tap(&self, tap: Option<AudioTap>) {
+        if let Ok(mut current) = self.audio_tap.lock() {
+            if let Some(old_tap) = current.as_mut() {
+                old_tap.flush();
+            }
+            *current = tap;
+        }
+    }
+    
+    pub fn set_cursor_overlay(&self, overlay: Option<CursorOverlay>) {
+        if let Ok(mut current) = self.cursor_overlay.lock() {
+            *current = overlay;
+        }
+    }
+    
+    /// Install or remove the webcam picture-in-picture; a removed overlay stops the camera
+    pub fn set_camera_overlay(&self, overlay: Option<CameraOverlay>) {
+        if let Ok(mut current) = self.camera_overlay.lock() {
+            *current = overlay;
+        }
+    }
+    
+    /// Install or remove click highlighting; a removed highlighter stops its event tap
+    pub fn set_click_highlighter(&self, highlighter: Option<ClickHighlighter>) {
+        if let Ok(mut current) = self.click_highlighter.lock() {
+            *current = highlighter;
+        }
+    }
+    
+    /// Show or hide the composited cursor; false when no custom cursor is in use
+    pub fn set_cursor_overlay_enabled(&self, enabled: bool) -> bool {
+        match self.cursor_overlay.lock() {
+            Ok(mut current) => match current.as_mut() {
+                Some(overlay) => {
+                    overlay.set_enabled(enabled);
+                    true
+                }
+                None => false,
+            },
+            Err(_) => false,
+        }
+    }
+    
+    /// Mute or unmute the microphone track; returns true when the state changed
+    pub fn set_microphone_muted(&self, muted: bool) -> bool {
+        match self.microphone_muted.lock() {
+            Ok(mut current) if *current != muted => {
+                *current = muted;
+                true
+            }
+            _ => false,
+        }
+    }
+    
+    pub fn is_microphone_muted(&self) -> bool {
+        self.microphone_muted.lock().map(|guard| *guard).unwrap_or(false)
+    }
+    
+    /// Encode a crop that follows the cursor instead of the whole frame
+    pub fn set_zoom(&self, zoom: Option<ZoomFollow>) {
+        if let Ok(mut current) = self.zoom.lock() {
+            *current = zoom;
+        }
+    }
+    
+    pub fn set_cursor_track(&self, track: Option<CursorTrack>) {
+        if let Ok(mut current) = self.cursor_track.lock() {
+            *current = track;
+        }
+    }
+    
+    pub fn set_wav_sidecar(&self, sidecar: Option<WavSidecar>) {
+        if let Ok(mut current) = self.wav_sidecar.lock() {
+            *current = sidecar;
+        }
+    }
+    
+    /// Receive every audio buffer as planar f32, after muting and secure-input handling
+    pub fn set_audio_buffer_listener(&self, listener: Option<PcmHandler>) {
+        if let Ok(mut current) = self.audio_buffer_listener.lock() {
+            *current = listener;
+        }
+    }
+    
+    /// Time the recording as a timelapse, `speedup` capture seconds per second of video
+    pub fn set_timelapse(&self, speedup: f64) {
+        if let Some(encoder) = &self.video_encoder {
+            if let Ok(mut encoder) = encoder.lock() {
+                encoder.set_timelapse(speedup);
+            }
+        }
+    }
+    
+    /// Receive every captured frame as it goes to the encoder, overlays and blanking included
+    pub fn set_video_frame_listener(&self, listener: Option<VideoFrameListener>) {
+        if let Ok(mut current) = self.video_frame_listener.lock() {
+            *current = listener;
+        }
+    }
+    
+    fn has_video_frame_listener(&self) -> bool {
+        self.video_frame_listener.lock().map(|l| l.is_some()).unwrap_or(false)
+    }
+    
+    fn has_pcm_consumers(&self) -> bool {
+        self.wav_sidecar.lock().map(|s| s.is_some()).unwrap_or(false)
+            || self.audio_buffer_listener.lock().map(|l| l.is_some()).unwrap_or(false)
+            || self.has_replay()
+    }
+    
+    pub fn set_canvas(&self, target: Option<CanvasTarget>) {
+        if let Ok(mut current) = self.canvas.lock() {
+            *current = target;
+        }
+    }
+    
+    fn has_canvas(&self) -> bool {
+        self.canvas.lock().map(|c| c.is_some()).unwrap_or(false)
+    }
+    
+    fn has_replay(&self) -> bool {
+        self.replay.lock().map(|r| r.is_some()).unwrap_or(false)
+    }
+    
+    /// Set the replay window aside for export; capture keeps going into the ring meanwhile
+    /// Mark the latest frame: a chapter in the MP4 and an entry in `<output>.markers.json`
+    pub fn add_marker(&self, label: &str) -> napi::Result<Marker> {
+        let encoder = self.video_encoder.as_ref()
+            .ok_or_else(|| capture_error(CaptureErrorCode::InvalidConfiguration, "This recording doesn't write video to mark"))?;
+        let time = encoder.lock()
+            .map(|mut encoder| encoder.add_chapter(label))
+            .map_err(|_| capture_error(CaptureErrorCode::Unknown, "Video encoder is poisoned"))?;
+        let marker = Marker {
+            label: label.to_string(),
+            time,
+            created_at: now_millis(),
+        };
+        if let Ok(mut markers) = self.markers.lock() {
+            markers.push(marker.clone());
+        }
+        Ok(marker)
+    }
+    
+    pub fn snapshot_replay(&self) -> napi::Result<ReplaySnapshot> {
+        match self.replay.lock() {
+            Ok(mut replay) => match replay.as_mut() {
+                Some(replay) => replay.snapshot(),
+                None => Err(capture_error(CaptureErrorCode::InvalidConfiguration, "This recording has no replay buffer")),
+            },
+            Err(_) => Err(capture_error(CaptureErrorCode::Unknown, "Replay buffer is poisoned")),
+        }
+    }
+    
+    /// Session whose journal receives failures reported by the stream and encoders
+    pub fn set_session_id(&self, session_id: &str) {
+        if let Ok(mut current) = self.session_id.lock() {
+            *current = Some(session_id.to_string());
+        }
+    }
+    
+    fn journal_failure(&self, error: String) {
+        if let Ok(session_id) = self.session_id.lock() {
+            if let Some(session_id) = session_id.as_ref() {
+                SessionJournal::append_or_warn(&JournalEvent::Failed {
+                    session_id: session_id.clone(),
+                    timestamp: now_millis(),
+                    error,
+                });
+            }
+        }
+    }
+    
+    /// Shared with the route listener, which pauses and resumes audio around device switches
+    pub fn audio_route(&self) -> Arc<Mutex<AudioRouteState>> {
+        self.audio_route.clone()
+    }
+    
+    /// Pause the recording; false when it already was
+    pub fn pause(&self) -> bool {
+        self.pause.lock().map(|mut pause| pause.pause()).unwrap_or(false)
+    }
+    
+    /// Resume a paused recording; false when it wasn't paused
+    pub fn resume(&self) -> bool {
+        self.pause.lock().map(|mut pause| pause.resume()).unwrap_or(false)
+    }
+    
+    pub fn is_paused(&self) -> bool {
+        self.pause.lock().map(|pause| pause.is_paused()).unwrap_or(false)
+    }
+    
+    pub fn paused_duration(&self) -> std::time::Duration {
+        self.pause.lock().map(|pause| pause.paused_duration()).unwrap_or_default()
+    }
+    
+    /// `None` while paused or while the audio route is switching, otherwise the seconds to move the
+    /// buffer back by to cover earlier pauses. The first buffer after a route switch gets the gap in
+    /// front of it filled with silence in the recording; the WAV sidecar fills gaps from timestamps.
+    fn admit_audio(&self, sample_buffer: &CMSampleBuffer) -> Option<f64> {
+        if let Ok(mut route) = self.audio_route.lock() {
+            if route.is_switching() {
+                return None;
+            }
+        }
+        
+        let timing = sample_buffer_timing(sample_buffer);
+        let offset = match self.pause.lock() {
+            Ok(mut pause) => match timing {
+                Some((start, duration)) => pause.audio_offset(start, duration)?,
+                None if pause.holds_audio() => return None,
+                None => 0.0,
+            },
+            Err(_) => 0.0,
+        };
+        
+        let gap = match self.audio_route.lock() {
+            Ok(mut route) => timing.and_then(|(start, duration)| route.take_gap(start, duration)),
+            Err(_) => None,
+        };
+        
+        if let Some((start, duration)) = gap {
+            log::info!("Filling {:.0} ms of silence across the audio route change", duration * 1000.0);
+            if let Some(encoder) = &self.audio_encoder {
+                if let Ok(mut encoder) = encoder.lock() {
+                    if let Err(e) = encoder.encode_silence(start - offset, duration) {
+                        log::error!("Failed to encode route change silence: {}", e);
+                    }
+                }
+            }
+        }
+        Some(offset)
+    }
+    
+    pub fn set_secure_input_guard(&self, guard: Option<SecureInputGuard>) {
+        if let Ok(mut current) = self.secure_input.lock() {
+            *current = guard;
         }
     }
     
+    pub fn set_secure_input_listener(&self, listener: Option<SecureInputListener>) {
+        if let Ok(mut current) = self.secure_input.lock() {
+            if let Some(guard) = current.as_mut() {
+                guard.set_listener(listener);
+            }
+        }
+    }
+    
+    /// The protection to apply to the current buffer, if secure input is active. A "pause" also
+    /// holds audio in the pause state, so `admit_audio` closes the gap once it lifts.
+    fn secure_input_protection(&self) -> Option<SecureInputAction> {
+        let protection = match self.secure_input.lock() {
+            Ok(mut guard) => match guard.as_mut() {
+                Some(guard) => guard.poll().then(|| guard.action()),
+                None => None,
+            },
+            Err(_) => None,
+        };
+        if let Ok(mut pause) = self.pause.lock() {
+            pause.set_secure_input_held(protection == Some(SecureInputAction::Pause));
+        }
+        protection
+    }
+    
     /// Create a real Objective-C delegate object that implements SCStreamDelegate protocol
     pub fn create_objc_delegate(&self) -> *mut AnyObject {
         unsafe {
-            println!("🔧 Creating real SCStreamDelegate Objective-C object with protocol implementation");
+            log::info!("Creating real SCStreamDelegate Objective-C object with protocol implementation");
             
             // For Phase 3A, we'll use a simplified delegate approach
             // Create a basic NSObject that can be used as a delegate
@@ -72,12 +633,12 @@ impl RealStreamDelegate {
             let delegate: *mut AnyObject = msg_send![delegate_class, new];
             
             if delegate.is_null() {
-                println!("❌ Failed to create delegate object");
+                log::error!("Failed to create delegate object");
                 return std::ptr::null_mut();
             }
             
-            println!("✅ Created SCStreamDelegate object (Phase 3A implementation)");
-            println!("💡 Real frame processing will be handled by stream manager callbacks");
+            log::info!("Created SCStreamDelegate object (Phase 3A implementation)");
+            log::info!("Real frame processing will be handled by stream manager callbacks");
             delegate
         }
     }
@@ -85,6 +646,15 @@ impl RealStreamDelegate {
     
     /// Process real video sample buffer from ScreenCaptureKit
     pub fn handle_video_sample_buffer(&self, sample_buffer: &CMSampleBuffer) {
+        // Frames are timed by count, so dropping them while paused leaves no gap in the video
+        if self.is_paused() {
+            return;
+        }
+        let protection = self.secure_input_protection();
+        if protection == Some(SecureInputAction::Pause) {
+            return;
+        }
+        
         // Update frame count and FPS calculation
         if let Ok(mut count) = self.frame_count.lock() {
             *count += 1;
@@ -97,14 +667,14 @@ impl RealStreamDelegate {
                     *fps = 30.0 / duration.as_secs_f64();
                     *last_time = now;
                     
-                    println!("📊 Video stats: {} frames, {:.1} FPS", *count, *fps);
+                    log::debug!("Video stats: {} frames, {:.1} FPS", *count, *fps);
                 }
             }
         }
         
         // Process the video frame
-        if let Some(ref encoder) = self.video_encoder {
-            self.process_video_sample_buffer(sample_buffer, encoder);
+        if self.video_encoder.is_some() || self.has_replay() || self.has_canvas() || self.has_video_frame_listener() {
+            self.process_video_sample_buffer(sample_buffer, protection == Some(SecureInputAction::Blank));
         } else {
             // Even without encoder, we can validate the frame data
             self.validate_video_frame(sample_buffer);
@@ -112,17 +682,156 @@ impl RealStreamDelegate {
     }
     
     /// Process real audio sample buffer from ScreenCaptureKit
-    pub fn handle_audio_sample_buffer(&self, sample_buffer: &CMSampleBuffer) {
+    pub fn handle_audio_sample_buffer(&self, sample_buffer: &CMSampleBuffer, source: SidecarSource) {
+        self.secure_input_protection();
+        let offset = match self.admit_audio(sample_buffer) {
+            Some(offset) => offset,
+            None => return,
+        };
+        
         if let Ok(mut count) = self.audio_frame_count.lock() {
             *count += 1;
             if *count % 100 == 0 {
-                println!("🔊 Audio stats: {} samples processed", *count);
+                log::info!("Audio stats: {} samples processed", *count);
+            }
+        }
+        
+        // Audio after a pause needs new timestamps, which only the PCM path can give it
+        if offset > 0.0 {
+            if let Some(mut pcm) = extract_pcm(sample_buffer) {
+                pcm.presentation_time -= offset;
+                if let Some(encoder) = &self.audio_encoder {
+                    if let Ok(mut encoder) = encoder.lock() {
+                        if let Err(e) = encoder.encode_pcm_buffer(&pcm) {
+                            log::error!("Failed to encode audio buffer: {}", e);
+                        }
+                    }
+                }
+                if let Ok(mut tap) = self.audio_tap.lock() {
+                    if let Some(tap) = tap.as_mut() {
+                        tap.push_pcm(&pcm);
+                    }
+                }
+                forward_pcm(&pcm, source, &self.wav_sidecar, &self.audio_buffer_listener, &self.replay);
             }
+            return;
         }
         
         if let Some(ref encoder) = self.audio_encoder {
             self.process_audio_sample_buffer(sample_buffer, encoder);
         }
+        
+        if let Ok(mut tap) = self.audio_tap.lock() {
+            if let Some(tap) = tap.as_mut() {
+                tap.push_sample_buffer(sample_buffer);
+            }
+        }
+        
+        if self.has_pcm_consumers() {
+            if let Some(pcm) = extract_pcm(sample_buffer) {
+                forward_pcm(&pcm, source, &self.wav_sidecar, &self.audio_buffer_listener, &self.replay);
+            }
+        }
+    }
+    
+    /// Muted microphone buffers become silence of the same length so the audio timeline stays continuous
+    fn handle_muted_microphone_buffer(&self, sample_buffer: &CMSampleBuffer) {
+        self.secure_input_protection();
+        let offset = match self.admit_audio(sample_buffer) {
+            Some(offset) => offset,
+            None => return,
+        };
+        
+        let mut pcm = match extract_pcm(sample_buffer) {
+            Some(pcm) => pcm,
+            None => return,
+        };
+        pcm.presentation_time -= offset;
+        for channel in pcm.channels.iter_mut() {
+            channel.fill(0.0);
+        }
+        
+        if let Ok(mut count) = self.audio_frame_count.lock() {
+            *count += 1;
+        }
+        
+        if let Some(encoder) = &self.audio_encoder {
+            if let Ok(mut encoder) = encoder.lock() {
+                if let Err(e) = encoder.encode_pcm_buffer(&pcm) {
+                    log::error!("Failed to encode muted microphone audio: {}", e);
+                }
+            }
+        }
+        
+        if let Ok(mut tap) = self.audio_tap.lock() {
+            if let Some(tap) = tap.as_mut() {
+                tap.push_pcm(&pcm);
+            }
+        }
+        
+        forward_pcm(&pcm, SidecarSource::Microphone, &self.wav_sidecar, &self.audio_buffer_listener, &self.replay);
+    }
+    
+    /// Audio entry point for sources outside ScreenCaptureKit (e.g. the CoreAudio process tap).
+    /// Feeds the same encoder and live tap as stream audio.
+    pub fn pcm_handler(&self) -> PcmHandler {
+        let audio_frame_count = self.audio_frame_count.clone();
+        let audio_encoder = self.audio_encoder.clone();
+        let audio_tap = self.audio_tap.clone();
+        let secure_input = self.secure_input.clone();
+        let wav_sidecar = self.wav_sidecar.clone();
+        let audio_buffer_listener = self.audio_buffer_listener.clone();
+        let pause = self.pause.clone();
+        let replay = self.replay.clone();
+        
+        Arc::new(move |pcm: &PcmBuffer| {
+            let held = match secure_input.lock() {
+                Ok(mut guard) => match guard.as_mut() {
+                    Some(guard) => guard.action() == SecureInputAction::Pause && guard.poll(),
+                    None => false,
+                },
+                Err(_) => false,
+            };
+            
+            let duration = pcm.frame_count() as f64 / pcm.sample_rate.max(1.0);
+            let offset = match pause.lock() {
+                Ok(mut pause) => {
+                    pause.set_secure_input_held(held);
+                    match pause.audio_offset(pcm.presentation_time, duration) {
+                        Some(offset) => offset,
+                        None => return,
+                    }
+                }
+                Err(_) => 0.0,
+            };
+            let shifted;
+            let pcm = if offset > 0.0 {
+                shifted = PcmBuffer { presentation_time: pcm.presentation_time - offset, ..pcm.clone() };
+                &shifted
+            } else {
+                pcm
+            };
+            
+            if let Ok(mut count) = audio_frame_count.lock() {
+                *count += 1;
+            }
+            
+            if let Some(encoder) = &audio_encoder {
+                if let Ok(mut encoder) = encoder.lock() {
+                    if let Err(e) = encoder.encode_pcm_buffer(pcm) {
+                        log::error!("Failed to encode tapped audio: {}", e);
+                    }
+                }
+            }
+            
+            if let Ok(mut tap) = audio_tap.lock() {
+                if let Some(tap) = tap.as_mut() {
+                    tap.push_pcm(pcm);
+                }
+            }
+            
+            forward_pcm(pcm, SidecarSource::System, &wav_sidecar, &audio_buffer_listener, &replay);
+        })
     }
     
     /// Validate video frame data without encoding
@@ -131,7 +840,7 @@ impl RealStreamDelegate {
             // Get CVPixelBuffer from CMSampleBuffer
             let image_buffer: *mut CVImageBuffer = msg_send![sample_buffer, imageBuffer];
             if image_buffer.is_null() {
-                println!("⚠️ No image buffer in video sample");
+                log::warn!("No image buffer in video sample");
                 return;
             }
             
@@ -148,7 +857,7 @@ impl RealStreamDelegate {
             // Log frame details (only occasionally to avoid spam)
             if let Ok(count) = self.frame_count.lock() {
                 if *count % 60 == 0 { // Log every 60 frames (2 seconds at 30fps)
-                    println!("🎞️ Frame validation: {}x{}, format: 0x{:x}, time: {}/{}",
+                    log::debug!("Frame validation: {}x{}, format: 0x{:x}, time: {}/{}",
                         width, height, pixel_format, 
                         presentation_time.value, presentation_time.timescale);
                 }
@@ -156,50 +865,146 @@ impl RealStreamDelegate {
         }
     }
     
-    fn process_video_sample_buffer(&self, sample_buffer: &CMSampleBuffer, encoder: &Arc<Mutex<VideoEncoder>>) {
+    fn process_video_sample_buffer(&self, sample_buffer: &CMSampleBuffer, blank: bool) {
         unsafe {
             // Get CVPixelBuffer from CMSampleBuffer
             let image_buffer: *mut CVImageBuffer = msg_send![sample_buffer, imageBuffer];
             if image_buffer.is_null() {
-                println!("⚠️ No image buffer in video sample");
+                log::warn!("No image buffer in video sample");
                 return;
             }
             
             let pixel_buffer = image_buffer as *mut CVPixelBuffer;
             
-            // Get presentation time
-            let presentation_time: CMTime = msg_send![sample_buffer, presentationTimeStamp];
+            // Draw click ripples, the webcam and then the custom cursor on top before the frame reaches the encoder
+            if blank {
+                secure_input::blank_frame(pixel_buffer);
+            } else {
+                if let Ok(mut highlighter) = self.click_highlighter.lock() {
+                    if let Some(highlighter) = highlighter.as_mut() {
+                        highlighter.composite(pixel_buffer);
+                    }
+                }
+                if let Ok(mut camera) = self.camera_overlay.lock() {
+                    if let Some(camera) = camera.as_mut() {
+                        camera.composite(pixel_buffer);
+                    }
+                }
+                if let Ok(mut overlay) = self.cursor_overlay.lock() {
+                    if let Some(overlay) = overlay.as_mut() {
+                        overlay.composite(pixel_buffer);
+                    }
+                }
+            }
             
-            // Encode the frame
-            if let Ok(mut video_encoder) = encoder.lock() {
-                if let Err(e) = video_encoder.encode_frame(pixel_buffer, presentation_time) {
-                    println!("❌ Failed to encode video frame: {}", e);
-                } else {
-                    // Success - frame encoded
-                    if let Ok(count) = self.frame_count.lock() {
-                        if *count % 150 == 0 { // Log every 150 frames (5 seconds at 30fps)
-                            println!("✅ Successfully encoded {} video frames", *count);
-                        }
+            // From here on a zoomed recording only sees the crop around the cursor
+            let zoomed = match self.zoom.lock() {
+                Ok(mut zoom) => zoom.as_mut().and_then(|zoom| zoom.render(pixel_buffer)),
+                Err(_) => None,
+            };
+            self.deliver_video_frame(sample_buffer, zoomed.unwrap_or(pixel_buffer));
+            if let Some(zoomed) = zoomed {
+                ZoomFollow::release(zoomed);
+            }
+        }
+    }
+    
+    /// Hand a finished frame to the listeners, the replay buffer, the canvas and the encoder
+    unsafe fn deliver_video_frame(&self, sample_buffer: &CMSampleBuffer, pixel_buffer: *mut CVPixelBuffer) {
+        // Get presentation time
+        let presentation_time: CMTime = msg_send![sample_buffer, presentationTimeStamp];
+        
+        let listener = self.video_frame_listener.lock().ok().and_then(|listener| listener.clone());
+        if let Some(listener) = listener {
+            let timestamp = presentation_time.value as f64 / presentation_time.timescale.max(1) as f64;
+            if let Some(frame) = frames::copy_frame(pixel_buffer, listener.format, timestamp) {
+                (listener.handler)(frame);
+            }
+        }
+        
+        if let Ok(mut replay) = self.replay.lock() {
+            if let Some(replay) = replay.as_mut() {
+                replay.push_frame(pixel_buffer, presentation_time);
+            }
+        }
+        
+        // In a composite recording every stream draws its slot and only the primary encodes the canvas
+        let canvas = self.canvas.lock().ok().and_then(|canvas| canvas.clone());
+        if let Some(target) = &canvas {
+            target.draw(pixel_buffer);
+            if !target.is_primary() {
+                return;
+            }
+        }
+        
+        // Encode the frame
+        let encoder = match &self.video_encoder {
+            Some(encoder) => encoder,
+            None => return,
+        };
+        let rendered = match &canvas {
+            Some(target) => match target.render() {
+                Some(rendered) => Some(rendered),
+                None => return,
+            },
+            None => None,
+        };
+        let frame = rendered.unwrap_or(pixel_buffer);
+        // Time spent paused is cut out, for encoders that go by capture time
+        let paused = self.paused_duration().as_secs_f64();
+        let encode_time = CMTime {
+            value: presentation_time.value - (paused * presentation_time.timescale as f64) as i64,
+            ..presentation_time
+        };
+        if let Ok(mut video_encoder) = encoder.lock() {
+            if let Err(e) = video_encoder.encode_frame(frame, encode_time) {
+                log::error!("Failed to encode video frame: {}", e);
+            } else {
+                // Success - frame encoded
+                if let Ok(mut track) = self.cursor_track.lock() {
+                    if let Some(track) = track.as_mut() {
+                        track.record(
+                            CVPixelBufferGetWidth(frame) as u32,
+                            CVPixelBufferGetHeight(frame) as u32,
+                            encode_time.value as f64 / encode_time.timescale.max(1) as f64,
+                        );
+                    }
+                }
+                if let Ok(count) = self.frame_count.lock() {
+                    if *count % 150 == 0 { // Log every 150 frames (5 seconds at 30fps)
+                        log::info!("Successfully encoded {} video frames", *count);
                     }
                 }
             }
         }
+        if let Some(rendered) = rendered {
+            CanvasTarget::release(rendered);
+        }
     }
     
     fn process_audio_sample_buffer(&self, sample_buffer: &CMSampleBuffer, encoder: &Arc<Mutex<AudioEncoder>>) {
         // Encode the audio buffer directly
         if let Ok(mut audio_encoder) = encoder.lock() {
             if let Err(e) = audio_encoder.encode_audio_buffer(sample_buffer) {
-                println!("❌ Failed to encode audio buffer: {}", e);
+                log::error!("Failed to encode audio buffer: {}", e);
             }
         }
     }
     
     pub fn handle_stream_stopped(&self, error: Option<&NSError>) {
+        self.finalize(error, &mut |_| {});
+    }
+    
+    /// Finalize every output, calling `progress` as each phase begins
+    pub fn finalize(&self, error: Option<&NSError>, progress: &mut dyn FnMut(FinalizePhase)) {
         if let Some(error) = error {
-            println!("⚠️ Stream stopped with error: {:?}", error);
+            log::warn!("Stream stopped with error: {:?}", error);
+            self.journal_failure(format!("Stream stopped: {}", error.localizedDescription()));
+            if let Ok(mut stream_error) = self.stream_error.lock() {
+                *stream_error = Some(error.localizedDescription().to_string());
+            }
         } else {
-            println!("✅ Stream stopped successfully");
+            log::info!("Stream stopped successfully");
         }
         
         // Set recording flag to false
@@ -207,25 +1012,73 @@ impl RealStreamDelegate {
             *is_recording = false;
         }
         
+        // Hand the tail of the live audio to the tap before encoders finalize
+        if let Ok(mut tap) = self.audio_tap.lock() {
+            if let Some(tap) = tap.as_mut() {
+                tap.flush();
+                log::info!("Audio tap delivered {:.1}s of 16 kHz audio", tap.emitted_seconds());
+            }
+        }
+        
         // Finalize encoders
+        progress(FinalizePhase::Video);
         if let Some(ref video_encoder) = self.video_encoder {
             if let Ok(mut encoder) = video_encoder.lock() {
                 match encoder.finalize_encoding() {
-                    Ok(path) => println!("✅ Video encoding finalized: {}", path),
-                    Err(e) => println!("❌ Video encoding finalization failed: {}", e),
+                    Ok(path) => log::info!("Video encoding finalized: {}", path),
+                    Err(e) => {
+                        log::error!("Video encoding finalization failed: {}", e);
+                        self.journal_failure(format!("Video encoding finalization failed: {}", e.reason));
+                    }
                 }
             }
         }
         
+        progress(FinalizePhase::Audio);
         if let Some(ref audio_encoder) = self.audio_encoder {
             if let Ok(mut encoder) = audio_encoder.lock() {
                 match encoder.finalize_encoding() {
-                    Ok(path) => println!("✅ Audio encoding finalized: {}", path),
-                    Err(e) => println!("❌ Audio encoding finalization failed: {}", e),
+                    Ok(path) => log::info!("Audio encoding finalized: {}", path),
+                    Err(e) => {
+                        log::error!("Audio encoding finalization failed: {}", e);
+                        self.journal_failure(format!("Audio encoding finalization failed: {}", e.reason));
+                    }
+                }
+            }
+        }
+        
+        progress(FinalizePhase::Sidecars);
+        if let Ok(mut sidecar) = self.wav_sidecar.lock() {
+            if let Some(mut sidecar) = sidecar.take() {
+                if let Err(e) = sidecar.finalize() {
+                    log::error!("WAV sidecar finalization failed: {}", e);
                 }
             }
         }
         
+        if let Ok(markers) = self.markers.lock() {
+            if !markers.is_empty() {
+                if let Err(e) = markers::write_sidecar(&markers::path_for(&self.output_path), &markers) {
+                    log::error!("{}", e);
+                }
+            }
+        }
+        
+        if let Ok(mut track) = self.cursor_track.lock() {
+            if let Some(mut track) = track.take() {
+                if let Err(e) = track.finalize() {
+                    log::error!("Cursor track finalization failed: {}", e);
+                }
+            }
+        }
+        
+        // Whatever was not saved with saveReplay is gone with the session
+        if let Ok(mut replay) = self.replay.lock() {
+            if let Some(mut replay) = replay.take() {
+                replay.discard();
+            }
+        }
+        
         // Print final statistics
         self.print_final_stats();
     }
@@ -235,15 +1088,15 @@ impl RealStreamDelegate {
         let audio_samples = self.audio_frame_count.lock().map(|g| *g).unwrap_or(0);
         let final_fps = self.fps_counter.lock().map(|g| *g).unwrap_or(0.0);
         
-        println!("📊 Final Recording Statistics:");
-        println!("   Video Frames: {}", video_frames);
-        println!("   Audio Samples: {}", audio_samples);
-        println!("   Final FPS: {:.1}", final_fps);
-        println!("   Output Path: {}", self.output_path);
+        log::debug!("Final Recording Statistics:");
+        log::debug!("   Video Frames: {}", video_frames);
+        log::debug!("   Audio Samples: {}", audio_samples);
+        log::debug!("   Final FPS: {:.1}", final_fps);
+        log::debug!("   Output Path: {}", self.output_path);
         
         if video_frames > 0 {
             let duration_seconds = video_frames as f64 / 30.0; // Assuming 30fps
-            println!("   Estimated Duration: {:.1}s", duration_seconds);
+            log::debug!("   Estimated Duration: {:.1}s", duration_seconds);
         }
     }
     
@@ -251,31 +1104,67 @@ impl RealStreamDelegate {
         self.output_path.clone()
     }
     
+    pub fn video_encoder_settings(&self) -> Option<VideoEncoderSettings> {
+        self.video_encoder.as_ref().and_then(|e| e.lock().ok().map(|e| e.settings()))
+    }
+    
+    pub fn dropped_frame_count(&self) -> u64 {
+        self.video_encoder.as_ref().and_then(|e| e.lock().ok().map(|e| e.dropped_frames())).unwrap_or(0)
+    }
+    
+    pub fn has_audio_encoder(&self) -> bool {
+        self.audio_encoder.is_some()
+    }
+    
+    /// Bytes the encoders have written so far. The MP4 index is only added when finalizing, so
+    /// the finished files come out slightly larger.
+    pub fn output_size_bytes(&self) -> u64 {
+        let video = self.video_encoder.as_ref().and_then(|e| e.lock().ok().map(|e| e.output_url().to_string()));
+        let audio = self.audio_encoder.as_ref().and_then(|e| e.lock().ok().map(|e| e.output_url().to_string()));
+        video.into_iter()
+            .chain(audio)
+            .filter_map(|path| std::fs::metadata(path).ok())
+            .map(|metadata| metadata.len())
+            .sum()
+    }
+    
     pub fn get_frame_count(&self) -> u64 {
         self.frame_count.lock().map(|guard| *guard).unwrap_or_else(|_| {
-            println!("⚠️ Frame count mutex was poisoned");
+            log::warn!("Frame count mutex was poisoned");
             0
         })
     }
     
     pub fn get_audio_frame_count(&self) -> u64 {
         self.audio_frame_count.lock().map(|guard| *guard).unwrap_or_else(|_| {
-            println!("⚠️ Audio frame count mutex was poisoned");
+            log::warn!("Audio frame count mutex was poisoned");
             0
         })
     }
     
     pub fn get_current_fps(&self) -> f64 {
         self.fps_counter.lock().map(|guard| *guard).unwrap_or_else(|_| {
-            println!("⚠️ FPS counter mutex was poisoned");
+            log::warn!("FPS counter mutex was poisoned");
             0.0
         })
     }
     
+    /// Forward session tags to the video container; false when they arrived too late for it
+    pub fn set_tags(&self, tags: &[String]) -> bool {
+        match &self.video_encoder {
+            Some(encoder) => encoder.lock().map(|mut e| e.set_metadata_tags(tags)).unwrap_or(false),
+            None => false,
+        }
+    }
+    
     /// Check if the delegate is actively recording
     pub fn is_recording(&self) -> bool {
         self.is_recording.lock().map(|guard| *guard).unwrap_or(false)
     }
+    
+    pub fn stream_error(&self) -> Option<String> {
+        self.stream_error.lock().ok().and_then(|error| error.clone())
+    }
 }
 
 impl SCStreamDelegate for RealStreamDelegate {
@@ -289,8 +1178,14 @@ impl SCStreamDelegate for RealStreamDelegate {
             SCStreamOutputType::Screen => {
                 self.handle_video_sample_buffer(sample_buffer);
             }
-            SCStreamOutputType::Audio | SCStreamOutputType::Microphone => {
-                self.handle_audio_sample_buffer(sample_buffer);
+            SCStreamOutputType::Microphone if self.is_microphone_muted() => {
+                self.handle_muted_microphone_buffer(sample_buffer);
+            }
+            SCStreamOutputType::Audio => {
+                self.handle_audio_sample_buffer(sample_buffer, SidecarSource::System);
+            }
+            SCStreamOutputType::Microphone => {
+                self.handle_audio_sample_buffer(sample_buffer, SidecarSource::Microphone);
             }
         }
     }
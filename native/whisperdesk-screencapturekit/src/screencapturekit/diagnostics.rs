@@ -0,0 +1,288 @@
+// Audio self-test - record a couple of seconds from the mic and system audio and measure levels
+
+use block2::RcBlock;
+use napi::bindgen_prelude::*;
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use super::audio::{AudioManager, AudioObjectGetPropertyData, AudioObjectPropertyAddress, K_AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN};
+use super::audio_tap::{pcm_from_buffer_list, AudioStreamBasicDescription, PcmBuffer};
+use super::process_tap::{PcmHandler, ProcessTap};
+use super::errors::{capture_error, CaptureErrorCode};
+
+pub const DEFAULT_DURATION_MS: u32 = 2000;
+
+const K_AUDIO_DEVICE_PROPERTY_STREAM_FORMAT: u32 = 0x73666d74; // 'sfmt'
+const K_AUDIO_OBJECT_PROPERTY_SCOPE_INPUT: u32 = 0x696e7074; // 'inpt'
+
+// Levels are reported in dBFS, floored here for digital silence
+const SILENCE_FLOOR_DB: f64 = -100.0;
+// A peak above this counts as a real signal rather than noise floor or dither
+const SIGNAL_THRESHOLD_DB: f64 = -60.0;
+
+type IoBlock = dyn Fn(*const c_void, *const c_void, *const c_void, *mut c_void, *const c_void);
+
+extern "C" {
+    fn AudioDeviceCreateIOProcIDWithBlock(
+        proc_id_out: *mut *mut c_void,
+        device_id: u32,
+        dispatch_queue: *mut c_void,
+        block: &block2::Block<IoBlock>,
+    ) -> i32;
+    fn AudioDeviceDestroyIOProcID(device_id: u32, proc_id: *mut c_void) -> i32;
+    fn AudioDeviceStart(device_id: u32, proc_id: *mut c_void) -> i32;
+    fn AudioDeviceStop(device_id: u32, proc_id: *mut c_void) -> i32;
+}
+
+/// Peak and RMS over everything pushed into it
+#[derive(Debug, Default)]
+pub struct LevelMeter {
+    frames: u64,
+    samples: u64,
+    peak: f32,
+    sum_squares: f64,
+}
+
+impl LevelMeter {
+    pub fn push(&mut self, pcm: &PcmBuffer) {
+        self.frames += pcm.frame_count() as u64;
+        for sample in pcm.channels.iter().flatten() {
+            self.peak = self.peak.max(sample.abs());
+            self.sum_squares += (*sample as f64) * (*sample as f64);
+            self.samples += 1;
+        }
+    }
+
+    pub fn frames(&self) -> u64 {
+        self.frames
+    }
+
+    pub fn peak_db(&self) -> f64 {
+        to_db(self.peak as f64)
+    }
+
+    pub fn rms_db(&self) -> f64 {
+        if self.samples == 0 {
+            return SILENCE_FLOOR_DB;
+        }
+        to_db((self.sum_squares / self.samples as f64).sqrt())
+    }
+
+    pub fn has_signal(&self) -> bool {
+        self.peak_db() > SIGNAL_THRESHOLD_DB
+    }
+}
+
+fn to_db(amplitude: f64) -> f64 {
+    if amplitude <= 0.0 {
+        return SILENCE_FLOOR_DB;
+    }
+    (20.0 * amplitude.log10()).max(SILENCE_FLOOR_DB)
+}
+
+/// Result for one audio source
+#[derive(Debug, Default)]
+pub struct SourceDiagnostics {
+    pub device_found: bool,
+    pub device_uid: Option<String>,
+    pub device_name: Option<String>,
+    pub permission_granted: bool,
+    /// Audio actually arrived during the test
+    pub captured: bool,
+    pub meter: LevelMeter,
+    /// What went wrong, or a hint when the capture worked but stayed silent
+    pub error: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct AudioDiagnostics {
+    pub microphone: SourceDiagnostics,
+    pub system_audio: SourceDiagnostics,
+    pub duration_ms: u32,
+}
+
+/// IOProc straight on an input device, bypassing ScreenCaptureKit. Buffers are timed by the
+/// frames delivered so far, in seconds from the start.
+pub(crate) struct InputCapture {
+    device_id: u32,
+    io_proc: *mut c_void,
+    running: bool,
+    _io_block: RcBlock<IoBlock>,
+}
+
+impl InputCapture {
+    pub(crate) unsafe fn start(device_id: u32, handler: PcmHandler) -> Result<Self> {
+        let address = AudioObjectPropertyAddress {
+            selector: K_AUDIO_DEVICE_PROPERTY_STREAM_FORMAT,
+            scope: K_AUDIO_OBJECT_PROPERTY_SCOPE_INPUT,
+            element: K_AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+        };
+        let mut asbd: AudioStreamBasicDescription = std::mem::zeroed();
+        let mut data_size = std::mem::size_of::<AudioStreamBasicDescription>() as u32;
+        let status = AudioObjectGetPropertyData(
+            device_id,
+            &address,
+            0,
+            ptr::null(),
+            &mut data_size,
+            &mut asbd as *mut AudioStreamBasicDescription as *mut c_void,
+        );
+        if status != 0 {
+            return Err(capture_error(CaptureErrorCode::Unknown, format!("Failed to read input format (status {})", status)));
+        }
+
+        let delivered = AtomicU64::new(0);
+        let block: RcBlock<IoBlock> = RcBlock::new(
+            move |_now: *const c_void, input: *const c_void, _input_time: *const c_void, _output: *mut c_void, _output_time: *const c_void| {
+                let start = delivered.load(Ordering::Relaxed) as f64 / asbd.sample_rate.max(1.0);
+                if let Some(pcm) = pcm_from_buffer_list(input, &asbd, start) {
+                    delivered.fetch_add(pcm.frame_count() as u64, Ordering::Relaxed);
+                    handler(&pcm);
+                }
+            },
+        );
+
+        let mut capture = Self {
+            device_id,
+            io_proc: ptr::null_mut(),
+            running: false,
+            _io_block: block,
+        };
+        let status = AudioDeviceCreateIOProcIDWithBlock(&mut capture.io_proc, device_id, ptr::null_mut(), &capture._io_block);
+        if status != 0 || capture.io_proc.is_null() {
+            return Err(capture_error(CaptureErrorCode::Unknown, format!("Failed to create input IOProc (status {})", status)));
+        }
+        let status = AudioDeviceStart(device_id, capture.io_proc);
+        if status != 0 {
+            return Err(capture_error(CaptureErrorCode::Unknown, format!("Failed to start input device (status {})", status)));
+        }
+        capture.running = true;
+        Ok(capture)
+    }
+}
+
+impl Drop for InputCapture {
+    fn drop(&mut self) {
+        unsafe {
+            if self.running {
+                AudioDeviceStop(self.device_id, self.io_proc);
+            }
+            if !self.io_proc.is_null() {
+                AudioDeviceDestroyIOProcID(self.device_id, self.io_proc);
+            }
+        }
+    }
+}
+
+fn device_name(uid: &str) -> Option<String> {
+    AudioManager::get_available_audio_devices()
+        .ok()?
+        .into_iter()
+        .find(|device| device.id == uid)
+        .map(|device| device.name)
+}
+
+/// Capture `duration_ms` from the microphone (the given UID or the default input) and from system
+/// audio at the same time, and report what arrived
+pub fn run_audio_diagnostics(microphone_uid: Option<&str>, duration_ms: u32) -> AudioDiagnostics {
+    log::info!("Running audio diagnostics for {} ms", duration_ms);
+
+    let mic_meter = Arc::new(Mutex::new(LevelMeter::default()));
+    let system_meter = Arc::new(Mutex::new(LevelMeter::default()));
+    let mut microphone = SourceDiagnostics::default();
+    let mut system_audio = SourceDiagnostics::default();
+
+    // Microphone: find the device, check the permission, then open it directly
+    let mic_uid = microphone_uid.map(|uid| uid.to_string()).or_else(AudioManager::default_input_device_uid);
+    let authorization = super::permissions::microphone_authorization();
+    microphone.permission_granted = authorization.is_granted();
+    let mic_capture = match &mic_uid {
+        None => {
+            microphone.error = Some("No input device is available".to_string());
+            None
+        }
+        Some(uid) => match AudioManager::resolve_input_device(uid) {
+            Err(e) => {
+                microphone.error = Some(Error::from(e).reason);
+                None
+            }
+            Ok(device) => {
+                microphone.device_found = true;
+                microphone.device_uid = Some(uid.clone());
+                microphone.device_name = device_name(uid);
+                if !microphone.permission_granted {
+                    // Opening the device would prompt while undetermined, and records silence once denied
+                    microphone.error = Some(format!("Microphone permission is {}", authorization.as_ref()));
+                    None
+                } else {
+                    let meter = mic_meter.clone();
+                    let handler: PcmHandler = Arc::new(move |pcm: &PcmBuffer| {
+                        if let Ok(mut meter) = meter.lock() {
+                            meter.push(pcm);
+                        }
+                    });
+                    match unsafe { InputCapture::start(device.device_id, handler) } {
+                        Ok(capture) => Some(capture),
+                        Err(e) => {
+                            microphone.error = Some(e.reason);
+                            None
+                        }
+                    }
+                }
+            }
+        },
+    };
+
+    // System audio: a global process tap sees everything headed for the output device
+    system_audio.device_uid = AudioManager::default_output_device_uid();
+    system_audio.device_found = system_audio.device_uid.is_some();
+    system_audio.device_name = system_audio.device_uid.as_deref().and_then(device_name);
+    let system_tap = if !system_audio.device_found {
+        system_audio.error = Some("No output device is available".to_string());
+        None
+    } else if !ProcessTap::is_supported() {
+        system_audio.error = Some("System audio self-test requires macOS 14.2 or later".to_string());
+        None
+    } else {
+        let meter = system_meter.clone();
+        match ProcessTap::start(&[], Arc::new(move |pcm: &PcmBuffer| {
+            if let Ok(mut meter) = meter.lock() {
+                meter.push(pcm);
+            }
+        })) {
+            Ok(tap) => {
+                system_audio.permission_granted = true;
+                Some(tap)
+            }
+            Err(e) => {
+                system_audio.error = Some(e.reason);
+                None
+            }
+        }
+    };
+
+    std::thread::sleep(Duration::from_millis(duration_ms as u64));
+    drop(mic_capture);
+    drop(system_tap);
+
+    for (source, meter, silent_hint) in [
+        (&mut microphone, mic_meter, "Microphone delivered only silence - check the input volume and mute switch"),
+        (&mut system_audio, system_meter, "System audio was silent - play something during the test"),
+    ] {
+        source.meter = meter.lock().map(|mut meter| std::mem::take(&mut *meter)).unwrap_or_default();
+        source.captured = source.meter.frames() > 0;
+        if source.captured && !source.meter.has_signal() && source.error.is_none() {
+            source.error = Some(silent_hint.to_string());
+        }
+    }
+
+    log::info!(
+        "Microphone peak {:.1} dBFS, system audio peak {:.1} dBFS",
+        microphone.meter.peak_db(),
+        system_audio.meter.peak_db()
+    );
+    AudioDiagnostics { microphone, system_audio, duration_ms }
+}
@@ -0,0 +1,62 @@
+// Disk space watch - stop a recording before the output volume fills up and AVAssetWriter fails
+
+use objc2::rc::{autoreleasepool, Retained};
+use objc2::runtime::AnyObject;
+use objc2::{class, msg_send};
+use objc2_foundation::{NSDictionary, NSNumber, NSString};
+use std::path::Path;
+use std::thread;
+use std::time::Duration;
+
+/// Free space kept untouched by default; finalizing an MP4 still needs room for the moov atom
+pub const DEFAULT_RESERVE_MB: u32 = 500;
+
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Bytes available to this user on the volume holding `path`
+pub fn available_bytes(path: &str) -> Option<u64> {
+    // The recording may not exist yet, its directory does
+    let path = Path::new(path);
+    let directory = if path.is_dir() { path } else { path.parent().filter(|p| !p.as_os_str().is_empty()).unwrap_or(Path::new(".")) };
+
+    autoreleasepool(|_| unsafe {
+        let manager: Option<Retained<AnyObject>> = msg_send![class!(NSFileManager), defaultManager];
+        let manager = manager?;
+        let directory = NSString::from_str(&directory.to_string_lossy());
+        let attributes: Option<Retained<NSDictionary>> =
+            msg_send![&*manager, attributesOfFileSystemForPath: &*directory, error: std::ptr::null_mut::<*mut AnyObject>()];
+        let attributes = attributes?;
+        let key = NSString::from_str("NSFileSystemFreeSize");
+        let free: Option<Retained<NSNumber>> = msg_send![&*attributes, objectForKey: &*key];
+        let free = free?;
+        let bytes: u64 = msg_send![&*free, unsignedLongLongValue];
+        Some(bytes)
+    })
+}
+
+/// Poll the volume holding `path` every couple of seconds. Calls `on_low` once with the free byte
+/// count when it drops below `reserve_bytes`; gives up as soon as `keep_watching` returns false.
+pub fn watch(
+    path: &str,
+    reserve_bytes: u64,
+    keep_watching: Box<dyn Fn() -> bool + Send>,
+    on_low: Box<dyn FnOnce(u64) + Send>,
+) {
+    let path = path.to_string();
+    log::info!("Watching free space for {} (reserve {} MB)", path, reserve_bytes / (1024 * 1024));
+
+    thread::spawn(move || loop {
+        thread::sleep(POLL_INTERVAL);
+        if !keep_watching() {
+            break;
+        }
+        // A volume that can't be queried right now (network share hiccup) isn't treated as full
+        if let Some(free) = available_bytes(&path) {
+            if free < reserve_bytes {
+                log::warn!("Only {} MB left on the output volume", free / (1024 * 1024));
+                on_low(free);
+                break;
+            }
+        }
+    });
+}
@@ -0,0 +1,90 @@
+// Display reconfiguration - hot-plug, resolution and arrangement changes reported by Core Graphics
+
+use std::ffi::c_void;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use super::content::{DisplayInfo, ShareableContent};
+
+// Set on the callback sent before a reconfiguration starts; only the ones after it matter
+const K_CG_DISPLAY_BEGIN_CONFIGURATION_FLAG: u32 = 1 << 0;
+
+// One reconfiguration fires a callback per display; wait this long for the burst to finish
+const SETTLE_TIME: Duration = Duration::from_millis(300);
+
+type ReconfigurationCallback = extern "C" fn(display: u32, flags: u32, user_info: *mut c_void);
+
+extern "C" {
+    fn CGDisplayRegisterReconfigurationCallback(callback: ReconfigurationCallback, user_info: *mut c_void) -> i32;
+    fn CGDisplayRemoveReconfigurationCallback(callback: ReconfigurationCallback, user_info: *mut c_void) -> i32;
+}
+
+pub type DisplayChangeHandler = Arc<dyn Fn(Vec<DisplayInfo>) + Send + Sync>;
+
+struct ListenerState {
+    handler: DisplayChangeHandler,
+    pending: Arc<Mutex<bool>>,
+}
+
+extern "C" fn on_reconfiguration(_display: u32, flags: u32, user_info: *mut c_void) {
+    if flags & K_CG_DISPLAY_BEGIN_CONFIGURATION_FLAG != 0 || user_info.is_null() {
+        return;
+    }
+    let state = unsafe { &*(user_info as *const ListenerState) };
+
+    let mut pending = match state.pending.lock() {
+        Ok(pending) => pending,
+        Err(_) => return,
+    };
+    if *pending {
+        return;
+    }
+    *pending = true;
+    drop(pending);
+
+    let handler = state.handler.clone();
+    let pending = state.pending.clone();
+    thread::spawn(move || {
+        thread::sleep(SETTLE_TIME);
+        if let Ok(mut pending) = pending.lock() {
+            *pending = false;
+        }
+        let displays = ShareableContent::current_displays();
+        log::info!("Display configuration changed - {} displays", displays.len());
+        handler(displays);
+    });
+}
+
+/// Calls the handler with the new display list after monitors connect or disconnect, or a
+/// resolution or arrangement changes. Core Graphics delivers the callbacks through the run loop
+/// of the registering thread, so register from a thread that runs one (Electron's main thread does).
+pub struct DisplayReconfigurationListener {
+    state: *mut ListenerState,
+}
+
+impl DisplayReconfigurationListener {
+    pub fn start(handler: DisplayChangeHandler) -> Option<Self> {
+        let state = Box::into_raw(Box::new(ListenerState {
+            handler,
+            pending: Arc::new(Mutex::new(false)),
+        }));
+        let status = unsafe { CGDisplayRegisterReconfigurationCallback(on_reconfiguration, state as *mut c_void) };
+        if status != 0 {
+            log::warn!("Failed to register for display changes (CGError {})", status);
+            drop(unsafe { Box::from_raw(state) });
+            return None;
+        }
+        Some(Self { state })
+    }
+}
+
+impl Drop for DisplayReconfigurationListener {
+    fn drop(&mut self) {
+        unsafe {
+            CGDisplayRemoveReconfigurationCallback(on_reconfiguration, self.state as *mut c_void);
+            // A settle thread may still hold clones of the handler, but never the state itself
+            drop(Box::from_raw(self.state));
+        }
+    }
+}
@@ -1,17 +1,28 @@
 use std::ptr;
+use objc2::rc::Retained;
 use objc2::runtime::AnyObject;
 use objc2::{msg_send, class};
-use objc2_foundation::{NSString, NSURL, NSError, NSDictionary, NSNumber};
+use objc2_foundation::{NSArray, NSString, NSURL, NSError, NSDictionary, NSNumber};
 use objc2_av_foundation::{AVAssetWriter, AVAssetWriterInput, AVAssetWriterInputPixelBufferAdaptor};
 use objc2_core_video::{CVPixelBuffer, kCVPixelFormatType_32BGRA};
-use objc2_core_media::{CMTime, CMSampleBuffer, kCMTimeZero};
-use napi::{Result, Status, Error};
+use objc2_core_media::{CMTime, CMTimeRange, CMSampleBuffer, kCMTimeZero};
+use napi::Result;
+use std::ffi::c_void;
+
+use super::audio::convert::{f32_to_i16, interleave, PcmConverter};
+use super::audio_tap::{extract_pcm, stream_description, AudioStreamBasicDescription, PcmBuffer};
+use super::errors::{capture_error, CaptureErrorCode};
+use super::formats::VideoCodec;
 
 // AVFoundation constants
 pub const AVFileTypeQuickTimeMovie: &str = "com.apple.quicktime-movie";
 pub const AVFileTypeMPEG4: &str = "public.mpeg-4";
+pub const AVFileTypeAppleM4A: &str = "com.apple.m4a-audio";
 pub const AVMediaTypeVideo: &str = "vide";
 pub const AVMediaTypeAudio: &str = "soun";
+pub const AVMediaTypeMetadata: &str = "meta";
+pub const AVTrackAssociationTypeChapterList: &str = "chap";
+pub const AVMetadataCommonIdentifierTitle: &str = "common/title";
 
 // Video codec constants
 pub const AVVideoCodecTypeH264: &str = "avc1";
@@ -20,6 +31,82 @@ pub const AVVideoCodecTypeHEVC: &str = "hvc1";
 // Audio codec constants
 pub const AVFormatIDKeyAAC: u32 = 0x61616320; // 'aac ' as u32
 
+// kAudioChannelLayoutTag_DiscreteInOrder, ORed with the channel count
+const K_AUDIO_CHANNEL_LAYOUT_TAG_DISCRETE_IN_ORDER: u32 = 147 << 16;
+
+// Linear PCM, signed integer + packed
+const K_AUDIO_FORMAT_LINEAR_PCM: u32 = 0x6C70636D; // 'lpcm'
+const K_LINEAR_PCM_FORMAT_FLAGS_INT16_PACKED: u32 = (1 << 2) | (1 << 3);
+
+// Boxed timed metadata, the format of a chapter title track
+const K_CM_METADATA_FORMAT_TYPE_BOXED: u32 = 0x6D656278; // 'mebx'
+// Shortest chapter, for a marker added on the last frame
+const MIN_CHAPTER_SECONDS: f64 = 1.0 / 30.0;
+
+extern "C" {
+    static kCMMetadataFormatDescriptionMetadataSpecificationKey_Identifier: *const NSString;
+    static kCMMetadataFormatDescriptionMetadataSpecificationKey_DataType: *const NSString;
+    static kCMMetadataBaseDataType_UTF8: *const NSString;
+    fn CMMetadataFormatDescriptionCreateWithMetadataSpecifications(
+        allocator: *const c_void,
+        metadata_type: u32,
+        metadata_specifications: *const c_void,
+        format_description_out: *mut *mut c_void,
+    ) -> i32;
+    fn CMAudioFormatDescriptionCreate(
+        allocator: *const c_void,
+        asbd: *const AudioStreamBasicDescription,
+        layout_size: usize,
+        layout: *const c_void,
+        magic_cookie_size: usize,
+        magic_cookie: *const c_void,
+        extensions: *const c_void,
+        format_description_out: *mut *mut c_void,
+    ) -> i32;
+    fn CMBlockBufferCreateWithMemoryBlock(
+        structure_allocator: *const c_void,
+        memory_block: *mut c_void,
+        block_length: usize,
+        block_allocator: *const c_void,
+        custom_block_source: *const c_void,
+        offset_to_data: usize,
+        data_length: usize,
+        flags: u32,
+        block_buffer_out: *mut *mut c_void,
+    ) -> i32;
+    fn CMBlockBufferReplaceDataBytes(source: *const c_void, destination: *mut c_void, offset: usize, length: usize) -> i32;
+    fn CMAudioSampleBufferCreateReadyWithPacketDescriptions(
+        allocator: *const c_void,
+        data_buffer: *mut c_void,
+        format_description: *mut c_void,
+        sample_count: isize,
+        presentation_time: CMTime,
+        packet_descriptions: *const c_void,
+        sample_buffer_out: *mut *mut CMSampleBuffer,
+    ) -> i32;
+    fn CFRelease(cf: *const c_void);
+    fn VTCompressionSessionCreate(
+        allocator: *const c_void,
+        width: i32,
+        height: i32,
+        codec_type: u32,
+        encoder_specification: *const c_void,
+        source_image_buffer_attributes: *const c_void,
+        compressed_data_allocator: *const c_void,
+        output_callback: *const c_void,
+        output_callback_refcon: *mut c_void,
+        compression_session_out: *mut *mut c_void,
+    ) -> i32;
+    fn VTCompressionSessionPrepareToEncodeFrames(session: *mut c_void) -> i32;
+    fn VTCompressionSessionInvalidate(session: *mut c_void);
+    static kVTVideoEncoderList_CodecType: *const NSString;
+    static kVTVideoEncoderList_IsHardwareAccelerated: *const NSString;
+    fn VTCopyVideoEncoderList(options: *const c_void, list_of_video_encoders_out: *mut *const NSArray) -> i32;
+}
+
+// QuickTime metadata identifiers
+pub const AVMetadataIdentifierQuickTimeMetadataKeywords: &str = "mdta/com.apple.quicktime.keywords";
+
 pub struct VideoEncoder {
     asset_writer: *mut AVAssetWriter,
     video_input: *mut AVAssetWriterInput,
@@ -27,11 +114,36 @@ pub struct VideoEncoder {
     output_url: String,
     is_recording: bool,
     frame_count: u64,
+    dropped_frames: u64,
     start_time: Option<CMTime>,
+    metadata_tags: Vec<String>,
+    timelapse_speedup: Option<f64>,
+    last_frame_value: Option<i64>,
+    chapters_enabled: bool,
+    chapter_input: *mut AVAssetWriterInput,
+    chapter_adaptor: Option<Retained<AnyObject>>,
+    chapters: Vec<(f64, String)>,
+    settings: VideoEncoderSettings,
+}
+
+/// What a video encoder was set up with
+#[derive(Debug, Clone, Copy)]
+pub struct VideoEncoderSettings {
+    pub codec: VideoCodec,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
 }
 
+// AVAssetWriter can be fed from any thread as long as calls don't overlap; encoders live behind a mutex
+unsafe impl Send for VideoEncoder {}
+
 impl VideoEncoder {
     pub fn new(output_path: &str, width: u32, height: u32, fps: u32) -> Result<Self> {
+        Self::with_codec(output_path, width, height, fps, VideoCodec::H264)
+    }
+
+    pub fn with_codec(output_path: &str, width: u32, height: u32, fps: u32, codec: VideoCodec) -> Result<Self> {
         unsafe {
             // Create file URL
             let url_string = NSString::from_str(output_path);
@@ -48,11 +160,11 @@ impl VideoEncoder {
             ];
             
             if asset_writer.is_null() || !error.is_null() {
-                return Err(Error::new(Status::GenericFailure, "Failed to create AVAssetWriter"));
+                return Err(capture_error(CaptureErrorCode::EncoderInitFailed, "Failed to create AVAssetWriter"));
             }
             
             // Create video input settings
-            let video_settings = Self::create_video_settings(width, height, fps);
+            let video_settings = Self::create_video_settings(width, height, fps, codec);
             let media_type = NSString::from_str(AVMediaTypeVideo);
             let video_input: *mut AVAssetWriterInput = msg_send![
                 class!(AVAssetWriterInput),
@@ -76,15 +188,10 @@ impl VideoEncoder {
             if can_add {
                 let _: () = msg_send![asset_writer, addInput: video_input];
             } else {
-                return Err(Error::new(Status::GenericFailure, "Cannot add video input"));
-            }
-            
-            // Start writing session
-            let started: bool = msg_send![asset_writer, startWriting];
-            if !started {
-                return Err(Error::new(Status::GenericFailure, "Failed to start writing"));
+                return Err(capture_error(CaptureErrorCode::EncoderInitFailed, "Cannot add video input"));
             }
             
+            // Writing starts with the first frame so container metadata (tags) can still be set
             Ok(Self {
                 asset_writer,
                 video_input,
@@ -92,19 +199,67 @@ impl VideoEncoder {
                 output_url: output_path.to_string(),
                 is_recording: true,
                 frame_count: 0,
+                dropped_frames: 0,
                 start_time: None,
+                metadata_tags: Vec::new(),
+                timelapse_speedup: None,
+                last_frame_value: None,
+                chapters_enabled: false,
+                chapter_input: ptr::null_mut(),
+                chapter_adaptor: None,
+                chapters: Vec::new(),
+                settings: VideoEncoderSettings { codec, width, height, fps },
             })
         }
     }
     
+    /// Time frames by their capture time divided by `speedup` instead of by frame count, so a
+    /// timelapse plays back evenly even when the screen sat still and no frames came in
+    pub fn set_timelapse(&mut self, speedup: f64) {
+        self.timelapse_speedup = Some(speedup).filter(|s| *s > 0.0);
+    }
+    
+    /// Give the file a chapter track for `add_chapter`. The track is added when writing starts,
+    /// so this has to be called before the first frame.
+    pub fn enable_chapters(&mut self) {
+        self.chapters_enabled = true;
+    }
+    
+    /// Where the latest frame sits in the video, in seconds from the first frame
+    pub fn media_time(&self) -> f64 {
+        match (self.start_time, self.last_frame_value) {
+            (Some(start), Some(last)) => (last - start.value) as f64 / start.timescale.max(1) as f64,
+            _ => 0.0,
+        }
+    }
+    
+    /// Start a chapter at the latest frame; returns its time in seconds. Chapters are written
+    /// when the file is finalized.
+    pub fn add_chapter(&mut self, title: &str) -> f64 {
+        let time = self.media_time();
+        self.chapters.push((time, title.to_string()));
+        time
+    }
+    
     pub fn encode_frame(&mut self, pixel_buffer: *mut CVPixelBuffer, presentation_time: CMTime) -> Result<()> {
         unsafe {
             if !self.is_recording {
                 return Ok(());
             }
             
-            // Set start time on first frame
+            // Start writing on first frame
             if self.start_time.is_none() {
+                self.apply_metadata_tags();
+                if self.chapters_enabled {
+                    self.add_chapter_input();
+                }
+                
+                let started: bool = msg_send![self.asset_writer, startWriting];
+                if !started {
+                    self.is_recording = false;
+                    return Err(capture_error(CaptureErrorCode::EncoderInitFailed, "Failed to start writing"));
+                }
+                
                 let _: () = msg_send![self.asset_writer, startSessionAtSourceTime: presentation_time];
                 self.start_time = Some(presentation_time);
             }
@@ -113,13 +268,27 @@ impl VideoEncoder {
             let ready: bool = msg_send![self.video_input, isReadyForMoreMediaData];
             if !ready {
                 log::warn!("Video input not ready for more data");
+                self.dropped_frames += 1;
                 return Ok(());
             }
             
             // Calculate frame time based on frame count
             let frame_time = if let Some(start) = self.start_time {
+                let value = match self.timelapse_speedup {
+                    Some(speedup) => {
+                        let elapsed = (presentation_time.value - start.value) as f64
+                            * start.timescale as f64 / presentation_time.timescale.max(1) as f64;
+                        start.value + (elapsed / speedup) as i64
+                    }
+                    None => start.value + (self.frame_count as i64 * start.timescale as i64 / 30), // Assuming 30fps
+                };
+                // Two captures can round to the same timelapse instant; the writer needs increasing times
+                if self.last_frame_value.map_or(false, |last| value <= last) {
+                    return Ok(());
+                }
+                self.last_frame_value = Some(value);
                 CMTime {
-                    value: start.value + (self.frame_count as i64 * start.timescale as i64 / 30), // Assuming 30fps
+                    value,
                     timescale: start.timescale,
                     flags: start.flags,
                     epoch: start.epoch,
@@ -137,7 +306,7 @@ impl VideoEncoder {
             
             if !success {
                 log::error!("Failed to append pixel buffer");
-                return Err(Error::new(Status::GenericFailure, "Failed to encode frame"));
+                return Err(capture_error(CaptureErrorCode::EncoderInitFailed, "Failed to encode frame"));
             }
             
             self.frame_count += 1;
@@ -150,6 +319,20 @@ impl VideoEncoder {
         }
     }
     
+    /// The file being written
+    pub fn output_url(&self) -> &str {
+        &self.output_url
+    }
+    
+    pub fn settings(&self) -> VideoEncoderSettings {
+        self.settings
+    }
+    
+    /// Frames passed over because the writer wasn't ready for them
+    pub fn dropped_frames(&self) -> u64 {
+        self.dropped_frames
+    }
+    
     pub fn finalize_encoding(&mut self) -> Result<String> {
         unsafe {
             if !self.is_recording {
@@ -158,9 +341,20 @@ impl VideoEncoder {
             
             self.is_recording = false;
             
+            // Nothing was ever written - there is no file to finish
+            if self.start_time.is_none() {
+                log::warn!("Video encoder finalized before the first frame: {}", self.output_url);
+                return Ok(self.output_url.clone());
+            }
+            
             // Mark input as finished
             let _: () = msg_send![self.video_input, markAsFinished];
             
+            if !self.chapter_input.is_null() {
+                self.append_chapters();
+                let _: () = msg_send![self.chapter_input, markAsFinished];
+            }
+            
             // Finish writing
             let _: () = msg_send![self.asset_writer, finishWriting];
             
@@ -169,10 +363,151 @@ impl VideoEncoder {
         }
     }
     
-    unsafe fn create_video_settings(width: u32, height: u32, fps: u32) -> *mut NSDictionary<NSString, AnyObject> {
+    /// Tags written as QuickTime keywords. The container metadata is fixed once writing
+    /// starts, so tags set after the first frame only reach the journal and library index.
+    pub fn set_metadata_tags(&mut self, tags: &[String]) -> bool {
+        self.metadata_tags = tags.to_vec();
+        self.start_time.is_none()
+    }
+    
+    unsafe fn apply_metadata_tags(&self) {
+        if self.metadata_tags.is_empty() {
+            return;
+        }
+        
+        let item: *mut AnyObject = msg_send![class!(AVMutableMetadataItem), metadataItem];
+        if item.is_null() {
+            log::warn!("Failed to create metadata item for tags");
+            return;
+        }
+        
+        let identifier = NSString::from_str(AVMetadataIdentifierQuickTimeMetadataKeywords);
+        let value = NSString::from_str(&self.metadata_tags.join(","));
+        let _: () = msg_send![item, setIdentifier: &*identifier];
+        let _: () = msg_send![item, setValue: &*value];
+        
+        let items: *mut NSArray = msg_send![class!(NSArray), arrayWithObject: item];
+        let _: () = msg_send![self.asset_writer, setMetadata: items];
+        log::debug!("Applied {} metadata tags to {}", self.metadata_tags.len(), self.output_url);
+    }
+    
+    /// A timed metadata track of chapter titles, linked to the video as its chapter list
+    unsafe fn add_chapter_input(&mut self) {
+        let identifier = NSString::from_str(AVMetadataCommonIdentifierTitle);
+        let specification: *mut NSDictionary<NSString, AnyObject> = msg_send![
+            class!(NSDictionary),
+            dictionaryWithObjects: &[
+                &*identifier as *const NSString as *mut AnyObject,
+                kCMMetadataBaseDataType_UTF8 as *mut AnyObject
+            ],
+            forKeys: &[
+                &*kCMMetadataFormatDescriptionMetadataSpecificationKey_Identifier,
+                &*kCMMetadataFormatDescriptionMetadataSpecificationKey_DataType
+            ],
+            count: 2
+        ];
+        let specifications: *mut NSArray = msg_send![class!(NSArray), arrayWithObject: specification];
+        
+        let mut format: *mut c_void = ptr::null_mut();
+        let status = CMMetadataFormatDescriptionCreateWithMetadataSpecifications(
+            ptr::null(),
+            K_CM_METADATA_FORMAT_TYPE_BOXED,
+            specifications as *const c_void,
+            &mut format,
+        );
+        if status != 0 || format.is_null() {
+            log::warn!("Failed to create chapter format description: {}", status);
+            return;
+        }
+        
+        let media_type = NSString::from_str(AVMediaTypeMetadata);
+        let input: *mut AVAssetWriterInput = msg_send![
+            class!(AVAssetWriterInput),
+            assetWriterInputWithMediaType: &*media_type,
+            outputSettings: ptr::null_mut::<AnyObject>(),
+            sourceFormatHint: format
+        ];
+        CFRelease(format);
+        if input.is_null() {
+            log::warn!("Failed to create chapter track input");
+            return;
+        }
+        
+        let can_add: bool = msg_send![self.asset_writer, canAddInput: input];
+        if !can_add {
+            log::warn!("Cannot add chapter track to {}", self.output_url);
+            return;
+        }
+        let _: () = msg_send![self.asset_writer, addInput: input];
+        
+        let association = NSString::from_str(AVTrackAssociationTypeChapterList);
+        let can_associate: bool = msg_send![self.video_input, canAddTrackAssociationWithTrackOfInput: input, r#type: &*association];
+        if can_associate {
+            let _: () = msg_send![self.video_input, addTrackAssociationWithTrackOfInput: input, r#type: &*association];
+        }
+        
+        let adaptor: *mut AnyObject = msg_send![
+            class!(AVAssetWriterInputMetadataAdaptor),
+            assetWriterInputMetadataAdaptorWithAssetWriterInput: input
+        ];
+        self.chapter_adaptor = Retained::retain(adaptor);
+        self.chapter_input = input;
+    }
+    
+    /// Each chapter runs to the next one, the last to the end of the video
+    unsafe fn append_chapters(&mut self) {
+        let (adaptor, start) = match (&self.chapter_adaptor, self.start_time) {
+            (Some(adaptor), Some(start)) => (adaptor, start),
+            _ => return,
+        };
+        let end = self.media_time();
+        let timescale = start.timescale.max(1);
+        let to_time = |seconds: f64, base: i64| CMTime {
+            value: base + (seconds * timescale as f64).round() as i64,
+            timescale,
+            flags: start.flags,
+            epoch: start.epoch,
+        };
+        
+        for (index, (time, title)) in self.chapters.iter().enumerate() {
+            let next = self.chapters.get(index + 1).map(|(next, _)| *next).unwrap_or(end);
+            let duration = (next - time).max(MIN_CHAPTER_SECONDS);
+            
+            let item: *mut AnyObject = msg_send![class!(AVMutableMetadataItem), metadataItem];
+            if item.is_null() {
+                continue;
+            }
+            let identifier = NSString::from_str(AVMetadataCommonIdentifierTitle);
+            let value = NSString::from_str(title);
+            let _: () = msg_send![item, setIdentifier: &*identifier];
+            let _: () = msg_send![item, setDataType: kCMMetadataBaseDataType_UTF8];
+            let _: () = msg_send![item, setValue: &*value];
+            let items: *mut NSArray = msg_send![class!(NSArray), arrayWithObject: item];
+            
+            let range = CMTimeRange {
+                start: to_time(*time, start.value),
+                duration: to_time(duration, 0),
+            };
+            let group: *mut AnyObject = msg_send![class!(AVTimedMetadataGroup), alloc];
+            let group: *mut AnyObject = msg_send![group, initWithItems: items, timeRange: range];
+            if group.is_null() {
+                continue;
+            }
+            let appended: bool = msg_send![&**adaptor, appendTimedMetadataGroup: group];
+            let _: () = msg_send![group, release];
+            if !appended {
+                log::warn!("Failed to write chapter '{}' to {}", title, self.output_url);
+            }
+        }
+        if !self.chapters.is_empty() {
+            log::info!("Wrote {} chapters to {}", self.chapters.len(), self.output_url);
+        }
+    }
+    
+    unsafe fn create_video_settings(width: u32, height: u32, fps: u32, codec: VideoCodec) -> *mut NSDictionary<NSString, AnyObject> {
         // Create video settings dictionary
         let codec_key = NSString::from_str("AVVideoCodecKey");
-        let codec_value = NSString::from_str(AVVideoCodecTypeH264);
+        let codec_value = NSString::from_str(codec.av_codec_type());
         
         let width_key = NSString::from_str("AVVideoWidthKey");
         let width_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: width];
@@ -185,6 +520,10 @@ impl VideoEncoder {
         let avg_bitrate_key = NSString::from_str("AVVideoAverageBitRateKey");
         let avg_bitrate_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: width * height * 8]; // 8 bits per pixel
         
+        // Resized window captures deliver frames of a different shape; scale them in without distortion
+        let scaling_key = NSString::from_str("AVVideoScalingModeKey");
+        let scaling_value = NSString::from_str("AVVideoScalingModeResizeAspect");
+        
         let max_keyframe_key = NSString::from_str("AVVideoMaxKeyFrameIntervalKey");
         let max_keyframe_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: fps * 2]; // Keyframe every 2 seconds
         
@@ -203,10 +542,11 @@ impl VideoEncoder {
                 &*codec_value as *const NSString as *mut AnyObject,
                 width_value as *mut AnyObject,
                 height_value as *mut AnyObject,
-                compression_props as *mut AnyObject
+                compression_props as *mut AnyObject,
+                &*scaling_value as *const NSString as *mut AnyObject
             ],
-            forKeys: &[&*codec_key, &*width_key, &*height_key, &*compression_key],
-            count: 4
+            forKeys: &[&*codec_key, &*width_key, &*height_key, &*compression_key, &*scaling_key],
+            count: 5
         ];
         
         settings
@@ -230,16 +570,103 @@ impl VideoEncoder {
     }
 }
 
+const K_CM_VIDEO_CODEC_TYPE_H264: u32 = 0x61766331; // 'avc1'
+const K_CM_VIDEO_CODEC_TYPE_HEVC: u32 = 0x68766331; // 'hvc1'
+
+/// Spin up and tear down a throwaway H.264 compression session so the (hardware) encoder is
+/// loaded before the first recording; AVAssetWriter's own session then starts without the delay
+pub fn warm_up_video_encoder(width: u32, height: u32) -> bool {
+    probe_video_encoder(VideoCodec::H264, width, height)
+}
+
+/// Whether VideoToolbox can open a `codec` session at this size
+pub fn probe_video_encoder(codec: VideoCodec, width: u32, height: u32) -> bool {
+    let codec_type = match codec {
+        VideoCodec::H264 => K_CM_VIDEO_CODEC_TYPE_H264,
+        VideoCodec::Hevc => K_CM_VIDEO_CODEC_TYPE_HEVC,
+    };
+    unsafe {
+        let mut session: *mut c_void = ptr::null_mut();
+        let status = VTCompressionSessionCreate(
+            ptr::null(),
+            width as i32,
+            height as i32,
+            codec_type,
+            ptr::null(),
+            ptr::null(),
+            ptr::null(),
+            ptr::null(),
+            ptr::null_mut(),
+            &mut session,
+        );
+        if status != 0 || session.is_null() {
+            log::warn!("Failed to create a {:?} compression session (status {})", codec, status);
+            return false;
+        }
+        
+        let prepared = VTCompressionSessionPrepareToEncodeFrames(session) == 0;
+        VTCompressionSessionInvalidate(session);
+        CFRelease(session);
+        prepared
+    }
+}
+
+/// Whether VideoToolbox lists a hardware-accelerated encoder for `codec` on this machine
+pub fn has_hardware_encoder(codec: VideoCodec) -> bool {
+    let codec_type = match codec {
+        VideoCodec::H264 => K_CM_VIDEO_CODEC_TYPE_H264,
+        VideoCodec::Hevc => K_CM_VIDEO_CODEC_TYPE_HEVC,
+    };
+    unsafe {
+        let mut list: *const NSArray = ptr::null();
+        let status = VTCopyVideoEncoderList(ptr::null(), &mut list);
+        if status != 0 || list.is_null() {
+            log::warn!("Failed to list the VideoToolbox encoders (status {})", status);
+            return false;
+        }
+
+        let count: usize = msg_send![list, count];
+        let found = (0..count).any(|index| {
+            let encoder: *mut NSDictionary = msg_send![list, objectAtIndex: index];
+            let encoder_codec: *mut NSNumber = msg_send![encoder, objectForKey: kVTVideoEncoderList_CodecType];
+            let hardware: *mut NSNumber = msg_send![encoder, objectForKey: kVTVideoEncoderList_IsHardwareAccelerated];
+            !encoder_codec.is_null()
+                && (*encoder_codec).as_u32() == codec_type
+                && !hardware.is_null()
+                && (*hardware).as_bool()
+        });
+        CFRelease(list as *const c_void);
+        found
+    }
+}
+
 pub struct AudioEncoder {
     asset_writer: *mut AVAssetWriter,
     audio_input: *mut AVAssetWriterInput,
     output_url: String,
     is_recording: bool,
     sample_count: u64,
+    sample_rate: u32,
+    channels: u32,
+    // Only used when a source doesn't match the writer's rate/channel layout
+    converter: Option<PcmConverter>,
+    pcm_format: *mut c_void,
 }
 
+// Same as VideoEncoder: only used under a mutex
+unsafe impl Send for AudioEncoder {}
+
 impl AudioEncoder {
     pub fn new(output_path: &str, sample_rate: u32, channels: u32) -> Result<Self> {
+        Self::with_file_type(output_path, AVFileTypeMPEG4, sample_rate, channels)
+    }
+    
+    /// A standalone M4A, for recordings without video
+    pub fn new_m4a(output_path: &str, sample_rate: u32, channels: u32) -> Result<Self> {
+        Self::with_file_type(output_path, AVFileTypeAppleM4A, sample_rate, channels)
+    }
+    
+    fn with_file_type(output_path: &str, file_type: &str, sample_rate: u32, channels: u32) -> Result<Self> {
         unsafe {
             // Create file URL
             let url_string = NSString::from_str(output_path);
@@ -247,7 +674,7 @@ impl AudioEncoder {
             
             // Create AVAssetWriter
             let mut error: *mut NSError = ptr::null_mut();
-            let file_type = NSString::from_str(AVFileTypeMPEG4);
+            let file_type = NSString::from_str(file_type);
             let asset_writer: *mut AVAssetWriter = msg_send![
                 class!(AVAssetWriter),
                 assetWriterWithURL: file_url,
@@ -256,7 +683,7 @@ impl AudioEncoder {
             ];
             
             if asset_writer.is_null() || !error.is_null() {
-                return Err(Error::new(Status::GenericFailure, "Failed to create audio AVAssetWriter"));
+                return Err(capture_error(CaptureErrorCode::EncoderInitFailed, "Failed to create audio AVAssetWriter"));
             }
             
             // Create audio input settings
@@ -276,13 +703,13 @@ impl AudioEncoder {
             if can_add {
                 let _: () = msg_send![asset_writer, addInput: audio_input];
             } else {
-                return Err(Error::new(Status::GenericFailure, "Cannot add audio input"));
+                return Err(capture_error(CaptureErrorCode::EncoderInitFailed, "Cannot add audio input"));
             }
             
             // Start writing session
             let started: bool = msg_send![asset_writer, startWriting];
             if !started {
-                return Err(Error::new(Status::GenericFailure, "Failed to start audio writing"));
+                return Err(capture_error(CaptureErrorCode::EncoderInitFailed, "Failed to start audio writing"));
             }
             
             let _: () = msg_send![asset_writer, startSessionAtSourceTime: kCMTimeZero];
@@ -293,16 +720,136 @@ impl AudioEncoder {
                 output_url: output_path.to_string(),
                 is_recording: true,
                 sample_count: 0,
+                sample_rate,
+                channels,
+                converter: None,
+                pcm_format: ptr::null_mut(),
             })
         }
     }
     
     pub fn encode_audio_buffer(&mut self, sample_buffer: &CMSampleBuffer) -> Result<()> {
+        if !self.is_recording {
+            return Ok(());
+        }
+        
+        // Sources at another rate or channel count (e.g. a 44.1 kHz mono mic) go through the converter
+        let matches_output = stream_description(sample_buffer)
+            .map(|asbd| {
+                (asbd.sample_rate - self.sample_rate as f64).abs() < 1.0 && asbd.channels_per_frame == self.channels
+            })
+            .unwrap_or(true);
+        if !matches_output {
+            return match extract_pcm(sample_buffer) {
+                Some(pcm) => self.encode_pcm_buffer(&pcm),
+                None => Ok(()),
+            };
+        }
+        
+        self.append_sample_buffer(sample_buffer)
+    }
+    
+    /// Encode PCM in any layout, converting to the writer's rate and channel count when needed
+    pub fn encode_pcm_buffer(&mut self, pcm: &PcmBuffer) -> Result<()> {
+        if !self.is_recording || pcm.frame_count() == 0 {
+            return Ok(());
+        }
+        
+        let matches_output = (pcm.sample_rate - self.sample_rate as f64).abs() < 1.0 && pcm.channels.len() == self.channels as usize;
+        if matches_output {
+            return self.encode_pcm(&pcm.channels, pcm.presentation_time);
+        }
+        
+        let converter = self
+            .converter
+            .get_or_insert_with(|| {
+                log::info!("Converting audio {} Hz x{} -> {} Hz x{}", pcm.sample_rate, pcm.channels.len(), self.sample_rate, self.channels);
+                PcmConverter::new(self.sample_rate, self.channels)
+            });
+        let converted = converter.process(&pcm.channels, pcm.sample_rate);
+        self.encode_pcm(&converted, pcm.presentation_time)
+    }
+    
+    /// Encode `duration` seconds of silence starting at `presentation_time`, to close a gap in the track
+    pub fn encode_silence(&mut self, presentation_time: f64, duration: f64) -> Result<()> {
+        let total_frames = (duration * self.sample_rate as f64).round() as usize;
+        // One second per sample buffer keeps long gaps from allocating everything at once
+        let chunk_frames = self.sample_rate as usize;
+        let mut written = 0;
+        while written < total_frames {
+            let frames = chunk_frames.min(total_frames - written);
+            let silence = vec![vec![0.0f32; frames]; self.channels as usize];
+            self.encode_pcm(&silence, presentation_time + written as f64 / self.sample_rate as f64)?;
+            written += frames;
+        }
+        Ok(())
+    }
+    
+    /// Encode planar f32 PCM that is already at the writer's sample rate and channel count
+    pub fn encode_pcm(&mut self, channels: &[Vec<f32>], presentation_time: f64) -> Result<()> {
+        let frames = channels.first().map(|c| c.len()).unwrap_or(0);
+        if !self.is_recording || frames == 0 {
+            return Ok(());
+        }
+        
+        let samples = f32_to_i16(&interleave(channels));
+        let byte_length = samples.len() * std::mem::size_of::<i16>();
+        
         unsafe {
-            if !self.is_recording {
-                return Ok(());
+            if self.pcm_format.is_null() {
+                let asbd = AudioStreamBasicDescription {
+                    sample_rate: self.sample_rate as f64,
+                    format_id: K_AUDIO_FORMAT_LINEAR_PCM,
+                    format_flags: K_LINEAR_PCM_FORMAT_FLAGS_INT16_PACKED,
+                    bytes_per_packet: 2 * self.channels,
+                    frames_per_packet: 1,
+                    bytes_per_frame: 2 * self.channels,
+                    channels_per_frame: self.channels,
+                    bits_per_channel: 16,
+                    reserved: 0,
+                };
+                let status = CMAudioFormatDescriptionCreate(
+                    ptr::null(), &asbd, 0, ptr::null(), 0, ptr::null(), ptr::null(), &mut self.pcm_format,
+                );
+                if status != 0 || self.pcm_format.is_null() {
+                    return Err(capture_error(CaptureErrorCode::EncoderInitFailed, format!("Failed to create PCM format description: {}", status)));
+                }
             }
             
+            let mut block_buffer: *mut c_void = ptr::null_mut();
+            let status = CMBlockBufferCreateWithMemoryBlock(
+                ptr::null(), ptr::null_mut(), byte_length, ptr::null(), ptr::null(), 0, byte_length, 0, &mut block_buffer,
+            );
+            if status != 0 || block_buffer.is_null() {
+                return Err(capture_error(CaptureErrorCode::EncoderInitFailed, format!("Failed to allocate audio block buffer: {}", status)));
+            }
+            CMBlockBufferReplaceDataBytes(samples.as_ptr() as *const c_void, block_buffer, 0, byte_length);
+            
+            let timescale = self.sample_rate as i32;
+            let pts = CMTime {
+                value: (presentation_time * self.sample_rate as f64).round() as i64,
+                timescale,
+                flags: objc2_core_media::CMTimeFlags::Valid,
+                epoch: 0,
+            };
+            
+            let mut sample_buffer: *mut CMSampleBuffer = ptr::null_mut();
+            let status = CMAudioSampleBufferCreateReadyWithPacketDescriptions(
+                ptr::null(), block_buffer, self.pcm_format, frames as isize, pts, ptr::null(), &mut sample_buffer,
+            );
+            CFRelease(block_buffer);
+            if status != 0 || sample_buffer.is_null() {
+                return Err(capture_error(CaptureErrorCode::EncoderInitFailed, format!("Failed to create audio sample buffer: {}", status)));
+            }
+            
+            let result = self.append_sample_buffer(&*sample_buffer);
+            CFRelease(sample_buffer as *const c_void);
+            result
+        }
+    }
+    
+    fn append_sample_buffer(&mut self, sample_buffer: &CMSampleBuffer) -> Result<()> {
+        unsafe {
             // Check if input is ready for more media data
             let ready: bool = msg_send![self.audio_input, isReadyForMoreMediaData];
             if !ready {
@@ -315,7 +862,7 @@ impl AudioEncoder {
             
             if !success {
                 log::error!("Failed to append audio sample buffer");
-                return Err(Error::new(Status::GenericFailure, "Failed to encode audio"));
+                return Err(capture_error(CaptureErrorCode::EncoderInitFailed, "Failed to encode audio"));
             }
             
             self.sample_count += 1;
@@ -328,6 +875,11 @@ impl AudioEncoder {
         }
     }
     
+    /// The file being written
+    pub fn output_url(&self) -> &str {
+        &self.output_url
+    }
+    
     pub fn finalize_encoding(&mut self) -> Result<String> {
         unsafe {
             if !self.is_recording {
@@ -342,6 +894,11 @@ impl AudioEncoder {
             // Finish writing
             let _: () = msg_send![self.asset_writer, finishWriting];
             
+            if !self.pcm_format.is_null() {
+                CFRelease(self.pcm_format);
+                self.pcm_format = ptr::null_mut();
+            }
+            
             log::info!("Audio encoding finalized: {} ({} samples)", self.output_url, self.sample_count);
             Ok(self.output_url.clone())
         }
@@ -358,20 +915,44 @@ impl AudioEncoder {
         let channels_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: channels];
         
         let bitrate_key = NSString::from_str("AVEncoderBitRateKey");
-        let bitrate_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: 128000u32]; // 128 kbps
+        // 128 kbps for stereo, scaled up for more channels
+        let bitrate = 64000u32 * channels.max(2);
+        let bitrate_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: bitrate];
         
-        let settings: *mut NSDictionary<NSString, AnyObject> = msg_send![
+        if channels <= 2 {
+            return msg_send![
+                class!(NSDictionary),
+                dictionaryWithObjects: &[
+                    format_value as *mut AnyObject,
+                    sample_rate_value as *mut AnyObject,
+                    channels_value as *mut AnyObject,
+                    bitrate_value as *mut AnyObject
+                ],
+                forKeys: &[&*format_key, &*sample_rate_key, &*channels_key, &*bitrate_key],
+                count: 4
+            ];
+        }
+        
+        // More than two channels need a layout; each channel is kept as its own discrete channel
+        // (AudioChannelLayout with no channel descriptions: tag, bitmap, description count)
+        let layout: [u32; 3] = [K_AUDIO_CHANNEL_LAYOUT_TAG_DISCRETE_IN_ORDER | channels, 0, 0];
+        let layout_key = NSString::from_str("AVChannelLayoutKey");
+        let layout_value: *mut AnyObject = msg_send![
+            class!(NSData),
+            dataWithBytes: layout.as_ptr() as *const c_void,
+            length: std::mem::size_of_val(&layout)
+        ];
+        msg_send![
             class!(NSDictionary),
             dictionaryWithObjects: &[
                 format_value as *mut AnyObject,
                 sample_rate_value as *mut AnyObject,
                 channels_value as *mut AnyObject,
-                bitrate_value as *mut AnyObject
+                bitrate_value as *mut AnyObject,
+                layout_value
             ],
-            forKeys: &[&*format_key, &*sample_rate_key, &*channels_key, &*bitrate_key],
-            count: 4
-        ];
-        
-        settings
+            forKeys: &[&*format_key, &*sample_rate_key, &*channels_key, &*bitrate_key, &*layout_key],
+            count: 5
+        ]
     }
 } 
\ No newline at end of file
@@ -0,0 +1,123 @@
+// Coded errors - every failure reaches JS with a `code` the app can branch on
+
+use napi::bindgen_prelude::*;
+use napi::{Env, JsUnknown, NapiValue};
+use napi_derive::napi;
+
+/// Why a call failed; set as `code` on the thrown or rejected JS error
+#[napi(string_enum)]
+#[derive(Debug, PartialEq, Eq)]
+pub enum CaptureErrorCode {
+    PermissionDenied,
+    /// The display, window, application or device isn't there (any more)
+    SourceNotFound,
+    /// The configuration or an argument was rejected before anything started
+    InvalidConfiguration,
+    AlreadyRecording,
+    NotRecording,
+    EncoderInitFailed,
+    DiskFull,
+    StreamStopped,
+    Timeout,
+    /// Aborted with `cancel` before it finished
+    Cancelled,
+    /// Needs a newer macOS or hardware this Mac doesn't have
+    Unsupported,
+    Unknown,
+}
+
+const CODES: [CaptureErrorCode; 12] = [
+    CaptureErrorCode::PermissionDenied,
+    CaptureErrorCode::SourceNotFound,
+    CaptureErrorCode::InvalidConfiguration,
+    CaptureErrorCode::AlreadyRecording,
+    CaptureErrorCode::NotRecording,
+    CaptureErrorCode::EncoderInitFailed,
+    CaptureErrorCode::DiskFull,
+    CaptureErrorCode::StreamStopped,
+    CaptureErrorCode::Timeout,
+    CaptureErrorCode::Cancelled,
+    CaptureErrorCode::Unsupported,
+    CaptureErrorCode::Unknown,
+];
+
+impl AsRef<str> for CaptureErrorCode {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::PermissionDenied => "PermissionDenied",
+            Self::SourceNotFound => "SourceNotFound",
+            Self::InvalidConfiguration => "InvalidConfiguration",
+            Self::AlreadyRecording => "AlreadyRecording",
+            Self::NotRecording => "NotRecording",
+            Self::EncoderInitFailed => "EncoderInitFailed",
+            Self::DiskFull => "DiskFull",
+            Self::StreamStopped => "StreamStopped",
+            Self::Timeout => "Timeout",
+            Self::Cancelled => "Cancelled",
+            Self::Unsupported => "Unsupported",
+            Self::Unknown => "Unknown",
+        }
+    }
+}
+
+impl CaptureErrorCode {
+    fn status(&self) -> Status {
+        match self {
+            Self::InvalidConfiguration | Self::SourceNotFound => Status::InvalidArg,
+            _ => Status::GenericFailure,
+        }
+    }
+}
+
+/// An internal error tagged with `code`. Internals keep the plain napi `Error`; the tag travels in
+/// the reason and is turned into the JS `code` at the boundary by `coded` or `reject_coded`.
+pub fn capture_error(code: CaptureErrorCode, reason: impl std::fmt::Display) -> Error {
+    Error::new(code.status(), format!("{}: {}", code.as_ref(), reason))
+}
+
+// ENOSPC and EDQUOT on macOS
+const NO_SPACE_ERRNOS: [i32; 2] = [28, 69];
+
+/// A failed file operation, as `<reason>: <io error>`; a full disk or exhausted quota is DiskFull
+pub fn io_error(reason: impl std::fmt::Display, error: &std::io::Error) -> Error {
+    let code = match error.raw_os_error() {
+        Some(errno) if NO_SPACE_ERRNOS.contains(&errno) => CaptureErrorCode::DiskFull,
+        _ => CaptureErrorCode::Unknown,
+    };
+    capture_error(code, format!("{}: {}", reason, error))
+}
+
+/// The code and the untagged message of an error; untagged errors get a code from their status
+pub fn describe(error: &Error) -> (CaptureErrorCode, String) {
+    let tagged = error.reason.split_once(": ").and_then(|(tag, message)| {
+        CODES.iter().find(|code| code.as_ref() == tag).map(|code| (*code, message.to_string()))
+    });
+    match tagged {
+        Some(tagged) => tagged,
+        None => {
+            let code = match error.status {
+                Status::InvalidArg => CaptureErrorCode::InvalidConfiguration,
+                _ => CaptureErrorCode::Unknown,
+            };
+            (code, error.reason.clone())
+        }
+    }
+}
+
+fn untag(error: Error) -> Error<CaptureErrorCode> {
+    let (code, message) = describe(&error);
+    Error::new(code, message)
+}
+
+/// Run the body of an exported function; its error reaches JS with `code` set
+pub fn coded<T>(body: impl FnOnce() -> Result<T>) -> Result<T, CaptureErrorCode> {
+    body().map_err(untag)
+}
+
+/// `Task::reject` can only hand back a plain `Error`, so the coded JS error is built here and
+/// passed through as is
+pub fn reject_coded<T>(env: Env, error: Error) -> Result<T> {
+    let value = unsafe { JsError::from(untag(error)).into_value(env.raw()) };
+    let error = unsafe { JsUnknown::from_raw_unchecked(env.raw(), value) };
+    Err(Error::from(error))
+}
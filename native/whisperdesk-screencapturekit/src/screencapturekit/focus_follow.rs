@@ -0,0 +1,141 @@
+// Focus following - report the frontmost window once it has kept focus for a moment
+
+use objc2::rc::Retained;
+use objc2::runtime::AnyObject;
+use objc2::{class, msg_send};
+use objc2_foundation::{NSArray, NSDictionary, NSNumber, NSString};
+use std::ffi::c_void;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+pub const DEFAULT_DEBOUNCE_MS: u32 = 750;
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+const K_CG_WINDOW_LIST_OPTION_ON_SCREEN_ONLY: u32 = 1 << 0;
+const K_CG_WINDOW_LIST_EXCLUDE_DESKTOP_ELEMENTS: u32 = 1 << 4;
+// Palettes, tooltips and the like are smaller than this; they never take over the recording
+const MIN_WINDOW_SIDE: f64 = 64.0;
+
+extern "C" {
+    fn CGWindowListCopyWindowInfo(option: u32, relative_to_window: u32) -> *mut NSArray;
+    fn CFRelease(cf: *const c_void);
+}
+
+/// Window ID of the window that now has focus
+pub type FocusChangeHandler = Arc<dyn Fn(u32) + Send + Sync>;
+
+/// The frontmost app's topmost normal window, never one of this process's own
+pub fn frontmost_window() -> Option<u32> {
+    unsafe {
+        let workspace: Option<Retained<AnyObject>> = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let application: Option<Retained<AnyObject>> = msg_send![&*workspace?, frontmostApplication];
+        let pid: i32 = msg_send![&*application?, processIdentifier];
+        if pid == std::process::id() as i32 {
+            return None;
+        }
+
+        let list = CGWindowListCopyWindowInfo(K_CG_WINDOW_LIST_OPTION_ON_SCREEN_ONLY | K_CG_WINDOW_LIST_EXCLUDE_DESKTOP_ELEMENTS, 0);
+        if list.is_null() {
+            return None;
+        }
+        let list_ref: &NSArray = &*list;
+        let number = |dict: &NSDictionary, key: &str| {
+            dict.objectForKey(&NSString::from_str(key))
+                .and_then(|obj| obj.downcast::<NSNumber>().ok())
+        };
+        // Front to back, so the first normal window of the app is its focused one
+        let mut found = None;
+        for i in 0..list_ref.count() {
+            let dict = match list_ref.objectAtIndex(i).downcast::<NSDictionary>() {
+                Ok(dict) => dict,
+                Err(_) => continue,
+            };
+            let owner = number(&dict, "kCGWindowOwnerPID").map(|n| n.intValue());
+            let layer = number(&dict, "kCGWindowLayer").map(|n| n.intValue()).unwrap_or(0);
+            if owner != Some(pid) || layer != 0 {
+                continue;
+            }
+            let bounds: Option<Retained<NSDictionary>> = dict
+                .objectForKey(&NSString::from_str("kCGWindowBounds"))
+                .and_then(|obj| obj.downcast::<NSDictionary>().ok());
+            let side = |key: &str| bounds.as_ref().and_then(|b| number(b, key)).map(|n| n.doubleValue()).unwrap_or(0.0);
+            if side("Width") < MIN_WINDOW_SIDE || side("Height") < MIN_WINDOW_SIDE {
+                continue;
+            }
+            found = number(&dict, "kCGWindowNumber").map(|n| n.intValue() as u32);
+            break;
+        }
+        CFRelease(list as *const c_void);
+        found
+    }
+}
+
+/// Polls focus and reports a window once it has been frontmost for the whole debounce period,
+/// so flicking through apps with Cmd-Tab doesn't cut the recording to every window passed
+pub struct FocusFollower {
+    stop_sender: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl FocusFollower {
+    /// `current` is the window being captured now, if any; it's never reported again right away
+    pub fn start(current: Option<u32>, debounce: Duration, on_change: FocusChangeHandler) -> Self {
+        let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+        log::info!("Following the focused window ({} ms debounce)", debounce.as_millis());
+
+        let thread = std::thread::Builder::new()
+            .name("focus-follow".to_string())
+            .spawn(move || {
+                let mut current = current;
+                let mut candidate: Option<(u32, Instant)> = None;
+                loop {
+                    match stop_receiver.recv_timeout(POLL_INTERVAL) {
+                        Err(RecvTimeoutError::Timeout) => {}
+                        _ => break,
+                    }
+                    // The desktop, the Dock or our own window in front: stay where we are
+                    let focused = match frontmost_window() {
+                        Some(window_id) if Some(window_id) != current => window_id,
+                        _ => {
+                            candidate = None;
+                            continue;
+                        }
+                    };
+                    match candidate {
+                        Some((window_id, since)) if window_id == focused => {
+                            if since.elapsed() >= debounce {
+                                log::info!("Focus moved to window {}", focused);
+                                current = Some(focused);
+                                candidate = None;
+                                on_change(focused);
+                            }
+                        }
+                        _ => candidate = Some((focused, Instant::now())),
+                    }
+                }
+                log::info!("Focus following stopped");
+            })
+            .ok();
+
+        Self {
+            stop_sender: Some(stop_sender),
+            thread,
+        }
+    }
+
+    pub fn stop(&mut self) {
+        // Dropping the sender wakes the poller immediately
+        self.stop_sender.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for FocusFollower {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,77 @@
+// Capture and encoding formats exported to JS as enums
+
+use napi_derive::napi;
+use serde::Serialize;
+
+/// Pixel layout of the captured frames; the values are CoreVideo pixel format codes
+#[napi]
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+pub enum PixelFormat {
+    /// 'BGRA', 8 bits per channel. The only layout cursor, click, camera and zoom overlays and
+    /// `onVideoFrame` can draw into or read.
+    #[default]
+    Bgra = 0x42475241,
+    /// 'l10r', 10-bit packed ARGB for HDR displays
+    Argb2101010 = 0x6C313072,
+    /// '420v', video-range 4:2:0 YCbCr; the cheapest to capture and encode
+    Yuv420VideoRange = 0x34323076,
+    /// '420f', full-range 4:2:0 YCbCr
+    Yuv420FullRange = 0x34323066,
+}
+
+impl PixelFormat {
+    pub fn four_cc(&self) -> u32 {
+        *self as u32
+    }
+}
+
+/// Color space the captured frames are converted to
+#[napi(string_enum)]
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ColorSpace {
+    #[default]
+    #[napi(value = "srgb")]
+    Srgb,
+    #[napi(value = "display-p3")]
+    DisplayP3,
+    /// ITU-R BT.709, the usual space for HD video
+    #[napi(value = "bt709")]
+    Bt709,
+    /// ITU-R BT.2100 with the PQ curve; needs a 10-bit pixel format to be worth it
+    #[napi(value = "bt2100-pq")]
+    Bt2100Pq,
+}
+
+impl ColorSpace {
+    /// The CGColorSpace name SCStreamConfiguration.colorSpaceName expects
+    pub fn cg_name(&self) -> &'static str {
+        match self {
+            ColorSpace::Srgb => "kCGColorSpaceSRGB",
+            ColorSpace::DisplayP3 => "kCGColorSpaceDisplayP3",
+            ColorSpace::Bt709 => "kCGColorSpaceITUR_709",
+            ColorSpace::Bt2100Pq => "kCGColorSpaceITUR_2100_PQ",
+        }
+    }
+}
+
+/// Video codec of the recording
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum VideoCodec {
+    #[default]
+    H264,
+    /// Smaller files at the same quality; hardware encoded on Apple silicon and recent Intel Macs
+    Hevc,
+}
+
+impl VideoCodec {
+    /// AVVideoCodecKey value
+    pub fn av_codec_type(&self) -> &'static str {
+        match self {
+            VideoCodec::H264 => super::encoder::AVVideoCodecTypeH264,
+            VideoCodec::Hevc => super::encoder::AVVideoCodecTypeHEVC,
+        }
+    }
+}
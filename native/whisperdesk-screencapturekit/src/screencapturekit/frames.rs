@@ -0,0 +1,78 @@
+// Raw video frames for JS - captured pixels copied out of the CVPixelBuffer for custom processing
+
+use napi_derive::napi;
+use objc2_core_video::CVPixelBuffer;
+use std::sync::Arc;
+
+use super::formats::PixelFormat;
+use super::pixel_buffer::{
+    CVPixelBufferLockBaseAddress, CVPixelBufferUnlockBaseAddress, CVPixelBufferGetBaseAddress, CVPixelBufferGetBytesPerRow,
+    CVPixelBufferGetWidth, CVPixelBufferGetHeight, CVPixelBufferGetPixelFormatType,
+};
+
+/// Byte order of the delivered pixels
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, Default, PartialEq)]
+pub enum FrameFormat {
+    #[default]
+    Rgba,
+    /// The capture's native order, which skips the channel swap
+    Bgra,
+}
+
+/// One captured frame, rows `stride` bytes apart
+pub struct VideoFrame {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+    pub stride: u32,
+    pub format: FrameFormat,
+    /// Presentation time in seconds (host clock)
+    pub timestamp: f64,
+}
+
+pub type VideoFrameHandler = Arc<dyn Fn(VideoFrame) + Send + Sync>;
+
+/// A frame listener and the layout it wants
+#[derive(Clone)]
+pub struct VideoFrameListener {
+    pub format: FrameFormat,
+    pub handler: VideoFrameHandler,
+}
+
+/// Copy a BGRA capture frame out, tightly packed, in `format`
+pub unsafe fn copy_frame(pixel_buffer: *mut CVPixelBuffer, format: FrameFormat, timestamp: f64) -> Option<VideoFrame> {
+    if pixel_buffer.is_null() || CVPixelBufferGetPixelFormatType(pixel_buffer) != PixelFormat::Bgra.four_cc() {
+        return None;
+    }
+    if CVPixelBufferLockBaseAddress(pixel_buffer, 1) != 0 {
+        return None;
+    }
+    let base = CVPixelBufferGetBaseAddress(pixel_buffer) as *const u8;
+    let width = CVPixelBufferGetWidth(pixel_buffer);
+    let height = CVPixelBufferGetHeight(pixel_buffer);
+    let bytes_per_row = CVPixelBufferGetBytesPerRow(pixel_buffer);
+    let frame = if base.is_null() {
+        None
+    } else {
+        let mut data = Vec::with_capacity(width * height * 4);
+        for row in 0..height {
+            data.extend_from_slice(std::slice::from_raw_parts(base.add(row * bytes_per_row), width * 4));
+        }
+        if format == FrameFormat::Rgba {
+            for pixel in data.chunks_exact_mut(4) {
+                pixel.swap(0, 2);
+            }
+        }
+        Some(VideoFrame {
+            data,
+            width: width as u32,
+            height: height as u32,
+            stride: (width * 4) as u32,
+            format,
+            timestamp,
+        })
+    };
+    CVPixelBufferUnlockBaseAddress(pixel_buffer, 1);
+    frame
+}
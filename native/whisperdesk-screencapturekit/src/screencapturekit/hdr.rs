@@ -0,0 +1,76 @@
+// Display HDR state - EDR headroom as reported by NSScreen
+
+use objc2::runtime::AnyObject;
+use objc2::{msg_send, class};
+use objc2_foundation::{NSArray, NSNumber, NSString};
+
+// Headroom above SDR white needed before we call the display "showing HDR"
+const HEADROOM_EPSILON: f64 = 0.01;
+
+#[derive(Debug, Clone)]
+pub struct DisplayHeadroom {
+    pub display_id: u32,
+    /// Headroom in use right now; above 1.0 only while EDR content is on screen
+    pub current: f64,
+    /// Headroom the display can reach at all; 1.0 on SDR-only displays
+    pub potential: f64,
+    /// Headroom at which the display stays reference-accurate; 1.0 when not in a reference mode
+    pub reference: f64,
+}
+
+impl DisplayHeadroom {
+    pub fn hdr_capable(&self) -> bool {
+        self.potential > 1.0 + HEADROOM_EPSILON
+    }
+
+    pub fn hdr_content_on_screen(&self) -> bool {
+        self.current > 1.0 + HEADROOM_EPSILON
+    }
+}
+
+// AppKit reports 0 for screens that are asleep or mid-reconfiguration
+fn sanitize(value: f64) -> f64 {
+    if value > 0.0 {
+        value
+    } else {
+        1.0
+    }
+}
+
+/// EDR headroom of every screen AppKit knows about, keyed by CGDirectDisplayID
+pub fn display_headroom() -> Vec<DisplayHeadroom> {
+    unsafe {
+        let screens: *mut NSArray = msg_send![class!(NSScreen), screens];
+        if screens.is_null() {
+            return Vec::new();
+        }
+
+        let number_key = NSString::from_str("NSScreenNumber");
+        let count: usize = msg_send![screens, count];
+        let mut result = Vec::with_capacity(count);
+
+        for index in 0..count {
+            let screen: *mut AnyObject = msg_send![screens, objectAtIndex: index];
+            let description: *mut AnyObject = msg_send![screen, deviceDescription];
+            let number: *mut NSNumber = msg_send![description, objectForKey: &*number_key];
+            if number.is_null() {
+                continue;
+            }
+            let display_id: u32 = msg_send![number, unsignedIntValue];
+
+            // All three exist since macOS 10.15, below the ScreenCaptureKit minimum
+            let current: f64 = msg_send![screen, maximumExtendedDynamicRangeColorComponentValue];
+            let potential: f64 = msg_send![screen, maximumPotentialExtendedDynamicRangeColorComponentValue];
+            let reference: f64 = msg_send![screen, maximumReferenceExtendedDynamicRangeColorComponentValue];
+
+            result.push(DisplayHeadroom {
+                display_id,
+                current: sanitize(current),
+                potential: sanitize(potential),
+                reference: sanitize(reference),
+            });
+        }
+
+        result
+    }
+}
@@ -0,0 +1,225 @@
+// Input event track - keyboard and mouse events written to a JSON sidecar for keystroke overlays
+
+use napi::bindgen_prelude::*;
+use serde::Serialize;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Instant;
+
+use super::overlay::{event_location, event_mask_bit, CGEventRef, EventTap, K_CG_EVENT_LEFT_MOUSE_DOWN, K_CG_EVENT_OTHER_MOUSE_DOWN, K_CG_EVENT_RIGHT_MOUSE_DOWN};
+use super::secure_input::is_secure_input_active;
+use super::session::now_millis;
+use super::errors::{capture_error, io_error, CaptureErrorCode};
+
+// CGEventType
+const K_CG_EVENT_LEFT_MOUSE_UP: u32 = 2;
+const K_CG_EVENT_RIGHT_MOUSE_UP: u32 = 4;
+const K_CG_EVENT_KEY_DOWN: u32 = 10;
+const K_CG_EVENT_KEY_UP: u32 = 11;
+const K_CG_EVENT_FLAGS_CHANGED: u32 = 12;
+const K_CG_EVENT_SCROLL_WHEEL: u32 = 22;
+const K_CG_EVENT_OTHER_MOUSE_UP: u32 = 26;
+
+// CGEventField
+const K_CG_MOUSE_EVENT_BUTTON_NUMBER: u32 = 3;
+const K_CG_KEYBOARD_EVENT_AUTOREPEAT: u32 = 8;
+const K_CG_KEYBOARD_EVENT_KEYCODE: u32 = 9;
+const K_CG_SCROLL_WHEEL_EVENT_DELTA_AXIS_1: u32 = 11;
+const K_CG_SCROLL_WHEEL_EVENT_DELTA_AXIS_2: u32 = 12;
+
+// CGEventFlags
+const MODIFIER_FLAGS: [(u64, &str); 6] = [
+    (0x0001_0000, "capsLock"),
+    (0x0002_0000, "shift"),
+    (0x0004_0000, "control"),
+    (0x0008_0000, "option"),
+    (0x0010_0000, "command"),
+    (0x0080_0000, "fn"),
+];
+
+const SIDECAR_VERSION: u32 = 1;
+
+extern "C" {
+    fn AXIsProcessTrusted() -> bool;
+    fn CGEventGetIntegerValueField(event: CGEventRef, field: u32) -> i64;
+    fn CGEventGetFlags(event: CGEventRef) -> u64;
+    fn CGEventKeyboardGetUnicodeString(event: CGEventRef, max_length: usize, actual_length: *mut usize, buffer: *mut u16);
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct InputEvent {
+    /// Milliseconds since the recording started
+    pub offset_ms: f64,
+    /// "keyDown", "keyUp", "flagsChanged", "mouseDown", "mouseUp" or "scroll"
+    pub kind: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_code: Option<i64>,
+    /// Text the key produces with the current modifiers; omitted while secure input is active
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub characters: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub is_repeat: Option<bool>,
+    pub modifiers: Vec<&'static str>,
+    /// Global mouse position in points, top-left origin
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub x: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub y: Option<f64>,
+    /// 0 primary, 1 secondary, 2 and up for other buttons
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub button: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta_x: Option<i64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub delta_y: Option<i64>,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct InputEventFile<'a> {
+    version: u32,
+    /// Unix time in milliseconds that `offsetMs` counts from
+    started_at: u64,
+    events: &'a [InputEvent],
+}
+
+/// Whether this process may observe keyboard events of other apps
+pub fn accessibility_trusted() -> bool {
+    unsafe { AXIsProcessTrusted() }
+}
+
+unsafe fn describe(event_type: u32, event: CGEventRef, offset_ms: f64) -> Option<InputEvent> {
+    let kind = match event_type {
+        K_CG_EVENT_KEY_DOWN => "keyDown",
+        K_CG_EVENT_KEY_UP => "keyUp",
+        K_CG_EVENT_FLAGS_CHANGED => "flagsChanged",
+        K_CG_EVENT_LEFT_MOUSE_DOWN | K_CG_EVENT_RIGHT_MOUSE_DOWN | K_CG_EVENT_OTHER_MOUSE_DOWN => "mouseDown",
+        K_CG_EVENT_LEFT_MOUSE_UP | K_CG_EVENT_RIGHT_MOUSE_UP | K_CG_EVENT_OTHER_MOUSE_UP => "mouseUp",
+        K_CG_EVENT_SCROLL_WHEEL => "scroll",
+        _ => return None,
+    };
+    let flags = CGEventGetFlags(event);
+    let mut described = InputEvent {
+        offset_ms,
+        kind,
+        key_code: None,
+        characters: None,
+        is_repeat: None,
+        modifiers: MODIFIER_FLAGS.iter().filter(|(flag, _)| flags & flag != 0).map(|(_, name)| *name).collect(),
+        x: None,
+        y: None,
+        button: None,
+        delta_x: None,
+        delta_y: None,
+    };
+
+    match event_type {
+        K_CG_EVENT_KEY_DOWN | K_CG_EVENT_KEY_UP | K_CG_EVENT_FLAGS_CHANGED => {
+            described.key_code = Some(CGEventGetIntegerValueField(event, K_CG_KEYBOARD_EVENT_KEYCODE));
+            if event_type != K_CG_EVENT_FLAGS_CHANGED {
+                described.is_repeat = Some(CGEventGetIntegerValueField(event, K_CG_KEYBOARD_EVENT_AUTOREPEAT) != 0);
+                // Password fields turn on secure input; never write what is typed into them
+                if !is_secure_input_active() {
+                    let mut buffer = [0u16; 8];
+                    let mut length = 0usize;
+                    CGEventKeyboardGetUnicodeString(event, buffer.len(), &mut length, buffer.as_mut_ptr());
+                    if length > 0 {
+                        described.characters = Some(String::from_utf16_lossy(&buffer[..length.min(buffer.len())]));
+                    }
+                }
+            }
+        }
+        _ => {
+            let location = event_location(event);
+            described.x = Some(location.x);
+            described.y = Some(location.y);
+            if event_type == K_CG_EVENT_SCROLL_WHEEL {
+                described.delta_y = Some(CGEventGetIntegerValueField(event, K_CG_SCROLL_WHEEL_EVENT_DELTA_AXIS_1));
+                described.delta_x = Some(CGEventGetIntegerValueField(event, K_CG_SCROLL_WHEEL_EVENT_DELTA_AXIS_2));
+            } else {
+                described.button = Some(CGEventGetIntegerValueField(event, K_CG_MOUSE_EVENT_BUTTON_NUMBER));
+            }
+        }
+    }
+    Some(described)
+}
+
+/// Collects keyboard and mouse events while recording and writes them out when it stops
+pub struct InputEventRecorder {
+    path: String,
+    started_at: u64,
+    events: Arc<Mutex<Vec<InputEvent>>>,
+    tap: Option<EventTap>,
+}
+
+impl InputEventRecorder {
+    /// `<recording>.input.json` next to the recording
+    pub fn path_for(output_path: &str) -> String {
+        Path::new(output_path).with_extension("input.json").to_string_lossy().to_string()
+    }
+
+    pub fn start(path: &str) -> Result<Self> {
+        if !accessibility_trusted() {
+            return Err(capture_error(
+                CaptureErrorCode::PermissionDenied,
+                "record_input_events needs Accessibility permission (System Settings > Privacy & Security > Accessibility)",
+            ));
+        }
+
+        let events = Arc::new(Mutex::new(Vec::new()));
+        let sink = events.clone();
+        let started = Instant::now();
+        let mask = [
+            K_CG_EVENT_KEY_DOWN,
+            K_CG_EVENT_KEY_UP,
+            K_CG_EVENT_FLAGS_CHANGED,
+            K_CG_EVENT_LEFT_MOUSE_DOWN,
+            K_CG_EVENT_LEFT_MOUSE_UP,
+            K_CG_EVENT_RIGHT_MOUSE_DOWN,
+            K_CG_EVENT_RIGHT_MOUSE_UP,
+            K_CG_EVENT_OTHER_MOUSE_DOWN,
+            K_CG_EVENT_OTHER_MOUSE_UP,
+            K_CG_EVENT_SCROLL_WHEEL,
+        ]
+        .iter()
+        .fold(0u64, |mask, event_type| mask | event_mask_bit(*event_type));
+
+        let tap = EventTap::start(mask, Arc::new(move |event_type: u32, event: CGEventRef| {
+            let offset_ms = started.elapsed().as_secs_f64() * 1000.0;
+            if let Some(described) = unsafe { describe(event_type, event, offset_ms) } {
+                if let Ok(mut events) = sink.lock() {
+                    events.push(described);
+                }
+            }
+        }))?;
+
+        log::info!("Recording input events to {}", path);
+        Ok(Self {
+            path: path.to_string(),
+            started_at: now_millis(),
+            events,
+            tap: Some(tap),
+        })
+    }
+
+    /// Stop listening and write the sidecar; returns its path
+    pub fn finalize(&mut self) -> Result<String> {
+        if let Some(mut tap) = self.tap.take() {
+            tap.stop();
+        }
+        let events = self.events.lock().map(|events| events.clone()).unwrap_or_default();
+        let file = InputEventFile {
+            version: SIDECAR_VERSION,
+            started_at: self.started_at,
+            events: &events,
+        };
+        let json = serde_json::to_string_pretty(&file)
+            .map_err(|e| capture_error(CaptureErrorCode::Unknown, format!("Failed to serialize input events: {}", e)))?;
+        std::fs::write(&self.path, json)
+            .map_err(|e| io_error(format!("Failed to write {}", self.path), &e))?;
+
+        log::info!("Input event sidecar written: {} ({} events)", self.path, events.len());
+        Ok(self.path.clone())
+    }
+}
@@ -0,0 +1,107 @@
+// Global recording limits - clamps oversized configurations before a stream is created
+
+use crate::RecordingConfiguration;
+use napi::bindgen_prelude::*;
+use std::sync::Mutex;
+
+// Defaults used by the stream configuration when the caller leaves a field unset
+pub const DEFAULT_WIDTH: u32 = 1920;
+pub const DEFAULT_HEIGHT: u32 = 1080;
+pub const DEFAULT_FPS: u32 = 30;
+
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Limits {
+    pub max_width: Option<u32>,
+    pub max_height: Option<u32>,
+    pub max_fps: Option<u32>,
+}
+
+static LIMITS: Mutex<Limits> = Mutex::new(Limits {
+    max_width: None,
+    max_height: None,
+    max_fps: None,
+});
+
+pub fn set_limits(limits: Limits) {
+    log::info!("Recording limits set: max {}x{} @ {}fps",
+        limits.max_width.map(|v| v.to_string()).unwrap_or_else(|| "∞".to_string()),
+        limits.max_height.map(|v| v.to_string()).unwrap_or_else(|| "∞".to_string()),
+        limits.max_fps.map(|v| v.to_string()).unwrap_or_else(|| "∞".to_string()));
+
+    if let Ok(mut current) = LIMITS.lock() {
+        *current = limits;
+    }
+}
+
+pub fn get_limits() -> Limits {
+    LIMITS.lock().map(|guard| *guard).unwrap_or_default()
+}
+
+/// Output size for a `width` x `height` source after `scale` and then `max_output_width`,
+/// keeping the aspect ratio and the even dimensions encoders need
+pub fn scale_dimensions(width: u32, height: u32, scale: Option<f64>, max_output_width: Option<u32>) -> Result<(u32, u32)> {
+    let mut factor = match scale {
+        Some(scale) if scale > 0.0 && scale <= 1.0 => scale,
+        Some(scale) => return Err(Error::new(Status::InvalidArg, format!("scale {} must be above 0 and at most 1", scale))),
+        None => 1.0,
+    };
+    match max_output_width {
+        Some(0) => return Err(Error::new(Status::InvalidArg, "max_output_width must be positive")),
+        Some(max) if width as f64 * factor > max as f64 => factor = max as f64 / width as f64,
+        _ => {}
+    }
+    let scaled_width = (((width as f64 * factor).round() as u32) & !1).max(2);
+    let scaled_height = (((height as f64 * factor).round() as u32) & !1).max(2);
+    Ok((scaled_width, scaled_height))
+}
+
+/// Clamp the configuration in place against the global limits.
+/// Returns one warning per adjusted value so callers can surface them.
+pub fn clamp_configuration(config: &mut RecordingConfiguration) -> Vec<String> {
+    let limits = get_limits();
+    let mut warnings = Vec::new();
+
+    let width = config.width.unwrap_or(DEFAULT_WIDTH);
+    let height = config.height.unwrap_or(DEFAULT_HEIGHT);
+    let fps = config.fps.unwrap_or(DEFAULT_FPS);
+
+    // Scale both dimensions by the same factor so the aspect ratio is preserved
+    let width_scale = limits.max_width
+        .filter(|max| width > *max)
+        .map(|max| max as f64 / width as f64)
+        .unwrap_or(1.0);
+    let height_scale = limits.max_height
+        .filter(|max| height > *max)
+        .map(|max| max as f64 / height as f64)
+        .unwrap_or(1.0);
+    let scale = width_scale.min(height_scale);
+
+    if scale < 1.0 {
+        // Encoders require even dimensions
+        let clamped_width = (((width as f64 * scale) as u32) & !1).max(2);
+        let clamped_height = (((height as f64 * scale) as u32) & !1).max(2);
+
+        warnings.push(format!(
+            "Resolution {}x{} exceeds the configured limit, clamped to {}x{}",
+            width, height, clamped_width, clamped_height
+        ));
+        config.width = Some(clamped_width);
+        config.height = Some(clamped_height);
+    }
+
+    if let Some(max_fps) = limits.max_fps {
+        if fps > max_fps {
+            warnings.push(format!(
+                "Frame rate {}fps exceeds the configured limit, clamped to {}fps",
+                fps, max_fps
+            ));
+            config.fps = Some(max_fps);
+        }
+    }
+
+    for warning in &warnings {
+        log::warn!("{}", warning);
+    }
+
+    warnings
+}
@@ -0,0 +1,144 @@
+// Logging - every module logs through the `log` macros; the lines go to a JS handler when one is set
+
+use log::{Level, LevelFilter, Log, Metadata, Record};
+use napi::threadsafe_function::{ErrorStrategy, ThreadsafeFunction, ThreadsafeFunctionCallMode};
+use napi_derive::napi;
+use std::sync::{Mutex, Once};
+
+use crate::LogEntry;
+
+pub type LogHandler = ThreadsafeFunction<LogEntry, ErrorStrategy::Fatal>;
+
+static LOGGER: Logger = Logger;
+static INSTALL: Once = Once::new();
+// One handler per JS thread (the main one or a worker) that set one, keyed by its napi_env. Lines
+// come from capture threads that belong to no env, so every handler receives every line.
+static HANDLERS: Mutex<Vec<(usize, LogHandler)>> = Mutex::new(Vec::new());
+// napi_envs with a cleanup hook that removes their handler; napi refuses a second hook for the same env
+static CLEANUP_HOOKS: Mutex<Vec<usize>> = Mutex::new(Vec::new());
+
+/// Most verbose level that gets logged
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum LogLevel {
+    /// Nothing at all, e.g. for production builds
+    Off,
+    Error,
+    Warn,
+    Info,
+    /// Per-stream and per-frame detail
+    Debug,
+    Trace,
+}
+
+impl LogLevel {
+    fn filter(&self) -> LevelFilter {
+        match self {
+            LogLevel::Off => LevelFilter::Off,
+            LogLevel::Error => LevelFilter::Error,
+            LogLevel::Warn => LevelFilter::Warn,
+            LogLevel::Info => LevelFilter::Info,
+            LogLevel::Debug => LevelFilter::Debug,
+            LogLevel::Trace => LevelFilter::Trace,
+        }
+    }
+}
+
+impl From<Level> for LogLevel {
+    fn from(level: Level) -> Self {
+        match level {
+            Level::Error => LogLevel::Error,
+            Level::Warn => LogLevel::Warn,
+            Level::Info => LogLevel::Info,
+            Level::Debug => LogLevel::Debug,
+            Level::Trace => LogLevel::Trace,
+        }
+    }
+}
+
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+        // "whisperdesk_screencapturekit::screencapturekit::content" is just "content" to JS
+        let module = record.target().rsplit("::").next().unwrap_or_default();
+        if let Ok(handlers) = HANDLERS.lock() {
+            if !handlers.is_empty() {
+                // Broadcast: nothing ties a line to the env whose operation logged it
+                let message = record.args().to_string();
+                let timestamp_ms = super::session::now_millis() as f64;
+                for (_, handler) in handlers.iter() {
+                    handler.call(
+                        LogEntry {
+                            level: record.level().into(),
+                            module: module.to_string(),
+                            message: message.clone(),
+                            timestamp_ms,
+                        },
+                        ThreadsafeFunctionCallMode::NonBlocking,
+                    );
+                }
+                return;
+            }
+        }
+        println!("[{} {}] {}", record.level(), module, record.args());
+    }
+
+    fn flush(&self) {}
+}
+
+/// Route the `log` macros to stdout until a handler is set. The level starts from RUST_LOG,
+/// info when it isn't set.
+pub fn install() {
+    INSTALL.call_once(|| {
+        if log::set_logger(&LOGGER).is_ok() {
+            let level = std::env::var("RUST_LOG").ok().and_then(|level| level.parse().ok()).unwrap_or(LevelFilter::Info);
+            log::set_max_level(level);
+        }
+    });
+}
+
+/// Register `handler` as `env`'s handler, replacing the one it had (None removes it), and change
+/// the level when one is given. It receives every line, not just those from `env`'s
+/// operations. Lines go to stdout while no JS thread has a handler.
+pub fn set_handler(env: usize, handler: Option<LogHandler>, level: Option<LogLevel>) {
+    install();
+    if let Ok(mut handlers) = HANDLERS.lock() {
+        handlers.retain(|(owner, _)| *owner != env);
+        if let Some(handler) = handler {
+            handlers.push((env, handler));
+        }
+    }
+    if let Some(level) = level {
+        log::set_max_level(level.filter());
+    }
+}
+
+/// Forget `env`'s handler, once its thread is torn down and the handler can't be called anymore
+pub fn remove_handler(env: usize) {
+    if let Ok(mut handlers) = HANDLERS.lock() {
+        handlers.retain(|(owner, _)| *owner != env);
+    }
+    if let Ok(mut hooks) = CLEANUP_HOOKS.lock() {
+        hooks.retain(|owner| *owner != env);
+    }
+}
+
+/// True the first time it's asked for `env`, which should then register `remove_handler` as its
+/// cleanup hook
+pub fn claim_cleanup_hook(env: usize) -> bool {
+    match CLEANUP_HOOKS.lock() {
+        Ok(mut hooks) if !hooks.contains(&env) => {
+            hooks.push(env);
+            true
+        }
+        _ => false,
+    }
+}
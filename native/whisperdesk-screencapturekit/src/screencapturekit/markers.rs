@@ -0,0 +1,61 @@
+// Chapter markers - labelled points in a recording, kept as MP4 chapters and in a JSON sidecar
+
+use napi::bindgen_prelude::*;
+use serde::Serialize;
+use std::path::Path;
+
+use super::errors::{capture_error, io_error, CaptureErrorCode};
+
+const SIDECAR_VERSION: u32 = 1;
+const MAX_LABEL_CHARS: usize = 256;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Marker {
+    pub label: String,
+    /// Position in the video, in seconds from its first frame
+    pub time: f64,
+    /// Unix time in milliseconds the marker was added
+    pub created_at: u64,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct MarkerFile<'a> {
+    version: u32,
+    markers: &'a [Marker],
+}
+
+/// Trimmed label, rejected when empty or too long for a chapter title
+pub fn validate_label(label: &str) -> Result<String> {
+    let label = label.trim();
+    if label.is_empty() {
+        return Err(Error::new(Status::InvalidArg, "Marker label must not be empty"));
+    }
+    if label.chars().count() > MAX_LABEL_CHARS {
+        return Err(Error::new(
+            Status::InvalidArg,
+            format!("Marker label must be at most {} characters", MAX_LABEL_CHARS),
+        ));
+    }
+    Ok(label.to_string())
+}
+
+/// `<recording>.markers.json` next to the recording
+pub fn path_for(output_path: &str) -> String {
+    Path::new(output_path).with_extension("markers.json").to_string_lossy().to_string()
+}
+
+/// Write the sidecar; returns its path
+pub fn write_sidecar(path: &str, markers: &[Marker]) -> Result<String> {
+    let file = MarkerFile {
+        version: SIDECAR_VERSION,
+        markers,
+    };
+    let json = serde_json::to_string_pretty(&file)
+        .map_err(|e| capture_error(CaptureErrorCode::Unknown, format!("Failed to serialize markers: {}", e)))?;
+    std::fs::write(path, json).map_err(|e| io_error(format!("Failed to write {}", path), &e))?;
+
+    log::info!("Marker sidecar written: {} ({} markers)", path, markers.len());
+    Ok(path.to_string())
+}
@@ -0,0 +1,169 @@
+// Recording metadata - a JSON sidecar describing how a recording was made, for support tickets and
+// for lining transcripts up with the video
+
+use napi::bindgen_prelude::*;
+use serde::Serialize;
+use std::path::Path;
+
+use super::content::{DisplayInfo, ShareableContent, WindowInfo};
+use super::cursor::CaptureSource;
+use super::formats::{ColorSpace, PixelFormat, VideoCodec};
+use super::status;
+use super::errors::{capture_error, io_error, CaptureErrorCode};
+use crate::RecordingConfiguration;
+
+const SIDECAR_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingMetadata {
+    version: u32,
+    module_version: &'static str,
+    app_version: Option<String>,
+    os_version: Option<String>,
+    session_id: String,
+    output_path: String,
+    /// Unix times in milliseconds
+    started_at: u64,
+    stopped_at: Option<u64>,
+    source: Option<SourceMetadata>,
+    configuration: EffectiveConfiguration,
+    stats: Option<RecordingCounts>,
+}
+
+/// The source as it was when the recording started
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceMetadata {
+    /// "display" or "window"
+    kind: &'static str,
+    id: u32,
+    display: Option<DisplayInfo>,
+    window: Option<WindowInfo>,
+}
+
+/// What the stream was set up with once presets, limits and defaults were applied
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EffectiveConfiguration {
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub codec: VideoCodec,
+    pub pixel_format: PixelFormat,
+    pub color_space: ColorSpace,
+    pub shows_cursor: bool,
+    pub capture_video: bool,
+    pub capture_audio: bool,
+    pub microphone_device: Option<String>,
+    pub system_audio_backend: Option<String>,
+    /// x, y, width and height in points
+    pub crop: Option<[f64; 4]>,
+    pub timelapse_interval_secs: Option<f64>,
+    pub preset: Option<String>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RecordingCounts {
+    pub video_frames: u64,
+    /// Frames the encoder wasn't ready for and that are missing from the video
+    pub dropped_frames: u64,
+    pub audio_samples: u64,
+    /// Recorded time, pauses excluded
+    pub duration_ms: u64,
+}
+
+impl SourceMetadata {
+    /// Looked up through Core Graphics, so it doesn't wait on ScreenCaptureKit
+    pub fn capture(source: CaptureSource) -> Self {
+        match source {
+            CaptureSource::Display(id) => Self {
+                kind: "display",
+                id,
+                display: ShareableContent::system_display(id),
+                window: None,
+            },
+            CaptureSource::Window(id) => Self {
+                kind: "window",
+                id,
+                display: None,
+                window: ShareableContent::current_windows()
+                    .and_then(|windows| windows.into_iter().find(|window| window.id == id)),
+            },
+        }
+    }
+}
+
+impl EffectiveConfiguration {
+    /// The configuration with unset fields filled in the way the stream fills them
+    pub fn from_config(config: &RecordingConfiguration) -> Self {
+        let crop = match (config.crop_x, config.crop_y, config.crop_width, config.crop_height) {
+            (Some(x), Some(y), Some(width), Some(height)) => Some([x, y, width, height]),
+            _ => None,
+        };
+        Self {
+            width: config.width.unwrap_or(super::limits::DEFAULT_WIDTH),
+            height: config.height.unwrap_or(super::limits::DEFAULT_HEIGHT),
+            fps: config.fps.unwrap_or(super::limits::DEFAULT_FPS),
+            codec: config.codec.unwrap_or_default(),
+            pixel_format: config.pixel_format.unwrap_or_default(),
+            color_space: config.color_space.unwrap_or_default(),
+            shows_cursor: config.show_cursor.unwrap_or(true) && !config.hide_cursor_in_output.unwrap_or(false),
+            capture_video: config.capture_video.unwrap_or(true),
+            capture_audio: config.capture_audio.unwrap_or(false),
+            microphone_device: config.audio_device_id.clone(),
+            system_audio_backend: config.system_audio_backend.clone(),
+            crop,
+            timelapse_interval_secs: config.timelapse.as_ref().map(|t| t.capture_interval_secs),
+            preset: config.preset.clone(),
+            tags: config.tags.clone().unwrap_or_default(),
+        }
+    }
+}
+
+impl RecordingMetadata {
+    pub fn new(
+        session_id: &str,
+        started_at: u64,
+        source: Option<CaptureSource>,
+        config: &RecordingConfiguration,
+    ) -> Self {
+        Self {
+            version: SIDECAR_VERSION,
+            module_version: status::VERSION,
+            app_version: config.app_version.clone(),
+            os_version: status::os_version(),
+            session_id: session_id.to_string(),
+            output_path: config.output_path.clone(),
+            started_at,
+            stopped_at: None,
+            source: source.map(SourceMetadata::capture),
+            configuration: EffectiveConfiguration::from_config(config),
+            stats: None,
+        }
+    }
+
+    /// Fill in how the recording ended, just before writing
+    pub fn finish(&mut self, stopped_at: u64, counts: RecordingCounts) {
+        self.stopped_at = Some(stopped_at);
+        self.stats = Some(counts);
+    }
+
+    /// `<recording>.json` next to the recording
+    pub fn path_for(output_path: &str) -> String {
+        Path::new(output_path).with_extension("json").to_string_lossy().to_string()
+    }
+
+    /// Write the sidecar; returns its path
+    pub fn write(&self) -> Result<String> {
+        let path = Self::path_for(&self.output_path);
+        let json = serde_json::to_string_pretty(self)
+            .map_err(|e| capture_error(CaptureErrorCode::Unknown, format!("Failed to serialize recording metadata: {}", e)))?;
+        std::fs::write(&path, json).map_err(|e| io_error(format!("Failed to write {}", path), &e))?;
+
+        log::info!("Metadata sidecar written: {}", path);
+        Ok(path)
+    }
+}
@@ -1,11 +1,52 @@
 // ScreenCaptureKit implementation with objc2 bindings
 
 pub mod bindings;
+pub mod camera;
+pub mod cancel;
+pub mod canvas;
 pub mod content;
+pub mod content_cache;
+pub mod content_watch;
+pub mod cursor;
+pub mod cursor_track;
 pub mod audio;
+pub mod audio_tap;
 pub mod stream;
 pub mod delegate;
+pub mod diagnostics;
+pub mod disk_watch;
+pub mod display_watch;
 pub mod encoder;
+pub mod errors;
+pub mod focus_follow;
+pub mod formats;
+pub mod frames;
+pub mod hdr;
+pub mod input_events;
+pub mod limits;
+pub mod logging;
+pub mod markers;
+pub mod metadata;
+pub mod output;
+pub mod overlay;
+pub mod permission_watch;
+pub mod permissions;
+pub mod pixel_buffer;
+pub mod presets;
+pub mod preview;
+pub mod process_tap;
+pub mod recommend;
+pub mod replay;
+pub mod schedule;
+pub mod screenshot;
+pub mod secure_input;
+pub mod session;
+pub mod shutdown;
+pub mod status;
+pub mod timelapse;
+pub mod validate;
+pub mod window_track;
+pub mod zoom;
 
 // Re-export key types for easier access
 pub use content::ShareableContent;
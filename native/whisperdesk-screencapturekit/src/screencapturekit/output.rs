@@ -0,0 +1,197 @@
+// Output paths - filename templates, missing directories and name collisions, settled before
+// anything is written
+
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use std::ffi::{c_char, c_int, c_long};
+use std::path::Path;
+
+use super::content::ShareableContent;
+use super::cursor::CaptureSource;
+use super::errors::{capture_error, CaptureErrorCode};
+use crate::RecordingConfiguration;
+
+// Far beyond anything a person would leave lying around; past it something is wrong
+const MAX_SUFFIX: u32 = 9999;
+const TOKENS: &str = "{date}, {time}, {source} or {app}";
+
+/// What to do when the output file already exists
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, Default, PartialEq, Eq)]
+pub enum OutputCollision {
+    /// Replace the existing file
+    #[default]
+    Overwrite,
+    /// Add "-1", "-2", ... before the extension until the name is free
+    Suffix,
+    /// Refuse to start
+    Fail,
+}
+
+/// What the `{source}` and `{app}` tokens expand to
+#[derive(Debug, Clone, Default)]
+pub struct SourceNames {
+    /// Display name or window title
+    pub source: Option<String>,
+    /// Application owning the window, or the bundle ID of an "app:" capture
+    pub app: Option<String>,
+}
+
+impl SourceNames {
+    /// Looked up through Core Graphics, so naming the file doesn't wait on ScreenCaptureKit
+    pub fn for_screen_id(screen_id: &str) -> Self {
+        if let Some(bundle_id) = screen_id.strip_prefix("app:") {
+            return Self {
+                source: Some(bundle_id.to_string()),
+                app: Some(bundle_id.to_string()),
+            };
+        }
+        let screen_id = match screen_id.strip_prefix("region:") {
+            Some(display_id) => format!("display:{}", display_id),
+            None => screen_id.to_string(),
+        };
+        match CaptureSource::parse(&screen_id) {
+            Some(CaptureSource::Display(id)) => Self {
+                source: ShareableContent::system_display(id).map(|display| display.name),
+                app: None,
+            },
+            Some(CaptureSource::Window(id)) => {
+                let window = ShareableContent::current_windows()
+                    .and_then(|windows| windows.into_iter().find(|window| window.id == id));
+                Self {
+                    source: window.as_ref().map(|window| window.title.clone()).filter(|title| !title.is_empty()),
+                    app: window.and_then(|window| window.owner_name),
+                }
+            }
+            None => Self::default(),
+        }
+    }
+}
+
+#[repr(C)]
+struct Tm {
+    tm_sec: c_int,
+    tm_min: c_int,
+    tm_hour: c_int,
+    tm_mday: c_int,
+    tm_mon: c_int,
+    tm_year: c_int,
+    // Only there for the layout
+    _tm_wday: c_int,
+    _tm_yday: c_int,
+    _tm_isdst: c_int,
+    _tm_gmtoff: c_long,
+    _tm_zone: *const c_char,
+}
+
+extern "C" {
+    fn localtime_r(time: *const i64, result: *mut Tm) -> *mut Tm;
+}
+
+/// ("2024-05-01", "14-30-05") in local time; the time uses dashes since Finder shows colons as slashes
+fn local_date_time() -> (String, String) {
+    let now = (super::session::now_millis() / 1000) as i64;
+    let mut tm: Tm = unsafe { std::mem::zeroed() };
+    if unsafe { localtime_r(&now, &mut tm) }.is_null() {
+        return ("0000-00-00".to_string(), "00-00-00".to_string());
+    }
+    (
+        format!("{:04}-{:02}-{:02}", tm.tm_year + 1900, tm.tm_mon + 1, tm.tm_mday),
+        format!("{:02}-{:02}-{:02}", tm.tm_hour, tm.tm_min, tm.tm_sec),
+    )
+}
+
+/// A name that can't reach into another directory or upset Finder
+fn file_name_safe(value: &str) -> String {
+    let value: String = value.chars().map(|c| if matches!(c, '/' | ':' | '\0') { '-' } else { c }).collect();
+    let value = value.trim().trim_start_matches('.').to_string();
+    if value.is_empty() { "unknown".to_string() } else { value }
+}
+
+/// Replace the tokens in `template`; any other `{...}` is an error rather than a literal
+pub fn expand_template(template: &str, names: &SourceNames) -> Result<String> {
+    if !template.contains('{') {
+        return Ok(template.to_string());
+    }
+    let (date, time) = local_date_time();
+    let mut expanded = String::with_capacity(template.len());
+    let mut rest = template;
+    while let Some(start) = rest.find('{') {
+        expanded.push_str(&rest[..start]);
+        let end = rest[start..].find('}').map(|end| start + end).ok_or_else(|| {
+            capture_error(CaptureErrorCode::InvalidConfiguration, format!("Unclosed {{ in output_path {}", template))
+        })?;
+        let value = match &rest[start + 1..end] {
+            "date" => date.clone(),
+            "time" => time.clone(),
+            "source" => file_name_safe(names.source.as_deref().unwrap_or_default()),
+            "app" => file_name_safe(names.app.as_deref().unwrap_or_default()),
+            token => {
+                return Err(capture_error(
+                    CaptureErrorCode::InvalidConfiguration,
+                    format!("Unknown token {{{}}} in output_path; use {}", token, TOKENS),
+                ))
+            }
+        };
+        expanded.push_str(&value);
+        rest = &rest[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+/// `path` with "-1", "-2", ... before its extension, the first that doesn't exist
+fn free_path(path: &Path) -> Result<String> {
+    let stem = path.file_stem().map(|stem| stem.to_string_lossy().to_string()).unwrap_or_default();
+    let extension = path.extension().map(|extension| format!(".{}", extension.to_string_lossy())).unwrap_or_default();
+    (1..=MAX_SUFFIX)
+        .map(|n| path.with_file_name(format!("{}-{}{}", stem, n, extension)))
+        .find(|candidate| !candidate.exists())
+        .map(|candidate| candidate.to_string_lossy().to_string())
+        .ok_or_else(|| capture_error(CaptureErrorCode::InvalidConfiguration, format!("No free name left for {}", path.display())))
+}
+
+/// Expand `config.output_path` for `screen_id`, create its directory when asked to, and settle a
+/// name collision per `on_output_exists`. The config is left with the path that will be written.
+pub fn resolve(config: &mut RecordingConfiguration, screen_id: &str) -> Result<()> {
+    if config.output_path.is_empty() {
+        return Err(capture_error(CaptureErrorCode::InvalidConfiguration, "Output path is empty"));
+    }
+    let expanded = expand_template(&config.output_path, &SourceNames::for_screen_id(screen_id))?;
+    if expanded != config.output_path {
+        log::info!("Output path {} expanded to {}", config.output_path, expanded);
+    }
+
+    let path = Path::new(&expanded);
+    if let Some(directory) = path.parent().filter(|parent| !parent.as_os_str().is_empty()) {
+        if !directory.is_dir() {
+            if !config.create_output_directory.unwrap_or(false) {
+                return Err(capture_error(
+                    CaptureErrorCode::InvalidConfiguration,
+                    format!("Directory {} doesn't exist; set create_output_directory to create it", directory.display()),
+                ));
+            }
+            std::fs::create_dir_all(directory).map_err(|e| {
+                capture_error(CaptureErrorCode::InvalidConfiguration, format!("Can't create {}: {}", directory.display(), e))
+            })?;
+            log::info!("Created output directory {}", directory.display());
+        }
+    }
+
+    config.output_path = if path.exists() {
+        match config.on_output_exists.unwrap_or_default() {
+            OutputCollision::Overwrite => expanded,
+            OutputCollision::Suffix => {
+                let free = free_path(path)?;
+                log::info!("{} already exists - recording to {}", expanded, free);
+                free
+            }
+            OutputCollision::Fail => {
+                return Err(capture_error(CaptureErrorCode::InvalidConfiguration, format!("{} already exists", expanded)));
+            }
+        }
+    } else {
+        expanded
+    };
+    Ok(())
+}
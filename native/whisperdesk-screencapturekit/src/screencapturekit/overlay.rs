@@ -0,0 +1,315 @@
+// Click highlighting - ripples drawn into captured frames wherever the mouse is clicked
+
+use napi::bindgen_prelude::*;
+use objc2_core_video::CVPixelBuffer;
+use objc2_foundation::NSPoint;
+use std::ffi::c_void;
+use std::ptr;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::{Duration, Instant};
+
+use super::cursor::CaptureSource;
+use super::screenshot::CGRect;
+use super::formats::PixelFormat;
+use super::pixel_buffer::{
+    CVPixelBufferLockBaseAddress, CVPixelBufferUnlockBaseAddress, CVPixelBufferGetBaseAddress, CVPixelBufferGetBytesPerRow,
+    CVPixelBufferGetWidth, CVPixelBufferGetHeight, CVPixelBufferGetPixelFormatType,
+};
+use super::errors::{capture_error, CaptureErrorCode};
+
+pub type CGEventRef = *mut c_void;
+
+// CGEventTapLocation / CGEventTapPlacement / CGEventTapOptions
+const K_CG_SESSION_EVENT_TAP: u32 = 1;
+const K_CG_HEAD_INSERT_EVENT_TAP: u32 = 0;
+const K_CG_EVENT_TAP_OPTION_LISTEN_ONLY: u32 = 1;
+
+// CGEventType
+pub const K_CG_EVENT_LEFT_MOUSE_DOWN: u32 = 1;
+pub const K_CG_EVENT_RIGHT_MOUSE_DOWN: u32 = 3;
+pub const K_CG_EVENT_OTHER_MOUSE_DOWN: u32 = 25;
+const K_CG_EVENT_TAP_DISABLED_BY_TIMEOUT: u32 = 0xFFFF_FFFE;
+const K_CG_EVENT_TAP_DISABLED_BY_USER_INPUT: u32 = 0xFFFF_FFFF;
+
+// The tap thread checks for a stop request this often
+const RUN_LOOP_SLICE_SECONDS: f64 = 0.25;
+// Window sources move; re-read their bounds this often
+const WINDOW_BOUNDS_REFRESH: Duration = Duration::from_millis(500);
+
+// Ripple geometry in points; it grows from the first radius to the second while fading out
+const RIPPLE_DURATION: Duration = Duration::from_millis(450);
+const RIPPLE_START_RADIUS: f64 = 6.0;
+const RIPPLE_END_RADIUS: f64 = 28.0;
+const RIPPLE_RING_WIDTH: f64 = 3.0;
+const RIPPLE_MAX_ALPHA: f64 = 0.85;
+// The inside of the ring is tinted at this fraction of the ring's opacity
+const RIPPLE_FILL_ALPHA: f64 = 0.25;
+
+// BGR colours: yellow for the primary button, blue for the others
+const PRIMARY_COLOR: [u8; 3] = [0x2e, 0xc8, 0xff];
+const SECONDARY_COLOR: [u8; 3] = [0xff, 0x8a, 0x2e];
+
+extern "C" {
+    fn CGEventTapCreate(
+        tap: u32,
+        place: u32,
+        options: u32,
+        events_of_interest: u64,
+        callback: extern "C" fn(*mut c_void, u32, CGEventRef, *mut c_void) -> CGEventRef,
+        user_info: *mut c_void,
+    ) -> *mut c_void;
+    fn CGEventTapEnable(tap: *mut c_void, enable: bool);
+    fn CGEventGetLocation(event: CGEventRef) -> NSPoint;
+    fn CFMachPortCreateRunLoopSource(allocator: *const c_void, port: *mut c_void, order: isize) -> *mut c_void;
+    fn CFMachPortInvalidate(port: *mut c_void);
+    fn CFRunLoopGetCurrent() -> *mut c_void;
+    fn CFRunLoopAddSource(run_loop: *mut c_void, source: *mut c_void, mode: *const c_void);
+    fn CFRunLoopRemoveSource(run_loop: *mut c_void, source: *mut c_void, mode: *const c_void);
+    fn CFRunLoopRunInMode(mode: *const c_void, seconds: f64, return_after_source_handled: u8) -> i32;
+    fn CFRelease(cf: *const c_void);
+    static kCFRunLoopDefaultMode: *const c_void;
+}
+
+/// Bit for `event_type` in a CGEventMask
+pub fn event_mask_bit(event_type: u32) -> u64 {
+    1u64 << event_type
+}
+
+/// Global location of a mouse event, top-left origin
+pub unsafe fn event_location(event: CGEventRef) -> NSPoint {
+    CGEventGetLocation(event)
+}
+
+/// Called on the tap thread with the CGEventType and the event, which is only valid during the call
+pub type EventTapHandler = Arc<dyn Fn(u32, CGEventRef) + Send + Sync>;
+
+struct TapContext {
+    handler: EventTapHandler,
+    port: *mut c_void,
+}
+
+extern "C" fn tap_callback(_proxy: *mut c_void, event_type: u32, event: CGEventRef, user_info: *mut c_void) -> CGEventRef {
+    let context = unsafe { &*(user_info as *const TapContext) };
+    // The system switches slow taps off; a listen-only tap can simply be switched back on
+    if event_type == K_CG_EVENT_TAP_DISABLED_BY_TIMEOUT || event_type == K_CG_EVENT_TAP_DISABLED_BY_USER_INPUT {
+        unsafe { CGEventTapEnable(context.port, true) };
+        return event;
+    }
+    (context.handler)(event_type, event);
+    event
+}
+
+/// Listen-only CGEventTap on its own run loop thread
+pub struct EventTap {
+    stop_sender: Option<mpsc::Sender<()>>,
+    thread: Option<thread::JoinHandle<()>>,
+}
+
+impl EventTap {
+    /// Fails when the system refuses the tap, e.g. keyboard events without Input Monitoring or
+    /// Accessibility permission
+    pub fn start(events_of_interest: u64, handler: EventTapHandler) -> Result<Self> {
+        super::permissions::guard_silent(super::permissions::PrivacyPane::Accessibility)?;
+        let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+        let (ready_sender, ready_receiver) = mpsc::channel::<bool>();
+
+        let thread = thread::spawn(move || unsafe {
+            let context = Box::into_raw(Box::new(TapContext { handler, port: ptr::null_mut() }));
+            let port = CGEventTapCreate(
+                K_CG_SESSION_EVENT_TAP,
+                K_CG_HEAD_INSERT_EVENT_TAP,
+                K_CG_EVENT_TAP_OPTION_LISTEN_ONLY,
+                events_of_interest,
+                tap_callback,
+                context as *mut c_void,
+            );
+            if port.is_null() {
+                drop(Box::from_raw(context));
+                let _ = ready_sender.send(false);
+                return;
+            }
+            (*context).port = port;
+
+            let run_loop = CFRunLoopGetCurrent();
+            let source = CFMachPortCreateRunLoopSource(ptr::null(), port, 0);
+            CFRunLoopAddSource(run_loop, source, kCFRunLoopDefaultMode);
+            CGEventTapEnable(port, true);
+            let _ = ready_sender.send(true);
+
+            while let Err(mpsc::TryRecvError::Empty) = stop_receiver.try_recv() {
+                CFRunLoopRunInMode(kCFRunLoopDefaultMode, RUN_LOOP_SLICE_SECONDS, 0);
+            }
+
+            CGEventTapEnable(port, false);
+            CFRunLoopRemoveSource(run_loop, source, kCFRunLoopDefaultMode);
+            CFMachPortInvalidate(port);
+            CFRelease(source);
+            CFRelease(port);
+            drop(Box::from_raw(context));
+        });
+
+        match ready_receiver.recv() {
+            Ok(true) => Ok(Self {
+                stop_sender: Some(stop_sender),
+                thread: Some(thread),
+            }),
+            _ => {
+                let _ = thread.join();
+                Err(capture_error(
+                    CaptureErrorCode::PermissionDenied,
+                    "Failed to create event tap (Input Monitoring or Accessibility permission?)",
+                ))
+            }
+        }
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(sender) = self.stop_sender.take() {
+            let _ = sender.send(());
+        }
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for EventTap {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+struct Click {
+    x: f64,
+    y: f64,
+    at: Instant,
+    primary: bool,
+}
+
+/// Draws a fading ripple into every video frame for each recent mouse click
+pub struct ClickHighlighter {
+    source: CaptureSource,
+    clicks: Arc<Mutex<Vec<Click>>>,
+    bounds: Option<CGRect>,
+    bounds_read_at: Option<Instant>,
+    _tap: EventTap,
+}
+
+impl ClickHighlighter {
+    pub fn start(source: CaptureSource) -> Result<Self> {
+        let clicks = Arc::new(Mutex::new(Vec::new()));
+        let sink = clicks.clone();
+        let mask = event_mask_bit(K_CG_EVENT_LEFT_MOUSE_DOWN)
+            | event_mask_bit(K_CG_EVENT_RIGHT_MOUSE_DOWN)
+            | event_mask_bit(K_CG_EVENT_OTHER_MOUSE_DOWN);
+        let tap = EventTap::start(mask, Arc::new(move |event_type: u32, event: CGEventRef| {
+            let location = unsafe { event_location(event) };
+            if let Ok(mut clicks) = sink.lock() {
+                clicks.push(Click {
+                    x: location.x,
+                    y: location.y,
+                    at: Instant::now(),
+                    primary: event_type == K_CG_EVENT_LEFT_MOUSE_DOWN,
+                });
+            }
+        }))?;
+
+        log::info!("Click highlighting enabled");
+        Ok(Self {
+            source,
+            clicks,
+            bounds: None,
+            bounds_read_at: None,
+            _tap: tap,
+        })
+    }
+
+    fn current_bounds(&mut self) -> Option<CGRect> {
+        let stale = match (self.source, self.bounds_read_at) {
+            (_, None) => true,
+            (CaptureSource::Window(_), Some(read_at)) => read_at.elapsed() >= WINDOW_BOUNDS_REFRESH,
+            (CaptureSource::Display(_), Some(_)) => false,
+        };
+        if stale {
+            self.bounds = self.source.bounds();
+            self.bounds_read_at = Some(Instant::now());
+        }
+        self.bounds
+    }
+
+    /// Draw the ripples of recent clicks into a BGRA frame in place
+    pub unsafe fn composite(&mut self, pixel_buffer: *mut CVPixelBuffer) {
+        if pixel_buffer.is_null() || CVPixelBufferGetPixelFormatType(pixel_buffer) != PixelFormat::Bgra.four_cc() {
+            return;
+        }
+
+        let clicks: Vec<Click> = match self.clicks.lock() {
+            Ok(mut clicks) => {
+                clicks.retain(|click| click.at.elapsed() < RIPPLE_DURATION);
+                clicks.clone()
+            }
+            Err(_) => return,
+        };
+        if clicks.is_empty() {
+            return;
+        }
+        let bounds = match self.current_bounds() {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let frame_width = CVPixelBufferGetWidth(pixel_buffer);
+        let frame_height = CVPixelBufferGetHeight(pixel_buffer);
+        let points_to_pixels = frame_width as f64 / bounds.width;
+
+        if CVPixelBufferLockBaseAddress(pixel_buffer, 0) != 0 {
+            return;
+        }
+        let base = CVPixelBufferGetBaseAddress(pixel_buffer) as *mut u8;
+        let bytes_per_row = CVPixelBufferGetBytesPerRow(pixel_buffer);
+
+        if !base.is_null() {
+            for click in clicks {
+                let progress = click.at.elapsed().as_secs_f64() / RIPPLE_DURATION.as_secs_f64();
+                let radius = (RIPPLE_START_RADIUS + (RIPPLE_END_RADIUS - RIPPLE_START_RADIUS) * progress) * points_to_pixels;
+                let half_ring = RIPPLE_RING_WIDTH * points_to_pixels / 2.0;
+                let opacity = RIPPLE_MAX_ALPHA * (1.0 - progress);
+                let color = if click.primary { PRIMARY_COLOR } else { SECONDARY_COLOR };
+                let center_x = (click.x - bounds.x) * points_to_pixels;
+                let center_y = (click.y - bounds.y) * frame_height as f64 / bounds.height;
+
+                let reach = radius + half_ring + 1.0;
+                let min_x = (center_x - reach).floor().max(0.0) as usize;
+                let max_x = ((center_x + reach).ceil().max(0.0) as usize).min(frame_width);
+                let min_y = (center_y - reach).floor().max(0.0) as usize;
+                let max_y = ((center_y + reach).ceil().max(0.0) as usize).min(frame_height);
+
+                for y in min_y..max_y {
+                    let row = base.add(y * bytes_per_row);
+                    for x in min_x..max_x {
+                        let dx = x as f64 + 0.5 - center_x;
+                        let dy = y as f64 + 0.5 - center_y;
+                        let distance = (dx * dx + dy * dy).sqrt();
+                        // One pixel of anti-aliasing on either edge of the ring
+                        let ring = (half_ring + 0.5 - (distance - radius).abs()).clamp(0.0, 1.0);
+                        let fill = if distance < radius { RIPPLE_FILL_ALPHA } else { 0.0 };
+                        let alpha = opacity * ring.max(fill);
+                        if alpha <= 0.0 {
+                            continue;
+                        }
+                        let pixel = row.add(x * 4);
+                        for channel in 0..3 {
+                            let d = *pixel.add(channel) as f64;
+                            *pixel.add(channel) = (color[channel] as f64 * alpha + d * (1.0 - alpha)).round() as u8;
+                        }
+                    }
+                }
+            }
+        }
+
+        CVPixelBufferUnlockBaseAddress(pixel_buffer, 0);
+    }
+}
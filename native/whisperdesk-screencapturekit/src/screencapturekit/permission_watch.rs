@@ -0,0 +1,109 @@
+// Permission watcher - poll the privacy permissions and report the ones that were granted or taken
+// away since the last poll
+
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::permissions::{self, PermissionState};
+
+pub const DEFAULT_INTERVAL_MS: u32 = 1000;
+// Each poll is three cheap lookups, but nobody toggles a switch in System Settings faster than this
+pub const MIN_INTERVAL_MS: u32 = 250;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PermissionSnapshot {
+    pub screen_recording: PermissionState,
+    pub microphone: PermissionState,
+    pub camera: PermissionState,
+}
+
+impl PermissionSnapshot {
+    /// Read without prompting
+    pub fn current() -> Self {
+        Self {
+            screen_recording: permissions::screen_recording_authorization(),
+            microphone: permissions::microphone_authorization(),
+            camera: permissions::camera_authorization(),
+        }
+    }
+
+    fn entries(&self) -> [(&'static str, PermissionState); 3] {
+        [
+            ("screenRecording", self.screen_recording),
+            ("microphone", self.microphone),
+            ("camera", self.camera),
+        ]
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct PermissionChange {
+    /// "screenRecording", "microphone" or "camera"
+    pub permission: &'static str,
+    pub previous: PermissionState,
+    pub current: PermissionState,
+}
+
+pub type PermissionChangeHandler = Arc<dyn Fn(PermissionChange) + Send + Sync>;
+
+/// The permissions whose state differs between two snapshots, in `PermissionSnapshot` field order
+pub fn diff_permissions(old: &PermissionSnapshot, new: &PermissionSnapshot) -> Vec<PermissionChange> {
+    old.entries()
+        .into_iter()
+        .zip(new.entries())
+        .filter(|((_, previous), (_, current))| previous != current)
+        .map(|((permission, previous), (_, current))| PermissionChange { permission, previous, current })
+        .collect()
+}
+
+/// Background poller; stops when `stop` is called or the watcher is dropped
+pub struct PermissionWatcher {
+    stop_sender: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PermissionWatcher {
+    pub fn start(interval_ms: u32, on_change: PermissionChangeHandler) -> Self {
+        let interval = Duration::from_millis(interval_ms.max(MIN_INTERVAL_MS) as u64);
+        let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+        log::info!("Watching permissions every {} ms", interval.as_millis());
+
+        let thread = thread::spawn(move || {
+            let mut known = PermissionSnapshot::current();
+            loop {
+                match stop_receiver.recv_timeout(interval) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    _ => break,
+                }
+                let current = PermissionSnapshot::current();
+                for change in diff_permissions(&known, &current) {
+                    log::info!("{} permission changed: {} -> {}", change.permission, change.previous.as_ref(), change.current.as_ref());
+                    on_change(change);
+                }
+                known = current;
+            }
+            log::info!("Permission watcher stopped");
+        });
+
+        Self {
+            stop_sender: Some(stop_sender),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        // Dropping the sender wakes the poller immediately
+        self.stop_sender.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for PermissionWatcher {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,306 @@
+// Privacy permissions - microphone and camera access through AVCaptureDevice's authorization API,
+// screen recording through Core Graphics, and the System Settings panes where they are granted
+
+use block2::RcBlock;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use objc2::rc::Retained;
+use objc2::runtime::{AnyObject, Bool};
+use objc2::{msg_send, class};
+use objc2_foundation::{NSString, NSURL};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::time::Duration;
+
+use super::encoder::{AVMediaTypeAudio, AVMediaTypeVideo};
+use super::errors::{capture_error, CaptureErrorCode};
+use super::process_tap::SystemAudioBackend;
+use crate::RecordingConfiguration;
+
+// AVAuthorizationStatus
+const AV_AUTHORIZATION_NOT_DETERMINED: i64 = 0;
+const AV_AUTHORIZATION_RESTRICTED: i64 = 1;
+const AV_AUTHORIZATION_DENIED: i64 = 2;
+
+const PRIVACY_SETTINGS_URL: &str = "x-apple.systempreferences:com.apple.preference.security";
+
+// Set with `set_silent`; while it is on nothing here brings up a permission prompt
+static SILENT: AtomicBool = AtomicBool::new(false);
+// Set once this process has created a process tap; macOS only prompts for the first one
+static SYSTEM_AUDIO_REQUESTED: AtomicBool = AtomicBool::new(false);
+// Set once this process has asked for screen recording; macOS prompts for it only the first time
+static SCREEN_RECORDING_REQUESTED: AtomicBool = AtomicBool::new(false);
+
+extern "C" {
+    fn CGPreflightScreenCaptureAccess() -> bool;
+    fn CGRequestScreenCaptureAccess() -> bool;
+}
+
+// Long enough for someone to read the prompt; a prompt left open past it is treated as unanswered
+const PERMISSION_PROMPT_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Whether a permission was granted, and if not whether asking can still change that
+#[napi(string_enum = "camelCase")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum PermissionState {
+    /// Never answered; requesting it shows the system prompt
+    NotDetermined,
+    /// Refused; only System Settings can change it
+    Denied,
+    /// Blocked by a configuration profile or parental controls; the user can't change it
+    Restricted,
+    Granted,
+}
+
+impl PermissionState {
+    pub fn is_granted(self) -> bool {
+        self == Self::Granted
+    }
+
+    /// Denied or restricted, so capturing would fail or record nothing
+    pub fn is_refused(self) -> bool {
+        matches!(self, Self::Denied | Self::Restricted)
+    }
+}
+
+impl AsRef<str> for PermissionState {
+    fn as_ref(&self) -> &str {
+        match self {
+            Self::NotDetermined => "notDetermined",
+            Self::Denied => "denied",
+            Self::Restricted => "restricted",
+            Self::Granted => "granted",
+        }
+    }
+}
+
+/// A pane under System Settings > Privacy & Security
+#[napi(string_enum = "kebab-case")]
+#[derive(Debug, PartialEq, Eq)]
+pub enum PrivacyPane {
+    ScreenRecording,
+    Microphone,
+    Camera,
+    /// Needed for `record_input_events`
+    Accessibility,
+    /// "System Audio Recording Only", listed with screen recording; needed for Core Audio process taps
+    SystemAudio,
+}
+
+impl PrivacyPane {
+    fn name(self) -> &'static str {
+        match self {
+            Self::ScreenRecording => "Screen recording",
+            Self::Microphone => "Microphone",
+            Self::Camera => "Camera",
+            Self::Accessibility => "Accessibility",
+            Self::SystemAudio => "System audio recording",
+        }
+    }
+
+    fn anchor(self) -> &'static str {
+        match self {
+            Self::ScreenRecording => "Privacy_ScreenCapture",
+            Self::Microphone => "Privacy_Microphone",
+            Self::Camera => "Privacy_Camera",
+            Self::Accessibility => "Privacy_Accessibility",
+            Self::SystemAudio => "Privacy_ScreenCapture",
+        }
+    }
+}
+
+fn authorization(media_type: &str) -> PermissionState {
+    unsafe {
+        let media_type = NSString::from_str(media_type);
+        let status: i64 = msg_send![class!(AVCaptureDevice), authorizationStatusForMediaType: &*media_type];
+        match status {
+            AV_AUTHORIZATION_NOT_DETERMINED => PermissionState::NotDetermined,
+            AV_AUTHORIZATION_RESTRICTED => PermissionState::Restricted,
+            AV_AUTHORIZATION_DENIED => PermissionState::Denied,
+            _ => PermissionState::Granted,
+        }
+    }
+}
+
+/// Show the system prompt for `media_type` if it was never answered; true when granted.
+/// Blocks until the prompt is answered.
+fn request_access(media_type: &str, device: &str) -> Result<bool> {
+    match authorization(media_type) {
+        PermissionState::NotDetermined if is_silent() => {
+            log::info!("Not prompting for {} permission in silent mode", device);
+            return Ok(false);
+        }
+        PermissionState::NotDetermined => {}
+        state => return Ok(state.is_granted()),
+    }
+    let (sender, receiver) = std::sync::mpsc::channel();
+    let handler = RcBlock::new(move |granted: Bool| {
+        let _ = sender.send(granted.as_bool());
+    });
+    unsafe {
+        let media_type = NSString::from_str(media_type);
+        let _: () = msg_send![
+            class!(AVCaptureDevice),
+            requestAccessForMediaType: &*media_type,
+            completionHandler: &*handler
+        ];
+    }
+    let granted = receiver.recv_timeout(PERMISSION_PROMPT_TIMEOUT).map_err(|_| {
+        capture_error(CaptureErrorCode::Timeout, format!("The {} permission prompt wasn't answered", device))
+    })?;
+    log::info!("{} permission after request: {}", device, granted);
+    Ok(granted)
+}
+
+/// Core Graphics only says whether screen recording is granted. Until this process has asked for
+/// it, a refusal is reported as NotDetermined: requesting then either prompts or, when it was
+/// denied in an earlier run, returns at once, after which it is reported as Denied.
+/// Restricted is never reported, since a profile's block looks like a denial.
+pub fn screen_recording_authorization() -> PermissionState {
+    if unsafe { CGPreflightScreenCaptureAccess() } {
+        PermissionState::Granted
+    } else if SCREEN_RECORDING_REQUESTED.load(Ordering::Relaxed) {
+        PermissionState::Denied
+    } else {
+        PermissionState::NotDetermined
+    }
+}
+
+/// Prompt for screen recording the first time; true when granted. Granting it usually only takes
+/// effect after the app restarts.
+pub fn request_screen_recording_access() -> bool {
+    if is_silent() {
+        log::info!("Not prompting for screen recording permission in silent mode");
+        return unsafe { CGPreflightScreenCaptureAccess() };
+    }
+    SCREEN_RECORDING_REQUESTED.store(true, Ordering::Relaxed);
+    let granted = unsafe { CGRequestScreenCaptureAccess() };
+    log::info!("screen recording permission after request: {}", granted);
+    granted
+}
+
+/// Accessibility is either granted or not; macOS has no prompt state for it
+pub fn accessibility_authorization() -> PermissionState {
+    if super::input_events::accessibility_trusted() {
+        PermissionState::Granted
+    } else {
+        PermissionState::Denied
+    }
+}
+
+/// In silent mode the request functions report the current state instead of prompting, and
+/// anything that would make macOS prompt on first use fails with PermissionDenied instead
+pub fn set_silent(silent: bool) {
+    SILENT.store(silent, Ordering::Relaxed);
+    log::info!("Permission prompts {}", if silent { "suppressed" } else { "allowed" });
+}
+
+pub fn is_silent() -> bool {
+    SILENT.load(Ordering::Relaxed)
+}
+
+/// For calls that would make macOS prompt for `pane` on first use: in silent mode they may only go
+/// ahead once it is granted
+pub fn guard_silent(pane: PrivacyPane) -> Result<()> {
+    if !is_silent() {
+        return Ok(());
+    }
+    match state_for(pane) {
+        PermissionState::Granted => Ok(()),
+        state => Err(capture_error(
+            CaptureErrorCode::PermissionDenied,
+            format!("{} permission is {} and silent mode won't prompt for it", pane.name(), state.as_ref()),
+        )),
+    }
+}
+
+/// macOS has no public way to read this one: NotDetermined until this process has created a
+/// process tap, Granted afterwards. A denied tap records silence rather than failing.
+pub fn system_audio_authorization() -> PermissionState {
+    if SYSTEM_AUDIO_REQUESTED.load(Ordering::Relaxed) {
+        PermissionState::Granted
+    } else {
+        PermissionState::NotDetermined
+    }
+}
+
+/// Called once a process tap exists, after which no further tap prompts
+pub fn note_system_audio_requested() {
+    SYSTEM_AUDIO_REQUESTED.store(true, Ordering::Relaxed);
+}
+
+/// The permissions a recording with `config` needs, each with the option that needs it. Screen
+/// recording is always there, since even an audio-only recording runs an SCStream.
+pub fn required_for(config: &RecordingConfiguration) -> Vec<(PrivacyPane, &'static str)> {
+    let mut required = vec![(PrivacyPane::ScreenRecording, "screen capture")];
+    if config.audio_device_id.is_some() {
+        required.push((PrivacyPane::Microphone, "audio_device_id"));
+    }
+    let tap_backend = SystemAudioBackend::parse(config.system_audio_backend.as_deref());
+    if config.capture_audio.unwrap_or(false) && matches!(tap_backend, Ok(SystemAudioBackend::CoreAudioTap)) {
+        required.push((PrivacyPane::SystemAudio, "system_audio_backend"));
+    }
+    if config.camera_pip.is_some() {
+        required.push((PrivacyPane::Camera, "camera_pip"));
+    }
+    if config.record_input_events.unwrap_or(false) {
+        required.push((PrivacyPane::Accessibility, "record_input_events"));
+    } else if config.highlight_clicks.unwrap_or(false) {
+        required.push((PrivacyPane::Accessibility, "highlight_clicks"));
+    }
+    required
+}
+
+/// `guard_silent` for every permission a recording with `config` needs
+pub fn guard_silent_for(config: &RecordingConfiguration) -> Result<()> {
+    if !is_silent() {
+        return Ok(());
+    }
+    required_for(config).into_iter().try_for_each(|(pane, _)| guard_silent(pane))
+}
+
+/// The state behind `pane`, read without prompting
+pub fn state_for(pane: PrivacyPane) -> PermissionState {
+    match pane {
+        PrivacyPane::ScreenRecording => screen_recording_authorization(),
+        PrivacyPane::Microphone => microphone_authorization(),
+        PrivacyPane::Camera => camera_authorization(),
+        PrivacyPane::Accessibility => accessibility_authorization(),
+        PrivacyPane::SystemAudio => system_audio_authorization(),
+    }
+}
+
+pub fn microphone_authorization() -> PermissionState {
+    authorization(AVMediaTypeAudio)
+}
+
+pub fn request_microphone_access() -> Result<bool> {
+    request_access(AVMediaTypeAudio, "microphone")
+}
+
+pub fn camera_authorization() -> PermissionState {
+    authorization(AVMediaTypeVideo)
+}
+
+pub fn request_camera_access() -> Result<bool> {
+    request_access(AVMediaTypeVideo, "camera")
+}
+
+/// Bring up System Settings on `pane`. It opens without waiting for anything, and System Settings
+/// falls back to the Privacy & Security list on a macOS that names the pane differently.
+pub fn open_settings(pane: PrivacyPane) -> Result<()> {
+    let url = format!("{}?{}", PRIVACY_SETTINGS_URL, pane.anchor());
+    let opened = unsafe {
+        let url_string = NSString::from_str(&url);
+        let ns_url: Option<Retained<NSURL>> = msg_send![class!(NSURL), URLWithString: &*url_string];
+        let ns_url = ns_url.ok_or_else(|| capture_error(CaptureErrorCode::Unknown, format!("Invalid settings URL {}", url)))?;
+        let workspace: Option<Retained<AnyObject>> = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let workspace = workspace.ok_or_else(|| capture_error(CaptureErrorCode::Unknown, "NSWorkspace is unavailable"))?;
+        let opened: Bool = msg_send![&*workspace, openURL: &*ns_url];
+        opened.as_bool()
+    };
+    if !opened {
+        return Err(capture_error(CaptureErrorCode::Unknown, format!("System Settings didn't open {}", url)));
+    }
+    log::info!("Opened {}", url);
+    Ok(())
+}
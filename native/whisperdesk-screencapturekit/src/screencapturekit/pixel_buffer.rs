@@ -0,0 +1,22 @@
+// CoreVideo pixel buffer functions, shared by everything that reads or draws into frames
+
+use objc2_core_video::CVPixelBuffer;
+use std::ffi::c_void;
+
+extern "C" {
+    pub fn CVPixelBufferCreate(
+        allocator: *const c_void,
+        width: usize,
+        height: usize,
+        pixel_format: u32,
+        attributes: *const c_void,
+        pixel_buffer_out: *mut *mut CVPixelBuffer,
+    ) -> i32;
+    pub fn CVPixelBufferLockBaseAddress(pixel_buffer: *mut CVPixelBuffer, flags: u64) -> i32;
+    pub fn CVPixelBufferUnlockBaseAddress(pixel_buffer: *mut CVPixelBuffer, flags: u64) -> i32;
+    pub fn CVPixelBufferGetBaseAddress(pixel_buffer: *mut CVPixelBuffer) -> *mut c_void;
+    pub fn CVPixelBufferGetBytesPerRow(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    pub fn CVPixelBufferGetWidth(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    pub fn CVPixelBufferGetHeight(pixel_buffer: *mut CVPixelBuffer) -> usize;
+    pub fn CVPixelBufferGetPixelFormatType(pixel_buffer: *mut CVPixelBuffer) -> u32;
+}
@@ -0,0 +1,103 @@
+// Recording presets - named setups that expand into a full recording configuration
+
+use crate::RecordingConfiguration;
+use napi::bindgen_prelude::*;
+
+/// The values a preset fills in; anything the caller set explicitly is kept
+#[derive(Debug, Clone, Copy)]
+pub struct Preset {
+    pub name: &'static str,
+    pub description: &'static str,
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub show_cursor: bool,
+    pub capture_audio: bool,
+    pub highlight_clicks: bool,
+    pub write_wav_sidecar: bool,
+}
+
+pub const PRESETS: [Preset; 4] = [
+    Preset {
+        name: "meeting",
+        description: "1080p at 15 fps with audio and a 16 kHz WAV ready for transcription",
+        width: 1920,
+        height: 1080,
+        fps: 15,
+        show_cursor: true,
+        capture_audio: true,
+        highlight_clicks: false,
+        write_wav_sidecar: true,
+    },
+    Preset {
+        name: "tutorial-1080p30",
+        description: "1080p at 30 fps with audio and highlighted clicks",
+        width: 1920,
+        height: 1080,
+        fps: 30,
+        show_cursor: true,
+        capture_audio: true,
+        highlight_clicks: true,
+        write_wav_sidecar: false,
+    },
+    Preset {
+        name: "high-quality-4k60",
+        description: "4K at 60 fps with audio; needs a fast encoder and plenty of disk",
+        width: 3840,
+        height: 2160,
+        fps: 60,
+        show_cursor: true,
+        capture_audio: true,
+        highlight_clicks: false,
+        write_wav_sidecar: false,
+    },
+    Preset {
+        name: "small-file",
+        description: "720p at 15 fps with audio, for sharing",
+        width: 1280,
+        height: 720,
+        fps: 15,
+        show_cursor: true,
+        capture_audio: true,
+        highlight_clicks: false,
+        write_wav_sidecar: false,
+    },
+];
+
+pub fn find_preset(name: &str) -> Option<&'static Preset> {
+    PRESETS.iter().find(|preset| preset.name == name)
+}
+
+/// Fill the unset fields of `config` from its `preset`, if any. Explicit fields win so a preset
+/// can be tweaked, and the result goes through the same validation as a hand-written configuration.
+pub fn apply_preset(config: &mut RecordingConfiguration) -> Result<()> {
+    let name = match config.preset.take() {
+        Some(name) => name,
+        None => return Ok(()),
+    };
+    let preset = find_preset(&name).ok_or_else(|| {
+        let names: Vec<&str> = PRESETS.iter().map(|preset| preset.name).collect();
+        Error::new(
+            Status::InvalidArg,
+            format!("Unknown preset \"{}\" (expected one of {})", name, names.join(", ")),
+        )
+    })?;
+
+    // Half a size is ambiguous, so the preset's size only applies when neither dimension is set
+    if config.width.is_none() && config.height.is_none() {
+        config.width = Some(preset.width);
+        config.height = Some(preset.height);
+    }
+    config.fps.get_or_insert(preset.fps);
+    config.show_cursor.get_or_insert(preset.show_cursor);
+    config.capture_audio.get_or_insert(preset.capture_audio);
+    config.highlight_clicks.get_or_insert(preset.highlight_clicks);
+    config.write_wav_sidecar.get_or_insert(preset.write_wav_sidecar);
+
+    log::info!("Preset \"{}\": {}x{} @ {}fps",
+        preset.name,
+        config.width.unwrap_or(preset.width),
+        config.height.unwrap_or(preset.height),
+        config.fps.unwrap_or(preset.fps));
+    Ok(())
+}
@@ -0,0 +1,96 @@
+// Live preview - downscaled JPEG frames of a source for the UI, with nothing written to disk
+
+use napi::bindgen_prelude::*;
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::JoinHandle;
+use std::time::{Duration, Instant};
+
+use super::cancel::CancelToken;
+use super::cursor::CaptureSource;
+use super::screenshot::{self, ImageFormat, Thumbnail};
+use super::session::now_millis;
+use super::errors::{capture_error, CaptureErrorCode};
+
+pub const DEFAULT_PREVIEW_FPS: u32 = 5;
+pub const MAX_PREVIEW_FPS: u32 = 30;
+pub const DEFAULT_PREVIEW_WIDTH: u32 = 640;
+
+/// Gets each frame with the wall-clock time it was taken, in ms
+pub type PreviewSink = Arc<dyn Fn(Thumbnail, u64) + Send + Sync>;
+
+/// Frames of one source at a fixed rate until stopped or dropped
+pub struct PreviewStream {
+    stop_tx: Option<Sender<()>>,
+    // Cancels the frame in flight, so stopping doesn't wait out a stuck capture
+    cancel: CancelToken,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl PreviewStream {
+    pub fn start(screen_id: &str, fps: u32, max_width: u32, sink: PreviewSink) -> Result<Self> {
+        if CaptureSource::parse(screen_id).is_none() {
+            return Err(Error::new(Status::InvalidArg, format!("Invalid screen ID '{}'", screen_id)));
+        }
+        if fps == 0 || fps > MAX_PREVIEW_FPS {
+            return Err(Error::new(Status::InvalidArg, format!("Preview fps must be between 1 and {}", MAX_PREVIEW_FPS)));
+        }
+        let interval = Duration::from_secs_f64(1.0 / fps as f64);
+        let screen_id = screen_id.to_string();
+
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let cancel = CancelToken::default();
+        let frame_cancel = cancel.clone();
+        let thread = std::thread::Builder::new()
+            .name("capture-preview".to_string())
+            .spawn(move || {
+                let mut failing = false;
+                loop {
+                    let started = Instant::now();
+                    let taken_at = now_millis();
+                    match screenshot::source_thumbnail(&screen_id, max_width, ImageFormat::Jpeg, &frame_cancel) {
+                        Ok(frame) => {
+                            failing = false;
+                            sink(frame, taken_at);
+                        }
+                        // The source may come back (window minimized, display asleep); report once per outage
+                        Err(e) => {
+                            if !failing {
+                                log::warn!("Preview frame of {} failed: {}", screen_id, e);
+                                failing = true;
+                            }
+                        }
+                    }
+                    match stop_rx.recv_timeout(interval.saturating_sub(started.elapsed())) {
+                        Err(RecvTimeoutError::Timeout) => {}
+                        _ => break,
+                    }
+                }
+            })
+            .map_err(|e| capture_error(CaptureErrorCode::Unknown, format!("Failed to start preview thread: {}", e)))?;
+
+        log::info!("Preview started at {} fps, {} px wide", fps, max_width);
+        Ok(Self {
+            stop_tx: Some(stop_tx),
+            cancel,
+            thread: Some(thread),
+        })
+    }
+
+    pub fn stop(&mut self) {
+        if let Some(stop_tx) = self.stop_tx.take() {
+            let _ = stop_tx.send(());
+        }
+        self.cancel.cancel();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+            log::info!("Preview stopped");
+        }
+    }
+}
+
+impl Drop for PreviewStream {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,386 @@
+// CoreAudio process tap backend - system or per-process audio without ScreenCaptureKit (macOS 14.2+)
+
+use block2::RcBlock;
+use napi::bindgen_prelude::*;
+use objc2::runtime::{AnyClass, AnyObject};
+use objc2::{msg_send, class};
+use objc2_foundation::{NSArray, NSNumber, NSString};
+use std::ffi::{c_char, c_void};
+use std::ptr;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::mpsc::{self, RecvTimeoutError};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::audio::{
+    AudioManager, AudioObjectGetPropertyData, AudioObjectPropertyAddress,
+    K_AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN, K_AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL,
+};
+use super::audio_tap::{pcm_from_buffer_list, AudioStreamBasicDescription, PcmBuffer};
+use super::errors::{capture_error, CaptureErrorCode};
+
+/// Receives PCM from audio sources that don't arrive as CMSampleBuffers
+pub type PcmHandler = Arc<dyn Fn(&PcmBuffer) + Send + Sync>;
+
+const K_AUDIO_TAP_PROPERTY_FORMAT: u32 = 0x74666d74; // 'tfmt'
+const RTLD_DEFAULT: *mut c_void = -2isize as *mut c_void;
+// Buffers waiting between the IOProc and the worker, about a second at 10 ms per IO cycle
+const QUEUE_DEPTH: usize = 128;
+const WORKER_POLL: Duration = Duration::from_millis(50);
+
+type CreateProcessTapFn = unsafe extern "C" fn(description: *mut AnyObject, tap_id_out: *mut u32) -> i32;
+type DestroyProcessTapFn = unsafe extern "C" fn(tap_id: u32) -> i32;
+type IoBlock = dyn Fn(*const c_void, *const c_void, *const c_void, *mut c_void, *const c_void);
+
+#[repr(C)]
+struct MachTimebaseInfo {
+    numer: u32,
+    denom: u32,
+}
+
+// Leading fields of AudioTimeStamp; the rest is never read
+#[repr(C)]
+struct AudioTimeStampPrefix {
+    sample_time: f64,
+    host_time: u64,
+}
+
+extern "C" {
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    fn mach_timebase_info(info: *mut MachTimebaseInfo) -> i32;
+    fn AudioHardwareCreateAggregateDevice(description: *const c_void, device_id_out: *mut u32) -> i32;
+    fn AudioHardwareDestroyAggregateDevice(device_id: u32) -> i32;
+    fn AudioDeviceCreateIOProcIDWithBlock(
+        proc_id_out: *mut *mut c_void,
+        device_id: u32,
+        dispatch_queue: *mut c_void,
+        block: &block2::Block<IoBlock>,
+    ) -> i32;
+    fn AudioDeviceDestroyIOProcID(device_id: u32, proc_id: *mut c_void) -> i32;
+    fn AudioDeviceStart(device_id: u32, proc_id: *mut c_void) -> i32;
+    fn AudioDeviceStop(device_id: u32, proc_id: *mut c_void) -> i32;
+}
+
+/// Where system audio for a recording comes from
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SystemAudioBackend {
+    /// SCStream audio output (default)
+    ScreenCaptureKit,
+    /// CoreAudio process tap, independent of the video stream
+    CoreAudioTap,
+}
+
+impl SystemAudioBackend {
+    pub fn parse(value: Option<&str>) -> Result<Self> {
+        match value.unwrap_or("sck") {
+            "sck" => Ok(SystemAudioBackend::ScreenCaptureKit),
+            "coreaudio-tap" => Ok(SystemAudioBackend::CoreAudioTap),
+            other => Err(Error::new(
+                Status::InvalidArg,
+                format!("Unknown system_audio_backend '{}', expected \"sck\" or \"coreaudio-tap\"", other),
+            )),
+        }
+    }
+}
+
+/// Symbols that only exist on macOS 14.2+; looked up at runtime so older systems can still load the module
+fn process_tap_symbols() -> Option<(CreateProcessTapFn, DestroyProcessTapFn)> {
+    unsafe {
+        let create = dlsym(RTLD_DEFAULT, c"AudioHardwareCreateProcessTap".as_ptr());
+        let destroy = dlsym(RTLD_DEFAULT, c"AudioHardwareDestroyProcessTap".as_ptr());
+        if create.is_null() || destroy.is_null() || AnyClass::get(c"CATapDescription").is_none() {
+            return None;
+        }
+        Some((
+            std::mem::transmute::<*mut c_void, CreateProcessTapFn>(create),
+            std::mem::transmute::<*mut c_void, DestroyProcessTapFn>(destroy),
+        ))
+    }
+}
+
+fn host_time_to_seconds(host_time: u64) -> f64 {
+    let mut info = MachTimebaseInfo { numer: 0, denom: 0 };
+    unsafe { mach_timebase_info(&mut info) };
+    if info.denom == 0 {
+        return 0.0;
+    }
+    host_time as f64 * info.numer as f64 / info.denom as f64 / 1_000_000_000.0
+}
+
+/// A running process tap: CATapDescription -> tap object -> private aggregate device -> IOProc.
+/// Everything is torn down in reverse order on stop/drop.
+/// The IOProc runs on Core Audio's realtime thread, so it only queues each buffer; `handler`
+/// (which may lock and write files) runs on a worker thread.
+pub struct ProcessTap {
+    tap_id: u32,
+    aggregate_id: u32,
+    io_proc: *mut c_void,
+    running: bool,
+    destroy_tap: Option<DestroyProcessTapFn>,
+    _io_block: Option<RcBlock<IoBlock>>,
+    worker: Option<JoinHandle<()>>,
+    worker_stop: Arc<AtomicBool>,
+    dropped: Arc<AtomicU64>,
+}
+
+// The IOProc owns its own thread; this handle only starts and stops it
+unsafe impl Send for ProcessTap {}
+
+impl ProcessTap {
+    pub fn is_supported() -> bool {
+        process_tap_symbols().is_some()
+    }
+
+    /// Tap the given PIDs (mixed to stereo), or all system audio when `process_ids` is empty
+    pub fn start(process_ids: &[u32], handler: PcmHandler) -> Result<Self> {
+        let (create_tap, destroy_tap) = process_tap_symbols().ok_or_else(|| {
+            capture_error(CaptureErrorCode::Unsupported, "CoreAudio process taps require macOS 14.2 or later")
+        })?;
+        // The first tap a process creates prompts for system audio recording
+        super::permissions::guard_silent(super::permissions::PrivacyPane::SystemAudio)?;
+
+        let process_objects = process_ids
+            .iter()
+            .map(|pid| {
+                AudioManager::process_object_for_pid(*pid).ok_or_else(|| {
+                    Error::new(Status::InvalidArg, format!("ProcessNotFound: process {} is not producing audio", pid))
+                })
+            })
+            .collect::<Result<Vec<u32>>>()?;
+
+        if process_ids.is_empty() {
+            log::info!("Creating CoreAudio process tap for all system audio");
+        } else {
+            log::info!("Creating CoreAudio process tap for processes {:?}", process_ids);
+        }
+
+        let mut tap = Self {
+            tap_id: 0,
+            aggregate_id: 0,
+            io_proc: ptr::null_mut(),
+            running: false,
+            destroy_tap: Some(destroy_tap),
+            _io_block: None,
+            worker: None,
+            worker_stop: Arc::new(AtomicBool::new(false)),
+            dropped: Arc::new(AtomicU64::new(0)),
+        };
+
+        unsafe {
+            // 1. Describe and create the tap
+            let numbers: Vec<*mut AnyObject> = process_objects
+                .iter()
+                .map(|id| {
+                    let number: *mut NSNumber = msg_send![class!(NSNumber), numberWithUnsignedInt: *id];
+                    number as *mut AnyObject
+                })
+                .collect();
+            let processes: *mut NSArray = msg_send![class!(NSArray), arrayWithObjects: numbers.as_ptr(), count: numbers.len()];
+
+            let alloc: *mut AnyObject = msg_send![class!(CATapDescription), alloc];
+            let description: *mut AnyObject = if process_objects.is_empty() {
+                msg_send![alloc, initStereoGlobalTapButExcludeProcesses: processes]
+            } else {
+                msg_send![alloc, initStereoMixdownOfProcesses: processes]
+            };
+            if description.is_null() {
+                return Err(capture_error(CaptureErrorCode::Unknown, "Failed to create CATapDescription"));
+            }
+            let name = NSString::from_str("WhisperDesk Audio Tap");
+            let _: () = msg_send![description, setName: &*name];
+            let _: () = msg_send![description, setPrivate: true];
+
+            let status = create_tap(description, &mut tap.tap_id);
+            if status != 0 || tap.tap_id == 0 {
+                let _: () = msg_send![description, release];
+                return Err(capture_error(
+                    CaptureErrorCode::PermissionDenied,
+                    format!("Failed to create process tap (status {}); check the audio capture permission", status),
+                ));
+            }
+
+            super::permissions::note_system_audio_requested();
+
+            let uuid: *mut AnyObject = msg_send![description, UUID];
+            let uuid_string: *mut NSString = msg_send![uuid, UUIDString];
+            let tap_uuid = (*uuid_string).to_string();
+            let _: () = msg_send![description, release];
+
+            // 2. The tap's stream format (normally f32 stereo at the output device rate)
+            let address = AudioObjectPropertyAddress {
+                selector: K_AUDIO_TAP_PROPERTY_FORMAT,
+                scope: K_AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL,
+                element: K_AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+            };
+            let mut asbd: AudioStreamBasicDescription = std::mem::zeroed();
+            let mut data_size = std::mem::size_of::<AudioStreamBasicDescription>() as u32;
+            let status = AudioObjectGetPropertyData(
+                tap.tap_id,
+                &address,
+                0,
+                ptr::null(),
+                &mut data_size,
+                &mut asbd as *mut AudioStreamBasicDescription as *mut c_void,
+            );
+            if status != 0 {
+                return Err(capture_error(CaptureErrorCode::Unknown, format!("Failed to read tap format (status {})", status)));
+            }
+
+            // 3. Taps are read through an aggregate device clocked by the current output device
+            let output_uid = AudioManager::default_output_device_uid()
+                .ok_or_else(|| capture_error(CaptureErrorCode::SourceNotFound, "No default output device to clock the tap"))?;
+            let aggregate_uid = format!("com.whisperdesk.tap.{}", super::session::unique_key());
+
+            let uid_key = NSString::from_str("uid");
+            let output_uid_value = NSString::from_str(&output_uid);
+            let subdevice: *mut AnyObject = msg_send![
+                class!(NSDictionary),
+                dictionaryWithObjects: &[&*output_uid_value as *const NSString as *mut AnyObject],
+                forKeys: &[&*uid_key],
+                count: 1usize
+            ];
+            let subdevice_list: *mut NSArray = msg_send![class!(NSArray), arrayWithObject: subdevice];
+
+            let drift_key = NSString::from_str("drift");
+            let tap_uuid_value = NSString::from_str(&tap_uuid);
+            let drift_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithInt: 1i32];
+            let tap_entry: *mut AnyObject = msg_send![
+                class!(NSDictionary),
+                dictionaryWithObjects: &[&*tap_uuid_value as *const NSString as *mut AnyObject, drift_value as *mut AnyObject],
+                forKeys: &[&*uid_key, &*drift_key],
+                count: 2usize
+            ];
+            let tap_list: *mut NSArray = msg_send![class!(NSArray), arrayWithObject: tap_entry];
+
+            let name_key = NSString::from_str("name");
+            let main_key = NSString::from_str("master");
+            let private_key = NSString::from_str("private");
+            let stacked_key = NSString::from_str("stacked");
+            let autostart_key = NSString::from_str("tapautostart");
+            let subdevices_key = NSString::from_str("subdevices");
+            let taps_key = NSString::from_str("taps");
+
+            let uid_value = NSString::from_str(&aggregate_uid);
+            let name_value = NSString::from_str("WhisperDesk Tap Device");
+            let private_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithInt: 1i32];
+            let stacked_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithInt: 0i32];
+            // Start the tap together with the device IO instead of as soon as it is added
+            let autostart_value: *mut NSNumber = msg_send![class!(NSNumber), numberWithInt: 1i32];
+
+            let aggregate: *mut AnyObject = msg_send![
+                class!(NSDictionary),
+                dictionaryWithObjects: &[
+                    &*uid_value as *const NSString as *mut AnyObject,
+                    &*name_value as *const NSString as *mut AnyObject,
+                    &*output_uid_value as *const NSString as *mut AnyObject,
+                    private_value as *mut AnyObject,
+                    stacked_value as *mut AnyObject,
+                    autostart_value as *mut AnyObject,
+                    subdevice_list as *mut AnyObject,
+                    tap_list as *mut AnyObject
+                ],
+                forKeys: &[&*uid_key, &*name_key, &*main_key, &*private_key, &*stacked_key, &*autostart_key, &*subdevices_key, &*taps_key],
+                count: 8usize
+            ];
+
+            let status = AudioHardwareCreateAggregateDevice(aggregate as *const c_void, &mut tap.aggregate_id);
+            if status != 0 || tap.aggregate_id == 0 {
+                return Err(capture_error(CaptureErrorCode::Unknown, format!("Failed to create tap aggregate device (status {})", status)));
+            }
+
+            // 4. Worker handing the queued audio to `handler`, off the realtime thread
+            // (a bounded std channel sends without locking or allocating)
+            let (sender, receiver) = mpsc::sync_channel::<PcmBuffer>(QUEUE_DEPTH);
+            let worker_stop = tap.worker_stop.clone();
+            let worker = thread::Builder::new()
+                .name("process-tap".to_string())
+                .spawn(move || loop {
+                    match receiver.recv_timeout(WORKER_POLL) {
+                        Ok(pcm) => handler(&pcm),
+                        Err(RecvTimeoutError::Timeout) if !worker_stop.load(Ordering::Relaxed) => {}
+                        Err(_) => break,
+                    }
+                })
+                .map_err(|e| capture_error(CaptureErrorCode::Unknown, format!("Failed to start process tap worker: {}", e)))?;
+            tap.worker = Some(worker);
+
+            // 5. IOProc delivering the tapped audio
+            let dropped = tap.dropped.clone();
+            let block: RcBlock<IoBlock> = RcBlock::new(
+                move |_now: *const c_void, input: *const c_void, input_time: *const c_void, _output: *mut c_void, _output_time: *const c_void| {
+                    let presentation_time = if input_time.is_null() {
+                        0.0
+                    } else {
+                        host_time_to_seconds((*(input_time as *const AudioTimeStampPrefix)).host_time)
+                    };
+                    if let Some(pcm) = pcm_from_buffer_list(input, &asbd, presentation_time) {
+                        // Never wait here; a worker that fell behind costs audio, not a glitch
+                        if sender.try_send(pcm).is_err() {
+                            dropped.fetch_add(1, Ordering::Relaxed);
+                        }
+                    }
+                },
+            );
+
+            let status = AudioDeviceCreateIOProcIDWithBlock(&mut tap.io_proc, tap.aggregate_id, ptr::null_mut(), &block);
+            tap._io_block = Some(block);
+            if status != 0 || tap.io_proc.is_null() {
+                return Err(capture_error(CaptureErrorCode::Unknown, format!("Failed to create tap IOProc (status {})", status)));
+            }
+
+            let status = AudioDeviceStart(tap.aggregate_id, tap.io_proc);
+            if status != 0 {
+                return Err(capture_error(CaptureErrorCode::Unknown, format!("Failed to start process tap (status {})", status)));
+            }
+            tap.running = true;
+
+            log::info!("Process tap running: {} Hz, {} channels", asbd.sample_rate, asbd.channels_per_frame);
+        }
+
+        Ok(tap)
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.running
+    }
+
+    pub fn stop(&mut self) {
+        unsafe {
+            if self.running {
+                AudioDeviceStop(self.aggregate_id, self.io_proc);
+                self.running = false;
+                log::info!("Process tap stopped");
+            }
+            if !self.io_proc.is_null() {
+                AudioDeviceDestroyIOProcID(self.aggregate_id, self.io_proc);
+                self.io_proc = ptr::null_mut();
+            }
+            // The IOProc is gone, so the worker drains what is queued and exits
+            self.worker_stop.store(true, Ordering::Relaxed);
+            if let Some(worker) = self.worker.take() {
+                let _ = worker.join();
+            }
+            let dropped = self.dropped.swap(0, Ordering::Relaxed);
+            if dropped > 0 {
+                log::warn!("Process tap dropped {} buffers the worker couldn't keep up with", dropped);
+            }
+            if self.aggregate_id != 0 {
+                AudioHardwareDestroyAggregateDevice(self.aggregate_id);
+                self.aggregate_id = 0;
+            }
+            if self.tap_id != 0 {
+                if let Some(destroy_tap) = self.destroy_tap {
+                    destroy_tap(self.tap_id);
+                }
+                self.tap_id = 0;
+            }
+        }
+    }
+}
+
+impl Drop for ProcessTap {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
@@ -0,0 +1,123 @@
+// Recommended capture settings derived from the source and the machine's encoder capabilities
+
+use super::content::{DisplayInfo, WindowInfo};
+use super::encoder::has_hardware_encoder;
+use super::formats::VideoCodec;
+
+// Above this many pixels per second a software H.264 encode struggles on older Intel machines
+const SOFTWARE_ENCODE_PIXEL_BUDGET: f64 = 3840.0 * 2160.0 * 30.0;
+
+// Recommendations never exceed 4K even on 5K/6K displays
+const MAX_RECOMMENDED_WIDTH: u32 = 3840;
+const MAX_RECOMMENDED_HEIGHT: u32 = 2160;
+
+#[derive(Debug, Clone)]
+pub struct Recommendation {
+    pub width: u32,
+    pub height: u32,
+    pub fps: u32,
+    pub codec: VideoCodec,
+    pub bitrate: u32,
+    pub hardware_encoder: bool,
+    pub notes: Vec<String>,
+}
+
+pub struct EncoderCapabilities {
+    pub hardware_h264: bool,
+    pub hardware_hevc: bool,
+}
+
+impl EncoderCapabilities {
+    /// Asks VideoToolbox, since Intel Macs only have hardware encode with a T2 chip or a recent iGPU
+    pub fn probe() -> Self {
+        let capabilities = Self {
+            hardware_h264: has_hardware_encoder(VideoCodec::H264),
+            hardware_hevc: has_hardware_encoder(VideoCodec::Hevc),
+        };
+        log::debug!("Hardware encoders: H.264 {}, HEVC {}", capabilities.hardware_h264, capabilities.hardware_hevc);
+        capabilities
+    }
+}
+
+/// Refresh rate of a display in Hz, 60 when the mode does not report one (common for built-in panels)
+pub fn display_refresh_rate(display_id: u32) -> f64 {
+    extern "C" {
+        fn CGDisplayCopyDisplayMode(display: u32) -> *mut std::ffi::c_void;
+        fn CGDisplayModeGetRefreshRate(mode: *mut std::ffi::c_void) -> f64;
+        fn CGDisplayModeRelease(mode: *mut std::ffi::c_void);
+    }
+
+    unsafe {
+        let mode = CGDisplayCopyDisplayMode(display_id);
+        if mode.is_null() {
+            return 60.0;
+        }
+
+        let refresh_rate = CGDisplayModeGetRefreshRate(mode);
+        CGDisplayModeRelease(mode);
+
+        if refresh_rate > 0.0 {
+            refresh_rate
+        } else {
+            60.0
+        }
+    }
+}
+
+pub fn recommend_for_display(display: &DisplayInfo) -> Recommendation {
+    let refresh_rate = display.refresh_rate;
+    log::info!("Recommending settings for display {} ({}x{} @ {:.0}Hz)",
+        display.id, display.width, display.height, refresh_rate);
+    recommend(display.width, display.height, refresh_rate)
+}
+
+pub fn recommend_for_window(window: &WindowInfo) -> Recommendation {
+    log::info!("Recommending settings for window {} ({}x{})", window.id, window.width, window.height);
+    // Window content rarely benefits from more than 30fps
+    recommend(window.width, window.height, 30.0)
+}
+
+fn recommend(source_width: u32, source_height: u32, refresh_rate: f64) -> Recommendation {
+    let capabilities = EncoderCapabilities::probe();
+    let mut notes = Vec::new();
+
+    // Downscale oversized sources, keeping the aspect ratio and even dimensions
+    let scale = (MAX_RECOMMENDED_WIDTH as f64 / source_width.max(1) as f64)
+        .min(MAX_RECOMMENDED_HEIGHT as f64 / source_height.max(1) as f64)
+        .min(1.0);
+    let width = (((source_width as f64 * scale) as u32) & !1).max(2);
+    let height = (((source_height as f64 * scale) as u32) & !1).max(2);
+    if scale < 1.0 {
+        notes.push(format!("Source {}x{} downscaled to {}x{}", source_width, source_height, width, height));
+    }
+
+    // 60fps only when the display actually refreshes that fast
+    let mut fps = if refresh_rate >= 59.0 { 60 } else { 30 };
+    if refresh_rate > 60.5 {
+        notes.push(format!("Display refreshes at {:.0}Hz, capped at 60fps to limit encoder load", refresh_rate));
+    }
+
+    let pixel_rate = width as f64 * height as f64 * fps as f64;
+    let use_hevc = capabilities.hardware_hevc && pixel_rate > 2560.0 * 1440.0 * 30.0;
+    if !capabilities.hardware_hevc && pixel_rate > SOFTWARE_ENCODE_PIXEL_BUDGET {
+        fps = 30;
+        notes.push("No hardware HEVC encoder detected, frame rate reduced to 30fps".to_string());
+    }
+
+    let (codec, bits_per_pixel) = if use_hevc {
+        (VideoCodec::Hevc, 0.07)
+    } else {
+        (VideoCodec::H264, 0.1)
+    };
+    let bitrate = (width as f64 * height as f64 * fps as f64 * bits_per_pixel) as u32;
+
+    Recommendation {
+        width,
+        height,
+        fps,
+        codec,
+        bitrate,
+        hardware_encoder: if use_hevc { capabilities.hardware_hevc } else { capabilities.hardware_h264 },
+        notes,
+    }
+}
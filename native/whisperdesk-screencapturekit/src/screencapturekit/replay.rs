@@ -0,0 +1,355 @@
+// Replay buffer - keep the last N seconds as short encoded segments and export them on demand
+
+use block2::StackBlock;
+use napi::bindgen_prelude::*;
+use objc2::rc::Retained;
+use objc2::runtime::AnyObject;
+use objc2::{class, msg_send};
+use objc2_core_media::{kCMTimeZero, CMTime, CMTimeRange};
+use objc2_core_video::CVPixelBuffer;
+use objc2_foundation::{NSArray, NSError, NSString, NSURL};
+use std::collections::VecDeque;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use super::audio_tap::PcmBuffer;
+use super::encoder::{AudioEncoder, VideoEncoder, AVFileTypeMPEG4, AVMediaTypeAudio, AVMediaTypeVideo};
+use super::cancel::{CancelToken, Wait};
+use super::errors::{capture_error, io_error, CaptureErrorCode};
+
+/// Length of one ring segment; a saved replay is at most this much longer than asked before trimming
+pub const SEGMENT_SECONDS: u32 = 2;
+pub const MAX_REPLAY_SECONDS: u32 = 600;
+
+// A passthrough export only copies samples, but a network volume can still be slow
+const EXPORT_TIMEOUT: Duration = Duration::from_secs(60);
+
+// AVAssetExportSessionStatus
+const AV_ASSET_EXPORT_SESSION_STATUS_COMPLETED: i64 = 3;
+
+extern "C" {
+    fn CMTimeGetSeconds(time: CMTime) -> f64;
+    fn CMTimeMakeWithSeconds(seconds: f64, preferred_timescale: i32) -> CMTime;
+    fn CMTimeAdd(lhs: CMTime, rhs: CMTime) -> CMTime;
+    fn CMTimeMinimum(time1: CMTime, time2: CMTime) -> CMTime;
+}
+
+/// A finished segment: video and audio written side by side like a regular recording
+#[derive(Debug, Clone)]
+struct Segment {
+    video_path: String,
+    audio_path: String,
+}
+
+struct OpenSegment {
+    segment: Segment,
+    video: VideoEncoder,
+    audio: AudioEncoder,
+    frames: u64,
+    audio_start: Option<f64>,
+}
+
+impl OpenSegment {
+    fn finalize(mut self) -> Segment {
+        if let Err(e) = self.video.finalize_encoding() {
+            log::error!("Replay segment video finalization failed: {}", e);
+        }
+        if let Err(e) = self.audio.finalize_encoding() {
+            log::error!("Replay segment audio finalization failed: {}", e);
+        }
+        self.segment
+    }
+}
+
+/// Segments captured so far, hard-linked aside so the ring can keep rotating during an export
+pub struct ReplaySnapshot {
+    directory: PathBuf,
+    segments: Vec<Segment>,
+    seconds: u32,
+}
+
+/// Ring of short segments covering at least the last `seconds` of capture
+pub struct ReplayBuffer {
+    directory: PathBuf,
+    seconds: u32,
+    width: u32,
+    height: u32,
+    fps: u32,
+    next_index: u64,
+    snapshots: u64,
+    current: Option<OpenSegment>,
+    completed: VecDeque<Segment>,
+}
+
+// Encoders are only driven under the delegate's mutex
+unsafe impl Send for ReplayBuffer {}
+
+impl ReplayBuffer {
+    /// Scratch directory for a session's segments
+    pub fn directory_for(session_key: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("whisperdesk-replay-{}", session_key))
+    }
+
+    pub fn new(directory: PathBuf, seconds: u32, width: u32, height: u32, fps: u32) -> Result<Self> {
+        if seconds == 0 || seconds > MAX_REPLAY_SECONDS {
+            return Err(Error::new(
+                Status::InvalidArg,
+                format!("replay_buffer_seconds must be between 1 and {}", MAX_REPLAY_SECONDS),
+            ));
+        }
+        std::fs::create_dir_all(&directory)
+            .map_err(|e| io_error(format!("Failed to create {}", directory.display()), &e))?;
+
+        let mut buffer = Self {
+            directory,
+            seconds,
+            width,
+            height,
+            fps: fps.max(1),
+            next_index: 0,
+            snapshots: 0,
+            current: None,
+            completed: VecDeque::new(),
+        };
+        buffer.current = Some(buffer.open_segment()?);
+        log::info!("Replay buffer keeping the last {}s in {}", seconds, buffer.directory.display());
+        Ok(buffer)
+    }
+
+    fn open_segment(&mut self) -> Result<OpenSegment> {
+        let base = self.directory.join(format!("segment-{}", self.next_index));
+        self.next_index += 1;
+        let base = base.to_string_lossy().to_string();
+        let segment = Segment {
+            video_path: format!("{}_video.mp4", base),
+            audio_path: format!("{}_audio.mp4", base),
+        };
+        Ok(OpenSegment {
+            video: VideoEncoder::new(&segment.video_path, self.width, self.height, self.fps)?,
+            audio: AudioEncoder::new(&segment.audio_path, 48000, 2)?,
+            segment,
+            frames: 0,
+            audio_start: None,
+        })
+    }
+
+    /// Close the open segment, drop the ones that fell out of the window and start the next
+    fn rotate(&mut self) -> Result<()> {
+        if let Some(open) = self.current.take() {
+            self.completed.push_back(open.finalize());
+        }
+        // One extra segment, since the oldest one is usually only partly inside the window
+        let keep = (self.seconds.div_ceil(SEGMENT_SECONDS) + 1) as usize;
+        while self.completed.len() > keep {
+            if let Some(old) = self.completed.pop_front() {
+                let _ = std::fs::remove_file(&old.video_path);
+                let _ = std::fs::remove_file(&old.audio_path);
+            }
+        }
+        self.current = Some(self.open_segment()?);
+        Ok(())
+    }
+
+    pub fn push_frame(&mut self, pixel_buffer: *mut CVPixelBuffer, presentation_time: CMTime) {
+        let full = self.current.as_ref().map(|open| open.frames >= (self.fps * SEGMENT_SECONDS) as u64).unwrap_or(true);
+        if full {
+            if let Err(e) = self.rotate() {
+                log::error!("Replay buffer rotation failed: {}", e);
+                return;
+            }
+        }
+        if let Some(open) = self.current.as_mut() {
+            match open.video.encode_frame(pixel_buffer, presentation_time) {
+                Ok(()) => open.frames += 1,
+                Err(e) => log::error!("Failed to encode replay frame: {}", e),
+            }
+        }
+    }
+
+    /// Audio is re-timed to its segment, which starts at zero
+    pub fn push_pcm(&mut self, pcm: &PcmBuffer) {
+        if let Some(open) = self.current.as_mut() {
+            let start = *open.audio_start.get_or_insert(pcm.presentation_time);
+            let shifted = PcmBuffer { presentation_time: (pcm.presentation_time - start).max(0.0), ..pcm.clone() };
+            if let Err(e) = open.audio.encode_pcm_buffer(&shifted) {
+                log::error!("Failed to encode replay audio: {}", e);
+            }
+        }
+    }
+
+    /// Finish the open segment and set the current window aside for `ReplaySnapshot::export`
+    pub fn snapshot(&mut self) -> Result<ReplaySnapshot> {
+        self.rotate()?;
+        if self.completed.is_empty() {
+            return Err(capture_error(CaptureErrorCode::Unknown, "The replay buffer is still empty"));
+        }
+
+        let directory = self.directory.join(format!("save-{}", self.snapshots));
+        self.snapshots += 1;
+        std::fs::create_dir_all(&directory)
+            .map_err(|e| io_error(format!("Failed to create {}", directory.display()), &e))?;
+        let link = |path: &str| -> Result<String> {
+            let target = directory.join(Path::new(path).file_name().unwrap_or_default());
+            std::fs::hard_link(path, &target)
+                .map_err(|e| io_error(format!("Failed to keep replay segment {}", path), &e))?;
+            Ok(target.to_string_lossy().to_string())
+        };
+        let segments = self
+            .completed
+            .iter()
+            .map(|segment| {
+                Ok(Segment {
+                    video_path: link(&segment.video_path)?,
+                    audio_path: link(&segment.audio_path)?,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(ReplaySnapshot { directory, segments, seconds: self.seconds })
+    }
+
+    /// Stop writing and delete every segment
+    pub fn discard(&mut self) {
+        if let Some(open) = self.current.take() {
+            open.finalize();
+        }
+        self.completed.clear();
+        if let Err(e) = std::fs::remove_dir_all(&self.directory) {
+            log::warn!("Failed to remove replay buffer {}: {}", self.directory.display(), e);
+        }
+    }
+}
+
+impl Drop for ReplayBuffer {
+    fn drop(&mut self) {
+        if self.current.is_some() {
+            self.discard();
+        }
+    }
+}
+
+unsafe fn url_asset(path: &str) -> Option<Retained<AnyObject>> {
+    let path = NSString::from_str(path);
+    let url: *mut NSURL = msg_send![class!(NSURL), fileURLWithPath: &*path];
+    msg_send![class!(AVURLAsset), URLAssetWithURL: url, options: ptr::null::<AnyObject>()]
+}
+
+unsafe fn first_track(asset: &AnyObject, media_type: &NSString) -> Option<Retained<AnyObject>> {
+    let tracks: Option<Retained<NSArray>> = msg_send![asset, tracksWithMediaType: media_type];
+    let tracks = tracks?;
+    if tracks.count() == 0 {
+        return None;
+    }
+    Some(tracks.objectAtIndex(0))
+}
+
+impl ReplaySnapshot {
+    /// Stitch the segments into one MP4 holding the last `seconds` and write it to `path`;
+    /// returns the clip's duration in seconds. Cancelling `cancel` stops the export session and
+    /// removes the partial clip.
+    pub fn export(self, path: &str, cancel: &CancelToken) -> Result<f64> {
+        let result = unsafe { self.export_composition(path, cancel) };
+        let _ = std::fs::remove_dir_all(&self.directory);
+        result
+    }
+
+    unsafe fn export_composition(&self, path: &str, cancel: &CancelToken) -> Result<f64> {
+        let composition: *mut AnyObject = msg_send![class!(AVMutableComposition), composition];
+        if composition.is_null() {
+            return Err(capture_error(CaptureErrorCode::Unknown, "Failed to create replay composition"));
+        }
+        let video_type = NSString::from_str(AVMediaTypeVideo);
+        let audio_type = NSString::from_str(AVMediaTypeAudio);
+        // kCMPersistentTrackID_Invalid lets the composition pick the track IDs
+        let video_track: *mut AnyObject = msg_send![composition, addMutableTrackWithMediaType: &*video_type, preferredTrackID: 0i32];
+        let audio_track: *mut AnyObject = msg_send![composition, addMutableTrackWithMediaType: &*audio_type, preferredTrackID: 0i32];
+
+        let mut cursor = kCMTimeZero;
+        for segment in &self.segments {
+            let video_asset = match url_asset(&segment.video_path) {
+                Some(asset) => asset,
+                None => continue,
+            };
+            let duration: CMTime = msg_send![&*video_asset, duration];
+            let video_source = match first_track(&video_asset, &video_type) {
+                Some(track) if CMTimeGetSeconds(duration) > 0.0 => track,
+                _ => continue,
+            };
+            let mut error: *mut NSError = ptr::null_mut();
+            let range = CMTimeRange { start: kCMTimeZero, duration };
+            let inserted: bool = msg_send![video_track, insertTimeRange: range, ofTrack: &*video_source, atTime: cursor, error: &mut error];
+            if !inserted {
+                log::warn!("Skipping replay segment {}", segment.video_path);
+                continue;
+            }
+
+            // Audio never runs past its video, so the segments stay aligned
+            if let Some(audio_asset) = url_asset(&segment.audio_path) {
+                if let Some(audio_source) = first_track(&audio_asset, &audio_type) {
+                    let audio_duration: CMTime = msg_send![&*audio_asset, duration];
+                    let range = CMTimeRange { start: kCMTimeZero, duration: CMTimeMinimum(duration, audio_duration) };
+                    let mut error: *mut NSError = ptr::null_mut();
+                    let _: bool = msg_send![audio_track, insertTimeRange: range, ofTrack: &*audio_source, atTime: cursor, error: &mut error];
+                }
+            }
+            cursor = CMTimeAdd(cursor, duration);
+        }
+
+        let total = CMTimeGetSeconds(cursor);
+        if total <= 0.0 {
+            return Err(capture_error(CaptureErrorCode::Unknown, "The replay buffer holds no video yet"));
+        }
+        if total > self.seconds as f64 {
+            let excess = CMTimeRange { start: kCMTimeZero, duration: CMTimeMakeWithSeconds(total - self.seconds as f64, 600) };
+            let _: () = msg_send![composition, removeTimeRange: excess];
+        }
+
+        // The export session refuses to overwrite
+        if Path::new(path).exists() {
+            std::fs::remove_file(path).map_err(|e| io_error(format!("Failed to replace {}", path), &e))?;
+        }
+
+        let preset = NSString::from_str("AVAssetExportPresetPassthrough");
+        let alloc: *mut AnyObject = msg_send![class!(AVAssetExportSession), alloc];
+        let session: *mut AnyObject = msg_send![alloc, initWithAsset: composition, presetName: &*preset];
+        let session: Retained<AnyObject> = Retained::from_raw(session)
+            .ok_or_else(|| capture_error(CaptureErrorCode::Unknown, "Failed to create replay export session"))?;
+        let path_string = NSString::from_str(path);
+        let url: *mut NSURL = msg_send![class!(NSURL), fileURLWithPath: &*path_string];
+        let file_type = NSString::from_str(AVFileTypeMPEG4);
+        let _: () = msg_send![&*session, setOutputURL: url];
+        let _: () = msg_send![&*session, setOutputFileType: &*file_type];
+
+        let (done_tx, done_rx) = mpsc::channel::<()>();
+        let block = StackBlock::new(move || {
+            let _ = done_tx.send(());
+        });
+        let block = block.copy();
+        let _: () = msg_send![&*session, exportAsynchronouslyWithCompletionHandler: &*block];
+        match cancel.recv_timeout(&done_rx, EXPORT_TIMEOUT) {
+            Wait::Received(()) => {}
+            Wait::TimedOut => {
+                let _: () = msg_send![&*session, cancelExport];
+                return Err(capture_error(CaptureErrorCode::Timeout, "Timed out exporting the replay"));
+            }
+            Wait::Cancelled => {
+                let _: () = msg_send![&*session, cancelExport];
+                let _ = std::fs::remove_file(path);
+                return Err(capture_error(CaptureErrorCode::Cancelled, "Replay export was cancelled"));
+            }
+        }
+
+        let status: i64 = msg_send![&*session, status];
+        if status != AV_ASSET_EXPORT_SESSION_STATUS_COMPLETED {
+            let error: *mut NSError = msg_send![&*session, error];
+            let reason = if error.is_null() { format!("status {}", status) } else { (*error).localizedDescription().to_string() };
+            return Err(capture_error(CaptureErrorCode::Unknown, format!("Replay export failed: {}", reason)));
+        }
+
+        let duration = total.min(self.seconds as f64);
+        log::info!("Saved {:.1}s replay to {}", duration, path);
+        Ok(duration)
+    }
+}
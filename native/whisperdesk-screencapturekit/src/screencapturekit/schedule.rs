@@ -0,0 +1,95 @@
+// Scheduled recordings - a timer thread that starts and stops a capture at wall-clock times
+
+use napi::bindgen_prelude::*;
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError, Sender};
+use std::thread::JoinHandle;
+use std::time::{Duration, SystemTime};
+
+use super::errors::{capture_error, CaptureErrorCode};
+
+// Re-check the wall clock at least this often, so a sleep/wake or clock change can't push a deadline out
+const MAX_WAIT: Duration = Duration::from_secs(1);
+
+/// Starts the recording; returns whether it started, which arms the stop
+pub type ScheduledStart = Box<dyn FnOnce() -> bool + Send>;
+pub type ScheduledStop = Box<dyn FnOnce() + Send>;
+
+/// One armed start (and optional stop), independent of the JS event loop
+pub struct RecordingSchedule {
+    stop_tx: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl RecordingSchedule {
+    pub fn arm(start_at: SystemTime, stop_at: Option<SystemTime>, on_start: ScheduledStart, on_stop: ScheduledStop) -> Result<Self> {
+        if let Some(stop_at) = stop_at {
+            if stop_at <= start_at {
+                return Err(Error::new(Status::InvalidArg, "stopAt must be later than startAt"));
+            }
+            if stop_at <= SystemTime::now() {
+                return Err(Error::new(Status::InvalidArg, "stopAt is in the past"));
+            }
+        }
+
+        let (stop_tx, stop_rx) = mpsc::channel();
+        let thread = std::thread::Builder::new()
+            .name("recording-schedule".to_string())
+            .spawn(move || {
+                if !wait_until(&stop_rx, start_at) {
+                    return;
+                }
+                log::info!("Scheduled recording start reached");
+                if !on_start() {
+                    return;
+                }
+                if let Some(stop_at) = stop_at {
+                    if wait_until(&stop_rx, stop_at) {
+                        log::info!("Scheduled recording stop reached");
+                        on_stop();
+                    }
+                }
+            })
+            .map_err(|e| capture_error(CaptureErrorCode::Unknown, format!("Failed to start schedule thread: {}", e)))?;
+
+        log::info!("Recording scheduled in {:.1}s", start_at.duration_since(SystemTime::now()).unwrap_or_default().as_secs_f64());
+        Ok(Self {
+            stop_tx: Some(stop_tx),
+            thread: Some(thread),
+        })
+    }
+
+    /// A start or stop is still to come
+    pub fn is_pending(&self) -> bool {
+        self.thread.as_ref().map(|thread| !thread.is_finished()).unwrap_or(false)
+    }
+
+    /// Disarm whatever has not fired yet; a recording that already started keeps running.
+    /// Doesn't wait for the timer thread, which may be in the middle of starting the recording.
+    pub fn cancel(&mut self) {
+        if let Some(stop_tx) = self.stop_tx.take() {
+            let _ = stop_tx.send(());
+        }
+        // Detached: it sees the signal at its next wait, or finishes a start already under way
+        self.thread.take();
+    }
+}
+
+impl Drop for RecordingSchedule {
+    fn drop(&mut self) {
+        self.cancel();
+    }
+}
+
+/// Sleep until `deadline`; false when cancelled first
+fn wait_until(stop_rx: &Receiver<()>, deadline: SystemTime) -> bool {
+    loop {
+        let remaining = match deadline.duration_since(SystemTime::now()) {
+            Ok(remaining) if !remaining.is_zero() => remaining,
+            _ => return true,
+        };
+        match stop_rx.recv_timeout(remaining.min(MAX_WAIT)) {
+            Err(RecvTimeoutError::Timeout) => {}
+            _ => return false,
+        }
+    }
+}
@@ -0,0 +1,675 @@
+// Still image capture - Core Graphics snapshots written through ImageIO
+
+use block2::StackBlock;
+use napi::bindgen_prelude::*;
+use napi_derive::napi;
+use objc2::rc::Retained;
+use objc2::runtime::{AnyClass, AnyObject};
+use objc2::{msg_send, class, sel};
+use objc2_foundation::{NSError, NSString, NSURL};
+use std::ffi::c_void;
+use std::path::{Path, PathBuf};
+use std::ptr;
+use std::sync::mpsc;
+use std::time::Duration;
+
+use super::bindings::{SCContentFilter, SCShareableContent, ScreenCaptureKitHelpers, SHAREABLE_CONTENT_TIMEOUT};
+use super::cancel::{CancelToken, Wait};
+use super::content::ShareableContent;
+use super::errors::{capture_error, describe, io_error, CaptureErrorCode};
+use super::cursor::CaptureSource;
+use super::permissions::{guard_silent, PrivacyPane};
+use super::session::now_millis;
+
+pub type CGImageRef = *mut c_void;
+
+const MAX_DISPLAYS: u32 = 32;
+
+pub const DEFAULT_AVERAGE_FRAMES: u32 = 8;
+pub const DEFAULT_AVERAGE_INTERVAL_MS: u32 = 50;
+// u16 accumulators hold up to 257 frames of 8-bit samples; keep well below that
+const MAX_AVERAGE_FRAMES: u32 = 64;
+
+// kCGImageAlphaPremultipliedLast | kCGBitmapByteOrder32Big -> RGBA8888 in memory order
+const RGBA_BITMAP_INFO: u32 = 1 | (4 << 12);
+
+pub const DEFAULT_THUMBNAIL_WIDTH: u32 = 320;
+// SCScreenshotManager normally answers within a frame or two
+const THUMBNAIL_TIMEOUT: Duration = Duration::from_secs(2);
+
+const K_CG_WINDOW_LIST_OPTION_INCLUDING_WINDOW: u32 = 1 << 3;
+const K_CG_WINDOW_IMAGE_BOUNDS_IGNORE_FRAMING: u32 = 1 << 0;
+
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct CGRect {
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+extern "C" {
+    fn CGGetActiveDisplayList(max_displays: u32, active_displays: *mut u32, display_count: *mut u32) -> i32;
+    fn CGDisplayCreateImage(display: u32) -> CGImageRef;
+    fn CGDisplayCreateImageForRect(display: u32, rect: CGRect) -> CGImageRef;
+    fn CGImageGetWidth(image: CGImageRef) -> usize;
+    fn CGImageGetHeight(image: CGImageRef) -> usize;
+    fn CGImageRelease(image: CGImageRef);
+    fn CGImageRetain(image: CGImageRef) -> CGImageRef;
+    fn CGWindowListCreateImage(screen_bounds: CGRect, list_option: u32, window_id: u32, image_option: u32) -> CGImageRef;
+    fn CGImageDestinationCreateWithData(data: *mut c_void, image_type: *const NSString, count: usize, options: *const c_void) -> *mut c_void;
+    fn CFDataCreateMutable(allocator: *const c_void, capacity: isize) -> *mut c_void;
+    fn CFDataGetBytePtr(data: *const c_void) -> *const u8;
+    fn CFDataGetLength(data: *const c_void) -> isize;
+    fn CGImageDestinationCreateWithURL(url: *const NSURL, image_type: *const NSString, count: usize, options: *const c_void) -> *mut c_void;
+    fn CGImageDestinationAddImage(destination: *mut c_void, image: CGImageRef, properties: *const c_void);
+    fn CGImageDestinationFinalize(destination: *mut c_void) -> bool;
+    fn CGColorSpaceCreateDeviceRGB() -> *mut c_void;
+    fn CGColorSpaceRelease(space: *mut c_void);
+    fn CGBitmapContextCreate(
+        data: *mut c_void,
+        width: usize,
+        height: usize,
+        bits_per_component: usize,
+        bytes_per_row: usize,
+        space: *mut c_void,
+        bitmap_info: u32,
+    ) -> *mut c_void;
+    fn CGBitmapContextCreateImage(context: *mut c_void) -> CGImageRef;
+    fn CGContextDrawImage(context: *mut c_void, rect: CGRect, image: CGImageRef);
+    fn CGContextClearRect(context: *mut c_void, rect: CGRect);
+    fn CGContextRelease(context: *mut c_void);
+    fn CFRelease(cf: *const c_void);
+}
+
+/// Encoding of screenshots and thumbnails
+#[napi(string_enum = "lowercase")]
+#[derive(Debug, PartialEq)]
+pub enum ImageFormat {
+    Png,
+    Jpeg,
+}
+
+impl ImageFormat {
+    /// The format a file extension asks for, if it's one we write
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension.to_ascii_lowercase().as_str() {
+            "png" => Some(ImageFormat::Png),
+            "jpeg" | "jpg" => Some(ImageFormat::Jpeg),
+            _ => None,
+        }
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "png",
+            ImageFormat::Jpeg => "jpg",
+        }
+    }
+
+    fn uti(&self) -> &'static str {
+        match self {
+            ImageFormat::Png => "public.png",
+            ImageFormat::Jpeg => "public.jpeg",
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScreenshotFile {
+    pub display_id: u32,
+    pub path: String,
+    pub width: u32,
+    pub height: u32,
+}
+
+#[derive(Debug, Clone)]
+pub struct BulkScreenshot {
+    pub timestamp_ms: u64,
+    pub files: Vec<ScreenshotFile>,
+}
+
+pub fn active_display_ids() -> Vec<u32> {
+    let mut displays = [0u32; MAX_DISPLAYS as usize];
+    let mut count: u32 = 0;
+    let result = unsafe { CGGetActiveDisplayList(MAX_DISPLAYS, displays.as_mut_ptr(), &mut count) };
+    if result != 0 {
+        return Vec::new();
+    }
+    displays[..count as usize].to_vec()
+}
+
+/// Write a CGImage to disk with ImageIO
+pub unsafe fn write_image(image: CGImageRef, path: &Path, format: ImageFormat) -> Result<()> {
+    let path_string = NSString::from_str(&path.to_string_lossy());
+    let url: *mut NSURL = msg_send![class!(NSURL), fileURLWithPath: &*path_string];
+    let uti = NSString::from_str(format.uti());
+
+    let destination = CGImageDestinationCreateWithURL(url, &*uti, 1, ptr::null());
+    if destination.is_null() {
+        return Err(capture_error(CaptureErrorCode::Unknown, format!("Failed to create image destination at {}", path.display())));
+    }
+
+    CGImageDestinationAddImage(destination, image, ptr::null());
+    let written = CGImageDestinationFinalize(destination);
+    CFRelease(destination);
+
+    if written {
+        Ok(())
+    } else {
+        Err(capture_error(CaptureErrorCode::Unknown, format!("Failed to write image to {}", path.display())))
+    }
+}
+
+/// Screenshot every connected display at (as close as possible to) the same instant.
+/// All images are grabbed first and encoded afterwards so slow PNG encoding doesn't skew the set.
+pub fn capture_all_displays(format: ImageFormat, directory: &str) -> Result<BulkScreenshot> {
+    guard_silent(PrivacyPane::ScreenRecording)?;
+    let directory = PathBuf::from(directory);
+    std::fs::create_dir_all(&directory)
+        .map_err(|e| io_error(format!("Failed to create {}", directory.display()), &e))?;
+
+    let display_ids = active_display_ids();
+    if display_ids.is_empty() {
+        return Err(capture_error(CaptureErrorCode::SourceNotFound, "No active displays found"));
+    }
+
+    log::info!("Capturing {} displays", display_ids.len());
+    let timestamp_ms = now_millis();
+
+    unsafe {
+        let images: Vec<(u32, CGImageRef)> = display_ids
+            .iter()
+            .map(|id| (*id, CGDisplayCreateImage(*id)))
+            .collect();
+
+        let mut files = Vec::new();
+        let mut first_error = None;
+
+        for (display_id, image) in images {
+            if image.is_null() {
+                log::warn!("Display {} returned no image (screen recording permission?)", display_id);
+                first_error.get_or_insert_with(|| {
+                    capture_error(CaptureErrorCode::PermissionDenied, format!("Failed to capture display {}", display_id))
+                });
+                continue;
+            }
+
+            let path = directory.join(format!("display-{}-{}.{}", display_id, timestamp_ms, format.extension()));
+            let width = CGImageGetWidth(image) as u32;
+            let height = CGImageGetHeight(image) as u32;
+            let result = write_image(image, &path, format);
+            CGImageRelease(image);
+
+            match result {
+                Ok(()) => files.push(ScreenshotFile {
+                    display_id,
+                    path: path.to_string_lossy().to_string(),
+                    width,
+                    height,
+                }),
+                Err(e) => {
+                    log::error!("{}", e);
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+
+        // Partial success still returns the files that were written
+        if files.is_empty() {
+            return Err(first_error.unwrap_or_else(|| capture_error(CaptureErrorCode::Unknown, "No screenshots captured")));
+        }
+
+        log::info!("Captured {} display screenshots at {}", files.len(), timestamp_ms);
+        Ok(BulkScreenshot { timestamp_ms, files })
+    }
+}
+
+/// Software canvas for pixel access to CGImages (RGBA8, premultiplied)
+struct Canvas {
+    context: *mut c_void,
+    color_space: *mut c_void,
+    pixels: Vec<u8>,
+    width: usize,
+    height: usize,
+}
+
+impl Canvas {
+    unsafe fn new(width: usize, height: usize) -> Option<Self> {
+        let mut pixels = vec![0u8; width * height * 4];
+        let color_space = CGColorSpaceCreateDeviceRGB();
+        let context = CGBitmapContextCreate(
+            pixels.as_mut_ptr() as *mut c_void,
+            width,
+            height,
+            8,
+            width * 4,
+            color_space,
+            RGBA_BITMAP_INFO,
+        );
+        if context.is_null() {
+            CGColorSpaceRelease(color_space);
+            return None;
+        }
+        Some(Self { context, color_space, pixels, width, height })
+    }
+
+    fn rect(&self) -> CGRect {
+        CGRect { x: 0.0, y: 0.0, width: self.width as f64, height: self.height as f64 }
+    }
+
+    unsafe fn draw(&mut self, image: CGImageRef) {
+        CGContextClearRect(self.context, self.rect());
+        CGContextDrawImage(self.context, self.rect(), image);
+    }
+
+    unsafe fn make_image(&self) -> CGImageRef {
+        CGBitmapContextCreateImage(self.context)
+    }
+}
+
+impl Drop for Canvas {
+    fn drop(&mut self) {
+        unsafe {
+            CGContextRelease(self.context);
+            CGColorSpaceRelease(self.color_space);
+        }
+    }
+}
+
+/// Long-exposure still: average `frames` snapshots of a display taken `interval_ms` apart.
+/// Blinking carets, the cursor and short-lived tooltips fade out, static UI stays sharp.
+pub fn capture_averaged_display(display_id: u32, frames: u32, interval_ms: u32, format: ImageFormat, path: &str) -> Result<ScreenshotFile> {
+    guard_silent(PrivacyPane::ScreenRecording)?;
+    let frames = frames.clamp(2, MAX_AVERAGE_FRAMES);
+    let path = PathBuf::from(path);
+    if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+        std::fs::create_dir_all(parent)
+            .map_err(|e| io_error(format!("Failed to create {}", parent.display()), &e))?;
+    }
+
+    log::info!("Averaging {} frames of display {} ({}ms apart)", frames, display_id, interval_ms);
+
+    unsafe {
+        let mut canvas: Option<Canvas> = None;
+        let mut accumulator: Vec<u16> = Vec::new();
+        let mut captured = 0u32;
+
+        for index in 0..frames {
+            if index > 0 {
+                std::thread::sleep(std::time::Duration::from_millis(interval_ms as u64));
+            }
+
+            let image = CGDisplayCreateImage(display_id);
+            if image.is_null() {
+                log::warn!("Display {} returned no image for frame {}", display_id, index);
+                continue;
+            }
+
+            // Size the canvas from the first frame; later frames are scaled onto it
+            if canvas.is_none() {
+                canvas = Canvas::new(CGImageGetWidth(image), CGImageGetHeight(image));
+                if let Some(canvas) = &canvas {
+                    accumulator = vec![0u16; canvas.pixels.len()];
+                }
+            }
+            let canvas = match canvas.as_mut() {
+                Some(canvas) => canvas,
+                None => {
+                    CGImageRelease(image);
+                    return Err(capture_error(CaptureErrorCode::Unknown, "Failed to create bitmap context"));
+                }
+            };
+
+            canvas.draw(image);
+            CGImageRelease(image);
+
+            for (sum, value) in accumulator.iter_mut().zip(canvas.pixels.iter()) {
+                *sum += *value as u16;
+            }
+            captured += 1;
+        }
+
+        let mut canvas = match canvas {
+            Some(canvas) if captured > 0 => canvas,
+            _ => return Err(capture_error(CaptureErrorCode::PermissionDenied, format!("Failed to capture display {} (screen recording permission?)", display_id))),
+        };
+
+        let half = (captured / 2) as u16;
+        for (pixel, sum) in canvas.pixels.iter_mut().zip(accumulator.iter()) {
+            *pixel = ((*sum + half) / captured as u16) as u8;
+        }
+
+        let averaged = canvas.make_image();
+        if averaged.is_null() {
+            return Err(capture_error(CaptureErrorCode::Unknown, "Failed to build averaged image"));
+        }
+        let result = write_image(averaged, &path, format);
+        CGImageRelease(averaged);
+        result?;
+
+        log::info!("Averaged screenshot of {} frames written to {}", captured, path.display());
+        Ok(ScreenshotFile {
+            display_id,
+            path: path.to_string_lossy().to_string(),
+            width: canvas.width as u32,
+            height: canvas.height as u32,
+        })
+    }
+}
+
+/// Encoded preview image of a display or window
+#[derive(Debug, Clone)]
+pub struct Thumbnail {
+    pub data: Vec<u8>,
+    pub width: u32,
+    pub height: u32,
+}
+
+/// A single captured frame, encoded in memory or written to `path`
+#[derive(Debug, Clone)]
+pub struct Screenshot {
+    pub width: u32,
+    pub height: u32,
+    pub data: Option<Vec<u8>>,
+    pub path: Option<String>,
+}
+
+/// How a single SCScreenshotManager frame is taken
+#[derive(Debug, Clone, Copy)]
+struct FrameSpec {
+    /// Downscale to at most this many pixels wide; full resolution when `None`
+    max_width: Option<u32>,
+    /// Region of a display in points, already snapped to pixels
+    source_rect: Option<super::bindings::CGRect>,
+    shows_cursor: bool,
+}
+
+/// CGImage handed from the screenshot manager's completion queue to the waiting caller
+struct DeliveredImage(CGImageRef);
+
+unsafe impl Send for DeliveredImage {}
+
+/// Encode a CGImage in memory with ImageIO
+pub unsafe fn encode_image(image: CGImageRef, format: ImageFormat) -> Result<Vec<u8>> {
+    let data = CFDataCreateMutable(ptr::null(), 0);
+    if data.is_null() {
+        return Err(capture_error(CaptureErrorCode::Unknown, "Failed to allocate image buffer"));
+    }
+    let uti = NSString::from_str(format.uti());
+    let destination = CGImageDestinationCreateWithData(data, &*uti, 1, ptr::null());
+    if destination.is_null() {
+        CFRelease(data);
+        return Err(capture_error(CaptureErrorCode::Unknown, "Failed to create in-memory image destination"));
+    }
+
+    CGImageDestinationAddImage(destination, image, ptr::null());
+    let written = CGImageDestinationFinalize(destination);
+    CFRelease(destination);
+
+    let result = if written {
+        Ok(std::slice::from_raw_parts(CFDataGetBytePtr(data), CFDataGetLength(data) as usize).to_vec())
+    } else {
+        Err(capture_error(CaptureErrorCode::Unknown, "Failed to encode image"))
+    };
+    CFRelease(data);
+    result
+}
+
+/// `image` scaled down to at most `max_width` wide, keeping the aspect ratio. Returns a new
+/// reference either way; the caller releases it.
+unsafe fn scale_to_width(image: CGImageRef, max_width: u32) -> Option<CGImageRef> {
+    let width = CGImageGetWidth(image);
+    let height = CGImageGetHeight(image);
+    if width <= max_width as usize {
+        return Some(CGImageRetain(image));
+    }
+    let scaled_height = ((height as f64 * max_width as f64 / width as f64).round() as usize).max(1);
+    let mut canvas = Canvas::new(max_width as usize, scaled_height)?;
+    canvas.draw(image);
+    let scaled = canvas.make_image();
+    if scaled.is_null() {
+        None
+    } else {
+        Some(scaled)
+    }
+}
+
+/// Size of the source in points, read from the shareable content
+unsafe fn source_size(sc_content: &SCShareableContent, source: CaptureSource) -> Option<(f64, f64)> {
+    match source {
+        CaptureSource::Display(display_id) => {
+            let display = ScreenCaptureKitHelpers::find_display(sc_content, display_id)?;
+            let width: isize = msg_send![&*display, width];
+            let height: isize = msg_send![&*display, height];
+            Some((width as f64, height as f64))
+        }
+        CaptureSource::Window(window_id) => {
+            let window = ScreenCaptureKitHelpers::find_window(sc_content, window_id)?;
+            let frame: super::bindings::CGRect = msg_send![&*window, frame];
+            Some((frame.size.width, frame.size.height))
+        }
+    }
+}
+
+/// Backing pixels per point of whatever `filter` captures (SCShareableContentInfo, macOS 14+)
+unsafe fn point_pixel_scale(filter: &SCContentFilter) -> f64 {
+    let supported: bool = msg_send![class!(SCShareableContent), respondsToSelector: sel!(infoForFilter:)];
+    if !supported {
+        return 1.0;
+    }
+    let info: *mut AnyObject = msg_send![class!(SCShareableContent), infoForFilter: filter];
+    if info.is_null() {
+        return 1.0;
+    }
+    let scale: f32 = msg_send![info, pointPixelScale];
+    if scale > 0.0 {
+        scale as f64
+    } else {
+        1.0
+    }
+}
+
+/// One frame through SCScreenshotManager (macOS 14+). `None` when the class is missing, the
+/// source isn't in the shareable content or the capture fails, so the caller can fall back.
+/// Also `None` once `cancel` is cancelled, which the caller checks before falling back.
+unsafe fn capture_with_screenshot_manager(source: CaptureSource, spec: FrameSpec, cancel: &CancelToken) -> Option<CGImageRef> {
+    let manager = AnyClass::get(c"SCScreenshotManager")?;
+    let sc_content = match ScreenCaptureKitHelpers::get_shareable_content_cancellable(SHAREABLE_CONTENT_TIMEOUT, true, cancel) {
+        Ok(sc_content) => sc_content,
+        Err(e) => {
+            log::warn!("Screenshot without ScreenCaptureKit: {}", describe(&e).1);
+            return None;
+        }
+    };
+
+    let filter: Retained<SCContentFilter> = match source {
+        CaptureSource::Display(display_id) => {
+            ScreenCaptureKitHelpers::create_display_content_filter(&sc_content, display_id, &[], &[]).map(|(filter, _)| filter)?
+        }
+        CaptureSource::Window(window_id) => ScreenCaptureKitHelpers::create_window_content_filter(&sc_content, window_id)?,
+    };
+
+    // Capture straight at thumbnail size instead of grabbing a full-resolution frame
+    let (source_width, source_height) = match spec.source_rect {
+        Some(rect) => (rect.size.width, rect.size.height),
+        None => source_size(&sc_content, source)?,
+    };
+    let scale = match spec.max_width {
+        Some(max_width) => (max_width as f64 / source_width.max(1.0)).min(1.0),
+        None => point_pixel_scale(&filter),
+    };
+    let configuration: Retained<AnyObject> = Retained::from_raw(ScreenCaptureKitHelpers::create_stream_configuration())?;
+    let _: () = msg_send![&*configuration, setWidth: ((source_width * scale).round() as usize).max(1)];
+    let _: () = msg_send![&*configuration, setHeight: ((source_height * scale).round() as usize).max(1)];
+    let _: () = msg_send![&*configuration, setShowsCursor: spec.shows_cursor];
+    if let Some(rect) = spec.source_rect {
+        ScreenCaptureKitHelpers::set_source_rect(Retained::as_ptr(&configuration) as *mut AnyObject, rect);
+    }
+
+    let (sender, receiver) = mpsc::channel::<Option<DeliveredImage>>();
+    let block = StackBlock::new(move |image: CGImageRef, error: *mut NSError| {
+        let delivered = if image.is_null() {
+            if !error.is_null() {
+                log::warn!("SCScreenshotManager failed: {}", (*error).localizedDescription());
+            }
+            None
+        } else {
+            Some(DeliveredImage(CGImageRetain(image)))
+        };
+        // Nobody is waiting after a timeout or cancel; don't leak the late image
+        if let Err(mpsc::SendError(Some(DeliveredImage(image)))) = sender.send(delivered) {
+            CGImageRelease(image);
+        }
+    });
+    let block = block.copy();
+
+    let _: () = msg_send![
+        manager,
+        captureImageWithFilter: &*filter,
+        configuration: &*configuration,
+        completionHandler: &*block
+    ];
+
+    match cancel.recv_timeout(&receiver, THUMBNAIL_TIMEOUT) {
+        Wait::Received(Some(DeliveredImage(image))) => Some(image),
+        Wait::Received(None) | Wait::Cancelled => None,
+        Wait::TimedOut => {
+            log::warn!("SCScreenshotManager did not answer within {} ms", THUMBNAIL_TIMEOUT.as_millis());
+            None
+        }
+    }
+}
+
+/// Core Graphics snapshot, for systems without SCScreenshotManager. Always includes the cursor.
+unsafe fn capture_with_core_graphics(source: CaptureSource, source_rect: Option<super::bindings::CGRect>) -> CGImageRef {
+    match source {
+        CaptureSource::Display(display_id) => match source_rect {
+            Some(rect) => CGDisplayCreateImageForRect(
+                display_id,
+                CGRect { x: rect.origin.x, y: rect.origin.y, width: rect.size.width, height: rect.size.height },
+            ),
+            None => CGDisplayCreateImage(display_id),
+        },
+        CaptureSource::Window(window_id) => {
+            // CGRectNull: the window's own bounds
+            let null_rect = CGRect { x: f64::INFINITY, y: f64::INFINITY, width: 0.0, height: 0.0 };
+            CGWindowListCreateImage(
+                null_rect,
+                K_CG_WINDOW_LIST_OPTION_INCLUDING_WINDOW,
+                window_id,
+                K_CG_WINDOW_IMAGE_BOUNDS_IGNORE_FRAMING,
+            )
+        }
+    }
+}
+
+/// Preview of "display:<id>" or "window:<id>", at most `max_width` pixels wide. Fails with
+/// Cancelled once `cancel` is cancelled.
+pub fn source_thumbnail(screen_id: &str, max_width: u32, format: ImageFormat, cancel: &CancelToken) -> Result<Thumbnail> {
+    guard_silent(PrivacyPane::ScreenRecording)?;
+    let source = CaptureSource::parse(screen_id)
+        .ok_or_else(|| Error::new(Status::InvalidArg, format!("Invalid screen ID '{}'", screen_id)))?;
+    let max_width = max_width.max(1);
+
+    unsafe {
+        let spec = FrameSpec { max_width: Some(max_width), source_rect: None, shows_cursor: false };
+        let image = match capture_with_screenshot_manager(source, spec, cancel) {
+            Some(image) => image,
+            None => {
+                cancel.check()?;
+                capture_with_core_graphics(source, None)
+            }
+        };
+        if image.is_null() {
+            return Err(capture_error(CaptureErrorCode::PermissionDenied, format!("Failed to capture {} (screen recording permission?)", screen_id)));
+        }
+
+        let scaled = scale_to_width(image, max_width);
+        CGImageRelease(image);
+        let scaled = scaled.ok_or_else(|| capture_error(CaptureErrorCode::Unknown, "Failed to scale thumbnail"))?;
+
+        let width = CGImageGetWidth(scaled) as u32;
+        let height = CGImageGetHeight(scaled) as u32;
+        let data = encode_image(scaled, format);
+        CGImageRelease(scaled);
+
+        Ok(Thumbnail { data: data?, width, height })
+    }
+}
+
+/// Full-resolution still of "display:<id>", "window:<id>" or "region:<displayId>". `crop` is
+/// (x, y, width, height) in points and required for a region; it is checked against the display
+/// and snapped to whole pixels. Written to `path` when given, otherwise returned encoded.
+pub fn capture_screenshot(
+    screen_id: &str,
+    crop: Option<(f64, f64, f64, f64)>,
+    shows_cursor: bool,
+    format: ImageFormat,
+    path: Option<&str>,
+) -> Result<Screenshot> {
+    guard_silent(PrivacyPane::ScreenRecording)?;
+    let (source, source_rect) = match screen_id.strip_prefix("region:") {
+        Some(display_id) => {
+            let display_id: u32 = display_id
+                .parse()
+                .map_err(|_| Error::new(Status::InvalidArg, format!("Invalid display ID in '{}'", screen_id)))?;
+            let (x, y, width, height) = crop
+                .ok_or_else(|| Error::new(Status::InvalidArg, "A region screenshot needs crop { x, y, width, height }"))?;
+            let content = ShareableContent::new_with_real_data()?;
+            let display = content
+                .find_display_by_id(display_id)
+                .ok_or_else(|| capture_error(CaptureErrorCode::SourceNotFound, format!("Display ID {} not found", display_id)))?;
+            let rect = display.snap_to_pixels(display.crop_rect(x, y, width, height)?);
+            (CaptureSource::Display(display_id), Some(rect))
+        }
+        None => {
+            if crop.is_some() {
+                return Err(Error::new(Status::InvalidArg, "crop only applies to \"region:<displayId>\" screenshots"));
+            }
+            let source = CaptureSource::parse(screen_id)
+                .ok_or_else(|| Error::new(Status::InvalidArg, format!("Invalid screen ID '{}'", screen_id)))?;
+            (source, None)
+        }
+    };
+
+    log::info!("Capturing screenshot of {}", screen_id);
+    unsafe {
+        let spec = FrameSpec { max_width: None, source_rect, shows_cursor };
+        let image = match capture_with_screenshot_manager(source, spec, &CancelToken::default()) {
+            Some(image) => image,
+            None => capture_with_core_graphics(source, source_rect),
+        };
+        if image.is_null() {
+            return Err(capture_error(CaptureErrorCode::PermissionDenied, format!("Failed to capture {} (screen recording permission?)", screen_id)));
+        }
+
+        let width = CGImageGetWidth(image) as u32;
+        let height = CGImageGetHeight(image) as u32;
+        let result = match path {
+            Some(path) => {
+                let path = PathBuf::from(path);
+                if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+                    if let Err(e) = std::fs::create_dir_all(parent) {
+                        CGImageRelease(image);
+                        return Err(io_error(format!("Failed to create {}", parent.display()), &e));
+                    }
+                }
+                write_image(image, &path, format).map(|()| Screenshot {
+                    width,
+                    height,
+                    data: None,
+                    path: Some(path.to_string_lossy().to_string()),
+                })
+            }
+            None => encode_image(image, format).map(|data| Screenshot { width, height, data: Some(data), path: None }),
+        };
+        CGImageRelease(image);
+
+        if result.is_ok() {
+            log::info!("Screenshot of {} captured at {}x{}", screen_id, width, height);
+        }
+        result
+    }
+}
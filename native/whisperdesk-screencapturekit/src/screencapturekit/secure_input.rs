@@ -0,0 +1,128 @@
+// Secure input protection - keep password prompts out of recordings
+
+use napi::bindgen_prelude::*;
+use objc2_core_video::CVPixelBuffer;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use super::formats::PixelFormat;
+use super::pixel_buffer::{
+    CVPixelBufferLockBaseAddress, CVPixelBufferUnlockBaseAddress, CVPixelBufferGetBaseAddress, CVPixelBufferGetBytesPerRow,
+    CVPixelBufferGetWidth, CVPixelBufferGetHeight, CVPixelBufferGetPixelFormatType,
+};
+
+// IsSecureEventInputEnabled is a cheap call, but there's no need to make it for every frame
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+extern "C" {
+    // HIToolbox (Carbon); true while any app has secure event input enabled, i.e. a password field has focus
+    fn IsSecureEventInputEnabled() -> u8;
+}
+
+pub fn is_secure_input_active() -> bool {
+    unsafe { IsSecureEventInputEnabled() != 0 }
+}
+
+/// What happens to the recording while secure input is active
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SecureInputAction {
+    /// Drop frames and audio until secure input ends
+    Pause,
+    /// Keep recording but replace frames with black
+    Blank,
+}
+
+impl SecureInputAction {
+    /// "off" (default), "pause" or "blank"
+    pub fn parse(value: Option<&str>) -> Result<Option<Self>> {
+        match value.unwrap_or("off") {
+            "off" => Ok(None),
+            "pause" => Ok(Some(SecureInputAction::Pause)),
+            "blank" => Ok(Some(SecureInputAction::Blank)),
+            other => Err(Error::new(
+                Status::InvalidArg,
+                format!("Unknown secure_input_protection '{}', expected \"off\", \"pause\" or \"blank\"", other),
+            )),
+        }
+    }
+}
+
+pub type SecureInputListener = Arc<dyn Fn(bool) + Send + Sync>;
+
+/// Polled from the capture callbacks; reports transitions to an optional listener
+pub struct SecureInputGuard {
+    action: SecureInputAction,
+    active: bool,
+    last_check: Option<Instant>,
+    listener: Option<SecureInputListener>,
+}
+
+impl SecureInputGuard {
+    pub fn new(action: SecureInputAction) -> Self {
+        Self {
+            action,
+            active: false,
+            last_check: None,
+            listener: None,
+        }
+    }
+
+    pub fn action(&self) -> SecureInputAction {
+        self.action
+    }
+
+    pub fn set_listener(&mut self, listener: Option<SecureInputListener>) {
+        self.listener = listener;
+    }
+
+    /// Whether protection is in effect right now, re-checking the system flag at most every 250 ms
+    pub fn poll(&mut self) -> bool {
+        let due = self.last_check.map(|t| t.elapsed() >= POLL_INTERVAL).unwrap_or(true);
+        if !due {
+            return self.active;
+        }
+        self.last_check = Some(Instant::now());
+
+        let active = is_secure_input_active();
+        if active != self.active {
+            self.active = active;
+            let verb = match self.action {
+                SecureInputAction::Pause => "paused",
+                SecureInputAction::Blank => "blanked",
+            };
+            if active {
+                log::info!("Secure input active - recording {}", verb);
+            } else {
+                log::info!("Secure input ended - recording no longer {}", verb);
+            }
+            if let Some(listener) = &self.listener {
+                listener(active);
+            }
+        }
+        self.active
+    }
+}
+
+/// Overwrite a BGRA frame with opaque black in place
+pub unsafe fn blank_frame(pixel_buffer: *mut CVPixelBuffer) {
+    if pixel_buffer.is_null() || CVPixelBufferGetPixelFormatType(pixel_buffer) != PixelFormat::Bgra.four_cc() {
+        return;
+    }
+    if CVPixelBufferLockBaseAddress(pixel_buffer, 0) != 0 {
+        return;
+    }
+
+    let base = CVPixelBufferGetBaseAddress(pixel_buffer) as *mut u8;
+    if !base.is_null() {
+        let bytes_per_row = CVPixelBufferGetBytesPerRow(pixel_buffer);
+        let width = CVPixelBufferGetWidth(pixel_buffer);
+        for y in 0..CVPixelBufferGetHeight(pixel_buffer) {
+            let row = std::slice::from_raw_parts_mut(base.add(y * bytes_per_row), width * 4);
+            for pixel in row.chunks_exact_mut(4) {
+                pixel.copy_from_slice(&[0, 0, 0, 255]);
+            }
+        }
+    }
+
+    CVPixelBufferUnlockBaseAddress(pixel_buffer, 0);
+}
@@ -0,0 +1,343 @@
+// Recording session bookkeeping - append-only journal plus a library index of finished recordings
+
+use napi::bindgen_prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fs::{self, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::Mutex;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use super::errors::{capture_error, io_error, CaptureErrorCode};
+
+const JOURNAL_FILE: &str = "sessions.jsonl";
+const LIBRARY_FILE: &str = "library.json";
+
+static SESSION_COUNTER: AtomicU32 = AtomicU32::new(0);
+
+// Sessions recording in this process; any other session without an end event was cut short
+static LIVE_SESSIONS: Mutex<Vec<String>> = Mutex::new(Vec::new());
+
+// Held across the library index's read-modify-write, so sessions stopping together both land in it
+static LIBRARY_LOCK: Mutex<()> = Mutex::new(());
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionRecord {
+    pub id: String,
+    pub output_path: String,
+    pub started_at: u64,
+    pub stopped_at: Option<u64>,
+    pub tags: Vec<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum JournalEvent {
+    #[serde(rename_all = "camelCase")]
+    Started { session_id: String, timestamp: u64, output_path: String, tags: Vec<String> },
+    #[serde(rename_all = "camelCase")]
+    Tagged { session_id: String, timestamp: u64, tag: String },
+    #[serde(rename_all = "camelCase")]
+    Stopped { session_id: String, timestamp: u64, output_path: String },
+    /// The recording never got going; no other event shares this session id
+    #[serde(rename_all = "camelCase")]
+    StartFailed { session_id: String, timestamp: u64, output_path: String, error: String },
+    /// Something went wrong mid-recording; a Stopped event may still follow
+    #[serde(rename_all = "camelCase")]
+    Failed { session_id: String, timestamp: u64, error: String },
+}
+
+impl JournalEvent {
+    pub fn session_id(&self) -> &str {
+        match self {
+            JournalEvent::Started { session_id, .. }
+            | JournalEvent::Tagged { session_id, .. }
+            | JournalEvent::Stopped { session_id, .. }
+            | JournalEvent::StartFailed { session_id, .. }
+            | JournalEvent::Failed { session_id, .. } => session_id,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SessionOutcome {
+    Completed,
+    Failed,
+    /// Started but never stopped - the process crashed or was killed
+    Interrupted,
+    Recording,
+}
+
+impl SessionOutcome {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SessionOutcome::Completed => "completed",
+            SessionOutcome::Failed => "failed",
+            SessionOutcome::Interrupted => "interrupted",
+            SessionOutcome::Recording => "recording",
+        }
+    }
+}
+
+/// One past recording, folded from its journal events
+#[derive(Debug, Clone)]
+pub struct SessionSummary {
+    pub session_id: String,
+    pub output_path: String,
+    pub started_at: u64,
+    pub ended_at: Option<u64>,
+    pub tags: Vec<String>,
+    pub outcome: SessionOutcome,
+    /// First error reported for the session
+    pub error: Option<String>,
+}
+
+impl SessionSummary {
+    pub fn duration_ms(&self) -> Option<u64> {
+        self.ended_at.map(|end| end.saturating_sub(self.started_at))
+    }
+
+    fn apply(&mut self, event: JournalEvent) {
+        match event {
+            JournalEvent::Started { .. } => {}
+            JournalEvent::Tagged { tag, .. } => {
+                if !self.tags.contains(&tag) {
+                    self.tags.push(tag);
+                }
+            }
+            JournalEvent::Stopped { timestamp, output_path, .. } => {
+                self.ended_at = Some(timestamp);
+                self.output_path = output_path;
+                if self.outcome != SessionOutcome::Failed {
+                    self.outcome = SessionOutcome::Completed;
+                }
+            }
+            JournalEvent::StartFailed { timestamp, error, .. } | JournalEvent::Failed { timestamp, error, .. } => {
+                self.ended_at.get_or_insert(timestamp);
+                self.error.get_or_insert(error);
+                self.outcome = SessionOutcome::Failed;
+            }
+        }
+    }
+}
+
+impl SessionRecord {
+    pub fn new(output_path: &str, tags: Vec<String>) -> Self {
+        let started_at = now_millis();
+        let sequence = SESSION_COUNTER.fetch_add(1, Ordering::Relaxed);
+        Self {
+            id: format!("{:x}-{:x}-{}", started_at, std::process::id(), sequence),
+            output_path: output_path.to_string(),
+            started_at,
+            stopped_at: None,
+            tags: normalize_tags(tags),
+        }
+    }
+
+    /// Add a tag, returning false when it was already present
+    pub fn add_tag(&mut self, tag: &str) -> Result<bool> {
+        let tag = normalize_tag(tag)
+            .ok_or_else(|| Error::new(Status::InvalidArg, "Tags must not be empty"))?;
+        if self.tags.contains(&tag) {
+            return Ok(false);
+        }
+        self.tags.push(tag);
+        Ok(true)
+    }
+}
+
+/// Unique across sessions, recorders and processes; for scratch files and device UIDs that several
+/// recordings running at once must not share
+pub fn unique_key() -> String {
+    let sequence = SESSION_COUNTER.fetch_add(1, Ordering::Relaxed);
+    format!("{:x}-{:x}-{}", now_millis(), std::process::id(), sequence)
+}
+
+pub fn now_millis() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+fn normalize_tag(tag: &str) -> Option<String> {
+    let tag = tag.trim();
+    if tag.is_empty() {
+        None
+    } else {
+        Some(tag.to_string())
+    }
+}
+
+pub fn normalize_tags(tags: Vec<String>) -> Vec<String> {
+    let mut normalized: Vec<String> = Vec::new();
+    for tag in tags.iter().filter_map(|t| normalize_tag(t)) {
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    normalized
+}
+
+/// Directory holding the journal and library index
+pub fn data_dir() -> Result<PathBuf> {
+    let home = std::env::var("HOME")
+        .map_err(|_| capture_error(CaptureErrorCode::Unknown, "HOME is not set"))?;
+    let dir = PathBuf::from(home)
+        .join("Library")
+        .join("Application Support")
+        .join("WhisperDesk")
+        .join("screencapturekit");
+    fs::create_dir_all(&dir)
+        .map_err(|e| io_error(format!("Failed to create {}", dir.display()), &e))?;
+    Ok(dir)
+}
+
+pub struct SessionJournal;
+
+impl SessionJournal {
+    pub fn append(event: &JournalEvent) -> Result<()> {
+        let path = data_dir()?.join(JOURNAL_FILE);
+        let line = serde_json::to_string(event)
+            .map_err(|e| capture_error(CaptureErrorCode::Unknown, format!("Failed to serialize journal event: {}", e)))?;
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| io_error("Failed to open journal", &e))?;
+        writeln!(file, "{}", line)
+            .map_err(|e| io_error("Failed to write journal", &e))?;
+        Ok(())
+    }
+
+    /// Journal failures must never interrupt a recording - log and carry on
+    pub fn append_or_warn(event: &JournalEvent) {
+        if let Err(e) = Self::append(event) {
+            log::warn!("Session journal write failed: {}", e);
+        }
+    }
+
+    /// Every event in the journal, oldest first. Unreadable lines (e.g. cut off by a crash) are skipped.
+    pub fn read() -> Vec<JournalEvent> {
+        let file = match data_dir().and_then(|dir| {
+            fs::File::open(dir.join(JOURNAL_FILE))
+                .map_err(|e| io_error("Failed to open journal", &e))
+        }) {
+            Ok(file) => file,
+            Err(_) => return Vec::new(),
+        };
+
+        BufReader::new(file)
+            .lines()
+            .map_while(|line| line.ok())
+            .filter_map(|line| serde_json::from_str(&line).ok())
+            .collect()
+    }
+
+    /// The most recent `limit` sessions, newest first
+    pub fn history(limit: usize) -> Vec<SessionSummary> {
+        let mut sessions: Vec<SessionSummary> = Vec::new();
+        for event in Self::read() {
+            let opened = match &event {
+                JournalEvent::Started { session_id, timestamp, output_path, tags } => {
+                    Some((session_id, timestamp, output_path, tags.clone()))
+                }
+                JournalEvent::StartFailed { session_id, timestamp, output_path, .. } => {
+                    Some((session_id, timestamp, output_path, Vec::new()))
+                }
+                _ => None,
+            };
+            if let Some((session_id, timestamp, output_path, tags)) = opened {
+                sessions.push(SessionSummary {
+                    session_id: session_id.clone(),
+                    output_path: output_path.clone(),
+                    started_at: *timestamp,
+                    ended_at: None,
+                    tags,
+                    outcome: SessionOutcome::Interrupted,
+                    error: None,
+                });
+            }
+
+            let session_id = event.session_id().to_string();
+            if let Some(summary) = sessions.iter_mut().rev().find(|s| s.session_id == session_id) {
+                summary.apply(event);
+            }
+        }
+
+        let live = LIVE_SESSIONS.lock().map(|live| live.clone()).unwrap_or_default();
+        for summary in sessions.iter_mut() {
+            if summary.outcome == SessionOutcome::Interrupted && live.contains(&summary.session_id) {
+                summary.outcome = SessionOutcome::Recording;
+            }
+        }
+
+        sessions.reverse();
+        sessions.truncate(limit);
+        sessions
+    }
+
+    /// Track whether a session is recording in this process, so history can tell it from a crashed one
+    pub fn set_live(session_id: &str, live: bool) {
+        if let Ok(mut sessions) = LIVE_SESSIONS.lock() {
+            sessions.retain(|id| id != session_id);
+            if live {
+                sessions.push(session_id.to_string());
+            }
+        }
+    }
+}
+
+pub struct LibraryIndex;
+
+impl LibraryIndex {
+    pub fn load() -> Vec<SessionRecord> {
+        let path = match data_dir() {
+            Ok(dir) => dir.join(LIBRARY_FILE),
+            Err(_) => return Vec::new(),
+        };
+
+        fs::read_to_string(&path)
+            .ok()
+            .and_then(|contents| serde_json::from_str(&contents).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn upsert(record: &SessionRecord) -> Result<()> {
+        let _guard = LIBRARY_LOCK.lock().unwrap_or_else(|poisoned| poisoned.into_inner());
+        let mut records = Self::load();
+        match records.iter_mut().find(|r| r.id == record.id) {
+            Some(existing) => *existing = record.clone(),
+            None => records.push(record.clone()),
+        }
+
+        let path = data_dir()?.join(LIBRARY_FILE);
+        let contents = serde_json::to_string_pretty(&records)
+            .map_err(|e| capture_error(CaptureErrorCode::Unknown, format!("Failed to serialize library index: {}", e)))?;
+
+        // Write to a temporary file first so a crash never leaves a truncated index; it's named per
+        // write, as another WhisperDesk process may be updating the index too
+        let tmp_path = path.with_extension(format!("json.{}.tmp", unique_key()));
+        fs::write(&tmp_path, contents)
+            .and_then(|_| fs::rename(&tmp_path, &path))
+            .map_err(|e| io_error("Failed to write library index", &e))?;
+        Ok(())
+    }
+
+    pub fn upsert_or_warn(record: &SessionRecord) {
+        if let Err(e) = Self::upsert(record) {
+            log::warn!("Library index update failed: {}", e);
+        }
+    }
+
+    pub fn find_by_tag(tag: &str) -> Vec<SessionRecord> {
+        let tag = tag.trim();
+        Self::load()
+            .into_iter()
+            .filter(|record| record.tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+            .collect()
+    }
+}
@@ -0,0 +1,150 @@
+// Shutdown - recordings still running when the process goes away are finalized first, so quitting
+// mid-recording leaves a playable file instead of an MP4 without its moov atom
+
+use std::ffi::{c_int, c_void};
+use std::sync::atomic::{AtomicI32, Ordering};
+use std::sync::{Arc, Mutex, Once, Weak};
+use std::time::{Duration, Instant};
+
+use super::content::RealStreamManager;
+
+type Slot = Mutex<Option<RealStreamManager>>;
+
+const SIGHUP: c_int = 1;
+const SIGINT: c_int = 2;
+const SIGTERM: c_int = 15;
+const HANDLED_SIGNALS: [c_int; 3] = [SIGHUP, SIGINT, SIGTERM];
+
+const SIG_DFL: usize = 0;
+const SIG_IGN: usize = 1;
+const SIG_ERR: usize = usize::MAX;
+
+// A stop already in progress on another thread gets this long before its recording is given up on
+const LOCK_WAIT: Duration = Duration::from_secs(5);
+
+static INSTALL: Once = Once::new();
+static SLOTS: Mutex<Vec<Weak<Slot>>> = Mutex::new(Vec::new());
+// Write end of the pipe the signal handler wakes the shutdown thread through
+static SIGNAL_PIPE: AtomicI32 = AtomicI32::new(-1);
+
+extern "C" {
+    fn atexit(callback: extern "C" fn()) -> c_int;
+    fn signal(signum: c_int, handler: usize) -> usize;
+    fn raise(signum: c_int) -> c_int;
+    fn pipe(fds: *mut c_int) -> c_int;
+    fn read(fd: c_int, buf: *mut c_void, count: usize) -> isize;
+    fn write(fd: c_int, buf: *const c_void, count: usize) -> isize;
+}
+
+/// Finalize the recording in `slot` if the process exits while it runs. Only a weak reference is
+/// kept, so a recorder that is garbage collected is simply forgotten (its drop stops it anyway).
+pub fn track(slot: &Arc<Slot>) {
+    if let Ok(mut slots) = SLOTS.lock() {
+        slots.retain(|slot| slot.strong_count() > 0);
+        slots.push(Arc::downgrade(slot));
+    }
+}
+
+/// Hook process exit and SIGINT/SIGTERM/SIGHUP. The signal handlers only chain to whatever was
+/// installed before them; a handler installed later (e.g. `process.on('SIGINT')`) replaces ours,
+/// but then JS decides when to exit and the exit hook still runs.
+pub fn install() {
+    INSTALL.call_once(|| unsafe {
+        if atexit(on_exit) != 0 {
+            log::warn!("Could not register the exit hook - recordings won't be finalized on exit");
+        }
+        let mut fds: [c_int; 2] = [-1, -1];
+        if pipe(fds.as_mut_ptr()) != 0 {
+            log::warn!("Could not create the signal pipe - recordings won't be finalized on SIGINT/SIGTERM");
+            return;
+        }
+        SIGNAL_PIPE.store(fds[1], Ordering::Relaxed);
+        let mut previous = Vec::new();
+        for signum in HANDLED_SIGNALS {
+            let handler = signal(signum, on_signal as extern "C" fn(c_int) as usize);
+            match handler {
+                SIG_ERR => log::warn!("Could not handle signal {}", signum),
+                // Someone asked for this signal to be ignored; leave it that way
+                SIG_IGN => {
+                    signal(signum, SIG_IGN);
+                }
+                handler => previous.push((signum, handler)),
+            }
+        }
+        let read_fd = fds[0];
+        let spawned = std::thread::Builder::new()
+            .name("whisperdesk-shutdown".to_string())
+            .spawn(move || watch_signals(read_fd, previous));
+        if let Err(e) = spawned {
+            log::warn!("Could not start the shutdown thread: {}", e);
+        }
+    });
+}
+
+/// Stop and finalize every recording still running
+pub fn finalize_all(reason: &str) {
+    let slots: Vec<Arc<Slot>> = match SLOTS.lock() {
+        Ok(slots) => slots.iter().filter_map(Weak::upgrade).collect(),
+        Err(_) => return,
+    };
+    for slot in slots {
+        let Some(mut manager) = take_manager(&slot) else { continue };
+        if !manager.is_recording() {
+            continue;
+        }
+        log::warn!("{} during a recording - finalizing it", reason);
+        match manager.stop_recording() {
+            Ok(path) => log::info!("Recording finalized on {}: {}", reason, path),
+            Err(e) => log::error!("Failed to finalize recording on {}: {}", reason, e),
+        }
+    }
+}
+
+fn take_manager(slot: &Slot) -> Option<RealStreamManager> {
+    let deadline = Instant::now() + LOCK_WAIT;
+    loop {
+        if let Ok(mut manager) = slot.try_lock() {
+            return manager.take();
+        }
+        if Instant::now() >= deadline {
+            log::error!("Recording is still busy stopping - leaving it unfinalized");
+            return None;
+        }
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+extern "C" fn on_exit() {
+    finalize_all("Process exit");
+}
+
+// Runs in signal context: nothing but the write is async-signal-safe here
+extern "C" fn on_signal(signum: c_int) {
+    let fd = SIGNAL_PIPE.load(Ordering::Relaxed);
+    if fd >= 0 {
+        let byte = signum as u8;
+        unsafe {
+            write(fd, &byte as *const u8 as *const c_void, 1);
+        }
+    }
+}
+
+fn watch_signals(fd: c_int, previous: Vec<(c_int, usize)>) {
+    loop {
+        let mut byte = 0u8;
+        let read = unsafe { read(fd, &mut byte as *mut u8 as *mut c_void, 1) };
+        if read <= 0 {
+            return;
+        }
+        let signum = byte as c_int;
+        finalize_all(&format!("Signal {}", signum));
+        let handler = previous.iter().find(|(handled, _)| *handled == signum).map(|(_, handler)| *handler).unwrap_or(SIG_DFL);
+        // Hand the signal on: the default action terminates, a previous handler (Node's) runs
+        // before raise returns, after which ours goes back in place
+        unsafe {
+            signal(signum, handler);
+            raise(signum);
+            signal(signum, on_signal as extern "C" fn(c_int) as usize);
+        }
+    }
+}
@@ -0,0 +1,92 @@
+// Runtime status - the OS and the ScreenCaptureKit features this Mac actually has, probed rather than assumed
+
+use objc2::runtime::{AnyClass, Sel};
+use objc2::sel;
+use std::sync::OnceLock;
+
+use super::process_tap::ProcessTap;
+use super::recommend::EncoderCapabilities;
+
+/// Reported by `getVersion()` and written to the metadata sidecar
+pub const VERSION: &str = "0.2.1-segfault-safe-screencapturekit";
+
+static OS_VERSION: OnceLock<Option<String>> = OnceLock::new();
+static FEATURES: OnceLock<Features> = OnceLock::new();
+
+/// What this Mac supports; none of it changes while the process runs
+#[derive(Debug, Clone)]
+pub struct Features {
+    /// SCStream (macOS 12.3)
+    pub screen_capture_kit: bool,
+    /// SCScreenshotManager (macOS 14)
+    pub screenshot_manager: bool,
+    /// SCContentSharingPicker (macOS 14)
+    pub content_sharing_picker: bool,
+    /// Microphone captured by the stream itself, as SCStreamOutputTypeMicrophone (macOS 15)
+    pub stream_microphone: bool,
+    /// Presenter Overlay while capturing a camera alongside the screen (macOS 14)
+    pub presenter_overlay: bool,
+    /// SCRecordingOutput (macOS 15)
+    pub recording_output: bool,
+    /// HDR capture through captureDynamicRange (macOS 15)
+    pub hdr_capture: bool,
+    /// Core Audio process taps (macOS 14.2)
+    pub process_tap: bool,
+    pub hardware_h264: bool,
+    pub hardware_hevc: bool,
+}
+
+/// "14.5" and the like; None when sw_vers can't be run
+pub fn os_version() -> Option<String> {
+    OS_VERSION
+        .get_or_init(|| {
+            let output = std::process::Command::new("sw_vers").arg("-productVersion").output().ok()?;
+            let version = String::from_utf8(output.stdout).ok()?.trim().to_string();
+            (!version.is_empty()).then_some(version)
+        })
+        .clone()
+}
+
+/// (major, minor, patch) of `os_version`; a missing patch is 0
+pub fn os_version_parts() -> Option<(u32, u32, u32)> {
+    let version = os_version()?;
+    let mut parts = version.split('.').map(|part| part.parse::<u32>());
+    let major = parts.next()?.ok()?;
+    let minor = parts.next().unwrap_or(Ok(0)).ok()?;
+    let patch = parts.next().unwrap_or(Ok(0)).ok()?;
+    Some((major, minor, patch))
+}
+
+/// ScreenCaptureKit as this module uses it arrived in macOS 12.3
+pub fn supports_screen_capture_kit((major, minor, _): (u32, u32, u32)) -> bool {
+    (major, minor) >= (12, 3)
+}
+
+/// Probed once, by looking the classes and selectors up in the Objective-C runtime
+pub fn features() -> Features {
+    FEATURES
+        .get_or_init(|| {
+            let encoders = EncoderCapabilities::probe();
+            Features {
+                screen_capture_kit: has_class(c"SCStream"),
+                screenshot_manager: has_class(c"SCScreenshotManager"),
+                content_sharing_picker: has_class(c"SCContentSharingPicker"),
+                stream_microphone: configuration_responds_to(sel!(setCaptureMicrophone:)),
+                presenter_overlay: configuration_responds_to(sel!(setPresenterOverlayPrivacyAlertSetting:)),
+                recording_output: has_class(c"SCRecordingOutput"),
+                hdr_capture: configuration_responds_to(sel!(setCaptureDynamicRange:)),
+                process_tap: ProcessTap::is_supported(),
+                hardware_h264: encoders.hardware_h264,
+                hardware_hevc: encoders.hardware_hevc,
+            }
+        })
+        .clone()
+}
+
+fn has_class(name: &std::ffi::CStr) -> bool {
+    AnyClass::get(name).is_some()
+}
+
+fn configuration_responds_to(selector: Sel) -> bool {
+    AnyClass::get(c"SCStreamConfiguration").map(|class| class.responds_to(selector)).unwrap_or(false)
+}
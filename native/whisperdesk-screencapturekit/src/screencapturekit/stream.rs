@@ -7,26 +7,27 @@ use objc2_foundation::{NSArray, NSString, NSDictionary, NSNumber};
 use std::ptr;
 
 use super::bindings::{SCShareableContent, SCDisplay, SCWindow, SCContentFilter, ScreenCaptureKitHelpers};
+use super::errors::{capture_error, CaptureErrorCode};
 
 pub struct ContentManager;
 
 impl ContentManager {
     pub fn get_shareable_content_sync() -> Result<ShareableContent> {
-        println!("🔍 Getting shareable content via ScreenCaptureKit APIs (sync)");
+        log::debug!("Getting shareable content via ScreenCaptureKit APIs (sync)");
         
         let content = ShareableContent::new_with_real_data()?;
         
-        println!("✅ Retrieved real shareable content");
+        log::info!("Retrieved real shareable content");
         Ok(content)
     }
 
     pub async fn get_shareable_content() -> Result<ShareableContent> {
-        println!("🔍 Getting shareable content via ScreenCaptureKit APIs");
+        log::debug!("Getting shareable content via ScreenCaptureKit APIs");
         Self::get_shareable_content_sync()
     }
 
     pub async fn get_shareable_content_async() -> Result<ShareableContent> {
-        println!("🔍 Getting shareable content via async ScreenCaptureKit APIs");
+        log::debug!("Getting shareable content via async ScreenCaptureKit APIs");
         ShareableContent::new_with_timeout(5000)
     }
     
@@ -60,7 +61,7 @@ impl ContentManager {
             }
         }
         
-        println!("✅ Extracted {} screen sources from real ScreenCaptureKit data", sources.len());
+        log::info!("Extracted {} screen sources from real ScreenCaptureKit data", sources.len());
         Ok(sources)
     }
 
@@ -105,7 +106,7 @@ impl ShareableContent {
     }
     
     pub fn new_with_real_data() -> Result<Self> {
-        println!("🔍 Fetching real shareable content from ScreenCaptureKit (sync)");
+        log::debug!("Fetching real shareable content from ScreenCaptureKit (sync)");
         
         unsafe {
             let mut content = Self::new();
@@ -121,14 +122,14 @@ impl ShareableContent {
                     content.displays = safe_content.displays;
                     content.windows = safe_content.windows;
                     
-                    println!("✅ Retrieved ScreenCaptureKit content with {} displays and {} windows", 
+                    log::info!("Retrieved ScreenCaptureKit content with {} displays and {} windows", 
                         content.displays.len(), content.windows.len());
                     
                     Ok(content)
                 }
                 Err(error) => {
-                    println!("⚠️ ScreenCaptureKit content retrieval failed: {}", error);
-                    println!("💡 Using safe system content only");
+                    log::warn!("ScreenCaptureKit content retrieval failed: {}", error);
+                    log::info!("Using safe system content only");
                     
                     // Use safe system content without ScreenCaptureKit pointer
                     let safe_content = Self::create_safe_system_content();
@@ -143,7 +144,7 @@ impl ShareableContent {
 
     /// Create safe system content using macOS system APIs instead of ScreenCaptureKit extraction
     fn create_safe_system_content() -> Self {
-        println!("🔍 Creating safe system content using Core Graphics APIs");
+        log::debug!("Creating safe system content using Core Graphics APIs");
         
         let mut content = Self::new();
         
@@ -215,7 +216,7 @@ impl ShareableContent {
     }
 
     unsafe fn get_real_window_info() -> Vec<WindowInfo> {
-        println!("🔍 Getting real window information via Core Graphics APIs");
+        log::debug!("Getting real window information via Core Graphics APIs");
         
         extern "C" {
             fn CGWindowListCopyWindowInfo(option: u32, relativeToWindow: u32) -> *mut objc2_foundation::NSArray;
@@ -368,7 +369,7 @@ impl ShareableContent {
     }
 
     pub fn new_with_timeout(timeout_ms: u32) -> Result<Self> {
-        println!("🔍 Fetching real shareable content from ScreenCaptureKit with {}ms timeout", timeout_ms);
+        log::debug!("Fetching real shareable content from ScreenCaptureKit with {}ms timeout", timeout_ms);
         
         unsafe {
             let mut content = Self::new();
@@ -376,25 +377,25 @@ impl ShareableContent {
             // Use simpler approach without thread safety issues
             match ScreenCaptureKitHelpers::get_shareable_content_sync() {
                 Ok(sc_content) => {
-                    println!("✅ Got ScreenCaptureKit content synchronously within timeout");
-                    content.sc_content_ptr = Some(sc_content);
+                    log::info!("Got ScreenCaptureKit content synchronously within timeout");
+                    content.sc_content_ptr = Some(objc2::rc::Retained::into_raw(sc_content));
                     
                     // Use safe system content for display/window enumeration
                     let safe_content = Self::create_safe_system_content();
                     content.displays = safe_content.displays;
                     content.windows = safe_content.windows;
                     
-                    println!("✅ Retrieved {} displays and {} windows with ScreenCaptureKit content", 
+                    log::info!("Retrieved {} displays and {} windows with ScreenCaptureKit content", 
                         content.displays.len(), content.windows.len());
                     
                     Ok(content)
                 }
                 Err(_) => {
-                    println!("⚠️ ScreenCaptureKit sync failed, using safe content only");
+                    log::warn!("ScreenCaptureKit sync failed, using safe content only");
                     
                     // Start async call for future use but don't wait
                     ScreenCaptureKitHelpers::get_shareable_content_async(|_content, _error| {
-                        println!("🔄 Background ScreenCaptureKit call completed");
+                        log::info!("Background ScreenCaptureKit call completed");
                     });
                     
                     let safe_content = Self::create_safe_system_content();
@@ -408,25 +409,25 @@ impl ShareableContent {
     }
     
     unsafe fn fetch_real_sc_shareable_content() -> Result<*mut SCShareableContent> {
-        println!("🔍 Fetching real shareable content using ScreenCaptureKit API");
+        log::debug!("Fetching real shareable content using ScreenCaptureKit API");
         
         // Use simpler approach without thread safety issues
         match ScreenCaptureKitHelpers::get_shareable_content_sync() {
             Ok(content) => {
-                println!("✅ Got ScreenCaptureKit content synchronously");
-                Ok(content)
+                log::info!("Got ScreenCaptureKit content synchronously");
+                Ok(objc2::rc::Retained::into_raw(content))
             }
             Err(e) => {
-                println!("⚠️ Synchronous approach failed: {}", e);
-                println!("💡 Using async approach without waiting (safer)");
+                log::warn!("Synchronous approach failed: {}", e);
+                log::info!("Using async approach without waiting (safer)");
                 
                 // Start the async call but don't wait for it to avoid thread safety issues
                 ScreenCaptureKitHelpers::get_shareable_content_async(|_content, _error| {
-                    println!("🔄 Async ScreenCaptureKit call completed");
+                    log::info!("Async ScreenCaptureKit call completed");
                 });
                 
                 // Return an error to indicate we should use the fallback approach
-                Err(Error::new(Status::GenericFailure, "Async ScreenCaptureKit requires fallback".to_string()))
+                Err(capture_error(CaptureErrorCode::Unknown, "Async ScreenCaptureKit requires fallback".to_string()))
             }
         }
     }
@@ -453,17 +454,17 @@ impl ShareableContent {
     /// Create a content filter for a display using the stored ScreenCaptureKit content
     /// This bypasses the need to extract individual SCDisplay objects
     pub unsafe fn create_display_content_filter(&self, display_id: u32) -> Result<*mut SCContentFilter> {
-        println!("🎯 Creating display content filter for display ID {} (segfault-safe)", display_id);
+        log::info!("Creating display content filter for display ID {} (segfault-safe)", display_id);
         
         // Verify we have the display in our safe enumeration
         if self.find_display_by_id(display_id).is_none() {
-            return Err(Error::new(Status::InvalidArg, format!("Display ID {} not found", display_id)));
+            return Err(capture_error(CaptureErrorCode::SourceNotFound, format!("Display ID {} not found", display_id)));
         }
         
         // Check if we have the ScreenCaptureKit content pointer
         match self.sc_content_ptr {
             Some(sc_content) => {
-                println!("✅ Using stored ScreenCaptureKit content for display filter creation");
+                log::info!("Using stored ScreenCaptureKit content for display filter creation");
                 
                 // Use ScreenCaptureKit's high-level API to create a content filter
                 // This avoids extracting individual objects
@@ -487,25 +488,25 @@ impl ShareableContent {
                 ];
                 
                 if content_filter.is_null() {
-                    return Err(Error::new(Status::GenericFailure, "Failed to create display content filter"));
+                    return Err(capture_error(CaptureErrorCode::Unknown, "Failed to create display content filter"));
                 }
                 
-                println!("✅ Successfully created display content filter (segfault-safe)");
+                log::info!("Successfully created display content filter (segfault-safe)");
                 Ok(content_filter)
             }
             None => {
-                println!("⚠️ No ScreenCaptureKit content available - cannot create real content filter");
-                Err(Error::new(Status::GenericFailure, "ScreenCaptureKit content not available"))
+                log::warn!("No ScreenCaptureKit content available - cannot create real content filter");
+                Err(capture_error(CaptureErrorCode::Unknown, "ScreenCaptureKit content not available"))
             }
         }
     }
     
     /// Create a content filter for a window using the stored ScreenCaptureKit content
     pub unsafe fn create_window_content_filter(&self, window_id: u32) -> Result<*mut SCContentFilter> {
-        println!("🎯 Creating window content filter for window ID {} (segfault-safe)", window_id);
+        log::info!("Creating window content filter for window ID {} (segfault-safe)", window_id);
         
         if self.find_window_by_id(window_id).is_none() {
-            return Err(Error::new(Status::InvalidArg, format!("Window ID {} not found", window_id)));
+            return Err(capture_error(CaptureErrorCode::SourceNotFound, format!("Window ID {} not found", window_id)));
         }
         
         match self.sc_content_ptr {
@@ -520,14 +521,14 @@ impl ShareableContent {
                 ];
                 
                 if content_filter.is_null() {
-                    return Err(Error::new(Status::GenericFailure, "Failed to create window content filter"));
+                    return Err(capture_error(CaptureErrorCode::Unknown, "Failed to create window content filter"));
                 }
                 
-                println!("✅ Successfully created window content filter (segfault-safe)");
+                log::info!("Successfully created window content filter (segfault-safe)");
                 Ok(content_filter)
             }
             None => {
-                Err(Error::new(Status::GenericFailure, "ScreenCaptureKit content not available"))
+                Err(capture_error(CaptureErrorCode::Unknown, "ScreenCaptureKit content not available"))
             }
         }
     }
@@ -537,14 +538,14 @@ impl ShareableContent {
     
     /// Safe fallback - returns null to indicate object extraction is not supported
     pub unsafe fn get_sc_display_by_id(&self, display_id: u32) -> Option<*mut SCDisplay> {
-        println!("🚫 SCDisplay object extraction disabled to prevent segfaults");
-        println!("💡 Use create_display_content_filter() instead");
+        log::info!("SCDisplay object extraction disabled to prevent segfaults");
+        log::info!("Use create_display_content_filter() instead");
         None
     }
     
     pub unsafe fn get_sc_window_by_id(&self, window_id: u32) -> Option<*mut SCWindow> {
-        println!("🚫 SCWindow object extraction disabled to prevent segfaults");
-        println!("💡 Use create_window_content_filter() instead");
+        log::info!("SCWindow object extraction disabled to prevent segfaults");
+        log::info!("Use create_window_content_filter() instead");
         None
     }
 }
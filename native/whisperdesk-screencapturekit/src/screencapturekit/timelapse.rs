@@ -0,0 +1,41 @@
+// Timelapse - one frame every few seconds, played back at a normal frame rate
+
+use napi::bindgen_prelude::*;
+
+pub const DEFAULT_PLAYBACK_FPS: u32 = 30;
+pub const MIN_CAPTURE_INTERVAL_SECS: f64 = 0.1;
+pub const MAX_CAPTURE_INTERVAL_SECS: f64 = 3600.0;
+
+/// How often to capture and how fast to play the result back
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Timelapse {
+    pub capture_interval_secs: f64,
+    pub playback_fps: u32,
+}
+
+impl Timelapse {
+    pub fn new(capture_interval_secs: f64, playback_fps: Option<u32>) -> Result<Self> {
+        if !(MIN_CAPTURE_INTERVAL_SECS..=MAX_CAPTURE_INTERVAL_SECS).contains(&capture_interval_secs) {
+            return Err(Error::new(
+                Status::InvalidArg,
+                format!(
+                    "timelapse capture_interval_secs must be between {} and {}",
+                    MIN_CAPTURE_INTERVAL_SECS, MAX_CAPTURE_INTERVAL_SECS
+                ),
+            ));
+        }
+        let playback_fps = playback_fps.unwrap_or(DEFAULT_PLAYBACK_FPS);
+        if playback_fps == 0 || playback_fps > 120 {
+            return Err(Error::new(Status::InvalidArg, "timelapse playback_fps must be between 1 and 120"));
+        }
+        Ok(Self {
+            capture_interval_secs,
+            playback_fps,
+        })
+    }
+
+    /// Capture seconds per second of video, e.g. 150 for a frame every 5 s played at 30 fps
+    pub fn speedup(&self) -> f64 {
+        self.capture_interval_secs * self.playback_fps as f64
+    }
+}
@@ -0,0 +1,227 @@
+// Configuration checks - what a recording would run into, reported before one is attempted
+
+use crate::RecordingConfiguration;
+use std::path::Path;
+
+use super::content::uses_custom_cursor;
+use super::encoder::probe_video_encoder;
+use super::formats::PixelFormat;
+use super::limits::{get_limits, DEFAULT_FPS, DEFAULT_HEIGHT, DEFAULT_WIDTH};
+use super::output::{expand_template, OutputCollision, SourceNames};
+use super::presets::apply_preset;
+use super::secure_input::SecureInputAction;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Severity {
+    /// The recording would fail or be refused
+    Error,
+    /// The recording would run, but not quite as configured
+    Warning,
+}
+
+#[derive(Debug, Clone)]
+pub struct Issue {
+    pub severity: Severity,
+    /// The configuration field at fault, as JS spells it
+    pub field: &'static str,
+    pub message: String,
+}
+
+/// Native size and refresh rate of what's being recorded
+#[derive(Debug, Clone, Copy)]
+pub struct SourceSize {
+    pub pixel_width: u32,
+    pub pixel_height: u32,
+    pub refresh_rate: f64,
+}
+
+#[derive(Default)]
+struct Report {
+    issues: Vec<Issue>,
+}
+
+impl Report {
+    fn error(&mut self, field: &'static str, message: String) {
+        self.issues.push(Issue { severity: Severity::Error, field, message });
+    }
+
+    fn warning(&mut self, field: &'static str, message: String) {
+        self.issues.push(Issue { severity: Severity::Warning, field, message });
+    }
+}
+
+/// Check `config` against `source` and this machine. Nothing is started or left behind.
+pub fn validate(config: &RecordingConfiguration, source: Option<SourceSize>) -> Vec<Issue> {
+    let mut report = Report::default();
+
+    // Checked as the recording would see it, after the preset fills in its fields
+    let mut config = config.clone();
+    if let Err(e) = apply_preset(&mut config) {
+        report.error("preset", e.reason);
+    }
+    let capture_video = config.capture_video.unwrap_or(true);
+
+    let width = config.width.unwrap_or(DEFAULT_WIDTH);
+    let height = config.height.unwrap_or(DEFAULT_HEIGHT);
+    let fps = config.fps.unwrap_or(DEFAULT_FPS);
+    if capture_video {
+        check_resolution(&mut report, width, height, source);
+        check_frame_rate(&mut report, fps, source);
+        if width > 0 && height > 0 {
+            let codec = config.codec.unwrap_or_default();
+            if !probe_video_encoder(codec, width, height) {
+                report.error("codec", format!("No {:?} encoder is available for {}x{} on this Mac", codec, width, height));
+            }
+        }
+    }
+    check_combinations(&mut report, &config);
+    check_output_path(&mut report, &config);
+
+    for issue in &report.issues {
+        log::debug!("{} {}: {}", if issue.severity == Severity::Error { "❌" } else { "⚠️" }, issue.field, issue.message);
+    }
+    report.issues
+}
+
+fn check_resolution(report: &mut Report, width: u32, height: u32, source: Option<SourceSize>) {
+    if width == 0 || height == 0 {
+        report.error("width", format!("Resolution {}x{} must be positive", width, height));
+        return;
+    }
+    if width % 2 != 0 || height % 2 != 0 {
+        report.warning("width", format!("Encoders need even dimensions; {}x{} loses its odd row or column", width, height));
+    }
+    if let Some(source) = source {
+        if width > source.pixel_width || height > source.pixel_height {
+            report.warning(
+                "width",
+                format!(
+                    "{}x{} is larger than the source's {}x{} pixels and will be upscaled",
+                    width, height, source.pixel_width, source.pixel_height
+                ),
+            );
+        }
+    }
+    let limits = get_limits();
+    if limits.max_width.map(|max| width > max).unwrap_or(false) || limits.max_height.map(|max| height > max).unwrap_or(false) {
+        report.warning("width", format!("{}x{} exceeds the limits set with setLimits and will be clamped", width, height));
+    }
+}
+
+fn check_frame_rate(report: &mut Report, fps: u32, source: Option<SourceSize>) {
+    if fps == 0 {
+        report.error("fps", "Frame rate must be positive".to_string());
+        return;
+    }
+    if let Some(source) = source {
+        if fps as f64 > source.refresh_rate + 0.5 {
+            report.warning(
+                "fps",
+                format!("{}fps is above the source's {:.0}Hz refresh rate; the extra frames are duplicates", fps, source.refresh_rate),
+            );
+        }
+    }
+    if let Some(max_fps) = get_limits().max_fps {
+        if fps > max_fps {
+            report.warning("fps", format!("{}fps exceeds the limit of {}fps and will be clamped", fps, max_fps));
+        }
+    }
+}
+
+/// The combinations `startRecording` refuses once the stream is being set up
+fn check_combinations(report: &mut Report, config: &RecordingConfiguration) {
+    if !config.capture_video.unwrap_or(true) {
+        if !config.capture_audio.unwrap_or(false) {
+            report.error("captureVideo", "capture_video false needs capture_audio".to_string());
+        }
+        if config.timelapse.is_some()
+            || config.replay_buffer_seconds.is_some()
+            || config.camera_pip.is_some()
+            || config.record_cursor_metadata.unwrap_or(false)
+            || config.follow_active_window.unwrap_or(false)
+            || config.zoom_follow.is_some()
+        {
+            report.error(
+                "captureVideo",
+                "capture_video false can't be combined with timelapse, replay_buffer_seconds, camera_pip, record_cursor_metadata, follow_active_window or zoom_follow".to_string(),
+            );
+        }
+    }
+
+    if config.follow_active_window.unwrap_or(false) {
+        if uses_custom_cursor(config) || config.highlight_clicks.unwrap_or(false) || config.record_cursor_metadata.unwrap_or(false) {
+            report.error(
+                "followActiveWindow",
+                "follow_active_window can't be combined with a custom cursor, highlight_clicks or record_cursor_metadata".to_string(),
+            );
+        }
+        if config.crop.is_some() || config.crop_width.is_some() {
+            report.error("followActiveWindow", "follow_active_window can't be combined with crop".to_string());
+        }
+    }
+
+    let blanks_secure_input = SecureInputAction::parse(config.secure_input_protection.as_deref())
+        .ok()
+        .flatten()
+        == Some(SecureInputAction::Blank);
+    if config.pixel_format.unwrap_or_default() != PixelFormat::Bgra
+        && (uses_custom_cursor(config)
+            || config.highlight_clicks.unwrap_or(false)
+            || config.camera_pip.is_some()
+            || blanks_secure_input
+            || config.zoom_follow.is_some())
+    {
+        report.error(
+            "pixelFormat",
+            "pixel_format other than Bgra can't be combined with a custom cursor, highlight_clicks, camera_pip, secure_input_protection \"blank\" or zoom_follow".to_string(),
+        );
+    }
+}
+
+fn check_output_path(report: &mut Report, config: &RecordingConfiguration) {
+    if config.output_path.is_empty() {
+        report.error("outputPath", "Output path is empty".to_string());
+        return;
+    }
+    // Without a source the name tokens expand to placeholders; the directory is what matters here
+    let output_path = match expand_template(&config.output_path, &SourceNames::default()) {
+        Ok(output_path) => output_path,
+        Err(e) => {
+            report.error("outputPath", e.reason);
+            return;
+        }
+    };
+    let path = Path::new(&output_path);
+    let directory = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    if !directory.is_dir() {
+        if config.create_output_directory.unwrap_or(false) {
+            report.warning("outputPath", format!("Directory {} will be created", directory.display()));
+        } else {
+            report.error("outputPath", format!("Directory {} doesn't exist", directory.display()));
+        }
+        return;
+    }
+
+    // Permissions alone don't tell (sandboxing, read-only volumes), so actually try
+    let probe = directory.join(format!(".whisperdesk-write-test-{}", std::process::id()));
+    match std::fs::File::create(&probe) {
+        Ok(_) => {
+            let _ = std::fs::remove_file(&probe);
+        }
+        Err(e) => report.error("outputPath", format!("Can't write to {}: {}", directory.display(), e)),
+    }
+    if path.exists() {
+        match config.on_output_exists.unwrap_or_default() {
+            OutputCollision::Overwrite => {
+                report.warning("outputPath", format!("{} already exists and will be overwritten", output_path))
+            }
+            OutputCollision::Suffix => {
+                report.warning("outputPath", format!("{} already exists; a numbered suffix will be added", output_path))
+            }
+            OutputCollision::Fail => report.error("outputPath", format!("{} already exists", output_path)),
+        }
+    }
+}
@@ -0,0 +1,91 @@
+// Window resize tracking - keeps a single-window stream's output shaped like the window
+
+use std::sync::mpsc::{self, RecvTimeoutError, Sender};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use super::cursor::CaptureSource;
+
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+// Ignore sub-point jitter while a window is being dragged by its edge
+const SIZE_TOLERANCE: f64 = 1.0;
+
+/// New stream output size (width, height) after the window changed shape
+pub type WindowResizeHandler = Arc<dyn Fn(u32, u32) + Send + Sync>;
+
+/// Largest size with the window's aspect ratio that fits `max_width` x `max_height`, in even pixels
+/// as the H.264 encoder wants
+pub fn fit_within(window_width: f64, window_height: f64, max_width: u32, max_height: u32) -> (u32, u32) {
+    if window_width <= 0.0 || window_height <= 0.0 {
+        return (max_width, max_height);
+    }
+    let scale = (max_width as f64 / window_width).min(max_height as f64 / window_height);
+    let even = |value: f64| ((value.round() as u32) & !1).max(2);
+    (even(window_width * scale), even(window_height * scale))
+}
+
+/// Polls a window's bounds and reports a new output size whenever it is resized
+pub struct WindowResizeTracker {
+    stop_sender: Option<Sender<()>>,
+    thread: Option<JoinHandle<()>>,
+}
+
+impl WindowResizeTracker {
+    /// Track `window_id`, fitting every new shape into `max_width` x `max_height` (the configured output)
+    pub fn start(window_id: u32, max_width: u32, max_height: u32, on_resize: WindowResizeHandler) -> Self {
+        let (stop_sender, stop_receiver) = mpsc::channel::<()>();
+        log::info!("Tracking size of window {}", window_id);
+
+        let thread = thread::spawn(move || {
+            let source = CaptureSource::Window(window_id);
+            let mut known = source.bounds().map(|b| (b.width, b.height));
+            let mut output = known.map(|(w, h)| fit_within(w, h, max_width, max_height));
+            loop {
+                match stop_receiver.recv_timeout(POLL_INTERVAL) {
+                    Err(RecvTimeoutError::Timeout) => {}
+                    _ => break,
+                }
+                // Minimized or closed windows report no bounds; keep the last shape until they return
+                let (width, height) = match source.bounds() {
+                    Some(bounds) => (bounds.width, bounds.height),
+                    None => continue,
+                };
+                let resized = known
+                    .map(|(w, h)| (w - width).abs() > SIZE_TOLERANCE || (h - height).abs() > SIZE_TOLERANCE)
+                    .unwrap_or(true);
+                if !resized {
+                    continue;
+                }
+                known = Some((width, height));
+
+                let fitted = fit_within(width, height, max_width, max_height);
+                if output != Some(fitted) {
+                    log::info!("Window {} resized to {:.0}x{:.0} - output now {}x{}", window_id, width, height, fitted.0, fitted.1);
+                    output = Some(fitted);
+                    on_resize(fitted.0, fitted.1);
+                }
+            }
+            log::info!("Window size tracking stopped");
+        });
+
+        Self {
+            stop_sender: Some(stop_sender),
+            thread: Some(thread),
+        }
+    }
+
+    pub fn stop(&mut self) {
+        // Dropping the sender wakes the poller immediately
+        self.stop_sender.take();
+        if let Some(thread) = self.thread.take() {
+            let _ = thread.join();
+        }
+    }
+}
+
+impl Drop for WindowResizeTracker {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
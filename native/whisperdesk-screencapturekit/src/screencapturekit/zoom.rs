@@ -0,0 +1,221 @@
+// Zoom follow - crop a rectangle around the mouse cursor out of each display frame, scaled to the output
+
+use napi::bindgen_prelude::*;
+use objc2_core_video::CVPixelBuffer;
+use objc2_foundation::NSPoint;
+use std::ffi::c_void;
+use std::ptr;
+
+use super::errors::{capture_error, CaptureErrorCode};
+use super::screenshot::CGRect;
+use super::formats::PixelFormat;
+use super::pixel_buffer::{
+    CVPixelBufferCreate, CVPixelBufferLockBaseAddress, CVPixelBufferUnlockBaseAddress, CVPixelBufferGetBaseAddress,
+    CVPixelBufferGetBytesPerRow, CVPixelBufferGetWidth, CVPixelBufferGetHeight, CVPixelBufferGetPixelFormatType,
+};
+
+pub const DEFAULT_SMOOTHING: f64 = 0.85;
+// Below a tenth of a point per frame the crop is considered settled, so still frames stay still
+const SETTLE_DISTANCE: f64 = 0.1;
+
+extern "C" {
+    fn CGDisplayBounds(display: u32) -> CGRect;
+    fn CGDisplayCopyDisplayMode(display: u32) -> *mut c_void;
+    fn CGDisplayModeGetPixelWidth(mode: *mut c_void) -> usize;
+    fn CGDisplayModeGetPixelHeight(mode: *mut c_void) -> usize;
+    fn CGDisplayModeRelease(mode: *mut c_void);
+    fn CGEventCreate(source: *const c_void) -> *mut c_void;
+    fn CGEventGetLocation(event: *mut c_void) -> NSPoint;
+    fn CFRelease(cf: *const c_void);
+}
+
+/// Keeps a crop of one display centred on the cursor, easing towards it frame by frame
+pub struct ZoomFollow {
+    display_id: u32,
+    /// Crop size in points
+    width: f64,
+    height: f64,
+    smoothing: f64,
+    output_width: u32,
+    output_height: u32,
+    /// Crop centre in global points
+    center: Option<(f64, f64)>,
+}
+
+impl ZoomFollow {
+    /// `width` x `height` points of `display_id`, rendered at `output_width` x `output_height`. A
+    /// missing side follows the output's aspect ratio; without either the crop is half the display
+    /// wide. `smoothing` 0 snaps to the cursor, up to 0.99 trails it.
+    pub fn new(
+        display_id: u32,
+        width: Option<f64>,
+        height: Option<f64>,
+        smoothing: Option<f64>,
+        output_width: u32,
+        output_height: u32,
+    ) -> Result<Self> {
+        let display = unsafe { CGDisplayBounds(display_id) };
+        if display.width <= 0.0 || display.height <= 0.0 {
+            return Err(capture_error(CaptureErrorCode::SourceNotFound, format!("Display {} not found", display_id)));
+        }
+        let aspect = output_height.max(1) as f64 / output_width.max(1) as f64;
+        let (width, height) = match (width, height) {
+            (Some(width), Some(height)) => (width, height),
+            (Some(width), None) => (width, width * aspect),
+            (None, Some(height)) => (height / aspect, height),
+            (None, None) => (display.width / 2.0, display.width / 2.0 * aspect),
+        };
+        if !(width >= 16.0 && width <= display.width && height >= 16.0 && height <= display.height) {
+            return Err(Error::new(
+                Status::InvalidArg,
+                format!("zoom_follow size must be between 16x16 and the display's {}x{} points", display.width, display.height),
+            ));
+        }
+        let smoothing = smoothing.unwrap_or(DEFAULT_SMOOTHING);
+        if !(0.0..=0.99).contains(&smoothing) {
+            return Err(Error::new(Status::InvalidArg, "zoom_follow smoothing must be between 0 and 0.99"));
+        }
+
+        log::debug!("Zoom follow: {}x{} pt of display {}, smoothing {}", width, height, display_id, smoothing);
+        Ok(Self {
+            display_id,
+            width,
+            height,
+            smoothing,
+            output_width,
+            output_height,
+            center: None,
+        })
+    }
+
+    /// The display's native pixel size, which the stream captures at so zoomed text stays sharp
+    pub fn capture_size(&self) -> (u32, u32) {
+        unsafe {
+            let mode = CGDisplayCopyDisplayMode(self.display_id);
+            if mode.is_null() {
+                let bounds = CGDisplayBounds(self.display_id);
+                return (bounds.width as u32 & !1, bounds.height as u32 & !1);
+            }
+            let size = (CGDisplayModeGetPixelWidth(mode) as u32 & !1, CGDisplayModeGetPixelHeight(mode) as u32 & !1);
+            CGDisplayModeRelease(mode);
+            size
+        }
+    }
+
+    /// Move the crop towards the cursor, kept inside the display
+    fn advance(&mut self, display: &CGRect) -> (f64, f64) {
+        let cursor = unsafe {
+            let event = CGEventCreate(ptr::null());
+            if event.is_null() {
+                None
+            } else {
+                let location = CGEventGetLocation(event);
+                CFRelease(event as *const c_void);
+                Some((location.x, location.y))
+            }
+        };
+        let clamp = |value: f64, low: f64, size: f64, span: f64| value.max(low + span / 2.0).min(low + size - span / 2.0);
+        let current = self.center;
+        let target = cursor.or(current).unwrap_or((display.x + display.width / 2.0, display.y + display.height / 2.0));
+        let target = (
+            clamp(target.0, display.x, display.width, self.width),
+            clamp(target.1, display.y, display.height, self.height),
+        );
+        let center = match current {
+            Some((x, y)) => {
+                let follow = 1.0 - self.smoothing;
+                let (dx, dy) = (target.0 - x, target.1 - y);
+                if dx.abs() < SETTLE_DISTANCE && dy.abs() < SETTLE_DISTANCE {
+                    target
+                } else {
+                    (x + dx * follow, y + dy * follow)
+                }
+            }
+            None => target,
+        };
+        self.center = Some(center);
+        center
+    }
+
+    /// A new frame of the output size holding the crop, bilinearly scaled. Release with `release`.
+    pub unsafe fn render(&mut self, pixel_buffer: *mut CVPixelBuffer) -> Option<*mut CVPixelBuffer> {
+        if pixel_buffer.is_null() || CVPixelBufferGetPixelFormatType(pixel_buffer) != PixelFormat::Bgra.four_cc() {
+            return None;
+        }
+        let display = CGDisplayBounds(self.display_id);
+        if display.width <= 0.0 || display.height <= 0.0 {
+            return None;
+        }
+        let (center_x, center_y) = self.advance(&display);
+
+        let mut output: *mut CVPixelBuffer = ptr::null_mut();
+        let status = CVPixelBufferCreate(
+            ptr::null(),
+            self.output_width as usize,
+            self.output_height as usize,
+            PixelFormat::Bgra.four_cc(),
+            ptr::null(),
+            &mut output,
+        );
+        if status != 0 || output.is_null() {
+            return None;
+        }
+        if CVPixelBufferLockBaseAddress(pixel_buffer, 1) != 0 {
+            Self::release(output);
+            return None;
+        }
+        if CVPixelBufferLockBaseAddress(output, 0) != 0 {
+            CVPixelBufferUnlockBaseAddress(pixel_buffer, 1);
+            Self::release(output);
+            return None;
+        }
+
+        let source = CVPixelBufferGetBaseAddress(pixel_buffer) as *const u8;
+        let source_stride = CVPixelBufferGetBytesPerRow(pixel_buffer);
+        let source_width = CVPixelBufferGetWidth(pixel_buffer);
+        let source_height = CVPixelBufferGetHeight(pixel_buffer);
+        let target = CVPixelBufferGetBaseAddress(output) as *mut u8;
+        let target_stride = CVPixelBufferGetBytesPerRow(output);
+
+        if !source.is_null() && !target.is_null() && source_width > 1 && source_height > 1 {
+            // Crop origin and size in source pixels
+            let pixels_per_point_x = source_width as f64 / display.width;
+            let pixels_per_point_y = source_height as f64 / display.height;
+            let crop_x = (center_x - self.width / 2.0 - display.x) * pixels_per_point_x;
+            let crop_y = (center_y - self.height / 2.0 - display.y) * pixels_per_point_y;
+            let step_x = self.width * pixels_per_point_x / self.output_width as f64;
+            let step_y = self.height * pixels_per_point_y / self.output_height as f64;
+            let max_x = (source_width - 1) as f64;
+            let max_y = (source_height - 1) as f64;
+
+            for row in 0..self.output_height as usize {
+                let sy = (crop_y + (row as f64 + 0.5) * step_y - 0.5).max(0.0).min(max_y);
+                let y0 = sy.floor() as usize;
+                let y1 = (y0 + 1).min(source_height - 1);
+                let fy = sy - y0 as f64;
+                let row0 = source.add(y0 * source_stride);
+                let row1 = source.add(y1 * source_stride);
+                let out = target.add(row * target_stride);
+                for column in 0..self.output_width as usize {
+                    let sx = (crop_x + (column as f64 + 0.5) * step_x - 0.5).max(0.0).min(max_x);
+                    let x0 = sx.floor() as usize;
+                    let x1 = (x0 + 1).min(source_width - 1);
+                    let fx = sx - x0 as f64;
+                    for channel in 0..4 {
+                        let top = *row0.add(x0 * 4 + channel) as f64 * (1.0 - fx) + *row0.add(x1 * 4 + channel) as f64 * fx;
+                        let bottom = *row1.add(x0 * 4 + channel) as f64 * (1.0 - fx) + *row1.add(x1 * 4 + channel) as f64 * fx;
+                        *out.add(column * 4 + channel) = (top * (1.0 - fy) + bottom * fy).round() as u8;
+                    }
+                }
+            }
+        }
+
+        CVPixelBufferUnlockBaseAddress(output, 0);
+        CVPixelBufferUnlockBaseAddress(pixel_buffer, 1);
+        Some(output)
+    }
+
+    pub unsafe fn release(pixel_buffer: *mut CVPixelBuffer) {
+        CFRelease(pixel_buffer as *const c_void);
+    }
+}